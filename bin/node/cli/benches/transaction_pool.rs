@@ -31,7 +31,7 @@ use sc_service::{
 	},
 	BasePath, Configuration, Role,
 };
-use sc_transaction_pool::PoolLimit;
+use sc_transaction_pool::{Options, PoolLimit};
 use sc_transaction_pool_api::{TransactionPool as _, TransactionSource, TransactionStatus};
 use sp_core::{crypto::Pair, sr25519};
 use sp_keyring::Sr25519Keyring;
@@ -57,15 +57,20 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		role: Role::Authority,
 		tokio_handle,
 		transaction_pool: TransactionPoolOptions {
-			ready: PoolLimit { count: 100_000, total_bytes: 100 * 1024 * 1024 },
-			future: PoolLimit { count: 100_000, total_bytes: 100 * 1024 * 1024 },
-			reject_future_transactions: false,
-			ban_time: Duration::from_secs(30 * 60),
+			options: Options {
+				ready: PoolLimit { count: 100_000, total_bytes: 100 * 1024 * 1024 },
+				future: PoolLimit { count: 100_000, total_bytes: 100 * 1024 * 1024 },
+				reject_future_transactions: false,
+				ban_time: Duration::from_secs(30 * 60),
+			},
+			txpool_type: Default::default(),
+			journal_path: None,
 		},
 		network: network_config,
 		keystore: KeystoreConfig::InMemory,
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(64 * 1024 * 1024),
+		trie_cache_snapshot_maximum_size: None,
 		state_pruning: Some(PruningMode::ArchiveAll),
 		blocks_pruning: BlocksPruning::KeepAll,
 		chain_spec: spec,
@@ -89,6 +94,8 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		rpc_max_response_size: None,
 		rpc_id_provider: None,
 		rpc_max_subs_per_conn: None,
+		rpc_rate_limit: Default::default(),
+		rpc_method_filter: Default::default(),
 		ws_max_out_buffer_capacity: None,
 		prometheus_config: None,
 		telemetry_endpoints: None,
@@ -101,6 +108,7 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		tracing_receiver: Default::default(),
 		max_runtime_instances: 8,
 		runtime_cache_size: 2,
+		profile_host_functions: false,
 		announce_block: true,
 		base_path: Some(base_path),
 		informant_output_format: Default::default(),
@@ -96,9 +96,15 @@ pub enum Subcommand {
 	/// Remove the whole chain.
 	PurgeChain(sc_cli::PurgeChainCmd),
 
+	/// Inspect and edit the peers banned by the node.
+	BanList(sc_cli::BanListCmd),
+
 	/// Revert the chain to a previous state.
 	Revert(sc_cli::RevertCmd),
 
 	/// Db meta columns information.
 	ChainInfo(sc_cli::ChainInfoCmd),
+
+	/// Inspect the database of a stopped node.
+	Db(sc_cli::DbCmd),
 }
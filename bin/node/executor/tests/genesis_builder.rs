@@ -0,0 +1,81 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use codec::{Decode, Encode};
+use kitchensink_runtime::{BuildStorage, GenesisConfig};
+use sp_runtime::traits::BlakeTwo256;
+
+pub mod common;
+use self::common::*;
+
+fn keys(t: &mut TestExternalities<BlakeTwo256>) -> Vec<Vec<u8>> {
+	t.execute_with(|| {
+		let mut keys = Vec::new();
+		let mut key = Vec::new();
+		while let Some(next) = sp_io::storage::next_key(&key) {
+			keys.push(next.clone());
+			key = next;
+		}
+		keys
+	})
+}
+
+#[test]
+fn wasm_runtime_creates_default_config() {
+	let mut t =
+		TestExternalities::<BlakeTwo256>::new_with_code(compact_code_unwrap(), Default::default());
+
+	let (r, _) = executor_call(&mut t, "GenesisBuilder_create_default_config", &[], false);
+	let json = Vec::<u8>::decode(&mut &r.unwrap()[..]).unwrap();
+
+	let config: GenesisConfig = serde_json::from_slice(&json).unwrap();
+	assert_eq!(
+		serde_json::to_vec(&config).unwrap(),
+		serde_json::to_vec(&GenesisConfig::default()).unwrap()
+	);
+}
+
+#[test]
+fn wasm_runtime_builds_same_genesis_as_native() {
+	let code = compact_code_unwrap();
+	let config = node_testing::genesis::config(Some(code));
+	let expected = config.build_storage().unwrap();
+
+	let mut t = TestExternalities::<BlakeTwo256>::new_with_code(code, Default::default());
+	let json = serde_json::to_vec(&config).unwrap();
+	let (r, _) = executor_call(&mut t, "GenesisBuilder_build_config", &json.encode(), false);
+	sp_genesis_builder::Result::decode(&mut &r.unwrap()[..]).unwrap().unwrap();
+
+	assert_eq!(keys(&mut t), expected.top.keys().cloned().collect::<Vec<_>>());
+	t.execute_with(|| {
+		for (key, value) in &expected.top {
+			assert_eq!(sp_io::storage::get(key).as_deref(), Some(&value[..]));
+		}
+	});
+}
+
+#[test]
+fn wasm_runtime_rejects_invalid_config() {
+	let mut t =
+		TestExternalities::<BlakeTwo256>::new_with_code(compact_code_unwrap(), Default::default());
+
+	let json = br#"{"system":{"unknownField":true}}"#.to_vec();
+	let (r, _) = executor_call(&mut t, "GenesisBuilder_build_config", &json.encode(), false);
+	let err = sp_genesis_builder::Result::decode(&mut &r.unwrap()[..]).unwrap().unwrap_err();
+
+	assert!(format!("{:?}", err).contains("Invalid JSON blob"));
+}
@@ -0,0 +1,84 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The presets of the `GenesisConfig`, see `sp_genesis_builder::GenesisBuilder::get_preset`.
+
+use crate::{constants::currency::*, Balance, Perbill, BABE_GENESIS_EPOCH_CONFIG};
+use serde_json::{json, Value};
+use sp_genesis_builder::PresetId;
+use sp_std::{vec, vec::Vec};
+
+/// A development chain with `//Alice` as the single validator, matching `--dev` of the node.
+pub const DEVELOPMENT: &str = "development";
+
+// The well known development accounts, without std there are no seeds to derive them from.
+const ALICE: &str = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+const ALICE_ED25519: &str = "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu";
+const ALICE_STASH: &str = "5GNJqTPyNqANBkUVMN1LPPrxXnFouWXoe2wNSmmEoLctxiZY";
+const BOB: &str = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+const BOB_STASH: &str = "5HpG9w8EBLe5XCrbczpwq5TSXvedjrBGCwqxK1iQ7qUsSWFc";
+
+// Balances in a JSON patch must fit into an `u64`.
+const ENDOWMENT: Balance = 100_000 * DOLLARS;
+const STASH: Balance = ENDOWMENT / 1000;
+
+fn development() -> Value {
+	let endowed = [ALICE, BOB, ALICE_STASH, BOB_STASH];
+
+	json!({
+		"balances": {
+			"balances": endowed.iter().map(|account| (account, ENDOWMENT)).collect::<Vec<_>>(),
+		},
+		"session": {
+			"keys": [[ALICE_STASH, ALICE_STASH, {
+				"grandpa": ALICE_ED25519,
+				"babe": ALICE,
+				"im_online": ALICE,
+				"authority_discovery": ALICE,
+			}]],
+		},
+		"staking": {
+			"validatorCount": 1,
+			"minimumValidatorCount": 1,
+			"invulnerables": [ALICE_STASH],
+			"slashRewardFraction": Perbill::from_percent(10),
+			"stakers": [[ALICE_STASH, ALICE, STASH, "Validator"]],
+		},
+		"sudo": { "key": ALICE },
+		"babe": { "epochConfig": BABE_GENESIS_EPOCH_CONFIG },
+		// This asset is used by the NIS pallet as counterpart currency.
+		"assets": { "assets": [[9, ALICE, true, 1]] },
+		"nominationPools": {
+			"minCreateBond": 10 * DOLLARS,
+			"minJoinBond": DOLLARS,
+		},
+	})
+}
+
+/// Returns the preset `id` as a JSON patch of the default `GenesisConfig`.
+pub fn get_preset(id: &PresetId) -> Option<Vec<u8>> {
+	let patch = match sp_std::str::from_utf8(id) {
+		Ok(DEVELOPMENT) => development(),
+		_ => return None,
+	};
+	Some(serde_json::to_vec(&patch).expect("serialization to json is expected to work; qed"))
+}
+
+/// Returns the names of all presets.
+pub fn preset_names() -> Vec<PresetId> {
+	vec![DEVELOPMENT.as_bytes().to_vec()]
+}
@@ -29,6 +29,7 @@ use frame_election_provider_support::{
 use frame_support::{
 	construct_runtime,
 	dispatch::DispatchClass,
+	genesis_builder_helper::BuildRuntimeGenesis,
 	pallet_prelude::Get,
 	parameter_types,
 	traits::{
@@ -110,6 +111,9 @@ mod voter_bags;
 /// Runtime API definition for assets.
 pub mod assets_api;
 
+/// Presets of the genesis config.
+pub mod genesis_config_presets;
+
 // Make the WASM binary available.
 #[cfg(feature = "std")]
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
@@ -2354,6 +2358,25 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(2)]
+	impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+		fn create_default_config() -> Vec<u8> {
+			<Runtime as BuildRuntimeGenesis>::create_default_config()
+		}
+
+		fn build_config(config: Vec<u8>) -> sp_genesis_builder::Result {
+			<Runtime as BuildRuntimeGenesis>::build_config(config)
+		}
+
+		fn get_preset(id: sp_genesis_builder::PresetId) -> Option<Vec<u8>> {
+			genesis_config_presets::get_preset(&id)
+		}
+
+		fn preset_names() -> Vec<sp_genesis_builder::PresetId> {
+			genesis_config_presets::preset_names()
+		}
+	}
+
 	#[cfg(feature = "try-runtime")]
 	impl frame_try_runtime::TryRuntime<Block> for Runtime {
 		fn on_runtime_upgrade(checks: frame_try_runtime::UpgradeCheckSelect) -> (Weight, Weight) {
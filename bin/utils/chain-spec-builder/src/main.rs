@@ -26,6 +26,7 @@ use clap::Parser;
 use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
 
 use node_cli::chain_spec::{self, AccountId};
+use sc_chain_spec::ChainType;
 use sc_keystore::LocalKeystore;
 use sp_core::{
 	crypto::{ByteArray, Ss58Codec},
@@ -33,8 +34,13 @@ use sp_core::{
 };
 use sp_keystore::KeystorePtr;
 
+mod runtime_genesis;
+
 /// A utility to easily create a testnet chain spec definition with a given set
 /// of authorities and endowed accounts and/or generate random accounts.
+///
+/// Chain specs for any runtime implementing the `GenesisBuilder` runtime api can be created from
+/// the runtime code alone.
 #[derive(Parser)]
 #[command(rename_all = "kebab-case")]
 enum ChainSpecBuilder {
@@ -83,16 +89,87 @@ enum ChainSpecBuilder {
 		#[arg(long, short)]
 		keystore_path: Option<PathBuf>,
 	},
+	/// Create a new chain spec from the given runtime code.
+	///
+	/// The genesis is the default `GenesisConfig` of the runtime, with the preset and the patches
+	/// applied in order.
+	Create {
+		/// The name of the chain.
+		#[arg(long, default_value = "Custom")]
+		chain_name: String,
+		/// The id of the chain.
+		#[arg(long, default_value = "custom")]
+		chain_id: String,
+		/// The type of the chain: `development`, `local`, `live` or a custom one.
+		#[arg(long, default_value = "live", value_parser = parse_chain_type)]
+		chain_type: ChainType,
+		/// The path to the runtime code.
+		#[arg(long, short)]
+		runtime_wasm_path: PathBuf,
+		/// The name of the preset of the runtime to start from.
+		#[arg(long)]
+		preset: Option<String>,
+		/// The path to a JSON merge patch of the `GenesisConfig`.
+		#[arg(long)]
+		patch_path: Vec<PathBuf>,
+		/// Output the genesis in the raw format.
+		#[arg(long)]
+		raw: bool,
+		/// The path where the chain spec should be saved.
+		#[arg(long, short, default_value = "./chain_spec.json")]
+		chain_spec_path: PathBuf,
+	},
+	/// Apply JSON merge patches to the `GenesisConfig` of a chain spec created from runtime code.
+	AddPatch {
+		/// The path to the chain spec to patch.
+		#[arg(long, short)]
+		input_chain_spec: PathBuf,
+		/// The path to a JSON merge patch of the `GenesisConfig`.
+		#[arg(long, required = true)]
+		patch_path: Vec<PathBuf>,
+		/// The path where the chain spec should be saved.
+		#[arg(long, short, default_value = "./chain_spec.json")]
+		chain_spec_path: PathBuf,
+	},
+	/// Convert a chain spec to the raw genesis format.
+	ConvertToRaw {
+		/// The path to the chain spec to convert.
+		#[arg(long, short)]
+		input_chain_spec: PathBuf,
+		/// The path where the chain spec should be saved.
+		#[arg(long, short, default_value = "./chain_spec.json")]
+		chain_spec_path: PathBuf,
+	},
+	/// List the presets of the given runtime code.
+	ListPresets {
+		/// The path to the runtime code.
+		#[arg(long, short)]
+		runtime_wasm_path: PathBuf,
+	},
+	/// Verify that the genesis of a raw chain spec is the one built by the given runtime code.
+	Verify {
+		/// The path to the raw chain spec to verify.
+		#[arg(long, short)]
+		input_chain_spec: PathBuf,
+		/// The path to the runtime code.
+		#[arg(long, short)]
+		runtime_wasm_path: PathBuf,
+		/// The name of the preset the chain spec was created with.
+		#[arg(long)]
+		preset: Option<String>,
+		/// The path to a JSON merge patch the chain spec was created with.
+		#[arg(long)]
+		patch_path: Vec<PathBuf>,
+	},
 }
 
-impl ChainSpecBuilder {
-	/// Returns the path where the chain spec should be saved.
-	fn chain_spec_path(&self) -> &Path {
-		match self {
-			ChainSpecBuilder::New { chain_spec_path, .. } => chain_spec_path.as_path(),
-			ChainSpecBuilder::Generate { chain_spec_path, .. } => chain_spec_path.as_path(),
-		}
-	}
+fn parse_chain_type(s: &str) -> Result<ChainType, String> {
+	Ok(match s {
+		"development" => ChainType::Development,
+		"local" => ChainType::Local,
+		"live" => ChainType::Live,
+		custom => ChainType::Custom(custom.to_owned()),
+	})
 }
 
 fn genesis_constructor(
@@ -237,10 +314,15 @@ fn main() -> Result<(), String> {
 	);
 
 	let builder = ChainSpecBuilder::parse();
-	let chain_spec_path = builder.chain_spec_path().to_path_buf();
 
-	let (authority_seeds, nominator_accounts, endowed_accounts, sudo_account) = match builder {
-		ChainSpecBuilder::Generate { authorities, nominators, endowed, keystore_path, .. } => {
+	let (chain_spec_path, json) = match builder {
+		ChainSpecBuilder::Generate {
+			authorities,
+			nominators,
+			endowed,
+			keystore_path,
+			chain_spec_path,
+		} => {
 			let authorities = authorities.max(1);
 			let rand_str = || -> String {
 				OsRng.sample_iter(&Alphanumeric).take(32).map(char::from).collect()
@@ -274,19 +356,69 @@ fn main() -> Result<(), String> {
 			let sudo_account =
 				chain_spec::get_account_id_from_seed::<sr25519::Public>(&sudo_seed).to_ss58check();
 
-			(authority_seeds, nominator_accounts, endowed_accounts, sudo_account)
+			let json = generate_chain_spec(
+				authority_seeds,
+				nominator_accounts,
+				endowed_accounts,
+				sudo_account,
+			)?;
+			(chain_spec_path, json)
 		},
 		ChainSpecBuilder::New {
 			authority_seeds,
 			nominator_accounts,
 			endowed_accounts,
 			sudo_account,
-			..
-		} => (authority_seeds, nominator_accounts, endowed_accounts, sudo_account),
+			chain_spec_path,
+		} => {
+			let json = generate_chain_spec(
+				authority_seeds,
+				nominator_accounts,
+				endowed_accounts,
+				sudo_account,
+			)?;
+			(chain_spec_path, json)
+		},
+		ChainSpecBuilder::Create {
+			chain_name,
+			chain_id,
+			chain_type,
+			runtime_wasm_path,
+			preset,
+			patch_path,
+			raw,
+			chain_spec_path,
+		} => {
+			let code = runtime_genesis::read_wasm(&runtime_wasm_path)?;
+			let json = runtime_genesis::create(
+				&chain_name,
+				&chain_id,
+				chain_type,
+				&code,
+				preset.as_deref(),
+				&patch_path,
+				raw,
+			)?;
+			(chain_spec_path, json)
+		},
+		ChainSpecBuilder::AddPatch { input_chain_spec, patch_path, chain_spec_path } =>
+			(chain_spec_path, runtime_genesis::add_patch(&input_chain_spec, &patch_path)?),
+		ChainSpecBuilder::ConvertToRaw { input_chain_spec, chain_spec_path } =>
+			(chain_spec_path, runtime_genesis::convert_to_raw(&input_chain_spec)?),
+		ChainSpecBuilder::ListPresets { runtime_wasm_path } => {
+			let code = runtime_genesis::read_wasm(&runtime_wasm_path)?;
+			for preset in runtime_genesis::list_presets(&code)? {
+				println!("{}", preset);
+			}
+			return Ok(())
+		},
+		ChainSpecBuilder::Verify { input_chain_spec, runtime_wasm_path, preset, patch_path } => {
+			let code = runtime_genesis::read_wasm(&runtime_wasm_path)?;
+			runtime_genesis::verify(&input_chain_spec, &code, preset.as_deref(), &patch_path)?;
+			println!("The genesis of the chain spec matches the runtime");
+			return Ok(())
+		},
 	};
 
-	let json =
-		generate_chain_spec(authority_seeds, nominator_accounts, endowed_accounts, sudo_account)?;
-
 	fs::write(chain_spec_path, json).map_err(|err| err.to_string())
 }
@@ -0,0 +1,204 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Chain specs whose genesis is built by the runtime code alone, see
+//! `sp_genesis_builder::GenesisBuilder`.
+
+use std::{
+	collections::BTreeSet,
+	fs,
+	path::{Path, PathBuf},
+};
+
+use sc_chain_spec::{json_merge, ChainType, GenericChainSpec, GenesisConfigBuilderRuntimeCaller};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use sp_core::{
+	hexdisplay::HexDisplay,
+	storage::{well_known_keys, Storage, StorageMap},
+};
+use sp_runtime::BuildStorage;
+
+const NO_NATIVE_GENESIS: &str = "The `runtime` genesis format requires the native GenesisConfig \
+	of the runtime, only the `runtimeGenesis` and `raw` formats are supported";
+
+/// Stands in for the native `GenesisConfig`, which isn't needed when the runtime builds the
+/// genesis.
+///
+/// Loading a chain spec in the `runtime` genesis format fails.
+pub struct NoNativeGenesis;
+
+impl Serialize for NoNativeGenesis {
+	fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+		Err(ser::Error::custom(NO_NATIVE_GENESIS))
+	}
+}
+
+impl<'de> Deserialize<'de> for NoNativeGenesis {
+	fn deserialize<D: Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+		Err(de::Error::custom(NO_NATIVE_GENESIS))
+	}
+}
+
+impl BuildStorage for NoNativeGenesis {
+	fn assimilate_storage(&self, _: &mut Storage) -> Result<(), String> {
+		Err(NO_NATIVE_GENESIS.into())
+	}
+}
+
+type ChainSpec = GenericChainSpec<NoNativeGenesis>;
+
+/// Reads the runtime code at `path`.
+pub fn read_wasm(path: &Path) -> Result<Vec<u8>, String> {
+	fs::read(path).map_err(|e| format!("Error reading runtime at `{}`: {}", path.display(), e))
+}
+
+fn read_json(path: &Path) -> Result<Value, String> {
+	let bytes = fs::read(path)
+		.map_err(|e| format!("Error reading JSON file at `{}`: {}", path.display(), e))?;
+	serde_json::from_slice(&bytes)
+		.map_err(|e| format!("Error parsing JSON file at `{}`: {}", path.display(), e))
+}
+
+/// The patch of the default `GenesisConfig`: the named `preset`, if any, with the patches at
+/// `patch_paths` applied in order.
+fn genesis_patch(
+	caller: &GenesisConfigBuilderRuntimeCaller,
+	preset: Option<&str>,
+	patch_paths: &[PathBuf],
+) -> Result<Value, String> {
+	let mut patch = match preset {
+		Some(name) => caller.get_named_preset(name)?,
+		None => Value::Object(Default::default()),
+	};
+	for path in patch_paths {
+		json_merge(&mut patch, read_json(path)?);
+	}
+	Ok(patch)
+}
+
+/// Creates a chain spec for the runtime `code`.
+pub fn create(
+	name: &str,
+	id: &str,
+	chain_type: ChainType,
+	code: &[u8],
+	preset: Option<&str>,
+	patch_paths: &[PathBuf],
+	raw: bool,
+) -> Result<String, String> {
+	let caller = GenesisConfigBuilderRuntimeCaller::new(code);
+	let patch = genesis_patch(&caller, preset, patch_paths)?;
+	if !raw {
+		// Fail now instead of when the chain spec is loaded by a node.
+		caller.get_storage_for_patch(patch.clone())?;
+	}
+
+	ChainSpec::from_runtime_code(
+		name,
+		id,
+		chain_type,
+		code,
+		patch,
+		Vec::new(),
+		None,
+		None,
+		None,
+		None,
+		Default::default(),
+	)
+	.as_json(raw)
+}
+
+/// Applies the patches at `patch_paths` to the genesis patch of the chain spec at `input`.
+pub fn add_patch(input: &Path, patch_paths: &[PathBuf]) -> Result<String, String> {
+	let mut spec = read_json(input)?;
+	let patch = spec
+		.pointer_mut("/genesis/runtimeGenesis/patch")
+		.ok_or("The chain spec does not use the `runtimeGenesis` genesis format")?;
+	for path in patch_paths {
+		json_merge(patch, read_json(path)?);
+	}
+	serde_json::to_string_pretty(&spec).map_err(|e| format!("Error generating spec json: {}", e))
+}
+
+/// Converts the chain spec at `input` to the raw genesis format.
+pub fn convert_to_raw(input: &Path) -> Result<String, String> {
+	ChainSpec::from_json_file(input.to_path_buf())?.as_json(true)
+}
+
+/// Returns the names of the presets of the runtime `code`.
+pub fn list_presets(code: &[u8]) -> Result<Vec<String>, String> {
+	GenesisConfigBuilderRuntimeCaller::new(code).preset_names()
+}
+
+/// Checks that the raw chain spec at `input` contains the genesis built by the runtime `code`,
+/// with the named `preset` and the patches at `patch_paths` applied.
+pub fn verify(
+	input: &Path,
+	code: &[u8],
+	preset: Option<&str>,
+	patch_paths: &[PathBuf],
+) -> Result<(), String> {
+	if read_json(input)?.pointer("/genesis/raw").is_none() {
+		return Err("The chain spec is not raw".into())
+	}
+	let genesis = ChainSpec::from_json_file(input.to_path_buf())?.build_storage()?;
+	if genesis.top.get(well_known_keys::CODE).map(Vec::as_slice) != Some(code) {
+		return Err("The runtime code of the chain spec differs from the given runtime".into())
+	}
+
+	let caller = GenesisConfigBuilderRuntimeCaller::new(code);
+	let mut expected =
+		caller.get_storage_for_patch(genesis_patch(&caller, preset, patch_paths)?)?;
+	expected.top.insert(well_known_keys::CODE.to_vec(), code.to_vec());
+
+	let mut mismatches = diff_keys(&genesis.top, &expected.top);
+	let empty = StorageMap::new();
+	let child_keys = genesis.children_default.keys().chain(expected.children_default.keys());
+	for child_key in child_keys.collect::<BTreeSet<_>>() {
+		let genesis_child = genesis.children_default.get(child_key).map_or(&empty, |c| &c.data);
+		let expected_child = expected.children_default.get(child_key).map_or(&empty, |c| &c.data);
+		mismatches.extend(
+			diff_keys(genesis_child, expected_child)
+				.into_iter()
+				.map(|key| format!("0x{}/{}", HexDisplay::from(child_key), key)),
+		);
+	}
+
+	if mismatches.is_empty() {
+		Ok(())
+	} else {
+		Err(format!(
+			"The genesis of the chain spec differs from the one built by the runtime at {} keys: {}",
+			mismatches.len(),
+			mismatches.join(", ")
+		))
+	}
+}
+
+/// The hex encoded keys whose values differ between `a` and `b`.
+fn diff_keys(a: &StorageMap, b: &StorageMap) -> Vec<String> {
+	a.keys()
+		.chain(b.keys())
+		.collect::<BTreeSet<_>>()
+		.into_iter()
+		.filter(|key| a.get(*key) != b.get(*key))
+		.map(|key| format!("0x{}", HexDisplay::from(key)))
+		.collect()
+}
@@ -0,0 +1,212 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use assert_cmd::cargo::cargo_bin;
+use kitchensink_runtime::{genesis_config_presets::DEVELOPMENT, SessionKeys};
+use node_cli::chain_spec::{authority_keys_from_seed, get_account_id_from_seed};
+use serde_json::{json, Value};
+use sp_core::sr25519;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	process::{Command, Output},
+};
+use tempfile::tempdir;
+
+fn chain_spec_builder(args: &[&dyn AsRef<std::ffi::OsStr>]) -> Output {
+	Command::new(cargo_bin("chain-spec-builder")).args(args).output().unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+	String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn write_runtime(dir: &Path) -> PathBuf {
+	let path = dir.join("runtime.wasm");
+	let code = kitchensink_runtime::WASM_BINARY.expect(
+		"Development wasm binary is not available. Testing is only supported with the flag \
+		 disabled.",
+	);
+	fs::write(&path, code).unwrap();
+	path
+}
+
+fn read_json(path: &Path) -> Value {
+	serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn list_presets_works() {
+	let dir = tempdir().unwrap();
+	let runtime = write_runtime(dir.path());
+
+	let output = chain_spec_builder(&[&"list-presets", &"--runtime-wasm-path", &runtime]);
+	assert!(output.status.success(), "{}", stderr(&output));
+	assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{}\n", DEVELOPMENT));
+}
+
+#[test]
+fn development_preset_matches_dev_chain_spec() {
+	let dir = tempdir().unwrap();
+	let runtime = write_runtime(dir.path());
+	let spec = dir.path().join("spec.json");
+
+	let output = chain_spec_builder(&[
+		&"create",
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+		&"--chain-spec-path",
+		&spec,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let patch = read_json(&spec)["genesis"]["runtimeGenesis"]["patch"].clone();
+	let account = |seed: &str| json!(get_account_id_from_seed::<sr25519::Public>(seed));
+	let (stash, controller, grandpa, babe, im_online, authority_discovery) =
+		authority_keys_from_seed("Alice");
+	let keys = SessionKeys { grandpa, babe, im_online, authority_discovery };
+
+	assert_eq!(patch["sudo"]["key"], account("Alice"));
+	assert_eq!(patch["session"]["keys"], json!([[stash, stash, keys]]));
+	assert_eq!(patch["staking"]["invulnerables"], json!([stash]));
+	assert_eq!(patch["staking"]["stakers"][0][0], json!(stash));
+	assert_eq!(patch["staking"]["stakers"][0][1], json!(controller));
+	let endowed = patch["balances"]["balances"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|balance| balance[0].clone())
+		.collect::<Vec<_>>();
+	assert_eq!(
+		endowed,
+		vec![account("Alice"), account("Bob"), account("Alice//stash"), account("Bob//stash")]
+	);
+}
+
+#[test]
+fn create_and_verify_work_with_preset_and_patch() {
+	let dir = tempdir().unwrap();
+	let runtime = write_runtime(dir.path());
+	let patch = dir.path().join("patch.json");
+	fs::write(
+		&patch,
+		json!({ "sudo": { "key": get_account_id_from_seed::<sr25519::Public>("Bob") } })
+			.to_string(),
+	)
+	.unwrap();
+	let spec = dir.path().join("spec.json");
+	let raw_spec = dir.path().join("raw.json");
+
+	let output = chain_spec_builder(&[
+		&"create",
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+		&"--patch-path",
+		&patch,
+		&"--chain-spec-path",
+		&spec,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let output = chain_spec_builder(&[
+		&"convert-to-raw",
+		&"--input-chain-spec",
+		&spec,
+		&"--chain-spec-path",
+		&raw_spec,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let output = chain_spec_builder(&[
+		&"verify",
+		&"--input-chain-spec",
+		&raw_spec,
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+		&"--patch-path",
+		&patch,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	// Without the patch Alice is the sudo key.
+	let output = chain_spec_builder(&[
+		&"verify",
+		&"--input-chain-spec",
+		&raw_spec,
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+	]);
+	assert!(!output.status.success());
+	assert!(stderr(&output).contains("differs from the one built by the runtime at 1 keys"));
+}
+
+#[test]
+fn create_raw_works_with_preset() {
+	let dir = tempdir().unwrap();
+	let runtime = write_runtime(dir.path());
+	let raw_spec = dir.path().join("raw.json");
+
+	let output = chain_spec_builder(&[
+		&"create",
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+		&"--raw",
+		&"--chain-spec-path",
+		&raw_spec,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+
+	let output = chain_spec_builder(&[
+		&"verify",
+		&"--input-chain-spec",
+		&raw_spec,
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&DEVELOPMENT,
+	]);
+	assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn unknown_preset_is_rejected() {
+	let dir = tempdir().unwrap();
+	let runtime = write_runtime(dir.path());
+
+	let output = chain_spec_builder(&[
+		&"create",
+		&"--runtime-wasm-path",
+		&runtime,
+		&"--preset",
+		&"unknown",
+		&"--chain-spec-path",
+		&dir.path().join("spec.json"),
+	]);
+	assert!(!output.status.success());
+	assert!(stderr(&output).contains("The runtime has no preset named `unknown`"));
+}
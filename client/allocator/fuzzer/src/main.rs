@@ -0,0 +1,175 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Running
+//! Running this fuzzer can be done with `cargo hfuzz run allocator`. `honggfuzz` CLI options can
+//! be used by setting `HFUZZ_RUN_ARGS`, such as `-n 4` to use 4 threads.
+//!
+//! # Debugging a panic
+//! Once a panic is found, it can be debugged with
+//! `cargo hfuzz run-debug allocator hfuzz_workspace/allocator/*.fuzz`.
+//!
+//! # More information
+//! More information about `honggfuzz` can be found
+//! [here](https://docs.rs/honggfuzz/).
+
+use honggfuzz::fuzz;
+use sc_allocator::{Allocator, AllocatorKind, Error, HeapAllocator, Memory};
+use sp_wasm_interface::Pointer;
+use std::collections::BTreeMap;
+
+const PAGE_SIZE: u32 = 65536;
+
+/// The maximum number of pages the memory can grow to.
+const MAX_PAGES: u32 = 256;
+
+/// The offset at which the heap starts.
+const HEAP_BASE: u32 = 1234;
+
+/// The largest allocation requested, big enough to let the memory run out.
+const MAX_ALLOCATION_SIZE: u32 = 4 * 1024 * 1024;
+
+/// A memory backed by a `Vec`, growing up to [`MAX_PAGES`].
+struct VecMemory(Vec<u8>);
+
+impl Memory for VecMemory {
+	fn with_access_mut<R>(&mut self, run: impl FnOnce(&mut [u8]) -> R) -> R {
+		run(&mut self.0)
+	}
+
+	fn with_access<R>(&self, run: impl FnOnce(&[u8]) -> R) -> R {
+		run(&self.0)
+	}
+
+	fn grow(&mut self, additional: u32) -> Result<(), ()> {
+		if self.pages() + additional > MAX_PAGES {
+			return Err(())
+		}
+		self.0.resize(((self.pages() + additional) * PAGE_SIZE) as usize, 0);
+		Ok(())
+	}
+
+	fn pages(&self) -> u32 {
+		(self.0.len() / PAGE_SIZE as usize) as u32
+	}
+
+	fn max_pages(&self) -> Option<u32> {
+		Some(MAX_PAGES)
+	}
+}
+
+/// Actions of an `Allocator` that we fuzz.
+enum Action {
+	/// Allocate the given number of bytes.
+	Allocate(u32),
+	/// Free the live allocation picked by the given seed.
+	Free(u32),
+}
+
+impl From<(bool, u32)> for Action {
+	fn from((allocate, seed): (bool, u32)) -> Self {
+		if allocate {
+			Action::Allocate(seed % (MAX_ALLOCATION_SIZE + 1))
+		} else {
+			Action::Free(seed)
+		}
+	}
+}
+
+/// The byte an allocation is filled with, so that overwrites by the allocator are detected.
+fn fill_byte(ptr: u32) -> u8 {
+	(ptr / 8) as u8 | 1
+}
+
+fn run(kind: AllocatorKind, actions: &[(bool, u32)]) {
+	let mut memory = VecMemory(vec![0; PAGE_SIZE as usize]);
+	let mut allocator = HeapAllocator::new(kind, HEAP_BASE);
+	// Maps the pointers of the live allocations to their sizes.
+	let mut live = BTreeMap::<u32, u32>::new();
+
+	for action in actions.iter().copied().map(Action::from) {
+		match action {
+			Action::Allocate(size) => {
+				let ptr = match allocator.allocate(&mut memory, size) {
+					Ok(ptr) => u32::from(ptr),
+					// Running out of space poisons the allocator, nothing can be freed anymore.
+					Err(Error::AllocatorOutOfSpace) => return,
+					Err(error) =>
+						panic!("{:?}: allocation of {} bytes failed: {}", kind, size, error),
+				};
+
+				assert_eq!(ptr % 8, 0, "{:?}: {} is not aligned", kind, ptr);
+				assert!(ptr >= HEAP_BASE, "{:?}: {} is below the heap", kind, ptr);
+				let end = ptr as usize + size as usize;
+				assert!(end <= memory.0.len(), "{:?}: {}+{} is out of bounds", kind, ptr, size);
+
+				if let Some((&prev, &prev_size)) = live.range(..=ptr).next_back() {
+					assert!(prev + prev_size <= ptr, "{:?}: {} overlaps {}", kind, ptr, prev);
+				}
+				if let Some((&next, _)) = live.range(ptr..).next() {
+					assert!(end <= next as usize, "{:?}: {} overlaps {}", kind, ptr, next);
+				}
+
+				memory.0[ptr as usize..end].fill(fill_byte(ptr));
+				live.insert(ptr, size);
+			},
+			Action::Free(seed) => {
+				if live.is_empty() {
+					continue
+				}
+				let index = seed as usize % live.len();
+				let (&ptr, &size) = live.iter().nth(index).expect("index is within bounds; qed");
+				free(kind, &mut allocator, &mut memory, ptr, size);
+				live.remove(&ptr);
+			},
+		}
+	}
+
+	for (ptr, size) in std::mem::take(&mut live) {
+		free(kind, &mut allocator, &mut memory, ptr, size);
+	}
+	assert_eq!(allocator.stats().bytes_allocated, 0, "{:?}: bytes are leaked", kind);
+}
+
+fn free(
+	kind: AllocatorKind,
+	allocator: &mut HeapAllocator,
+	memory: &mut VecMemory,
+	ptr: u32,
+	size: u32,
+) {
+	let contents = &memory.0[ptr as usize..(ptr + size) as usize];
+	assert!(
+		contents.iter().all(|byte| *byte == fill_byte(ptr)),
+		"{:?}: the contents of {} were overwritten",
+		kind,
+		ptr,
+	);
+	allocator
+		.deallocate(memory, Pointer::new(ptr))
+		.unwrap_or_else(|error| panic!("{:?}: freeing {} failed: {}", kind, ptr, error));
+}
+
+fn main() {
+	loop {
+		fuzz!(|actions: Vec<(bool, u32)>| {
+			for kind in [AllocatorKind::FreeingBump, AllocatorKind::SizeClass] {
+				run(kind, &actions);
+			}
+		});
+	}
+}
@@ -87,11 +87,11 @@ const ALIGNMENT: u32 = 8;
 const HEADER_SIZE: u32 = 8;
 
 /// Create an allocator error.
-fn error(msg: &'static str) -> Error {
+pub(crate) fn error(msg: &'static str) -> Error {
 	Error::Other(msg)
 }
 
-const LOG_TARGET: &str = "wasm-heap";
+pub(crate) const LOG_TARGET: &str = "wasm-heap";
 
 // The minimum possible allocation size is chosen to be 8 bytes because in that case we would have
 // easier time to provide the guaranteed alignment of 8.
@@ -339,8 +339,9 @@ pub struct AllocationStats {
 	/// This is calculated as the difference between the allocator's bumper
 	/// and the heap base.
 	///
-	/// Currently the bumper's only ever incremented, so this is simultaneously
-	/// the current value as well as the peak value.
+	/// The bumper of the freeing-bump allocator is only ever incremented, so this is
+	/// simultaneously the current value as well as the peak value. For the other allocators
+	/// this is the peak value.
 	pub address_space_used: u32,
 }
 
@@ -349,7 +350,7 @@ pub struct AllocationStats {
 /// The returned number of pages is ensured to be big enough to hold memory with the given `size`.
 ///
 /// Returns `None` if the number of pages to not fit into `u32`.
-fn pages_from_size(size: u64) -> Option<u32> {
+pub(crate) fn pages_from_size(size: u64) -> Option<u32> {
 	u32::try_from((size + PAGE_SIZE as u64 - 1) / PAGE_SIZE as u64).ok()
 }
 
@@ -416,7 +417,7 @@ impl FreeingBumpHeapAllocator {
 
 		let bomb = PoisonBomb { poisoned: &mut self.poisoned };
 
-		Self::observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+		observe_memory_size(&mut self.last_observed_memory_size, mem)?;
 		let order = Order::from_size(size)?;
 
 		let header_ptr: u32 = match self.free_lists[order] {
@@ -437,7 +438,7 @@ impl FreeingBumpHeapAllocator {
 			},
 			Link::Nil => {
 				// Corresponding free list is empty. Allocate a new item.
-				Self::bump(&mut self.bumper, order.size() + HEADER_SIZE, mem)?
+				bump(&mut self.bumper, order.size() + HEADER_SIZE, mem)?
 			},
 		};
 
@@ -474,7 +475,7 @@ impl FreeingBumpHeapAllocator {
 
 		let bomb = PoisonBomb { poisoned: &mut self.poisoned };
 
-		Self::observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+		observe_memory_size(&mut self.last_observed_memory_size, mem)?;
 
 		let header_ptr = u32::from(ptr)
 			.checked_sub(HEADER_SIZE)
@@ -504,74 +505,75 @@ impl FreeingBumpHeapAllocator {
 	pub fn stats(&self) -> AllocationStats {
 		self.stats.clone()
 	}
+}
 
-	/// Increases the `bumper` by `size`.
-	///
-	/// Returns the `bumper` from before the increase. Returns an `Error::AllocatorOutOfSpace` if
-	/// the operation would exhaust the heap.
-	fn bump(bumper: &mut u32, size: u32, memory: &mut impl Memory) -> Result<u32, Error> {
-		let required_size = u64::from(*bumper) + u64::from(size);
-
-		if required_size > memory.size() {
-			let required_pages =
-				pages_from_size(required_size).ok_or_else(|| Error::AllocatorOutOfSpace)?;
-
-			let current_pages = memory.pages();
-			let max_pages = memory.max_pages().unwrap_or(MAX_WASM_PAGES);
-			debug_assert!(
-				current_pages < required_pages,
-				"current pages {current_pages} < required pages {required_pages}"
-			);
+/// Increases the `bumper` by `size`.
+///
+/// Returns the `bumper` from before the increase. Returns an `Error::AllocatorOutOfSpace` if
+/// the operation would exhaust the heap.
+pub(crate) fn bump(bumper: &mut u32, size: u32, memory: &mut impl Memory) -> Result<u32, Error> {
+	let required_size = u64::from(*bumper) + u64::from(size);
+
+	if required_size > memory.size() {
+		let required_pages =
+			pages_from_size(required_size).ok_or_else(|| Error::AllocatorOutOfSpace)?;
+
+		let current_pages = memory.pages();
+		let max_pages = memory.max_pages().unwrap_or(MAX_WASM_PAGES);
+		debug_assert!(
+			current_pages < required_pages,
+			"current pages {current_pages} < required pages {required_pages}"
+		);
 
-			if current_pages >= max_pages {
-				log::debug!(
-					target: LOG_TARGET,
-					"Wasm pages ({current_pages}) are already at the maximum.",
-				);
-
-				return Err(Error::AllocatorOutOfSpace)
-			} else if required_pages > max_pages {
-				log::debug!(
-					target: LOG_TARGET,
-					"Failed to grow memory from {current_pages} pages to at least {required_pages}\
-						 pages due to the maximum limit of {max_pages} pages",
-				);
-				return Err(Error::AllocatorOutOfSpace)
-			}
+		if current_pages >= max_pages {
+			log::debug!(
+				target: LOG_TARGET,
+				"Wasm pages ({current_pages}) are already at the maximum.",
+			);
 
-			// Ideally we want to double our current number of pages,
-			// as long as it's less than the absolute maximum we can have.
-			let next_pages = min(current_pages * 2, max_pages);
-			// ...but if even more pages are required then try to allocate that many.
-			let next_pages = max(next_pages, required_pages);
+			return Err(Error::AllocatorOutOfSpace)
+		} else if required_pages > max_pages {
+			log::debug!(
+				target: LOG_TARGET,
+				"Failed to grow memory from {current_pages} pages to at least {required_pages}\
+					 pages due to the maximum limit of {max_pages} pages",
+			);
+			return Err(Error::AllocatorOutOfSpace)
+		}
 
-			if memory.grow(next_pages - current_pages).is_err() {
-				log::error!(
-					target: LOG_TARGET,
-					"Failed to grow memory from {current_pages} pages to {next_pages} pages",
-				);
+		// Ideally we want to double our current number of pages,
+		// as long as it's less than the absolute maximum we can have.
+		let next_pages = min(current_pages * 2, max_pages);
+		// ...but if even more pages are required then try to allocate that many.
+		let next_pages = max(next_pages, required_pages);
 
-				return Err(Error::AllocatorOutOfSpace)
-			}
+		if memory.grow(next_pages - current_pages).is_err() {
+			log::error!(
+				target: LOG_TARGET,
+				"Failed to grow memory from {current_pages} pages to {next_pages} pages",
+			);
 
-			debug_assert_eq!(memory.pages(), next_pages, "Number of pages should have increased!");
+			return Err(Error::AllocatorOutOfSpace)
 		}
 
-		let res = *bumper;
-		*bumper += size;
-		Ok(res)
+		debug_assert_eq!(memory.pages(), next_pages, "Number of pages should have increased!");
 	}
 
-	fn observe_memory_size(
-		last_observed_memory_size: &mut u64,
-		mem: &mut impl Memory,
-	) -> Result<(), Error> {
-		if mem.size() < *last_observed_memory_size {
-			return Err(Error::MemoryShrinked)
-		}
-		*last_observed_memory_size = mem.size();
-		Ok(())
+	let res = *bumper;
+	*bumper += size;
+	Ok(res)
+}
+
+/// Fails if the memory is smaller than when it was last observed.
+pub(crate) fn observe_memory_size(
+	last_observed_memory_size: &mut u64,
+	mem: &mut impl Memory,
+) -> Result<(), Error> {
+	if mem.size() < *last_observed_memory_size {
+		return Err(Error::MemoryShrinked)
 	}
+	*last_observed_memory_size = mem.size();
+	Ok(())
 }
 
 /// A trait for abstraction of accesses to a wasm linear memory. Used to read or modify the
@@ -581,7 +583,7 @@ impl FreeingBumpHeapAllocator {
 /// accessible up to the reported size.
 ///
 /// The linear memory can grow in size with the wasm page granularity (64KiB), but it cannot shrink.
-trait MemoryExt: Memory {
+pub(crate) trait MemoryExt: Memory {
 	/// Read a u64 from the heap in LE form. Returns an error if any of the bytes read are out of
 	/// bounds.
 	fn read_le_u64(&self, ptr: u32) -> Result<u64, Error> {
@@ -628,12 +630,12 @@ fn heap_range(offset: u32, length: u32, heap_len: usize) -> Option<Range<usize>>
 }
 
 /// A guard that will raise the poisoned flag on drop unless disarmed.
-struct PoisonBomb<'a> {
-	poisoned: &'a mut bool,
+pub(crate) struct PoisonBomb<'a> {
+	pub(crate) poisoned: &'a mut bool,
 }
 
 impl<'a> PoisonBomb<'a> {
-	fn disarm(self) {
+	pub(crate) fn disarm(self) {
 		mem::forget(self)
 	}
 }
@@ -645,7 +647,7 @@ impl<'a> Drop for PoisonBomb<'a> {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
 	use super::*;
 
 	/// Makes a pointer out of the given address.
@@ -654,17 +656,17 @@ mod tests {
 	}
 
 	#[derive(Debug)]
-	struct MemoryInstance {
+	pub(crate) struct MemoryInstance {
 		data: Vec<u8>,
 		max_wasm_pages: u32,
 	}
 
 	impl MemoryInstance {
-		fn with_pages(pages: u32) -> Self {
+		pub(crate) fn with_pages(pages: u32) -> Self {
 			Self { data: vec![0; (pages * PAGE_SIZE) as usize], max_wasm_pages: MAX_WASM_PAGES }
 		}
 
-		fn set_max_wasm_pages(&mut self, max_pages: u32) {
+		pub(crate) fn set_max_wasm_pages(&mut self, max_pages: u32) {
 			self.max_wasm_pages = max_pages;
 		}
 	}
@@ -19,14 +19,22 @@
 //!
 //! This crate provides the following allocator implementations:
 //! - A freeing-bump allocator: [`FreeingBumpHeapAllocator`](freeing_bump::FreeingBumpHeapAllocator)
+//! - A size-class allocator which coalesces freed chunks:
+//!   [`SizeClassHeapAllocator`](size_class::SizeClassHeapAllocator)
+//!
+//! Both implement the [`Allocator`] trait. [`HeapAllocator`] is either of them, chosen at runtime
+//! with an [`AllocatorKind`].
 
 #![warn(missing_docs)]
 
 mod error;
 mod freeing_bump;
+mod size_class;
 
 pub use error::Error;
 pub use freeing_bump::{AllocationStats, FreeingBumpHeapAllocator};
+pub use size_class::SizeClassHeapAllocator;
+use sp_wasm_interface::{Pointer, WordSize};
 
 /// The size of one wasm page in bytes.
 ///
@@ -59,3 +67,109 @@ pub trait Memory {
 	/// If `None` is returned, there is no maximum (besides the maximum defined in the wasm spec).
 	fn max_pages(&self) -> Option<u32>;
 }
+
+/// An allocator of the heap of a wasm instance.
+pub trait Allocator {
+	/// Allocates `size` bytes and returns a pointer to them, aligned to 8 bytes.
+	///
+	/// The memory must not shrink compared to the memory passed in previous invocations. Once the
+	/// allocator has returned an error all subsequent requests return an error.
+	fn allocate(&mut self, mem: &mut impl Memory, size: WordSize) -> Result<Pointer<u8>, Error>;
+
+	/// Deallocates the allocation at `ptr`.
+	///
+	/// The same requirements as for [`Self::allocate`] apply.
+	fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error>;
+
+	/// Returns the stats gathered during the lifetime of the allocator.
+	fn stats(&self) -> AllocationStats;
+}
+
+impl Allocator for FreeingBumpHeapAllocator {
+	fn allocate(&mut self, mem: &mut impl Memory, size: WordSize) -> Result<Pointer<u8>, Error> {
+		FreeingBumpHeapAllocator::allocate(self, mem, size)
+	}
+
+	fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		FreeingBumpHeapAllocator::deallocate(self, mem, ptr)
+	}
+
+	fn stats(&self) -> AllocationStats {
+		FreeingBumpHeapAllocator::stats(self)
+	}
+}
+
+impl Allocator for SizeClassHeapAllocator {
+	fn allocate(&mut self, mem: &mut impl Memory, size: WordSize) -> Result<Pointer<u8>, Error> {
+		SizeClassHeapAllocator::allocate(self, mem, size)
+	}
+
+	fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		SizeClassHeapAllocator::deallocate(self, mem, ptr)
+	}
+
+	fn stats(&self) -> AllocationStats {
+		SizeClassHeapAllocator::stats(self)
+	}
+}
+
+/// The allocator implementations.
+///
+/// The allocator determines which calls run out of memory, so all the nodes executing the blocks
+/// of a chain have to use the same one. Block execution always uses the default
+/// [`FreeingBump`](Self::FreeingBump) allocator.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AllocatorKind {
+	/// The [`FreeingBumpHeapAllocator`].
+	///
+	/// Fast, but sizes are rounded up to powers of two and freed memory can only be reused by
+	/// allocations of the same size class.
+	#[default]
+	FreeingBump,
+	/// The [`SizeClassHeapAllocator`].
+	///
+	/// Slower, but sizes are rounded up to multiples of 8 bytes and freed memory is coalesced.
+	SizeClass,
+}
+
+/// An allocator of the [`AllocatorKind`] chosen at runtime.
+pub enum HeapAllocator {
+	/// A [`FreeingBumpHeapAllocator`].
+	FreeingBump(FreeingBumpHeapAllocator),
+	/// A [`SizeClassHeapAllocator`].
+	SizeClass(SizeClassHeapAllocator),
+}
+
+impl HeapAllocator {
+	/// Creates a new allocator of the given `kind` for the heap starting at `heap_base`.
+	pub fn new(kind: AllocatorKind, heap_base: u32) -> Self {
+		match kind {
+			AllocatorKind::FreeingBump =>
+				Self::FreeingBump(FreeingBumpHeapAllocator::new(heap_base)),
+			AllocatorKind::SizeClass => Self::SizeClass(SizeClassHeapAllocator::new(heap_base)),
+		}
+	}
+}
+
+impl Allocator for HeapAllocator {
+	fn allocate(&mut self, mem: &mut impl Memory, size: WordSize) -> Result<Pointer<u8>, Error> {
+		match self {
+			Self::FreeingBump(allocator) => allocator.allocate(mem, size),
+			Self::SizeClass(allocator) => allocator.allocate(mem, size),
+		}
+	}
+
+	fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		match self {
+			Self::FreeingBump(allocator) => allocator.deallocate(mem, ptr),
+			Self::SizeClass(allocator) => allocator.deallocate(mem, ptr),
+		}
+	}
+
+	fn stats(&self) -> AllocationStats {
+		match self {
+			Self::FreeingBump(allocator) => allocator.stats(),
+			Self::SizeClass(allocator) => allocator.stats(),
+		}
+	}
+}
@@ -0,0 +1,645 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements a size-class allocator which coalesces freed chunks.
+//!
+//! Like with the freeing-bump allocator, chunks are carved out of the unallocated part of the heap
+//! by a bumper. Unlike it, the size of a chunk is the size of the allocation rounded up to a
+//! multiple of 8 bytes rather than to the next power of two, a freed chunk is merged with its free
+//! neighbours and a free chunk ending at the bumper is given back to the unallocated part of the
+//! heap by moving the bumper down.
+//!
+//! ```ignore
+//! +---------+------+---------+------+-------------------------------------+
+//! | <chunk> | free | <chunk> | free | <unallocated>                       |
+//! +---------+------+---------+------+-------------------------------------+
+//!                                   ^
+//!                                   |_ bumper
+//! ```
+//!
+//! Each chunk starts with an 8 bytes header, which is followed by the allocation. The header
+//! holds the size of the chunk, header included, whether the chunk is occupied and the size of the
+//! chunk right before it, which is needed to merge a freed chunk with the chunk before it:
+//!
+//! ```ignore
+//! 64                          32                     1            0
+//! +---------------------------+----------------------+------------+
+//! |       previous chunk size |           chunk size |   occupied |
+//! +---------------------------+----------------------+------------+
+//! ```
+//!
+//! The size of the first chunk of the heap is recorded as the previous chunk size of the next one
+//! and so on, the first chunk has a previous chunk size of 0.
+//!
+//! Free chunks are kept in doubly linked lists, one per size class. The class of a chunk is the
+//! power of two its size is rounded down to. The links are stored in the first 8 bytes following
+//! the header of the free chunk, which is why a chunk is at least 16 bytes.
+//!
+//! An allocation takes the first chunk that is big enough out of the list of its class or else
+//! a chunk out of a list of a bigger class, and splits off the end of the chunk when that end can
+//! be a chunk of its own. Only when there isn't such a free chunk the bumper is increased.
+//!
+//! # Caveats
+//!
+//! Allocations and deallocations are slower than with the freeing-bump allocator, as they may
+//! look through a free list and touch the neighbouring chunks. In order to keep the time spent in
+//! an allocation bounded, only the first [`MAX_SCANNED_CHUNKS`] chunks of a list are considered.
+//!
+//! The heap can still be fragmented by chunks which stay allocated between free chunks.
+
+use crate::{
+	freeing_bump::{bump, error, observe_memory_size, MemoryExt, PoisonBomb, LOG_TARGET},
+	AllocationStats, Error, Memory,
+};
+use sp_core::MAX_POSSIBLE_ALLOCATION;
+use sp_wasm_interface::{Pointer, WordSize};
+use std::cmp::max;
+
+/// The minimal alignment guaranteed by this allocator.
+const ALIGNMENT: u32 = 8;
+
+/// The size of the header preceding each allocation.
+const HEADER_SIZE: u32 = 8;
+
+/// The smallest chunk, which can hold the header and the links of a free chunk.
+const MIN_CHUNK_SIZE: u32 = 16;
+
+/// The number of size classes.
+///
+/// Classes range from chunks of `2^4` bytes, the minimal chunk size, to chunks of `2^31` bytes
+/// and more, as merged chunks are only bounded by the size of the memory.
+const N_CLASSES: usize = 28;
+
+/// The maximum number of chunks of the list of the requested class looked at by an allocation.
+const MAX_SCANNED_CHUNKS: usize = 32;
+
+/// A special value for a link that denotes the end of a free list.
+const NIL: u32 = u32::MAX;
+
+/// Returns the size class of a chunk of the given `size`.
+fn class(size: u32) -> usize {
+	debug_assert!(size >= MIN_CHUNK_SIZE);
+	(size.ilog2() - MIN_CHUNK_SIZE.ilog2()) as usize
+}
+
+/// The header of a chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+	/// The size of the chunk, including the header.
+	size: u32,
+	/// The size of the chunk right before this one, 0 for the first chunk of the heap.
+	prev_size: u32,
+	/// Whether the chunk holds an allocation.
+	occupied: bool,
+}
+
+impl Header {
+	/// Reads a header from memory.
+	///
+	/// Returns an error if the `header_ptr` is out of bounds of the linear memory or if the read
+	/// header is corrupted.
+	fn read_from(memory: &impl Memory, header_ptr: u32) -> Result<Self, Error> {
+		let raw_header = memory.read_le_u64(header_ptr)?;
+		let size = raw_header as u32 & !(ALIGNMENT - 1);
+		if size < MIN_CHUNK_SIZE || raw_header as u32 & (ALIGNMENT - 2) != 0 {
+			return Err(error("invalid chunk header"))
+		}
+
+		Ok(Self { size, prev_size: (raw_header >> 32) as u32, occupied: raw_header & 1 != 0 })
+	}
+
+	/// Writes out this header to memory.
+	///
+	/// Returns an error if the `header_ptr` is out of bounds of the linear memory.
+	fn write_into(&self, memory: &mut impl Memory, header_ptr: u32) -> Result<(), Error> {
+		let raw_header =
+			u64::from(self.prev_size) << 32 | u64::from(self.size | self.occupied as u32);
+		memory.write_le_u64(header_ptr, raw_header)
+	}
+}
+
+/// The links of a free chunk to its neighbours in the free list of its class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Links {
+	next: u32,
+	prev: u32,
+}
+
+impl Links {
+	/// Reads the links of the free chunk at `header_ptr`.
+	fn read_from(memory: &impl Memory, header_ptr: u32) -> Result<Self, Error> {
+		let raw_links = memory.read_le_u64(header_ptr.saturating_add(HEADER_SIZE))?;
+		Ok(Self { next: raw_links as u32, prev: (raw_links >> 32) as u32 })
+	}
+
+	/// Writes out the links of the free chunk at `header_ptr`.
+	fn write_into(&self, memory: &mut impl Memory, header_ptr: u32) -> Result<(), Error> {
+		let raw_links = u64::from(self.prev) << 32 | u64::from(self.next);
+		memory.write_le_u64(header_ptr.saturating_add(HEADER_SIZE), raw_links)
+	}
+}
+
+/// An implementation of a size-class allocator which coalesces freed chunks.
+///
+/// Refer to the module-level documentation for further details.
+pub struct SizeClassHeapAllocator {
+	heap_base: u32,
+	bumper: u32,
+	/// The size of the chunk ending at the bumper, 0 if the heap is empty.
+	last_chunk_size: u32,
+	free_lists: [u32; N_CLASSES],
+	poisoned: bool,
+	last_observed_memory_size: u64,
+	stats: AllocationStats,
+}
+
+impl Drop for SizeClassHeapAllocator {
+	fn drop(&mut self) {
+		log::debug!(target: LOG_TARGET, "size-class allocator dropped: {:?}", self.stats)
+	}
+}
+
+impl SizeClassHeapAllocator {
+	/// Creates a new allocation heap which follows a size-class strategy.
+	///
+	/// # Arguments
+	///
+	/// - `heap_base` - the offset from the beginning of the linear memory where the heap starts.
+	pub fn new(heap_base: u32) -> Self {
+		let aligned_heap_base = (heap_base + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT;
+
+		SizeClassHeapAllocator {
+			heap_base: aligned_heap_base,
+			bumper: aligned_heap_base,
+			last_chunk_size: 0,
+			free_lists: [NIL; N_CLASSES],
+			poisoned: false,
+			last_observed_memory_size: 0,
+			stats: AllocationStats::default(),
+		}
+	}
+
+	/// Gets requested number of bytes to allocate and returns a pointer.
+	/// The maximum size which can be allocated at once is 32 MiB.
+	/// The size is rounded up to a multiple of 8 bytes, and to 8 bytes if it is smaller.
+	///
+	/// The identity or the type of the passed memory object does not matter. However, the size of
+	/// memory cannot shrink compared to the memory passed in previous invocations.
+	///
+	/// NOTE: Once the allocator has returned an error all subsequent requests will return an error.
+	///
+	/// # Arguments
+	///
+	/// - `mem` - a slice representing the linear memory on which this allocator operates.
+	/// - `size` - size in bytes of the allocation request
+	pub fn allocate(
+		&mut self,
+		mem: &mut impl Memory,
+		size: WordSize,
+	) -> Result<Pointer<u8>, Error> {
+		if self.poisoned {
+			return Err(error("the allocator has been poisoned"))
+		}
+
+		let bomb = PoisonBomb { poisoned: &mut self.poisoned };
+
+		observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+		if size > MAX_POSSIBLE_ALLOCATION {
+			log::warn!(target: LOG_TARGET, "going to fail due to allocating {:?}", size);
+			return Err(Error::RequestedAllocationTooLarge)
+		}
+		let needed =
+			max(MIN_CHUNK_SIZE, (size + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT + HEADER_SIZE);
+
+		let (header_ptr, chunk_size) =
+			match Self::find_free(&self.free_lists, self.heap_base, self.bumper, mem, needed)? {
+				Some((header_ptr, header)) => {
+					self.unlink(mem, header_ptr, header.size)?;
+
+					let rest_size = header.size - needed;
+					if rest_size >= MIN_CHUNK_SIZE {
+						// Split off the end of the chunk. A free chunk never ends at the bumper, so
+						// there always is a chunk after it.
+						let rest_ptr = header_ptr + needed;
+						Header { size: rest_size, prev_size: needed, occupied: false }
+							.write_into(mem, rest_ptr)?;
+						self.link(mem, rest_ptr, rest_size)?;
+						self.set_prev_size(mem, rest_ptr + rest_size, rest_size)?;
+
+						Header { size: needed, prev_size: header.prev_size, occupied: true }
+							.write_into(mem, header_ptr)?;
+						(header_ptr, needed)
+					} else {
+						Header { occupied: true, ..header }.write_into(mem, header_ptr)?;
+						(header_ptr, header.size)
+					}
+				},
+				None => {
+					// No free chunk fits. Allocate a new one.
+					let header_ptr = bump(&mut self.bumper, needed, mem)?;
+					Header { size: needed, prev_size: self.last_chunk_size, occupied: true }
+						.write_into(mem, header_ptr)?;
+					self.last_chunk_size = needed;
+					(header_ptr, needed)
+				},
+			};
+
+		self.stats.bytes_allocated += chunk_size;
+		self.stats.bytes_allocated_sum += u128::from(chunk_size);
+		self.stats.bytes_allocated_peak =
+			max(self.stats.bytes_allocated_peak, self.stats.bytes_allocated);
+		self.stats.address_space_used =
+			max(self.stats.address_space_used, self.bumper - self.heap_base);
+
+		log::trace!(target: LOG_TARGET, "after allocation: {:?}", self.stats);
+
+		bomb.disarm();
+		Ok(Pointer::new(header_ptr + HEADER_SIZE))
+	}
+
+	/// Deallocates the space which was allocated for a pointer.
+	///
+	/// The identity or the type of the passed memory object does not matter. However, the size of
+	/// memory cannot shrink compared to the memory passed in previous invocations.
+	///
+	/// NOTE: Once the allocator has returned an error all subsequent requests will return an error.
+	///
+	/// # Arguments
+	///
+	/// - `mem` - a slice representing the linear memory on which this allocator operates.
+	/// - `ptr` - pointer to the allocated chunk
+	pub fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		if self.poisoned {
+			return Err(error("the allocator has been poisoned"))
+		}
+
+		let bomb = PoisonBomb { poisoned: &mut self.poisoned };
+
+		observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+
+		let header_ptr = u32::from(ptr)
+			.checked_sub(HEADER_SIZE)
+			.filter(|header_ptr| *header_ptr >= self.heap_base)
+			.ok_or_else(|| error("Invalid pointer for deallocation"))?;
+
+		let header = Self::read_chunk(self.heap_base, self.bumper, mem, header_ptr)?;
+		if !header.occupied {
+			return Err(error("the allocation points to a free chunk"))
+		}
+
+		self.stats.bytes_allocated = self
+			.stats
+			.bytes_allocated
+			.checked_sub(header.size)
+			.ok_or_else(|| error("underflow of the currently allocated bytes count"))?;
+
+		let (mut chunk_ptr, mut chunk_size, mut prev_size) =
+			(header_ptr, header.size, header.prev_size);
+
+		// Merge with the next chunk if it is free.
+		let next_ptr = chunk_ptr + chunk_size;
+		if next_ptr < self.bumper {
+			let next = Self::read_chunk(self.heap_base, self.bumper, mem, next_ptr)?;
+			if next.prev_size != chunk_size {
+				return Err(error("chunk headers are inconsistent"))
+			}
+			if !next.occupied {
+				self.unlink(mem, next_ptr, next.size)?;
+				chunk_size += next.size;
+			}
+		}
+
+		// Merge with the previous chunk if it is free.
+		if prev_size != 0 {
+			let prev_ptr = chunk_ptr
+				.checked_sub(prev_size)
+				.filter(|prev_ptr| *prev_ptr >= self.heap_base)
+				.ok_or_else(|| error("chunk headers are inconsistent"))?;
+			let prev = Self::read_chunk(self.heap_base, self.bumper, mem, prev_ptr)?;
+			if prev.size != prev_size {
+				return Err(error("chunk headers are inconsistent"))
+			}
+			if !prev.occupied {
+				self.unlink(mem, prev_ptr, prev.size)?;
+				chunk_ptr = prev_ptr;
+				chunk_size += prev.size;
+				prev_size = prev.prev_size;
+			}
+		}
+
+		if chunk_ptr + chunk_size == self.bumper {
+			// Give the chunk back to the unallocated part of the heap. The chunk before it, if
+			// any, is occupied as it would have been merged otherwise.
+			self.bumper = chunk_ptr;
+			self.last_chunk_size = prev_size;
+		} else {
+			Header { size: chunk_size, prev_size, occupied: false }.write_into(mem, chunk_ptr)?;
+			self.link(mem, chunk_ptr, chunk_size)?;
+			self.set_prev_size(mem, chunk_ptr + chunk_size, chunk_size)?;
+		}
+
+		log::trace!(target: LOG_TARGET, "after deallocation: {:?}", self.stats);
+
+		bomb.disarm();
+		Ok(())
+	}
+
+	/// Returns the allocation stats for this allocator.
+	pub fn stats(&self) -> AllocationStats {
+		self.stats.clone()
+	}
+
+	/// Reads the header of the chunk at `header_ptr`, checking that the chunk lies between the
+	/// heap base and the bumper.
+	fn read_chunk(
+		heap_base: u32,
+		bumper: u32,
+		mem: &impl Memory,
+		header_ptr: u32,
+	) -> Result<Header, Error> {
+		let header = Header::read_from(mem, header_ptr)?;
+		if header_ptr < heap_base ||
+			u64::from(header_ptr) + u64::from(header.size) > u64::from(bumper)
+		{
+			return Err(error("chunk out of heap bounds"))
+		}
+		Ok(header)
+	}
+
+	/// Finds a free chunk of at least `size` bytes.
+	///
+	/// Returns the pointer to the header of the chunk and the header.
+	fn find_free(
+		free_lists: &[u32; N_CLASSES],
+		heap_base: u32,
+		bumper: u32,
+		mem: &impl Memory,
+		size: u32,
+	) -> Result<Option<(u32, Header)>, Error> {
+		let read_free_chunk = |header_ptr: u32, class_index: usize| {
+			let header = Self::read_chunk(heap_base, bumper, mem, header_ptr)?;
+			if header.occupied || class(header.size) != class_index {
+				return Err(error("free list points to an invalid chunk"))
+			}
+			Ok(header)
+		};
+
+		// Chunks of the requested class may be smaller than `size`.
+		let class_index = class(size);
+		let mut header_ptr = free_lists[class_index];
+		for _ in 0..MAX_SCANNED_CHUNKS {
+			if header_ptr == NIL {
+				break
+			}
+			let header = read_free_chunk(header_ptr, class_index)?;
+			if header.size >= size {
+				return Ok(Some((header_ptr, header)))
+			}
+			header_ptr = Links::read_from(mem, header_ptr)?.next;
+		}
+
+		// Any chunk of a bigger class is big enough.
+		for (class_index, &header_ptr) in free_lists.iter().enumerate().skip(class_index + 1) {
+			if header_ptr != NIL {
+				return Ok(Some((header_ptr, read_free_chunk(header_ptr, class_index)?)))
+			}
+		}
+
+		Ok(None)
+	}
+
+	/// Inserts the free chunk at `header_ptr` at the head of the free list of its class.
+	fn link(&mut self, mem: &mut impl Memory, header_ptr: u32, size: u32) -> Result<(), Error> {
+		let head = &mut self.free_lists[class(size)];
+		if *head != NIL {
+			let links = Links::read_from(mem, *head)?;
+			Links { prev: header_ptr, ..links }.write_into(mem, *head)?;
+		}
+		Links { next: *head, prev: NIL }.write_into(mem, header_ptr)?;
+		*head = header_ptr;
+		Ok(())
+	}
+
+	/// Removes the free chunk at `header_ptr` from the free list of its class.
+	fn unlink(&mut self, mem: &mut impl Memory, header_ptr: u32, size: u32) -> Result<(), Error> {
+		let Links { next, prev } = Links::read_from(mem, header_ptr)?;
+		let in_heap = |ptr: u32| ptr == NIL || (ptr >= self.heap_base && ptr < self.bumper);
+		if next == header_ptr || prev == header_ptr || !in_heap(next) || !in_heap(prev) {
+			return Err(error("free list is corrupted"))
+		}
+		if prev == NIL {
+			let head = &mut self.free_lists[class(size)];
+			if *head != header_ptr {
+				return Err(error("free list is corrupted"))
+			}
+			*head = next;
+		} else {
+			let links = Links::read_from(mem, prev)?;
+			if links.next != header_ptr {
+				return Err(error("free list is corrupted"))
+			}
+			Links { next, ..links }.write_into(mem, prev)?;
+		}
+		if next != NIL {
+			let links = Links::read_from(mem, next)?;
+			if links.prev != header_ptr {
+				return Err(error("free list is corrupted"))
+			}
+			Links { prev, ..links }.write_into(mem, next)?;
+		}
+		Ok(())
+	}
+
+	/// Records `prev_size` in the header of the chunk at `header_ptr`, if there is a chunk there.
+	fn set_prev_size(
+		&mut self,
+		mem: &mut impl Memory,
+		header_ptr: u32,
+		prev_size: u32,
+	) -> Result<(), Error> {
+		if header_ptr == self.bumper {
+			self.last_chunk_size = prev_size;
+			return Ok(())
+		}
+		let header = Self::read_chunk(self.heap_base, self.bumper, mem, header_ptr)?;
+		Header { prev_size, ..header }.write_into(mem, header_ptr)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{freeing_bump::tests::MemoryInstance, PAGE_SIZE};
+
+	fn allocate(heap: &mut SizeClassHeapAllocator, mem: &mut MemoryInstance, size: u32) -> u32 {
+		heap.allocate(mem, size).unwrap().into()
+	}
+
+	fn deallocate(heap: &mut SizeClassHeapAllocator, mem: &mut MemoryInstance, ptr: u32) {
+		heap.deallocate(mem, Pointer::new(ptr)).unwrap()
+	}
+
+	#[test]
+	fn should_allocate_properly() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(13);
+
+		let first = allocate(&mut heap, &mut mem, 1);
+		let second = allocate(&mut heap, &mut mem, 9);
+		let third = allocate(&mut heap, &mut mem, 0);
+
+		// The heap base is aligned, the first allocation takes a chunk of the minimal size and
+		// the second one is rounded up to 16 bytes.
+		assert_eq!(first, 16 + HEADER_SIZE);
+		assert_eq!(second, first + MIN_CHUNK_SIZE);
+		assert_eq!(third, second + 16 + HEADER_SIZE);
+		assert!([first, second, third].iter().all(|ptr| ptr % ALIGNMENT == 0));
+	}
+
+	#[test]
+	fn should_reuse_freed_chunk_of_different_size() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let first = allocate(&mut heap, &mut mem, 1000);
+		let _guard = allocate(&mut heap, &mut mem, 8);
+		deallocate(&mut heap, &mut mem, first);
+
+		// The freed chunk is split between both allocations.
+		let second = allocate(&mut heap, &mut mem, 100);
+		let third = allocate(&mut heap, &mut mem, 500);
+		assert_eq!(second, first);
+		assert_eq!(third, second + 104 + HEADER_SIZE);
+	}
+
+	#[test]
+	fn should_coalesce_freed_chunks() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let first = allocate(&mut heap, &mut mem, 24);
+		let second = allocate(&mut heap, &mut mem, 24);
+		let third = allocate(&mut heap, &mut mem, 24);
+		let _guard = allocate(&mut heap, &mut mem, 8);
+
+		deallocate(&mut heap, &mut mem, first);
+		deallocate(&mut heap, &mut mem, third);
+		deallocate(&mut heap, &mut mem, second);
+
+		// The three chunks were merged into one.
+		let merged = allocate(&mut heap, &mut mem, 3 * (24 + HEADER_SIZE) - HEADER_SIZE);
+		assert_eq!(merged, first);
+	}
+
+	#[test]
+	fn should_give_back_chunks_ending_at_the_bumper() {
+		let mut mem = MemoryInstance::with_pages(1);
+		mem.set_max_wasm_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		// Fill the heap with small allocations and free them.
+		let ptrs: Vec<_> = (0..1000).map(|_| allocate(&mut heap, &mut mem, 24)).collect();
+		for ptr in ptrs.iter().rev().step_by(2).chain(ptrs.iter().rev().skip(1).step_by(2)) {
+			deallocate(&mut heap, &mut mem, *ptr);
+		}
+		assert_eq!(heap.stats().bytes_allocated, 0);
+
+		// The whole heap can be used by an allocation of another size.
+		let ptr = allocate(&mut heap, &mut mem, PAGE_SIZE - HEADER_SIZE);
+		assert_eq!(ptr, HEADER_SIZE);
+		assert_eq!(heap.stats().address_space_used, PAGE_SIZE);
+	}
+
+	#[test]
+	fn should_not_allocate_if_too_large() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		assert_eq!(
+			heap.allocate(&mut mem, MAX_POSSIBLE_ALLOCATION + 1).unwrap_err(),
+			Error::RequestedAllocationTooLarge,
+		);
+		// The allocator is poisoned.
+		assert!(heap.allocate(&mut mem, 8).is_err());
+	}
+
+	#[test]
+	fn should_not_allocate_if_full() {
+		let mut mem = MemoryInstance::with_pages(1);
+		mem.set_max_wasm_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		allocate(&mut heap, &mut mem, PAGE_SIZE - HEADER_SIZE);
+		assert_eq!(heap.allocate(&mut mem, 1).unwrap_err(), Error::AllocatorOutOfSpace);
+	}
+
+	#[test]
+	fn should_allocate_max_possible_allocation_size() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let ptr = allocate(&mut heap, &mut mem, MAX_POSSIBLE_ALLOCATION);
+		assert_eq!(ptr, HEADER_SIZE);
+	}
+
+	#[test]
+	fn should_reject_double_free() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let ptr = allocate(&mut heap, &mut mem, 8);
+		let _guard = allocate(&mut heap, &mut mem, 8);
+		deallocate(&mut heap, &mut mem, ptr);
+		assert!(heap.deallocate(&mut mem, Pointer::new(ptr)).is_err());
+	}
+
+	#[test]
+	fn should_not_loop_on_a_cyclic_free_list() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let ptr = allocate(&mut heap, &mut mem, 24);
+		let _guard = allocate(&mut heap, &mut mem, 8);
+		deallocate(&mut heap, &mut mem, ptr);
+
+		// Make the free chunk point to itself.
+		let header_ptr = ptr - HEADER_SIZE;
+		Links { next: header_ptr, prev: NIL }.write_into(&mut mem, header_ptr).unwrap();
+
+		// The free chunk is too small, so a new chunk is allocated.
+		assert_ne!(allocate(&mut heap, &mut mem, 40), ptr);
+		// Taking the free chunk out of the list fails.
+		assert!(heap.allocate(&mut mem, 24).is_err());
+	}
+
+	#[test]
+	fn should_track_stats() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SizeClassHeapAllocator::new(0);
+
+		let first = allocate(&mut heap, &mut mem, 100);
+		let second = allocate(&mut heap, &mut mem, 20);
+		deallocate(&mut heap, &mut mem, first);
+		deallocate(&mut heap, &mut mem, second);
+
+		let stats = heap.stats();
+		assert_eq!(stats.bytes_allocated, 0);
+		assert_eq!(stats.bytes_allocated_peak, 104 + 24 + 2 * HEADER_SIZE);
+		assert_eq!(stats.bytes_allocated_sum, u128::from(stats.bytes_allocated_peak));
+		assert_eq!(stats.address_space_used, stats.bytes_allocated_peak);
+	}
+}
@@ -36,7 +36,9 @@ use sp_storage::{ChildInfo, StorageData, StorageKey};
 
 use crate::{blockchain::Backend as BlockchainBackend, UsageInfo};
 
-pub use sp_state_machine::{Backend as StateBackend, KeyValueStates};
+pub use sp_state_machine::{
+	Backend as StateBackend, KeyValueStates, MerkleValue, TrieDiff, TrieDiffKind, TrieDiffValue,
+};
 
 /// Extracts the state backend type for the given backend.
 pub type StateBackendFor<B, Block> = <B as Backend<Block>>::State;
@@ -476,6 +478,39 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		child_info: &ChildInfo,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<Option<Block::Hash>>;
+
+	/// Given a block's `Hash` and a key, return the merkle value of the closest descendant node
+	/// of the key in that block.
+	fn closest_merkle_value(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>>;
+
+	/// Given a block's `Hash`, a key and a child storage key, return the merkle value of the
+	/// closest descendant node of the key in that block.
+	fn child_closest_merkle_value(
+		&self,
+		hash: Block::Hash,
+		child_info: &ChildInfo,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>>;
+
+	/// Compute the storage differences between the state of `previous_hash` and the state of
+	/// `hash`, for the keys starting with `prefix` in the main trie or in the given child trie.
+	///
+	/// `on_diff` is called for every difference found and can return `false` to stop the
+	/// iteration. The values are only provided when `fetch_values` is true, otherwise the
+	/// hashes of the values are reported.
+	fn storage_diff(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Block::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&StorageKey>,
+		fetch_values: bool,
+		on_diff: &mut dyn FnMut(TrieDiff<Block::Hash>) -> bool,
+	) -> sp_blockchain::Result<()>;
 }
 
 /// Client backend.
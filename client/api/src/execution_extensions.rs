@@ -284,7 +284,8 @@ impl<Block: BlockT> ExecutionExtensions<Block> {
 			ExecutionContext::Importing => self.strategies.importing.get_manager(),
 			ExecutionContext::OffchainCall(Some((_, capabilities))) if capabilities.is_all() =>
 				self.strategies.offchain_worker.get_manager(),
-			ExecutionContext::OffchainCall(_) => self.strategies.other.get_manager(),
+			ExecutionContext::OffchainCall(_) | ExecutionContext::UntrustedCall =>
+				self.strategies.other.get_manager(),
 		};
 
 		(manager, self.extensions(block_hash, block_number, context))
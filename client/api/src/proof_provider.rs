@@ -40,6 +40,21 @@ pub trait ProofProvider<Block: BlockT> {
 		keys: &mut dyn Iterator<Item = &[u8]>,
 	) -> sp_blockchain::Result<StorageProof>;
 
+	/// Reads the storage values of the top trie, or of a child trie, at a given block in key
+	/// order, returning read proof.
+	///
+	/// Only keys starting with `prefix` are read, starting at `start_at` inclusively. The proof
+	/// is built until `size_limit` is reached and always includes at least one key.
+	/// Returns the proof and the number of keys it covers.
+	fn read_range_proof(
+		&self,
+		hash: Block::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_at: Option<&[u8]>,
+		size_limit: usize,
+	) -> sp_blockchain::Result<(StorageProof, u32)>;
+
 	/// Execute a call to a contract on top of state in a block of given hash
 	/// AND returning execution proof.
 	///
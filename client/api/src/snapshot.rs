@@ -0,0 +1,90 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Snapshots of finalized state.
+//!
+//! A snapshot splits the state of a block into chunks, each of them a compact proof of the keys
+//! following the point where the previous chunk stopped. Chunks are addressed by the hash of
+//! their encoding and listed, along with the key cursor they start after, in a manifest. As every
+//! chunk is a range proof against the state root of the block, chunks can be downloaded from
+//! different peers and verified independently of each other.
+
+use codec::{Decode, Encode};
+use sp_runtime::traits::Block as BlockT;
+
+/// Size limit of the proofs making up snapshot chunks. A chunk always contains at least one key,
+/// so it may be bigger.
+pub const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+
+/// Default number of blocks between two snapshots.
+///
+/// Snapshots are only taken of the finalized blocks whose number is a multiple of the period, so
+/// that the nodes serving them and the nodes downloading them agree on which blocks they are of.
+pub const SNAPSHOT_PERIOD: u32 = 4096;
+
+/// Hash of the encoding of a snapshot chunk.
+pub type ChunkHash = [u8; 32];
+
+/// List of the chunks of a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SnapshotManifest<Hash> {
+	/// Block the snapshot is of.
+	pub block: Hash,
+	/// State root of the block.
+	pub state_root: Hash,
+	/// Chunks, in key order.
+	pub chunks: Vec<ChunkInfo>,
+}
+
+/// Entry of a [`SnapshotManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ChunkInfo {
+	/// Hash of the chunk.
+	pub hash: ChunkHash,
+	/// Key cursor the chunk starts after, empty for the first chunk.
+	pub start: Vec<Vec<u8>>,
+}
+
+impl<Hash: PartialEq> SnapshotManifest<Hash> {
+	/// Check that the manifest is well-formed and describes the state of `block`, with the given
+	/// state root.
+	///
+	/// The chunks themselves are verified against the state root as they are downloaded.
+	pub fn is_valid_for(&self, block: &Hash, state_root: &Hash) -> bool {
+		&self.block == block &&
+			&self.state_root == state_root &&
+			self.chunks.first().map_or(false, |chunk| chunk.start.is_empty()) &&
+			self.chunks.windows(2).all(|pair| pair[0].start < pair[1].start)
+	}
+}
+
+/// Provides the snapshots that were taken of finalized state.
+pub trait SnapshotProvider<Block: BlockT>: Send + Sync {
+	/// Returns the manifest of the snapshot of `block`, if one was taken.
+	fn snapshot_manifest(
+		&self,
+		block: Block::Hash,
+	) -> sp_blockchain::Result<Option<SnapshotManifest<Block::Hash>>>;
+
+	/// Returns the chunk of the snapshot of `block` with the given hash, if there is one.
+	fn snapshot_chunk(
+		&self,
+		block: Block::Hash,
+		hash: ChunkHash,
+	) -> sp_blockchain::Result<Option<Vec<u8>>>;
+}
@@ -19,13 +19,16 @@
 //! Substrate chain configurations.
 #![warn(missing_docs)]
 
-use crate::{extension::GetExtension, ChainType, Properties, RuntimeGenesis};
+use crate::{
+	extension::GetExtension, genesis_config_builder::GenesisConfigBuilderRuntimeCaller, ChainType,
+	Properties, RuntimeGenesis,
+};
 use sc_network::config::MultiaddrWithPeerId;
 use sc_telemetry::TelemetryEndpoints;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 use sp_core::{
-	storage::{ChildInfo, Storage, StorageChild, StorageData, StorageKey},
+	storage::{well_known_keys, ChildInfo, Storage, StorageChild, StorageData, StorageKey},
 	Bytes,
 };
 use sp_runtime::BuildStorage;
@@ -36,6 +39,7 @@ enum GenesisSource<G> {
 	Binary(Cow<'static, [u8]>),
 	Factory(Arc<dyn Fn() -> G + Send + Sync>),
 	Storage(Storage),
+	RuntimeGenesis(RuntimeCodeGenesis),
 }
 
 impl<G> Clone for GenesisSource<G> {
@@ -45,6 +49,7 @@ impl<G> Clone for GenesisSource<G> {
 			Self::Binary(ref d) => Self::Binary(d.clone()),
 			Self::Factory(ref f) => Self::Factory(f.clone()),
 			Self::Storage(ref s) => Self::Storage(s.clone()),
+			Self::RuntimeGenesis(ref r) => Self::RuntimeGenesis(r.clone()),
 		}
 	}
 }
@@ -80,30 +85,9 @@ impl<G: RuntimeGenesis> GenesisSource<G> {
 				Ok(genesis.genesis)
 			},
 			Self::Factory(f) => Ok(Genesis::Runtime(f())),
-			Self::Storage(storage) => {
-				let top = storage
-					.top
-					.iter()
-					.map(|(k, v)| (StorageKey(k.clone()), StorageData(v.clone())))
-					.collect();
-
-				let children_default = storage
-					.children_default
-					.iter()
-					.map(|(k, child)| {
-						(
-							StorageKey(k.clone()),
-							child
-								.data
-								.iter()
-								.map(|(k, v)| (StorageKey(k.clone()), StorageData(v.clone())))
-								.collect(),
-						)
-					})
-					.collect();
-
-				Ok(Genesis::Raw(RawGenesis { top, children_default }))
-			},
+			Self::Storage(storage) => Ok(Genesis::Raw(storage.clone().into())),
+			Self::RuntimeGenesis(runtime_genesis) =>
+				Ok(Genesis::RuntimeGenesis(runtime_genesis.clone())),
 		}
 	}
 }
@@ -112,6 +96,7 @@ impl<G: RuntimeGenesis, E> BuildStorage for ChainSpec<G, E> {
 	fn assimilate_storage(&self, storage: &mut Storage) -> Result<(), String> {
 		match self.genesis.resolve()? {
 			Genesis::Runtime(gc) => gc.assimilate_storage(storage),
+			Genesis::RuntimeGenesis(runtime_genesis) => runtime_genesis.assimilate_storage(storage),
 			Genesis::Raw(RawGenesis { top: map, children_default: children_map }) => {
 				storage.top.extend(map.into_iter().map(|(k, v)| (k.0, v.0)));
 				children_map.into_iter().for_each(|(k, v)| {
@@ -144,12 +129,65 @@ pub struct RawGenesis {
 	pub children_default: BTreeMap<StorageKey, GenesisStorage>,
 }
 
+impl From<Storage> for RawGenesis {
+	fn from(storage: Storage) -> Self {
+		let top = storage.top.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect();
+		let children_default = storage
+			.children_default
+			.into_iter()
+			.map(|(sk, child)| {
+				(
+					StorageKey(sk),
+					child.data.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect(),
+				)
+			})
+			.collect();
+
+		RawGenesis { top, children_default }
+	}
+}
+
+/// Genesis built by the runtime code itself, see [`sp_genesis_builder::GenesisBuilder`].
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+struct RuntimeCodeGenesis {
+	/// The runtime code, which is also put into the genesis storage.
+	code: Bytes,
+	/// Applied to the default `GenesisConfig` of the runtime to get the genesis `GenesisConfig`.
+	patch: json::Value,
+}
+
+impl BuildStorage for RuntimeCodeGenesis {
+	fn assimilate_storage(&self, storage: &mut Storage) -> Result<(), String> {
+		let mut genesis = GenesisConfigBuilderRuntimeCaller::new(&self.code[..])
+			.get_storage_for_patch(self.patch.clone())?;
+		genesis.top.insert(well_known_keys::CODE.to_vec(), self.code.to_vec());
+
+		storage.top.extend(genesis.top);
+		for (key, child) in genesis.children_default {
+			storage
+				.children_default
+				.entry(key)
+				.or_insert_with(|| StorageChild {
+					data: Default::default(),
+					child_info: child.child_info,
+				})
+				.data
+				.extend(child.data);
+		}
+		Ok(())
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
 enum Genesis<G> {
 	Runtime(G),
 	Raw(RawGenesis),
+	/// The runtime code and a patch of its default `GenesisConfig`.
+	RuntimeGenesis(RuntimeCodeGenesis),
 	/// State root hash of the genesis storage.
 	StateRootHash(StorageData),
 }
@@ -290,6 +328,42 @@ impl<G, E> ChainSpec<G, E> {
 		ChainSpec { client_spec, genesis: GenesisSource::Factory(Arc::new(constructor)) }
 	}
 
+	/// Create a spec whose genesis is built by the runtime `code` itself.
+	///
+	/// The genesis `GenesisConfig` is the default one of the runtime with `patch` applied as a
+	/// JSON merge patch. The runtime must implement [`sp_genesis_builder::GenesisBuilder`].
+	pub fn from_runtime_code(
+		name: &str,
+		id: &str,
+		chain_type: ChainType,
+		code: &[u8],
+		patch: json::Value,
+		boot_nodes: Vec<MultiaddrWithPeerId>,
+		telemetry_endpoints: Option<TelemetryEndpoints>,
+		protocol_id: Option<&str>,
+		fork_id: Option<&str>,
+		properties: Option<Properties>,
+		extensions: E,
+	) -> Self {
+		let client_spec = ClientSpec {
+			name: name.to_owned(),
+			id: id.to_owned(),
+			chain_type,
+			boot_nodes,
+			telemetry_endpoints,
+			protocol_id: protocol_id.map(str::to_owned),
+			fork_id: fork_id.map(str::to_owned),
+			properties,
+			extensions,
+			consensus_engine: (),
+			genesis: Default::default(),
+			code_substitutes: BTreeMap::new(),
+		};
+		let genesis = RuntimeCodeGenesis { code: code.to_vec().into(), patch };
+
+		ChainSpec { client_spec, genesis: GenesisSource::RuntimeGenesis(genesis) }
+	}
+
 	/// Type of the chain.
 	fn chain_type(&self) -> ChainType {
 		self.client_spec.chain_type.clone()
@@ -335,27 +409,8 @@ struct JsonContainer<G, E> {
 impl<G: RuntimeGenesis, E: serde::Serialize + Clone + 'static> ChainSpec<G, E> {
 	fn json_container(&self, raw: bool) -> Result<JsonContainer<G, E>, String> {
 		let genesis = match (raw, self.genesis.resolve()?) {
-			(true, Genesis::Runtime(g)) => {
-				let storage = g.build_storage()?;
-				let top =
-					storage.top.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect();
-				let children_default = storage
-					.children_default
-					.into_iter()
-					.map(|(sk, child)| {
-						(
-							StorageKey(sk),
-							child
-								.data
-								.into_iter()
-								.map(|(k, v)| (StorageKey(k), StorageData(v)))
-								.collect(),
-						)
-					})
-					.collect();
-
-				Genesis::Raw(RawGenesis { top, children_default })
-			},
+			(true, Genesis::Runtime(g)) => Genesis::Raw(g.build_storage()?.into()),
+			(true, Genesis::RuntimeGenesis(g)) => Genesis::Raw(g.build_storage()?.into()),
 			(_, genesis) => genesis,
 		};
 		Ok(JsonContainer { client_spec: self.client_spec.clone(), genesis })
@@ -536,4 +591,38 @@ mod tests {
 			);
 		}
 	}
+
+	#[test]
+	fn runtime_genesis_round_trips() {
+		let spec = TestSpec::from_runtime_code(
+			"Test",
+			"test",
+			ChainType::Local,
+			&[1, 2, 3],
+			json::json!({ "balances": { "balances": [] } }),
+			Vec::new(),
+			None,
+			None,
+			None,
+			None,
+			None,
+		);
+
+		let json = spec.as_json(false).unwrap();
+		let value: json::Value = json::from_str(&json).unwrap();
+		assert_eq!(
+			value["genesis"],
+			json::json!({
+				"runtimeGenesis": { "code": "0x010203", "patch": { "balances": { "balances": [] } } }
+			})
+		);
+
+		assert_eq!(
+			json,
+			TestSpec::from_json_bytes(json.as_bytes().to_vec())
+				.unwrap()
+				.as_json(false)
+				.unwrap()
+		);
+	}
 }
@@ -0,0 +1,137 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Calling the [`GenesisBuilder`](sp_genesis_builder::GenesisBuilder) runtime API of arbitrary
+//! runtime code.
+
+use codec::{Decode, Encode};
+use sc_executor::WasmExecutor;
+use serde_json::Value;
+use sp_core::{
+	storage::Storage,
+	traits::{CallContext, CodeExecutor, Externalities, FetchRuntimeCode, RuntimeCode},
+};
+use sp_state_machine::BasicExternalities;
+use std::borrow::Cow;
+
+/// Builds the genesis storage of a runtime, given only its code.
+///
+/// The runtime must implement [`GenesisBuilder`](sp_genesis_builder::GenesisBuilder).
+pub struct GenesisConfigBuilderRuntimeCaller<'a> {
+	code: Cow<'a, [u8]>,
+	code_hash: Vec<u8>,
+	executor: WasmExecutor<sp_io::SubstrateHostFunctions>,
+}
+
+impl<'a> FetchRuntimeCode for GenesisConfigBuilderRuntimeCaller<'a> {
+	fn fetch_runtime_code(&self) -> Option<Cow<[u8]>> {
+		Some(self.code.as_ref().into())
+	}
+}
+
+impl<'a> GenesisConfigBuilderRuntimeCaller<'a> {
+	/// Creates a new instance for the given runtime `code`.
+	pub fn new(code: &'a [u8]) -> Self {
+		GenesisConfigBuilderRuntimeCaller {
+			code: code.into(),
+			code_hash: sp_core::blake2_256(code).to_vec(),
+			executor: WasmExecutor::<sp_io::SubstrateHostFunctions>::builder()
+				.with_allow_missing_host_functions(true)
+				.build(),
+		}
+	}
+
+	fn call(
+		&self,
+		ext: &mut dyn Externalities,
+		method: &str,
+		data: &[u8],
+	) -> Result<Vec<u8>, String> {
+		self.executor
+			.call(
+				ext,
+				&RuntimeCode { heap_pages: None, code_fetcher: self, hash: self.code_hash.clone() },
+				method,
+				data,
+				false,
+				CallContext::Offchain,
+			)
+			.0
+			.map_err(|e| format!("Error calling `{}` of the runtime: {}", method, e))
+	}
+
+	/// Returns the default `GenesisConfig` of the runtime.
+	pub fn get_default_config(&self) -> Result<Value, String> {
+		let mut ext = BasicExternalities::new_empty();
+		let call_result = self.call(&mut ext, "GenesisBuilder_create_default_config", &[])?;
+		let default_config = Vec::<u8>::decode(&mut &call_result[..])
+			.map_err(|e| format!("Invalid default GenesisConfig returned by the runtime: {}", e))?;
+		serde_json::from_slice(&default_config)
+			.map_err(|e| format!("Invalid default GenesisConfig returned by the runtime: {}", e))
+	}
+
+	/// Returns the names of the presets provided by the runtime.
+	///
+	/// Requires version 2 of [`GenesisBuilder`](sp_genesis_builder::GenesisBuilder).
+	pub fn preset_names(&self) -> Result<Vec<String>, String> {
+		let mut ext = BasicExternalities::new_empty();
+		let call_result = self.call(&mut ext, "GenesisBuilder_preset_names", &[])?;
+		Vec::<sp_genesis_builder::PresetId>::decode(&mut &call_result[..])
+			.map_err(|e| format!("Invalid preset names returned by the runtime: {}", e))?
+			.into_iter()
+			.map(|name| {
+				String::from_utf8(name)
+					.map_err(|e| format!("Invalid preset name returned by the runtime: {}", e))
+			})
+			.collect()
+	}
+
+	/// Returns the patch of the default `GenesisConfig` that the runtime provides as preset
+	/// `name`.
+	///
+	/// Requires version 2 of [`GenesisBuilder`](sp_genesis_builder::GenesisBuilder).
+	pub fn get_named_preset(&self, name: &str) -> Result<Value, String> {
+		let mut ext = BasicExternalities::new_empty();
+		let call_result =
+			self.call(&mut ext, "GenesisBuilder_get_preset", &name.as_bytes().to_vec().encode())?;
+		let preset = Option::<Vec<u8>>::decode(&mut &call_result[..])
+			.map_err(|e| format!("Invalid preset returned by the runtime: {}", e))?
+			.ok_or_else(|| format!("The runtime has no preset named `{}`", name))?;
+		serde_json::from_slice(&preset)
+			.map_err(|e| format!("Invalid preset `{}` returned by the runtime: {}", name, e))
+	}
+
+	/// Builds the genesis storage from the full `GenesisConfig` of the runtime.
+	pub fn get_storage_for_config(&self, config: Value) -> Result<Storage, String> {
+		let mut ext = BasicExternalities::new_empty();
+		let call_result =
+			self.call(&mut ext, "GenesisBuilder_build_config", &config.to_string().encode())?;
+		sp_genesis_builder::Result::decode(&mut &call_result[..])
+			.map_err(|e| format!("Invalid result returned by the runtime: {}", e))?
+			.map_err(|e| format!("Error building the GenesisConfig: {}", String::from(e)))?;
+		Ok(ext.into_storages())
+	}
+
+	/// Builds the genesis storage from the default `GenesisConfig` of the runtime, with `patch`
+	/// applied on top of it.
+	pub fn get_storage_for_patch(&self, patch: Value) -> Result<Storage, String> {
+		let mut config = self.get_default_config()?;
+		crate::json_patch::merge(&mut config, patch);
+		self.get_storage_for_config(config)
+	}
+}
@@ -0,0 +1,87 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! JSON merge patch, see [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396).
+
+use serde_json::Value;
+
+/// Recursively merges `patch` into `doc`.
+///
+/// Objects are merged key by key, a `null` in `patch` removes the key from `doc`. Any other value
+/// replaces the one in `doc`, including arrays.
+pub fn merge(doc: &mut Value, patch: Value) {
+	let Value::Object(patch) = patch else {
+		*doc = patch;
+		return
+	};
+
+	if !doc.is_object() {
+		*doc = Value::Object(Default::default());
+	}
+	let doc = doc.as_object_mut().expect("`doc` was just made an object; qed");
+	for (key, value) in patch {
+		if value.is_null() {
+			doc.remove(&key);
+		} else {
+			merge(doc.entry(key).or_insert(Value::Null), value);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn merge_objects_works() {
+		let mut doc = json!({
+			"system": { "code": "0x00" },
+			"balances": { "balances": [["a", 1]] },
+			"sudo": { "key": "a" },
+		});
+		merge(
+			&mut doc,
+			json!({
+				"balances": { "balances": [["b", 2]] },
+				"sudo": null,
+				"babe": { "epochConfig": { "c": [1, 4] } },
+			}),
+		);
+
+		assert_eq!(
+			doc,
+			json!({
+				"system": { "code": "0x00" },
+				"balances": { "balances": [["b", 2]] },
+				"babe": { "epochConfig": { "c": [1, 4] } },
+			})
+		);
+	}
+
+	#[test]
+	fn merge_non_objects_replaces() {
+		let mut doc = json!({ "a": 1 });
+		merge(&mut doc, json!([1, 2]));
+		assert_eq!(doc, json!([1, 2]));
+
+		let mut doc = json!(1);
+		merge(&mut doc, json!({ "a": { "b": null, "c": 2 } }));
+		assert_eq!(doc, json!({ "a": { "c": 2 } }));
+	}
+}
@@ -148,13 +148,17 @@
 //! "consensusEngine": null,
 //! // The genesis declaration of the chain.
 //! //
-//! // `runtime`, `raw`, `stateRootHash` denote the type of the genesis declaration.
+//! // `runtime`, `raw`, `runtimeGenesis`, `stateRootHash` denote the type of the genesis
+//! // declaration.
 //! //
 //! // These declarations are in the following formats:
 //! // - `runtime` is a `json` object that can be parsed by a compatible `GenesisConfig`. This
 //! //  `GenesisConfig` is declared by a runtime and opaque to the node.
 //! // - `raw` is a `json` object with two fields `top` and `children_default`. Each of these
 //! //   fields is a map of `key => value`. These key/value pairs represent the genesis storage.
+//! // - `runtimeGenesis` is a `json` object with two fields `code` and `patch`. `code` is the hex
+//! //   encoded runtime code, which builds the genesis storage from its default `GenesisConfig`
+//! //   with the JSON merge patch `patch` applied, see `sp_genesis_builder::GenesisBuilder`.
 //! // - `stateRootHash` is a single hex encoded hash that represents the genesis hash. The hash
 //! //   type depends on the hash used by the chain.
 //! //
@@ -178,6 +182,8 @@
 mod chain_spec;
 mod extension;
 mod genesis;
+mod genesis_config_builder;
+mod json_patch;
 
 pub use self::{
 	chain_spec::{ChainSpec as GenericChainSpec, NoExtension},
@@ -186,6 +192,8 @@ pub use self::{
 		construct_genesis_block, resolve_state_version_from_wasm, BuildGenesisBlock,
 		GenesisBlockBuilder,
 	},
+	genesis_config_builder::GenesisConfigBuilderRuntimeCaller,
+	json_patch::merge as json_merge,
 };
 pub use sc_chain_spec_derive::{ChainSpecExtension, ChainSpecGroup};
 
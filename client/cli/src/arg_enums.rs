@@ -264,6 +264,18 @@ impl Into<sc_network::config::SyncMode> for SyncMode {
 	}
 }
 
+/// What to download for the blocks skipped by warp sync.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq)]
+#[value(rename_all = "kebab-case")]
+pub enum GapSync {
+	/// Download headers only.
+	Headers,
+	/// Download headers and justifications.
+	HeadersAndJustifications,
+	/// Download headers, justifications and block bodies.
+	Full,
+}
+
 /// Default value for the `--execution-syncing` parameter.
 pub const DEFAULT_EXECUTION_SYNCING: ExecutionStrategy = ExecutionStrategy::Wasm;
 /// Default value for the `--execution-import-block` parameter.
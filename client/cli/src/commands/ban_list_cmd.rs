@@ -0,0 +1,102 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `ban-list` subcommand
+
+use crate::{config::DEFAULT_NETWORK_CONFIG_PATH, Error, SharedParams, SubstrateCli};
+use clap::{Parser, Subcommand};
+use sc_network::{BanList, BanTarget};
+use sc_service::config::BasePath;
+use std::time::{Duration, SystemTime};
+
+/// The `ban-list` command used to edit the peers and IP addresses banned by a node.
+///
+/// The ban list is read when the node starts, use the `system_banPeer` and `system_unbanPeer`
+/// RPCs to change the bans of a running node.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "ban-list", about = "Inspect and edit the peers banned by a node.")]
+pub struct BanListCmd {
+	#[allow(missing_docs)]
+	#[command(subcommand)]
+	pub action: BanListAction,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+}
+
+/// Actions of the `ban-list` command.
+#[derive(Debug, Clone, Subcommand)]
+pub enum BanListAction {
+	/// Print the bans currently in force.
+	List,
+
+	/// Ban a peer or an IP address.
+	Ban {
+		/// Base58-encoded peer ID or IP address to ban.
+		target: BanTarget,
+
+		/// Duration of the ban, in seconds.
+		#[arg(long, default_value_t = 24 * 60 * 60)]
+		duration: u64,
+	},
+
+	/// Lift the ban of a peer or an IP address.
+	Unban {
+		/// Base58-encoded peer ID or IP address to unban.
+		target: BanTarget,
+	},
+}
+
+impl BanListCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let base_path = self
+			.shared_params
+			.base_path()?
+			.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
+		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
+		let chain_spec = cli.load_spec(&chain_id)?;
+		let net_config_dir =
+			base_path.config_dir(chain_spec.id()).join(DEFAULT_NETWORK_CONFIG_PATH);
+
+		let mut ban_list = BanList::load(&net_config_dir)?;
+		match &self.action {
+			BanListAction::List => {
+				let now = SystemTime::now();
+				for ban in ban_list.bans() {
+					let remaining = ban.expires_at.duration_since(now).unwrap_or_default();
+					println!("{}\t{}s", ban.target, remaining.as_secs());
+				}
+			},
+			BanListAction::Ban { target, duration } => {
+				ban_list.ban(*target, Duration::from_secs(*duration));
+				ban_list.save()?;
+				println!("Banned {} for {}s", target, duration);
+			},
+			BanListAction::Unban { target } =>
+				if ban_list.unban(target) {
+					ban_list.save()?;
+					println!("Unbanned {}", target);
+				} else {
+					println!("{} is not banned", target);
+				},
+		}
+
+		Ok(())
+	}
+}
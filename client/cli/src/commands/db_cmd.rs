@@ -0,0 +1,198 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::{
+	CliConfiguration, DatabaseParams, Error, PruningParams, Result as CliResult, SharedParams,
+};
+use clap::{Parser, Subcommand};
+use parity_scale_codec::Decode;
+use sc_client_db::inspect::DbInspector;
+use sp_core::hexdisplay::HexDisplay;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+/// The `db` command used to inspect the database of a node that isn't running.
+///
+/// The database is opened as is, without being upgraded. Only `orphans --repair` writes to it.
+#[derive(Debug, Clone, Parser)]
+#[command(name = "db", about = "Inspect the database of a stopped node.")]
+pub struct DbCmd {
+	#[allow(missing_docs)]
+	#[command(subcommand)]
+	pub action: DbAction,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub pruning_params: PruningParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub database_params: DatabaseParams,
+}
+
+/// Actions of the `db` command.
+#[derive(Debug, Clone, Subcommand)]
+pub enum DbAction {
+	/// Print the number and size of the entries of every column.
+	Columns,
+
+	/// Print the best and finalized blocks and the leaves of the block tree.
+	Leaves,
+
+	/// Print the journals of the state database.
+	///
+	/// Pinned blocks are only known to a running node and are not printed.
+	StateJournal,
+
+	/// Check that every trie node of a state is present and matches its hash.
+	VerifyState {
+		/// Hex-encoded state root, defaults to the state root of the best block.
+		#[arg(long)]
+		root: Option<String>,
+	},
+
+	/// Find the trie nodes that no state kept by the database references.
+	Orphans {
+		/// Remove the orphaned nodes. Not supported by ParityDb.
+		#[arg(long)]
+		repair: bool,
+	},
+}
+
+impl DbCmd {
+	/// Run the `db` command
+	pub fn run<B>(&self, config: &sc_service::Configuration) -> CliResult<()>
+	where
+		B: BlockT,
+	{
+		let writable = matches!(self.action, DbAction::Orphans { repair: true });
+		let inspector = DbInspector::<B>::open(&config.database, writable)?;
+
+		match &self.action {
+			DbAction::Columns =>
+				for column in inspector.columns()? {
+					println!(
+						"{}\t{}\t{} entries\t{} key bytes\t{} value bytes",
+						column.id,
+						column.name,
+						column.entries,
+						column.key_bytes,
+						column.value_bytes,
+					);
+				},
+			DbAction::Leaves => {
+				let leaves = inspector.leaves()?;
+				println!("best\t#{}\t{:?}", leaves.best.1, leaves.best.0);
+				println!("finalized\t#{}\t{:?}", leaves.finalized.1, leaves.finalized.0);
+				for leaf in leaves.leaves {
+					let number = leaf.number.map_or_else(|| "?".into(), |n| n.to_string());
+					let kind = if leaf.canonical { "canonical" } else { "non-canonical" };
+					println!("leaf\t#{}\t{:?}\t{}", number, leaf.hash, kind);
+				}
+			},
+			DbAction::StateJournal => {
+				let journals = inspector.journals()?;
+				println!("pruning mode\t{:?}", journals.pruning_mode);
+				println!("last canonicalized\t{:?}", journals.last_canonicalized);
+				println!("last pruned\t{:?}", journals.last_pruned);
+				for record in journals.non_canonical {
+					println!(
+						"non-canonical\t#{}.{}\t{:?}\tparent {:?}\t{} inserted\t{} deleted",
+						record.number,
+						record.index,
+						record.hash,
+						record.parent_hash,
+						record.inserted.len(),
+						record.deleted.len(),
+					);
+				}
+				for record in journals.pruning {
+					println!(
+						"pruning\t#{}\t{:?}\t{} inserted\t{} deleted",
+						record.number,
+						record.hash,
+						record.inserted.len(),
+						record.deleted.len(),
+					);
+				}
+			},
+			DbAction::VerifyState { root } => {
+				let root = match root {
+					Some(root) => {
+						let bytes =
+							array_bytes::hex2bytes(root).map_err(Error::HexDataConversion)?;
+						B::Hash::decode(&mut &bytes[..])?
+					},
+					None => {
+						let best = inspector.leaves()?.best.0;
+						*inspector
+							.header(best)?
+							.ok_or_else(|| format!("Missing header of best block {:?}", best))?
+							.state_root()
+					},
+				};
+				let report = inspector.verify_state(root)?;
+				println!(
+					"{} keys\t{} child tries\t{} nodes",
+					report.keys, report.child_tries, report.nodes
+				);
+				if let Some(error) = report.error {
+					return Err(format!("State {:?} is corrupted: {}", root, error).into())
+				}
+				println!("State {:?} is complete", root);
+			},
+			DbAction::Orphans { repair } => {
+				let report = inspector.find_orphans()?;
+				println!(
+					"{} states\t{} referenced nodes\t{} orphaned nodes\t{} orphaned bytes",
+					report.states,
+					report.referenced,
+					report.orphans.len(),
+					report.orphan_bytes,
+				);
+				if *repair {
+					inspector.remove_orphans(&report.orphans)?;
+					println!("Removed {} orphaned nodes", report.orphans.len());
+				} else {
+					for key in &report.orphans {
+						println!("{}", HexDisplay::from(key));
+					}
+				}
+			},
+		}
+
+		Ok(())
+	}
+}
+
+impl CliConfiguration for DbCmd {
+	fn shared_params(&self) -> &SharedParams {
+		&self.shared_params
+	}
+
+	fn pruning_params(&self) -> Option<&PruningParams> {
+		Some(&self.pruning_params)
+	}
+
+	fn database_params(&self) -> Option<&DatabaseParams> {
+		Some(&self.database_params)
+	}
+}
@@ -0,0 +1,65 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the `encrypt-keystore` subcommand
+
+use crate::{Error, KeystoreParams, SharedParams, SubstrateCli};
+use clap::Parser;
+use sc_keystore::LocalKeystore;
+use sc_service::config::{BasePath, KeystoreConfig};
+
+/// The `encrypt-keystore` command
+#[derive(Debug, Clone, Parser)]
+#[command(
+	name = "encrypt-keystore",
+	about = "Encrypt the key files of a node's keystore which are still stored in plaintext."
+)]
+pub struct EncryptKeystoreCmd {
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub shared_params: SharedParams,
+
+	#[allow(missing_docs)]
+	#[clap(flatten)]
+	pub keystore_params: KeystoreParams,
+}
+
+impl EncryptKeystoreCmd {
+	/// Run the command
+	pub fn run<C: SubstrateCli>(&self, cli: &C) -> Result<(), Error> {
+		let base_path = self
+			.shared_params
+			.base_path()?
+			.unwrap_or_else(|| BasePath::from_project("", "", &C::executable_name()));
+		let chain_id = self.shared_params.chain_id(self.shared_params.is_dev());
+		let chain_spec = cli.load_spec(&chain_id)?;
+		let config_dir = base_path.config_dir(chain_spec.id());
+
+		let keystore = match self.keystore_params.keystore_config(&config_dir)? {
+			KeystoreConfig::Path { path, password, passphrase: Some(passphrase) } =>
+				LocalKeystore::open_encrypted(path, password, passphrase)?,
+			KeystoreConfig::Path { passphrase: None, .. } =>
+				return Err(Error::Input("A keystore passphrase is required".into())),
+			_ => return Err(Error::Input("Only local keystores can be encrypted".into())),
+		};
+
+		let encrypted = keystore.encrypt_plaintext_keys()?;
+		println!("Encrypted {} key file(s)", encrypted);
+
+		Ok(())
+	}
+}
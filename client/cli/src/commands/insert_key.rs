@@ -21,7 +21,7 @@ use crate::{
 	utils, with_crypto_scheme, CryptoScheme, Error, KeystoreParams, SharedParams, SubstrateCli,
 };
 use clap::Parser;
-use sc_keystore::LocalKeystore;
+use sc_keystore::{LocalKeystore, RemoteKeystore};
 use sc_service::config::{BasePath, KeystoreConfig};
 use sp_core::crypto::{KeyTypeId, SecretString};
 use sp_keystore::KeystorePtr;
@@ -66,12 +66,22 @@ impl InsertKeyCmd {
 		let config_dir = base_path.config_dir(chain_spec.id());
 
 		let (keystore, public) = match self.keystore_params.keystore_config(&config_dir)? {
-			KeystoreConfig::Path { path, password } => {
+			KeystoreConfig::Path { path, password, passphrase } => {
 				let public = with_crypto_scheme!(self.scheme, to_vec(&suri, password.clone()))?;
-				let keystore: KeystorePtr = LocalKeystore::open(path, password)?.into();
+				let keystore: KeystorePtr = match passphrase {
+					Some(passphrase) =>
+						LocalKeystore::open_encrypted(path, password, passphrase)?.into(),
+					None => LocalKeystore::open(path, password)?.into(),
+				};
 				(keystore, public)
 			},
-			_ => unreachable!("keystore_config always returns path and password; qed"),
+			KeystoreConfig::Remote { uri } => {
+				let public = with_crypto_scheme!(self.scheme, to_vec(&suri, None))?;
+				let keystore: KeystorePtr = RemoteKeystore::open(&uri)?.into();
+				(keystore, public)
+			},
+			KeystoreConfig::InMemory =>
+				unreachable!("keystore_config never returns an in-memory keystore; qed"),
 		};
 
 		let key_type =
@@ -168,4 +178,36 @@ mod tests {
 				.unwrap();
 		assert!(keystore.has_keys(&[(key.public().to_raw_vec(), KeyTypeId(*b"test"))]));
 	}
+
+	#[test]
+	fn insert_into_encrypted_keystore() {
+		let path = TempDir::new().unwrap();
+		let path_str = format!("{}", path.path().display());
+		let (key, uri, _) = Pair::generate_with_phrase(None);
+
+		let inspect = InsertKeyCmd::parse_from(&[
+			"insert-key",
+			"-d",
+			&path_str,
+			"--key-type",
+			"test",
+			"--suri",
+			&uri,
+			"--scheme=sr25519",
+			"--keystore-passphrase",
+			"passphrase",
+		]);
+		assert!(inspect.run(&Cli).is_ok());
+
+		let keystore_path = path.path().join("chains").join("test_id").join("keystore");
+		assert!(LocalKeystore::open(&keystore_path, None).is_err());
+
+		let keystore = LocalKeystore::open_encrypted(
+			&keystore_path,
+			None,
+			SecretString::new("passphrase".into()),
+		)
+		.unwrap();
+		assert!(keystore.has_keys(&[(key.public().to_raw_vec(), KeyTypeId(*b"test"))]));
+	}
 }
@@ -23,7 +23,7 @@ use crate::{
 };
 use clap::Parser;
 use sp_core::crypto::{ExposeSecret, SecretString, SecretUri, Ss58Codec};
-use std::str::FromStr;
+use std::{path::Path, str::FromStr};
 
 /// The `inspect` command
 #[derive(Debug, Parser)]
@@ -74,7 +74,12 @@ pub struct InspectKeyCmd {
 impl InspectKeyCmd {
 	/// Run the command
 	pub fn run(&self) -> Result<(), Error> {
-		let uri = utils::read_uri(self.uri.as_ref())?;
+		let uri = match (&self.uri, self.keystore_params.read_passphrase()?) {
+			// A key file of an encrypted keystore.
+			(Some(file), Some(passphrase)) if Path::new(file).is_file() =>
+				sc_keystore::read_key_file(Path::new(file), Some(&passphrase))?,
+			_ => utils::read_uri(self.uri.as_ref())?,
+		};
 		let password = self.keystore_params.read_password()?;
 
 		if self.public {
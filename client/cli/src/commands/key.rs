@@ -18,8 +18,9 @@
 //! Key related CLI utilities
 
 use super::{
-	generate::GenerateCmd, generate_node_key::GenerateNodeKeyCmd, insert_key::InsertKeyCmd,
-	inspect_key::InspectKeyCmd, inspect_node_key::InspectNodeKeyCmd,
+	encrypt_keystore::EncryptKeystoreCmd, generate::GenerateCmd,
+	generate_node_key::GenerateNodeKeyCmd, insert_key::InsertKeyCmd, inspect_key::InspectKeyCmd,
+	inspect_node_key::InspectNodeKeyCmd,
 };
 use crate::{Error, SubstrateCli};
 
@@ -41,6 +42,9 @@ pub enum KeySubcommand {
 
 	/// Insert a key to the keystore of a node.
 	Insert(InsertKeyCmd),
+
+	/// Encrypt the key files of a node's keystore which are still stored in plaintext.
+	EncryptKeystore(EncryptKeystoreCmd),
 }
 
 impl KeySubcommand {
@@ -52,6 +56,7 @@ impl KeySubcommand {
 			KeySubcommand::Inspect(cmd) => cmd.run(),
 			KeySubcommand::Insert(cmd) => cmd.run(cli),
 			KeySubcommand::InspectNodeKey(cmd) => cmd.run(),
+			KeySubcommand::EncryptKeystore(cmd) => cmd.run(cli),
 		}
 	}
 }
@@ -15,9 +15,12 @@
 
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
+mod ban_list_cmd;
 mod build_spec_cmd;
 mod chain_info_cmd;
 mod check_block_cmd;
+mod db_cmd;
+mod encrypt_keystore;
 mod export_blocks_cmd;
 mod export_state_cmd;
 mod generate;
@@ -37,7 +40,8 @@ mod vanity;
 mod verify;
 
 pub use self::{
-	build_spec_cmd::BuildSpecCmd, chain_info_cmd::ChainInfoCmd, check_block_cmd::CheckBlockCmd,
+	ban_list_cmd::BanListCmd, build_spec_cmd::BuildSpecCmd, chain_info_cmd::ChainInfoCmd,
+	check_block_cmd::CheckBlockCmd, db_cmd::DbCmd, encrypt_keystore::EncryptKeystoreCmd,
 	export_blocks_cmd::ExportBlocksCmd, export_state_cmd::ExportStateCmd, generate::GenerateCmd,
 	generate_node_key::GenerateNodeKeyCmd, import_blocks_cmd::ImportBlocksCmd,
 	insert_key::InsertKeyCmd, inspect_key::InspectKeyCmd, inspect_node_key::InspectNodeKeyCmd,
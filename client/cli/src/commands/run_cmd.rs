@@ -28,7 +28,10 @@ use crate::{
 use clap::Parser;
 use regex::Regex;
 use sc_service::{
-	config::{BasePath, PrometheusConfig, TransactionPoolOptions},
+	config::{
+		BasePath, PrometheusConfig, RpcMethodFilter, RpcRateLimit, RpcRateLimitConfig,
+		TransactionPoolOptions,
+	},
 	ChainSpec, Role,
 };
 use sc_telemetry::TelemetryEndpoints;
@@ -110,6 +113,42 @@ pub struct RunCmd {
 	#[arg(long)]
 	pub rpc_max_subscriptions_per_connection: Option<usize>,
 
+	/// Maximum number of RPC calls per second accepted on a single HTTP or WebSocket connection.
+	/// Calls are weighted by `--rpc-method-cost`. Disabled by default.
+	#[arg(long, value_name = "CALLS", value_parser = clap::value_parser!(u32).range(1..))]
+	pub rpc_rate_limit: Option<u32>,
+
+	/// Maximum number of RPC calls per second accepted from a single remote address, over all
+	/// its connections. Disabled by default.
+	#[arg(long, value_name = "CALLS", value_parser = clap::value_parser!(u32).range(1..))]
+	pub rpc_rate_limit_per_ip: Option<u32>,
+
+	/// Number of calls that can be made at once before the RPC rate limits apply.
+	/// Defaults to one second worth of calls.
+	#[arg(long, value_name = "CALLS", value_parser = clap::value_parser!(u32).range(1..))]
+	pub rpc_rate_limit_burst: Option<u32>,
+
+	/// Reverse proxy trusted to report the client address in the `X-Forwarded-For` or
+	/// `X-Real-IP` header, used by `--rpc-rate-limit-per-ip`. Can be passed multiple times.
+	/// Requests are charged to the address they are received from by default.
+	#[arg(long, value_name = "IP")]
+	pub rpc_rate_limit_trusted_proxy: Vec<IpAddr>,
+
+	/// Cost of a call to an RPC method against the rate limits, as `METHOD=COST`.
+	/// Calls cost 1 by default. Can be passed multiple times.
+	#[arg(long, value_name = "METHOD=COST", value_parser = parse_method_cost)]
+	pub rpc_method_cost: Vec<(String, u32)>,
+
+	/// Only expose the given RPC method. Can be passed multiple times.
+	/// All methods are exposed by default.
+	#[arg(long, value_name = "METHOD")]
+	pub rpc_allow_method: Vec<String>,
+
+	/// Never expose the given RPC method, even if passed to `--rpc-allow-method`.
+	/// Can be passed multiple times.
+	#[arg(long, value_name = "METHOD")]
+	pub rpc_deny_method: Vec<String>,
+
 	/// DEPRECATED, IPC support has been removed.
 	#[arg(long, value_name = "PATH")]
 	pub ipc_path: Option<String>,
@@ -415,6 +454,27 @@ impl CliConfiguration for RunCmd {
 		Ok(self.rpc_max_subscriptions_per_connection)
 	}
 
+	fn rpc_rate_limit(&self) -> Result<RpcRateLimitConfig> {
+		let limit = |per_second| RpcRateLimit {
+			burst: self.rpc_rate_limit_burst.unwrap_or(per_second),
+			per_second,
+		};
+
+		Ok(RpcRateLimitConfig {
+			per_connection: self.rpc_rate_limit.map(limit),
+			per_ip: self.rpc_rate_limit_per_ip.map(limit),
+			method_costs: self.rpc_method_cost.iter().cloned().collect(),
+			trusted_proxies: self.rpc_rate_limit_trusted_proxy.clone(),
+		})
+	}
+
+	fn rpc_method_filter(&self) -> Result<RpcMethodFilter> {
+		Ok(RpcMethodFilter {
+			allow: (!self.rpc_allow_method.is_empty()).then(|| self.rpc_allow_method.clone()),
+			deny: self.rpc_deny_method.clone(),
+		})
+	}
+
 	fn ws_max_out_buffer_capacity(&self) -> Result<Option<usize>> {
 		Ok(self.ws_max_out_buffer_capacity)
 	}
@@ -431,6 +491,10 @@ impl CliConfiguration for RunCmd {
 		Ok(self.runtime_params.runtime_cache_size)
 	}
 
+	fn profile_host_functions(&self) -> Result<bool> {
+		Ok(self.runtime_params.profile_host_functions)
+	}
+
 	fn base_path(&self) -> Result<Option<BasePath>> {
 		Ok(if self.tmp {
 			Some(BasePath::new_temp_dir()?)
@@ -537,6 +601,18 @@ fn parse_cors(s: &str) -> Result<Cors> {
 	}
 }
 
+/// Parse the cost of an RPC method, as `METHOD=COST`.
+fn parse_method_cost(s: &str) -> Result<(String, u32)> {
+	let (method, cost) = s.split_once('=').ok_or_else(|| {
+		Error::Input(format!("Invalid RPC method cost `{}`, expected `METHOD=COST`", s))
+	})?;
+	let cost = cost
+		.parse()
+		.map_err(|e| Error::Input(format!("Invalid cost of RPC method `{}`: {}", method, e)))?;
+
+	Ok((method.to_owned(), cost))
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -28,8 +28,9 @@ use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::{
 	config::{
 		BasePath, Configuration, DatabaseSource, KeystoreConfig, NetworkConfiguration,
-		NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-		TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+		NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethodFilter,
+		RpcMethods, RpcRateLimitConfig, TelemetryEndpoints, TransactionPoolOptions,
+		WasmExecutionMethod,
 	},
 	BlocksPruning, ChainSpec, TracingReceiver,
 };
@@ -238,6 +239,17 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(self.import_params().map(|x| x.trie_cache_maximum_size()).unwrap_or_default())
 	}
 
+	/// Get the maximum size of the trie cache snapshot.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `0`.
+	/// If `None` is returned no snapshot of the trie cache is written or restored.
+	fn trie_cache_snapshot_maximum_size(&self) -> Result<Option<usize>> {
+		Ok(self
+			.import_params()
+			.map(|x| x.trie_cache_snapshot_maximum_size())
+			.unwrap_or_default())
+	}
+
 	/// Get the state pruning mode.
 	///
 	/// By default this is retrieved from `PruningMode` if it is available. Otherwise its
@@ -365,6 +377,20 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(None)
 	}
 
+	/// Get the rate limits applied to RPC requests.
+	///
+	/// By default no limit is applied.
+	fn rpc_rate_limit(&self) -> Result<RpcRateLimitConfig> {
+		Ok(Default::default())
+	}
+
+	/// Get the filter of exposed RPC methods.
+	///
+	/// By default all methods are exposed.
+	fn rpc_method_filter(&self) -> Result<RpcMethodFilter> {
+		Ok(Default::default())
+	}
+
 	/// Get maximum WS output buffer capacity.
 	fn ws_max_out_buffer_capacity(&self) -> Result<Option<usize>> {
 		Ok(None)
@@ -468,6 +494,13 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(2)
 	}
 
+	/// Time every call of the runtime into a host function or not.
+	///
+	/// By default this is `false`.
+	fn profile_host_functions(&self) -> Result<bool> {
+		Ok(false)
+	}
+
 	/// Activate or not the automatic announcing of blocks after import
 	///
 	/// By default this is `false`.
@@ -527,6 +560,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			keystore,
 			database: self.database_config(&config_dir, database_cache_size, database)?,
 			trie_cache_maximum_size: self.trie_cache_maximum_size()?,
+			trie_cache_snapshot_maximum_size: self.trie_cache_snapshot_maximum_size()?,
 			state_pruning: self.state_pruning()?,
 			blocks_pruning: self.blocks_pruning()?,
 			wasm_method: self.wasm_method()?,
@@ -543,6 +577,8 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			rpc_max_response_size: self.rpc_max_response_size()?,
 			rpc_id_provider: None,
 			rpc_max_subs_per_conn: self.rpc_max_subscriptions_per_connection()?,
+			rpc_rate_limit: self.rpc_rate_limit()?,
+			rpc_method_filter: self.rpc_method_filter()?,
 			ws_max_out_buffer_capacity: self.ws_max_out_buffer_capacity()?,
 			prometheus_config: self
 				.prometheus_config(DCV::prometheus_listen_port(), &chain_spec)?,
@@ -561,6 +597,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			base_path: Some(base_path),
 			informant_output_format: Default::default(),
 			runtime_cache_size,
+			profile_host_functions: self.profile_host_functions()?,
 		})
 	}
 
@@ -82,6 +82,12 @@ pub struct ImportParams {
 	#[arg(long, value_name = "Bytes", default_value_t = 67108864)]
 	pub trie_cache_size: usize,
 
+	/// Specify the maximum size of the snapshot of the trie cache.
+	/// The snapshot is written to the database directory on shutdown and restored on startup,
+	/// if the best block didn't change in between. Providing `0` will disable the snapshot.
+	#[arg(long, value_name = "Bytes", default_value_t = 0)]
+	pub trie_cache_snapshot_size: usize,
+
 	/// DEPRECATED
 	/// Switch to `--trie-cache-size`.
 	#[arg(long)]
@@ -102,6 +108,15 @@ impl ImportParams {
 		}
 	}
 
+	/// Specify the maximum size of the trie cache snapshot.
+	pub fn trie_cache_snapshot_maximum_size(&self) -> Option<usize> {
+		if self.trie_cache_snapshot_size == 0 {
+			None
+		} else {
+			Some(self.trie_cache_snapshot_size)
+		}
+	}
+
 	/// Get the WASM execution method from the parameters
 	pub fn wasm_method(&self) -> sc_service::config::WasmExecutionMethod {
 		crate::execution_method_from_cli(self.wasm_method, self.wasmtime_instantiation_strategy)
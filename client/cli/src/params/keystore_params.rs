@@ -31,8 +31,23 @@ const DEFAULT_KEYSTORE_CONFIG_PATH: &str = "keystore";
 /// Parameters of the keystore
 #[derive(Debug, Clone, Args)]
 pub struct KeystoreParams {
-	/// Specify custom URIs to connect to for keystore-services
-	#[arg(long)]
+	/// Use a remote signer holding the keys instead of the local keystore.
+	///
+	/// Accepts `unix:<path>` for a Unix domain socket or `http://<host>:<port>[/<path>]`.
+	/// Keys can only be inserted over a Unix domain socket.
+	#[arg(
+		long,
+		value_name = "URI",
+		conflicts_with_all = &[
+			"keystore_path",
+			"password_interactive",
+			"password",
+			"password_filename",
+			"keystore_passphrase_interactive",
+			"keystore_passphrase",
+			"keystore_passphrase_filename",
+		]
+	)]
 	pub keystore_uri: Option<String>,
 
 	/// Specify custom keystore path.
@@ -59,6 +74,32 @@ pub struct KeystoreParams {
 		conflicts_with_all = &["password_interactive", "password"]
 	)]
 	pub password_filename: Option<PathBuf>,
+
+	/// Use interactive shell for entering the passphrase the key files are encrypted with.
+	#[arg(
+		long,
+		conflicts_with_all = &["keystore_passphrase", "keystore_passphrase_filename"]
+	)]
+	pub keystore_passphrase_interactive: bool,
+
+	/// Passphrase the key files are encrypted with at rest.
+	///
+	/// A keystore opened with a passphrase encrypts every key file it writes. Key files written
+	/// before stay readable until they are encrypted with `key encrypt-keystore`.
+	#[arg(
+		long,
+		value_parser = secret_string_from_str,
+		conflicts_with_all = &["keystore_passphrase_interactive", "keystore_passphrase_filename"]
+	)]
+	pub keystore_passphrase: Option<SecretString>,
+
+	/// File that contains the passphrase the key files are encrypted with.
+	#[arg(
+		long,
+		value_name = "PATH",
+		conflicts_with_all = &["keystore_passphrase_interactive", "keystore_passphrase"]
+	)]
+	pub keystore_passphrase_filename: Option<PathBuf>,
 }
 
 /// Parse a secret string, returning a displayable error.
@@ -69,6 +110,10 @@ pub fn secret_string_from_str(s: &str) -> std::result::Result<SecretString, Stri
 impl KeystoreParams {
 	/// Get the keystore configuration for the parameters
 	pub fn keystore_config(&self, config_dir: &Path) -> Result<KeystoreConfig> {
+		if let Some(uri) = &self.keystore_uri {
+			return Ok(KeystoreConfig::Remote { uri: uri.clone() })
+		}
+
 		let password = if self.password_interactive {
 			Some(SecretString::new(input_keystore_password()?))
 		} else if let Some(ref file) = self.password_filename {
@@ -83,7 +128,24 @@ impl KeystoreParams {
 			.clone()
 			.unwrap_or_else(|| config_dir.join(DEFAULT_KEYSTORE_CONFIG_PATH));
 
-		Ok(KeystoreConfig::Path { path, password })
+		Ok(KeystoreConfig::Path { path, password, passphrase: self.read_passphrase()? })
+	}
+
+	/// Get the passphrase the key files are encrypted with, if any.
+	pub fn read_passphrase(&self) -> Result<Option<SecretString>> {
+		let passphrase = if self.keystore_passphrase_interactive {
+			Some(SecretString::new(
+				rpassword::prompt_password("Keystore passphrase: ")
+					.map_err(|e| format!("{:?}", e))?,
+			))
+		} else if let Some(ref file) = self.keystore_passphrase_filename {
+			let passphrase = fs::read_to_string(file).map_err(|e| format!("{}", e))?;
+			Some(SecretString::new(passphrase))
+		} else {
+			self.keystore_passphrase.clone()
+		};
+
+		Ok(passphrase)
 	}
 
 	/// helper method to fetch password from `KeyParams` or read from stdin
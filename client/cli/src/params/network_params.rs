@@ -16,11 +16,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_enums::SyncMode, params::node_key_params::NodeKeyParams};
+use crate::{
+	arg_enums::{GapSync, SyncMode},
+	params::node_key_params::NodeKeyParams,
+};
 use clap::Args;
 use sc_network::{
 	config::{
-		NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig,
+		BandwidthLimits, GapSyncMode, NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode,
+		ProtocolBandwidthLimits, SetConfig, TransportConfig, DEFAULT_REQUESTS_BANDWIDTH_SHARE,
 	},
 	multiaddr::Protocol,
 };
@@ -28,7 +32,7 @@ use sc_service::{
 	config::{Multiaddr, MultiaddrWithPeerId},
 	ChainSpec, ChainType,
 };
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, num::NonZeroU32, path::PathBuf, str::FromStr};
 
 /// Parameters used to create the network configuration.
 #[derive(Debug, Clone, Args)]
@@ -60,10 +64,12 @@ pub struct NetworkParams {
 	/// By default:
 	/// If `--validator` is passed: `/ip4/0.0.0.0/tcp/<port>` and `/ip6/[::]/tcp/<port>`.
 	/// Otherwise: `/ip4/0.0.0.0/tcp/<port>/ws` and `/ip6/[::]/tcp/<port>/ws`.
+	/// If `--quic` is passed, additionally `/ip4/0.0.0.0/udp/<port>/quic` and
+	/// `/ip6/[::]/udp/<port>/quic`.
 	#[arg(long, value_name = "LISTEN_ADDR", num_args = 1..)]
 	pub listen_addr: Vec<Multiaddr>,
 
-	/// Specify p2p protocol TCP port.
+	/// Specify p2p protocol TCP port (and UDP port, if QUIC is enabled).
 	#[arg(long, value_name = "PORT", conflicts_with_all = &[ "listen_addr" ])]
 	pub port: Option<u16>,
 
@@ -98,6 +104,14 @@ pub struct NetworkParams {
 	#[arg(long)]
 	pub no_mdns: bool,
 
+	/// Enable the QUIC transport in addition to TCP and WebSocket.
+	///
+	/// QUIC saves round trips when establishing connections and avoids head-of-line blocking
+	/// between substreams. The node listens on `/quic` addresses and advertises them to its
+	/// peers, which can still connect over TCP.
+	#[arg(long)]
+	pub quic: bool,
+
 	/// Maximum number of peers from which to ask for the same blocks in parallel.
 	/// This allows downloading announced blocks from multiple peers. Decrease to save
 	/// traffic and risk increased latency.
@@ -125,6 +139,12 @@ pub struct NetworkParams {
 	#[arg(long)]
 	pub ipfs_server: bool,
 
+	/// Export snapshots of finalized state and serve them to syncing nodes.
+	///
+	/// A snapshot is exported every 4096 finalized blocks, next to the database.
+	#[arg(long)]
+	pub serve_snapshots: bool,
+
 	/// Blockchain syncing mode.
 	#[arg(
 		long,
@@ -136,12 +156,104 @@ pub struct NetworkParams {
 	)]
 	pub sync: SyncMode,
 
+	/// What to download for the blocks preceding the block warp sync started from.
+	///
+	/// Bodies of blocks that are pruned right away (see `--blocks-pruning`) are never
+	/// downloaded.
+	#[arg(
+		long,
+		value_enum,
+		value_name = "GAP_SYNC",
+		default_value_t = GapSync::Full,
+		ignore_case = true
+	)]
+	pub gap_sync: GapSync,
+
+	/// Only download bodies of the last `COUNT` blocks preceding the block warp sync started
+	/// from. Ignored unless `--gap-sync` is `full`.
+	#[arg(long, value_name = "COUNT")]
+	pub gap_sync_body_depth: Option<u32>,
+
 	/// Maximum number of blocks per request.
 	///
 	/// Try reducing this number from the default value if you have a slow network connection
 	/// and observe block requests timing out.
 	#[arg(long, value_name = "COUNT", default_value_t = 64)]
 	pub max_blocks_per_request: u32,
+
+	/// Maximum rate at which the node downloads data, in KiB/s, over all connections.
+	/// Unlimited by default.
+	///
+	/// GRANDPA and other notification protocols are not prioritized within this limit, they are
+	/// guaranteed the share of it that `--requests-bandwidth-share` keeps away from requests.
+	#[arg(long, value_name = "KIB_PER_SEC")]
+	pub max_download_rate: Option<NonZeroU32>,
+
+	/// Maximum rate at which the node uploads data, in KiB/s, over all connections.
+	/// Unlimited by default.
+	///
+	/// GRANDPA and other notification protocols are not prioritized within this limit, they are
+	/// guaranteed the share of it that `--requests-bandwidth-share` keeps away from requests.
+	#[arg(long, value_name = "KIB_PER_SEC")]
+	pub max_upload_rate: Option<NonZeroU32>,
+
+	/// Percentage of `--max-download-rate` and `--max-upload-rate` that block, state and light
+	/// client requests may use in total, unless they are given a limit of their own with
+	/// `--protocol-rate-limit`.
+	///
+	/// The rest of the bandwidth is kept for notification protocols such as GRANDPA gossip.
+	/// With `100`, requests can use all of it and GRANDPA may be delayed by syncing peers.
+	#[arg(
+		long,
+		value_name = "PERCENT",
+		default_value_t = DEFAULT_REQUESTS_BANDWIDTH_SHARE,
+		value_parser = clap::value_parser!(u8).range(0..=100)
+	)]
+	pub requests_bandwidth_share: u8,
+
+	/// Limit the bandwidth used by a single request-response or notification protocol, in
+	/// KiB/s.
+	///
+	/// The value has the format `<PROTOCOL>=<DOWNLOAD>:<UPLOAD>`, e.g.
+	/// `/dot/sync/2=1024:256`. A rate of `0` leaves the direction unlimited.
+	#[arg(long, value_name = "PROTOCOL=DOWNLOAD:UPLOAD", num_args = 1..)]
+	pub protocol_rate_limit: Vec<ProtocolRateLimit>,
+}
+
+/// Bandwidth limits of a single protocol, in KiB/s, as passed with `--protocol-rate-limit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolRateLimit {
+	/// Name of the protocol.
+	pub protocol: String,
+	/// Maximum download rate, `0` if unlimited.
+	pub download: u32,
+	/// Maximum upload rate, `0` if unlimited.
+	pub upload: u32,
+}
+
+impl FromStr for ProtocolRateLimit {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("`{}` isn't of the form `<PROTOCOL>=<DOWNLOAD>:<UPLOAD>`", s);
+
+		let (protocol, rates) = s.rsplit_once('=').ok_or_else(invalid)?;
+		let (download, upload) = rates.split_once(':').ok_or_else(invalid)?;
+		if protocol.is_empty() {
+			return Err(invalid())
+		}
+
+		Ok(Self {
+			protocol: protocol.to_string(),
+			download: download.parse().map_err(|_| invalid())?,
+			upload: upload.parse().map_err(|_| invalid())?,
+		})
+	}
+}
+
+/// Converts a rate in KiB/s into bytes per second.
+fn kib_per_sec(rate: u32) -> Option<NonZeroU32> {
+	NonZeroU32::new(rate.saturating_mul(1024))
 }
 
 impl NetworkParams {
@@ -185,6 +297,22 @@ impl NetworkParams {
 			self.listen_addr.clone()
 		};
 
+		let listen_addresses = if self.quic && self.listen_addr.is_empty() {
+			let quic_addresses = [
+				Multiaddr::empty()
+					.with(Protocol::Ip6([0, 0, 0, 0, 0, 0, 0, 0].into()))
+					.with(Protocol::Udp(port))
+					.with(Protocol::Quic),
+				Multiaddr::empty()
+					.with(Protocol::Ip4([0, 0, 0, 0].into()))
+					.with(Protocol::Udp(port))
+					.with(Protocol::Quic),
+			];
+			listen_addresses.into_iter().chain(quic_addresses).collect()
+		} else {
+			listen_addresses
+		};
+
 		let public_addresses = self.public_addr.clone();
 
 		let mut boot_nodes = chain_spec.boot_nodes().to_vec();
@@ -205,6 +333,23 @@ impl NetworkParams {
 				is_dev || matches!(chain_type, ChainType::Local | ChainType::Development),
 		};
 
+		let bandwidth_limits = BandwidthLimits {
+			inbound: self.max_download_rate.and_then(|rate| kib_per_sec(rate.get())),
+			outbound: self.max_upload_rate.and_then(|rate| kib_per_sec(rate.get())),
+			requests_share: self.requests_bandwidth_share,
+			protocols: self
+				.protocol_rate_limit
+				.iter()
+				.map(|limit| {
+					let limits = ProtocolBandwidthLimits {
+						inbound: kib_per_sec(limit.download),
+						outbound: kib_per_sec(limit.upload),
+					};
+					(limit.protocol.clone().into(), limits)
+				})
+				.collect(),
+		};
+
 		NetworkConfiguration {
 			boot_nodes,
 			net_config_path,
@@ -229,6 +374,7 @@ impl NetworkParams {
 			transport: TransportConfig::Normal {
 				enable_mdns: !is_dev && !self.no_mdns,
 				allow_private_ip,
+				enable_quic: self.quic,
 			},
 			max_parallel_downloads: self.max_parallel_downloads,
 			max_blocks_per_request: self.max_blocks_per_request,
@@ -237,7 +383,15 @@ impl NetworkParams {
 			kademlia_disjoint_query_paths: self.kademlia_disjoint_query_paths,
 			yamux_window_size: None,
 			ipfs_server: self.ipfs_server,
+			serve_snapshots: self.serve_snapshots,
+			snapshot_period: sc_client_api::snapshot::SNAPSHOT_PERIOD,
 			sync_mode: self.sync.into(),
+			gap_sync_mode: match self.gap_sync {
+				GapSync::Headers => GapSyncMode::Headers,
+				GapSync::HeadersAndJustifications => GapSyncMode::HeadersAndJustifications,
+				GapSync::Full => GapSyncMode::Full { body_depth: self.gap_sync_body_depth },
+			},
+			bandwidth_limits,
 		}
 	}
 }
@@ -292,4 +446,40 @@ mod tests {
 
 		assert_eq!(SyncMode::Warp, params.network_params.sync);
 	}
+
+	#[test]
+	fn gap_sync_is_parsed() {
+		let params = Cli::try_parse_from(["", "--gap-sync", "headers-and-justifications"])
+			.expect("Parses network params");
+		assert_eq!(GapSync::HeadersAndJustifications, params.network_params.gap_sync);
+		assert_eq!(None, params.network_params.gap_sync_body_depth);
+
+		let params = Cli::try_parse_from(["", "--gap-sync-body-depth", "4096"])
+			.expect("Parses network params");
+		assert_eq!(GapSync::Full, params.network_params.gap_sync);
+		assert_eq!(Some(4096), params.network_params.gap_sync_body_depth);
+
+		assert!(Cli::try_parse_from(["", "--gap-sync", "bodies"]).is_err());
+	}
+
+	#[test]
+	fn protocol_rate_limits_are_parsed() {
+		let params = Cli::try_parse_from([
+			"",
+			"--protocol-rate-limit",
+			"/dot/sync/2=1024:256",
+			"/dot/state/2=0:128",
+		])
+		.expect("Parses network params");
+
+		assert_eq!(
+			params.network_params.protocol_rate_limit,
+			vec![
+				ProtocolRateLimit { protocol: "/dot/sync/2".into(), download: 1024, upload: 256 },
+				ProtocolRateLimit { protocol: "/dot/state/2".into(), download: 0, upload: 128 },
+			],
+		);
+		assert!(Cli::try_parse_from(["", "--protocol-rate-limit", "/dot/sync/2=1024"]).is_err());
+		assert!(Cli::try_parse_from(["", "--requests-bandwidth-share", "101"]).is_err());
+	}
 }
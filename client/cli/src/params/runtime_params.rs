@@ -29,6 +29,14 @@ pub struct RuntimeParams {
 	/// Maximum number of different runtimes that can be cached.
 	#[arg(long, default_value_t = 2)]
 	pub runtime_cache_size: u8,
+
+	/// Time every call of the runtime into a host function.
+	///
+	/// The timings are exported as Prometheus metrics and can be queried for a block with the
+	/// `state_profileBlock` RPC. Only the compiled wasm execution method is profiled, and
+	/// profiling slightly slows down every host function call.
+	#[arg(long)]
+	pub profile_host_functions: bool,
 }
 
 fn parse_max_runtime_instances(s: &str) -> Result<usize, String> {
@@ -16,8 +16,28 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use clap::Args;
-use sc_service::config::TransactionPoolOptions;
+use clap::{Args, ValueEnum};
+use sc_service::config::{TransactionPoolOptions, TransactionPoolType};
+use std::path::PathBuf;
+
+/// Type of transaction pool to be used.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum TransactionPoolTypeParam {
+	/// Uses a single state transaction pool, revalidated against the best block.
+	SingleState,
+	/// Uses a fork-aware transaction pool, maintaining a view per best-fork leaf.
+	ForkAware,
+}
+
+impl From<TransactionPoolTypeParam> for TransactionPoolType {
+	fn from(pool_type: TransactionPoolTypeParam) -> Self {
+		match pool_type {
+			TransactionPoolTypeParam::SingleState => TransactionPoolType::SingleState,
+			TransactionPoolTypeParam::ForkAware => TransactionPoolType::ForkAware,
+		}
+	}
+}
 
 /// Parameters used to create the pool configuration.
 #[derive(Debug, Clone, Args)]
@@ -33,6 +53,22 @@ pub struct TransactionPoolParams {
 	/// How long a transaction is banned for, if it is considered invalid. Defaults to 1800s.
 	#[arg(long, value_name = "SECONDS")]
 	pub tx_ban_seconds: Option<u64>,
+
+	/// The type of transaction pool to be instantiated.
+	#[arg(
+		long,
+		value_enum,
+		value_name = "TYPE",
+		default_value_t = TransactionPoolTypeParam::SingleState
+	)]
+	pub pool_type: TransactionPoolTypeParam,
+
+	/// Persist the transactions of the pool to the given file across restarts.
+	///
+	/// On startup the transactions found in the file are revalidated and submitted again to
+	/// the pool.
+	#[arg(long, value_name = "PATH")]
+	pub pool_journal: Option<PathBuf>,
 }
 
 impl TransactionPoolParams {
@@ -41,15 +77,15 @@ impl TransactionPoolParams {
 		let mut opts = TransactionPoolOptions::default();
 
 		// ready queue
-		opts.ready.count = self.pool_limit;
-		opts.ready.total_bytes = self.pool_kbytes * 1024;
+		opts.options.ready.count = self.pool_limit;
+		opts.options.ready.total_bytes = self.pool_kbytes * 1024;
 
 		// future queue
 		let factor = 10;
-		opts.future.count = self.pool_limit / factor;
-		opts.future.total_bytes = self.pool_kbytes * 1024 / factor;
+		opts.options.future.count = self.pool_limit / factor;
+		opts.options.future.total_bytes = self.pool_kbytes * 1024 / factor;
 
-		opts.ban_time = if let Some(ban_seconds) = self.tx_ban_seconds {
+		opts.options.ban_time = if let Some(ban_seconds) = self.tx_ban_seconds {
 			std::time::Duration::from_secs(ban_seconds)
 		} else if is_dev {
 			std::time::Duration::from_secs(0)
@@ -57,6 +93,9 @@ impl TransactionPoolParams {
 			std::time::Duration::from_secs(30 * 60)
 		};
 
+		opts.txpool_type = self.pool_type.into();
+		opts.journal_path = self.pool_journal.clone();
+
 		opts
 	}
 }
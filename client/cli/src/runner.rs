@@ -270,6 +270,7 @@ mod tests {
 				keystore: sc_service::config::KeystoreConfig::InMemory,
 				database: sc_client_db::DatabaseSource::ParityDb { path: PathBuf::from("db") },
 				trie_cache_maximum_size: None,
+				trie_cache_snapshot_maximum_size: None,
 				state_pruning: None,
 				blocks_pruning: sc_client_db::BlocksPruning::KeepAll,
 				chain_spec: Box::new(GenericChainSpec::from_genesis(
@@ -298,6 +299,8 @@ mod tests {
 				rpc_max_response_size: None,
 				rpc_id_provider: None,
 				rpc_max_subs_per_conn: None,
+				rpc_rate_limit: Default::default(),
+				rpc_method_filter: Default::default(),
 				ws_max_out_buffer_capacity: None,
 				prometheus_config: None,
 				telemetry_endpoints: None,
@@ -313,6 +316,7 @@ mod tests {
 				base_path: None,
 				informant_output_format: Default::default(),
 				runtime_cache_size: 2,
+				profile_host_functions: false,
 			},
 			runtime,
 			Signals::dummy(),
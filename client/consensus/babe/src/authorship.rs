@@ -24,7 +24,7 @@ use sc_consensus_epochs::Epoch as EpochT;
 use sp_application_crypto::AppCrypto;
 use sp_consensus_babe::{
 	digests::{PreDigest, PrimaryPreDigest, SecondaryPlainPreDigest, SecondaryVRFPreDigest},
-	make_transcript, AuthorityId, BabeAuthorityWeight, Randomness, Slot,
+	make_transcript, make_transcript_data, AuthorityId, BabeAuthorityWeight, Randomness, Slot,
 };
 use sp_core::{
 	blake2_256,
@@ -149,8 +149,13 @@ fn claim_secondary_slot(
 		if authority_id == expected_author {
 			let pre_digest = if author_secondary_vrf {
 				let transcript = make_transcript(randomness, slot, epoch_index);
-				let result =
-					keystore.sr25519_vrf_sign(AuthorityId::ID, authority_id.as_ref(), &transcript);
+				let data = make_transcript_data(randomness, slot, epoch_index);
+				let result = keystore.sr25519_vrf_sign_with_data(
+					AuthorityId::ID,
+					authority_id.as_ref(),
+					&transcript,
+					&data,
+				);
 				if let Ok(Some(vrf_signature)) = result {
 					Some(PreDigest::SecondaryVRF(SecondaryVRFPreDigest {
 						slot,
@@ -240,9 +245,15 @@ fn claim_primary_slot(
 	}
 
 	let transcript = make_transcript(randomness, slot, epoch_index);
+	let data = make_transcript_data(randomness, slot, epoch_index);
 
 	for (authority_id, authority_index) in keys {
-		let result = keystore.sr25519_vrf_sign(AuthorityId::ID, authority_id.as_ref(), &transcript);
+		let result = keystore.sr25519_vrf_sign_with_data(
+			AuthorityId::ID,
+			authority_id.as_ref(),
+			&transcript,
+			&data,
+		);
 		if let Ok(Some(vrf_signature)) = result {
 			let threshold = calculate_primary_threshold(c, authorities, *authority_index);
 
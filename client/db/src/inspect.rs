@@ -0,0 +1,595 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Offline inspection of the database of a node that isn't running.
+//!
+//! [`DbInspector`] reads the database directly, without going through [`crate::Backend`], so
+//! that it can be used on databases the backend fails to open.
+
+use crate::{
+	columns,
+	utils::{self, meta_keys, DatabaseType, NUM_COLUMNS},
+	DatabaseSource, DbHash, StateMetaDb,
+};
+use hash_db::{Hasher, Prefix};
+use parking_lot::Mutex;
+use sc_client_api::leaves::LeafSet;
+use sc_state_db::PruningMode;
+use sp_blockchain::{Error as ClientError, Result as ClientResult};
+use sp_core::storage::well_known_keys;
+use sp_database::{Database, Transaction};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, HashFor, Header as HeaderT, NumberFor, SaturatedConversion},
+};
+use sp_state_machine::{DBValue, Storage};
+use sp_trie::{empty_trie_root, prefixed_key, LayoutV1, NodeCodec};
+use std::{
+	collections::{HashMap, HashSet},
+	marker::PhantomData,
+	sync::Arc,
+};
+use trie_db::{
+	nibble_ops,
+	node::{Node, NodeHandle, Value},
+	NibbleVec, NodeCodec as NodeCodecT,
+};
+
+pub use sc_state_db::{Journals, NonCanonicalJournalRecord, PruningJournalRecord};
+
+/// Number of orphaned nodes removed per transaction.
+const REMOVAL_BATCH_SIZE: usize = 10_000;
+
+/// Number and size of the entries of a database column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+	/// Column index.
+	pub id: u32,
+	/// Column name.
+	pub name: &'static str,
+	/// Number of entries.
+	pub entries: u64,
+	/// Total size of the keys. Keys the database only keeps a hash of are not counted.
+	pub key_bytes: u64,
+	/// Total size of the values.
+	pub value_bytes: u64,
+}
+
+/// A leaf of the block tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leaf<Block: BlockT> {
+	/// Block hash.
+	pub hash: Block::Hash,
+	/// Block number, `None` if the header is missing.
+	pub number: Option<NumberFor<Block>>,
+	/// Whether the block is part of the canonical chain.
+	pub canonical: bool,
+}
+
+/// Heads of the block tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leaves<Block: BlockT> {
+	/// Best block hash and number.
+	pub best: (Block::Hash, NumberFor<Block>),
+	/// Finalized block hash and number.
+	pub finalized: (Block::Hash, NumberFor<Block>),
+	/// All the leaves, highest first.
+	pub leaves: Vec<Leaf<Block>>,
+}
+
+/// Result of walking the trie of a state, see [`DbInspector::verify_state`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateReport {
+	/// Number of keys found, child tries included.
+	pub keys: u64,
+	/// Number of child tries found.
+	pub child_tries: u64,
+	/// Number of trie nodes read.
+	pub nodes: u64,
+	/// First error encountered, which stops the walk, e.g. a missing or corrupted node.
+	pub error: Option<String>,
+}
+
+/// Trie nodes that no state kept by the database references, see
+/// [`DbInspector::find_orphans`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OrphanReport {
+	/// Number of states walked to find the referenced nodes.
+	pub states: usize,
+	/// Number of referenced nodes.
+	pub referenced: u64,
+	/// Keys of the orphaned nodes in the state column.
+	pub orphans: Vec<Vec<u8>>,
+	/// Total size of the orphaned nodes.
+	pub orphan_bytes: u64,
+}
+
+/// Reads the database of a node that isn't running.
+pub struct DbInspector<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	_block: PhantomData<Block>,
+}
+
+impl<Block: BlockT> DbInspector<Block> {
+	/// Open an existing database.
+	///
+	/// The database is not upgraded, and is opened read-only unless `writable` is set, which
+	/// [`Self::remove_orphans`] needs.
+	pub fn open(source: &DatabaseSource, writable: bool) -> ClientResult<Self> {
+		let db = utils::open_existing_database(source, DatabaseType::Full, writable)?;
+		Ok(Self { db, _block: PhantomData })
+	}
+
+	/// Returns the number and size of the entries of every column.
+	///
+	/// This reads the whole database.
+	pub fn columns(&self) -> ClientResult<Vec<ColumnInfo>> {
+		(0..NUM_COLUMNS)
+			.map(|id| {
+				let mut info = ColumnInfo {
+					id,
+					name: column_name(id),
+					entries: 0,
+					key_bytes: 0,
+					value_bytes: 0,
+				};
+				self.db
+					.iter_column_while(id, &mut |key, value| {
+						info.entries += 1;
+						info.key_bytes += key.map_or(0, |key| key.len() as u64);
+						info.value_bytes += value.len() as u64;
+						true
+					})
+					.map_err(|e| ClientError::Backend(e.to_string()))?;
+				Ok(info)
+			})
+			.collect()
+	}
+
+	/// Returns the best and finalized blocks along with all the leaves of the block tree.
+	pub fn leaves(&self) -> ClientResult<Leaves<Block>> {
+		let meta = utils::read_meta::<Block>(&*self.db, columns::HEADER)?;
+		let leaf_set = LeafSet::<Block::Hash, NumberFor<Block>>::read_from_db(
+			&*self.db,
+			columns::META,
+			meta_keys::LEAF_PREFIX,
+		)?;
+
+		let leaves = leaf_set
+			.hashes()
+			.into_iter()
+			.map(|hash| {
+				let number = self.header(hash)?.map(|header| *header.number());
+				let canonical = match number {
+					Some(number) =>
+						self.read_header(BlockId::Number(number))?.map(|header| header.hash()) ==
+							Some(hash),
+					None => false,
+				};
+				Ok(Leaf { hash, number, canonical })
+			})
+			.collect::<ClientResult<_>>()?;
+
+		Ok(Leaves {
+			best: (meta.best_hash, meta.best_number),
+			finalized: (meta.finalized_hash, meta.finalized_number),
+			leaves,
+		})
+	}
+
+	/// Returns the journals of the state database.
+	///
+	/// Pinned blocks are only tracked in memory by a running node and are never part of the
+	/// journals.
+	pub fn journals(&self) -> ClientResult<Journals<Block::Hash, Vec<u8>>> {
+		sc_state_db::read_journals(&StateMetaDb(self.db.clone()))
+			.map_err(ClientError::from_state_db)
+	}
+
+	/// Walks the whole state trie with the given root, child tries included, checking that every
+	/// node is present and matches its hash.
+	///
+	/// Nodes of the non-canonical blocks are read from the journal of the state database.
+	pub fn verify_state(&self, root: Block::Hash) -> ClientResult<StateReport> {
+		let overlay = Arc::new(non_canonical_nodes(&self.journals()?));
+		let storage = self.storage(overlay);
+		let mut report = StateReport::default();
+		report.error = walk_state(&storage, root, false, &mut report).err();
+		report.nodes = storage.visited.lock().len() as u64;
+		Ok(report)
+	}
+
+	/// Finds the trie nodes of the state column that are not referenced by any state the
+	/// database keeps, e.g. because the node stopped in the middle of pruning.
+	///
+	/// This walks every state kept by the database, which includes the whole canonical chain
+	/// for [`PruningMode::ArchiveCanonical`]. Subtrees shared with a state walked before are
+	/// only read once. Archive databases keeping every block can't have orphaned nodes.
+	pub fn find_orphans(&self) -> ClientResult<OrphanReport> {
+		let journals = self.journals()?;
+
+		let mut kept: Vec<Block::Hash> = Vec::new();
+		match journals.pruning_mode {
+			Some(PruningMode::Constrained(_)) =>
+				kept.extend(journals.pruning.iter().map(|record| record.hash)),
+			Some(PruningMode::ArchiveCanonical) => {
+				let last_canonicalized =
+					journals.last_canonicalized.map_or(0, |(_, number)| number);
+				for number in 0..=last_canonicalized {
+					let number: NumberFor<Block> = number.saturated_into();
+					match self.read_header(BlockId::Number(number))? {
+						Some(header) => kept.push(header.hash()),
+						None =>
+							return Err(ClientError::Backend(format!(
+								"Missing canonical header #{}",
+								number
+							))),
+					}
+				}
+			},
+			Some(PruningMode::ArchiveAll) | None =>
+				return Err(ClientError::Backend(
+					"Orphaned nodes can only be found in databases that prune state".into(),
+				)),
+		}
+		kept.extend(journals.last_canonicalized.as_ref().map(|(hash, _)| *hash));
+		kept.extend(journals.non_canonical.iter().map(|record| record.hash));
+		kept.sort();
+		kept.dedup();
+
+		let overlay = Arc::new(non_canonical_nodes(&journals));
+		let storage = self.storage(overlay);
+		for hash in &kept {
+			let header = self.header(*hash)?.ok_or_else(|| {
+				ClientError::Backend(format!("Missing header of kept state {:?}", hash))
+			})?;
+			walk_state(&storage, *header.state_root(), true, &mut Default::default()).map_err(
+				|e| ClientError::Backend(format!("Failed to walk the state of {:?}: {}", hash, e)),
+			)?;
+		}
+
+		// Nodes of the states that left the pruning window are deleted when the next block is
+		// pruned.
+		let scheduled: HashSet<&Vec<u8>> =
+			journals.pruning.iter().flat_map(|record| record.deleted.iter()).collect();
+		let visited = storage.visited.lock();
+		let mut report = OrphanReport {
+			states: kept.len(),
+			referenced: visited.len() as u64,
+			..Default::default()
+		};
+		self.db
+			.iter_column_while(columns::STATE, &mut |key, value| {
+				let key = match key {
+					Some(key) => key.to_vec(),
+					// Keys are only hashed by databases with reference counting, which don't
+					// prefix them.
+					None => HashFor::<Block>::hash(value).as_ref().to_vec(),
+				};
+				if !visited.contains(&key) && !scheduled.contains(&key) {
+					report.orphan_bytes += value.len() as u64;
+					report.orphans.push(key);
+				}
+				true
+			})
+			.map_err(|e| ClientError::Backend(e.to_string()))?;
+
+		Ok(report)
+	}
+
+	/// Removes the given orphaned nodes, as found by [`Self::find_orphans`], from the state
+	/// column.
+	///
+	/// Databases with reference counting are not supported, as their nodes can't be removed
+	/// without knowing how many times they were referenced.
+	pub fn remove_orphans(&self, orphans: &[Vec<u8>]) -> ClientResult<()> {
+		if self.db.supports_ref_counting() {
+			return Err(ClientError::Backend(
+				"Orphaned nodes can't be removed from databases with reference counting".into(),
+			))
+		}
+
+		for batch in orphans.chunks(REMOVAL_BATCH_SIZE) {
+			let mut transaction = Transaction::new();
+			for key in batch {
+				transaction.remove(columns::STATE, key);
+			}
+			self.db.commit(transaction).map_err(|e| ClientError::Backend(e.to_string()))?;
+		}
+		Ok(())
+	}
+
+	/// Returns the header of the given block.
+	pub fn header(&self, hash: Block::Hash) -> ClientResult<Option<Block::Header>> {
+		self.read_header(BlockId::Hash(hash))
+	}
+
+	fn read_header(&self, id: BlockId<Block>) -> ClientResult<Option<Block::Header>> {
+		utils::read_header(&*self.db, columns::KEY_LOOKUP, columns::HEADER, id)
+	}
+
+	fn storage(&self, overlay: Arc<HashMap<Vec<u8>, DBValue>>) -> Arc<InspectionStorage<Block>> {
+		Arc::new(InspectionStorage {
+			db: self.db.clone(),
+			prefix_keys: !self.db.supports_ref_counting(),
+			overlay,
+			visited: Default::default(),
+			_block: PhantomData,
+		})
+	}
+}
+
+/// Reads trie nodes from the state column, keeping track of the nodes read.
+struct InspectionStorage<Block: BlockT> {
+	db: Arc<dyn Database<DbHash>>,
+	prefix_keys: bool,
+	/// Nodes inserted by the non-canonical blocks, not written to the state column yet.
+	overlay: Arc<HashMap<Vec<u8>, DBValue>>,
+	/// Database keys of the nodes read so far.
+	visited: Mutex<HashSet<Vec<u8>>>,
+	_block: PhantomData<Block>,
+}
+
+impl<Block: BlockT> InspectionStorage<Block> {
+	fn db_key(&self, key: &Block::Hash, prefix: Prefix) -> Vec<u8> {
+		if self.prefix_keys {
+			prefixed_key::<HashFor<Block>>(key, prefix)
+		} else {
+			key.as_ref().to_vec()
+		}
+	}
+
+	/// Returns `true` if the node was already read.
+	fn is_visited(&self, key: &Block::Hash, prefix: Prefix) -> bool {
+		self.visited.lock().contains(&self.db_key(key, prefix))
+	}
+}
+
+impl<Block: BlockT> Storage<HashFor<Block>> for InspectionStorage<Block> {
+	fn get(&self, key: &Block::Hash, prefix: Prefix) -> Result<Option<DBValue>, String> {
+		let db_key = self.db_key(key, prefix);
+		let value = match self.overlay.get(&db_key) {
+			Some(value) => Some(value.clone()),
+			None => self.db.get(columns::STATE, &db_key),
+		};
+
+		if let Some(value) = &value {
+			if HashFor::<Block>::hash(value) != *key {
+				return Err(format!("Trie node {:?} doesn't match its hash", key))
+			}
+			self.visited.lock().insert(db_key);
+		}
+		Ok(value)
+	}
+}
+
+/// Walks the trie nodes of the state with the given root, child tries included.
+///
+/// If `skip_visited` is set, the subtrees whose root was already read, e.g. while walking another
+/// state, are skipped and their keys aren't counted.
+fn walk_state<Block: BlockT>(
+	storage: &InspectionStorage<Block>,
+	root: Block::Hash,
+	skip_visited: bool,
+	report: &mut StateReport,
+) -> Result<(), String> {
+	if root == empty_trie_root::<LayoutV1<HashFor<Block>>>() {
+		return Ok(())
+	}
+
+	let mut walk =
+		StateWalk { storage, skip_visited, keyspace: Vec::new(), child_roots: Vec::new(), report };
+	walk.node(NodeHandle::Hash(root.as_ref()), &mut NibbleVec::new())?;
+
+	for (keyspace, root) in std::mem::take(&mut walk.child_roots) {
+		walk.report.child_tries += 1;
+		walk.keyspace = keyspace;
+		walk.node(NodeHandle::Hash(root.as_ref()), &mut NibbleVec::new())?;
+	}
+	Ok(())
+}
+
+/// State of [`walk_state`].
+struct StateWalk<'a, Block: BlockT> {
+	storage: &'a InspectionStorage<Block>,
+	skip_visited: bool,
+	/// Keyspace of the child trie being walked, empty for the top trie.
+	keyspace: Vec<u8>,
+	/// Keyspaces and roots of the child tries found in the top trie.
+	child_roots: Vec<(Vec<u8>, Block::Hash)>,
+	report: &'a mut StateReport,
+}
+
+impl<'a, Block: BlockT> StateWalk<'a, Block> {
+	/// Walks the node with the given handle, found at `key`, and its descendants.
+	fn node(&mut self, handle: NodeHandle, key: &mut NibbleVec) -> Result<(), String> {
+		let data = match handle {
+			NodeHandle::Hash(hash) => {
+				let hash = decode_hash::<Block>(hash)?;
+				let (key_bytes, nibble) = key.as_prefix();
+				let prefix = [&self.keyspace[..], key_bytes].concat();
+				if self.skip_visited && self.storage.is_visited(&hash, (&prefix, nibble)) {
+					return Ok(())
+				}
+				self.read(&hash, (&prefix, nibble))?
+			},
+			NodeHandle::Inline(data) => data.to_vec(),
+		};
+
+		let node = <NodeCodec<HashFor<Block>> as NodeCodecT>::decode(&data)
+			.map_err(|e| format!("Failed to decode trie node: {:?}", e))?;
+		match node {
+			Node::Empty => {},
+			Node::Leaf(partial, value) => {
+				key.append_partial(partial.right());
+				self.value(value, key)?;
+				key.drop_lasts(partial.len());
+			},
+			Node::Extension(partial, child) => {
+				key.append_partial(partial.right());
+				self.node(child, key)?;
+				key.drop_lasts(partial.len());
+			},
+			Node::Branch(children, value) => self.branch(children, value, key)?,
+			Node::NibbledBranch(partial, children, value) => {
+				key.append_partial(partial.right());
+				self.branch(children, value, key)?;
+				key.drop_lasts(partial.len());
+			},
+		}
+		Ok(())
+	}
+
+	fn branch(
+		&mut self,
+		children: [Option<NodeHandle>; nibble_ops::NIBBLE_LENGTH],
+		value: Option<Value>,
+		key: &mut NibbleVec,
+	) -> Result<(), String> {
+		if let Some(value) = value {
+			self.value(value, key)?;
+		}
+		for (nibble, child) in children.into_iter().enumerate() {
+			if let Some(child) = child {
+				key.push(nibble as u8);
+				self.node(child, key)?;
+				key.pop();
+			}
+		}
+		Ok(())
+	}
+
+	/// Reads the value stored at `key`, keeping track of the child tries.
+	fn value(&mut self, value: Value, key: &NibbleVec) -> Result<(), String> {
+		let (key, nibble) = key.as_prefix();
+		if nibble.is_some() {
+			return Err(format!("Value at incomplete key {:?}", key))
+		}
+		let value = match value {
+			Value::Inline(value) => value.to_vec(),
+			Value::Node(hash) => {
+				let prefix = [&self.keyspace[..], key].concat();
+				self.read(&decode_hash::<Block>(hash)?, (&prefix, None))?
+			},
+		};
+
+		self.report.keys += 1;
+		if self.keyspace.is_empty() {
+			if let Some(storage_key) =
+				key.strip_prefix(well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX)
+			{
+				let root = decode_hash::<Block>(&value)?;
+				self.child_roots.push((storage_key.to_vec(), root));
+			}
+		}
+		Ok(())
+	}
+
+	fn read(&self, hash: &Block::Hash, prefix: Prefix) -> Result<DBValue, String> {
+		self.storage
+			.get(hash, prefix)?
+			.ok_or_else(|| format!("Missing trie node {:?}", hash))
+	}
+}
+
+fn decode_hash<Block: BlockT>(data: &[u8]) -> Result<Block::Hash, String> {
+	let mut hash = Block::Hash::default();
+	if data.len() != hash.as_ref().len() {
+		return Err(format!("Invalid trie node hash {:?}", data))
+	}
+	hash.as_mut().copy_from_slice(data);
+	Ok(hash)
+}
+
+fn non_canonical_nodes<Hash: sc_state_db::Hash>(
+	journals: &Journals<Hash, Vec<u8>>,
+) -> HashMap<Vec<u8>, DBValue> {
+	journals
+		.non_canonical
+		.iter()
+		.flat_map(|record| record.inserted.iter().cloned())
+		.collect()
+}
+
+fn column_name(column: u32) -> &'static str {
+	match column {
+		columns::META => "meta",
+		columns::STATE => "state",
+		columns::STATE_META => "state_meta",
+		columns::KEY_LOOKUP => "key_lookup",
+		columns::HEADER => "header",
+		columns::BODY => "body",
+		columns::JUSTIFICATIONS => "justifications",
+		columns::AUX => "aux",
+		columns::OFFCHAIN => "offchain",
+		columns::TRANSACTION => "transaction",
+		columns::BODY_INDEX => "body_index",
+		_ => "unused",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		tests::{insert_header, Block},
+		Backend,
+	};
+	use sp_core::H256;
+
+	fn inspector(backend: &Backend<Block>) -> DbInspector<Block> {
+		let source =
+			DatabaseSource::Custom { db: backend.storage.db.clone(), require_create_flag: false };
+		DbInspector::open(&source, true).unwrap()
+	}
+
+	#[test]
+	fn verifies_state_and_removes_orphans() {
+		let backend = Backend::<Block>::new_test(2, 0);
+		let mut hash = insert_header(&backend, 0, Default::default(), None, Default::default());
+		for number in 1..6 {
+			hash = insert_header(&backend, number, hash, None, Default::default());
+		}
+		let inspector = inspector(&backend);
+
+		let leaves = inspector.leaves().unwrap();
+		assert_eq!(leaves.best, (hash, 5));
+		assert_eq!(leaves.leaves, vec![Leaf { hash, number: Some(5), canonical: true }]);
+
+		let root = *inspector.header(hash).unwrap().unwrap().state_root();
+		let report = inspector.verify_state(root).unwrap();
+		assert_eq!(report.error, None);
+		assert_eq!(report.keys, 6);
+
+		let report = inspector.verify_state(H256::repeat_byte(1)).unwrap();
+		assert!(report.error.is_some());
+
+		assert!(inspector.find_orphans().unwrap().orphans.is_empty());
+
+		let mut transaction = Transaction::new();
+		transaction.set(columns::STATE, b"orphan", b"node");
+		backend.storage.db.commit(transaction).unwrap();
+
+		let report = inspector.find_orphans().unwrap();
+		assert_eq!(report.orphans, vec![b"orphan".to_vec()]);
+		assert_eq!(report.orphan_bytes, 4);
+
+		inspector.remove_orphans(&report.orphans).unwrap();
+		assert!(inspector.find_orphans().unwrap().orphans.is_empty());
+	}
+}
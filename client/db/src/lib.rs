@@ -31,12 +31,15 @@
 pub mod offchain;
 
 pub mod bench;
+pub mod inspect;
 
 mod children;
 mod parity_db;
 mod pinned_blocks_cache;
 mod record_stats_state;
+mod snapshot;
 mod stats;
+mod trie_cache_snapshot;
 #[cfg(any(feature = "rocksdb", test))]
 mod upgrade;
 mod utils;
@@ -55,6 +58,7 @@ use crate::{
 	pinned_blocks_cache::PinnedBlocksCache,
 	record_stats_state::RecordStatsState,
 	stats::StateUsageStats,
+	trie_cache_snapshot::TrieCacheSnapshot,
 	utils::{meta_keys, read_db, read_meta, DatabaseType, Meta},
 };
 use codec::{Decode, Encode};
@@ -97,6 +101,7 @@ pub use sc_state_db::PruningMode;
 pub use sp_database::Database;
 
 pub use bench::BenchmarkingState;
+pub use snapshot::SnapshotStore;
 
 const CACHE_HEADERS: usize = 8;
 
@@ -291,6 +296,12 @@ pub struct DatabaseSettings {
 	///
 	/// If `None` is given, the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// The maximum size in bytes of the snapshot of the trie cache.
+	///
+	/// The snapshot is written to the database directory when the backend is dropped and
+	/// restored when it is created again, if the best block is still the same. If `None` is
+	/// given or the trie cache is disabled, no snapshot is written or restored.
+	pub trie_cache_snapshot_maximum_size: Option<usize>,
 	/// Requested state pruning mode.
 	pub state_pruning: Option<PruningMode>,
 	/// Where to find the database.
@@ -1105,6 +1116,7 @@ pub struct Backend<Block: BlockT> {
 	state_usage: Arc<StateUsageStats>,
 	genesis_state: RwLock<Option<Arc<DbGenesisStorage<Block>>>>,
 	shared_trie_cache: Option<sp_trie::cache::SharedTrieCache<HashFor<Block>>>,
+	trie_cache_snapshot: Option<TrieCacheSnapshot>,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -1151,6 +1163,7 @@ impl<Block: BlockT> Backend<Block> {
 		};
 		let db_setting = DatabaseSettings {
 			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_snapshot_maximum_size: None,
 			state_pruning: Some(state_pruning),
 			source: DatabaseSource::Custom { db, require_create_flag: true },
 			blocks_pruning,
@@ -1221,6 +1234,9 @@ impl<Block: BlockT> Backend<Block> {
 			shared_trie_cache: config.trie_cache_maximum_size.map(|maximum_size| {
 				SharedTrieCache::new(sp_trie::cache::CacheSize::new(maximum_size))
 			}),
+			trie_cache_snapshot: config
+				.trie_cache_snapshot_maximum_size
+				.and_then(|maximum_size| TrieCacheSnapshot::new(&config.source, maximum_size)),
 		};
 
 		// Older DB versions have no last state key. Check if the state is available and set it.
@@ -1243,6 +1259,14 @@ impl<Block: BlockT> Backend<Block> {
 
 		db.commit(db_init_transaction)?;
 
+		if let (Some(snapshot), Some(cache)) =
+			(&backend.trie_cache_snapshot, &backend.shared_trie_cache)
+		{
+			if let Some(header) = backend.blockchain.header(info.best_hash)? {
+				snapshot.restore(cache, header.state_root());
+			}
+		}
+
 		Ok(backend)
 	}
 
@@ -1682,7 +1706,8 @@ impl<Block: BlockT> Backend<Block> {
 						);
 					}
 				} else if number > best_num + One::one() &&
-					number > One::one() && self.blockchain.header(parent_hash)?.is_none()
+					number > One::one() &&
+					self.blockchain.header(parent_hash)?.is_none()
 				{
 					let gap = (best_num + One::one(), number - One::one());
 					transaction.set(columns::META, meta_keys::BLOCK_GAP, &gap.encode());
@@ -1999,6 +2024,19 @@ fn apply_indexed_body<Block: BlockT>(transaction: &mut Transaction<DbHash>, body
 	}
 }
 
+impl<Block: BlockT> Drop for Backend<Block> {
+	fn drop(&mut self) {
+		if let (Some(snapshot), Some(cache)) = (&self.trie_cache_snapshot, &self.shared_trie_cache)
+		{
+			match self.blockchain.header(self.blockchain.info().best_hash) {
+				Ok(Some(header)) => snapshot.write(cache, header.state_root()),
+				Ok(None) => {},
+				Err(e) => warn!(target: "db", "Failed to snapshot the trie cache: {}", e),
+			}
+		}
+	}
+}
+
 impl<Block> sc_client_api::backend::AuxStore for Backend<Block>
 where
 	Block: BlockT,
@@ -2669,6 +2707,7 @@ pub(crate) mod tests {
 		let backend = Backend::<Block>::new(
 			DatabaseSettings {
 				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_snapshot_maximum_size: None,
 				state_pruning: Some(PruningMode::blocks_pruning(1)),
 				source: DatabaseSource::Custom { db: backing, require_create_flag: false },
 				blocks_pruning: BlocksPruning::KeepFinalized,
@@ -4371,4 +4410,46 @@ pub(crate) mod tests {
 		backend.unpin_block(fork_hash_3);
 		assert!(bc.body(fork_hash_3).unwrap().is_none());
 	}
+
+	#[test]
+	fn trie_cache_snapshot_is_restored_at_the_same_best_block() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let open = |snapshot_size| {
+			Backend::<Block>::new(
+				DatabaseSettings {
+					trie_cache_maximum_size: Some(16 * 1024 * 1024),
+					trie_cache_snapshot_maximum_size: snapshot_size,
+					state_pruning: Some(PruningMode::ArchiveAll),
+					source: DatabaseSource::ParityDb { path: db_dir.path().into() },
+					blocks_pruning: BlocksPruning::KeepAll,
+				},
+				0,
+			)
+			.unwrap()
+		};
+		let cached_root = |backend: &Backend<Block>, hash| {
+			let root = *backend.blockchain().header(hash).unwrap().unwrap().state_root();
+			backend.shared_trie_cache.as_ref().unwrap().peek_node(&root).is_some()
+		};
+
+		let backend = open(Some(1024 * 1024));
+		let hash0 = insert_header(&backend, 0, Default::default(), None, Default::default());
+		// Reading the state fills the cache.
+		backend.state_at(hash0).unwrap().storage(&[0; 32]).unwrap().unwrap();
+		assert!(cached_root(&backend, hash0));
+		drop(backend);
+
+		let backend = open(Some(1024 * 1024));
+		assert!(cached_root(&backend, hash0));
+		drop(backend);
+
+		// The snapshot isn't restored once the best block changed.
+		let backend = open(None);
+		let hash1 = insert_header(&backend, 1, hash0, None, Default::default());
+		drop(backend);
+
+		let backend = open(Some(1024 * 1024));
+		assert!(!cached_root(&backend, hash0));
+		assert!(!cached_root(&backend, hash1));
+	}
 }
@@ -40,6 +40,45 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 	create: bool,
 	upgrade: bool,
 ) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
+	let config = options(path, db_type);
+
+	if upgrade {
+		log::info!("Upgrading database metadata.");
+		if let Some(meta) = parity_db::Options::load_metadata(path)? {
+			config.write_metadata_with_version(path, &meta.salt, Some(meta.version))?;
+		}
+	}
+
+	let db = if create {
+		parity_db::Db::open_or_create(&config)?
+	} else {
+		parity_db::Db::open(&config)?
+	};
+
+	Ok(std::sync::Arc::new(DbAdapter(db)))
+}
+
+/// Wrap an existing parity-db database into a trait object that implements
+/// `sp_database::Database`, without upgrading it.
+///
+/// Committing to a database opened read-only fails.
+pub fn open_existing<H: Clone + AsRef<[u8]>>(
+	path: &std::path::Path,
+	db_type: DatabaseType,
+	read_only: bool,
+) -> parity_db::Result<std::sync::Arc<dyn Database<H>>> {
+	let config = options(path, db_type);
+
+	let db = if read_only {
+		parity_db::Db::open_read_only(&config)?
+	} else {
+		parity_db::Db::open(&config)?
+	};
+
+	Ok(std::sync::Arc::new(DbAdapter(db)))
+}
+
+fn options(path: &std::path::Path, db_type: DatabaseType) -> parity_db::Options {
 	let mut config = parity_db::Options::with_columns(path, NUM_COLUMNS as u8);
 
 	match db_type {
@@ -70,20 +109,7 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 		},
 	}
 
-	if upgrade {
-		log::info!("Upgrading database metadata.");
-		if let Some(meta) = parity_db::Options::load_metadata(path)? {
-			config.write_metadata_with_version(path, &meta.salt, Some(meta.version))?;
-		}
-	}
-
-	let db = if create {
-		parity_db::Db::open_or_create(&config)?
-	} else {
-		parity_db::Db::open(&config)?
-	};
-
-	Ok(std::sync::Arc::new(DbAdapter(db)))
+	config
 }
 
 fn ref_counted_column(col: u32) -> bool {
@@ -159,4 +185,15 @@ impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
 	fn sanitize_key(&self, key: &mut Vec<u8>) {
 		let _prefix = key.drain(0..key.len() - crate::DB_HASH_LEN);
 	}
+
+	fn iter_column_while(
+		&self,
+		col: ColumnId,
+		f: &mut dyn FnMut(Option<&[u8]>, &[u8]) -> bool,
+	) -> Result<(), DatabaseError> {
+		// All the columns are hash indexed, which only keeps a hash of the keys.
+		self.0
+			.iter_column_while(col as u8, |state| f(None, &state.value))
+			.map_err(|e| DatabaseError(Box::new(e)))
+	}
 }
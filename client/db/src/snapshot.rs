@@ -0,0 +1,343 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Storage of the snapshots of finalized state served to other nodes.
+//!
+//! Every snapshot is exported to its own directory, named after the number and hash of its
+//! block, holding the encoded manifest and one file per chunk. A snapshot is written to a
+//! temporary directory first and only renamed once complete, so that a partially exported
+//! snapshot is never served.
+
+use crate::{utils::database_directory, DatabaseSource};
+use codec::{Decode, Encode};
+use log::{debug, info, warn};
+use sc_client_api::{
+	backend::Backend,
+	snapshot::{ChunkHash, ChunkInfo, SnapshotManifest, SnapshotProvider, CHUNK_SIZE},
+};
+use smallvec::SmallVec;
+use sp_blockchain::HeaderBackend;
+use sp_core::hashing::blake2_256;
+use sp_runtime::traits::{Block as BlockT, HashFor, Header as HeaderT, UniqueSaturatedInto};
+use sp_state_machine::{
+	prove_range_read_with_child_with_size_on_trie_backend, read_range_proof_check_with_child,
+	AsTrieBackend,
+};
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// The name of the snapshot directory in the database directory.
+const SNAPSHOTS_DIRECTORY_NAME: &str = "snapshots";
+
+/// The name of the manifest file in the directory of a snapshot.
+const MANIFEST_FILE_NAME: &str = "manifest";
+
+/// Snapshots of finalized state, stored on disk.
+pub struct SnapshotStore {
+	/// The directory holding one directory per snapshot.
+	directory: PathBuf,
+	/// The number of snapshots kept, the oldest ones are removed first.
+	keep: usize,
+}
+
+impl SnapshotStore {
+	/// Create a store in `directory`, keeping the `keep` most recent snapshots.
+	pub fn new(directory: PathBuf, keep: usize) -> Self {
+		Self { directory, keep: keep.max(1) }
+	}
+
+	/// Create a store in the directory of the database at `source`.
+	///
+	/// Returns `None` if the database isn't stored on disk.
+	pub fn in_database(source: &DatabaseSource, keep: usize) -> Option<Self> {
+		Some(Self::new(database_directory(source)?.join(SNAPSHOTS_DIRECTORY_NAME), keep))
+	}
+
+	/// Export the snapshot of the state of the finalized block `hash`, unless it was already.
+	///
+	/// This reads the whole state of the block, which is kept from being pruned until the export
+	/// is done.
+	pub fn export<B: BlockT, BE: Backend<B>>(
+		&self,
+		backend: &BE,
+		hash: B::Hash,
+	) -> sp_blockchain::Result<()> {
+		let header = backend
+			.blockchain()
+			.header(hash)?
+			.ok_or_else(|| sp_blockchain::Error::UnknownBlock(format!("{:?}", hash)))?;
+		let name = snapshot_name::<B>(*header.number(), &hash);
+		let path = self.directory.join(&name);
+		if path.is_dir() {
+			return Ok(())
+		}
+
+		debug!(target: "db", "Exporting the snapshot of block {:?}", hash);
+		let state_root = *header.state_root();
+		let state = backend.state_at(hash)?;
+		let tmp_path = self.directory.join(format!("{}.tmp", name));
+		let export = || -> sp_blockchain::Result<SnapshotManifest<B::Hash>> {
+			if tmp_path.exists() {
+				fs::remove_dir_all(&tmp_path).map_err(io_error)?;
+			}
+			fs::create_dir_all(&tmp_path).map_err(io_error)?;
+
+			let mut chunks = Vec::new();
+			let mut cursor = SmallVec::<[Vec<u8>; 2]>::new();
+			loop {
+				let (proof, _) = prove_range_read_with_child_with_size_on_trie_backend(
+					state.as_trie_backend(),
+					CHUNK_SIZE,
+					cursor.as_slice(),
+				)?;
+				// The next chunk starts after the last key of this one, which is where
+				// verification stops.
+				let (values, completed) = read_range_proof_check_with_child::<HashFor<B>>(
+					state_root,
+					proof.clone(),
+					cursor.as_slice(),
+				)?;
+				let chunk = proof
+					.into_compact_proof::<HashFor<B>>(state_root)
+					.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))?
+					.encode();
+				let chunk_hash = blake2_256(&chunk);
+				fs::write(tmp_path.join(array_bytes::bytes2hex("", chunk_hash)), &chunk)
+					.map_err(io_error)?;
+				chunks.push(ChunkInfo { hash: chunk_hash, start: cursor.to_vec() });
+
+				if completed == 0 {
+					break
+				}
+				let start = cursor.clone();
+				if !values.update_last_key(completed, &mut cursor) || cursor <= start {
+					return Err(sp_blockchain::Error::Backend(format!(
+						"Failed to advance snapshot cursor past {:?}",
+						start,
+					)))
+				}
+			}
+
+			let manifest = SnapshotManifest { block: hash, state_root, chunks };
+			fs::write(tmp_path.join(MANIFEST_FILE_NAME), manifest.encode()).map_err(io_error)?;
+			fs::rename(&tmp_path, &path).map_err(io_error)?;
+			Ok(manifest)
+		};
+
+		match export() {
+			Ok(manifest) => info!(
+				target: "db",
+				"📸 Exported the snapshot of block #{} ({}) in {} chunks",
+				header.number(),
+				hash,
+				manifest.chunks.len(),
+			),
+			Err(e) => {
+				let _ = fs::remove_dir_all(&tmp_path);
+				return Err(e)
+			},
+		}
+		self.prune();
+		Ok(())
+	}
+
+	/// Remove the oldest snapshots, keeping `self.keep` of them.
+	fn prune(&self) {
+		let mut snapshots = match self.snapshots() {
+			Ok(snapshots) => snapshots,
+			Err(e) => {
+				warn!(target: "db", "Failed to list snapshots: {}", e);
+				return
+			},
+		};
+		snapshots.sort_by_key(|(number, _)| *number);
+		let stale = snapshots.len().saturating_sub(self.keep);
+		for (_, path) in snapshots.into_iter().take(stale) {
+			match fs::remove_dir_all(&path) {
+				Ok(()) => debug!(target: "db", "Removed snapshot {}", path.display()),
+				Err(e) =>
+					warn!(target: "db", "Failed to remove snapshot {}: {}", path.display(), e),
+			}
+		}
+	}
+
+	/// Returns the block number and path of the exported snapshots.
+	fn snapshots(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+		let entries = match fs::read_dir(&self.directory) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e),
+		};
+		let mut snapshots = Vec::new();
+		for entry in entries {
+			let path = entry?.path();
+			let number = path
+				.file_name()
+				.and_then(|name| name.to_str())
+				.filter(|name| !name.ends_with(".tmp"))
+				.and_then(|name| name.split_once('-'))
+				.and_then(|(number, _)| number.parse().ok());
+			if let Some(number) = number {
+				snapshots.push((number, path));
+			}
+		}
+		Ok(snapshots)
+	}
+
+	/// Returns the path of the snapshot of `block`, if it was exported.
+	fn find<B: BlockT>(&self, block: &B::Hash) -> sp_blockchain::Result<Option<PathBuf>> {
+		let suffix = format!("-{}", array_bytes::bytes2hex("", block.as_ref()));
+		let snapshots = self.snapshots().map_err(io_error)?;
+		Ok(snapshots.into_iter().map(|(_, path)| path).find(|path| {
+			path.file_name()
+				.and_then(|name| name.to_str())
+				.map_or(false, |name| name.ends_with(&suffix))
+		}))
+	}
+}
+
+impl<B: BlockT> SnapshotProvider<B> for SnapshotStore {
+	fn snapshot_manifest(
+		&self,
+		block: B::Hash,
+	) -> sp_blockchain::Result<Option<SnapshotManifest<B::Hash>>> {
+		let Some(path) = self.find::<B>(&block)? else { return Ok(None) };
+		let Some(encoded) = read(&path.join(MANIFEST_FILE_NAME))? else { return Ok(None) };
+		let manifest = SnapshotManifest::decode(&mut &encoded[..]).map_err(|e| {
+			sp_blockchain::Error::Backend(format!("Invalid snapshot manifest: {}", e))
+		})?;
+		Ok(Some(manifest))
+	}
+
+	fn snapshot_chunk(
+		&self,
+		block: B::Hash,
+		hash: ChunkHash,
+	) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		let Some(path) = self.find::<B>(&block)? else { return Ok(None) };
+		let Some(chunk) = read(&path.join(array_bytes::bytes2hex("", hash)))? else {
+			return Ok(None)
+		};
+		if blake2_256(&chunk) != hash {
+			return Err(sp_blockchain::Error::Backend(format!(
+				"Snapshot chunk {} doesn't match its hash",
+				path.display(),
+			)))
+		}
+		Ok(Some(chunk))
+	}
+}
+
+/// Name of the directory of the snapshot of block `hash`, with the given number.
+fn snapshot_name<B: BlockT>(number: <B::Header as HeaderT>::Number, hash: &B::Hash) -> String {
+	let number: u64 = number.unique_saturated_into();
+	format!("{}-{}", number, array_bytes::bytes2hex("", hash.as_ref()))
+}
+
+/// Read the file at `path`, if it exists.
+fn read(path: &Path) -> sp_blockchain::Result<Option<Vec<u8>>> {
+	match fs::read(path) {
+		Ok(content) => Ok(Some(content)),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+		Err(e) => Err(io_error(e)),
+	}
+}
+
+fn io_error(e: io::Error) -> sp_blockchain::Error {
+	sp_blockchain::Error::Backend(format!("Snapshot I/O error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{insert_header, Block};
+	use sc_client_api::{
+		backend::{BlockImportOperation, NewBlockState},
+		CompactProof,
+	};
+	use sp_runtime::{testing::Header, traits::BlakeTwo256, StateVersion, Storage};
+
+	#[test]
+	fn exported_snapshots_cover_the_whole_state() {
+		let backend = crate::Backend::<Block>::new_test(2, 0);
+		// Large enough to take a few chunks.
+		let storage = Storage {
+			top: (0u32..40_000)
+				.map(|i| (blake2_256(&i.encode()).to_vec(), vec![0x42; 512]))
+				.collect(),
+			children_default: Default::default(),
+		};
+		let mut op = backend.begin_operation().unwrap();
+		let state_root = op.reset_storage(storage, StateVersion::V1).unwrap();
+		let header = Header {
+			number: 0,
+			parent_hash: Default::default(),
+			state_root,
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		let hash = header.hash();
+		op.set_block_data(header, Some(Vec::new()), None, None, NewBlockState::Final)
+			.unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let directory = tempfile::tempdir().unwrap();
+		let store = SnapshotStore::new(directory.path().to_path_buf(), 1);
+		store.export(&backend, hash).unwrap();
+
+		let manifest = SnapshotProvider::<Block>::snapshot_manifest(&store, hash).unwrap().unwrap();
+		assert!(manifest.chunks.len() > 1);
+		assert!(manifest.is_valid_for(&hash, &state_root));
+
+		let mut keys = 0;
+		for (index, info) in manifest.chunks.iter().enumerate() {
+			let chunk = SnapshotProvider::<Block>::snapshot_chunk(&store, hash, info.hash)
+				.unwrap()
+				.unwrap();
+			let (proof, _) = CompactProof::decode(&mut &chunk[..])
+				.unwrap()
+				.to_storage_proof::<BlakeTwo256>(Some(&state_root))
+				.unwrap();
+			let (values, completed) =
+				read_range_proof_check_with_child::<BlakeTwo256>(state_root, proof, &info.start)
+					.unwrap();
+			keys += values.0[0].key_values.len();
+			match manifest.chunks.get(index + 1) {
+				Some(next) => {
+					let mut cursor = SmallVec::from_vec(info.start.clone());
+					assert!(values.update_last_key(completed, &mut cursor));
+					assert_eq!(cursor.as_slice(), next.start.as_slice());
+				},
+				None => assert_eq!(completed, 0),
+			}
+		}
+		assert_eq!(keys, 40_000);
+		assert!(SnapshotProvider::<Block>::snapshot_chunk(&store, hash, [0; 32])
+			.unwrap()
+			.is_none());
+
+		// Only the most recent snapshot is kept.
+		let next = insert_header(&backend, 1, hash, None, Default::default());
+		backend.finalize_block(next, None).unwrap();
+		store.export(&backend, next).unwrap();
+		assert!(SnapshotProvider::<Block>::snapshot_manifest(&store, hash).unwrap().is_none());
+		assert!(SnapshotProvider::<Block>::snapshot_manifest(&store, next).unwrap().is_some());
+	}
+}
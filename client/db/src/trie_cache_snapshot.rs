@@ -0,0 +1,95 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of the trie cache across restarts.
+
+use crate::{utils::database_directory, DatabaseSource};
+use codec::Codec;
+use hash_db::Hasher;
+use log::{debug, info, warn};
+use sp_trie::cache::{SharedTrieCache, SnapshotError};
+use std::{fs, io, path::PathBuf};
+
+/// The name of the snapshot file in the database directory.
+const SNAPSHOT_FILE_NAME: &str = "trie_cache_snapshot";
+
+/// A snapshot of the trie cache stored next to the database.
+pub(crate) struct TrieCacheSnapshot {
+	/// The path of the snapshot file.
+	path: PathBuf,
+	/// The maximum size of the snapshot in bytes.
+	maximum_size: usize,
+}
+
+impl TrieCacheSnapshot {
+	/// Create a snapshot stored in the directory of the database at `source`.
+	///
+	/// Returns `None` if the database isn't stored on disk.
+	pub fn new(source: &DatabaseSource, maximum_size: usize) -> Option<Self> {
+		let directory = database_directory(source)?;
+		Some(Self { path: directory.join(SNAPSHOT_FILE_NAME), maximum_size })
+	}
+
+	/// Restore the snapshot into `cache`, if it was written at `state_root`.
+	pub fn restore<H: Hasher>(&self, cache: &SharedTrieCache<H>, state_root: &H::Out)
+	where
+		H::Out: Codec,
+	{
+		let snapshot = match fs::read(&self.path) {
+			Ok(snapshot) => snapshot,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+			Err(e) => {
+				warn!(target: "db", "Failed to read the trie cache snapshot: {}", e);
+				return
+			},
+		};
+
+		match cache.restore_snapshot(state_root, &snapshot) {
+			Ok(()) => info!(
+				target: "db",
+				"Restored the trie cache from a snapshot of {} bytes",
+				snapshot.len(),
+			),
+			Err(SnapshotError::StateRootMismatch) => debug!(
+				target: "db",
+				"Ignoring the trie cache snapshot taken at another best block",
+			),
+			Err(e) => warn!(target: "db", "Failed to restore the trie cache snapshot: {}", e),
+		}
+	}
+
+	/// Write a snapshot of `cache` taken at `state_root`, replacing the previous one.
+	pub fn write<H: Hasher>(&self, cache: &SharedTrieCache<H>, state_root: &H::Out)
+	where
+		H::Out: Codec,
+	{
+		let snapshot = cache.snapshot(state_root, self.maximum_size);
+
+		// Write to a temporary file first to never leave a truncated snapshot behind.
+		let tmp_path = self.path.with_extension("tmp");
+		match fs::write(&tmp_path, &snapshot).and_then(|()| fs::rename(&tmp_path, &self.path)) {
+			Ok(()) => debug!(
+				target: "db",
+				"Wrote a trie cache snapshot of {} bytes to {}",
+				snapshot.len(),
+				self.path.display(),
+			),
+			Err(e) => warn!(target: "db", "Failed to write the trie cache snapshot: {}", e),
+		}
+	}
+}
@@ -216,6 +216,71 @@ fn open_database_at<Block: BlockT>(
 	Ok(db)
 }
 
+/// Opens an existing database without creating, upgrading or migrating it, e.g. to inspect it
+/// offline.
+///
+/// Databases are opened read-only unless `writable` is set. RocksDB databases are then opened as
+/// a secondary instance, which keeps its log in the `secondary` directory of the database.
+pub fn open_existing_database(
+	db_source: &DatabaseSource,
+	db_type: DatabaseType,
+	writable: bool,
+) -> OpenDbResult {
+	let db: Arc<dyn Database<DbHash>> = match &db_source {
+		DatabaseSource::ParityDb { path } =>
+			crate::parity_db::open_existing(path, db_type, !writable)?,
+		#[cfg(feature = "rocksdb")]
+		DatabaseSource::RocksDb { path, cache_size } =>
+			open_existing_kvdb_rocksdb(path, *cache_size, writable)?,
+		DatabaseSource::Custom { db, .. } => db.clone(),
+		DatabaseSource::Auto { paritydb_path, rocksdb_path, cache_size } =>
+			match open_existing_kvdb_rocksdb(rocksdb_path, *cache_size, writable) {
+				Ok(db) => db,
+				Err(OpenDbError::NotEnabled(_)) | Err(OpenDbError::DoesNotExist) =>
+					crate::parity_db::open_existing(paritydb_path, db_type, !writable)?,
+				Err(as_is) => return Err(as_is),
+			},
+	};
+
+	match db.get(COLUMN_META, meta_keys::TYPE) {
+		Some(stored_type) if db_type.as_str().as_bytes() == &*stored_type => Ok(db),
+		Some(stored_type) =>
+			Err(OpenDbError::UnexpectedDbType { expected: db_type, found: stored_type }),
+		None => Err(OpenDbError::DoesNotExist),
+	}
+}
+
+#[cfg(any(feature = "rocksdb", test))]
+fn open_existing_kvdb_rocksdb(path: &Path, cache_size: usize, writable: bool) -> OpenDbResult {
+	match crate::upgrade::current_version(path) {
+		Ok(version) if version == crate::upgrade::CURRENT_VERSION => (),
+		Ok(version) =>
+			return Err(OpenDbError::Internal(format!(
+				"Database version {} needs to be upgraded by starting the node",
+				version
+			))),
+		Err(crate::upgrade::UpgradeError::MissingDatabaseVersionFile) =>
+			return Err(OpenDbError::DoesNotExist),
+		Err(err) => return Err(OpenDbError::Internal(err.to_string())),
+	}
+
+	let mut db_config = kvdb_rocksdb::DatabaseConfig::with_columns(NUM_COLUMNS);
+	db_config.create_if_missing = false;
+	db_config.memory_budget =
+		(0..NUM_COLUMNS).map(|i| (i, cache_size / NUM_COLUMNS as usize)).collect();
+	if !writable {
+		db_config.secondary = Some(path.join("secondary"));
+	}
+
+	let db = kvdb_rocksdb::Database::open(&db_config, path)?;
+	Ok(sp_database::as_database(db))
+}
+
+#[cfg(not(any(feature = "rocksdb", test)))]
+fn open_existing_kvdb_rocksdb(_path: &Path, _cache_size: usize, _writable: bool) -> OpenDbResult {
+	Err(OpenDbError::NotEnabled("with-kvdb-rocksdb"))
+}
+
 #[derive(Debug)]
 pub enum OpenDbError {
 	// constructed only when rocksdb and paritydb are disabled
@@ -353,6 +418,20 @@ fn open_kvdb_rocksdb<Block: BlockT>(
 	Err(OpenDbError::NotEnabled("with-kvdb-rocksdb"))
 }
 
+/// Returns the directory of the database at `source`, if it is stored on disk.
+pub(crate) fn database_directory(source: &DatabaseSource) -> Option<&Path> {
+	match source {
+		// Same order as when opening the database: RocksDB is used if it exists.
+		DatabaseSource::Auto { paritydb_path, rocksdb_path, .. } =>
+			if rocksdb_path.is_dir() {
+				Some(rocksdb_path)
+			} else {
+				Some(paritydb_path)
+			},
+		source => source.path(),
+	}
+}
+
 /// Check database type.
 pub fn check_database_type(
 	db: &dyn Database<DbHash>,
@@ -782,6 +861,27 @@ mod tests {
 		}
 	}
 
+	#[cfg(feature = "rocksdb")]
+	#[test]
+	fn existing_rocksdb_database_is_opened_read_only() {
+		let db_dir = tempfile::TempDir::new().unwrap();
+		let source =
+			DatabaseSource::RocksDb { path: db_dir.path().join("rocksdb_path"), cache_size: 128 };
+		drop(open_database::<Block>(&source, DatabaseType::Full, true).unwrap());
+
+		let db = open_existing_database(&source, DatabaseType::Full, false).unwrap();
+		assert!(db.get(COLUMN_META, meta_keys::TYPE).is_some());
+		let mut transaction = Transaction::new();
+		transaction.set(COLUMN_META, b"key", b"value");
+		assert!(db.commit(transaction).is_err());
+		drop(db);
+
+		let db = open_existing_database(&source, DatabaseType::Full, true).unwrap();
+		let mut transaction = Transaction::new();
+		transaction.set(COLUMN_META, b"key", b"value");
+		assert!(db.commit(transaction).is_ok());
+	}
+
 	#[cfg(feature = "rocksdb")]
 	#[test]
 	fn test_open_database_paritydb_new() {
@@ -21,7 +21,7 @@ use codec::Encode;
 
 use sc_executor_common::{
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{WasmInstance, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY},
+	wasm_runtime::{AllocatorKind, WasmInstance, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY},
 };
 use sc_executor_wasmtime::InstantiationStrategy;
 use sc_runtime_test::wasm_binary_unwrap as test_runtime;
@@ -57,6 +57,7 @@ fn initialize(
 		Method::Interpreted => sc_executor_wasmi::create_runtime(
 			blob,
 			DEFAULT_HEAP_ALLOC_STRATEGY,
+			AllocatorKind::default(),
 			host_functions,
 			allow_missing_func_imports,
 		)
@@ -75,7 +76,10 @@ fn initialize(
 					wasm_bulk_memory: false,
 					wasm_reference_types: false,
 					wasm_simd: false,
+					resource_limits: None,
+					allocator: AllocatorKind::default(),
 				},
+				host_function_profiler: None,
 			};
 
 			if precompile {
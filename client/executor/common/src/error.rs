@@ -107,6 +107,9 @@ pub enum Error {
 
 	#[error("Output exceeds bounds of wasm memory")]
 	OutputExceedsBounds,
+
+	#[error("Execution exceeded its {0} limit")]
+	ResourceLimitExceeded(&'static str),
 }
 
 impl wasmi::HostError for Error {}
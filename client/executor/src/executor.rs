@@ -29,14 +29,19 @@ use std::{
 	sync::Arc,
 };
 
-use codec::Encode;
+use codec::{Decode, Encode};
 use sc_executor_common::{
 	runtime_blob::RuntimeBlob,
 	wasm_runtime::{
-		AllocationStats, HeapAllocStrategy, WasmInstance, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY,
+		AllocationStats, AllocatorKind, HeapAllocStrategy, WasmInstance, WasmModule,
+		DEFAULT_HEAP_ALLOC_STRATEGY,
 	},
 };
-use sp_core::traits::{CallContext, CodeExecutor, Externalities, RuntimeCode};
+use sc_executor_wasmtime::{HostFunctionProfiler, ResourceLimits};
+use sp_core::{
+	storage::well_known_keys,
+	traits::{CallContext, CodeExecutor, Externalities, RuntimeCode},
+};
 use sp_version::{GetNativeVersion, NativeVersion, RuntimeVersion};
 use sp_wasm_interface::{ExtendedHostFunctions, HostFunctions};
 
@@ -92,6 +97,9 @@ pub struct WasmExecutorBuilder<H> {
 	cache_path: Option<PathBuf>,
 	allow_missing_host_functions: bool,
 	runtime_cache_size: u8,
+	untrusted_call_limits: Option<ResourceLimits>,
+	offchain_allocator: AllocatorKind,
+	host_function_profiler: Option<HostFunctionProfiler>,
 }
 
 impl<H> WasmExecutorBuilder<H> {
@@ -108,6 +116,9 @@ impl<H> WasmExecutorBuilder<H> {
 			runtime_cache_size: 4,
 			allow_missing_host_functions: false,
 			cache_path: None,
+			untrusted_call_limits: Some(ResourceLimits::default()),
+			offchain_allocator: AllocatorKind::default(),
+			host_function_profiler: None,
 		}
 	}
 
@@ -176,6 +187,9 @@ impl<H> WasmExecutorBuilder<H> {
 	///
 	/// Defines the number of different runtimes/instantiated wasm blobs the cache stores.
 	/// Runtimes/wasm blobs are differentiated based on the hash and the number of heap pages.
+	/// The runtimes created for calls with resource limits or a non-default offchain allocator
+	/// are stored in a second cache of the same size, such that they don't evict the runtimes
+	/// used to execute blocks.
 	///
 	/// By default this value is set to `4`.
 	pub fn with_runtime_cache_size(mut self, runtime_cache_size: u8) -> Self {
@@ -183,6 +197,47 @@ impl<H> WasmExecutorBuilder<H> {
 		self
 	}
 
+	/// Create the wasm executor with the given resource `limits` for untrusted runtime calls.
+	///
+	/// Calls made in the [`CallContext::Untrusted`] context are aborted with
+	/// [`Error::ResourceLimitExceeded`] once they exceed the `limits`. `None` doesn't limit them.
+	/// The limits are only enforced by the compiled execution method.
+	///
+	/// By default the limits are [`ResourceLimits::default`].
+	pub fn with_untrusted_call_limits(mut self, limits: Option<ResourceLimits>) -> Self {
+		self.untrusted_call_limits = limits;
+		self
+	}
+
+	/// Create the wasm executor with the given heap `allocator` for offchain runtime calls.
+	///
+	/// The `allocator` serves the allocations the runtime requests from the host in the
+	/// [`CallContext::Offchain`] and [`CallContext::Untrusted`] contexts. Calls made in the
+	/// [`CallContext::Onchain`] context always use [`AllocatorKind::FreeingBump`]: whether a
+	/// block runs out of memory depends on the allocator, so it is part of the consensus rules
+	/// and every node importing blocks has to use the same one.
+	///
+	/// A non-default allocator makes the executor create every runtime a second time for the
+	/// offchain calls.
+	///
+	/// By default the allocator is [`AllocatorKind::FreeingBump`].
+	pub fn with_offchain_allocator(mut self, allocator: AllocatorKind) -> Self {
+		self.offchain_allocator = allocator;
+		self
+	}
+
+	/// Create the wasm executor which times every host function call with the given `profiler`.
+	///
+	/// The timings can be collected with
+	/// [`record_host_function_calls`](crate::record_host_function_calls). Profiling only applies
+	/// to the compiled execution method.
+	///
+	/// By default the host function calls are not profiled.
+	pub fn with_host_function_profiler(mut self, profiler: HostFunctionProfiler) -> Self {
+		self.host_function_profiler = Some(profiler);
+		self
+	}
+
 	/// Build the configured [`WasmExecutor`].
 	pub fn build(self) -> WasmExecutor<H> {
 		WasmExecutor {
@@ -197,9 +252,19 @@ impl<H> WasmExecutorBuilder<H> {
 				self.max_runtime_instances,
 				self.cache_path.clone(),
 				self.runtime_cache_size,
+				self.host_function_profiler.clone(),
+			)),
+			offchain_cache: Arc::new(RuntimeCache::new(
+				self.max_runtime_instances,
+				self.cache_path.clone(),
+				self.runtime_cache_size,
+				self.host_function_profiler.clone(),
 			)),
 			cache_path: self.cache_path,
 			allow_missing_host_functions: self.allow_missing_host_functions,
+			untrusted_call_limits: self.untrusted_call_limits,
+			offchain_allocator: self.offchain_allocator,
+			host_function_profiler: self.host_function_profiler,
 			phantom: PhantomData,
 		}
 	}
@@ -216,11 +281,22 @@ pub struct WasmExecutor<H> {
 	default_offchain_heap_alloc_strategy: HeapAllocStrategy,
 	/// WASM runtime cache.
 	cache: Arc<RuntimeCache>,
+	/// Cache of the runtimes created for calls with resource limits or a non-default allocator.
+	///
+	/// Kept apart from `cache` such that offchain calls, e.g. made over RPC, can't evict the
+	/// runtimes used to execute blocks.
+	offchain_cache: Arc<RuntimeCache>,
 	/// The path to a directory which the executor can leverage for a file cache, e.g. put there
 	/// compiled artifacts.
 	cache_path: Option<PathBuf>,
 	/// Ignore missing function imports.
 	allow_missing_host_functions: bool,
+	/// The resource limits of untrusted Wasm calls.
+	untrusted_call_limits: Option<ResourceLimits>,
+	/// The allocator managing the heap of offchain Wasm calls.
+	offchain_allocator: AllocatorKind,
+	/// Times the host function calls of Wasm calls, if any.
+	host_function_profiler: Option<HostFunctionProfiler>,
 	phantom: PhantomData<H>,
 }
 
@@ -231,8 +307,12 @@ impl<H> Clone for WasmExecutor<H> {
 			default_onchain_heap_alloc_strategy: self.default_onchain_heap_alloc_strategy,
 			default_offchain_heap_alloc_strategy: self.default_offchain_heap_alloc_strategy,
 			cache: self.cache.clone(),
+			offchain_cache: self.offchain_cache.clone(),
 			cache_path: self.cache_path.clone(),
 			allow_missing_host_functions: self.allow_missing_host_functions,
+			untrusted_call_limits: self.untrusted_call_limits,
+			offchain_allocator: self.offchain_allocator,
+			host_function_profiler: self.host_function_profiler.clone(),
 			phantom: self.phantom,
 		}
 	}
@@ -280,9 +360,19 @@ where
 				max_runtime_instances,
 				cache_path.clone(),
 				runtime_cache_size,
+				None,
+			)),
+			offchain_cache: Arc::new(RuntimeCache::new(
+				max_runtime_instances,
+				cache_path.clone(),
+				runtime_cache_size,
+				None,
 			)),
 			cache_path,
 			allow_missing_host_functions: false,
+			untrusted_call_limits: Some(ResourceLimits::default()),
+			offchain_allocator: AllocatorKind::default(),
+			host_function_profiler: None,
 			phantom: PhantomData,
 		}
 	}
@@ -326,11 +416,48 @@ where
 			AssertUnwindSafe<&mut dyn Externalities>,
 		) -> Result<Result<R>>,
 	{
-		match self.cache.with_instance::<H, _, _>(
+		self.with_limited_instance(
+			runtime_code,
+			ext,
+			heap_alloc_strategy,
+			None,
+			AllocatorKind::default(),
+			f,
+		)
+	}
+
+	/// Same as `with_instance`, except the calls into the instance are bounded by
+	/// `resource_limits` and their heap is managed by `allocator`.
+	fn with_limited_instance<R, F>(
+		&self,
+		runtime_code: &RuntimeCode,
+		ext: &mut dyn Externalities,
+		heap_alloc_strategy: HeapAllocStrategy,
+		resource_limits: Option<ResourceLimits>,
+		allocator: AllocatorKind,
+		f: F,
+	) -> Result<R>
+	where
+		F: FnOnce(
+			AssertUnwindSafe<&dyn WasmModule>,
+			AssertUnwindSafe<&mut dyn WasmInstance>,
+			Option<&RuntimeVersion>,
+			AssertUnwindSafe<&mut dyn Externalities>,
+		) -> Result<Result<R>>,
+	{
+		let cache = if resource_limits.is_none() && allocator == AllocatorKind::default() {
+			&self.cache
+		} else {
+			&self.offchain_cache
+		};
+
+		match cache.with_instance::<H, _, _>(
 			runtime_code,
 			ext,
 			self.method,
 			heap_alloc_strategy,
+			resource_limits,
+			allocator,
 			self.allow_missing_host_functions,
 			|module, instance, version, ext| {
 				let module = AssertUnwindSafe(module);
@@ -344,6 +471,40 @@ where
 		}
 	}
 
+	/// Precompiles the new runtime code set by an on-chain call, if any.
+	///
+	/// A runtime upgrade is enacted by the block following the one changing `:code`. Compiling
+	/// the new code in the background while that block is imported saves the next block from
+	/// compiling it. Every on-chain call is checked, whichever runtime function changed `:code`,
+	/// e.g. a block being executed, an extrinsic being applied or an upgrade authorized earlier
+	/// being enacted.
+	fn precompile_pending_upgrade(
+		&self,
+		context: CallContext,
+		ext: &mut dyn Externalities,
+		runtime_code: &RuntimeCode,
+	) {
+		if context != CallContext::Onchain {
+			return
+		}
+		let Some(code_hash) = ext.storage_hash(well_known_keys::CODE) else { return };
+		if code_hash == runtime_code.hash {
+			return
+		}
+
+		let heap_alloc_strategy = ext
+			.storage(well_known_keys::HEAP_PAGES)
+			.and_then(|pages| u64::decode(&mut &pages[..]).ok())
+			.map(|h| HeapAllocStrategy::Static { extra_pages: h as _ })
+			.unwrap_or_else(|| self.default_onchain_heap_alloc_strategy);
+		self.cache.precompile(
+			&code_hash,
+			|| ext.storage(well_known_keys::CODE),
+			self.method,
+			heap_alloc_strategy,
+		);
+	}
+
 	/// Perform a call into the given runtime.
 	///
 	/// The runtime is passed as a [`RuntimeBlob`]. The runtime will be instantiated with the
@@ -404,6 +565,8 @@ where
 		let module = crate::wasm_runtime::create_wasm_runtime_with_code::<H>(
 			self.method,
 			self.default_onchain_heap_alloc_strategy,
+			AllocatorKind::default(),
+			self.host_function_profiler.as_ref(),
 			runtime_blob,
 			allow_missing_host_functions,
 			self.cache_path.as_deref(),
@@ -491,20 +654,32 @@ where
 			.map(|h| HeapAllocStrategy::Static { extra_pages: h as _ })
 			.unwrap_or_else(|| self.default_onchain_heap_alloc_strategy);
 
-		let heap_alloc_strategy = match context {
-			CallContext::Offchain => self.default_offchain_heap_alloc_strategy,
-			CallContext::Onchain => on_chain_heap_alloc_strategy,
+		let (heap_alloc_strategy, resource_limits, allocator) = match context {
+			CallContext::Offchain =>
+				(self.default_offchain_heap_alloc_strategy, None, self.offchain_allocator),
+			CallContext::Untrusted => (
+				self.default_offchain_heap_alloc_strategy,
+				self.untrusted_call_limits,
+				self.offchain_allocator,
+			),
+			CallContext::Onchain => (on_chain_heap_alloc_strategy, None, AllocatorKind::default()),
 		};
 
-		let result = self.with_instance(
+		let result = self.with_limited_instance(
 			runtime_code,
 			ext,
 			heap_alloc_strategy,
+			resource_limits,
+			allocator,
 			|_, mut instance, _onchain_version, mut ext| {
 				with_externalities_safe(&mut **ext, move || instance.call_export(method, data))
 			},
 		);
 
+		if result.is_ok() {
+			self.precompile_pending_upgrade(context, ext, runtime_code);
+		}
+
 		(result, false)
 	}
 }
@@ -636,16 +811,26 @@ impl<D: NativeExecutionDispatch + 'static> CodeExecutor for NativeElseWasmExecut
 			.map(|h| HeapAllocStrategy::Static { extra_pages: h as _ })
 			.unwrap_or_else(|| self.wasm.default_onchain_heap_alloc_strategy);
 
-		let heap_alloc_strategy = match context {
-			CallContext::Offchain => self.wasm.default_offchain_heap_alloc_strategy,
-			CallContext::Onchain => on_chain_heap_alloc_strategy,
+		let (heap_alloc_strategy, resource_limits, allocator) = match context {
+			CallContext::Offchain =>
+				(self.wasm.default_offchain_heap_alloc_strategy, None, self.wasm.offchain_allocator),
+			CallContext::Untrusted => (
+				self.wasm.default_offchain_heap_alloc_strategy,
+				self.wasm.untrusted_call_limits,
+				self.wasm.offchain_allocator,
+			),
+			CallContext::Onchain => (on_chain_heap_alloc_strategy, None, AllocatorKind::default()),
 		};
+		// Native calls can't be bounded.
+		let use_native = use_native && context != CallContext::Untrusted;
 
 		let mut used_native = false;
-		let result = self.wasm.with_instance(
+		let result = self.wasm.with_limited_instance(
 			runtime_code,
 			ext,
 			heap_alloc_strategy,
+			resource_limits,
+			allocator,
 			|_, mut instance, onchain_version, mut ext| {
 				let onchain_version =
 					onchain_version.ok_or_else(|| Error::ApiError("Unknown version".into()))?;
@@ -678,6 +863,11 @@ impl<D: NativeExecutionDispatch + 'static> CodeExecutor for NativeElseWasmExecut
 				}
 			},
 		);
+
+		if result.is_ok() {
+			self.wasm.precompile_pending_upgrade(context, ext, runtime_code);
+		}
+
 		(result, used_native)
 	}
 }
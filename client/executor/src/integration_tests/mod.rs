@@ -24,7 +24,7 @@ use codec::{Decode, Encode};
 use sc_executor_common::{
 	error::Error,
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{HeapAllocStrategy, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY},
+	wasm_runtime::{AllocatorKind, HeapAllocStrategy, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY},
 };
 use sc_runtime_test::wasm_binary_unwrap;
 use sp_core::{
@@ -480,6 +480,35 @@ fn should_trap_when_heap_exhausted(wasm_method: WasmExecutionMethod) {
 	}
 }
 
+test_wasm_execution!(size_class_allocator_should_use_less_memory);
+fn size_class_allocator_should_use_less_memory(wasm_method: WasmExecutionMethod) {
+	let allocate_vec = |allocator| {
+		let runtime = crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
+			wasm_method,
+			DEFAULT_HEAP_ALLOC_STRATEGY,
+			allocator,
+			None,
+			RuntimeBlob::uncompress_if_needed(wasm_binary_unwrap()).unwrap(),
+			true,
+			None,
+		)
+		.unwrap();
+
+		let (result, stats) = runtime
+			.new_instance()
+			.unwrap()
+			.call_with_allocation_stats("test_allocate_vec".into(), &5000_u32.encode());
+		result.unwrap();
+		stats.unwrap()
+	};
+
+	let freeing_bump = allocate_vec(AllocatorKind::FreeingBump);
+	let size_class = allocate_vec(AllocatorKind::SizeClass);
+
+	assert!(size_class.bytes_allocated_peak < freeing_bump.bytes_allocated_peak);
+	assert!(size_class.address_space_used < freeing_bump.address_space_used);
+}
+
 fn mk_test_runtime(
 	wasm_method: WasmExecutionMethod,
 	pages: HeapAllocStrategy,
@@ -490,6 +519,8 @@ fn mk_test_runtime(
 	crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
 		wasm_method,
 		pages,
+		AllocatorKind::default(),
+		None,
 		blob,
 		true,
 		None,
@@ -735,6 +766,8 @@ fn memory_is_cleared_between_invocations(wasm_method: WasmExecutionMethod) {
 	let runtime = crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
 		wasm_method,
 		HeapAllocStrategy::Dynamic { maximum_pages: Some(1024) },
+		AllocatorKind::default(),
+		None,
 		RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
 		true,
 		None,
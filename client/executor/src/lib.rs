@@ -53,9 +53,14 @@ pub use wasmi;
 
 pub use sc_executor_common::{
 	error,
-	wasm_runtime::{HeapAllocStrategy, DEFAULT_HEAP_ALLOC_PAGES, DEFAULT_HEAP_ALLOC_STRATEGY},
+	wasm_runtime::{
+		AllocatorKind, HeapAllocStrategy, DEFAULT_HEAP_ALLOC_PAGES, DEFAULT_HEAP_ALLOC_STRATEGY,
+	},
+};
+pub use sc_executor_wasmtime::{
+	record_host_function_calls, HostFunctionProfiler, HostFunctionStats,
+	InstantiationStrategy as WasmtimeInstantiationStrategy, ResourceLimits,
 };
-pub use sc_executor_wasmtime::InstantiationStrategy as WasmtimeInstantiationStrategy;
 
 /// Extracts the runtime version of a given runtime code.
 pub trait RuntimeVersionOf {
@@ -24,22 +24,43 @@
 use crate::error::{Error, WasmError};
 use codec::Decode;
 use lru::LruCache;
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
 use sc_executor_common::{
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{HeapAllocStrategy, WasmInstance, WasmModule},
+	wasm_runtime::{AllocatorKind, HeapAllocStrategy, WasmInstance, WasmModule},
+};
+use sc_executor_wasmtime::{
+	HostFunctionProfiler, InstantiationStrategy, ResourceLimits, Semantics,
+};
+use sp_core::{
+	hashing::twox_64,
+	hexdisplay::HexDisplay,
+	traits::{Externalities, FetchRuntimeCode, RuntimeCode},
 };
-use sp_core::traits::{Externalities, FetchRuntimeCode, RuntimeCode};
 use sp_version::RuntimeVersion;
 use std::{
+	collections::HashSet,
+	fs,
 	num::NonZeroUsize,
 	panic::AssertUnwindSafe,
 	path::{Path, PathBuf},
-	sync::Arc,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{Duration, Instant},
 };
 
 use sp_wasm_interface::HostFunctions;
 
+/// The maximum number of artifacts kept in the cache path, the least recently written ones are
+/// removed first.
+const MAX_ARTIFACTS: usize = 16;
+
+/// The age after which temporary files left in the artifacts directory, e.g. by a crash while
+/// writing an artifact, are removed.
+const STALE_TMP_FILE_AGE: Duration = Duration::from_secs(60 * 60);
+
 /// Specification of different methods of executing the runtime Wasm code.
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum WasmExecutionMethod {
@@ -66,6 +87,10 @@ struct VersionedRuntimeId {
 	wasm_method: WasmExecutionMethod,
 	/// The heap allocation strategy this runtime was created with.
 	heap_alloc_strategy: HeapAllocStrategy,
+	/// The resource limits of the calls into this runtime.
+	resource_limits: Option<ResourceLimits>,
+	/// The allocator managing the heap of this runtime.
+	allocator: AllocatorKind,
 }
 
 /// A Wasm runtime object along with its cached runtime version.
@@ -167,6 +192,39 @@ pub struct RuntimeCache {
 	/// The size of the instances cache for each runtime.
 	max_runtime_instances: usize,
 	cache_path: Option<PathBuf>,
+	/// Times the host function calls of the runtimes, if any.
+	host_function_profiler: Option<HostFunctionProfiler>,
+	/// Artifacts being written by a background precompilation.
+	precompiling: Arc<Precompilations>,
+}
+
+/// The artifacts being written by background precompilations.
+#[derive(Default)]
+struct Precompilations {
+	in_progress: Mutex<HashSet<PathBuf>>,
+	finished: Condvar,
+}
+
+impl Precompilations {
+	/// Marks the precompilation of the artifact at `path` as started, returns `false` if it
+	/// already is in progress.
+	fn start(&self, path: &Path) -> bool {
+		self.in_progress.lock().insert(path.to_path_buf())
+	}
+
+	/// Marks the precompilation of the artifact at `path` as finished.
+	fn finish(&self, path: &Path) {
+		self.in_progress.lock().remove(path);
+		self.finished.notify_all();
+	}
+
+	/// Blocks until the artifact at `path` isn't being precompiled anymore.
+	fn wait(&self, path: &Path) {
+		let mut in_progress = self.in_progress.lock();
+		while in_progress.contains(path) {
+			self.finished.wait(&mut in_progress);
+		}
+	}
 }
 
 impl RuntimeCache {
@@ -180,14 +238,24 @@ impl RuntimeCache {
 	///
 	/// `runtime_cache_size` specifies the number of different runtimes versions preserved in an
 	/// in-memory cache, must always be at least 1.
+	///
+	/// `host_function_profiler` times the host function calls of the runtimes, if any. It only
+	/// applies to the compiled execution method.
 	pub fn new(
 		max_runtime_instances: usize,
 		cache_path: Option<PathBuf>,
 		runtime_cache_size: u8,
+		host_function_profiler: Option<HostFunctionProfiler>,
 	) -> RuntimeCache {
 		let cap =
 			NonZeroUsize::new(runtime_cache_size.max(1) as usize).expect("cache size is not zero");
-		RuntimeCache { runtimes: Mutex::new(LruCache::new(cap)), max_runtime_instances, cache_path }
+		RuntimeCache {
+			runtimes: Mutex::new(LruCache::new(cap)),
+			max_runtime_instances,
+			cache_path,
+			host_function_profiler,
+			precompiling: Default::default(),
+		}
 	}
 
 	/// Prepares a WASM module instance and executes given function for it.
@@ -216,12 +284,20 @@ impl RuntimeCache {
 	///
 	/// `Error::InvalidMemoryReference` is returned if no memory export with the
 	/// identifier `memory` can be found in the runtime.
+	///
+	/// `resource_limits` bound the calls into the runtime. They are ignored by the interpreted
+	/// execution method.
+	///
+	/// `allocator` manages the heap of the calls into the runtime. Runtimes are cached per
+	/// allocator, such that using a non-default allocator creates the runtime another time.
 	pub fn with_instance<'c, H, R, F>(
 		&self,
 		runtime_code: &'c RuntimeCode<'c>,
 		ext: &mut dyn Externalities,
 		wasm_method: WasmExecutionMethod,
 		heap_alloc_strategy: HeapAllocStrategy,
+		resource_limits: Option<ResourceLimits>,
+		allocator: AllocatorKind,
 		allow_missing_func_imports: bool,
 		f: F,
 	) -> Result<Result<R, Error>, Error>
@@ -236,8 +312,13 @@ impl RuntimeCache {
 	{
 		let code_hash = &runtime_code.hash;
 
-		let versioned_runtime_id =
-			VersionedRuntimeId { code_hash: code_hash.clone(), heap_alloc_strategy, wasm_method };
+		let versioned_runtime_id = VersionedRuntimeId {
+			code_hash: code_hash.clone(),
+			heap_alloc_strategy,
+			wasm_method,
+			resource_limits,
+			allocator,
+		};
 
 		let mut runtimes = self.runtimes.lock(); // this must be released prior to calling f
 		let versioned_runtime = if let Some(versioned_runtime) = runtimes.get(&versioned_runtime_id)
@@ -246,16 +327,31 @@ impl RuntimeCache {
 		} else {
 			let code = runtime_code.fetch_runtime_code().ok_or(WasmError::CodeNotFound)?;
 
-			let time = std::time::Instant::now();
+			let time = Instant::now();
 
+			let artifact_path = artifact_path(
+				self.cache_path.as_deref(),
+				code_hash,
+				wasm_method,
+				heap_alloc_strategy,
+				resource_limits,
+			);
+			// Compiling the runtime again while its artifact is being written would be wasted.
+			if let Some(path) = artifact_path.as_deref() {
+				self.precompiling.wait(path);
+			}
 			let result = create_versioned_wasm_runtime::<H>(
 				&code,
 				ext,
 				wasm_method,
 				heap_alloc_strategy,
+				resource_limits,
+				allocator,
+				self.host_function_profiler.as_ref(),
 				allow_missing_func_imports,
 				self.max_runtime_instances,
 				self.cache_path.as_deref(),
+				artifact_path.as_deref(),
 			);
 
 			match result {
@@ -285,12 +381,234 @@ impl RuntimeCache {
 
 		Ok(versioned_runtime.with_instance(ext, f))
 	}
+
+	/// Compiles the runtime code with the given hash in a background thread and writes its
+	/// artifact to the cache path, so that the runtime doesn't have to be compiled when it is
+	/// first called.
+	///
+	/// `fetch_code` is only called if the code needs to be compiled. Nothing is done if the
+	/// artifact already exists, if there's no cache path or if `wasm_method` doesn't use
+	/// artifacts.
+	pub fn precompile(
+		&self,
+		code_hash: &[u8],
+		fetch_code: impl FnOnce() -> Option<Vec<u8>>,
+		wasm_method: WasmExecutionMethod,
+		heap_alloc_strategy: HeapAllocStrategy,
+	) {
+		let WasmExecutionMethod::Compiled { instantiation_strategy } = wasm_method else { return };
+		let Some(path) = artifact_path(
+			self.cache_path.as_deref(),
+			code_hash,
+			wasm_method,
+			heap_alloc_strategy,
+			None,
+		) else {
+			return
+		};
+		if path.exists() || !self.precompiling.start(&path) {
+			return
+		}
+		let Some(code) = fetch_code() else {
+			self.precompiling.finish(&path);
+			return
+		};
+
+		// Upgrades are precompiled for on-chain calls, which always use the default allocator.
+		let semantics = wasmtime_semantics(
+			heap_alloc_strategy,
+			instantiation_strategy,
+			None,
+			AllocatorKind::default(),
+		);
+		let precompiling = self.precompiling.clone();
+		let artifact = path.clone();
+		let spawned =
+			std::thread::Builder::new().name("runtime-precompile".into()).spawn(move || {
+				let time = Instant::now();
+				let result = RuntimeBlob::uncompress_if_needed(&code)
+					.and_then(|blob| write_artifact(blob, &semantics, &artifact));
+				match result {
+					Ok(()) => tracing::debug!(
+						target: "wasm-runtime",
+						"Precompiled runtime to {} in {} ms.",
+						artifact.display(),
+						time.elapsed().as_millis(),
+					),
+					Err(err) => tracing::warn!(
+						target: "wasm-runtime",
+						error = %err,
+						"Cannot precompile runtime",
+					),
+				}
+				precompiling.finish(&artifact);
+			});
+		if let Err(err) = spawned {
+			tracing::warn!(target: "wasm-runtime", error = %err, "Cannot spawn runtime precompilation");
+			self.precompiling.finish(&path);
+		}
+	}
+}
+
+/// Returns the path of the precompiled artifact of the runtime code with the given hash, `None`
+/// if there's no cache path or if `wasm_method` doesn't use artifacts.
+///
+/// Artifacts are stored in the `artifacts` directory of the cache path, which keeps at most
+/// [`MAX_ARTIFACTS`] of them.
+fn artifact_path(
+	cache_path: Option<&Path>,
+	code_hash: &[u8],
+	wasm_method: WasmExecutionMethod,
+	heap_alloc_strategy: HeapAllocStrategy,
+	resource_limits: Option<ResourceLimits>,
+) -> Option<PathBuf> {
+	match wasm_method {
+		// The legacy instance reuse strategy can't instantiate precompiled modules.
+		WasmExecutionMethod::Compiled { instantiation_strategy }
+			if instantiation_strategy != InstantiationStrategy::LegacyInstanceReuse =>
+		{
+			let settings = twox_64(
+				format!(
+					"{:?}{:?}{:?}",
+					instantiation_strategy, heap_alloc_strategy, resource_limits
+				)
+				.as_bytes(),
+			);
+			let file_name =
+				format!("{}-{}.bin", HexDisplay::from(&code_hash), HexDisplay::from(&settings));
+			Some(cache_path?.join("artifacts").join(file_name))
+		},
+		_ => None,
+	}
+}
+
+/// Compiles `blob` and writes its artifact to `path`, replacing any existing artifact.
+fn write_artifact(blob: RuntimeBlob, semantics: &Semantics, path: &Path) -> Result<(), WasmError> {
+	let artifact = sc_executor_wasmtime::prepare_runtime_artifact(blob, semantics)?;
+
+	// Runtimes map their artifact in memory, which must not be modified while they're alive. The
+	// artifact is written to a new file that then replaces any existing one.
+	static NEXT_TMP_FILE: AtomicU64 = AtomicU64::new(0);
+	let tmp_path = path.with_extension(format!(
+		"{}-{}.tmp",
+		std::process::id(),
+		NEXT_TMP_FILE.fetch_add(1, Ordering::Relaxed)
+	));
+	path.parent()
+		.map_or(Ok(()), fs::create_dir_all)
+		.and_then(|()| fs::write(&tmp_path, artifact))
+		.and_then(|()| fs::rename(&tmp_path, path))
+		.map_err(|e| {
+			let _ = fs::remove_file(&tmp_path);
+			WasmError::Other(format!("cannot write artifact {}: {}", path.display(), e))
+		})?;
+
+	if let Err(e) = remove_stale_artifacts(path) {
+		tracing::debug!(target: "wasm-runtime", error = %e, "Cannot remove stale artifacts");
+	}
+	Ok(())
+}
+
+/// Removes the least recently written artifacts next to the artifact at `written`, such that at
+/// most [`MAX_ARTIFACTS`] remain, as well as the temporary files older than
+/// [`STALE_TMP_FILE_AGE`]. The artifact at `written` is kept.
+///
+/// Runtimes map their artifact in memory, removing it doesn't affect the runtimes alive.
+fn remove_stale_artifacts(written: &Path) -> std::io::Result<()> {
+	let Some(dir) = written.parent() else { return Ok(()) };
+
+	let mut artifacts = Vec::new();
+	for entry in fs::read_dir(dir)? {
+		let path = entry?.path();
+		let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+			continue
+		};
+		match path.extension().and_then(|extension| extension.to_str()) {
+			Some("bin") if path != written => artifacts.push((modified, path)),
+			Some("tmp") =>
+				if modified.elapsed().map_or(false, |age| age > STALE_TMP_FILE_AGE) {
+					let _ = fs::remove_file(&path);
+				},
+			_ => {},
+		}
+	}
+
+	// The artifact at `written` is one of the artifacts kept.
+	let keep = MAX_ARTIFACTS.saturating_sub(1);
+	if artifacts.len() > keep {
+		artifacts.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+		for (_, path) in artifacts.drain(keep..) {
+			tracing::debug!(target: "wasm-runtime", "Removing stale artifact {}", path.display());
+			fs::remove_file(path)?;
+		}
+	}
+	Ok(())
+}
+
+/// Creates a wasmtime runtime from the artifact at `artifact_path`, compiling `blob` and writing
+/// the artifact first if it is missing or was produced by an incompatible version of the
+/// executor.
+fn create_runtime_with_artifact<H>(
+	blob: RuntimeBlob,
+	artifact_path: &Path,
+	config: sc_executor_wasmtime::Config,
+) -> Result<Box<dyn WasmModule>, WasmError>
+where
+	H: HostFunctions,
+{
+	if artifact_path.exists() {
+		// SAFETY: artifacts are written by `write_artifact`, which never modifies them in place.
+		match unsafe {
+			sc_executor_wasmtime::create_runtime_from_artifact::<H>(artifact_path, config.clone())
+		} {
+			Ok(runtime) => return Ok(Box::new(runtime)),
+			Err(err) => tracing::debug!(
+				target: "wasm-runtime",
+				error = %err,
+				"Recompiling unusable runtime artifact {}",
+				artifact_path.display(),
+			),
+		}
+	}
+
+	if let Err(err) = write_artifact(blob.clone(), &config.semantics, artifact_path) {
+		tracing::warn!(target: "wasm-runtime", error = %err, "Cannot write runtime artifact");
+		return sc_executor_wasmtime::create_runtime::<H>(blob, config)
+			.map(|runtime| -> Box<dyn WasmModule> { Box::new(runtime) })
+	}
+
+	// SAFETY: see above.
+	unsafe { sc_executor_wasmtime::create_runtime_from_artifact::<H>(artifact_path, config) }
+		.map(|runtime| -> Box<dyn WasmModule> { Box::new(runtime) })
+}
+
+fn wasmtime_semantics(
+	heap_alloc_strategy: HeapAllocStrategy,
+	instantiation_strategy: InstantiationStrategy,
+	resource_limits: Option<ResourceLimits>,
+	allocator: AllocatorKind,
+) -> Semantics {
+	Semantics {
+		heap_alloc_strategy,
+		instantiation_strategy,
+		resource_limits,
+		allocator,
+		deterministic_stack_limit: None,
+		canonicalize_nans: false,
+		parallel_compilation: true,
+		wasm_multi_value: false,
+		wasm_bulk_memory: false,
+		wasm_reference_types: false,
+		wasm_simd: false,
+	}
 }
 
 /// Create a wasm runtime with the given `code`.
 pub fn create_wasm_runtime_with_code<H>(
 	wasm_method: WasmExecutionMethod,
 	heap_alloc_strategy: HeapAllocStrategy,
+	allocator: AllocatorKind,
+	host_function_profiler: Option<&HostFunctionProfiler>,
 	blob: RuntimeBlob,
 	allow_missing_func_imports: bool,
 	cache_path: Option<&Path>,
@@ -305,10 +623,13 @@ where
 			// We drop the cache_path here to silence warnings that cache_path is not used if
 			// compiling without the `wasmtime` flag.
 			let _ = cache_path;
+			// Wasmi doesn't support profiling the host functions.
+			let _ = host_function_profiler;
 
 			sc_executor_wasmi::create_runtime(
 				blob,
 				heap_alloc_strategy,
+				allocator,
 				H::host_functions(),
 				allow_missing_func_imports,
 			)
@@ -320,17 +641,13 @@ where
 				sc_executor_wasmtime::Config {
 					allow_missing_func_imports,
 					cache_path: cache_path.map(ToOwned::to_owned),
-					semantics: sc_executor_wasmtime::Semantics {
+					semantics: wasmtime_semantics(
 						heap_alloc_strategy,
 						instantiation_strategy,
-						deterministic_stack_limit: None,
-						canonicalize_nans: false,
-						parallel_compilation: true,
-						wasm_multi_value: false,
-						wasm_bulk_memory: false,
-						wasm_reference_types: false,
-						wasm_simd: false,
-					},
+						None,
+						allocator,
+					),
+					host_function_profiler: host_function_profiler.cloned(),
 				},
 			)
 			.map(|runtime| -> Box<dyn WasmModule> { Box::new(runtime) }),
@@ -398,9 +715,13 @@ fn create_versioned_wasm_runtime<H>(
 	ext: &mut dyn Externalities,
 	wasm_method: WasmExecutionMethod,
 	heap_alloc_strategy: HeapAllocStrategy,
+	resource_limits: Option<ResourceLimits>,
+	allocator: AllocatorKind,
+	host_function_profiler: Option<&HostFunctionProfiler>,
 	allow_missing_func_imports: bool,
 	max_instances: usize,
 	cache_path: Option<&Path>,
+	artifact_path: Option<&Path>,
 ) -> Result<VersionedRuntime, WasmError>
 where
 	H: HostFunctions,
@@ -414,13 +735,36 @@ where
 	// runtime.
 	let mut version = read_embedded_version(&blob)?;
 
-	let runtime = create_wasm_runtime_with_code::<H>(
-		wasm_method,
-		heap_alloc_strategy,
-		blob,
-		allow_missing_func_imports,
-		cache_path,
-	)?;
+	let runtime = match wasm_method {
+		WasmExecutionMethod::Compiled { instantiation_strategy } => {
+			let config = sc_executor_wasmtime::Config {
+				allow_missing_func_imports,
+				cache_path: cache_path.map(ToOwned::to_owned),
+				semantics: wasmtime_semantics(
+					heap_alloc_strategy,
+					instantiation_strategy,
+					resource_limits,
+					allocator,
+				),
+				host_function_profiler: host_function_profiler.cloned(),
+			};
+			match artifact_path {
+				Some(artifact_path) =>
+					create_runtime_with_artifact::<H>(blob, artifact_path, config)?,
+				None => sc_executor_wasmtime::create_runtime::<H>(blob, config)
+					.map(|runtime| -> Box<dyn WasmModule> { Box::new(runtime) })?,
+			}
+		},
+		WasmExecutionMethod::Interpreted => create_wasm_runtime_with_code::<H>(
+			wasm_method,
+			heap_alloc_strategy,
+			allocator,
+			host_function_profiler,
+			blob,
+			allow_missing_func_imports,
+			cache_path,
+		)?,
+	};
 
 	// If the runtime blob doesn't embed the runtime version then use the legacy version query
 	// mechanism: call the runtime.
@@ -455,7 +799,9 @@ where
 mod tests {
 	use super::*;
 	use codec::Encode;
+	use sc_executor_common::wasm_runtime::DEFAULT_HEAP_ALLOC_STRATEGY;
 	use sp_api::{Core, RuntimeApiInfo};
+	use sp_core::traits::WrappedRuntimeCode;
 	use sp_runtime::RuntimeString;
 	use sp_wasm_interface::HostFunctions;
 	use substrate_test_runtime::Block;
@@ -541,6 +887,80 @@ mod tests {
 		assert_eq!(4, version.state_version);
 	}
 
+	#[test]
+	fn precompiled_artifact_is_used() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let code = sc_runtime_test::wasm_binary_unwrap().to_vec();
+		let code_hash = sp_core::blake2_256(&code).to_vec();
+		let wasm_method = WasmExecutionMethod::Compiled {
+			instantiation_strategy: InstantiationStrategy::RecreateInstanceCopyOnWrite,
+		};
+		let heap_alloc_strategy = DEFAULT_HEAP_ALLOC_STRATEGY;
+		let path = artifact_path(
+			Some(cache_dir.path()),
+			&code_hash,
+			wasm_method,
+			heap_alloc_strategy,
+			None,
+		)
+		.unwrap();
+
+		let cache = RuntimeCache::new(1, Some(cache_dir.path().into()), 1, None);
+		cache.precompile(&code_hash, || Some(code.clone()), wasm_method, heap_alloc_strategy);
+		cache.precompiling.wait(&path);
+		let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+		let runtime_code = RuntimeCode {
+			code_fetcher: &WrappedRuntimeCode(code.into()),
+			hash: code_hash,
+			heap_pages: None,
+		};
+		let mut ext = sp_io::TestExternalities::default();
+		let result = cache
+			.with_instance::<sp_io::SubstrateHostFunctions, _, _>(
+				&runtime_code,
+				&mut ext.ext(),
+				wasm_method,
+				heap_alloc_strategy,
+				None,
+				AllocatorKind::default(),
+				false,
+				|_, instance, _, _| instance.call_export("test_empty_return", &[]),
+			)
+			.unwrap()
+			.unwrap();
+		assert!(result.is_empty());
+
+		// The runtime was created from the artifact instead of compiling the code again.
+		assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+	}
+
+	#[test]
+	fn stale_artifacts_are_removed() {
+		let cache_dir = tempfile::tempdir().unwrap();
+		let artifact = |i: usize| cache_dir.path().join(format!("{}.bin", i));
+
+		// The artifact just written is the oldest one, e.g. it replaced an unusable artifact.
+		for i in 0..MAX_ARTIFACTS + 4 {
+			fs::write(artifact(i), []).unwrap();
+			std::thread::sleep(Duration::from_millis(5));
+		}
+		let in_progress = cache_dir.path().join("0.bin.1-0.tmp");
+		fs::write(&in_progress, []).unwrap();
+
+		remove_stale_artifacts(&artifact(0)).unwrap();
+
+		assert!(artifact(0).exists());
+		for i in 1..5 {
+			assert!(!artifact(i).exists());
+		}
+		for i in 5..MAX_ARTIFACTS + 4 {
+			assert!(artifact(i).exists());
+		}
+		// Recent temporary files may belong to an artifact being written.
+		assert!(in_progress.exists());
+	}
+
 	#[test]
 	fn embed_runtime_version_works() {
 		let wasm = sp_maybe_compressed_blob::decompress(
@@ -28,7 +28,7 @@ use wasmi::{
 	TableRef,
 };
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, Allocator, AllocatorKind, HeapAllocator};
 use sc_executor_common::{
 	error::{Error, MessageWithBacktrace, WasmError},
 	runtime_blob::{DataSegmentsSnapshot, RuntimeBlob},
@@ -73,7 +73,7 @@ impl sc_allocator::Memory for MemoryWrapper<'_> {
 }
 
 struct FunctionExecutor {
-	heap: RefCell<sc_allocator::FreeingBumpHeapAllocator>,
+	heap: RefCell<HeapAllocator>,
 	memory: MemoryRef,
 	host_functions: Arc<Vec<&'static dyn Function>>,
 	allow_missing_func_imports: bool,
@@ -85,12 +85,13 @@ impl FunctionExecutor {
 	fn new(
 		m: MemoryRef,
 		heap_base: u32,
+		allocator: AllocatorKind,
 		host_functions: Arc<Vec<&'static dyn Function>>,
 		allow_missing_func_imports: bool,
 		missing_functions: Arc<Vec<String>>,
 	) -> Result<Self, Error> {
 		Ok(FunctionExecutor {
-			heap: RefCell::new(FreeingBumpHeapAllocator::new(heap_base)),
+			heap: RefCell::new(HeapAllocator::new(allocator, heap_base)),
 			memory: m,
 			host_functions,
 			allow_missing_func_imports,
@@ -261,6 +262,7 @@ fn call_in_wasm_module(
 	memory: &MemoryRef,
 	method: InvokeMethod,
 	data: &[u8],
+	allocator: AllocatorKind,
 	host_functions: Arc<Vec<&'static dyn Function>>,
 	allow_missing_func_imports: bool,
 	missing_functions: Arc<Vec<String>>,
@@ -275,6 +277,7 @@ fn call_in_wasm_module(
 	let mut function_executor = FunctionExecutor::new(
 		memory.clone(),
 		heap_base,
+		allocator,
 		host_functions,
 		allow_missing_func_imports,
 		missing_functions,
@@ -438,6 +441,8 @@ pub struct WasmiRuntime {
 	/// Enable stub generation for functions that are not available in `host_functions`.
 	/// These stubs will error when the wasm blob tries to call them.
 	allow_missing_func_imports: bool,
+	/// The allocator managing the heap of the instances.
+	allocator: AllocatorKind,
 
 	global_vals_snapshot: GlobalValsSnapshot,
 	data_segments_snapshot: DataSegmentsSnapshot,
@@ -457,6 +462,7 @@ impl WasmModule for WasmiRuntime {
 			data_segments_snapshot: self.data_segments_snapshot.clone(),
 			host_functions: self.host_functions.clone(),
 			allow_missing_func_imports: self.allow_missing_func_imports,
+			allocator: self.allocator,
 			missing_functions: Arc::new(missing_functions),
 			memory_zeroed: true,
 		}))
@@ -465,9 +471,12 @@ impl WasmModule for WasmiRuntime {
 
 /// Create a new `WasmiRuntime` given the code. This function loads the module and
 /// stores it in the instance.
+///
+/// The heap of the instances is managed by an `allocator` of the given kind.
 pub fn create_runtime(
 	mut blob: RuntimeBlob,
 	heap_alloc_strategy: HeapAllocStrategy,
+	allocator: AllocatorKind,
 	host_functions: Vec<&'static dyn Function>,
 	allow_missing_func_imports: bool,
 ) -> Result<WasmiRuntime, WasmError> {
@@ -495,6 +504,7 @@ pub fn create_runtime(
 		global_vals_snapshot,
 		host_functions: Arc::new(host_functions),
 		allow_missing_func_imports,
+		allocator,
 	})
 }
 
@@ -515,6 +525,8 @@ pub struct WasmiInstance {
 	/// Enable stub generation for functions that are not available in `host_functions`.
 	/// These stubs will error when the wasm blob trie to call them.
 	allow_missing_func_imports: bool,
+	/// The allocator managing the heap of the instance.
+	allocator: AllocatorKind,
 	/// List of missing functions detected during function resolution
 	missing_functions: Arc<Vec<String>>,
 }
@@ -556,6 +568,7 @@ impl WasmiInstance {
 			&self.memory,
 			method,
 			data,
+			self.allocator,
 			self.host_functions.clone(),
 			self.allow_missing_func_imports,
 			self.missing_functions.clone(),
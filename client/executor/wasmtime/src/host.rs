@@ -21,7 +21,7 @@
 
 use wasmtime::Caller;
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, Allocator, HeapAllocator};
 use sp_wasm_interface::{Pointer, WordSize};
 
 use crate::{instance_wrapper::MemoryWrapper, runtime::StoreData, util};
@@ -35,13 +35,13 @@ pub struct HostState {
 	/// This is stored as an `Option` as we need to temporarly set this to `None` when we are
 	/// allocating/deallocating memory. The problem being that we can only mutable access `caller`
 	/// once.
-	allocator: Option<FreeingBumpHeapAllocator>,
+	allocator: Option<HeapAllocator>,
 	panic_message: Option<String>,
 }
 
 impl HostState {
 	/// Constructs a new `HostState`.
-	pub fn new(allocator: FreeingBumpHeapAllocator) -> Self {
+	pub fn new(allocator: HeapAllocator) -> Self {
 		HostState { allocator: Some(allocator), panic_message: None }
 	}
 
@@ -16,18 +16,25 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{host::HostContext, runtime::StoreData};
+use crate::{host::HostContext, profiler::HostFunctionProfiler, runtime::StoreData, util};
 use sc_executor_common::error::WasmError;
-use sp_wasm_interface::{FunctionContext, HostFunctions};
-use std::collections::HashMap;
-use wasmtime::{ExternType, FuncType, ImportType, Linker, Module};
+use sp_wasm_interface::{FunctionContext, HostFunctions, Signature, ValueType};
+use std::{
+	collections::HashMap,
+	panic::{catch_unwind, AssertUnwindSafe},
+	time::Instant,
+};
+use wasmtime::{ExternType, FuncType, ImportType, Linker, Module, ValType};
 
 /// Goes over all imports of a module and prepares the given linker for instantiation of the module.
 /// Returns an error if there are imports that cannot be satisfied.
+///
+/// The host functions are timed by the `profiler`, if any.
 pub(crate) fn prepare_imports<H>(
 	linker: &mut Linker<StoreData>,
 	module: &Module,
 	allow_missing_func_imports: bool,
+	profiler: Option<&HostFunctionProfiler>,
 ) -> Result<(), WasmError>
 where
 	H: HostFunctions,
@@ -58,7 +65,10 @@ where
 	}
 
 	let mut registry = Registry { linker, pending_func_imports };
-	H::register_static(&mut registry)?;
+	match profiler {
+		Some(profiler) => register_profiled::<H>(&mut registry, profiler)?,
+		None => H::register_static(&mut registry)?,
+	}
 
 	if !registry.pending_func_imports.is_empty() {
 		if allow_missing_func_imports {
@@ -87,6 +97,89 @@ where
 	Ok(())
 }
 
+/// Registers the host functions through their dynamic interface, so that every call can be
+/// timed by the `profiler`.
+fn register_profiled<H>(
+	registry: &mut Registry<'_, '_>,
+	profiler: &HostFunctionProfiler,
+) -> Result<(), WasmError>
+where
+	H: HostFunctions,
+{
+	for function in H::host_functions() {
+		let name = function.name();
+		let Some((import_ty, func_ty)) = registry.pending_func_imports.remove(name) else {
+			continue
+		};
+		if func_ty != wasmtime_func_type(&function.signature()) {
+			return Err(WasmError::Other(format!(
+				"signature mismatch for: {}:{}",
+				import_ty.module(),
+				name
+			)))
+		}
+
+		let probe = profiler.probe(name);
+		registry
+			.linker
+			.func_new("env", name, func_ty, move |caller, params, results| {
+				let start = Instant::now();
+				let result = catch_unwind(AssertUnwindSafe(|| {
+					let mut args = params.iter().cloned().map(util::from_wasmtime_val);
+					function.execute(&mut HostContext { caller }, &mut args)
+				}));
+				probe.report(start.elapsed());
+
+				match result {
+					Ok(Ok(value)) => {
+						if let (Some(value), Some(result)) = (value, results.first_mut()) {
+							*result = util::into_wasmtime_val(value);
+						}
+						Ok(())
+					},
+					Ok(Err(error)) => Err(anyhow::Error::msg(error)),
+					Err(panic) => {
+						let message = if let Some(message) = panic.downcast_ref::<String>() {
+							format!(
+								"host code panicked while being called by the runtime: {}",
+								message
+							)
+						} else if let Some(message) = panic.downcast_ref::<&'static str>() {
+							format!(
+								"host code panicked while being called by the runtime: {}",
+								message
+							)
+						} else {
+							"host code panicked while being called by the runtime".to_owned()
+						};
+						Err(anyhow::Error::msg(message))
+					},
+				}
+			})
+			.map_err(|error| {
+				WasmError::Other(format!(
+					"failed to register host function '{}' with the WASM linker: {:#}",
+					name, error
+				))
+			})?;
+	}
+
+	Ok(())
+}
+
+fn wasmtime_func_type(signature: &Signature) -> FuncType {
+	let into_val_type = |value_type: &ValueType| match value_type {
+		ValueType::I32 => ValType::I32,
+		ValueType::I64 => ValType::I64,
+		ValueType::F32 => ValType::F32,
+		ValueType::F64 => ValType::F64,
+	};
+	FuncType::new(
+		signature.args.iter().map(into_val_type),
+		signature.return_value.iter().map(into_val_type),
+	)
+}
+
 struct Registry<'a, 'b> {
 	linker: &'a mut Linker<StoreData>,
 	pending_func_imports: HashMap<String, (ImportType<'b>, FuncType)>,
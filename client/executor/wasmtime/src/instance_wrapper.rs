@@ -19,12 +19,13 @@
 //! Defines data and logic needed for interaction with an WebAssembly instance of a substrate
 //! runtime module.
 
-use crate::runtime::{Store, StoreData};
+use crate::runtime::{CallLimiter, ResourceLimits, Store, StoreData};
 use sc_executor_common::{
 	error::{Backtrace, Error, MessageWithBacktrace, Result, WasmError},
 	wasm_runtime::InvokeMethod,
 };
 use sp_wasm_interface::{Pointer, Value, WordSize};
+use std::time::Instant;
 use wasmtime::{
 	AsContext, AsContextMut, Engine, Extern, Instance, InstancePre, Memory, Table, Val,
 };
@@ -162,8 +163,23 @@ pub struct InstanceWrapper {
 }
 
 impl InstanceWrapper {
-	pub(crate) fn new(engine: &Engine, instance_pre: &InstancePre<StoreData>) -> Result<Self> {
-		let mut store = Store::new(engine, Default::default());
+	pub(crate) fn new(
+		engine: &Engine,
+		instance_pre: &InstancePre<StoreData>,
+		resource_limits: Option<ResourceLimits>,
+	) -> Result<Self> {
+		let limiter = resource_limits.map(CallLimiter::new);
+		let mut store = Store::new(engine, StoreData { limiter, ..Default::default() });
+		let deadline_epochs = store.data().limiter.as_ref().map(|limiter| limiter.deadline_epochs);
+		if let Some(deadline_epochs) = deadline_epochs {
+			// Epoch interruption is enabled along with the limits, instantiation must not trap.
+			store.set_epoch_deadline(deadline_epochs);
+			store.limiter(|data| {
+				data.limiter
+					.as_mut()
+					.expect("the limiter is set when there are resource limits; qed")
+			});
+		}
 		let instance = instance_pre.instantiate(&mut store).map_err(|error| {
 			WasmError::Other(format!(
 				"failed to instantiate a new WASM module instance: {:#}",
@@ -180,6 +196,42 @@ impl InstanceWrapper {
 		Ok(InstanceWrapper { instance, memory, store })
 	}
 
+	/// Refuels the store, sets the deadline and clears the exceeded limits, before a call starts.
+	pub(crate) fn reset_limits(&mut self) -> Result<()> {
+		let Some(limiter) = self.store.data_mut().limiter.as_mut() else { return Ok(()) };
+		limiter.memory_exceeded = false;
+		limiter.started = Some(Instant::now());
+		let fuel = limiter.fuel;
+		let deadline_epochs = limiter.deadline_epochs;
+
+		self.store.set_epoch_deadline(deadline_epochs);
+
+		let remaining = self
+			.store
+			.consume_fuel(0)
+			.map_err(|e| Error::from(format!("cannot read the remaining fuel: {:#}", e)))?;
+		self.store
+			.add_fuel(fuel.saturating_sub(remaining))
+			.map_err(|e| Error::from(format!("cannot add fuel: {:#}", e)))
+	}
+
+	/// Returns the resource limit exceeded since the last call started, if any.
+	pub(crate) fn exceeded_limit(&mut self) -> Option<&'static str> {
+		let limiter = self.store.data().limiter.as_ref()?;
+		if limiter.memory_exceeded {
+			return Some("memory")
+		}
+		let timed_out = limiter
+			.started
+			.map_or(false, |started| started.elapsed() >= limiter.max_duration);
+
+		match self.store.consume_fuel(0) {
+			Ok(0) => Some("fuel"),
+			_ if timed_out => Some("time"),
+			_ => None,
+		}
+	}
+
 	/// Resolves a substrate entrypoint by the given name.
 	///
 	/// An entrypoint must have a signature `(i32, i32) -> i64`, otherwise this function will return
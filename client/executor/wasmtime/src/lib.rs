@@ -30,13 +30,15 @@
 mod host;
 mod imports;
 mod instance_wrapper;
+mod profiler;
 mod runtime;
 mod util;
 
 #[cfg(test)]
 mod tests;
 
+pub use profiler::{record_host_function_calls, HostFunctionProfiler, HostFunctionStats};
 pub use runtime::{
 	create_runtime, create_runtime_from_artifact, prepare_runtime_artifact, Config,
-	DeterministicStackLimit, InstantiationStrategy, Semantics,
+	DeterministicStackLimit, InstantiationStrategy, ResourceLimits, Semantics,
 };
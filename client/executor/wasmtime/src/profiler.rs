@@ -0,0 +1,212 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Profiling of the host functions called by the runtime.
+
+use prometheus_endpoint::{
+	exponential_buckets, register, Counter, CounterVec, Histogram, HistogramOpts, HistogramVec,
+	Opts, PrometheusError, Registry, U64,
+};
+use std::{cell::RefCell, collections::BTreeMap, sync::Arc, time::Duration};
+
+thread_local! {
+	/// The calls recorded by the innermost [`record_host_function_calls`] running on this thread.
+	static RECORDED_CALLS: RefCell<Option<BTreeMap<&'static str, HostFunctionStats>>> =
+		RefCell::new(None);
+}
+
+/// The statistics of the calls of one host function.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HostFunctionStats {
+	/// The number of calls.
+	pub calls: u64,
+	/// The time spent in all the calls.
+	pub total_time: Duration,
+	/// The time spent in the longest call.
+	pub max_time: Duration,
+}
+
+impl HostFunctionStats {
+	fn record(&mut self, elapsed: Duration) {
+		self.calls += 1;
+		self.total_time += elapsed;
+		self.max_time = self.max_time.max(elapsed);
+	}
+
+	fn merge(&mut self, other: &Self) {
+		self.calls += other.calls;
+		self.total_time += other.total_time;
+		self.max_time = self.max_time.max(other.max_time);
+	}
+}
+
+/// Runs `f` and returns the statistics of the host functions it called on this thread, keyed by
+/// the names of the host functions.
+///
+/// Only calls into runtimes created with a [`HostFunctionProfiler`] are recorded. The calls are
+/// also recorded by an enclosing `record_host_function_calls`, if any.
+pub fn record_host_function_calls<R>(
+	f: impl FnOnce() -> R,
+) -> (R, BTreeMap<&'static str, HostFunctionStats>) {
+	let outer = RECORDED_CALLS.with(|recorded| recorded.replace(Some(BTreeMap::new())));
+	// Restores the outer recording even if `f` panics.
+	let recording = Recording { outer };
+
+	let result = f();
+
+	let calls = RECORDED_CALLS.with(|recorded| recorded.borrow().clone().unwrap_or_default());
+	drop(recording);
+	(result, calls)
+}
+
+struct Recording {
+	outer: Option<BTreeMap<&'static str, HostFunctionStats>>,
+}
+
+impl Drop for Recording {
+	fn drop(&mut self) {
+		RECORDED_CALLS.with(|recorded| {
+			let inner = recorded.replace(self.outer.take());
+			if let (Some(inner), Some(outer)) = (inner, recorded.borrow_mut().as_mut()) {
+				for (name, stats) in inner {
+					outer.entry(name).or_default().merge(&stats);
+				}
+			}
+		});
+	}
+}
+
+/// Times every call of the runtime into a host function.
+///
+/// The timings are exported as Prometheus metrics if a registry is given, and to
+/// [`record_host_function_calls`].
+///
+/// Profiled host functions are dispatched dynamically rather than statically, which makes every
+/// call slightly slower.
+#[derive(Clone, Default)]
+pub struct HostFunctionProfiler {
+	metrics: Option<Arc<Metrics>>,
+}
+
+impl HostFunctionProfiler {
+	/// Creates a new profiler exporting its metrics to the given Prometheus `registry`.
+	pub fn new(registry: Option<&Registry>) -> Self {
+		let metrics = registry.and_then(|registry| {
+			Metrics::register(registry)
+				.map_err(|err| {
+					log::warn!("Failed to register host function metrics: {}", err);
+				})
+				.ok()
+		});
+		Self { metrics: metrics.map(Arc::new) }
+	}
+
+	/// Returns a probe reporting the calls of the host function with the given `name`.
+	pub(crate) fn probe(&self, name: &'static str) -> Probe {
+		let metrics = self.metrics.as_ref().map(|metrics| {
+			(metrics.calls.with_label_values(&[name]), metrics.call_time.with_label_values(&[name]))
+		});
+		Probe { name, metrics }
+	}
+}
+
+/// Reports the calls of one host function.
+pub(crate) struct Probe {
+	name: &'static str,
+	metrics: Option<(Counter<U64>, Histogram)>,
+}
+
+impl Probe {
+	/// Reports a call which took `elapsed`.
+	pub(crate) fn report(&self, elapsed: Duration) {
+		if let Some((calls, call_time)) = &self.metrics {
+			calls.inc();
+			call_time.observe(elapsed.as_secs_f64());
+		}
+		RECORDED_CALLS.with(|recorded| {
+			if let Some(recorded) = recorded.borrow_mut().as_mut() {
+				recorded.entry(self.name).or_default().record(elapsed);
+			}
+		});
+	}
+}
+
+struct Metrics {
+	calls: CounterVec<U64>,
+	call_time: HistogramVec,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			calls: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_host_function_calls_total",
+						"Number of calls of the runtime into host functions",
+					),
+					&["function"],
+				)?,
+				registry,
+			)?,
+			call_time: register(
+				HistogramVec::new(
+					HistogramOpts {
+						common_opts: Opts::new(
+							"substrate_host_function_call_time",
+							"Time spent in calls of the runtime into host functions",
+						),
+						// From 100ns to ~100ms.
+						buckets: exponential_buckets(0.000_000_1, 4.0, 11)
+							.expect("parameters are always valid values; qed"),
+					},
+					&["function"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn nested_recordings_are_merged() {
+		let probe = HostFunctionProfiler::default().probe("ext_test");
+		let second = Duration::from_secs(1);
+
+		let ((_, inner), outer) = record_host_function_calls(|| {
+			probe.report(second);
+			record_host_function_calls(|| probe.report(2 * second))
+		});
+
+		let stats = |calls, total, max| HostFunctionStats {
+			calls,
+			total_time: total * second,
+			max_time: max * second,
+		};
+		assert_eq!(inner, BTreeMap::from([("ext_test", stats(1, 2, 2))]));
+		assert_eq!(outer, BTreeMap::from([("ext_test", stats(2, 3, 2))]));
+
+		// Nothing is recorded outside of `record_host_function_calls`.
+		probe.report(second);
+		assert_eq!(record_host_function_calls(|| ()).1, BTreeMap::new());
+	}
+}
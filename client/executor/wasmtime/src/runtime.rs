@@ -21,10 +21,11 @@
 use crate::{
 	host::HostState,
 	instance_wrapper::{EntryPoint, InstanceWrapper, MemoryWrapper},
+	profiler::HostFunctionProfiler,
 	util::{self, replace_strategy_if_broken},
 };
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, Allocator, AllocatorKind, HeapAllocator};
 use sc_executor_common::{
 	error::{Error, Result, WasmError},
 	runtime_blob::{
@@ -41,6 +42,8 @@ use std::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
 	},
+	thread::JoinHandle,
+	time::{Duration, Instant},
 };
 use wasmtime::{AsContext, Engine, Memory, Table};
 
@@ -52,6 +55,8 @@ pub(crate) struct StoreData {
 	pub(crate) memory: Option<Memory>,
 	/// This will be set only if the runtime actually contains a table.
 	pub(crate) table: Option<Table>,
+	/// This will be set only if the runtime was created with [`ResourceLimits`].
+	pub(crate) limiter: Option<CallLimiter>,
 }
 
 impl StoreData {
@@ -68,6 +73,103 @@ impl StoreData {
 
 pub(crate) type Store = wasmtime::Store<StoreData>;
 
+/// The interval at which the epoch of the engines of runtimes with [`ResourceLimits`] advances.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Advances the epoch of an engine every [`EPOCH_TICK`] until dropped, such that calls exceeding
+/// their deadline are interrupted.
+struct EpochTicker {
+	stop: Arc<AtomicBool>,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl EpochTicker {
+	fn start(engine: Engine) -> std::result::Result<Self, WasmError> {
+		let stop = Arc::new(AtomicBool::new(false));
+		let thread = {
+			let stop = stop.clone();
+			std::thread::Builder::new()
+				.name("wasmtime-epoch".into())
+				.spawn(move || {
+					while !stop.load(Ordering::Relaxed) {
+						std::thread::sleep(EPOCH_TICK);
+						engine.increment_epoch();
+					}
+				})
+				.map_err(|e| WasmError::Other(format!("cannot spawn the epoch ticker: {}", e)))?
+		};
+		Ok(Self { stop, thread: Some(thread) })
+	}
+}
+
+impl Drop for EpochTicker {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Enforces the [`ResourceLimits`] of a store, keeping track of whether they were exceeded.
+pub(crate) struct CallLimiter {
+	/// Fuel given to each call.
+	pub(crate) fuel: u64,
+	max_memory_bytes: usize,
+	/// The wall-clock time a call may take.
+	pub(crate) max_duration: Duration,
+	/// The number of epochs after which a call is interrupted.
+	///
+	/// One more than the epochs fitting in `max_duration`, as the epoch may advance right after
+	/// the call started.
+	pub(crate) deadline_epochs: u64,
+	/// Whether the linear memory was denied to grow since the last call started.
+	pub(crate) memory_exceeded: bool,
+	/// When the last call started.
+	pub(crate) started: Option<Instant>,
+}
+
+impl CallLimiter {
+	pub(crate) fn new(limits: ResourceLimits) -> Self {
+		const WASM_PAGE_SIZE: usize = 65536;
+
+		let tick = EPOCH_TICK.as_nanos();
+		let epochs = (limits.max_duration.as_nanos() + tick - 1) / tick;
+		Self {
+			fuel: limits.fuel,
+			max_memory_bytes: limits.max_memory_pages as usize * WASM_PAGE_SIZE,
+			max_duration: limits.max_duration,
+			deadline_epochs: u64::try_from(epochs).unwrap_or(u64::MAX - 1) + 1,
+			memory_exceeded: false,
+			started: None,
+		}
+	}
+}
+
+impl wasmtime::ResourceLimiter for CallLimiter {
+	fn memory_growing(
+		&mut self,
+		_current: usize,
+		desired: usize,
+		_maximum: Option<usize>,
+	) -> anyhow::Result<bool> {
+		if desired > self.max_memory_bytes {
+			self.memory_exceeded = true;
+			return Ok(false)
+		}
+		Ok(true)
+	}
+
+	fn table_growing(
+		&mut self,
+		_current: u32,
+		_desired: u32,
+		_maximum: Option<u32>,
+	) -> anyhow::Result<bool> {
+		Ok(true)
+	}
+}
+
 enum Strategy {
 	LegacyInstanceReuse {
 		instance_wrapper: InstanceWrapper,
@@ -81,11 +183,12 @@ enum Strategy {
 struct InstanceCreator {
 	engine: wasmtime::Engine,
 	instance_pre: Arc<wasmtime::InstancePre<StoreData>>,
+	resource_limits: Option<ResourceLimits>,
 }
 
 impl InstanceCreator {
 	fn instantiate(&mut self) -> Result<InstanceWrapper> {
-		InstanceWrapper::new(&self.engine, &self.instance_pre)
+		InstanceWrapper::new(&self.engine, &self.instance_pre, self.resource_limits)
 	}
 }
 
@@ -125,13 +228,18 @@ pub struct WasmtimeRuntime {
 	engine: wasmtime::Engine,
 	instance_pre: Arc<wasmtime::InstancePre<StoreData>>,
 	instantiation_strategy: InternalInstantiationStrategy,
+	resource_limits: Option<ResourceLimits>,
+	allocator: AllocatorKind,
+	/// Interrupts the calls exceeding their deadline, if there are resource limits.
+	epoch_ticker: Option<Arc<EpochTicker>>,
 }
 
 impl WasmModule for WasmtimeRuntime {
 	fn new_instance(&self) -> Result<Box<dyn WasmInstance>> {
 		let strategy = match self.instantiation_strategy {
 			InternalInstantiationStrategy::LegacyInstanceReuse(ref snapshot_data) => {
-				let mut instance_wrapper = InstanceWrapper::new(&self.engine, &self.instance_pre)?;
+				let mut instance_wrapper =
+					InstanceWrapper::new(&self.engine, &self.instance_pre, self.resource_limits)?;
 				let heap_base = instance_wrapper.extract_heap_base()?;
 
 				// This function panics if the instance was created from a runtime blob different
@@ -153,10 +261,15 @@ impl WasmModule for WasmtimeRuntime {
 			InternalInstantiationStrategy::Builtin => Strategy::RecreateInstance(InstanceCreator {
 				engine: self.engine.clone(),
 				instance_pre: self.instance_pre.clone(),
+				resource_limits: self.resource_limits,
 			}),
 		};
 
-		Ok(Box::new(WasmtimeInstance { strategy }))
+		Ok(Box::new(WasmtimeInstance {
+			strategy,
+			allocator: self.allocator,
+			_epoch_ticker: self.epoch_ticker.clone(),
+		}))
 	}
 }
 
@@ -164,6 +277,9 @@ impl WasmModule for WasmtimeRuntime {
 /// to execute the compiled code.
 pub struct WasmtimeInstance {
 	strategy: Strategy,
+	allocator: AllocatorKind,
+	/// Keeps the epoch of the engine advancing as long as the instance is alive.
+	_epoch_ticker: Option<Arc<EpochTicker>>,
 }
 
 impl WasmtimeInstance {
@@ -190,7 +306,7 @@ impl WasmtimeInstance {
 					)
 				})?;
 				globals_snapshot.apply(&mut InstanceGlobals { instance: instance_wrapper });
-				let allocator = FreeingBumpHeapAllocator::new(*heap_base);
+				let allocator = HeapAllocator::new(self.allocator, *heap_base);
 
 				let result =
 					perform_call(data, instance_wrapper, entrypoint, allocator, allocation_stats);
@@ -206,7 +322,7 @@ impl WasmtimeInstance {
 				let heap_base = instance_wrapper.extract_heap_base()?;
 				let entrypoint = instance_wrapper.resolve_entrypoint(method)?;
 
-				let allocator = FreeingBumpHeapAllocator::new(heap_base);
+				let allocator = HeapAllocator::new(self.allocator, heap_base);
 				perform_call(data, &mut instance_wrapper, entrypoint, allocator, allocation_stats)
 			},
 		}
@@ -333,6 +449,9 @@ fn common_config(semantics: &Semantics) -> std::result::Result<wasmtime::Config,
 	config.wasm_threads(false);
 	config.wasm_memory64(false);
 
+	config.consume_fuel(semantics.resource_limits.is_some());
+	config.epoch_interruption(semantics.resource_limits.is_some());
+
 	let (use_pooling, use_cow) = match semantics.instantiation_strategy {
 		InstantiationStrategy::PoolingCopyOnWrite => (true, true),
 		InstantiationStrategy::Pooling => (true, false),
@@ -463,6 +582,37 @@ pub enum InstantiationStrategy {
 	LegacyInstanceReuse,
 }
 
+/// Bounds on the resources used by a single call into the runtime.
+///
+/// Exceeding them aborts the call with [`Error::ResourceLimitExceeded`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ResourceLimits {
+	/// The fuel given to each call, one unit of fuel is roughly consumed per executed wasm
+	/// instruction. Host functions don't consume fuel.
+	pub fuel: u64,
+
+	/// The maximum size of the linear memory, in pages of 64KiB.
+	pub max_memory_pages: u32,
+
+	/// The wall-clock time a call may take, including the time spent in host functions, which
+	/// the fuel doesn't account for.
+	///
+	/// The wasm code is interrupted once the deadline passed, so a call stuck in a single host
+	/// function is only aborted when the function returns.
+	pub max_duration: Duration,
+}
+
+impl Default for ResourceLimits {
+	fn default() -> Self {
+		// About a few seconds of execution and 1GiB of memory, at most 10 seconds overall.
+		Self {
+			fuel: 10_000_000_000,
+			max_memory_pages: 16384,
+			max_duration: Duration::from_secs(10),
+		}
+	}
+}
+
 enum InternalInstantiationStrategy {
 	LegacyInstanceReuse(InstanceSnapshotData),
 	Builtin,
@@ -516,6 +666,15 @@ pub struct Semantics {
 
 	/// Enables WASM Fixed-Width SIMD proposal
 	pub wasm_simd: bool,
+
+	/// Bounds the resources used by each call into the runtime when `Some`.
+	///
+	/// This instruments the compiled code to meter the executed instructions, which makes it
+	/// slower.
+	pub resource_limits: Option<ResourceLimits>,
+
+	/// The allocator managing the heap of the runtime.
+	pub allocator: AllocatorKind,
 }
 
 #[derive(Clone)]
@@ -531,6 +690,11 @@ pub struct Config {
 
 	/// Tuning of various semantics of the wasmtime executor.
 	pub semantics: Semantics,
+
+	/// Times every call into a host function when `Some`.
+	///
+	/// This doesn't affect the compiled artifacts.
+	pub host_function_profiler: Option<HostFunctionProfiler>,
 }
 
 enum CodeSupplyMode<'a> {
@@ -666,13 +830,30 @@ where
 	};
 
 	let mut linker = wasmtime::Linker::new(&engine);
-	crate::imports::prepare_imports::<H>(&mut linker, &module, config.allow_missing_func_imports)?;
+	crate::imports::prepare_imports::<H>(
+		&mut linker,
+		&module,
+		config.allow_missing_func_imports,
+		config.host_function_profiler.as_ref(),
+	)?;
 
 	let instance_pre = linker
 		.instantiate_pre(&module)
 		.map_err(|e| WasmError::Other(format!("cannot preinstantiate module: {:#}", e)))?;
 
-	Ok(WasmtimeRuntime { engine, instance_pre: Arc::new(instance_pre), instantiation_strategy })
+	let epoch_ticker = match config.semantics.resource_limits {
+		Some(_) => Some(Arc::new(EpochTicker::start(engine.clone())?)),
+		None => None,
+	};
+
+	Ok(WasmtimeRuntime {
+		engine,
+		instance_pre: Arc::new(instance_pre),
+		instantiation_strategy,
+		resource_limits: config.semantics.resource_limits,
+		allocator: config.semantics.allocator,
+		epoch_ticker,
+	})
 }
 
 fn prepare_blob_for_compilation(
@@ -723,7 +904,22 @@ fn perform_call(
 	data: &[u8],
 	instance_wrapper: &mut InstanceWrapper,
 	entrypoint: EntryPoint,
-	mut allocator: FreeingBumpHeapAllocator,
+	allocator: HeapAllocator,
+	allocation_stats: &mut Option<AllocationStats>,
+) -> Result<Vec<u8>> {
+	instance_wrapper.reset_limits()?;
+	let result = call_entrypoint(data, instance_wrapper, entrypoint, allocator, allocation_stats);
+	match instance_wrapper.exceeded_limit() {
+		Some(limit) if result.is_err() => Err(Error::ResourceLimitExceeded(limit)),
+		_ => result,
+	}
+}
+
+fn call_entrypoint(
+	data: &[u8],
+	instance_wrapper: &mut InstanceWrapper,
+	entrypoint: EntryPoint,
+	mut allocator: HeapAllocator,
 	allocation_stats: &mut Option<AllocationStats>,
 ) -> Result<Vec<u8>> {
 	let (data_ptr, data_len) = inject_input_data(instance_wrapper, &mut allocator, data)?;
@@ -751,7 +947,7 @@ fn perform_call(
 
 fn inject_input_data(
 	instance: &mut InstanceWrapper,
-	allocator: &mut FreeingBumpHeapAllocator,
+	allocator: &mut HeapAllocator,
 	data: &[u8],
 ) -> Result<(Pointer<u8>, WordSize)> {
 	let mut ctx = instance.store_mut();
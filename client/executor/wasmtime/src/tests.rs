@@ -17,12 +17,14 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use codec::{Decode as _, Encode as _};
+use sc_allocator::AllocatorKind;
 use sc_executor_common::{
 	error::Error,
 	runtime_blob::RuntimeBlob,
 	wasm_runtime::{HeapAllocStrategy, WasmModule, DEFAULT_HEAP_ALLOC_STRATEGY},
 };
 use sc_runtime_test::wasm_binary_unwrap;
+use std::time::{Duration, Instant};
 
 use crate::InstantiationStrategy;
 
@@ -83,6 +85,9 @@ struct RuntimeBuilder {
 	deterministic_stack: bool,
 	heap_pages: HeapAllocStrategy,
 	precompile_runtime: bool,
+	resource_limits: Option<crate::ResourceLimits>,
+	allocator: AllocatorKind,
+	host_function_profiler: Option<crate::HostFunctionProfiler>,
 	tmpdir: Option<tempfile::TempDir>,
 }
 
@@ -95,6 +100,9 @@ impl RuntimeBuilder {
 			deterministic_stack: false,
 			heap_pages: DEFAULT_HEAP_ALLOC_STRATEGY,
 			precompile_runtime: false,
+			resource_limits: None,
+			allocator: AllocatorKind::default(),
+			host_function_profiler: None,
 			tmpdir: None,
 		}
 	}
@@ -124,6 +132,21 @@ impl RuntimeBuilder {
 		self
 	}
 
+	fn resource_limits(mut self, resource_limits: crate::ResourceLimits) -> Self {
+		self.resource_limits = Some(resource_limits);
+		self
+	}
+
+	fn allocator(mut self, allocator: AllocatorKind) -> Self {
+		self.allocator = allocator;
+		self
+	}
+
+	fn host_function_profiler(mut self, profiler: crate::HostFunctionProfiler) -> Self {
+		self.host_function_profiler = Some(profiler);
+		self
+	}
+
 	fn build(&mut self) -> impl WasmModule + '_ {
 		let blob = {
 			let wasm: Vec<u8>;
@@ -159,7 +182,10 @@ impl RuntimeBuilder {
 				wasm_bulk_memory: false,
 				wasm_reference_types: false,
 				wasm_simd: false,
+				resource_limits: self.resource_limits,
+				allocator: self.allocator,
 			},
+			host_function_profiler: self.host_function_profiler.clone(),
 		};
 
 		if self.precompile_runtime {
@@ -258,6 +284,144 @@ fn test_consume_over_1mb_of_stack_does_trap(instantiation_strategy: Instantiatio
 	}
 }
 
+test_wasm_execution!(test_fuel_limit_aborts_call);
+fn test_fuel_limit_aborts_call(instantiation_strategy: InstantiationStrategy) {
+	let wat = r#"
+		(module
+		  (memory $0 32)
+		  (export "memory" (memory $0))
+		  (global (export "__heap_base") i32 (i32.const 0))
+		  (func (export "main")
+		    (param i32 i32) (result i64)
+		    (loop $loop (br $loop))
+		    (i64.const 0)
+		  )
+		)
+	"#;
+	let mut builder = RuntimeBuilder::new(instantiation_strategy)
+		.use_wat(wat.to_string())
+		.resource_limits(crate::ResourceLimits {
+			fuel: 1_000_000,
+			max_memory_pages: 64,
+			max_duration: Duration::from_secs(60),
+		});
+	let runtime = builder.build();
+	let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+
+	// The fuel is given again to each call.
+	for _ in 0..2 {
+		match instance.call_export("main", &[]).unwrap_err() {
+			Error::ResourceLimitExceeded(limit) => assert_eq!(limit, "fuel"),
+			error => panic!("unexpected error: {:?}", error),
+		}
+	}
+}
+
+test_wasm_execution!(test_time_limit_aborts_call);
+fn test_time_limit_aborts_call(instantiation_strategy: InstantiationStrategy) {
+	let wat = r#"
+		(module
+		  (memory $0 32)
+		  (export "memory" (memory $0))
+		  (global (export "__heap_base") i32 (i32.const 0))
+		  (func (export "main")
+		    (param i32 i32) (result i64)
+		    (loop $loop (br $loop))
+		    (i64.const 0)
+		  )
+		)
+	"#;
+	let mut builder = RuntimeBuilder::new(instantiation_strategy)
+		.use_wat(wat.to_string())
+		.resource_limits(crate::ResourceLimits {
+			fuel: u64::MAX,
+			max_memory_pages: 64,
+			max_duration: Duration::from_millis(100),
+		});
+	let runtime = builder.build();
+	let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+
+	// The deadline is set again for each call.
+	for _ in 0..2 {
+		let started = Instant::now();
+		match instance.call_export("main", &[]).unwrap_err() {
+			Error::ResourceLimitExceeded(limit) => assert_eq!(limit, "time"),
+			error => panic!("unexpected error: {:?}", error),
+		}
+		let elapsed = started.elapsed();
+		assert!(elapsed >= Duration::from_millis(100));
+		assert!(elapsed < Duration::from_secs(10));
+	}
+}
+
+test_wasm_execution!(test_memory_limit_aborts_call);
+fn test_memory_limit_aborts_call(instantiation_strategy: InstantiationStrategy) {
+	let mut builder = RuntimeBuilder::new(instantiation_strategy)
+		.heap_alloc_strategy(HeapAllocStrategy::Dynamic { maximum_pages: None })
+		.resource_limits(crate::ResourceLimits {
+			fuel: 1_000_000_000,
+			max_memory_pages: 256,
+			max_duration: Duration::from_secs(60),
+		});
+	let runtime = builder.build();
+	let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+
+	// Small allocations stay within the limit.
+	instance.call_export("test_allocate_vec", &1024u32.encode()).unwrap();
+
+	match instance
+		.call_export("test_allocate_vec", &(32 * 1024 * 1024u32).encode())
+		.unwrap_err()
+	{
+		Error::ResourceLimitExceeded(limit) => assert_eq!(limit, "memory"),
+		error => panic!("unexpected error: {:?}", error),
+	}
+}
+
+test_wasm_execution!(test_size_class_allocator);
+fn test_size_class_allocator(instantiation_strategy: InstantiationStrategy) {
+	let allocation_stats = |allocator| {
+		let mut builder = RuntimeBuilder::new(instantiation_strategy).allocator(allocator);
+		let runtime = builder.build();
+		let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+		let (result, stats) =
+			instance.call_with_allocation_stats("test_allocate_vec".into(), &5000u32.encode());
+		result.unwrap();
+		stats.unwrap()
+	};
+
+	let freeing_bump = allocation_stats(AllocatorKind::FreeingBump);
+	let size_class = allocation_stats(AllocatorKind::SizeClass);
+
+	// The vector isn't rounded up to 8KiB.
+	assert!(size_class.bytes_allocated_peak < freeing_bump.bytes_allocated_peak);
+	assert!(size_class.address_space_used < freeing_bump.address_space_used);
+}
+
+test_wasm_execution!(test_host_function_profiler);
+fn test_host_function_profiler(instantiation_strategy: InstantiationStrategy) {
+	let mut builder = RuntimeBuilder::new(instantiation_strategy)
+		.heap_alloc_strategy(HeapAllocStrategy::Static { extra_pages: 17 })
+		.host_function_profiler(crate::HostFunctionProfiler::default());
+	let runtime = builder.build();
+	let mut instance = runtime.new_instance().expect("failed to instantiate a runtime");
+
+	let (result, calls) = crate::record_host_function_calls(|| {
+		instance.call_export("test_blake2_256", &b"profiled".to_vec().encode()).unwrap()
+	});
+	assert_eq!(result, sp_io::hashing::blake2_256(b"profiled").to_vec().encode());
+	assert_eq!(calls["ext_hashing_blake2_256_version_1"].calls, 1);
+
+	// Panics in host functions are reported the same way as without the profiler.
+	match instance.call_export("test_allocate_vec", &16777216_u32.encode()).unwrap_err() {
+		Error::AbortedDueToTrap(error) => assert_eq!(
+			error.message,
+			r#"host code panicked while being called by the runtime: Failed to allocate memory: "Allocator ran out of space""#
+		),
+		error => panic!("unexpected error: {:?}", error),
+	}
+}
+
 test_wasm_execution!(test_nan_canonicalization);
 fn test_nan_canonicalization(instantiation_strategy: InstantiationStrategy) {
 	let mut builder = RuntimeBuilder::new(instantiation_strategy).canonicalize_nans(true);
@@ -482,7 +646,10 @@ fn test_instances_without_reuse_are_not_leaked() {
 				wasm_bulk_memory: false,
 				wasm_reference_types: false,
 				wasm_simd: false,
+				resource_limits: None,
+				allocator: AllocatorKind::default(),
 			},
+			host_function_profiler: None,
 		},
 	)
 	.unwrap();
@@ -0,0 +1,231 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Encryption of key files at rest.
+//!
+//! An encrypted keystore holds an [`ENCRYPTION_FILE`] next to the key files. It stores the
+//! scrypt parameters used to derive the encryption key from the passphrase, together with a
+//! known plaintext encrypted with that key, so a wrong passphrase is detected when the keystore
+//! is opened. Every key file is then encrypted with XChaCha20-Poly1305 under a random nonce.
+//!
+//! Plaintext key files, as written by keystores without a passphrase, stay readable. They can
+//! be converted with
+//! [`LocalKeystore::encrypt_plaintext_keys`](crate::LocalKeystore::encrypt_plaintext_keys).
+
+use chacha20poly1305::{
+	aead::{Aead, NewAead},
+	Key, XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sp_core::crypto::{ExposeSecret, SecretString};
+use std::{fs, path::Path};
+use zeroize::Zeroize;
+
+use crate::{Error, Result};
+
+/// Name of the file holding the encryption parameters of a keystore.
+///
+/// It is not valid hex, so it is never mistaken for a key file.
+pub(crate) const ENCRYPTION_FILE: &str = "encryption.json";
+
+/// The current version of the encryption format.
+const VERSION: u32 = 1;
+
+/// Plaintext encrypted into [`EncryptionParams::check`].
+const CHECK_PLAINTEXT: &[u8] = b"substrate-keystore";
+
+/// scrypt cost parameters for newly encrypted keystores.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+
+/// The content of [`ENCRYPTION_FILE`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptionParams {
+	version: u32,
+	kdf: ScryptParams,
+	check: EncryptedData,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScryptParams {
+	log_n: u8,
+	r: u32,
+	p: u32,
+	salt: String,
+}
+
+/// Data encrypted with XChaCha20-Poly1305, both fields are hex encoded.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct EncryptedData {
+	nonce: String,
+	ciphertext: String,
+}
+
+/// The content of a key file.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeyFile {
+	/// The secret URI in plaintext.
+	Plain(String),
+	/// The encrypted secret URI.
+	Encrypted(EncryptedData),
+}
+
+/// Encrypts and decrypts the key files of a keystore.
+pub(crate) struct KeyFileCipher(XChaCha20Poly1305);
+
+impl KeyFileCipher {
+	/// Unlock the encrypted keystore in `dir` with `passphrase`.
+	///
+	/// If the keystore isn't encrypted yet, new encryption parameters are generated and stored.
+	pub(crate) fn open_or_create(dir: &Path, passphrase: &SecretString) -> Result<Self> {
+		if is_encrypted(dir) {
+			return Self::open(dir, passphrase)
+		}
+
+		let mut salt = [0u8; SALT_LENGTH];
+		OsRng.fill_bytes(&mut salt);
+		let kdf = ScryptParams {
+			log_n: SCRYPT_LOG_N,
+			r: SCRYPT_R,
+			p: SCRYPT_P,
+			salt: array_bytes::bytes2hex("0x", salt),
+		};
+
+		let cipher = Self::derive(&kdf, passphrase)?;
+		let params =
+			EncryptionParams { version: VERSION, check: cipher.encrypt(CHECK_PLAINTEXT)?, kdf };
+		fs::write(dir.join(ENCRYPTION_FILE), serde_json::to_vec_pretty(&params)?)?;
+
+		Ok(cipher)
+	}
+
+	/// Unlock the encrypted keystore in `dir` with `passphrase`.
+	pub(crate) fn open(dir: &Path, passphrase: &SecretString) -> Result<Self> {
+		let params: EncryptionParams =
+			serde_json::from_slice(&fs::read(dir.join(ENCRYPTION_FILE))?)?;
+		if params.version != VERSION {
+			return Err(Error::Encryption(format!(
+				"Unsupported encryption version {}",
+				params.version
+			)))
+		}
+
+		let cipher = Self::derive(&params.kdf, passphrase)?;
+		match cipher.decrypt(&params.check) {
+			Ok(check) if check == CHECK_PLAINTEXT => Ok(cipher),
+			_ => Err(Error::InvalidPassphrase),
+		}
+	}
+
+	fn derive(kdf: &ScryptParams, passphrase: &SecretString) -> Result<Self> {
+		let salt = decode_hex(&kdf.salt)?;
+		let params = scrypt::Params::new(kdf.log_n, kdf.r, kdf.p)
+			.map_err(|e| Error::Encryption(e.to_string()))?;
+
+		let mut key = [0u8; 32];
+		scrypt::scrypt(passphrase.expose_secret().as_bytes(), &salt, &params, &mut key)
+			.map_err(|e| Error::Encryption(e.to_string()))?;
+		let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+		key.zeroize();
+
+		Ok(Self(cipher))
+	}
+
+	fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedData> {
+		let mut nonce = [0u8; NONCE_LENGTH];
+		OsRng.fill_bytes(&mut nonce);
+		let ciphertext = self
+			.0
+			.encrypt(XNonce::from_slice(&nonce), plaintext)
+			.map_err(|_| Error::Encryption("Encryption failed".into()))?;
+
+		Ok(EncryptedData {
+			nonce: array_bytes::bytes2hex("0x", nonce),
+			ciphertext: array_bytes::bytes2hex("0x", ciphertext),
+		})
+	}
+
+	fn decrypt(&self, data: &EncryptedData) -> Result<Vec<u8>> {
+		let nonce = decode_hex(&data.nonce)?;
+		if nonce.len() != NONCE_LENGTH {
+			return Err(Error::Encryption("Invalid nonce length".into()))
+		}
+		self.0
+			.decrypt(XNonce::from_slice(&nonce), &decode_hex(&data.ciphertext)?[..])
+			.map_err(|_| Error::InvalidPassphrase)
+	}
+}
+
+/// Returns whether the keystore in `dir` is encrypted.
+pub(crate) fn is_encrypted(dir: &Path) -> bool {
+	dir.join(ENCRYPTION_FILE).exists()
+}
+
+/// Encode `suri` as the content of a key file, encrypting it if a `cipher` is given.
+pub(crate) fn encode_key_file(cipher: Option<&KeyFileCipher>, suri: &str) -> Result<Vec<u8>> {
+	let file = match cipher {
+		Some(cipher) => KeyFile::Encrypted(cipher.encrypt(suri.as_bytes())?),
+		None => KeyFile::Plain(suri.into()),
+	};
+	serde_json::to_vec(&file).map_err(Into::into)
+}
+
+/// Decode the secret URI from the `content` of a key file.
+pub(crate) fn decode_key_file(cipher: Option<&KeyFileCipher>, content: &[u8]) -> Result<String> {
+	match serde_json::from_slice(content)? {
+		KeyFile::Plain(suri) => Ok(suri),
+		KeyFile::Encrypted(data) => {
+			let cipher = cipher.ok_or(Error::PassphraseRequired)?;
+			String::from_utf8(cipher.decrypt(&data)?)
+				.map_err(|_| Error::Encryption("Decrypted key is not valid UTF-8".into()))
+		},
+	}
+}
+
+/// Returns whether the `content` of a key file is in plaintext.
+pub(crate) fn is_plaintext_key_file(content: &[u8]) -> bool {
+	matches!(serde_json::from_slice(content), Ok(KeyFile::Plain(_)))
+}
+
+/// Read the secret URI from the key file at `path`.
+///
+/// Encrypted key files are unlocked with `passphrase` and the encryption parameters of the
+/// keystore the file is in.
+pub fn read_key_file(path: &Path, passphrase: Option<&SecretString>) -> Result<String> {
+	let content = fs::read(path)?;
+	if is_plaintext_key_file(&content) {
+		return decode_key_file(None, &content)
+	}
+
+	let passphrase = passphrase.ok_or(Error::PassphraseRequired)?;
+	let dir = path.parent().unwrap_or_else(|| Path::new("."));
+	let cipher = KeyFileCipher::open(dir, passphrase)?;
+	decode_key_file(Some(&cipher), &content)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+	array_bytes::hex2bytes(hex).map_err(|_| Error::Encryption(format!("Invalid hex `{}`", hex)))
+}
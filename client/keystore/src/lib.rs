@@ -23,10 +23,18 @@ use sp_core::crypto::KeyTypeId;
 use sp_keystore::Error as TraitError;
 use std::io;
 
+/// Encryption of the local keystore at rest
+mod encryption;
+pub use encryption::read_key_file;
+
 /// Local keystore implementation
 mod local;
 pub use local::LocalKeystore;
 
+/// Remote signer backed keystore implementation
+pub mod remote;
+pub use remote::RemoteKeystore;
+
 /// Keystore error.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -54,6 +62,18 @@ pub enum Error {
 	/// Keystore unavailable
 	#[error("Keystore unavailable")]
 	Unavailable,
+	/// Invalid passphrase of an encrypted keystore
+	#[error("Invalid keystore passphrase")]
+	InvalidPassphrase,
+	/// The keystore is encrypted, but no passphrase was given
+	#[error("The keystore is encrypted, a passphrase is required to unlock it")]
+	PassphraseRequired,
+	/// Encrypting or decrypting a key file failed
+	#[error("Keystore encryption error: {0}")]
+	Encryption(String),
+	/// Invalid remote signer URI
+	#[error("Invalid remote signer URI: {0}")]
+	InvalidUri(String),
 }
 
 /// Keystore Result
@@ -63,11 +83,16 @@ impl From<Error> for TraitError {
 	fn from(error: Error) -> Self {
 		match error {
 			Error::KeyNotSupported(id) => TraitError::KeyNotSupported(id),
-			Error::InvalidSeed | Error::InvalidPhrase | Error::PublicKeyMismatch =>
-				TraitError::ValidationError(error.to_string()),
+			Error::InvalidSeed |
+			Error::InvalidPhrase |
+			Error::PublicKeyMismatch |
+			Error::InvalidPassphrase |
+			Error::PassphraseRequired |
+			Error::InvalidUri(_) => TraitError::ValidationError(error.to_string()),
 			Error::Unavailable => TraitError::Unavailable,
 			Error::Io(e) => TraitError::Other(e.to_string()),
 			Error::Json(e) => TraitError::Other(e.to_string()),
+			Error::Encryption(e) => TraitError::Other(e),
 		}
 	}
 }
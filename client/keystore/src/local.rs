@@ -32,15 +32,34 @@ use std::{
 	sync::Arc,
 };
 
-use crate::{Error, Result};
+use crate::{
+	encryption::{self, KeyFileCipher},
+	Error, Result,
+};
 
 /// A local based keystore that is either memory-based or filesystem-based.
 pub struct LocalKeystore(RwLock<KeystoreInner>);
 
 impl LocalKeystore {
 	/// Create a local keystore from filesystem.
+	///
+	/// Fails with [`Error::PassphraseRequired`] if the keystore is encrypted.
 	pub fn open<T: Into<PathBuf>>(path: T, password: Option<SecretString>) -> Result<Self> {
-		let inner = KeystoreInner::open(path, password)?;
+		let inner = KeystoreInner::open(path, password, None)?;
+		Ok(Self(RwLock::new(inner)))
+	}
+
+	/// Create a local keystore from filesystem, encrypting the key files at rest.
+	///
+	/// Every key file written is encrypted with a key derived from `passphrase`. If the keystore
+	/// isn't encrypted yet, it is set up for encryption with the given `passphrase`; key files
+	/// which already exist stay readable, see [`Self::encrypt_plaintext_keys`].
+	pub fn open_encrypted<T: Into<PathBuf>>(
+		path: T,
+		password: Option<SecretString>,
+		passphrase: SecretString,
+	) -> Result<Self> {
+		let inner = KeystoreInner::open(path, password, Some(passphrase))?;
 		Ok(Self(RwLock::new(inner)))
 	}
 
@@ -61,6 +80,14 @@ impl LocalKeystore {
 		self.0.read().key_pair::<Pair>(public)
 	}
 
+	/// Encrypt all key files which are still stored in plaintext.
+	///
+	/// This migrates a keystore created without a passphrase to the encrypted format. Returns the
+	/// number of key files which got encrypted.
+	pub fn encrypt_plaintext_keys(&self) -> Result<usize> {
+		self.0.read().encrypt_plaintext_keys()
+	}
+
 	fn public_keys<T: CorePair>(&self, key_type: KeyTypeId) -> Vec<T::Public> {
 		self.0
 			.read()
@@ -245,17 +272,30 @@ struct KeystoreInner {
 	/// Map over `(KeyTypeId, Raw public key)` -> `Key phrase/seed`
 	additional: HashMap<(KeyTypeId, Vec<u8>), String>,
 	password: Option<SecretString>,
+	/// Encrypts the key files at rest, if the store is encrypted.
+	cipher: Option<KeyFileCipher>,
 }
 
 impl KeystoreInner {
 	/// Open the store at the given path.
 	///
-	/// Optionally takes a password that will be used to encrypt/decrypt the keys.
-	fn open<T: Into<PathBuf>>(path: T, password: Option<SecretString>) -> Result<Self> {
+	/// Optionally takes a password that will be used to encrypt/decrypt the keys and a
+	/// passphrase that will be used to encrypt the key files at rest.
+	fn open<T: Into<PathBuf>>(
+		path: T,
+		password: Option<SecretString>,
+		passphrase: Option<SecretString>,
+	) -> Result<Self> {
 		let path = path.into();
 		fs::create_dir_all(&path)?;
 
-		Ok(Self { path: Some(path), additional: HashMap::new(), password })
+		let cipher = match passphrase {
+			Some(passphrase) => Some(KeyFileCipher::open_or_create(&path, &passphrase)?),
+			None if encryption::is_encrypted(&path) => return Err(Error::PassphraseRequired),
+			None => None,
+		};
+
+		Ok(Self { path: Some(path), additional: HashMap::new(), password, cipher })
 	}
 
 	/// Get the password for this store.
@@ -265,7 +305,7 @@ impl KeystoreInner {
 
 	/// Create a new in-memory store.
 	fn new_in_memory() -> Self {
-		Self { path: None, additional: HashMap::new(), password: None }
+		Self { path: None, additional: HashMap::new(), password: None, cipher: None }
 	}
 
 	/// Get the key phrase for the given public key and key type from the in-memory store.
@@ -292,7 +332,7 @@ impl KeystoreInner {
 	/// Places it into the file system store, if a path is configured.
 	fn insert(&self, key_type: KeyTypeId, suri: &str, public: &[u8]) -> Result<()> {
 		if let Some(path) = self.key_file_path(public, key_type) {
-			Self::write_to_file(path, suri)?;
+			self.write_to_file(path, suri)?;
 		}
 
 		Ok(())
@@ -305,7 +345,7 @@ impl KeystoreInner {
 	fn generate_by_type<Pair: CorePair>(&mut self, key_type: KeyTypeId) -> Result<Pair> {
 		let (pair, phrase, _) = Pair::generate_with_phrase(self.password());
 		if let Some(path) = self.key_file_path(pair.public().as_slice(), key_type) {
-			Self::write_to_file(path, &phrase)?;
+			self.write_to_file(path, &phrase)?;
 		} else {
 			self.insert_ephemeral_pair(&pair, &phrase, key_type);
 		}
@@ -313,8 +353,9 @@ impl KeystoreInner {
 		Ok(pair)
 	}
 
-	/// Write the given `data` to `file`.
-	fn write_to_file(file: PathBuf, data: &str) -> Result<()> {
+	/// Write the given `data` to `file`, encrypted if the store is encrypted.
+	fn write_to_file(&self, file: PathBuf, data: &str) -> Result<()> {
+		let content = encryption::encode_key_file(self.cipher.as_ref(), data)?;
 		let mut file = File::create(file)?;
 
 		#[cfg(target_family = "unix")]
@@ -323,11 +364,45 @@ impl KeystoreInner {
 			file.set_permissions(fs::Permissions::from_mode(0o600))?;
 		}
 
-		serde_json::to_writer(&file, data)?;
+		file.write_all(&content)?;
 		file.flush()?;
 		Ok(())
 	}
 
+	/// Encrypt all key files which are still stored in plaintext.
+	fn encrypt_plaintext_keys(&self) -> Result<usize> {
+		if self.cipher.is_none() {
+			return Err(Error::PassphraseRequired)
+		}
+		let Some(path) = &self.path else { return Ok(0) };
+
+		let mut encrypted = 0;
+		for entry in fs::read_dir(path)? {
+			let file = entry?.path();
+			let is_key_file = file
+				.file_name()
+				.and_then(|n| n.to_str())
+				.map_or(false, |n| array_bytes::hex2bytes(n).map_or(false, |hex| hex.len() > 4));
+			if !is_key_file {
+				continue
+			}
+
+			let content = fs::read(&file)?;
+			if !encryption::is_plaintext_key_file(&content) {
+				continue
+			}
+
+			// Write to a temporary file first, so a failure never leaves a key file half written.
+			let suri = encryption::decode_key_file(None, &content)?;
+			let tmp = file.with_extension("tmp");
+			self.write_to_file(tmp.clone(), &suri)?;
+			fs::rename(tmp, file)?;
+			encrypted += 1;
+		}
+
+		Ok(encrypted)
+	}
+
 	/// Create a new key from seed.
 	///
 	/// Does not place it into the file system store.
@@ -354,9 +429,9 @@ impl KeystoreInner {
 		};
 
 		if path.exists() {
-			let file = File::open(path)?;
+			let content = fs::read(path)?;
 
-			serde_json::from_reader(&file).map_err(Into::into).map(Some)
+			encryption::decode_key_file(self.cipher.as_ref(), &content).map(Some)
 		} else {
 			Ok(None)
 		}
@@ -470,7 +545,7 @@ mod tests {
 	#[test]
 	fn basic_store() {
 		let temp_dir = TempDir::new().unwrap();
-		let mut store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let mut store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 
 		assert!(store.public_keys::<ed25519::AppPublic>().unwrap().is_empty());
 
@@ -503,7 +578,7 @@ mod tests {
 	#[test]
 	fn test_insert_ephemeral_from_seed() {
 		let temp_dir = TempDir::new().unwrap();
-		let mut store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let mut store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 
 		let pair: ed25519::AppPair = store
 			.insert_ephemeral_from_seed(
@@ -516,7 +591,7 @@ mod tests {
 		);
 
 		drop(store);
-		let store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 		// Keys generated from seed should not be persisted!
 		assert!(store.key_pair::<ed25519::AppPair>(&pair.public()).unwrap().is_none());
 	}
@@ -528,6 +603,7 @@ mod tests {
 		let mut store = KeystoreInner::open(
 			temp_dir.path(),
 			Some(FromStr::from_str(password.as_str()).unwrap()),
+			None,
 		)
 		.unwrap();
 
@@ -538,12 +614,13 @@ mod tests {
 		);
 
 		// Without the password the key should not be retrievable
-		let store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 		assert!(store.key_pair::<ed25519::AppPair>(&pair.public()).is_err());
 
 		let store = KeystoreInner::open(
 			temp_dir.path(),
 			Some(FromStr::from_str(password.as_str()).unwrap()),
+			None,
 		)
 		.unwrap();
 		assert_eq!(
@@ -555,7 +632,7 @@ mod tests {
 	#[test]
 	fn public_keys_are_returned() {
 		let temp_dir = TempDir::new().unwrap();
-		let mut store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let mut store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 
 		let mut keys = Vec::new();
 		for i in 0..10 {
@@ -584,7 +661,7 @@ mod tests {
 	#[test]
 	fn store_unknown_and_extract_it() {
 		let temp_dir = TempDir::new().unwrap();
-		let store = KeystoreInner::open(temp_dir.path(), None).unwrap();
+		let store = KeystoreInner::open(temp_dir.path(), None, None).unwrap();
 
 		let secret_uri = "//Alice";
 		let key_pair = sr25519::AppPair::from_string(secret_uri, None).expect("Generates key pair");
@@ -650,4 +727,68 @@ mod tests {
 
 		assert_eq!(0o100600, permissions.mode());
 	}
+
+	#[test]
+	fn encrypted_store_works() {
+		let temp_dir = TempDir::new().unwrap();
+		let passphrase = SecretString::new("passphrase".into());
+		let store =
+			LocalKeystore::open_encrypted(temp_dir.path(), None, passphrase.clone()).unwrap();
+
+		let suri = "//Alice";
+		let pair = sr25519::AppPair::from_string(suri, None).unwrap();
+		store.insert(SR25519, suri, pair.public().as_ref()).unwrap();
+		let public = store.sr25519_generate_new(TEST_KEY_TYPE, None).unwrap();
+
+		// Neither key file contains the secret in plaintext.
+		for entry in fs::read_dir(temp_dir.path()).unwrap() {
+			let content = fs::read_to_string(entry.unwrap().path()).unwrap();
+			assert!(!content.contains(suri));
+		}
+		drop(store);
+
+		let store = LocalKeystore::open_encrypted(temp_dir.path(), None, passphrase).unwrap();
+		assert!(store.has_keys(&[(pair.public().to_raw_vec(), SR25519)]));
+		assert!(store.sr25519_sign(TEST_KEY_TYPE, &public, b"msg").unwrap().is_some());
+	}
+
+	#[test]
+	fn encrypted_store_requires_correct_passphrase() {
+		let temp_dir = TempDir::new().unwrap();
+		LocalKeystore::open_encrypted(temp_dir.path(), None, SecretString::new("right".into()))
+			.unwrap();
+
+		assert!(matches!(
+			LocalKeystore::open_encrypted(temp_dir.path(), None, SecretString::new("wrong".into())),
+			Err(Error::InvalidPassphrase)
+		));
+		assert!(matches!(
+			LocalKeystore::open(temp_dir.path(), None),
+			Err(Error::PassphraseRequired)
+		));
+	}
+
+	#[test]
+	fn plaintext_keys_are_migrated() {
+		let temp_dir = TempDir::new().unwrap();
+		let store = LocalKeystore::open(temp_dir.path(), None).unwrap();
+		let public = store.ed25519_generate_new(TEST_KEY_TYPE, None).unwrap();
+		let path = store.0.read().key_file_path(public.as_ref(), TEST_KEY_TYPE).unwrap();
+		let phrase = store.0.read().key_phrase_by_type(public.as_ref(), TEST_KEY_TYPE).unwrap();
+		drop(store);
+
+		let passphrase = SecretString::new("passphrase".into());
+		let store =
+			LocalKeystore::open_encrypted(temp_dir.path(), None, passphrase.clone()).unwrap();
+		// Plaintext keys stay readable before the migration.
+		assert!(store.ed25519_sign(TEST_KEY_TYPE, &public, b"msg").unwrap().is_some());
+
+		assert_eq!(store.encrypt_plaintext_keys().unwrap(), 1);
+		assert_eq!(store.encrypt_plaintext_keys().unwrap(), 0);
+		assert!(!fs::read_to_string(&path).unwrap().contains(&phrase.clone().unwrap()));
+		assert!(store.ed25519_sign(TEST_KEY_TYPE, &public, b"msg").unwrap().is_some());
+
+		assert_eq!(crate::read_key_file(&path, Some(&passphrase)).unwrap(), phrase.unwrap());
+		assert!(matches!(crate::read_key_file(&path, None), Err(Error::PassphraseRequired)));
+	}
 }
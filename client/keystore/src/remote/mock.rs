@@ -0,0 +1,377 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A mock remote signer, backed by a [`LocalKeystore`].
+//!
+//! Only meant for tests: every connection is served by its own thread.
+
+use codec::Encode;
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use sp_core::{
+	crypto::{ByteArray, KeyTypeId},
+	ecdsa, ed25519, sr25519,
+};
+use sp_keystore::Keystore;
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{SocketAddr, TcpListener, TcpStream},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+	thread,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+use super::{decode_hex, encode_hex, CryptoType};
+use crate::LocalKeystore;
+
+/// Where the mock signer is listening.
+enum Endpoint {
+	#[cfg(unix)]
+	Unix(PathBuf),
+	Http(SocketAddr),
+}
+
+/// A remote signer serving the protocol of [`RemoteKeystore`](super::RemoteKeystore).
+///
+/// The signer stops listening when dropped.
+pub struct MockSigner {
+	keystore: Arc<LocalKeystore>,
+	endpoint: Endpoint,
+	stop: Arc<AtomicBool>,
+}
+
+impl MockSigner {
+	/// Start a signer using `keystore`, listening on the Unix domain socket at `path`.
+	#[cfg(unix)]
+	pub fn unix(path: impl AsRef<Path>, keystore: LocalKeystore) -> io::Result<Self> {
+		let path = path.as_ref().to_path_buf();
+		let listener = UnixListener::bind(&path)?;
+		let keystore = Arc::new(keystore);
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let (ks, should_stop) = (keystore.clone(), stop.clone());
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				if should_stop.load(Ordering::Relaxed) {
+					break
+				}
+				if let Ok(stream) = stream {
+					let ks = ks.clone();
+					thread::spawn(move || serve_unix(&ks, stream));
+				}
+			}
+		});
+
+		Ok(Self { keystore, endpoint: Endpoint::Unix(path), stop })
+	}
+
+	/// Start a signer using `keystore`, listening for HTTP requests on a random local port.
+	pub fn http(keystore: LocalKeystore) -> io::Result<Self> {
+		let listener = TcpListener::bind("127.0.0.1:0")?;
+		let addr = listener.local_addr()?;
+		let keystore = Arc::new(keystore);
+		let stop = Arc::new(AtomicBool::new(false));
+
+		let (ks, should_stop) = (keystore.clone(), stop.clone());
+		thread::spawn(move || {
+			for stream in listener.incoming() {
+				if should_stop.load(Ordering::Relaxed) {
+					break
+				}
+				if let Ok(stream) = stream {
+					let ks = ks.clone();
+					thread::spawn(move || serve_http(&ks, stream));
+				}
+			}
+		});
+
+		Ok(Self { keystore, endpoint: Endpoint::Http(addr), stop })
+	}
+
+	/// The URI to pass to [`RemoteKeystore::open`](super::RemoteKeystore::open).
+	pub fn uri(&self) -> String {
+		match &self.endpoint {
+			#[cfg(unix)]
+			Endpoint::Unix(path) => format!("unix://{}", path.display()),
+			Endpoint::Http(addr) => format!("http://{}", addr),
+		}
+	}
+
+	/// The keystore holding the keys of the signer.
+	pub fn keystore(&self) -> &Arc<LocalKeystore> {
+		&self.keystore
+	}
+}
+
+impl Drop for MockSigner {
+	fn drop(&mut self) {
+		self.stop.store(true, Ordering::Relaxed);
+		// Wake up the listener thread, so that it notices it should stop.
+		match &self.endpoint {
+			#[cfg(unix)]
+			Endpoint::Unix(path) => {
+				let _ = UnixStream::connect(path);
+				let _ = std::fs::remove_file(path);
+			},
+			Endpoint::Http(addr) => {
+				let _ = TcpStream::connect(addr);
+			},
+		}
+	}
+}
+
+#[cfg(unix)]
+fn serve_unix(keystore: &LocalKeystore, stream: UnixStream) -> io::Result<()> {
+	let mut reader = BufReader::new(&stream);
+	loop {
+		let mut request = Vec::new();
+		if reader.read_until(b'\n', &mut request)? == 0 {
+			return Ok(())
+		}
+
+		let mut response = handle(keystore, &request).to_string().into_bytes();
+		response.push(b'\n');
+		(&stream).write_all(&response)?;
+	}
+}
+
+fn serve_http(keystore: &LocalKeystore, stream: TcpStream) -> io::Result<()> {
+	let mut reader = BufReader::new(&stream);
+	loop {
+		let mut content_length = 0;
+		loop {
+			let mut line = String::new();
+			if reader.read_line(&mut line)? == 0 {
+				return Ok(())
+			}
+			let line = line.trim();
+			if line.is_empty() {
+				break
+			}
+			if let Some((name, value)) = line.split_once(':') {
+				if name.eq_ignore_ascii_case("content-length") {
+					content_length = value.trim().parse().unwrap_or(0);
+				}
+			}
+		}
+
+		let mut request = vec![0; content_length];
+		reader.read_exact(&mut request)?;
+
+		let body = handle(keystore, &request).to_string();
+		write!(
+			&stream,
+			"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+			body.len(),
+			body,
+		)?;
+	}
+}
+
+/// Handle a single JSON-RPC request and build the response.
+fn handle(keystore: &LocalKeystore, request: &[u8]) -> Value {
+	let request: Value = match serde_json::from_slice(request) {
+		Ok(request) => request,
+		Err(e) => return error(Value::Null, -32700, &e.to_string()),
+	};
+	let id = request.get("id").cloned().unwrap_or(Value::Null);
+	let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+	let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+	match dispatch(keystore, method, &params) {
+		Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+		Err(e) => error(id, -32602, &e),
+	}
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+	json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn dispatch(keystore: &LocalKeystore, method: &str, params: &Value) -> Result<Value, String> {
+	match method {
+		"signer_publicKeys" => {
+			let key_type = key_type(params)?;
+			let keys: Vec<Vec<u8>> = match crypto_type(params)? {
+				CryptoType::Sr25519 => keystore
+					.sr25519_public_keys(key_type)
+					.into_iter()
+					.map(|k| k.to_raw_vec())
+					.collect(),
+				CryptoType::Ed25519 => keystore
+					.ed25519_public_keys(key_type)
+					.into_iter()
+					.map(|k| k.to_raw_vec())
+					.collect(),
+				CryptoType::Ecdsa => keystore
+					.ecdsa_public_keys(key_type)
+					.into_iter()
+					.map(|k| k.to_raw_vec())
+					.collect(),
+			};
+			Ok(keys.iter().map(|k| encode_hex(k)).collect())
+		},
+		"signer_generateNew" => {
+			let key_type = key_type(params)?;
+			let seed = params.get("seed").and_then(Value::as_str);
+			let public = match crypto_type(params)? {
+				CryptoType::Sr25519 =>
+					keystore.sr25519_generate_new(key_type, seed).map(|k| k.to_raw_vec()),
+				CryptoType::Ed25519 =>
+					keystore.ed25519_generate_new(key_type, seed).map(|k| k.to_raw_vec()),
+				CryptoType::Ecdsa =>
+					keystore.ecdsa_generate_new(key_type, seed).map(|k| k.to_raw_vec()),
+			}
+			.map_err(|e| e.to_string())?;
+			Ok(encode_hex(&public).into())
+		},
+		"signer_sign" => {
+			let key_type = key_type(params)?;
+			let public = bytes(params, "public")?;
+			let message = bytes(params, "message")?;
+			let signature = match crypto_type(params)? {
+				CryptoType::Sr25519 => keystore
+					.sr25519_sign(key_type, &public_key::<sr25519::Public>(&public)?, &message)
+					.map(|s| s.map(|s| s.0.to_vec())),
+				CryptoType::Ed25519 => keystore
+					.ed25519_sign(key_type, &public_key::<ed25519::Public>(&public)?, &message)
+					.map(|s| s.map(|s| s.0.to_vec())),
+				CryptoType::Ecdsa => keystore
+					.ecdsa_sign(key_type, &public_key::<ecdsa::Public>(&public)?, &message)
+					.map(|s| s.map(|s| s.0.to_vec())),
+			}
+			.map_err(|e| e.to_string())?;
+			Ok(signature.map_or(Value::Null, |s| encode_hex(&s).into()))
+		},
+		"signer_signPrehashed" => {
+			if crypto_type(params)? != CryptoType::Ecdsa {
+				return Err("Prehashed signing is only supported for ecdsa".into())
+			}
+			let key_type = key_type(params)?;
+			let public = public_key::<ecdsa::Public>(&bytes(params, "public")?)?;
+			let message: [u8; 32] = bytes(params, "message")?
+				.try_into()
+				.map_err(|_| "`message` must be 32 bytes".to_string())?;
+			let signature = keystore
+				.ecdsa_sign_prehashed(key_type, &public, &message)
+				.map_err(|e| e.to_string())?;
+			Ok(signature.map_or(Value::Null, |s| encode_hex(&s.0).into()))
+		},
+		"signer_vrfSign" => {
+			let key_type = key_type(params)?;
+			let public = public_key::<sr25519::Public>(&bytes(params, "public")?)?;
+			let transcript = params.get("transcript").ok_or("Missing `transcript`")?;
+			let label = intern(bytes(transcript, "label")?);
+			let items = transcript
+				.get("items")
+				.and_then(Value::as_array)
+				.ok_or("Missing transcript `items`")?
+				.iter()
+				.map(|item| match item.as_array().map(Vec::as_slice) {
+					Some([l, m]) => Ok((intern(hex(l)?), hex(m)?)),
+					_ => Err("Transcript items must be `[label, message]` pairs".to_string()),
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			let items: Vec<_> = items.iter().map(|(l, m)| (*l, &m[..])).collect();
+			let transcript = sr25519::vrf::VrfTranscript::new(label, &items);
+
+			let signature = keystore
+				.sr25519_vrf_sign(key_type, &public, &transcript)
+				.map_err(|e| e.to_string())?;
+			Ok(signature.map_or(Value::Null, |s| encode_hex(&s.encode()).into()))
+		},
+		"signer_insert" => {
+			let key_type = key_type(params)?;
+			let suri = params.get("suri").and_then(Value::as_str).ok_or("Missing `suri`")?;
+			let public = bytes(params, "public")?;
+			keystore
+				.insert(key_type, suri, &public)
+				.map_err(|_| "Failed to insert key".to_string())?;
+			Ok(Value::Null)
+		},
+		"signer_keys" => {
+			let keys = keystore.keys(key_type(params)?).map_err(|e| e.to_string())?;
+			Ok(keys.iter().map(|k| encode_hex(k)).collect())
+		},
+		"signer_hasKeys" => {
+			let keys = params
+				.get("keys")
+				.and_then(Value::as_array)
+				.ok_or("Missing `keys`")?
+				.iter()
+				.map(|item| match item.as_array().map(Vec::as_slice) {
+					Some([public, key_type]) => Ok((hex(public)?, parse_key_type(key_type)?)),
+					_ => Err("Keys must be `[public, keyType]` pairs".to_string()),
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			Ok(keystore.has_keys(&keys).into())
+		},
+		_ => Err(format!("Unknown method `{}`", method)),
+	}
+}
+
+fn hex(value: &Value) -> Result<Vec<u8>, String> {
+	decode_hex(value).map_err(|_| "Expected a hex string".to_string())
+}
+
+fn bytes(params: &Value, name: &str) -> Result<Vec<u8>, String> {
+	hex(params.get(name).ok_or_else(|| format!("Missing `{}`", name))?)
+}
+
+fn parse_key_type(value: &Value) -> Result<KeyTypeId, String> {
+	let bytes: [u8; 4] =
+		hex(value)?.try_into().map_err(|_| "Key types must be 4 bytes".to_string())?;
+	Ok(KeyTypeId(bytes))
+}
+
+fn key_type(params: &Value) -> Result<KeyTypeId, String> {
+	parse_key_type(params.get("keyType").ok_or("Missing `keyType`")?)
+}
+
+fn crypto_type(params: &Value) -> Result<CryptoType, String> {
+	params
+		.get("cryptoType")
+		.and_then(Value::as_str)
+		.and_then(CryptoType::from_str)
+		.ok_or_else(|| "Missing or unknown `cryptoType`".to_string())
+}
+
+fn public_key<T: ByteArray>(bytes: &[u8]) -> Result<T, String> {
+	T::from_slice(bytes).map_err(|_| "Invalid public key".to_string())
+}
+
+/// Turn a transcript label into the `'static` slice expected by `merlin`.
+///
+/// Every distinct label is leaked once, which is fine for the handful of labels used in tests.
+fn intern(label: Vec<u8>) -> &'static [u8] {
+	static LABELS: Mutex<Vec<&'static [u8]>> = parking_lot::const_mutex(Vec::new());
+
+	let mut labels = LABELS.lock();
+	if let Some(known) = labels.iter().find(|l| **l == &label[..]) {
+		return *known
+	}
+	let leaked: &'static [u8] = Box::leak(label.into_boxed_slice());
+	labels.push(leaked);
+	leaked
+}
@@ -0,0 +1,534 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Keystore forwarding all requests to a remote signer.
+//!
+//! The node never sees the secret keys, it only sends the data to sign to an external process
+//! which holds them. The signer is reached either over a Unix domain socket or over HTTP and
+//! speaks JSON-RPC 2.0. All binary values (key types, public keys, messages and signatures) are
+//! `0x` prefixed hex strings:
+//!
+//! | Method | Params | Result |
+//! |--------|--------|--------|
+//! | `signer_publicKeys` | `keyType`, `cryptoType` | list of public keys |
+//! | `signer_generateNew` | `keyType`, `cryptoType`, `seed` | public key |
+//! | `signer_sign` | `keyType`, `cryptoType`, `public`, `message` | signature or `null` |
+//! | `signer_signPrehashed` | `keyType`, `cryptoType`, `public`, `message` | signature or `null` |
+//! | `signer_vrfSign` | `keyType`, `public`, `transcript` | SCALE encoded VRF signature or `null` |
+//! | `signer_insert` | `keyType`, `suri`, `public` | `null` |
+//! | `signer_keys` | `keyType` | list of public keys |
+//! | `signer_hasKeys` | `keys`, a list of `[public, keyType]` pairs | boolean |
+//!
+//! `cryptoType` is one of `sr25519`, `ed25519` or `ecdsa`. A VRF `transcript` is an object with
+//! the transcript `label` and its `items`, a list of `[label, message]` pairs.
+//!
+//! Secrets, i.e. the `suri` of `signer_insert` and the `seed` of `signer_generateNew`, are only
+//! sent over Unix domain sockets, never over plain HTTP.
+
+use codec::Decode;
+use serde_json::{json, Value};
+use sp_core::{
+	crypto::{ByteArray, KeyTypeId},
+	ecdsa, ed25519, sr25519,
+};
+use sp_keystore::{Error as TraitError, Keystore, KeystorePtr};
+use std::sync::{
+	atomic::{AtomicU64, Ordering},
+	Arc,
+};
+
+use crate::Result;
+
+#[cfg(any(test, feature = "test-helpers"))]
+pub mod mock;
+mod transport;
+
+use transport::Transport;
+
+const LOG_TARGET: &str = "remote-keystore";
+
+/// The crypto scheme of a key, as understood by the remote signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CryptoType {
+	Sr25519,
+	Ed25519,
+	Ecdsa,
+}
+
+impl CryptoType {
+	pub(crate) fn as_str(&self) -> &'static str {
+		match self {
+			Self::Sr25519 => "sr25519",
+			Self::Ed25519 => "ed25519",
+			Self::Ecdsa => "ecdsa",
+		}
+	}
+
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub(crate) fn from_str(s: &str) -> Option<Self> {
+		match s {
+			"sr25519" => Some(Self::Sr25519),
+			"ed25519" => Some(Self::Ed25519),
+			"ecdsa" => Some(Self::Ecdsa),
+			_ => None,
+		}
+	}
+}
+
+/// A keystore which holds no keys itself but asks a remote signer for every operation.
+pub struct RemoteKeystore {
+	transport: Transport,
+	next_id: AtomicU64,
+}
+
+impl RemoteKeystore {
+	/// Create a keystore talking to the signer at `uri`.
+	///
+	/// Supported are `unix:<path>` for a Unix domain socket and `http://<host>:<port>[/<path>]`.
+	/// No connection is made until the first request.
+	pub fn open(uri: &str) -> Result<Self> {
+		Ok(Self { transport: Transport::parse(uri)?, next_id: AtomicU64::new(0) })
+	}
+
+	/// Call `method` on the remote signer and return its result.
+	fn call(&self, method: &str, params: Value) -> std::result::Result<Value, TraitError> {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+		let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+
+		let response = self.transport.request(request.to_string().as_bytes()).map_err(|e| {
+			log::warn!(target: LOG_TARGET, "Failed to reach remote signer for `{}`: {}", method, e);
+			TraitError::Unavailable
+		})?;
+		let mut response: Value = serde_json::from_slice(&response).map_err(|e| {
+			self.transport.reset();
+			TraitError::Other(format!("Invalid response from remote signer: {}", e))
+		})?;
+
+		if response.get("id") != Some(&json!(id)) {
+			// The connection is out of sync, the responses on it can't be matched anymore.
+			self.transport.reset();
+			return Err(TraitError::Other(format!(
+				"Remote signer answered request {} with id {}",
+				id,
+				response.get("id").unwrap_or(&Value::Null),
+			)))
+		}
+
+		if let Some(error) = response.get("error") {
+			let message = error.get("message").and_then(Value::as_str).unwrap_or("unknown error");
+			return Err(TraitError::Other(format!("Remote signer error: {}", message)))
+		}
+
+		match response.get_mut("result") {
+			Some(result) => Ok(result.take()),
+			None => Err(TraitError::Other("Remote signer response has no result".into())),
+		}
+	}
+
+	fn public_keys<T: ByteArray>(&self, crypto: CryptoType, key_type: KeyTypeId) -> Vec<T> {
+		let params = json!({ "keyType": encode_key_type(key_type), "cryptoType": crypto.as_str() });
+		let keys = self.call("signer_publicKeys", params).and_then(|r| decode_list(&r));
+
+		match keys {
+			Ok(keys) => keys.into_iter().filter_map(|k| T::from_slice(&k).ok()).collect(),
+			Err(e) => {
+				log::warn!(target: LOG_TARGET, "Failed to fetch {:?} public keys: {}", key_type, e);
+				Vec::new()
+			},
+		}
+	}
+
+	fn generate_new<T: ByteArray>(
+		&self,
+		crypto: CryptoType,
+		key_type: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<T, TraitError> {
+		if seed.is_some() && !self.transport.is_confidential() {
+			return Err(TraitError::ValidationError(
+				"Seeds are not sent to a remote signer over plain HTTP".into(),
+			))
+		}
+		let params = json!({
+			"keyType": encode_key_type(key_type),
+			"cryptoType": crypto.as_str(),
+			"seed": seed,
+		});
+		let public = decode_hex(&self.call("signer_generateNew", params)?)?;
+		T::from_slice(&public).map_err(|_| invalid_response("public key"))
+	}
+
+	fn sign<S: for<'a> TryFrom<&'a [u8]>>(
+		&self,
+		method: &str,
+		crypto: CryptoType,
+		key_type: KeyTypeId,
+		public: &[u8],
+		msg: &[u8],
+	) -> std::result::Result<Option<S>, TraitError> {
+		let params = json!({
+			"keyType": encode_key_type(key_type),
+			"cryptoType": crypto.as_str(),
+			"public": encode_hex(public),
+			"message": encode_hex(msg),
+		});
+		match self.call(method, params)? {
+			Value::Null => Ok(None),
+			signature => S::try_from(&decode_hex(&signature)?[..])
+				.map(Some)
+				.map_err(|_| invalid_response("signature")),
+		}
+	}
+}
+
+impl Keystore for RemoteKeystore {
+	fn sr25519_public_keys(&self, key_type: KeyTypeId) -> Vec<sr25519::Public> {
+		self.public_keys(CryptoType::Sr25519, key_type)
+	}
+
+	fn sr25519_generate_new(
+		&self,
+		key_type: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<sr25519::Public, TraitError> {
+		self.generate_new(CryptoType::Sr25519, key_type, seed)
+	}
+
+	fn sr25519_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		msg: &[u8],
+	) -> std::result::Result<Option<sr25519::Signature>, TraitError> {
+		self.sign("signer_sign", CryptoType::Sr25519, key_type, public.as_slice(), msg)
+	}
+
+	fn sr25519_vrf_sign(
+		&self,
+		_key_type: KeyTypeId,
+		_public: &sr25519::Public,
+		_transcript: &sr25519::vrf::VrfTranscript,
+	) -> std::result::Result<Option<sr25519::vrf::VrfSignature>, TraitError> {
+		Err(TraitError::Other(
+			"A remote signer needs the transcript data, use `sr25519_vrf_sign_with_data`".into(),
+		))
+	}
+
+	fn sr25519_vrf_sign_with_data(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		_transcript: &sr25519::vrf::VrfTranscript,
+		data: &sr25519::vrf::VrfTranscriptData,
+	) -> std::result::Result<Option<sr25519::vrf::VrfSignature>, TraitError> {
+		let items: Vec<_> =
+			data.items.iter().map(|(l, m)| json!([encode_hex(l), encode_hex(m)])).collect();
+		let params = json!({
+			"keyType": encode_key_type(key_type),
+			"public": encode_hex(public.as_slice()),
+			"transcript": { "label": encode_hex(&data.label), "items": items },
+		});
+		match self.call("signer_vrfSign", params)? {
+			Value::Null => Ok(None),
+			signature => sr25519::vrf::VrfSignature::decode(&mut &decode_hex(&signature)?[..])
+				.map(Some)
+				.map_err(|_| invalid_response("VRF signature")),
+		}
+	}
+
+	fn ed25519_public_keys(&self, key_type: KeyTypeId) -> Vec<ed25519::Public> {
+		self.public_keys(CryptoType::Ed25519, key_type)
+	}
+
+	fn ed25519_generate_new(
+		&self,
+		key_type: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ed25519::Public, TraitError> {
+		self.generate_new(CryptoType::Ed25519, key_type, seed)
+	}
+
+	fn ed25519_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &ed25519::Public,
+		msg: &[u8],
+	) -> std::result::Result<Option<ed25519::Signature>, TraitError> {
+		self.sign("signer_sign", CryptoType::Ed25519, key_type, public.as_slice(), msg)
+	}
+
+	fn ecdsa_public_keys(&self, key_type: KeyTypeId) -> Vec<ecdsa::Public> {
+		self.public_keys(CryptoType::Ecdsa, key_type)
+	}
+
+	fn ecdsa_generate_new(
+		&self,
+		key_type: KeyTypeId,
+		seed: Option<&str>,
+	) -> std::result::Result<ecdsa::Public, TraitError> {
+		self.generate_new(CryptoType::Ecdsa, key_type, seed)
+	}
+
+	fn ecdsa_sign(
+		&self,
+		key_type: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8],
+	) -> std::result::Result<Option<ecdsa::Signature>, TraitError> {
+		self.sign("signer_sign", CryptoType::Ecdsa, key_type, public.as_slice(), msg)
+	}
+
+	fn ecdsa_sign_prehashed(
+		&self,
+		key_type: KeyTypeId,
+		public: &ecdsa::Public,
+		msg: &[u8; 32],
+	) -> std::result::Result<Option<ecdsa::Signature>, TraitError> {
+		self.sign("signer_signPrehashed", CryptoType::Ecdsa, key_type, public.as_slice(), msg)
+	}
+
+	fn insert(
+		&self,
+		key_type: KeyTypeId,
+		suri: &str,
+		public: &[u8],
+	) -> std::result::Result<(), ()> {
+		if !self.transport.is_confidential() {
+			log::warn!(
+				target: LOG_TARGET,
+				"Refusing to send a secret key to the remote signer over plain HTTP",
+			);
+			return Err(())
+		}
+		let params = json!({ "keyType": encode_key_type(key_type), "suri": suri, "public": encode_hex(public) });
+		self.call("signer_insert", params).map(|_| ()).map_err(|e| {
+			log::warn!(target: LOG_TARGET, "Failed to insert key into remote signer: {}", e);
+		})
+	}
+
+	fn keys(&self, key_type: KeyTypeId) -> std::result::Result<Vec<Vec<u8>>, TraitError> {
+		let params = json!({ "keyType": encode_key_type(key_type) });
+		decode_list(&self.call("signer_keys", params)?)
+	}
+
+	fn has_keys(&self, public_keys: &[(Vec<u8>, KeyTypeId)]) -> bool {
+		let keys: Vec<_> = public_keys
+			.iter()
+			.map(|(p, t)| json!([encode_hex(p), encode_key_type(*t)]))
+			.collect();
+		match self.call("signer_hasKeys", json!({ "keys": keys })) {
+			Ok(result) => result.as_bool().unwrap_or(false),
+			Err(e) => {
+				log::warn!(target: LOG_TARGET, "Failed to query keys of remote signer: {}", e);
+				false
+			},
+		}
+	}
+}
+
+impl Into<KeystorePtr> for RemoteKeystore {
+	fn into(self) -> KeystorePtr {
+		Arc::new(self)
+	}
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+	array_bytes::bytes2hex("0x", bytes)
+}
+
+pub(crate) fn encode_key_type(key_type: KeyTypeId) -> String {
+	encode_hex(&key_type.0)
+}
+
+fn invalid_response(what: &str) -> TraitError {
+	TraitError::Other(format!("Remote signer returned an invalid {}", what))
+}
+
+pub(crate) fn decode_hex(value: &Value) -> std::result::Result<Vec<u8>, TraitError> {
+	value
+		.as_str()
+		.and_then(|s| array_bytes::hex2bytes(s).ok())
+		.ok_or_else(|| invalid_response("hex string"))
+}
+
+fn decode_list(value: &Value) -> std::result::Result<Vec<Vec<u8>>, TraitError> {
+	value
+		.as_array()
+		.ok_or_else(|| invalid_response("list"))?
+		.iter()
+		.map(decode_hex)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{mock::MockSigner, *};
+	use crate::LocalKeystore;
+	use sp_core::{
+		crypto::Pair as _,
+		testing::{ECDSA, ED25519, SR25519},
+	};
+	use tempfile::TempDir;
+
+	fn signers(dir: &TempDir) -> Vec<MockSigner> {
+		let keystore = |name: &str| LocalKeystore::open(dir.path().join(name), None).unwrap();
+		vec![
+			MockSigner::unix(dir.path().join("signer.sock"), keystore("unix")).unwrap(),
+			MockSigner::http(keystore("http")).unwrap(),
+		]
+	}
+
+	#[test]
+	fn signing_works() {
+		let dir = TempDir::new().unwrap();
+		for signer in signers(&dir) {
+			let store = RemoteKeystore::open(&signer.uri()).unwrap();
+			let msg = b"hello remote signer";
+
+			let sr = store.sr25519_generate_new(SR25519, None).unwrap();
+			let sig = store.sr25519_sign(SR25519, &sr, msg).unwrap().unwrap();
+			assert!(sr25519::Pair::verify(&sig, msg, &sr));
+
+			let ed = store.ed25519_generate_new(ED25519, None).unwrap();
+			let sig = store.ed25519_sign(ED25519, &ed, msg).unwrap().unwrap();
+			assert!(ed25519::Pair::verify(&sig, msg, &ed));
+
+			let ec = store.ecdsa_generate_new(ECDSA, None).unwrap();
+			let sig = store.ecdsa_sign(ECDSA, &ec, msg).unwrap().unwrap();
+			assert!(ecdsa::Pair::verify(&sig, msg, &ec));
+
+			let prehashed = sp_core::hashing::blake2_256(msg);
+			let sig = store.ecdsa_sign_prehashed(ECDSA, &ec, &prehashed).unwrap().unwrap();
+			assert!(ecdsa::Pair::verify_prehashed(&sig, &prehashed, &ec));
+
+			assert_eq!(store.sr25519_public_keys(SR25519), vec![sr]);
+			assert_eq!(store.ed25519_public_keys(ED25519), vec![ed]);
+			assert_eq!(store.ecdsa_public_keys(ECDSA), vec![ec]);
+		}
+	}
+
+	#[test]
+	fn unknown_key_returns_none() {
+		let dir = TempDir::new().unwrap();
+		for signer in signers(&dir) {
+			let store = RemoteKeystore::open(&signer.uri()).unwrap();
+			let unknown = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+
+			assert!(store.sr25519_sign(SR25519, &unknown, b"msg").unwrap().is_none());
+			assert!(!store.has_keys(&[(unknown.to_raw_vec(), SR25519)]));
+		}
+	}
+
+	#[test]
+	fn vrf_signing_works() {
+		let dir = TempDir::new().unwrap();
+		for signer in signers(&dir) {
+			let store = RemoteKeystore::open(&signer.uri()).unwrap();
+			let public = store.sr25519_generate_new(SR25519, None).unwrap();
+			let items: &[(&'static [u8], &[u8])] =
+				&[(b"one", &1_u64.to_le_bytes()), (b"two", b"test")];
+			let transcript = sr25519::vrf::VrfTranscript::new(b"My label", items);
+			let data = sr25519::vrf::VrfTranscriptData::new(b"My label", items);
+
+			let signature = store
+				.sr25519_vrf_sign_with_data(SR25519, &public, &transcript, &data)
+				.unwrap()
+				.unwrap();
+
+			use sp_core::crypto::VrfVerifier;
+			assert!(public.vrf_verify(&transcript, &signature));
+			// Without the data the transcript can't be sent to the signer.
+			assert!(store.sr25519_vrf_sign(SR25519, &public, &transcript).is_err());
+		}
+	}
+
+	#[test]
+	fn insert_and_has_keys_works() {
+		let dir = TempDir::new().unwrap();
+		let keystore = LocalKeystore::open(dir.path().join("keystore"), None).unwrap();
+		let signer = MockSigner::unix(dir.path().join("signer.sock"), keystore).unwrap();
+		let store = RemoteKeystore::open(&signer.uri()).unwrap();
+		let suri = "//Alice";
+		let public = sr25519::Pair::from_string(suri, None).unwrap().public();
+
+		store.insert(SR25519, suri, public.as_slice()).unwrap();
+
+		assert!(store.has_keys(&[(public.to_raw_vec(), SR25519)]));
+		assert!(signer.keystore().has_keys(&[(public.to_raw_vec(), SR25519)]));
+		assert_eq!(store.keys(SR25519).unwrap(), vec![public.to_raw_vec()]);
+	}
+
+	#[test]
+	fn secrets_are_not_sent_over_http() {
+		let dir = TempDir::new().unwrap();
+		let keystore = LocalKeystore::open(dir.path().join("keystore"), None).unwrap();
+		let signer = MockSigner::http(keystore).unwrap();
+		let store = RemoteKeystore::open(&signer.uri()).unwrap();
+		let suri = "//Alice";
+		let public = sr25519::Pair::from_string(suri, None).unwrap().public();
+
+		assert!(store.insert(SR25519, suri, public.as_slice()).is_err());
+		assert!(store.sr25519_generate_new(SR25519, Some(suri)).is_err());
+		assert!(signer.keystore().keys(SR25519).unwrap().is_empty());
+	}
+
+	#[test]
+	fn mismatching_response_id_is_rejected() {
+		let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+		let uri = format!("http://{}", listener.local_addr().unwrap());
+		std::thread::spawn(move || {
+			use std::io::{BufRead, BufReader, Read, Write};
+
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(&stream);
+			let mut content_length = 0;
+			loop {
+				let mut line = String::new();
+				reader.read_line(&mut line).unwrap();
+				match line.trim().split_once(':') {
+					Some((name, value)) if name.eq_ignore_ascii_case("content-length") =>
+						content_length = value.trim().parse().unwrap(),
+					None if line.trim().is_empty() => break,
+					_ => {},
+				}
+			}
+			reader.read_exact(&mut vec![0; content_length]).unwrap();
+
+			let body = r#"{"jsonrpc":"2.0","id":42,"result":[]}"#;
+			write!(&stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body,)
+				.unwrap();
+		});
+
+		let store = RemoteKeystore::open(&uri).unwrap();
+		assert!(matches!(store.keys(SR25519), Err(TraitError::Other(_))));
+	}
+
+	#[test]
+	fn unreachable_signer_is_unavailable() {
+		let dir = TempDir::new().unwrap();
+		let uri = format!("unix://{}", dir.path().join("missing.sock").display());
+		let store = RemoteKeystore::open(&uri).unwrap();
+		let public = sr25519::Pair::from_string("//Alice", None).unwrap().public();
+
+		assert!(matches!(
+			store.sr25519_sign(SR25519, &public, b"msg"),
+			Err(TraitError::Unavailable)
+		));
+		assert!(store.sr25519_public_keys(SR25519).is_empty());
+	}
+}
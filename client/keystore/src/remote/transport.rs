@@ -0,0 +1,308 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Transports used to reach a remote signer.
+
+use parking_lot::Mutex;
+use std::{
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{TcpStream, ToSocketAddrs},
+	path::PathBuf,
+	time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use crate::{Error, Result};
+
+/// How long we wait for the remote signer before giving up on a request.
+///
+/// Requests block the caller, which includes claiming BABE slots, so the signer is expected to
+/// run close to the node and to answer quickly.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Where the remote signer is listening.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Endpoint {
+	/// Newline delimited JSON over a Unix domain socket.
+	Unix(PathBuf),
+	/// JSON in the body of an HTTP `POST` request.
+	Http {
+		/// The `host:port` pair to connect to.
+		authority: String,
+		/// The request path, always starting with `/`.
+		path: String,
+	},
+}
+
+impl Endpoint {
+	/// Parse the endpoint from a signer URI.
+	///
+	/// Supported are `unix:<path>`, `unix://<path>` and `http://<host>:<port>[/<path>]`.
+	pub(crate) fn parse(uri: &str) -> Result<Self> {
+		if let Some(path) = uri.strip_prefix("unix://").or_else(|| uri.strip_prefix("unix:")) {
+			if path.is_empty() {
+				return Err(Error::InvalidUri(uri.into()))
+			}
+			return Ok(Self::Unix(path.into()))
+		}
+
+		if let Some(rest) = uri.strip_prefix("http://") {
+			let (authority, path) = match rest.find('/') {
+				Some(pos) => (&rest[..pos], &rest[pos..]),
+				None => (rest, "/"),
+			};
+			if authority.is_empty() {
+				return Err(Error::InvalidUri(uri.into()))
+			}
+			let authority = if authority.contains(':') {
+				authority.into()
+			} else {
+				format!("{}:80", authority)
+			};
+			return Ok(Self::Http { authority, path: path.into() })
+		}
+
+		Err(Error::InvalidUri(uri.into()))
+	}
+
+	fn connect(&self) -> io::Result<Connection> {
+		match self {
+			Self::Unix(path) => connect_unix(path),
+			Self::Http { authority, .. } => connect_tcp(authority),
+		}
+	}
+}
+
+/// An open connection to the remote signer.
+enum Connection {
+	#[cfg(unix)]
+	Unix(BufReader<UnixStream>),
+	Tcp(BufReader<TcpStream>),
+}
+
+/// The way requests are delivered to the remote signer.
+///
+/// A single connection is kept open and reused for all requests, which are sent one at a time.
+pub(crate) struct Transport {
+	endpoint: Endpoint,
+	connection: Mutex<Option<Connection>>,
+}
+
+impl Transport {
+	/// Parse the transport from a signer URI, see [`Endpoint::parse`].
+	pub(crate) fn parse(uri: &str) -> Result<Self> {
+		Ok(Self { endpoint: Endpoint::parse(uri)?, connection: Mutex::new(None) })
+	}
+
+	/// Whether the data sent to the signer can't be read by others on the way.
+	///
+	/// Plain HTTP may cross the network unencrypted, so secrets must never be sent over it.
+	pub(crate) fn is_confidential(&self) -> bool {
+		matches!(self.endpoint, Endpoint::Unix(_))
+	}
+
+	/// Send `body` to the signer and return the body of the response.
+	///
+	/// If a reused connection turns out to be closed, the signer may just have dropped it while
+	/// idle, so the request is retried once on a new connection.
+	pub(crate) fn request(&self, body: &[u8]) -> io::Result<Vec<u8>> {
+		let mut connection = self.connection.lock();
+		let reused = connection.is_some();
+
+		match self.request_on(&mut connection, body) {
+			Err(e) if reused && is_closed(&e) => self.request_on(&mut connection, body),
+			result => result,
+		}
+	}
+
+	/// Drop the current connection, e.g. because the responses on it can't be trusted anymore.
+	pub(crate) fn reset(&self) {
+		*self.connection.lock() = None;
+	}
+
+	fn request_on(&self, connection: &mut Option<Connection>, body: &[u8]) -> io::Result<Vec<u8>> {
+		let mut open = match connection.take() {
+			Some(open) => open,
+			None => self.endpoint.connect()?,
+		};
+
+		// On error the connection is dropped: a late response would be mistaken for the answer
+		// to the next request otherwise.
+		let (response, keep_alive) = match (&mut open, &self.endpoint) {
+			#[cfg(unix)]
+			(Connection::Unix(stream), _) => (unix_request(stream, body)?, true),
+			(Connection::Tcp(stream), Endpoint::Http { authority, path }) =>
+				http_request(stream, authority, path, body)?,
+			_ => unreachable!("Connections are always opened for their endpoint; qed"),
+		};
+
+		if keep_alive {
+			*connection = Some(open);
+		}
+		Ok(response)
+	}
+}
+
+fn is_closed(error: &io::Error) -> bool {
+	matches!(
+		error.kind(),
+		io::ErrorKind::BrokenPipe |
+			io::ErrorKind::ConnectionReset |
+			io::ErrorKind::ConnectionAborted |
+			io::ErrorKind::UnexpectedEof
+	)
+}
+
+#[cfg(unix)]
+fn connect_unix(path: &PathBuf) -> io::Result<Connection> {
+	let stream = UnixStream::connect(path)?;
+	stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+	stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+	Ok(Connection::Unix(BufReader::new(stream)))
+}
+
+#[cfg(not(unix))]
+fn connect_unix(_: &PathBuf) -> io::Result<Connection> {
+	Err(io::Error::new(
+		io::ErrorKind::Unsupported,
+		"Unix sockets are not supported on this platform",
+	))
+}
+
+fn connect_tcp(authority: &str) -> io::Result<Connection> {
+	let addr = authority.to_socket_addrs()?.next().ok_or_else(|| {
+		io::Error::new(io::ErrorKind::NotFound, format!("Could not resolve `{}`", authority))
+	})?;
+	let stream = TcpStream::connect_timeout(&addr, REQUEST_TIMEOUT)?;
+	stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+	stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+	stream.set_nodelay(true)?;
+	Ok(Connection::Tcp(BufReader::new(stream)))
+}
+
+#[cfg(unix)]
+fn unix_request(stream: &mut BufReader<UnixStream>, body: &[u8]) -> io::Result<Vec<u8>> {
+	let mut writer = stream.get_ref();
+	writer.write_all(body)?;
+	writer.write_all(b"\n")?;
+	writer.flush()?;
+
+	let mut response = Vec::new();
+	stream.read_until(b'\n', &mut response)?;
+	if response.last() != Some(&b'\n') {
+		return Err(io::ErrorKind::UnexpectedEof.into())
+	}
+	Ok(response)
+}
+
+/// A minimal blocking HTTP/1.1 client.
+///
+/// Returns the body of the response and whether the connection can be reused. Only responses
+/// with a `Content-Length` are supported, which keeps reading them trivial.
+fn http_request(
+	stream: &mut BufReader<TcpStream>,
+	authority: &str,
+	path: &str,
+	body: &[u8],
+) -> io::Result<(Vec<u8>, bool)> {
+	let mut writer = stream.get_ref();
+	write!(
+		writer,
+		"POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+		path,
+		authority,
+		body.len(),
+	)?;
+	writer.write_all(body)?;
+	writer.flush()?;
+
+	let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+	let mut status_line = String::new();
+	if stream.read_line(&mut status_line)? == 0 {
+		return Err(io::ErrorKind::UnexpectedEof.into())
+	}
+	let status_line = status_line.trim().to_string();
+
+	let mut content_length = None;
+	let mut keep_alive = status_line.starts_with("HTTP/1.1");
+	loop {
+		let mut line = String::new();
+		if stream.read_line(&mut line)? == 0 {
+			return Err(io::ErrorKind::UnexpectedEof.into())
+		}
+		let line = line.trim();
+		if line.is_empty() {
+			break
+		}
+		let Some((name, value)) = line.split_once(':') else {
+			return Err(invalid("Malformed HTTP response header"))
+		};
+		let (name, value) = (name.trim(), value.trim());
+		if name.eq_ignore_ascii_case("content-length") {
+			content_length =
+				Some(value.parse().map_err(|_| invalid("Invalid HTTP Content-Length"))?);
+		} else if name.eq_ignore_ascii_case("connection") {
+			keep_alive = !value.eq_ignore_ascii_case("close");
+		} else if name.eq_ignore_ascii_case("transfer-encoding") {
+			return Err(invalid("Chunked HTTP responses are not supported"))
+		}
+	}
+
+	let content_length: usize =
+		content_length.ok_or_else(|| invalid("HTTP response without Content-Length"))?;
+	let mut response = vec![0; content_length];
+	stream.read_exact(&mut response)?;
+
+	match status_line.split_whitespace().nth(1) {
+		Some("200") => Ok((response, keep_alive)),
+		_ => Err(io::Error::new(
+			io::ErrorKind::Other,
+			format!("Remote signer responded with `{}`", status_line),
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_uris() {
+		assert_eq!(
+			Endpoint::parse("unix:///run/signer.sock").unwrap(),
+			Endpoint::Unix("/run/signer.sock".into()),
+		);
+		assert_eq!(
+			Endpoint::parse("unix:signer.sock").unwrap(),
+			Endpoint::Unix("signer.sock".into()),
+		);
+		assert_eq!(
+			Endpoint::parse("http://127.0.0.1:9955").unwrap(),
+			Endpoint::Http { authority: "127.0.0.1:9955".into(), path: "/".into() },
+		);
+		assert_eq!(
+			Endpoint::parse("http://signer/rpc").unwrap(),
+			Endpoint::Http { authority: "signer:80".into(), path: "/rpc".into() },
+		);
+		assert!(Endpoint::parse("https://signer").is_err());
+		assert!(Endpoint::parse("unix:").is_err());
+	}
+}
@@ -223,6 +223,7 @@ pub enum PeerRequest<B: BlockT> {
 	Block(BlockRequest<B>),
 	State,
 	WarpProof,
+	Snapshot,
 }
 
 /// Wrapper for implementation-specific state request.
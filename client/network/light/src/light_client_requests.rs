@@ -24,20 +24,35 @@ use std::time::Duration;
 
 /// For incoming light client requests.
 pub mod handler;
+/// For incoming light client requests of the v2 protocol.
+pub mod handler_v2;
+
+/// Version in the protocol name (`/light/2`) of the v1 light client request schema.
+const SCHEMA_V1_PROTOCOL_NAME_VERSION: u32 = 2;
+
+/// Version in the protocol name (`/light/3`) of the v2 light client request schema.
+///
+/// The schema and protocol name versions don't match because the v1 schema has always been
+/// served as `/light/2`.
+const SCHEMA_V2_PROTOCOL_NAME_VERSION: u32 = 3;
 
 /// Generate the light client protocol name from the genesis hash and fork id.
-fn generate_protocol_name<Hash: AsRef<[u8]>>(genesis_hash: Hash, fork_id: Option<&str>) -> String {
+fn generate_protocol_name<Hash: AsRef<[u8]>>(
+	genesis_hash: Hash,
+	fork_id: Option<&str>,
+	version: u32,
+) -> String {
 	let genesis_hash = genesis_hash.as_ref();
 	if let Some(fork_id) = fork_id {
-		format!("/{}/{}/light/2", array_bytes::bytes2hex("", genesis_hash), fork_id)
+		format!("/{}/{}/light/{}", array_bytes::bytes2hex("", genesis_hash), fork_id, version)
 	} else {
-		format!("/{}/light/2", array_bytes::bytes2hex("", genesis_hash))
+		format!("/{}/light/{}", array_bytes::bytes2hex("", genesis_hash), version)
 	}
 }
 
 /// Generate the legacy light client protocol name from chain specific protocol identifier.
-fn generate_legacy_protocol_name(protocol_id: &ProtocolId) -> String {
-	format!("/{}/light/2", protocol_id.as_ref())
+fn generate_legacy_protocol_name(protocol_id: &ProtocolId, version: u32) -> String {
+	format!("/{}/light/{}", protocol_id.as_ref(), version)
 }
 
 /// Generates a [`ProtocolConfig`] for the light client request protocol, refusing incoming
@@ -46,10 +61,29 @@ pub fn generate_protocol_config<Hash: AsRef<[u8]>>(
 	protocol_id: &ProtocolId,
 	genesis_hash: Hash,
 	fork_id: Option<&str>,
+) -> ProtocolConfig {
+	protocol_config(protocol_id, genesis_hash, fork_id, SCHEMA_V1_PROTOCOL_NAME_VERSION)
+}
+
+/// Generates a [`ProtocolConfig`] for the v2 light client request protocol, refusing incoming
+/// requests.
+pub fn generate_protocol_config_v2<Hash: AsRef<[u8]>>(
+	protocol_id: &ProtocolId,
+	genesis_hash: Hash,
+	fork_id: Option<&str>,
+) -> ProtocolConfig {
+	protocol_config(protocol_id, genesis_hash, fork_id, SCHEMA_V2_PROTOCOL_NAME_VERSION)
+}
+
+fn protocol_config<Hash: AsRef<[u8]>>(
+	protocol_id: &ProtocolId,
+	genesis_hash: Hash,
+	fork_id: Option<&str>,
+	version: u32,
 ) -> ProtocolConfig {
 	ProtocolConfig {
-		name: generate_protocol_name(genesis_hash, fork_id).into(),
-		fallback_names: std::iter::once(generate_legacy_protocol_name(protocol_id).into())
+		name: generate_protocol_name(genesis_hash, fork_id, version).into(),
+		fallback_names: std::iter::once(generate_legacy_protocol_name(protocol_id, version).into())
 			.collect(),
 		max_request_size: 1 * 1024 * 1024,
 		max_response_size: 16 * 1024 * 1024,
@@ -298,7 +298,7 @@ enum HandleRequestError {
 	Codec(#[from] codec::Error),
 }
 
-fn fmt_keys(first: Option<&Vec<u8>>, last: Option<&Vec<u8>>) -> String {
+pub(super) fn fmt_keys(first: Option<&Vec<u8>>, last: Option<&Vec<u8>>) -> String {
 	if let (Some(first), Some(last)) = (first, last) {
 		if first == last {
 			HexDisplay::from(first).to_string()
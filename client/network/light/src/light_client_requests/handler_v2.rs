@@ -0,0 +1,618 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helper for incoming light client requests of the v2 protocol.
+//!
+//! Compared to [`LightClientRequestHandler`](super::handler::LightClientRequestHandler), reads
+//! can be batched across multiple blocks and child tries, storage ranges can be enumerated and
+//! headers can be requested by range, which saves light clients many round trips. The protocol
+//! name ends with `/light/3`.
+
+use super::handler::fmt_keys;
+use crate::schema::v2::light as schema;
+use codec::{self, Decode, Encode};
+use futures::{channel::mpsc, prelude::*};
+use libp2p::PeerId;
+use log::{debug, trace};
+use prost::Message;
+use sc_client_api::{BlockBackend, ProofProvider, StorageProof};
+use sc_network::{
+	config::ProtocolId,
+	request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig},
+};
+use sc_peerset::ReputationChange;
+use sp_blockchain::HeaderBackend;
+use sp_core::{
+	hexdisplay::HexDisplay,
+	storage::{ChildInfo, ChildType, PrefixedStorageKey},
+};
+use sp_runtime::traits::{Block, Header, One, Zero};
+use std::{marker::PhantomData, sync::Arc};
+
+const LOG_TARGET: &str = "light-client-request-handler";
+
+/// Maximum number of blocks read by a single request.
+const MAX_BLOCK_READS: usize = 64;
+
+/// Maximum number of storage keys read by a single request, over all blocks and child tries.
+const MAX_READ_KEYS: usize = 1024;
+
+/// Maximum size of the proof of a range read.
+const MAX_RANGE_PROOF_SIZE: usize = 2 * 1024 * 1024;
+
+/// Maximum number of headers returned by a single request.
+const MAX_HEADERS: u32 = 1024;
+
+/// Handler for incoming light client requests of the v2 protocol from a remote peer.
+pub struct LightClientRequestHandlerV2<B, Client> {
+	request_receiver: mpsc::Receiver<IncomingRequest>,
+	/// Blockchain client.
+	client: Arc<Client>,
+	_block: PhantomData<B>,
+}
+
+impl<B, Client> LightClientRequestHandlerV2<B, Client>
+where
+	B: Block,
+	Client: BlockBackend<B> + HeaderBackend<B> + ProofProvider<B> + Send + Sync + 'static,
+{
+	/// Create a new [`LightClientRequestHandlerV2`].
+	pub fn new(
+		protocol_id: &ProtocolId,
+		fork_id: Option<&str>,
+		client: Arc<Client>,
+	) -> (Self, ProtocolConfig) {
+		// Same limit as the v1 protocol.
+		let (tx, request_receiver) = mpsc::channel(20);
+
+		let mut protocol_config = super::generate_protocol_config_v2(
+			protocol_id,
+			client
+				.block_hash(0u32.into())
+				.ok()
+				.flatten()
+				.expect("Genesis block exists; qed"),
+			fork_id,
+		);
+		protocol_config.inbound_queue = Some(tx);
+
+		(Self { client, request_receiver, _block: PhantomData::default() }, protocol_config)
+	}
+
+	/// Run [`LightClientRequestHandlerV2`].
+	pub async fn run(mut self) {
+		while let Some(request) = self.request_receiver.next().await {
+			let IncomingRequest { peer, payload, pending_response } = request;
+
+			let response = match self.handle_request(peer, payload) {
+				Ok(response_data) => OutgoingResponse {
+					result: Ok(response_data),
+					reputation_changes: Vec::new(),
+					sent_feedback: None,
+				},
+				Err(e) => {
+					debug!(
+						target: LOG_TARGET,
+						"Failed to handle light client request from {}: {}", peer, e,
+					);
+
+					let reputation_changes = match e {
+						HandleRequestError::BadRequest(_) => {
+							vec![ReputationChange::new(-(1 << 12), "bad request")]
+						},
+						_ => Vec::new(),
+					};
+
+					OutgoingResponse { result: Err(()), reputation_changes, sent_feedback: None }
+				},
+			};
+
+			match pending_response.send(response) {
+				Ok(()) => trace!(target: LOG_TARGET, "Handled light client request from {}.", peer),
+				Err(_) => debug!(
+					target: LOG_TARGET,
+					"Failed to handle light client request from {}: {}",
+					peer,
+					HandleRequestError::SendResponse,
+				),
+			}
+		}
+	}
+
+	fn handle_request(
+		&mut self,
+		peer: PeerId,
+		payload: Vec<u8>,
+	) -> Result<Vec<u8>, HandleRequestError> {
+		let request = schema::Request::decode(&payload[..])?;
+
+		let response = match &request.request {
+			Some(schema::request::Request::RemoteCallRequest(r)) =>
+				self.on_remote_call_request(&peer, r)?,
+			Some(schema::request::Request::RemoteReadRequest(r)) =>
+				self.on_remote_read_request(&peer, r)?,
+			Some(schema::request::Request::RemoteReadRangeRequest(r)) =>
+				self.on_remote_read_range_request(&peer, r)?,
+			Some(schema::request::Request::RemoteHeadersRequest(r)) =>
+				self.on_remote_headers_request(&peer, r)?,
+			None =>
+				return Err(HandleRequestError::BadRequest("Remote request without request data.")),
+		};
+
+		let mut data = Vec::new();
+		response.encode(&mut data)?;
+
+		Ok(data)
+	}
+
+	fn on_remote_call_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::RemoteCallRequest,
+	) -> Result<schema::Response, HandleRequestError> {
+		trace!("Remote call request from {} ({} at {:?}).", peer, request.method, request.block);
+
+		let block = Decode::decode(&mut request.block.as_ref())?;
+
+		let response = match self.client.execution_proof(block, &request.method, &request.data) {
+			Ok((_, proof)) => schema::RemoteCallResponse { proof: Some(proof.encode()) },
+			Err(e) => {
+				trace!(
+					"remote call request from {} ({} at {:?}) failed with: {}",
+					peer,
+					request.method,
+					request.block,
+					e,
+				);
+				schema::RemoteCallResponse { proof: None }
+			},
+		};
+
+		Ok(schema::Response {
+			response: Some(schema::response::Response::RemoteCallResponse(response)),
+		})
+	}
+
+	fn on_remote_read_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::RemoteReadRequest,
+	) -> Result<schema::Response, HandleRequestError> {
+		if request.reads.is_empty() {
+			debug!("Invalid remote read request sent by {}.", peer);
+			return Err(HandleRequestError::BadRequest("Remote read request without reads."))
+		}
+		if request.reads.len() > MAX_BLOCK_READS {
+			debug!("Invalid remote read request sent by {}.", peer);
+			return Err(HandleRequestError::BadRequest("Remote read request with too many reads."))
+		}
+		let keys = request
+			.reads
+			.iter()
+			.flat_map(|read| {
+				std::iter::once(read.keys.len())
+					.chain(read.child_reads.iter().map(|child_read| child_read.keys.len()))
+			})
+			.sum::<usize>();
+		if keys > MAX_READ_KEYS {
+			debug!("Invalid remote read request sent by {}.", peer);
+			return Err(HandleRequestError::BadRequest("Remote read request with too many keys."))
+		}
+
+		let mut proofs = Vec::with_capacity(request.reads.len());
+		for read in &request.reads {
+			if (read.keys.is_empty() && read.child_reads.is_empty()) ||
+				read.child_reads.iter().any(|child_read| child_read.keys.is_empty())
+			{
+				debug!("Invalid remote read request sent by {}.", peer);
+				return Err(HandleRequestError::BadRequest("Remote read request without keys."))
+			}
+
+			trace!(
+				"Remote read request from {} ({} and {} child tries at {:?}).",
+				peer,
+				fmt_keys(read.keys.first(), read.keys.last()),
+				read.child_reads.len(),
+				read.block,
+			);
+
+			let block = Decode::decode(&mut read.block.as_ref())?;
+
+			let proof = match self.read_proof(block, read) {
+				Ok(proof) => schema::ReadProof { proof: Some(proof.encode()) },
+				Err(error) => {
+					trace!(
+						"remote read request from {} ({} and {} child tries at {:?}) failed \
+						 with: {}",
+						peer,
+						fmt_keys(read.keys.first(), read.keys.last()),
+						read.child_reads.len(),
+						read.block,
+						error,
+					);
+					schema::ReadProof { proof: None }
+				},
+			};
+			proofs.push(proof);
+		}
+
+		Ok(schema::Response {
+			response: Some(schema::response::Response::RemoteReadResponse(
+				schema::RemoteReadResponse { proofs },
+			)),
+		})
+	}
+
+	/// Returns a single proof of all the top and child trie keys of `read`.
+	fn read_proof(
+		&self,
+		block: B::Hash,
+		read: &schema::BlockRead,
+	) -> sp_blockchain::Result<StorageProof> {
+		let mut proofs = Vec::with_capacity(read.child_reads.len() + 1);
+		if !read.keys.is_empty() {
+			proofs.push(self.client.read_proof(block, &mut read.keys.iter().map(AsRef::as_ref))?);
+		}
+		for child_read in &read.child_reads {
+			let child_info = child_info(&child_read.storage_key)?;
+			proofs.push(self.client.read_child_proof(
+				block,
+				&child_info,
+				&mut child_read.keys.iter().map(AsRef::as_ref),
+			)?);
+		}
+		Ok(StorageProof::merge(proofs))
+	}
+
+	fn on_remote_read_range_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::RemoteReadRangeRequest,
+	) -> Result<schema::Response, HandleRequestError> {
+		trace!(
+			"Remote read range request from {} ({:?} {:?} from {:?} at {:?}).",
+			peer,
+			request.storage_key.as_ref().map(HexDisplay::from),
+			request.prefix.as_ref().map(HexDisplay::from),
+			request.start_key.as_ref().map(HexDisplay::from),
+			request.block,
+		);
+
+		let block = Decode::decode(&mut request.block.as_ref())?;
+
+		let child_info = request.storage_key.as_ref().map(child_info).transpose();
+		let response = match child_info.and_then(|child_info| {
+			self.client.read_range_proof(
+				block,
+				child_info.as_ref(),
+				request.prefix.as_deref(),
+				request.start_key.as_deref(),
+				MAX_RANGE_PROOF_SIZE,
+			)
+		}) {
+			Ok((proof, count)) =>
+				schema::RemoteReadRangeResponse { proof: Some(proof.encode()), count: Some(count) },
+			Err(error) => {
+				trace!(
+					"remote read range request from {} (at {:?}) failed with: {}",
+					peer,
+					request.block,
+					error,
+				);
+				schema::RemoteReadRangeResponse { proof: None, count: None }
+			},
+		};
+
+		Ok(schema::Response {
+			response: Some(schema::response::Response::RemoteReadRangeResponse(response)),
+		})
+	}
+
+	fn on_remote_headers_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::RemoteHeadersRequest,
+	) -> Result<schema::Response, HandleRequestError> {
+		if request.max_headers == 0 {
+			debug!("Invalid remote headers request sent by {}.", peer);
+			return Err(HandleRequestError::BadRequest("Remote headers request without headers."))
+		}
+
+		trace!(
+			"Remote headers request from {} ({} {:?} from {:?}).",
+			peer,
+			request.max_headers,
+			request.direction(),
+			request.from_block,
+		);
+
+		let from_block: B::Hash = Decode::decode(&mut request.from_block.as_ref())?;
+		let max_headers = request.max_headers.min(MAX_HEADERS) as usize;
+
+		let mut headers = Vec::new();
+		let mut next = self.client.header(from_block)?;
+		// Ascending ranges follow the canonical chain, which the first block must be part of.
+		// Otherwise there is no way to tell which of its descendants to follow.
+		let ascending = request.direction() == schema::Direction::Ascending;
+		if let Some(header) = next.as_ref().filter(|_| ascending) {
+			if self.client.hash(*header.number())? != Some(from_block) {
+				trace!("Remote headers request from {} starts at a non-canonical block.", peer);
+				next = None;
+			}
+		}
+
+		while let Some(header) = next.take() {
+			if headers.len() == max_headers {
+				break
+			}
+
+			let number = *header.number();
+			next = if ascending {
+				match self.client.hash(number + One::one())? {
+					Some(hash) => self.client.header(hash)?,
+					None => None,
+				}
+			} else if number.is_zero() {
+				None
+			} else {
+				self.client.header(*header.parent_hash())?
+			};
+			headers.push(header.encode());
+		}
+
+		Ok(schema::Response {
+			response: Some(schema::response::Response::RemoteHeadersResponse(
+				schema::RemoteHeadersResponse { headers },
+			)),
+		})
+	}
+}
+
+/// Returns the [`ChildInfo`] of a child trie from its prefixed storage key.
+fn child_info(storage_key: &Vec<u8>) -> sp_blockchain::Result<ChildInfo> {
+	match ChildType::from_prefixed_key(PrefixedStorageKey::new_ref(storage_key)) {
+		Some((ChildType::ParentKeyId, storage_key)) => Ok(ChildInfo::new_default(storage_key)),
+		None => Err(sp_blockchain::Error::InvalidChildStorageKey),
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+enum HandleRequestError {
+	#[error("Failed to decode request: {0}.")]
+	DecodeProto(#[from] prost::DecodeError),
+	#[error("Failed to encode response: {0}.")]
+	EncodeProto(#[from] prost::EncodeError),
+	#[error("Failed to send response.")]
+	SendResponse,
+	/// A bad request has been received.
+	#[error("bad request: {0}")]
+	BadRequest(&'static str),
+	/// Encoding or decoding of some data failed.
+	#[error("codec error: {0}")]
+	Codec(#[from] codec::Error),
+	/// Reading from the blockchain failed.
+	#[error(transparent)]
+	Client(#[from] sp_blockchain::Error),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::executor::block_on;
+	use sc_block_builder::BlockBuilderProvider;
+	use sp_core::storage::well_known_keys;
+	use sp_runtime::traits::{BlakeTwo256, Block as BlockT, Header as HeaderT};
+	use substrate_test_runtime_client::{
+		runtime::{Block, Hash, Header},
+		sp_consensus::BlockOrigin,
+		BlockBuilderExt, ClientBlockImportExt, DefaultTestClientBuilderExt, TestClient,
+		TestClientBuilder, TestClientBuilderExt,
+	};
+
+	type Handler = LightClientRequestHandlerV2<Block, TestClient>;
+
+	/// A chain of three blocks on top of genesis, plus a fork at block 2 changing key `[1]`.
+	///
+	/// Returns the handler, the canonical block hashes starting at genesis and the fork.
+	fn setup() -> (Handler, Vec<Hash>, Hash) {
+		let mut client = Arc::new(TestClientBuilder::new().build());
+		let mut canonical = vec![client.info().genesis_hash];
+		for _ in 0..3 {
+			let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+			canonical.push(block.hash());
+			block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		}
+
+		let mut builder = client.new_block_at(canonical[1], Default::default(), false).unwrap();
+		builder.push_storage_change(vec![1], Some(vec![2])).unwrap();
+		let fork = builder.build().unwrap().block;
+		let fork_hash = fork.hash();
+		block_on(client.import(BlockOrigin::Own, fork)).unwrap();
+		assert_eq!(client.info().best_hash, canonical[3]);
+
+		let (handler, _) = Handler::new(&ProtocolId::from("test"), None, client);
+		(handler, canonical, fork_hash)
+	}
+
+	fn send(
+		handler: &mut Handler,
+		request: schema::request::Request,
+	) -> Result<schema::response::Response, HandleRequestError> {
+		let payload = schema::Request { request: Some(request) }.encode_to_vec();
+		let response = handler.handle_request(PeerId::random(), payload)?;
+		Ok(schema::Response::decode(&response[..]).unwrap().response.unwrap())
+	}
+
+	fn read_at(block: Hash, keys: Vec<Vec<u8>>) -> schema::BlockRead {
+		schema::BlockRead { block: block.encode(), keys, child_reads: Vec::new() }
+	}
+
+	fn headers(
+		handler: &mut Handler,
+		from: Hash,
+		max_headers: u32,
+		direction: schema::Direction,
+	) -> Vec<Hash> {
+		let request = schema::RemoteHeadersRequest {
+			from_block: from.encode(),
+			max_headers,
+			direction: direction as i32,
+		};
+		match send(handler, schema::request::Request::RemoteHeadersRequest(request)).unwrap() {
+			schema::response::Response::RemoteHeadersResponse(response) => response
+				.headers
+				.iter()
+				.map(|h| Header::decode(&mut &h[..]).unwrap().hash())
+				.collect(),
+			response => panic!("Unexpected response: {:?}", response),
+		}
+	}
+
+	#[test]
+	fn batched_reads_are_proven_per_block() {
+		let (mut handler, canonical, fork) = setup();
+		let unknown = Hash::repeat_byte(42);
+		let reads = vec![
+			read_at(canonical[3], vec![vec![1], well_known_keys::CODE.to_vec()]),
+			read_at(unknown, vec![vec![1]]),
+			read_at(fork, vec![vec![1]]),
+		];
+
+		let request = schema::RemoteReadRequest { reads };
+		let proofs = match send(&mut handler, schema::request::Request::RemoteReadRequest(request))
+			.unwrap()
+		{
+			schema::response::Response::RemoteReadResponse(response) => response.proofs,
+			response => panic!("Unexpected response: {:?}", response),
+		};
+		assert_eq!(proofs.len(), 3);
+		assert!(proofs[1].proof.is_none());
+
+		let check = |block: Hash, proof: &schema::ReadProof, key: &[u8]| {
+			let root = handler.client.header(block).unwrap().unwrap().state_root;
+			let proof = StorageProof::decode(&mut &proof.proof.as_ref().unwrap()[..]).unwrap();
+			sp_state_machine::read_proof_check::<BlakeTwo256, _>(root, proof, [key])
+				.unwrap()
+				.remove(key)
+				.unwrap()
+		};
+		assert_eq!(check(canonical[3], &proofs[0], &[1]), None);
+		assert!(check(canonical[3], &proofs[0], well_known_keys::CODE).is_some());
+		assert_eq!(check(fork, &proofs[2], &[1]), Some(vec![2]));
+	}
+
+	#[test]
+	fn read_requests_are_bounded() {
+		let (mut handler, canonical, _) = setup();
+		let mut request = |reads| {
+			send(
+				&mut handler,
+				schema::request::Request::RemoteReadRequest(schema::RemoteReadRequest { reads }),
+			)
+		};
+
+		assert!(matches!(request(Vec::new()), Err(HandleRequestError::BadRequest(_))));
+		assert!(matches!(
+			request(vec![read_at(canonical[0], Vec::new())]),
+			Err(HandleRequestError::BadRequest(_)),
+		));
+		assert!(matches!(
+			request(vec![read_at(canonical[0], vec![vec![1]]); MAX_BLOCK_READS + 1]),
+			Err(HandleRequestError::BadRequest(_)),
+		));
+
+		let keys: Vec<_> = (0..=MAX_READ_KEYS as u32).map(|i| i.encode()).collect();
+		let (first, second) = keys.split_at(MAX_READ_KEYS / 2);
+		let reads =
+			vec![read_at(canonical[0], first.to_vec()), read_at(canonical[1], second.to_vec())];
+		assert!(matches!(request(reads), Err(HandleRequestError::BadRequest(_))));
+
+		let reads = vec![read_at(canonical[0], keys[..MAX_READ_KEYS].to_vec())];
+		assert!(request(reads).is_ok());
+	}
+
+	#[test]
+	fn range_reads_cover_keys_with_prefix() {
+		let (mut handler, canonical, _) = setup();
+		let request = schema::RemoteReadRangeRequest {
+			block: canonical[0].encode(),
+			storage_key: None,
+			prefix: Some(well_known_keys::CODE.to_vec()),
+			start_key: None,
+		};
+
+		match send(&mut handler, schema::request::Request::RemoteReadRangeRequest(request)).unwrap()
+		{
+			schema::response::Response::RemoteReadRangeResponse(response) => {
+				assert!(response.count.unwrap() >= 1);
+				assert!(response.proof.is_some());
+			},
+			response => panic!("Unexpected response: {:?}", response),
+		}
+
+		let request = schema::RemoteReadRangeRequest {
+			block: Hash::repeat_byte(42).encode(),
+			storage_key: None,
+			prefix: None,
+			start_key: None,
+		};
+		match send(&mut handler, schema::request::Request::RemoteReadRangeRequest(request)).unwrap()
+		{
+			schema::response::Response::RemoteReadRangeResponse(response) => {
+				assert_eq!(response.count, None);
+				assert_eq!(response.proof, None);
+			},
+			response => panic!("Unexpected response: {:?}", response),
+		}
+	}
+
+	#[test]
+	fn headers_follow_the_requested_direction() {
+		let (mut handler, canonical, fork) = setup();
+		use schema::Direction::{Ascending, Descending};
+
+		assert_eq!(headers(&mut handler, canonical[0], 10, Ascending), canonical);
+		assert_eq!(headers(&mut handler, canonical[1], 2, Ascending), &canonical[1..3]);
+		assert_eq!(
+			headers(&mut handler, canonical[3], 2, Descending),
+			vec![canonical[3], canonical[2]]
+		);
+		assert_eq!(
+			headers(&mut handler, fork, 10, Descending),
+			vec![fork, canonical[1], canonical[0]],
+		);
+		assert!(headers(&mut handler, Hash::repeat_byte(42), 10, Descending).is_empty());
+
+		let request = schema::RemoteHeadersRequest {
+			from_block: canonical[0].encode(),
+			max_headers: 0,
+			direction: Ascending as i32,
+		};
+		assert!(matches!(
+			send(&mut handler, schema::request::Request::RemoteHeadersRequest(request)),
+			Err(HandleRequestError::BadRequest(_)),
+		));
+	}
+
+	#[test]
+	fn ascending_headers_from_non_canonical_block_are_empty() {
+		let (mut handler, _, fork) = setup();
+
+		assert!(headers(&mut handler, fork, 10, schema::Direction::Ascending).is_empty());
+	}
+}
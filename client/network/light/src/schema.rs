@@ -24,6 +24,12 @@ pub(crate) mod v1 {
 	}
 }
 
+pub(crate) mod v2 {
+	pub(crate) mod light {
+		include!(concat!(env!("OUT_DIR"), "/api.v2.light.rs"));
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use prost::Message as _;
@@ -66,4 +72,34 @@ mod tests {
 
 		assert_eq!(encoded, vec![(2 << 3) | 2, 6, (2 << 3) | 2, 4, 1, 2, 3, 4]);
 	}
+
+	#[test]
+	fn batched_proofs_keep_their_position() {
+		let encoded = super::v2::light::Response {
+			response: Some(super::v2::light::response::Response::RemoteReadResponse(
+				super::v2::light::RemoteReadResponse {
+					proofs: vec![
+						super::v2::light::ReadProof { proof: None },
+						super::v2::light::ReadProof { proof: Some(vec![1]) },
+					],
+				},
+			)),
+		}
+		.encode_to_vec();
+
+		// Make sure that a missing proof is encoded as an empty message rather than skipped.
+		assert_eq!(
+			encoded,
+			vec![(2 << 3) | 2, 7, (1 << 3) | 2, 0, (1 << 3) | 2, 3, (1 << 3) | 2, 1, 1]
+		);
+
+		let decoded = super::v2::light::Response::decode(&encoded[..]).unwrap();
+		let Some(super::v2::light::response::Response::RemoteReadResponse(response)) =
+			decoded.response
+		else {
+			panic!("Unexpected response: {:?}", decoded)
+		};
+		assert_eq!(response.proofs[0].proof, None);
+		assert_eq!(response.proofs[1].proof, Some(vec![1]));
+	}
 }
@@ -0,0 +1,376 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Enforcement of the [`BandwidthLimits`].
+//!
+//! Every limit is a token bucket, a [`RateLimiter`], shared by all the connections or substreams
+//! it applies to. The global limits wrap the raw TCP and WebSocket connections of the transport
+//! and the substreams of QUIC connections, see [`ThrottledMuxer`], while the limits of individual
+//! protocols are applied by the request-response codec and the notifications handler, which know
+//! what protocol the bytes belong to.
+
+use crate::{config::BandwidthLimits, types::ProtocolName};
+
+use futures::{
+	io::{AsyncRead, AsyncWrite},
+	ready, FutureExt,
+};
+use futures_timer::Delay;
+use libp2p::core::muxing::{StreamMuxer, StreamMuxerEvent};
+use parking_lot::Mutex;
+use std::{
+	collections::HashMap,
+	io,
+	num::NonZeroU32,
+	pin::Pin,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	task::{Context, Poll},
+	time::{Duration, Instant},
+};
+
+/// Token bucket allowing up to one second worth of data to be transferred in a burst.
+#[derive(Debug)]
+pub struct RateLimiter {
+	bytes_per_second: f64,
+	bucket: Mutex<Bucket>,
+	/// Number of bytes that went through the limiter.
+	total_bytes: AtomicU64,
+	/// Number of times a transfer had to wait for the bucket to refill.
+	total_delays: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+	/// Number of bytes that can be transferred right now. Negative if more than that was
+	/// transferred at once.
+	available: f64,
+	last_refill: Instant,
+}
+
+impl RateLimiter {
+	/// Creates a limiter allowing `bytes_per_second` bytes per second on average.
+	pub fn new(bytes_per_second: NonZeroU32) -> Self {
+		let bytes_per_second = f64::from(bytes_per_second.get());
+		Self {
+			bytes_per_second,
+			bucket: Mutex::new(Bucket { available: bytes_per_second, last_refill: Instant::now() }),
+			total_bytes: AtomicU64::new(0),
+			total_delays: AtomicU64::new(0),
+		}
+	}
+
+	/// Returns the number of bytes that can be transferred right now, which is always at least
+	/// one.
+	///
+	/// If none can, `delay` is used to wake up the task once the bucket has been refilled.
+	pub fn poll_available(&self, cx: &mut Context, delay: &mut Option<Delay>) -> Poll<usize> {
+		loop {
+			if let Some(timer) = delay {
+				ready!(timer.poll_unpin(cx));
+				*delay = None;
+			}
+
+			let mut bucket = self.bucket.lock();
+			let now = Instant::now();
+			let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+			bucket.available =
+				(bucket.available + elapsed * self.bytes_per_second).min(self.bytes_per_second);
+			bucket.last_refill = now;
+
+			if bucket.available >= 1.0 {
+				return Poll::Ready(bucket.available as usize)
+			}
+
+			self.total_delays.fetch_add(1, Ordering::Relaxed);
+			let missing = 1.0 - bucket.available;
+			*delay = Some(Delay::new(Duration::from_secs_f64(missing / self.bytes_per_second)));
+		}
+	}
+
+	/// Takes `bytes` out of the bucket, possibly more than are available.
+	pub fn consume(&self, bytes: usize) {
+		self.bucket.lock().available -= bytes as f64;
+		self.total_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+	}
+
+	/// Number of bytes that went through the limiter.
+	pub fn total_bytes(&self) -> u64 {
+		self.total_bytes.load(Ordering::Relaxed)
+	}
+
+	/// Number of times a transfer had to wait because the limit was reached.
+	pub fn total_delays(&self) -> u64 {
+		self.total_delays.load(Ordering::Relaxed)
+	}
+}
+
+/// The limiters applying to one direction each of some traffic.
+#[derive(Debug, Clone, Default)]
+pub struct Throttle {
+	/// Limiter of the received data, if any.
+	pub inbound: Option<Arc<RateLimiter>>,
+	/// Limiter of the sent data, if any.
+	pub outbound: Option<Arc<RateLimiter>>,
+}
+
+impl Throttle {
+	fn new(inbound: Option<NonZeroU32>, outbound: Option<NonZeroU32>) -> Self {
+		Self {
+			inbound: inbound.map(|limit| Arc::new(RateLimiter::new(limit))),
+			outbound: outbound.map(|limit| Arc::new(RateLimiter::new(limit))),
+		}
+	}
+}
+
+/// All the limiters built from the [`BandwidthLimits`] of the node.
+#[derive(Debug, Default)]
+pub struct BandwidthLimiters {
+	/// Limits applying to all the connections.
+	pub global: Throttle,
+	/// Limits shared by the request-response protocols without limits of their own.
+	requests: Throttle,
+	/// Limits of individual protocols.
+	protocols: HashMap<ProtocolName, Throttle>,
+}
+
+impl BandwidthLimiters {
+	/// Builds the limiters enforcing `limits`.
+	pub fn new(limits: &BandwidthLimits) -> Self {
+		let share = |limit: Option<NonZeroU32>| {
+			limit.and_then(|limit| {
+				let share =
+					u64::from(limit.get()) * u64::from(limits.requests_share.min(100)) / 100;
+				NonZeroU32::new(share as u32)
+			})
+		};
+
+		Self {
+			global: Throttle::new(limits.inbound, limits.outbound),
+			requests: Throttle::new(share(limits.inbound), share(limits.outbound)),
+			protocols: limits
+				.protocols
+				.iter()
+				.map(|(protocol, limits)| {
+					(protocol.clone(), Throttle::new(limits.inbound, limits.outbound))
+				})
+				.collect(),
+		}
+	}
+
+	/// Returns the limiters of the request-response protocol `protocol`.
+	pub fn request_response(&self, protocol: &ProtocolName) -> Throttle {
+		self.protocols.get(protocol).unwrap_or(&self.requests).clone()
+	}
+
+	/// Returns the limiters of the notification protocol `protocol`.
+	pub fn notifications(&self, protocol: &ProtocolName) -> Throttle {
+		self.protocols.get(protocol).cloned().unwrap_or_default()
+	}
+
+	/// Returns all the limiters, along with the name of what they limit and the direction they
+	/// apply to.
+	pub fn limiters(&self) -> impl Iterator<Item = (&str, &'static str, &RateLimiter)> {
+		iter_throttle("global", &self.global)
+			.chain(iter_throttle("requests", &self.requests))
+			.chain(
+				self.protocols
+					.iter()
+					.flat_map(|(protocol, throttle)| iter_throttle(protocol, throttle)),
+			)
+	}
+}
+
+fn iter_throttle<'a>(
+	name: &'a str,
+	throttle: &'a Throttle,
+) -> impl Iterator<Item = (&'a str, &'static str, &'a RateLimiter)> {
+	throttle
+		.inbound
+		.iter()
+		.map(move |limiter| (name, "in", &**limiter))
+		.chain(throttle.outbound.iter().map(move |limiter| (name, "out", &**limiter)))
+}
+
+/// Wraps around an `AsyncRead + AsyncWrite` and throttles the reads and writes.
+#[pin_project::pin_project]
+pub struct Throttled<S> {
+	#[pin]
+	inner: S,
+	throttle: Throttle,
+	read_delay: Option<Delay>,
+	write_delay: Option<Delay>,
+}
+
+impl<S> Throttled<S> {
+	/// Throttles `inner` with the limiters of `throttle`.
+	pub fn new(inner: S, throttle: Throttle) -> Self {
+		Self { inner, throttle, read_delay: None, write_delay: None }
+	}
+}
+
+impl<S: AsyncRead> AsyncRead for Throttled<S> {
+	fn poll_read(
+		self: Pin<&mut Self>,
+		cx: &mut Context,
+		buf: &mut [u8],
+	) -> Poll<io::Result<usize>> {
+		let this = self.project();
+		let Some(limiter) = &this.throttle.inbound else { return this.inner.poll_read(cx, buf) };
+
+		let available = ready!(limiter.poll_available(cx, this.read_delay));
+		let len = buf.len().min(available);
+		let read = ready!(this.inner.poll_read(cx, &mut buf[..len]))?;
+		limiter.consume(read);
+		Poll::Ready(Ok(read))
+	}
+}
+
+impl<S: AsyncWrite> AsyncWrite for Throttled<S> {
+	fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+		let this = self.project();
+		let Some(limiter) = &this.throttle.outbound else { return this.inner.poll_write(cx, buf) };
+
+		let available = ready!(limiter.poll_available(cx, this.write_delay));
+		let len = buf.len().min(available);
+		let written = ready!(this.inner.poll_write(cx, &buf[..len]))?;
+		limiter.consume(written);
+		Poll::Ready(Ok(written))
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_flush(cx)
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+		self.project().inner.poll_close(cx)
+	}
+}
+
+/// Wraps around a [`StreamMuxer`] and throttles all its substreams.
+///
+/// Used for the QUIC connections, which can't be throttled below the encryption and multiplexing
+/// layers. Only the payload of the substreams is accounted for.
+#[pin_project::pin_project]
+pub struct ThrottledMuxer<M> {
+	#[pin]
+	inner: M,
+	throttle: Throttle,
+}
+
+impl<M> ThrottledMuxer<M> {
+	/// Throttles the substreams of `inner` with the limiters of `throttle`.
+	pub fn new(inner: M, throttle: Throttle) -> Self {
+		Self { inner, throttle }
+	}
+}
+
+impl<M: StreamMuxer> StreamMuxer for ThrottledMuxer<M> {
+	type Substream = Throttled<M::Substream>;
+	type Error = M::Error;
+
+	fn poll_inbound(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<Self::Substream, Self::Error>> {
+		let this = self.project();
+		let substream = ready!(this.inner.poll_inbound(cx))?;
+		Poll::Ready(Ok(Throttled::new(substream, this.throttle.clone())))
+	}
+
+	fn poll_outbound(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<Self::Substream, Self::Error>> {
+		let this = self.project();
+		let substream = ready!(this.inner.poll_outbound(cx))?;
+		Poll::Ready(Ok(Throttled::new(substream, this.throttle.clone())))
+	}
+
+	fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.project().inner.poll_close(cx)
+	}
+
+	fn poll(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+	) -> Poll<Result<StreamMuxerEvent, Self::Error>> {
+		self.project().inner.poll(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::config::ProtocolBandwidthLimits;
+	use futures::io::{AsyncReadExt, Cursor};
+
+	fn limit(bytes_per_second: u32) -> Option<NonZeroU32> {
+		NonZeroU32::new(bytes_per_second)
+	}
+
+	#[tokio::test]
+	async fn reads_are_throttled() {
+		let throttle = Throttle::new(limit(1000), None);
+		let limiter = throttle.inbound.clone().unwrap();
+		let mut stream = Throttled::new(Cursor::new(vec![0; 1500]), throttle);
+
+		let start = Instant::now();
+		let mut buffer = Vec::new();
+		stream.read_to_end(&mut buffer).await.unwrap();
+
+		// The first second worth of data is read right away, the rest has to wait.
+		assert_eq!(buffer.len(), 1500);
+		assert!(start.elapsed() >= Duration::from_millis(400));
+		assert_eq!(limiter.total_bytes(), 1500);
+		assert!(limiter.total_delays() > 0);
+	}
+
+	#[test]
+	fn requests_share_global_limits() {
+		let sync = ProtocolName::from("/sync/2");
+		let grandpa = ProtocolName::from("/grandpa/1");
+		let state = ProtocolName::from("/state/2");
+		let limiters = BandwidthLimiters::new(&BandwidthLimits {
+			inbound: limit(1000),
+			outbound: None,
+			requests_share: 75,
+			protocols: [(
+				state.clone(),
+				ProtocolBandwidthLimits { inbound: None, outbound: limit(10) },
+			)]
+			.into_iter()
+			.collect(),
+		});
+
+		let requests = limiters.request_response(&sync);
+		assert_eq!(requests.inbound.unwrap().bytes_per_second, 750.0);
+		assert!(requests.outbound.is_none());
+
+		let state = limiters.request_response(&state);
+		assert!(state.inbound.is_none());
+		assert_eq!(state.outbound.unwrap().bytes_per_second, 10.0);
+
+		let grandpa = limiters.notifications(&grandpa);
+		assert!(grandpa.inbound.is_none() && grandpa.outbound.is_none());
+
+		assert_eq!(limiters.limiters().count(), 3);
+	}
+}
@@ -42,12 +42,14 @@ use zeroize::Zeroize;
 
 use sp_runtime::traits::Block as BlockT;
 use std::{
+	collections::HashMap,
 	error::Error,
 	fmt, fs,
 	future::Future,
 	io::{self, Write},
 	iter,
 	net::Ipv4Addr,
+	num::NonZeroU32,
 	path::{Path, PathBuf},
 	pin::Pin,
 	str::{self, FromStr},
@@ -246,6 +248,10 @@ pub enum TransportConfig {
 		/// [RFC1918](https://tools.ietf.org/html/rfc1918)). Irrelevant for addresses that have
 		/// been passed in `::sc_network::config::NetworkConfiguration::boot_nodes`.
 		allow_private_ip: bool,
+
+		/// If true, the QUIC transport is enabled in addition to TCP and WebSocket. Only then
+		/// `/quic` addresses can be listened on and dialed.
+		enable_quic: bool,
 	},
 
 	/// Only allow connections within the same process.
@@ -307,6 +313,47 @@ impl Default for SyncMode {
 	}
 }
 
+/// What is downloaded for the blocks preceding the block warp sync started from.
+///
+/// Headers are always downloaded, since they are needed to link the gap to genesis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GapSyncMode {
+	/// Download headers only.
+	Headers,
+	/// Download headers and justifications.
+	HeadersAndJustifications,
+	/// Download headers, justifications and block bodies.
+	Full {
+		/// Only download bodies of the last `body_depth` blocks of the gap. Blocks below that
+		/// are downloaded as in [`Self::HeadersAndJustifications`]. `None` downloads all of them.
+		body_depth: Option<u32>,
+	},
+}
+
+impl GapSyncMode {
+	/// Limit the number of gap blocks whose bodies are downloaded to `depth`.
+	///
+	/// Modes that don't download bodies are returned unchanged.
+	pub fn limit_body_depth(self, depth: u32) -> Self {
+		match self {
+			Self::Full { body_depth } =>
+				Self::Full { body_depth: Some(body_depth.map_or(depth, |d| d.min(depth))) },
+			mode => mode,
+		}
+	}
+
+	/// Returns if justifications are downloaded for gap blocks.
+	pub fn justifications(&self) -> bool {
+		!matches!(self, Self::Headers)
+	}
+}
+
+impl Default for GapSyncMode {
+	fn default() -> Self {
+		Self::Full { body_depth: None }
+	}
+}
+
 /// The configuration of a node's secret key, describing the type of key
 /// and how it is obtained. A node's identity keypair is the result of
 /// the evaluation of the node key configuration.
@@ -597,6 +644,9 @@ pub struct NetworkConfiguration {
 	/// Initial syncing mode.
 	pub sync_mode: SyncMode,
 
+	/// What to download for the blocks skipped by warp sync.
+	pub gap_sync_mode: GapSyncMode,
+
 	/// True if Kademlia random discovery should be enabled.
 	///
 	/// If true, the node will automatically randomly walk the DHT in order to find new peers.
@@ -612,6 +662,13 @@ pub struct NetworkConfiguration {
 	/// Enable serving block data over IPFS bitswap.
 	pub ipfs_server: bool,
 
+	/// Export snapshots of finalized state and serve them to other nodes.
+	pub serve_snapshots: bool,
+
+	/// Number of blocks between two snapshots of finalized state, both exported and downloaded.
+	/// Must be the same across the network.
+	pub snapshot_period: u32,
+
 	/// Size of Yamux receive window of all substreams. `None` for the default (256kiB).
 	/// Any value less than 256kiB is invalid.
 	///
@@ -632,6 +689,9 @@ pub struct NetworkConfiguration {
 	/// a modification of the way the implementation works. Different nodes with different
 	/// configured values remain compatible with each other.
 	pub yamux_window_size: Option<u32>,
+
+	/// Limits on the bandwidth used by the node.
+	pub bandwidth_limits: BandwidthLimits,
 }
 
 impl NetworkConfiguration {
@@ -655,15 +715,23 @@ impl NetworkConfiguration {
 			extra_sets: Vec::new(),
 			client_version: client_version.into(),
 			node_name: node_name.into(),
-			transport: TransportConfig::Normal { enable_mdns: false, allow_private_ip: true },
+			transport: TransportConfig::Normal {
+				enable_mdns: false,
+				allow_private_ip: true,
+				enable_quic: false,
+			},
 			max_parallel_downloads: 5,
 			max_blocks_per_request: 64,
 			sync_mode: SyncMode::Full,
+			gap_sync_mode: GapSyncMode::default(),
 			enable_dht_random_walk: true,
 			allow_non_globals_in_dht: false,
 			kademlia_disjoint_query_paths: false,
 			yamux_window_size: None,
 			ipfs_server: false,
+			serve_snapshots: false,
+			snapshot_period: sc_client_api::snapshot::SNAPSHOT_PERIOD,
+			bandwidth_limits: Default::default(),
 		}
 	}
 
@@ -698,6 +766,56 @@ impl NetworkConfiguration {
 	}
 }
 
+/// Percentage of the global bandwidth limits that request-response protocols without a limit of
+/// their own may use in total.
+///
+/// Keeps syncing peers from starving notification protocols, such as GRANDPA and BEEFY gossip,
+/// when the bandwidth is constrained.
+pub const DEFAULT_REQUESTS_BANDWIDTH_SHARE: u8 = 75;
+
+/// Limits on the bandwidth used by the node, in bytes per second.
+///
+/// There is no strict priority between protocols. Notification protocols, such as GRANDPA and
+/// BEEFY gossip, are only subject to the global limits, and are guaranteed the part of them that
+/// [`BandwidthLimits::requests_share`] keeps away from the request-response protocols.
+#[derive(Clone, Debug)]
+pub struct BandwidthLimits {
+	/// Maximum rate at which data is received, over all connections.
+	pub inbound: Option<NonZeroU32>,
+
+	/// Maximum rate at which data is sent, over all connections.
+	pub outbound: Option<NonZeroU32>,
+
+	/// Percentage of [`BandwidthLimits::inbound`] and [`BandwidthLimits::outbound`] that
+	/// request-response protocols without an entry in [`BandwidthLimits::protocols`] may use in
+	/// total. Notification protocols are only subject to the global limits.
+	pub requests_share: u8,
+
+	/// Limits of individual request-response and notification protocols.
+	pub protocols: HashMap<ProtocolName, ProtocolBandwidthLimits>,
+}
+
+impl Default for BandwidthLimits {
+	fn default() -> Self {
+		Self {
+			inbound: None,
+			outbound: None,
+			requests_share: DEFAULT_REQUESTS_BANDWIDTH_SHARE,
+			protocols: HashMap::new(),
+		}
+	}
+}
+
+/// Bandwidth limits of a single protocol, in bytes per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProtocolBandwidthLimits {
+	/// Maximum rate at which data of the protocol is received, over all peers.
+	pub inbound: Option<NonZeroU32>,
+
+	/// Maximum rate at which data of the protocol is sent, over all peers.
+	pub outbound: Option<NonZeroU32>,
+}
+
 /// Network initialization parameters.
 pub struct Params<Block: BlockT> {
 	/// Assigned role for our node (full, light, ...).
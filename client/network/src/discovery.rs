@@ -101,6 +101,7 @@ pub struct DiscoveryConfig {
 	allow_non_globals_in_dht: bool,
 	discovery_only_if_under_num: u64,
 	enable_mdns: bool,
+	enable_quic: bool,
 	kademlia_disjoint_query_paths: bool,
 	kademlia_protocols: Vec<Vec<u8>>,
 }
@@ -116,6 +117,7 @@ impl DiscoveryConfig {
 			allow_non_globals_in_dht: false,
 			discovery_only_if_under_num: std::u64::MAX,
 			enable_mdns: false,
+			enable_quic: false,
 			kademlia_disjoint_query_paths: false,
 			kademlia_protocols: Vec::new(),
 		}
@@ -161,6 +163,12 @@ impl DiscoveryConfig {
 		self
 	}
 
+	/// Does the local node support QUIC? If not, discovered `/quic` addresses are ignored.
+	pub fn with_quic(&mut self, value: bool) -> &mut Self {
+		self.enable_quic = value;
+		self
+	}
+
 	/// Add discovery via Kademlia for the given protocol.
 	///
 	/// Currently accepts `protocol_id`. This should be removed once all the nodes
@@ -194,6 +202,7 @@ impl DiscoveryConfig {
 			allow_non_globals_in_dht,
 			discovery_only_if_under_num,
 			enable_mdns,
+			enable_quic,
 			kademlia_disjoint_query_paths,
 			kademlia_protocols,
 		} = self;
@@ -246,6 +255,7 @@ impl DiscoveryConfig {
 				None
 			},
 			allow_non_globals_in_dht,
+			enable_quic,
 			known_external_addresses: LruHashSet::new(
 				NonZeroUsize::new(MAX_KNOWN_EXTERNAL_ADDRESSES)
 					.expect("value is a constant; constant is non-zero; qed."),
@@ -286,6 +296,8 @@ pub struct DiscoveryBehaviour {
 	discovery_only_if_under_num: u64,
 	/// Should non-global addresses be added to the DHT?
 	allow_non_globals_in_dht: bool,
+	/// If false, discovered QUIC addresses are ignored, as we couldn't dial them anyway.
+	enable_quic: bool,
 	/// A cache of discovered external addresses. Only used for logging purposes.
 	known_external_addresses: LruHashSet<Multiaddr>,
 	/// Records to publish per QueryId.
@@ -351,6 +363,14 @@ impl DiscoveryBehaviour {
 				return
 			}
 
+			if !self.enable_quic && is_quic_address(&addr) {
+				trace!(
+					target: "sub-libp2p",
+					"Ignoring self-reported QUIC address {} from {}.", addr, peer_id
+				);
+				return
+			}
+
 			if let Some(matching_protocol) = supported_protocols
 				.iter()
 				.find(|p| kademlia.protocol_names().iter().any(|k| k.as_ref() == p.as_ref()))
@@ -439,6 +459,11 @@ impl DiscoveryBehaviour {
 	}
 }
 
+/// Returns whether `addr` is a QUIC address, which can only be dialed with QUIC enabled.
+fn is_quic_address(addr: &Multiaddr) -> bool {
+	addr.iter().any(|p| matches!(p, Protocol::Quic))
+}
+
 /// Event generated by the `DiscoveryBehaviour`.
 #[derive(Debug)]
 pub enum DiscoveryOut {
@@ -517,6 +542,10 @@ impl NetworkBehaviour for DiscoveryBehaviour {
 				});
 			}
 
+			if !self.enable_quic {
+				list_to_filter.retain(|addr| !is_quic_address(addr));
+			}
+
 			list.extend(list_to_filter);
 		}
 
@@ -914,8 +943,8 @@ mod tests {
 		},
 		identity::{ed25519, Keypair},
 		noise,
-		swarm::{Executor, Swarm, SwarmEvent},
-		yamux, Multiaddr,
+		swarm::{Executor, NetworkBehaviour, Swarm, SwarmEvent},
+		yamux, Multiaddr, PeerId,
 	};
 	use sp_core::hash::H256;
 	use std::{collections::HashSet, pin::Pin, task::Poll};
@@ -1143,4 +1172,38 @@ mod tests {
 			);
 		}
 	}
+
+	#[test]
+	fn discovery_ignores_quic_addresses_if_quic_is_disabled() {
+		let genesis_hash = H256::from_low_u64_be(1);
+		let protocol_id = ProtocolId::from("a");
+		let quic_addr: Multiaddr = "/ip4/127.0.0.1/udp/30333/quic".parse().unwrap();
+		let tcp_addr: Multiaddr = "/ip4/127.0.0.1/tcp/30333".parse().unwrap();
+
+		for enable_quic in [false, true] {
+			let mut discovery = {
+				let keypair = Keypair::generate_ed25519();
+				let mut config = DiscoveryConfig::new(keypair.public());
+				config
+					.allow_private_ip(true)
+					.allow_non_globals_in_dht(true)
+					.with_quic(enable_quic)
+					.with_kademlia(genesis_hash, None, &protocol_id);
+				config.finish()
+			};
+
+			let remote_peer_id = PeerId::random();
+			for addr in [&tcp_addr, &quic_addr] {
+				discovery.add_self_reported_address(
+					&remote_peer_id,
+					&[kademlia_protocol_name(genesis_hash, None)],
+					addr.clone(),
+				);
+			}
+
+			let addresses = discovery.addresses_of_peer(&remote_peer_id);
+			assert!(addresses.contains(&tcp_addr));
+			assert_eq!(addresses.contains(&quic_addr), enable_quic);
+		}
+	}
 }
@@ -19,7 +19,7 @@
 //! Network event types. These are are not the part of the protocol, but rather
 //! events that happen on the network like DHT get/put results received.
 
-use crate::{types::ProtocolName, NotificationsSink};
+use crate::{service::traits::MessageSink, types::ProtocolName};
 
 use bytes::Bytes;
 use futures::channel::oneshot;
@@ -105,7 +105,7 @@ pub enum SyncEvent<B: BlockT> {
 		/// Received handshake.
 		received_handshake: BlockAnnouncesHandshake<B>,
 		/// Notification sink.
-		sink: NotificationsSink,
+		sink: Box<dyn MessageSink>,
 		/// Channel for reporting accept/reject of the substream.
 		tx: oneshot::Sender<bool>,
 	},
@@ -122,7 +122,7 @@ pub enum SyncEvent<B: BlockT> {
 		/// Node we closed the substream with.
 		remote: PeerId,
 		/// Notification sink.
-		sink: NotificationsSink,
+		sink: Box<dyn MessageSink>,
 	},
 
 	/// Received one or more messages from the given node using the given protocol.
@@ -244,6 +244,7 @@
 //!
 //! More precise usage details are still being worked on and will likely change in the future.
 
+mod bandwidth;
 mod behaviour;
 mod discovery;
 mod peer_info;
@@ -271,10 +272,12 @@ pub use sc_network_common::{
 	},
 };
 pub use service::{
+	ban_list::{Ban, BanList, BanTarget},
 	signature::Signature,
 	traits::{
-		KademliaKey, NetworkBlock, NetworkDHTProvider, NetworkEventStream, NetworkNotification,
-		NetworkPeers, NetworkRequest, NetworkSigner, NetworkStateInfo, NetworkStatus,
+		KademliaKey, MessageSink, NetworkBackend, NetworkBlock, NetworkDHTProvider,
+		NetworkEventStream, NetworkNotification, NetworkPeerAdmin, NetworkPeers, NetworkRequest,
+		NetworkService as NetworkServiceT, NetworkSigner, NetworkStateInfo, NetworkStatus,
 		NetworkStatusProvider, NetworkSyncForkRequest, NotificationSender as NotificationSenderT,
 		NotificationSenderError, NotificationSenderReady,
 	},
@@ -283,7 +286,7 @@ pub use service::{
 };
 pub use types::ProtocolName;
 
-pub use sc_peerset::ReputationChange;
+pub use sc_peerset::{PeerReputation, ReputationChange};
 
 /// The maximum allowed number of established connections per peer.
 ///
@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
+	bandwidth::BandwidthLimiters,
 	config::{self, NonReservedPeerMode},
 	error,
 	types::ProtocolName,
@@ -104,6 +105,7 @@ impl<B: BlockT> Protocol<B> {
 		network_config: &config::NetworkConfiguration,
 		block_announces_protocol: config::NonDefaultSetConfig,
 		tx: TracingUnboundedSender<crate::event::SyncEvent<B>>,
+		bandwidth: &BandwidthLimiters,
 	) -> error::Result<(Self, sc_peerset::PeersetHandle, Vec<(PeerId, Multiaddr)>)> {
 		let mut known_addresses = Vec::new();
 
@@ -168,12 +170,17 @@ impl<B: BlockT> Protocol<B> {
 					fallback_names: block_announces_protocol.fallback_names.clone(),
 					handshake: block_announces_protocol.handshake.as_ref().unwrap().to_vec(),
 					max_notification_size: block_announces_protocol.max_notification_size,
+					throttle: bandwidth
+						.notifications(&block_announces_protocol.notifications_protocol),
 				})
-				.chain(network_config.extra_sets.iter().map(|s| notifications::ProtocolConfig {
-					name: s.notifications_protocol.clone(),
-					fallback_names: s.fallback_names.clone(),
-					handshake: s.handshake.as_ref().map_or(roles.encode(), |h| (*h).to_vec()),
-					max_notification_size: s.max_notification_size,
+				.chain(network_config.extra_sets.iter().map(|s| {
+					notifications::ProtocolConfig {
+						name: s.notifications_protocol.clone(),
+						fallback_names: s.fallback_names.clone(),
+						handshake: s.handshake.as_ref().map_or(roles.encode(), |h| (*h).to_vec()),
+						max_notification_size: s.max_notification_size,
+						throttle: bandwidth.notifications(&s.notifications_protocol),
+					}
 				})),
 			)
 		};
@@ -468,7 +475,7 @@ impl<B: BlockT> NetworkBehaviour for Protocol<B> {
 								crate::SyncEvent::NotificationStreamOpened {
 									remote: peer_id,
 									received_handshake: handshake,
-									sink: notifications_sink,
+									sink: Box::new(notifications_sink),
 									tx,
 								},
 							);
@@ -508,7 +515,7 @@ impl<B: BlockT> NetworkBehaviour for Protocol<B> {
 										crate::SyncEvent::NotificationStreamOpened {
 											remote: peer_id,
 											received_handshake: handshake,
-											sink: notifications_sink,
+											sink: Box::new(notifications_sink),
 											tx,
 										},
 									);
@@ -584,7 +591,7 @@ impl<B: BlockT> NetworkBehaviour for Protocol<B> {
 				} else if set_id == HARDCODED_PEERSETS_SYNC {
 					let _ = self.tx.unbounded_send(crate::SyncEvent::NotificationSinkReplaced {
 						remote: peer_id,
-						sink: notifications_sink,
+						sink: Box::new(notifications_sink),
 					});
 					CustomMessageOutcome::None
 				} else {
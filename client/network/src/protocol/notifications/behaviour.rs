@@ -17,6 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
+	bandwidth::Throttle,
 	protocol::notifications::handler::{
 		self, NotificationsSink, NotifsHandlerIn, NotifsHandlerOut, NotifsHandlerProto,
 	},
@@ -150,6 +151,8 @@ pub struct ProtocolConfig {
 	pub handshake: Vec<u8>,
 	/// Maximum allowed size for a notification.
 	pub max_notification_size: u64,
+	/// Bandwidth limiters of the protocol.
+	pub throttle: Throttle,
 }
 
 /// Identifier for a delay firing.
@@ -368,6 +371,7 @@ impl Notifications {
 				fallback_names: cfg.fallback_names,
 				handshake: Arc::new(RwLock::new(cfg.handshake)),
 				max_notification_size: cfg.max_notification_size,
+				throttle: cfg.throttle,
 			})
 			.collect::<Vec<_>>();
 
@@ -2130,6 +2134,7 @@ mod tests {
 					fallback_names: Vec::new(),
 					handshake: vec![1, 2, 3, 4],
 					max_notification_size: u64::MAX,
+					throttle: Default::default(),
 				}),
 			),
 			peerset_handle,
@@ -58,6 +58,7 @@
 //! [`NotifsHandlerIn::Open`] has gotten an answer.
 
 use crate::{
+	bandwidth::Throttle,
 	protocol::notifications::upgrade::{
 		NotificationsIn, NotificationsInSubstream, NotificationsOut, NotificationsOutSubstream,
 		UpgradeCollec,
@@ -71,6 +72,7 @@ use futures::{
 	lock::{Mutex as FuturesMutex, MutexGuard as FuturesMutexGuard},
 	prelude::*,
 };
+use futures_timer::Delay;
 use libp2p::{
 	core::{ConnectedPoint, PeerId},
 	swarm::{
@@ -151,6 +153,8 @@ pub struct ProtocolConfig {
 	pub handshake: Arc<RwLock<Vec<u8>>>,
 	/// Maximum allowed size for a notification.
 	pub max_notification_size: u64,
+	/// Bandwidth limiters of the protocol, shared by all connections.
+	pub throttle: Throttle,
 }
 
 /// Fields specific for each individual protocol.
@@ -163,6 +167,12 @@ struct Protocol {
 
 	/// Current state of the substreams for this protocol.
 	state: State,
+
+	/// Fires when the inbound bandwidth limiter allows receiving notifications again.
+	read_delay: Option<Delay>,
+
+	/// Fires when the outbound bandwidth limiter allows sending notifications again.
+	write_delay: Option<Delay>,
 }
 
 /// See the module-level documentation to learn about the meaning of these variants.
@@ -251,7 +261,13 @@ impl IntoConnectionHandler for NotifsHandlerProto {
 						config.max_notification_size,
 					);
 
-					Protocol { config, in_upgrade, state: State::Closed { pending_opening: false } }
+					Protocol {
+						config,
+						in_upgrade,
+						state: State::Closed { pending_opening: false },
+						read_delay: None,
+						write_delay: None,
+					}
 				})
 				.collect(),
 			peer_id: *peer_id,
@@ -733,10 +749,10 @@ impl ConnectionHandler for NotifsHandler {
 
 		// For each open substream, try send messages from `notifications_sink_rx` to the
 		// substream.
-		for protocol_index in 0..self.protocols.len() {
+		for protocol in &mut self.protocols {
 			if let State::Open {
 				notifications_sink_rx, out_substream: Some(out_substream), ..
-			} = &mut self.protocols[protocol_index].state
+			} = &mut protocol.state
 			{
 				loop {
 					// Only proceed with `out_substream.poll_ready_unpin` if there is an element
@@ -752,7 +768,13 @@ impl ConnectionHandler for NotifsHandler {
 					}
 
 					// Before we extract the element from `notifications_sink_rx`, check that the
-					// substream is ready to accept a message.
+					// bandwidth limit of the protocol isn't reached and that the substream is
+					// ready to accept a message.
+					if let Some(limiter) = &protocol.config.throttle.outbound {
+						if limiter.poll_available(cx, &mut protocol.write_delay).is_pending() {
+							break
+						}
+					}
 					match out_substream.poll_ready_unpin(cx) {
 						Poll::Ready(_) => {},
 						Poll::Pending => break,
@@ -771,6 +793,9 @@ impl ConnectionHandler for NotifsHandler {
 						},
 					};
 
+					if let Some(limiter) = &protocol.config.throttle.outbound {
+						limiter.consume(message.len());
+					}
 					let _ = out_substream.start_send_unpin(message);
 					// Note that flushing is performed later down this function.
 				}
@@ -806,30 +831,44 @@ impl ConnectionHandler for NotifsHandler {
 
 		// Poll inbound substreams.
 		for protocol_index in 0..self.protocols.len() {
+			let protocol = &mut self.protocols[protocol_index];
+
 			// Inbound substreams being closed is always tolerated, except for the
 			// `OpenDesiredByRemote` state which might need to be switched back to `Closed`.
-			match &mut self.protocols[protocol_index].state {
+			match &mut protocol.state {
 				State::Closed { .. } |
 				State::Open { in_substream: None, .. } |
 				State::Opening { in_substream: None } => {},
 
-				State::Open { in_substream: in_substream @ Some(_), .. } =>
+				State::Open { in_substream: in_substream @ Some(_), .. } => {
+					// Notifications are left in the substream while the bandwidth limit of the
+					// protocol is reached, applying back-pressure on the remote.
+					let limiter = &protocol.config.throttle.inbound;
+					if let Some(limiter) = limiter {
+						if limiter.poll_available(cx, &mut protocol.read_delay).is_pending() {
+							continue
+						}
+					}
+
 					match Stream::poll_next(Pin::new(in_substream.as_mut().unwrap()), cx) {
 						Poll::Pending => {},
 						Poll::Ready(Some(Ok(message))) => {
+							if let Some(limiter) = limiter {
+								limiter.consume(message.len());
+							}
 							let event = NotifsHandlerOut::Notification { protocol_index, message };
 							return Poll::Ready(ConnectionHandlerEvent::Custom(event))
 						},
 						Poll::Ready(None) | Poll::Ready(Some(Err(_))) => *in_substream = None,
-					},
+					}
+				},
 
 				State::OpenDesiredByRemote { in_substream, pending_opening } =>
 					match NotificationsInSubstream::poll_process(Pin::new(in_substream), cx) {
 						Poll::Pending => {},
 						Poll::Ready(Ok(void)) => match void {},
 						Poll::Ready(Err(_)) => {
-							self.protocols[protocol_index].state =
-								State::Closed { pending_opening: *pending_opening };
+							protocol.state = State::Closed { pending_opening: *pending_opening };
 							return Poll::Ready(ConnectionHandlerEvent::Custom(
 								NotifsHandlerOut::CloseDesired { protocol_index },
 							))
@@ -1059,9 +1098,12 @@ pub mod tests {
 				fallback_names: vec![],
 				handshake: Arc::new(RwLock::new(b"hello, world".to_vec())),
 				max_notification_size: u64::MAX,
+				throttle: Default::default(),
 			},
 			in_upgrade: NotificationsIn::new("/foo", Vec::new(), u64::MAX),
 			state: State::Closed { pending_opening: false },
+			read_delay: None,
+			write_delay: None,
 		};
 
 		NotifsHandler {
@@ -34,7 +34,11 @@
 //! - If provided, a ["requests processing"](ProtocolConfig::inbound_queue) channel
 //! is used to handle incoming requests.
 
-use crate::{types::ProtocolName, ReputationChange};
+use crate::{
+	bandwidth::{BandwidthLimiters, Throttle, Throttled},
+	types::ProtocolName,
+	ReputationChange,
+};
 
 use futures::{
 	channel::{mpsc, oneshot},
@@ -318,9 +322,12 @@ struct RequestProcessingOutcome {
 impl RequestResponsesBehaviour {
 	/// Creates a new behaviour. Must be passed a list of supported protocols. Returns an error if
 	/// the same protocol is passed twice.
+	///
+	/// The traffic of each protocol is throttled by the limiters `bandwidth` assigns to it.
 	pub fn new(
 		list: impl Iterator<Item = ProtocolConfig>,
 		peerset: PeersetHandle,
+		bandwidth: &BandwidthLimiters,
 	) -> Result<Self, RegisterError> {
 		let mut protocols = HashMap::new();
 		for protocol in list {
@@ -338,6 +345,7 @@ impl RequestResponsesBehaviour {
 				GenericCodec {
 					max_request_size: protocol.max_request_size,
 					max_response_size: protocol.max_response_size,
+					throttle: bandwidth.request_response(&protocol.name),
 				},
 				iter::once(protocol.name.as_bytes().to_vec())
 					.chain(protocol.fallback_names.iter().map(|name| name.as_bytes().to_vec()))
@@ -920,6 +928,7 @@ pub enum ResponseFailure {
 pub struct GenericCodec {
 	max_request_size: u64,
 	max_response_size: u64,
+	throttle: Throttle,
 }
 
 #[async_trait::async_trait]
@@ -928,14 +937,12 @@ impl RequestResponseCodec for GenericCodec {
 	type Request = Vec<u8>;
 	type Response = Result<Vec<u8>, ()>;
 
-	async fn read_request<T>(
-		&mut self,
-		_: &Self::Protocol,
-		mut io: &mut T,
-	) -> io::Result<Self::Request>
+	async fn read_request<T>(&mut self, _: &Self::Protocol, io: &mut T) -> io::Result<Self::Request>
 	where
 		T: AsyncRead + Unpin + Send,
 	{
+		let mut io = Throttled::new(io, self.throttle.clone());
+
 		// Read the length.
 		let length = unsigned_varint::aio::read_usize(&mut io)
 			.await
@@ -956,11 +963,13 @@ impl RequestResponseCodec for GenericCodec {
 	async fn read_response<T>(
 		&mut self,
 		_: &Self::Protocol,
-		mut io: &mut T,
+		io: &mut T,
 	) -> io::Result<Self::Response>
 	where
 		T: AsyncRead + Unpin + Send,
 	{
+		let mut io = Throttled::new(io, self.throttle.clone());
+
 		// Note that this function returns a `Result<Result<...>>`. Returning an `Err` is
 		// considered as a protocol error and will result in the entire connection being closed.
 		// Returning `Ok(Err(_))` signifies that a response has successfully been fetched, and
@@ -997,6 +1006,8 @@ impl RequestResponseCodec for GenericCodec {
 	where
 		T: AsyncWrite + Unpin + Send,
 	{
+		let mut io = Throttled::new(io, self.throttle.clone());
+
 		// TODO: check the length?
 		// Write the length.
 		{
@@ -1020,6 +1031,8 @@ impl RequestResponseCodec for GenericCodec {
 	where
 		T: AsyncWrite + Unpin + Send,
 	{
+		let mut io = Throttled::new(io, self.throttle.clone());
+
 		// If `res` is an `Err`, we jump to closing the substream without writing anything on it.
 		if let Ok(res) = res {
 			// TODO: check the length?
@@ -1093,7 +1106,7 @@ mod tests {
 
 		let (peerset, handle) = Peerset::from_config(config);
 
-		let behaviour = RequestResponsesBehaviour::new(list, handle).unwrap();
+		let behaviour = RequestResponsesBehaviour::new(list, handle, &Default::default()).unwrap();
 
 		let runtime = tokio::runtime::Runtime::new().unwrap();
 		let mut swarm = Swarm::with_executor(
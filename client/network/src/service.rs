@@ -28,6 +28,7 @@
 //! which is then processed by [`NetworkWorker::next_action`].
 
 use crate::{
+	bandwidth::BandwidthLimiters,
 	behaviour::{self, Behaviour, BehaviourOut},
 	config::{MultiaddrWithPeerId, Params, TransportConfig},
 	discovery::DiscoveryConfig,
@@ -39,12 +40,13 @@ use crate::{
 	protocol::{self, NotifsHandlerError, Protocol, Ready},
 	request_responses::{IfDisconnected, RequestFailure},
 	service::{
+		ban_list::{Ban, BanList, BanTarget},
 		signature::{Signature, SigningError},
 		traits::{
-			NetworkDHTProvider, NetworkEventStream, NetworkNotification, NetworkPeers,
-			NetworkRequest, NetworkSigner, NetworkStateInfo, NetworkStatus, NetworkStatusProvider,
-			NotificationSender as NotificationSenderT, NotificationSenderError,
-			NotificationSenderReady as NotificationSenderReadyT,
+			NetworkBackend, NetworkDHTProvider, NetworkEventStream, NetworkNotification,
+			NetworkPeerAdmin, NetworkPeers, NetworkRequest, NetworkSigner, NetworkStateInfo,
+			NetworkStatus, NetworkStatusProvider, NotificationSender as NotificationSenderT,
+			NotificationSenderError, NotificationSenderReady as NotificationSenderReadyT,
 		},
 	},
 	transport,
@@ -71,7 +73,7 @@ use metrics::{Histogram, HistogramVec, MetricSources, Metrics};
 use parking_lot::Mutex;
 
 use sc_network_common::ExHashT;
-use sc_peerset::PeersetHandle;
+use sc_peerset::{PeerReputation, PeersetHandle};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 use sp_runtime::traits::Block as BlockT;
 
@@ -87,6 +89,7 @@ use std::{
 		atomic::{AtomicUsize, Ordering},
 		Arc,
 	},
+	time::{Duration, SystemTime},
 };
 
 pub use behaviour::{InboundFailure, OutboundFailure, ResponseFailure};
@@ -96,6 +99,8 @@ pub use protocol::NotificationsSink;
 mod metrics;
 mod out_events;
 
+pub mod ban_list;
+
 pub mod signature;
 pub mod traits;
 
@@ -224,13 +229,32 @@ where
 			local_peer_id.to_base58(),
 		);
 
+		let bandwidth_limiters =
+			Arc::new(BandwidthLimiters::new(&params.network_config.bandwidth_limits));
+
 		let (protocol, peerset_handle, mut known_addresses) = Protocol::new(
 			From::from(&params.role),
 			&params.network_config,
 			params.block_announce_config,
 			params.tx,
+			&bandwidth_limiters,
 		)?;
 
+		let ban_list = match &params.network_config.net_config_path {
+			Some(path) => BanList::load(path).unwrap_or_else(|e| {
+				warn!(target: "sub-libp2p", "Failed to load the list of banned peers: {}", e);
+				BanList::default()
+			}),
+			None => BanList::default(),
+		};
+		let now = SystemTime::now();
+		for ban in ban_list.bans() {
+			if let BanTarget::Peer(peer_id) = ban.target {
+				let remaining = ban.expires_at.duration_since(now).unwrap_or_default();
+				peerset_handle.ban_peer(peer_id, remaining);
+			}
+		}
+
 		// List of multiaddresses that we know in the network.
 		let mut boot_node_ids = HashSet::new();
 
@@ -295,10 +319,11 @@ where
 					TransportConfig::Normal {
 						enable_mdns,
 						allow_private_ip: allow_private_ipv4,
-						..
+						enable_quic,
 					} => {
 						config.with_mdns(enable_mdns);
 						config.allow_private_ip(allow_private_ipv4);
+						config.with_quic(enable_quic);
 					},
 				}
 
@@ -306,9 +331,9 @@ where
 			};
 
 			let (transport, bandwidth) = {
-				let config_mem = match params.network_config.transport {
-					TransportConfig::MemoryOnly => true,
-					TransportConfig::Normal { .. } => false,
+				let (config_mem, enable_quic) = match params.network_config.transport {
+					TransportConfig::MemoryOnly => (true, false),
+					TransportConfig::Normal { enable_quic, .. } => (false, enable_quic),
 				};
 
 				// The yamux buffer size limit is configured to be equal to the maximum frame size
@@ -351,8 +376,10 @@ where
 				transport::build_transport(
 					local_identity.clone(),
 					config_mem,
+					enable_quic,
 					params.network_config.yamux_window_size,
 					yamux_maximum_buffer_size,
+					bandwidth_limiters.global.clone(),
 				)
 			};
 
@@ -364,6 +391,7 @@ where
 					discovery_config,
 					params.network_config.request_response_protocols,
 					peerset_handle.clone(),
+					&bandwidth_limiters,
 				);
 
 				match result {
@@ -409,6 +437,7 @@ where
 				registry,
 				MetricSources {
 					bandwidth: bandwidth.clone(),
+					bandwidth_limiters,
 					connected_peers: num_connected.clone(),
 				},
 			)?),
@@ -463,6 +492,7 @@ where
 			peers_notifications_sinks,
 			metrics,
 			boot_node_ids,
+			ban_list,
 			_marker: Default::default(),
 			_block: Default::default(),
 		})
@@ -623,40 +653,6 @@ where
 }
 
 impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
-	/// Get network state.
-	///
-	/// **Note**: Use this only for debugging. This API is unstable. There are warnings literally
-	/// everywhere about this. Please don't use this function to retrieve actual information.
-	///
-	/// Returns an error if the `NetworkWorker` is no longer running.
-	pub async fn network_state(&self) -> Result<NetworkState, ()> {
-		let (tx, rx) = oneshot::channel();
-
-		let _ = self
-			.to_worker
-			.unbounded_send(ServiceToWorkerMsg::NetworkState { pending_response: tx });
-
-		match rx.await {
-			Ok(v) => v.map_err(|_| ()),
-			// The channel can only be closed if the network worker no longer exists.
-			Err(_) => Err(()),
-		}
-	}
-
-	/// Get the list of reserved peers.
-	///
-	/// Returns an error if the `NetworkWorker` is no longer running.
-	pub async fn reserved_peers(&self) -> Result<Vec<PeerId>, ()> {
-		let (tx, rx) = oneshot::channel();
-
-		let _ = self
-			.to_worker
-			.unbounded_send(ServiceToWorkerMsg::ReservedPeers { pending_response: tx });
-
-		// The channel can only be closed if the network worker no longer exists.
-		rx.await.map_err(|_| ())
-	}
-
 	/// Utility function to extract `PeerId` from each `Multiaddr` for peer set updates.
 	///
 	/// Returns an `Err` if one of the given addresses is invalid or contains an
@@ -760,6 +756,89 @@ where
 	}
 }
 
+#[async_trait::async_trait]
+impl<B, H> NetworkPeerAdmin for NetworkService<B, H>
+where
+	B: BlockT + 'static,
+	H: ExHashT,
+{
+	/// Get network state.
+	///
+	/// **Note**: Use this only for debugging. This API is unstable. There are warnings literally
+	/// everywhere about this. Please don't use this function to retrieve actual information.
+	///
+	/// Returns an error if the `NetworkWorker` is no longer running.
+	async fn network_state(&self) -> Result<NetworkState, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::NetworkState { pending_response: tx });
+
+		match rx.await {
+			Ok(v) => v.map_err(|_| ()),
+			// The channel can only be closed if the network worker no longer exists.
+			Err(_) => Err(()),
+		}
+	}
+
+	/// Get the list of reserved peers.
+	///
+	/// Returns an error if the `NetworkWorker` is no longer running.
+	async fn reserved_peers(&self) -> Result<Vec<PeerId>, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::ReservedPeers { pending_response: tx });
+
+		// The channel can only be closed if the network worker no longer exists.
+		rx.await.map_err(|_| ())
+	}
+
+	/// Get the reputation of all the peers known to the peer set manager, along with the most
+	/// recent changes made to it.
+	///
+	/// Returns an error if the `NetworkWorker` is no longer running.
+	async fn peer_reputations(&self) -> Result<Vec<PeerReputation>, ()> {
+		self.peerset.clone().peer_reputations().await
+	}
+
+	/// Ban a peer or an IP address for the given duration, disconnecting from it.
+	///
+	/// The ban is persisted in the network configuration directory, if there is one.
+	fn ban(&self, target: BanTarget, duration: Duration) {
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Ban(target, duration));
+	}
+
+	/// Lift a ban placed with [`NetworkPeerAdmin::ban`].
+	///
+	/// Returns `false` if the target wasn't banned, or an error if the `NetworkWorker` is no
+	/// longer running.
+	async fn unban(&self, target: BanTarget) -> Result<bool, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self
+			.to_worker
+			.unbounded_send(ServiceToWorkerMsg::Unban { target, pending_response: tx });
+
+		// The channel can only be closed if the network worker no longer exists.
+		rx.await.map_err(|_| ())
+	}
+
+	/// Get the bans placed with [`NetworkPeerAdmin::ban`] that are still in force.
+	///
+	/// Returns an error if the `NetworkWorker` is no longer running.
+	async fn bans(&self) -> Result<Vec<Ban>, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self.to_worker.unbounded_send(ServiceToWorkerMsg::Bans { pending_response: tx });
+
+		// The channel can only be closed if the network worker no longer exists.
+		rx.await.map_err(|_| ())
+	}
+}
+
 impl<B, H> NetworkPeers for NetworkService<B, H>
 where
 	B: BlockT + 'static,
@@ -1146,6 +1225,14 @@ enum ServiceToWorkerMsg {
 	ReservedPeers {
 		pending_response: oneshot::Sender<Vec<PeerId>>,
 	},
+	Ban(BanTarget, Duration),
+	Unban {
+		target: BanTarget,
+		pending_response: oneshot::Sender<bool>,
+	},
+	Bans {
+		pending_response: oneshot::Sender<Vec<Ban>>,
+	},
 }
 
 /// Main network worker. Must be polled in order for the network to advance.
@@ -1178,6 +1265,8 @@ where
 	/// For each peer and protocol combination, an object that allows sending notifications to
 	/// that peer. Shared with the [`NetworkService`].
 	peers_notifications_sinks: Arc<Mutex<HashMap<(PeerId, ProtocolName), NotificationsSink>>>,
+	/// Peers and IP addresses banned through [`NetworkPeerAdmin::ban`].
+	ban_list: BanList,
 	/// Marker to pin the `H` generic. Serves no purpose except to not break backwards
 	/// compatibility.
 	_marker: PhantomData<H>,
@@ -1349,6 +1438,64 @@ where
 				let _ =
 					pending_response.send(self.reserved_peers().map(ToOwned::to_owned).collect());
 			},
+			ServiceToWorkerMsg::Ban(target, duration) => {
+				info!(target: "sub-libp2p", "Banning {} for {:?}", target, duration);
+				self.ban_list.ban(target, duration);
+				self.save_ban_list();
+
+				if let BanTarget::Peer(peer_id) = target {
+					self.service.peerset.ban_peer(peer_id, duration);
+				}
+				self.disconnect_banned();
+			},
+			ServiceToWorkerMsg::Unban { target, pending_response } => {
+				let unbanned = self.ban_list.unban(&target);
+				if unbanned {
+					info!(target: "sub-libp2p", "Unbanning {}", target);
+					self.save_ban_list();
+				}
+
+				if let BanTarget::Peer(peer_id) = target {
+					self.service.peerset.unban_peer(peer_id);
+				}
+				let _ = pending_response.send(unbanned);
+			},
+			ServiceToWorkerMsg::Bans { pending_response } => {
+				let _ = pending_response.send(self.ban_list.bans());
+			},
+		}
+	}
+
+	fn save_ban_list(&mut self) {
+		if let Err(e) = self.ban_list.save() {
+			warn!(target: "sub-libp2p", "Failed to save the list of banned peers: {}", e);
+		}
+	}
+
+	/// Returns `true` if a connection to `peer_id` at `endpoint` must not be kept.
+	fn is_banned(&self, peer_id: &PeerId, endpoint: &ConnectedPoint) -> bool {
+		self.ban_list.is_banned(&BanTarget::Peer(*peer_id)) ||
+			self.ban_list.is_address_banned(endpoint.get_remote_address())
+	}
+
+	/// Close the connections to all the banned peers and IP addresses.
+	fn disconnect_banned(&mut self) {
+		let banned = self
+			.network_service
+			.connected_peers()
+			.filter(|peer_id| {
+				self.network_service
+					.behaviour()
+					.node(peer_id)
+					.and_then(|node| node.endpoint().map(|e| self.is_banned(peer_id, e)))
+					.unwrap_or_else(|| self.ban_list.is_banned(&BanTarget::Peer(**peer_id)))
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+
+		for peer_id in banned {
+			debug!(target: "sub-libp2p", "Disconnecting banned peer {}", peer_id);
+			let _ = self.network_service.disconnect_peer_id(peer_id);
 		}
 	}
 
@@ -1592,6 +1739,11 @@ where
 					debug!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 				}
 
+				if self.is_banned(&peer_id, &endpoint) {
+					debug!(target: "sub-libp2p", "Disconnecting banned peer {}", peer_id);
+					let _ = self.network_service.disconnect_peer_id(peer_id);
+				}
+
 				if let Some(metrics) = self.metrics.as_ref() {
 					let direction = match endpoint {
 						ConnectedPoint::Dialer { .. } => "out",
@@ -1755,6 +1907,31 @@ where
 	}
 }
 
+#[async_trait::async_trait]
+impl<B, H> NetworkBackend<B, H> for NetworkWorker<B, H>
+where
+	B: BlockT + 'static,
+	H: ExHashT,
+{
+	type NetworkService = Arc<NetworkService<B, H>>;
+
+	fn new(params: Params<B>) -> Result<Self, Error> {
+		NetworkWorker::new(params)
+	}
+
+	fn network_service(&self) -> Self::NetworkService {
+		self.service.clone()
+	}
+
+	fn num_connected_peers(&self) -> usize {
+		NetworkWorker::num_connected_peers(self)
+	}
+
+	async fn run(self) {
+		NetworkWorker::run(self).await
+	}
+}
+
 impl<B, H> Unpin for NetworkWorker<B, H>
 where
 	B: BlockT + 'static,
@@ -0,0 +1,287 @@
+// This file is part of Substrate.
+//
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Peers and IP addresses banned by the node operator.
+//!
+//! The list is stored as JSON in [`BAN_LIST_FILE`] inside the network configuration directory,
+//! so that bans survive restarts.
+
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use sc_peerset::MAX_BAN_DURATION;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt, fs, io,
+	net::IpAddr,
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Name of the file the ban list is stored in, relative to the network configuration directory.
+pub const BAN_LIST_FILE: &str = "banned_peers.json";
+
+/// What a ban applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BanTarget {
+	/// All connections with the given peer.
+	Peer(PeerId),
+	/// All connections with a remote using the given IP address.
+	Ip(IpAddr),
+}
+
+impl fmt::Display for BanTarget {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Peer(peer_id) => peer_id.fmt(f),
+			Self::Ip(ip) => ip.fmt(f),
+		}
+	}
+}
+
+impl FromStr for BanTarget {
+	type Err = String;
+
+	/// Parses either an IP address or a base58-encoded [`PeerId`].
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Ok(ip) = s.parse::<IpAddr>() {
+			return Ok(Self::Ip(ip))
+		}
+
+		s.parse::<PeerId>()
+			.map(Self::Peer)
+			.map_err(|_| format!("`{}` is neither a peer ID nor an IP address", s))
+	}
+}
+
+/// A ban currently in force.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ban {
+	/// What is banned.
+	pub target: BanTarget,
+	/// When the ban expires.
+	pub expires_at: SystemTime,
+}
+
+/// On-disk representation of the ban list, expiry times are in seconds since the UNIX epoch.
+#[derive(Default, Serialize, Deserialize)]
+struct BanListFile {
+	#[serde(default)]
+	peers: BTreeMap<String, u64>,
+	#[serde(default)]
+	ips: BTreeMap<IpAddr, u64>,
+}
+
+/// List of peers and IP addresses banned by the node operator.
+#[derive(Debug, Default)]
+pub struct BanList {
+	bans: HashMap<BanTarget, SystemTime>,
+	/// Where the list is persisted, if anywhere.
+	path: Option<PathBuf>,
+}
+
+impl BanList {
+	/// Load the ban list stored in `dir`. Bans that have expired in the meantime are dropped.
+	///
+	/// A missing file results in an empty list, which is written to `dir` on the first change.
+	pub fn load(dir: &Path) -> io::Result<Self> {
+		let path = dir.join(BAN_LIST_FILE);
+		let file: BanListFile = match fs::read(&path) {
+			Ok(content) => serde_json::from_slice(&content)
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => Default::default(),
+			Err(e) => return Err(e),
+		};
+
+		let mut bans = HashMap::new();
+		for (peer_id, expires_at) in file.peers {
+			let peer_id = peer_id
+				.parse::<PeerId>()
+				.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+			bans.insert(BanTarget::Peer(peer_id), stored_expiry(expires_at));
+		}
+		for (ip, expires_at) in file.ips {
+			bans.insert(BanTarget::Ip(ip), stored_expiry(expires_at));
+		}
+
+		let mut list = Self { bans, path: Some(path) };
+		list.remove_expired();
+		Ok(list)
+	}
+
+	/// Ban `target` for `duration`, replacing any previous ban of the same target.
+	///
+	/// The duration is capped to [`MAX_BAN_DURATION`].
+	pub fn ban(&mut self, target: BanTarget, duration: Duration) {
+		self.bans.insert(target, expiry(duration));
+	}
+
+	/// Lift the ban of `target`. Returns `false` if it wasn't banned.
+	pub fn unban(&mut self, target: &BanTarget) -> bool {
+		self.remove_expired();
+		self.bans.remove(target).is_some()
+	}
+
+	/// Returns `true` if `target` is currently banned.
+	pub fn is_banned(&self, target: &BanTarget) -> bool {
+		self.bans
+			.get(target)
+			.map_or(false, |expires_at| *expires_at > SystemTime::now())
+	}
+
+	/// Returns `true` if the IP address `address` connects from is banned.
+	pub fn is_address_banned(&self, address: &Multiaddr) -> bool {
+		ip_address(address).map_or(false, |ip| self.is_banned(&BanTarget::Ip(ip)))
+	}
+
+	/// Returns the bans currently in force, sorted by target.
+	pub fn bans(&self) -> Vec<Ban> {
+		let now = SystemTime::now();
+		let mut bans = self
+			.bans
+			.iter()
+			.filter(|(_, expires_at)| **expires_at > now)
+			.map(|(target, expires_at)| Ban { target: *target, expires_at: *expires_at })
+			.collect::<Vec<_>>();
+		bans.sort_by(|a, b| a.target.cmp(&b.target));
+		bans
+	}
+
+	/// Write the list back to the directory it was loaded from.
+	///
+	/// Does nothing for lists that weren't [loaded](BanList::load) from disk.
+	pub fn save(&mut self) -> io::Result<()> {
+		self.remove_expired();
+
+		let Some(path) = &self.path else { return Ok(()) };
+		let mut file = BanListFile::default();
+		for (target, expires_at) in &self.bans {
+			let expires_at = expires_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+			match target {
+				BanTarget::Peer(peer_id) => file.peers.insert(peer_id.to_base58(), expires_at),
+				BanTarget::Ip(ip) => file.ips.insert(*ip, expires_at),
+			};
+		}
+
+		let content = serde_json::to_vec_pretty(&file)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+		fs::write(path, content)
+	}
+
+	fn remove_expired(&mut self) {
+		let now = SystemTime::now();
+		self.bans.retain(|_, expires_at| *expires_at > now);
+	}
+}
+
+/// Returns when a ban placed now for `duration` expires.
+fn expiry(duration: Duration) -> SystemTime {
+	let now = SystemTime::now();
+	now.checked_add(duration.min(MAX_BAN_DURATION)).unwrap_or(now)
+}
+
+/// Returns when a ban stored as expiring `expires_at` seconds after the UNIX epoch expires.
+///
+/// The stored value isn't trusted, bans are never extended past [`MAX_BAN_DURATION`] from now.
+fn stored_expiry(expires_at: u64) -> SystemTime {
+	let latest = expiry(MAX_BAN_DURATION);
+	UNIX_EPOCH
+		.checked_add(Duration::from_secs(expires_at))
+		.map_or(latest, |expires_at| expires_at.min(latest))
+}
+
+/// Returns the IP address contained in `address`, if any.
+fn ip_address(address: &Multiaddr) -> Option<IpAddr> {
+	address.iter().find_map(|protocol| match protocol {
+		Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+		Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+		_ => None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ban_list_survives_reload() {
+		let dir = tempfile::tempdir().unwrap();
+		let peer_id = PeerId::random();
+		let ip = "198.51.100.19".parse::<IpAddr>().unwrap();
+
+		let mut list = BanList::load(dir.path()).unwrap();
+		assert!(list.bans().is_empty());
+		list.ban(BanTarget::Peer(peer_id), Duration::from_secs(3600));
+		list.ban(BanTarget::Ip(ip), Duration::from_secs(3600));
+		list.ban(BanTarget::Peer(PeerId::random()), Duration::ZERO);
+		list.save().unwrap();
+
+		let mut list = BanList::load(dir.path()).unwrap();
+		let targets = list.bans().into_iter().map(|ban| ban.target).collect::<Vec<_>>();
+		assert_eq!(targets, vec![BanTarget::Peer(peer_id), BanTarget::Ip(ip)]);
+		assert!(list.is_address_banned(&"/ip4/198.51.100.19/tcp/30333".parse().unwrap()));
+		assert!(!list.is_address_banned(&"/ip4/198.51.100.20/tcp/30333".parse().unwrap()));
+
+		assert!(list.unban(&BanTarget::Ip(ip)));
+		assert!(!list.unban(&BanTarget::Ip(ip)));
+		list.save().unwrap();
+		assert_eq!(BanList::load(dir.path()).unwrap().bans().len(), 1);
+	}
+
+	#[test]
+	fn ban_duration_is_capped() {
+		let dir = tempfile::tempdir().unwrap();
+		let peer_id = PeerId::random();
+		let latest = SystemTime::now() + MAX_BAN_DURATION;
+
+		let mut list = BanList::load(dir.path()).unwrap();
+		list.ban(BanTarget::Peer(peer_id), Duration::from_secs(u64::MAX));
+		assert!(list.bans()[0].expires_at <= SystemTime::now() + MAX_BAN_DURATION);
+		assert!(list.bans()[0].expires_at >= latest);
+
+		// Expiry times past the maximum are capped when loading a tampered file.
+		let ip = "198.51.100.19";
+		fs::write(
+			dir.path().join(BAN_LIST_FILE),
+			format!(
+				r#"{{"peers": {{"{}": {}}}, "ips": {{"{}": {}}}}}"#,
+				peer_id,
+				u64::MAX,
+				ip,
+				u64::MAX
+			),
+		)
+		.unwrap();
+		let list = BanList::load(dir.path()).unwrap();
+		let bans = list.bans();
+		assert_eq!(bans.len(), 2);
+		assert!(bans.iter().all(|ban| ban.expires_at <= SystemTime::now() + MAX_BAN_DURATION));
+	}
+
+	#[test]
+	fn parse_ban_target() {
+		let peer_id = PeerId::random();
+
+		assert_eq!(peer_id.to_base58().parse(), Ok(BanTarget::Peer(peer_id)));
+		assert_eq!("::1".parse(), Ok(BanTarget::Ip("::1".parse().unwrap())));
+		assert!("not-a-target".parse::<BanTarget>().is_err());
+	}
+}
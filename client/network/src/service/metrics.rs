@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::transport::BandwidthSinks;
+use crate::{bandwidth::BandwidthLimiters, transport::BandwidthSinks};
 use prometheus_endpoint::{
 	self as prometheus, Counter, CounterVec, Gauge, GaugeVec, HistogramOpts, MetricSource, Opts,
 	PrometheusError, Registry, SourcedCounter, SourcedGauge, U64,
@@ -34,6 +34,8 @@ pub use prometheus_endpoint::{Histogram, HistogramVec};
 /// Registers all networking metrics with the given registry.
 pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics, PrometheusError> {
 	BandwidthCounters::register(registry, sources.bandwidth)?;
+	RateLimitedBytesCounter::register(registry, sources.bandwidth_limiters.clone())?;
+	RateLimitedDelaysCounter::register(registry, sources.bandwidth_limiters)?;
 	NumConnectedGauge::register(registry, sources.connected_peers)?;
 	Metrics::register(registry)
 }
@@ -41,6 +43,7 @@ pub fn register(registry: &Registry, sources: MetricSources) -> Result<Metrics,
 /// Predefined metric sources that are fed directly into prometheus.
 pub struct MetricSources {
 	pub bandwidth: Arc<BandwidthSinks>,
+	pub bandwidth_limiters: Arc<BandwidthLimiters>,
 	pub connected_peers: Arc<AtomicUsize>,
 }
 
@@ -264,6 +267,82 @@ impl MetricSource for BandwidthCounters {
 	}
 }
 
+/// The metric of the bytes that went through each bandwidth limiter.
+#[derive(Clone)]
+pub struct RateLimitedBytesCounter(Arc<BandwidthLimiters>);
+
+impl RateLimitedBytesCounter {
+	/// Registers the `RateLimitedBytesCounter` metric whose values are
+	/// obtained from the given limiters.
+	fn register(
+		registry: &Registry,
+		limiters: Arc<BandwidthLimiters>,
+	) -> Result<(), PrometheusError> {
+		prometheus::register(
+			SourcedCounter::new(
+				&Opts::new(
+					"substrate_sub_libp2p_rate_limited_bytes_total",
+					"Total bytes that went through each bandwidth limiter",
+				)
+				.variable_label("limiter")
+				.variable_label("direction"),
+				RateLimitedBytesCounter(limiters),
+			)?,
+			registry,
+		)?;
+
+		Ok(())
+	}
+}
+
+impl MetricSource for RateLimitedBytesCounter {
+	type N = u64;
+
+	fn collect(&self, mut set: impl FnMut(&[&str], Self::N)) {
+		for (limiter, direction, rate_limiter) in self.0.limiters() {
+			set(&[limiter, direction], rate_limiter.total_bytes());
+		}
+	}
+}
+
+/// The metric of the number of times transfers waited for a bandwidth limiter.
+#[derive(Clone)]
+pub struct RateLimitedDelaysCounter(Arc<BandwidthLimiters>);
+
+impl RateLimitedDelaysCounter {
+	/// Registers the `RateLimitedDelaysCounter` metric whose values are
+	/// obtained from the given limiters.
+	fn register(
+		registry: &Registry,
+		limiters: Arc<BandwidthLimiters>,
+	) -> Result<(), PrometheusError> {
+		prometheus::register(
+			SourcedCounter::new(
+				&Opts::new(
+					"substrate_sub_libp2p_rate_limited_delays_total",
+					"Number of times transfers had to wait because of a bandwidth limit",
+				)
+				.variable_label("limiter")
+				.variable_label("direction"),
+				RateLimitedDelaysCounter(limiters),
+			)?,
+			registry,
+		)?;
+
+		Ok(())
+	}
+}
+
+impl MetricSource for RateLimitedDelaysCounter {
+	type N = u64;
+
+	fn collect(&self, mut set: impl FnMut(&[&str], Self::N)) {
+		for (limiter, direction, rate_limiter) in self.0.limiters() {
+			set(&[limiter, direction], rate_limiter.total_delays());
+		}
+	}
+}
+
 /// The connected peers metric.
 #[derive(Clone)]
 pub struct NumConnectedGauge(Arc<AtomicUsize>);
@@ -19,19 +19,26 @@
 // If you read this, you are very thorough, congratulations.
 
 use crate::{
-	config::MultiaddrWithPeerId,
+	config::{MultiaddrWithPeerId, Params},
+	error::Error,
 	event::Event,
+	network_state::NetworkState,
 	request_responses::{IfDisconnected, RequestFailure},
-	service::signature::Signature,
+	service::{
+		ban_list::{Ban, BanTarget},
+		signature::Signature,
+	},
 	types::ProtocolName,
 };
 
 use futures::{channel::oneshot, Stream};
 use libp2p::{Multiaddr, PeerId};
 
-use sc_peerset::ReputationChange;
+use sc_network_common::ExHashT;
+use sc_peerset::{PeerReputation, ReputationChange};
+use sp_runtime::traits::Block as BlockT;
 
-use std::{collections::HashSet, future::Future, pin::Pin, sync::Arc};
+use std::{collections::HashSet, future::Future, pin::Pin, sync::Arc, time::Duration};
 
 pub use libp2p::{identity::error::SigningError, kad::record::Key as KademliaKey};
 
@@ -132,6 +139,74 @@ where
 	}
 }
 
+/// Inspection and administration of the peers, as offered to the node operator through the
+/// `system_*` RPC methods.
+#[async_trait::async_trait]
+pub trait NetworkPeerAdmin {
+	/// Get network state.
+	///
+	/// **Note**: Use this only for debugging. This API is unstable. Please don't use this function
+	/// to retrieve actual information.
+	///
+	/// Returns an error if the backend is no longer running.
+	async fn network_state(&self) -> Result<NetworkState, ()>;
+
+	/// Get the list of reserved peers.
+	///
+	/// Returns an error if the backend is no longer running.
+	async fn reserved_peers(&self) -> Result<Vec<PeerId>, ()>;
+
+	/// Get the reputation of all the known peers, along with the most recent changes made to it.
+	///
+	/// Returns an error if the backend is no longer running.
+	async fn peer_reputations(&self) -> Result<Vec<PeerReputation>, ()>;
+
+	/// Ban a peer or an IP address for the given duration, disconnecting from it.
+	fn ban(&self, target: BanTarget, duration: Duration);
+
+	/// Lift a ban placed with [`NetworkPeerAdmin::ban`].
+	///
+	/// Returns `false` if the target wasn't banned, or an error if the backend is no longer
+	/// running.
+	async fn unban(&self, target: BanTarget) -> Result<bool, ()>;
+
+	/// Get the bans placed with [`NetworkPeerAdmin::ban`] that are still in force.
+	///
+	/// Returns an error if the backend is no longer running.
+	async fn bans(&self) -> Result<Vec<Ban>, ()>;
+}
+
+#[async_trait::async_trait]
+impl<T> NetworkPeerAdmin for Arc<T>
+where
+	T: ?Sized,
+	T: NetworkPeerAdmin + Send + Sync,
+{
+	async fn network_state(&self) -> Result<NetworkState, ()> {
+		T::network_state(self).await
+	}
+
+	async fn reserved_peers(&self) -> Result<Vec<PeerId>, ()> {
+		T::reserved_peers(self).await
+	}
+
+	async fn peer_reputations(&self) -> Result<Vec<PeerReputation>, ()> {
+		T::peer_reputations(self).await
+	}
+
+	fn ban(&self, target: BanTarget, duration: Duration) {
+		T::ban(self, target, duration)
+	}
+
+	async fn unban(&self, target: BanTarget) -> Result<bool, ()> {
+		T::unban(self, target).await
+	}
+
+	async fn bans(&self) -> Result<Vec<Ban>, ()> {
+		T::bans(self).await
+	}
+}
+
 /// Provides low-level API for manipulating network peers.
 pub trait NetworkPeers {
 	/// Set authorized peers.
@@ -390,6 +465,23 @@ pub trait NotificationSender: Send + Sync + 'static {
 		-> Result<Box<dyn NotificationSenderReady + '_>, NotificationSenderError>;
 }
 
+/// Substream of a notification protocol opened with a peer, as handed to the syncing engine.
+///
+/// Lets the engine answer a peer without knowing which backend opened the substream.
+pub trait MessageSink: std::fmt::Debug + Send + Sync {
+	/// Sends a notification to the peer.
+	///
+	/// Never blocks: if the substream can't keep up, the notification is silently discarded and
+	/// the backend is expected to close the substream shortly after.
+	fn send_sync_notification(&self, notification: Vec<u8>);
+}
+
+impl MessageSink for crate::NotificationsSink {
+	fn send_sync_notification(&self, notification: Vec<u8>) {
+		crate::NotificationsSink::send_sync_notification(self, notification)
+	}
+}
+
 /// Error returned by [`NetworkNotification::notification_sender`].
 #[derive(Debug, thiserror::Error)]
 pub enum NotificationSenderError {
@@ -435,9 +527,9 @@ pub trait NetworkNotification {
 	/// a receiver. With a `NotificationSender` at hand, sending a notification is done in two
 	/// steps:
 	///
-	/// 1.  [`NotificationSender::ready`] is used to wait for the sender to become ready
+	/// 1. [`NotificationSender::ready`] is used to wait for the sender to become ready
 	/// for another notification, yielding a [`NotificationSenderReady`] token.
-	/// 2.  [`NotificationSenderReady::send`] enqueues the notification for sending. This operation
+	/// 2. [`NotificationSenderReady::send`] enqueues the notification for sending. This operation
 	/// can only fail if the underlying notification substream or connection has suddenly closed.
 	///
 	/// An error is returned by [`NotificationSenderReady::send`] if there exists no open
@@ -632,3 +724,69 @@ where
 		T::new_best_block_imported(self, hash, number)
 	}
 }
+
+/// Everything the rest of the node can ask of a running network backend.
+///
+/// Implemented for any type providing the individual network traits, so there is no need to
+/// implement it manually.
+pub trait NetworkService:
+	NetworkSigner
+	+ NetworkDHTProvider
+	+ NetworkStatusProvider
+	+ NetworkPeers
+	+ NetworkPeerAdmin
+	+ NetworkEventStream
+	+ NetworkStateInfo
+	+ NetworkNotification
+	+ NetworkRequest
+	+ Send
+	+ Sync
+	+ 'static
+{
+}
+
+impl<T> NetworkService for T where
+	T: NetworkSigner
+		+ NetworkDHTProvider
+		+ NetworkStatusProvider
+		+ NetworkPeers
+		+ NetworkPeerAdmin
+		+ NetworkEventStream
+		+ NetworkStateInfo
+		+ NetworkNotification
+		+ NetworkRequest
+		+ Send
+		+ Sync
+		+ 'static
+{
+}
+
+/// Networking backend.
+///
+/// A backend owns the connections to other nodes and implements notification protocols,
+/// request-response protocols, peer management and discovery on top of them. The rest of the
+/// node only talks to it through the handle returned by [`NetworkBackend::network_service`],
+/// while the backend itself is driven by [`NetworkBackend::run`].
+///
+/// The block announcement substreams aren't reported on the event streams: the backend hands them
+/// to the syncing engine through [`Params::tx`] instead, along with a [`MessageSink`] the engine
+/// uses to answer the peer.
+#[async_trait::async_trait]
+pub trait NetworkBackend<B: BlockT + 'static, H: ExHashT>: Send + 'static {
+	/// Handle through which the backend is controlled.
+	type NetworkService: NetworkService + Clone;
+
+	/// Create a new backend from the network parameters.
+	fn new(params: Params<B>) -> Result<Self, Error>
+	where
+		Self: Sized;
+
+	/// Returns a handle to the backend.
+	fn network_service(&self) -> Self::NetworkService;
+
+	/// Returns the number of peers we're connected to.
+	fn num_connected_peers(&self) -> usize;
+
+	/// Drive the backend. Only returns when the node is shutting down.
+	async fn run(self);
+}
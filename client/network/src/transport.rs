@@ -16,16 +16,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+use crate::bandwidth::{Throttle, Throttled, ThrottledMuxer};
+
 use libp2p::{
 	bandwidth,
 	core::{
 		self,
-		either::EitherTransport,
+		either::{EitherOutput, EitherTransport},
 		muxing::StreamMuxerBox,
 		transport::{Boxed, OptionalTransport},
 		upgrade,
 	},
-	dns, identity, mplex, noise, tcp, websocket, PeerId, Transport,
+	dns, identity, mplex, noise, quic, tcp, websocket, PeerId, Transport,
 };
 use std::{sync::Arc, time::Duration};
 
@@ -36,6 +38,11 @@ pub use self::bandwidth::BandwidthSinks;
 /// If `memory_only` is true, then only communication within the same process are allowed. Only
 /// addresses with the format `/memory/...` are allowed.
 ///
+/// If `enable_quic` is true, QUIC is supported in addition to TCP and WebSocket, i.e. addresses
+/// with the format `/ip4/.../udp/.../quic` can be listened on and dialed. QUIC connections
+/// bring their own encryption and multiplexing, and aren't accounted for in the returned
+/// `BandwidthSinks`. Ignored if `memory_only` is true.
+///
 /// `yamux_window_size` is the maximum size of the Yamux receive windows. `None` to leave the
 /// default (256kiB).
 ///
@@ -44,13 +51,19 @@ pub use self::bandwidth::BandwidthSinks;
 /// high-level protocols combined, or to some generously high value if you are sure that a maximum
 /// size is enforced on all high-level protocols.
 ///
+/// `throttle` limits the bandwidth used by all the connections combined. TCP and WebSocket
+/// connections are throttled below the encryption and multiplexing layers, QUIC connections at
+/// the level of their substreams.
+///
 /// Returns a `BandwidthSinks` object that allows querying the average bandwidth produced by all
 /// the connections spawned with this transport.
 pub fn build_transport(
 	keypair: identity::Keypair,
 	memory_only: bool,
+	enable_quic: bool,
 	yamux_window_size: Option<u32>,
 	yamux_maximum_buffer_size: usize,
+	throttle: Throttle,
 ) -> (Boxed<(PeerId, StreamMuxerBox)>, Arc<BandwidthSinks>) {
 	// Build the base layer of the transport.
 	let transport = if !memory_only {
@@ -82,6 +95,8 @@ pub fn build_transport(
 		))
 	};
 
+	let quic_throttle = throttle.clone();
+	let transport = transport.map(move |stream, _| Throttled::new(stream, throttle.clone()));
 	let (transport, bandwidth) = bandwidth::BandwidthLogging::new(transport);
 
 	let authentication_config =
@@ -125,8 +140,32 @@ pub fn build_transport(
 		.upgrade(upgrade::Version::V1Lazy)
 		.authenticate(authentication_config)
 		.multiplex(multiplexing_config)
-		.timeout(Duration::from_secs(20))
-		.boxed();
+		.timeout(Duration::from_secs(20));
+
+	let transport = if enable_quic && !memory_only {
+		// QUIC transport: DNS(QUIC)
+		let quic_trans = quic::tokio::Transport::new(quic::Config::new(&keypair));
+		let quic_trans = match dns::TokioDnsConfig::system(quic_trans) {
+			Ok(dns) => EitherTransport::Left(dns),
+			Err(_) =>
+				EitherTransport::Right(quic::tokio::Transport::new(quic::Config::new(&keypair))),
+		}
+		.map(move |either, _| {
+			let (peer_id, connection) = match either {
+				EitherOutput::First(output) | EitherOutput::Second(output) => output,
+			};
+			(peer_id, StreamMuxerBox::new(ThrottledMuxer::new(connection, quic_throttle.clone())))
+		});
+
+		transport
+			.or_transport(quic_trans)
+			.map(|either, _| match either {
+				EitherOutput::First(output) | EitherOutput::Second(output) => output,
+			})
+			.boxed()
+	} else {
+		transport.boxed()
+	};
 
 	(transport, bandwidth)
 }
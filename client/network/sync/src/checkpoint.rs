@@ -0,0 +1,351 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Checkpoints of the warp and state sync progress, kept in the auxiliary storage of the client
+//! so that an interrupted sync can resume after a restart.
+//!
+//! The state is stored as a list of chunks, one per imported state response or snapshot chunk,
+//! next to a [`StateCheckpoint`] describing them. Both are written in the same auxiliary storage
+//! transaction, and a checkpoint that can't be fully read back is discarded.
+
+use codec::{Decode, Encode};
+use log::{debug, warn};
+use sc_client_api::{snapshot::SnapshotManifest, AuxStore};
+use sp_consensus_grandpa::{AuthorityList, SetId};
+use sp_runtime::{traits::Block as BlockT, Justifications};
+
+const WARP_CHECKPOINT_KEY: &[u8] = b"sync_warp_checkpoint";
+const STATE_CHECKPOINT_KEY: &[u8] = b"sync_state_checkpoint";
+const STATE_CHUNK_PREFIX: &[u8] = b"sync_state_chunk";
+
+/// Key-value pairs downloaded for each trie, identified by its root. The top trie has an empty
+/// root.
+pub type StateChunk = Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>;
+
+/// Last verified point of a warp sync.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub enum WarpCheckpoint<B: BlockT> {
+	/// Downloading warp proofs, starting from `last_hash`.
+	Proofs { set_id: SetId, authorities: AuthorityList, last_hash: B::Hash, total_proof_bytes: u64 },
+	/// Warp proofs have been verified up to the target block, whose state is being downloaded.
+	TargetBlock { header: B::Header, total_proof_bytes: u64 },
+}
+
+/// Progress of a state sync.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub struct StateCheckpoint<B: BlockT> {
+	/// Header of the block whose state is downloaded.
+	pub header: B::Header,
+	/// Body of the target block, if it is imported along with the state.
+	pub body: Option<Vec<B::Extrinsic>>,
+	/// Justifications of the target block.
+	pub justifications: Option<Justifications>,
+	/// Whether the state is downloaded without proofs.
+	pub skip_proof: bool,
+	/// Key ranges left to download.
+	pub ranges: Vec<RangeCheckpoint>,
+	/// Number of bytes downloaded so far.
+	pub imported_bytes: u64,
+	/// Number of [`StateChunk`]s stored.
+	pub chunks: u32,
+	/// Snapshot being downloaded, if the state is downloaded as a snapshot.
+	pub snapshot: Option<SnapshotCheckpoint<B::Hash>>,
+}
+
+/// Progress of the download of a snapshot.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub struct SnapshotCheckpoint<Hash> {
+	/// Manifest of the snapshot.
+	pub manifest: SnapshotManifest<Hash>,
+	/// Indices of the chunks downloaded and verified so far.
+	pub imported: Vec<u32>,
+}
+
+/// Progress of the download of a range of keys.
+#[derive(Debug, PartialEq, Encode, Decode)]
+pub struct RangeCheckpoint {
+	/// Key cursor of the next state request.
+	pub cursor: Vec<Vec<u8>>,
+	/// Last key of the range, if any.
+	pub end: Option<Vec<u8>>,
+}
+
+fn state_chunk_key(index: u32) -> Vec<u8> {
+	let mut key = STATE_CHUNK_PREFIX.to_vec();
+	key.extend(index.to_le_bytes());
+	key
+}
+
+/// Load the warp sync checkpoint, if any.
+pub fn load_warp<B: BlockT, Client: AuxStore>(client: &Client) -> Option<WarpCheckpoint<B>> {
+	let encoded = match client.get_aux(WARP_CHECKPOINT_KEY) {
+		Ok(encoded) => encoded?,
+		Err(e) => {
+			warn!(target: "sync", "Failed to read warp sync checkpoint: {}", e);
+			return None
+		},
+	};
+
+	match WarpCheckpoint::decode(&mut &encoded[..]) {
+		Ok(checkpoint) => Some(checkpoint),
+		Err(e) => {
+			warn!(target: "sync", "Discarding invalid warp sync checkpoint: {}", e);
+			clear_warp(client);
+			None
+		},
+	}
+}
+
+/// Replace the warp sync checkpoint.
+pub fn store_warp<B: BlockT, Client: AuxStore>(client: &Client, checkpoint: &WarpCheckpoint<B>) {
+	if let Err(e) = client.insert_aux(&[(WARP_CHECKPOINT_KEY, &checkpoint.encode()[..])], &[]) {
+		warn!(target: "sync", "Failed to store warp sync checkpoint: {}", e);
+	}
+}
+
+/// Remove the warp sync checkpoint.
+pub fn clear_warp<Client: AuxStore>(client: &Client) {
+	if let Err(e) = client.insert_aux(&[], &[WARP_CHECKPOINT_KEY]) {
+		warn!(target: "sync", "Failed to remove warp sync checkpoint: {}", e);
+	}
+}
+
+/// Load the state sync checkpoint, if any, along with the chunks of state it describes.
+///
+/// A checkpoint whose chunks can't all be read is removed.
+pub fn load_state<B: BlockT, Client: AuxStore>(
+	client: &Client,
+) -> Option<(StateCheckpoint<B>, Vec<StateChunk>)> {
+	let checkpoint = load_state_checkpoint::<B, _>(client)?;
+
+	let mut chunks = Vec::with_capacity(checkpoint.chunks as usize);
+	for index in 0..checkpoint.chunks {
+		let chunk = client
+			.get_aux(&state_chunk_key(index))
+			.ok()
+			.flatten()
+			.and_then(|encoded| StateChunk::decode(&mut &encoded[..]).ok());
+		match chunk {
+			Some(chunk) => chunks.push(chunk),
+			None => {
+				warn!(target: "sync", "Discarding state sync checkpoint with missing chunk {}", index);
+				clear_state(client);
+				return None
+			},
+		}
+	}
+
+	Some((checkpoint, chunks))
+}
+
+/// Load the state sync checkpoint, if any, without the chunks of state.
+pub fn load_state_checkpoint<B: BlockT, Client: AuxStore>(
+	client: &Client,
+) -> Option<StateCheckpoint<B>> {
+	let encoded = match client.get_aux(STATE_CHECKPOINT_KEY) {
+		Ok(encoded) => encoded?,
+		Err(e) => {
+			warn!(target: "sync", "Failed to read state sync checkpoint: {}", e);
+			return None
+		},
+	};
+
+	match StateCheckpoint::decode(&mut &encoded[..]) {
+		Ok(checkpoint) => Some(checkpoint),
+		Err(e) => {
+			warn!(target: "sync", "Discarding invalid state sync checkpoint: {}", e);
+			clear_state(client);
+			None
+		},
+	}
+}
+
+/// Store a new chunk of state, along with the checkpoint accounting for it.
+///
+/// `checkpoint.chunks` must already include the new chunk.
+pub fn store_state_chunk<B: BlockT, Client: AuxStore>(
+	client: &Client,
+	checkpoint: &StateCheckpoint<B>,
+	chunk: &StateChunk,
+) {
+	let Some(index) = checkpoint.chunks.checked_sub(1) else { return };
+	let chunk_key = state_chunk_key(index);
+	let (encoded_checkpoint, encoded_chunk) = (checkpoint.encode(), chunk.encode());
+	let insert =
+		[(&chunk_key[..], &encoded_chunk[..]), (STATE_CHECKPOINT_KEY, &encoded_checkpoint[..])];
+	if let Err(e) = client.insert_aux(&insert, &[]) {
+		warn!(target: "sync", "Failed to store state sync checkpoint: {}", e);
+	}
+}
+
+/// Remove the state sync checkpoint and all the chunks of state.
+pub fn clear_state<Client: AuxStore>(client: &Client) {
+	// The chunk count can't be trusted if the checkpoint is corrupted, delete chunks until there
+	// are no more.
+	let mut index = 0;
+	loop {
+		let key = state_chunk_key(index);
+		match client.get_aux(&key) {
+			Ok(Some(_)) =>
+				if let Err(e) = client.insert_aux(&[], &[&key[..]]) {
+					warn!(target: "sync", "Failed to remove state sync checkpoint: {}", e);
+					return
+				},
+			Ok(None) | Err(_) => break,
+		}
+		index += 1;
+	}
+
+	if index > 0 {
+		debug!(target: "sync", "Removed {} chunks of checkpointed state", index);
+	}
+	if let Err(e) = client.insert_aux(&[], &[STATE_CHECKPOINT_KEY]) {
+		warn!(target: "sync", "Failed to remove state sync checkpoint: {}", e);
+	}
+}
+
+/// Remove all checkpoints.
+pub fn clear<Client: AuxStore>(client: &Client) {
+	clear_warp(client);
+	clear_state(client);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::state::{StateSync, StateSyncRequest};
+	use libp2p::PeerId;
+	use sp_blockchain::HeaderBackend;
+	use sp_runtime::traits::Header as _;
+	use std::sync::Arc;
+	use substrate_test_runtime_client::{
+		runtime::{Block, Hash, Header},
+		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
+
+	fn header(number: u64) -> Header {
+		Header::new(
+			number,
+			Hash::repeat_byte(1),
+			Hash::repeat_byte(2),
+			Hash::repeat_byte(3),
+			Default::default(),
+		)
+	}
+
+	fn start(request: Option<StateSyncRequest<Block>>) -> Vec<Vec<u8>> {
+		match request {
+			Some(StateSyncRequest::State(request)) => request.start,
+			request => panic!("Expected a state request, got {:?}", request),
+		}
+	}
+
+	fn state_checkpoint(chunks: u32) -> StateCheckpoint<Block> {
+		StateCheckpoint {
+			header: header(10),
+			body: Some(Vec::new()),
+			justifications: None,
+			skip_proof: false,
+			ranges: vec![RangeCheckpoint { cursor: vec![b"key".to_vec()], end: None }],
+			imported_bytes: 42,
+			chunks,
+			snapshot: None,
+		}
+	}
+
+	#[test]
+	fn warp_checkpoint_roundtrip() {
+		let client = TestClientBuilder::new().build();
+		assert_eq!(load_warp::<Block, _>(&client), None);
+
+		let checkpoint =
+			WarpCheckpoint::<Block>::TargetBlock { header: header(100), total_proof_bytes: 1024 };
+		store_warp(&client, &checkpoint);
+		assert_eq!(load_warp::<Block, _>(&client), Some(checkpoint));
+
+		clear_warp(&client);
+		assert_eq!(load_warp::<Block, _>(&client), None);
+	}
+
+	#[test]
+	fn state_checkpoint_roundtrip() {
+		let client = TestClientBuilder::new().build();
+		let chunks: Vec<StateChunk> = vec![
+			vec![(Vec::new(), vec![(b"a".to_vec(), b"1".to_vec())])],
+			vec![(b"child".to_vec(), vec![(b"b".to_vec(), b"2".to_vec())])],
+		];
+		for (index, chunk) in chunks.iter().enumerate() {
+			store_state_chunk(&client, &state_checkpoint(index as u32 + 1), chunk);
+		}
+
+		assert_eq!(load_state::<Block, _>(&client), Some((state_checkpoint(2), chunks)));
+
+		clear_state(&client);
+		assert_eq!(load_state::<Block, _>(&client), None);
+		assert_eq!(client.get_aux(&state_chunk_key(0)).unwrap(), None);
+	}
+
+	#[test]
+	fn inconsistent_state_checkpoint_is_discarded() {
+		let client = TestClientBuilder::new().build();
+		store_state_chunk(&client, &state_checkpoint(1), &vec![(Vec::new(), Vec::new())]);
+
+		// The checkpoint claims a chunk that was never written.
+		let checkpoint = state_checkpoint(3).encode();
+		client.insert_aux(&[(STATE_CHECKPOINT_KEY, &checkpoint[..])], &[]).unwrap();
+
+		assert_eq!(load_state::<Block, _>(&client), None);
+		assert_eq!(client.get_aux(STATE_CHECKPOINT_KEY).unwrap(), None);
+		assert_eq!(client.get_aux(&state_chunk_key(0)).unwrap(), None);
+	}
+
+	#[test]
+	fn state_sync_resumes_from_checkpoint() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.header(client.info().genesis_hash).unwrap().unwrap();
+		let checkpoint = StateCheckpoint::<Block> {
+			header: genesis.clone(),
+			body: None,
+			justifications: None,
+			skip_proof: true,
+			ranges: vec![
+				RangeCheckpoint { cursor: vec![b"b".to_vec()], end: Some(b"m".to_vec()) },
+				RangeCheckpoint { cursor: vec![b"x".to_vec()], end: None },
+			],
+			imported_bytes: 1,
+			chunks: 1,
+			snapshot: None,
+		};
+		store_state_chunk(
+			&*client,
+			&checkpoint,
+			&vec![(Vec::new(), vec![(b"a".to_vec(), b"1".to_vec())])],
+		);
+
+		let mut sync = StateSync::new(client.clone(), genesis.clone(), None, None, true);
+		assert_eq!(start(sync.next_request(PeerId::random())), vec![b"b".to_vec()]);
+		assert_eq!(start(sync.next_request(PeerId::random())), vec![b"x".to_vec()]);
+		assert_eq!(sync.progress().size, 1);
+		assert!(load_state::<Block, _>(&*client).is_some());
+
+		// Downloading the same state with proofs can't reuse the checkpoint.
+		let mut sync = StateSync::new(client.clone(), genesis, None, None, false);
+		assert!(start(sync.next_request(PeerId::random())).is_empty());
+		assert_eq!(load_state::<Block, _>(&*client), None);
+	}
+}
@@ -33,14 +33,14 @@ use prometheus_endpoint::{
 	register, Gauge, GaugeVec, MetricSource, Opts, PrometheusError, Registry, SourcedGauge, U64,
 };
 
-use sc_client_api::{BlockBackend, HeaderBackend, ProofProvider};
+use sc_client_api::{AuxStore, BlockBackend, HeaderBackend, ProofProvider};
 use sc_consensus::import_queue::ImportQueueService;
 use sc_network::{
 	config::{
 		NetworkConfiguration, NonDefaultSetConfig, ProtocolId, SyncMode as SyncOperationMode,
 	},
 	utils::LruHashSet,
-	NotificationsSink, ProtocolName,
+	MessageSink, ProtocolName,
 };
 use sc_network_common::{
 	role::Roles,
@@ -167,7 +167,7 @@ pub struct Peer<B: BlockT> {
 	/// Holds a set of blocks known to this peer.
 	pub known_blocks: LruHashSet<B::Hash>,
 	/// Notification sink.
-	sink: NotificationsSink,
+	sink: Box<dyn MessageSink>,
 	/// Instant when the last notification was sent to peer.
 	last_notification_sent: Instant,
 	/// Instant when the last notification was received from peer.
@@ -252,6 +252,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -270,6 +271,7 @@ where
 		block_request_protocol_name: ProtocolName,
 		state_request_protocol_name: ProtocolName,
 		warp_sync_protocol_name: Option<ProtocolName>,
+		snapshot_request_protocol_name: Option<ProtocolName>,
 		rx: sc_utils::mpsc::TracingUnboundedReceiver<sc_network::SyncEvent<B>>,
 	) -> Result<(Self, SyncingService<B>, NonDefaultSetConfig), ClientError> {
 		let mode = match network_config.sync_mode {
@@ -343,12 +345,15 @@ where
 			max_parallel_downloads,
 			max_blocks_per_request,
 			warp_sync_params,
+			network_config.gap_sync_mode,
 			metrics_registry,
 			network_service.clone(),
 			import_queue,
 			block_request_protocol_name,
 			state_request_protocol_name,
 			warp_sync_protocol_name,
+			snapshot_request_protocol_name,
+			network_config.snapshot_period,
 		)?;
 
 		let block_announce_protocol_name = block_announce_config.notifications_protocol.clone();
@@ -821,7 +826,7 @@ where
 		&mut self,
 		who: PeerId,
 		status: &BlockAnnouncesHandshake<B>,
-		sink: NotificationsSink,
+		sink: Box<dyn MessageSink>,
 	) -> Result<(), ()> {
 		log::trace!(target: "sync", "New peer {} {:?}", who, status);
 
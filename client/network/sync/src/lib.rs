@@ -31,7 +31,8 @@
 use crate::{
 	blocks::BlockCollection,
 	schema::v1::{StateRequest, StateResponse},
-	state::StateSync,
+	snapshot::{SnapshotRequest, SnapshotResponse},
+	state::{StateSync, StateSyncRequest},
 	warp::{WarpProofImportResult, WarpSync},
 };
 
@@ -45,13 +46,14 @@ use log::{debug, error, info, trace, warn};
 use prost::Message;
 
 use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
-use sc_client_api::{BlockBackend, ProofProvider};
+use sc_client_api::{AuxStore, BlockBackend, ProofProvider};
 use sc_consensus::{
 	import_queue::ImportQueueService, BlockImportError, BlockImportStatus, IncomingBlock,
 };
 use sc_network::{
 	config::{
-		NonDefaultSetConfig, NonReservedPeerMode, NotificationHandshake, ProtocolId, SetConfig,
+		GapSyncMode, NonDefaultSetConfig, NonReservedPeerMode, NotificationHandshake, ProtocolId,
+		SetConfig,
 	},
 	request_responses::{IfDisconnected, RequestFailure},
 	types::ProtocolName,
@@ -94,6 +96,7 @@ use std::{
 
 pub use service::chain_sync::SyncingService;
 
+mod checkpoint;
 mod extra_requests;
 mod schema;
 
@@ -102,6 +105,8 @@ pub mod blocks;
 pub mod engine;
 pub mod mock;
 pub mod service;
+pub mod snapshot;
+pub mod snapshot_request_handler;
 pub mod state;
 pub mod state_request_handler;
 pub mod warp;
@@ -269,6 +274,34 @@ struct GapSync<B: BlockT> {
 	blocks: BlockCollection<B>,
 	best_queued_number: NumberFor<B>,
 	target: NumberFor<B>,
+	mode: GapSyncMode,
+}
+
+impl<B: BlockT> GapSync<B> {
+	/// Attributes to request for the gap blocks in `range`, given the `attrs` requested for
+	/// blocks above the gap.
+	///
+	/// Bodies are requested for the whole range if any block in it is within the body depth.
+	fn block_attributes(
+		&self,
+		mut attrs: BlockAttributes,
+		range: &Range<NumberFor<B>>,
+	) -> BlockAttributes {
+		if !self.mode.justifications() {
+			attrs.remove(BlockAttributes::JUSTIFICATION);
+		}
+		let bodies = match self.mode {
+			GapSyncMode::Headers | GapSyncMode::HeadersAndJustifications => false,
+			GapSyncMode::Full { body_depth: None } => true,
+			// The end is not part of the range.
+			GapSyncMode::Full { body_depth: Some(depth) } =>
+				range.end.saturating_add(depth.into()) > self.target + One::one(),
+		};
+		if !bodies {
+			attrs.remove(BlockAttributes::BODY | BlockAttributes::INDEXED_BODY);
+		}
+		attrs
+	}
 }
 
 type PendingResponse<B> = Pin<
@@ -333,6 +366,8 @@ pub struct ChainSync<B: BlockT, Client> {
 	import_existing: bool,
 	/// Gap download process.
 	gap_sync: Option<GapSync<B>>,
+	/// What to download for the blocks of the gap.
+	gap_sync_mode: GapSyncMode,
 	/// Handle for communicating with `NetworkService`
 	network_service: service::network::NetworkServiceHandle,
 	/// Protocol name used for block announcements
@@ -343,6 +378,13 @@ pub struct ChainSync<B: BlockT, Client> {
 	state_request_protocol_name: ProtocolName,
 	/// Protocol name used to send out warp sync requests
 	warp_sync_protocol_name: Option<ProtocolName>,
+	/// Protocol name used to send out snapshot requests, if state is downloaded as snapshots
+	snapshot_request_protocol_name: Option<ProtocolName>,
+	/// Number of blocks between two snapshots.
+	snapshot_period: u32,
+	/// Whether peers failed to serve the snapshot last downloaded, in which case the state of a
+	/// recent block is downloaded by key ranges instead.
+	snapshots_unavailable: bool,
 	/// Pending responses
 	pending_responses: HashMap<PeerId, PendingResponse<B>>,
 	/// Handle to import queue.
@@ -476,6 +518,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -643,7 +686,11 @@ where
 					{
 						log::debug!(target: "sync", "Starting warp state sync.");
 						if let Some(params) = self.warp_sync_params.take() {
-							self.warp_sync = Some(WarpSync::new(self.client.clone(), params));
+							self.warp_sync = Some(WarpSync::new(
+								self.client.clone(),
+								params,
+								self.snapshot_request_protocol_name.is_some(),
+							));
 						}
 					}
 				}
@@ -1086,23 +1133,46 @@ where
 				let mut heads: Vec<_> = self.peers.values().map(|peer| peer.best_number).collect();
 				heads.sort();
 				let median = heads[heads.len() / 2];
-				if number + STATE_SYNC_FINALITY_THRESHOLD.saturated_into() >= median {
-					if let Ok(Some(header)) = self.client.header(*hash) {
-						log::debug!(
-							target: "sync",
-							"Starting state sync for #{} ({})",
-							number,
-							hash,
-						);
-						self.state_sync = Some(StateSync::new(
-							self.client.clone(),
-							header,
-							None,
-							None,
-							*skip_proofs,
-						));
-						self.allowed_requests.set_all();
+				let is_recent = |number: NumberFor<B>| {
+					number + STATE_SYNC_FINALITY_THRESHOLD.saturated_into() >= median
+				};
+				// Snapshots are only taken every `snapshot_period` blocks, the state of the last
+				// of them is downloaded instead of the state of the block just finalized.
+				let snapshot_number = (self.snapshot_request_protocol_name.is_some() &&
+					!self.snapshots_unavailable &&
+					!*skip_proofs)
+					.then(|| number - number % self.snapshot_period.into())
+					.filter(|snapshot_number| !snapshot_number.is_zero());
+				// Prefer resuming the download of the state checkpointed before a restart.
+				let target =
+					StateSync::<B, Client>::checkpointed_target(&*self.client, *skip_proofs)
+						.filter(|header| {
+							is_recent(*header.number()) || Some(*header.number()) == snapshot_number
+						})
+						.or_else(|| {
+							let target_hash = match snapshot_number {
+								Some(snapshot_number) =>
+									self.client.hash(snapshot_number).ok().flatten()?,
+								None => *hash,
+							};
+							is_recent(number)
+								.then(|| self.client.header(target_hash).ok().flatten())
+								.flatten()
+						});
+				if let Some(header) = target {
+					log::debug!(
+						target: "sync",
+						"Starting state sync for #{} ({})",
+						header.number(),
+						header.hash(),
+					);
+					let mut state_sync =
+						StateSync::new(self.client.clone(), header, None, None, *skip_proofs);
+					if snapshot_number == Some(*header.number()) {
+						state_sync.enable_snapshot();
 					}
+					self.state_sync = Some(state_sync);
+					self.allowed_requests.set_all();
 				}
 			}
 		}
@@ -1237,7 +1307,11 @@ where
 		if let Some(gap_sync) = &mut self.gap_sync {
 			gap_sync.blocks.clear_peer_download(who)
 		}
-		self.peers.remove(who);
+		if let Some(peer) = self.peers.remove(who) {
+			if let PeerSyncState::DownloadingState = peer.state {
+				self.release_state_request(who);
+			}
+		}
 		self.pending_responses.remove(who);
 		self.extra_justifications.peer_disconnected(who);
 		self.allowed_requests.set_all();
@@ -1393,6 +1467,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -1408,12 +1483,15 @@ where
 		max_parallel_downloads: u32,
 		max_blocks_per_request: u32,
 		warp_sync_params: Option<WarpSyncParams<B>>,
+		gap_sync_mode: GapSyncMode,
 		metrics_registry: Option<&Registry>,
 		network_service: service::network::NetworkServiceHandle,
 		import_queue: Box<dyn ImportQueueService<B>>,
 		block_request_protocol_name: ProtocolName,
 		state_request_protocol_name: ProtocolName,
 		warp_sync_protocol_name: Option<ProtocolName>,
+		snapshot_request_protocol_name: Option<ProtocolName>,
+		snapshot_period: u32,
 	) -> Result<(Self, NonDefaultSetConfig), ClientError> {
 		let block_announce_config = Self::get_block_announce_proto_config(
 			protocol_id,
@@ -1449,11 +1527,15 @@ where
 			warp_sync: None,
 			import_existing: false,
 			gap_sync: None,
+			gap_sync_mode,
 			network_service,
 			block_request_protocol_name,
 			state_request_protocol_name,
 			warp_sync_params,
 			warp_sync_protocol_name,
+			snapshot_request_protocol_name,
+			snapshot_period: snapshot_period.max(1),
+			snapshots_unavailable: false,
 			block_announce_protocol_name: block_announce_config
 				.notifications_protocol
 				.clone()
@@ -1474,6 +1556,10 @@ where
 		};
 
 		sync.reset_sync_start_point()?;
+		if sync.mode == SyncMode::Full {
+			// Leftovers of an earlier warp or state sync.
+			checkpoint::clear(&*sync.client);
+		}
 		Ok((sync, block_announce_config))
 	}
 
@@ -1813,6 +1899,9 @@ where
 
 			// since the request is not a justification, remove it from pending responses
 			self.pending_responses.remove(&id);
+			if let PeerSyncState::DownloadingState = p.state {
+				self.release_state_request(&id);
+			}
 
 			// handle peers that were in other states.
 			match self.new_peer(id, p.best_hash, p.best_number) {
@@ -1862,11 +1951,18 @@ where
 		}
 
 		if let Some((start, end)) = info.block_gap {
-			debug!(target: "sync", "Starting gap sync #{} - #{}", start, end);
+			debug!(
+				target: "sync",
+				"Starting gap sync #{} - #{} ({:?})",
+				start,
+				end,
+				self.gap_sync_mode,
+			);
 			self.gap_sync = Some(GapSync {
 				best_queued_number: start - One::one(),
 				target: end,
 				blocks: BlockCollection::new(),
+				mode: self.gap_sync_mode,
 			});
 		}
 		trace!(target: "sync", "Restarted sync at #{} ({:?})", self.best_queued_number, self.best_queued_hash);
@@ -2036,6 +2132,32 @@ where
 		}
 	}
 
+	fn send_snapshot_request(&mut self, who: PeerId, request: SnapshotRequest<B::Hash>) {
+		let (tx, rx) = oneshot::channel();
+
+		if self.peers.contains_key(&who) {
+			self.pending_responses
+				.insert(who, Box::pin(async move { (who, PeerRequest::Snapshot, rx.await) }));
+		}
+
+		match &self.snapshot_request_protocol_name {
+			Some(name) => self.network_service.start_request(
+				who,
+				name.clone(),
+				request.encode(),
+				tx,
+				IfDisconnected::ImmediateError,
+			),
+			None => {
+				log::warn!(
+					target: "sync",
+					"Trying to send snapshot request when no protocol is configured {:?}",
+					request,
+				);
+			},
+		}
+	}
+
 	fn send_warp_sync_request(&mut self, who: PeerId, request: WarpProofRequest<B>) {
 		let (tx, rx) = oneshot::channel();
 
@@ -2152,6 +2274,24 @@ where
 		}
 	}
 
+	fn on_snapshot_response(
+		&mut self,
+		peer_id: PeerId,
+		response: SnapshotResponse<B::Hash>,
+	) -> Option<ImportResult<B>> {
+		match self.on_snapshot_data(&peer_id, response) {
+			Ok(OnStateData::Import(origin, block)) =>
+				Some(ImportResult::BlockImport(origin, vec![block])),
+			Ok(OnStateData::Continue) => None,
+			Err(BadPeer(id, repu)) => {
+				self.network_service
+					.disconnect_peer(id, self.block_announce_protocol_name.clone());
+				self.network_service.report_peer(id, repu);
+				None
+			},
+		}
+	}
+
 	pub fn on_warp_sync_response(&mut self, peer_id: PeerId, response: EncodedProof) {
 		if let Err(BadPeer(id, repu)) = self.on_warp_sync_data(&peer_id, response) {
 			self.network_service
@@ -2165,8 +2305,12 @@ where
 			self.send_block_request(id, request);
 		}
 
-		if let Some((id, request)) = self.state_request() {
-			self.send_state_request(id, request);
+		for (id, request) in self.state_requests() {
+			match request {
+				StateSyncRequest::State(request) =>
+					self.send_state_request(id, OpaqueStateRequest(Box::new(request))),
+				StateSyncRequest::Snapshot(request) => self.send_snapshot_request(id, request),
+			}
 		}
 
 		for (id, request) in self.justification_requests().collect::<Vec<_>>() {
@@ -2240,10 +2384,46 @@ where
 					PeerRequest::WarpProof => {
 						self.on_warp_sync_response(id, EncodedProof(resp));
 					},
+					PeerRequest::Snapshot => {
+						let response = match SnapshotResponse::decode(&mut &resp[..]) {
+							Ok(response) => response,
+							Err(e) => {
+								debug!(
+									target: "sync",
+									"Failed to decode snapshot response from peer {:?}: {:?}.",
+									id,
+									e
+								);
+								self.network_service.report_peer(id, rep::BAD_MESSAGE);
+								self.network_service
+									.disconnect_peer(id, self.block_announce_protocol_name.clone());
+								continue
+							},
+						};
+
+						if let Some(import) = self.on_snapshot_response(id, response) {
+							return Poll::Ready(import)
+						}
+					},
 				},
 				Ok(Err(e)) => {
 					debug!(target: "sync", "Request to peer {:?} failed: {:?}.", id, e);
 
+					// Serving snapshots is optional, the state is downloaded from elsewhere.
+					if matches!(request, PeerRequest::Snapshot) &&
+						matches!(
+							e,
+							RequestFailure::Refused |
+								RequestFailure::Network(OutboundFailure::UnsupportedProtocols)
+						) {
+						if let Some(import) =
+							self.on_snapshot_response(id, SnapshotResponse::Unavailable)
+						{
+							return Poll::Ready(import)
+						}
+						continue
+					}
+
 					match e {
 						RequestFailure::Network(OutboundFailure::Timeout) => {
 							self.network_service.report_peer(id, rep::TIMEOUT);
@@ -2457,7 +2637,7 @@ where
 					peer.state = PeerSyncState::DownloadingStale(hash);
 					Some((id, req))
 				} else if let Some((range, req)) = gap_sync.as_mut().and_then(|sync| {
-					peer_gap_block_request(
+					let (range, mut req) = peer_gap_block_request(
 						&id,
 						peer,
 						&mut sync.blocks,
@@ -2465,7 +2645,9 @@ where
 						sync.target,
 						sync.best_queued_number,
 						max_blocks_per_request,
-					)
+					)?;
+					req.fields = sync.block_attributes(attrs, &range);
+					Some((range, req))
 				}) {
 					peer.state = PeerSyncState::DownloadingGap(range.start);
 					trace!(
@@ -2485,49 +2667,52 @@ where
 		// Box::new(iter)
 	}
 
-	fn state_request(&mut self) -> Option<(PeerId, OpaqueStateRequest)> {
+	/// Get state requests to send, one for each available peer as long as there are ranges of
+	/// keys or snapshot chunks left to download.
+	fn state_requests(&mut self) -> Vec<(PeerId, StateSyncRequest<B>)> {
+		let mut requests = Vec::new();
 		if self.allowed_requests.is_empty() {
-			return None
+			return requests
 		}
-		if (self.state_sync.is_some() || self.warp_sync.is_some()) &&
-			self.peers.iter().any(|(_, peer)| peer.state == PeerSyncState::DownloadingState)
-		{
-			// Only one pending state request is allowed.
-			return None
-		}
-		if let Some(sync) = &self.state_sync {
-			if sync.is_complete() {
-				return None
-			}
-
-			for (id, peer) in self.peers.iter_mut() {
-				if peer.state.is_available() && peer.common_number >= sync.target_block_num() {
-					peer.state = PeerSyncState::DownloadingState;
-					let request = sync.next_request();
-					trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
-					self.allowed_requests.clear();
-					return Some((*id, OpaqueStateRequest(Box::new(request))))
+		if let Some(sync) = &mut self.state_sync {
+			if !sync.is_complete() {
+				let target = sync.target_block_num();
+				for (id, peer) in self.peers.iter_mut() {
+					if peer.state.is_available() && peer.common_number >= target {
+						let Some(request) = sync.next_request(*id) else { break };
+						trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
+						peer.state = PeerSyncState::DownloadingState;
+						requests.push((*id, request));
+					}
 				}
 			}
-		}
-		if let Some(sync) = &self.warp_sync {
-			if sync.is_complete() {
-				return None
-			}
-			if let (Some(request), Some(target)) =
-				(sync.next_state_request(), sync.target_block_number())
-			{
+		} else if let Some(sync) = &mut self.warp_sync {
+			if let (false, Some(target)) = (sync.is_complete(), sync.target_block_number()) {
 				for (id, peer) in self.peers.iter_mut() {
 					if peer.state.is_available() && peer.best_number >= target {
+						let Some(request) = sync.next_state_request(*id) else { break };
 						trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
 						peer.state = PeerSyncState::DownloadingState;
-						self.allowed_requests.clear();
-						return Some((*id, OpaqueStateRequest(Box::new(request))))
+						requests.push((*id, request));
 					}
 				}
 			}
 		}
-		None
+		if !requests.is_empty() {
+			self.allowed_requests.clear();
+		}
+		requests
+	}
+
+	/// Make the keys requested from `who` available to other peers, as no response is expected
+	/// anymore.
+	fn release_state_request(&mut self, who: &PeerId) {
+		if let Some(sync) = &mut self.state_sync {
+			sync.release_peer(who);
+		}
+		if let Some(sync) = &mut self.warp_sync {
+			sync.release_peer(who);
+		}
 	}
 
 	fn warp_sync_request(&mut self) -> Option<(PeerId, WarpProofRequest<B>)> {
@@ -2589,7 +2774,7 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import(*response)
+			sync.import(*who, *response)
 		} else if let Some(sync) = &mut self.warp_sync {
 			debug!(
 				target: "sync",
@@ -2598,12 +2783,60 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import_state(*response)
+			sync.import_state(*who, *response)
 		} else {
 			debug!(target: "sync", "Ignored obsolete state response from {}", who);
 			return Err(BadPeer(*who, rep::NOT_REQUESTED))
 		};
 
+		self.on_state_import_result(who, import_result)
+	}
+
+	fn on_snapshot_data(
+		&mut self,
+		who: &PeerId,
+		response: SnapshotResponse<B::Hash>,
+	) -> Result<OnStateData<B>, BadPeer> {
+		if let Some(peer) = self.peers.get_mut(who) {
+			if let PeerSyncState::DownloadingState = peer.state {
+				peer.state = PeerSyncState::Available;
+				self.allowed_requests.set_all();
+			}
+		}
+		let import_result = if let Some(sync) = &mut self.state_sync {
+			let downloaded_snapshot = sync.downloads_snapshot();
+			let import_result = sync.import_snapshot(*who, response);
+			if downloaded_snapshot && !sync.downloads_snapshot() {
+				// The block of the snapshot is likely too old for its state to be served by key
+				// ranges, the state of the next block finalized is downloaded instead.
+				debug!(target: "sync", "Snapshot unavailable, restarting state sync");
+				self.snapshots_unavailable = true;
+				self.state_sync = None;
+				for (id, peer) in self.peers.iter_mut() {
+					if let PeerSyncState::DownloadingState = peer.state {
+						peer.state = PeerSyncState::Available;
+						self.pending_responses.remove(id);
+					}
+				}
+				self.allowed_requests.set_all();
+				return Ok(OnStateData::Continue)
+			}
+			import_result
+		} else if let Some(sync) = &mut self.warp_sync {
+			sync.import_snapshot(*who, response)
+		} else {
+			debug!(target: "sync", "Ignored obsolete snapshot response from {}", who);
+			return Err(BadPeer(*who, rep::NOT_REQUESTED))
+		};
+
+		self.on_state_import_result(who, import_result)
+	}
+
+	fn on_state_import_result(
+		&self,
+		who: &PeerId,
+		import_result: state::ImportResult<B>,
+	) -> Result<OnStateData<B>, BadPeer> {
 		match import_result {
 			state::ImportResult::Import(hash, header, state, body, justifications) => {
 				let origin = BlockOrigin::NetworkInitialSync;
@@ -2752,6 +2985,7 @@ where
 						);
 						self.state_sync = None;
 						self.mode = SyncMode::Full;
+						checkpoint::clear(&*self.client);
 						output.extend(self.restart());
 					}
 					let warp_sync_complete = self
@@ -2766,6 +3000,7 @@ where
 						);
 						self.warp_sync = None;
 						self.mode = SyncMode::Full;
+						checkpoint::clear(&*self.client);
 						output.extend(self.restart());
 					}
 					let gap_sync_complete =
@@ -2818,8 +3053,10 @@ where
 				},
 				e @ Err(BlockImportError::UnknownParent) | e @ Err(BlockImportError::Other(_)) => {
 					warn!(target: "sync", "💔 Error importing block {hash:?}: {}", e.unwrap_err());
+					// The checkpointed state may be the reason of the failure, start over.
 					self.state_sync = None;
 					self.warp_sync = None;
+					checkpoint::clear(&*self.client);
 					output.extend(self.restart());
 				},
 				Err(BlockImportError::Cancelled) => {},
@@ -3184,7 +3421,7 @@ fn validate_blocks<Block: BlockT>(
 #[cfg(test)]
 mod test {
 	use super::*;
-	use crate::service::network::NetworkServiceProvider;
+	use crate::{service::network::NetworkServiceProvider, snapshot::SNAPSHOT_PERIOD};
 	use futures::{executor::block_on, future::poll_fn};
 	use sc_block_builder::BlockBuilderProvider;
 	use sc_network_common::{
@@ -3222,12 +3459,15 @@ mod test {
 			1,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -3289,12 +3529,15 @@ mod test {
 			1,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -3471,12 +3714,15 @@ mod test {
 			5,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -3598,12 +3844,15 @@ mod test {
 			5,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -3756,12 +4005,15 @@ mod test {
 			5,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -3899,12 +4151,15 @@ mod test {
 			5,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -4044,12 +4299,15 @@ mod test {
 			1,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -4090,12 +4348,15 @@ mod test {
 			1,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -4144,12 +4405,15 @@ mod test {
 			1,
 			64,
 			None,
+			GapSyncMode::default(),
 			None,
 			chain_sync_network_handle,
 			import_queue,
 			ProtocolName::from("block-request"),
 			ProtocolName::from("state-request"),
 			None,
+			None,
+			SNAPSHOT_PERIOD,
 		)
 		.unwrap();
 
@@ -4211,4 +4475,56 @@ mod test {
 		sync.peer_disconnected(&peers[1]);
 		assert_eq!(sync.pending_responses.len(), 0);
 	}
+
+	#[test]
+	fn gap_block_attributes_follow_gap_sync_mode() {
+		let full = BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION | BlockAttributes::BODY;
+		let gap = |mode| GapSync::<Block> {
+			blocks: BlockCollection::new(),
+			best_queued_number: 0,
+			target: 100,
+			mode,
+		};
+
+		assert_eq!(
+			gap(GapSyncMode::Headers).block_attributes(full, &(1..65)),
+			BlockAttributes::HEADER
+		);
+		assert_eq!(
+			gap(GapSyncMode::HeadersAndJustifications).block_attributes(full, &(1..65)),
+			BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION,
+		);
+		assert_eq!(
+			gap(GapSyncMode::Full { body_depth: None }).block_attributes(full, &(1..65)),
+			full,
+		);
+
+		// Bodies of blocks 91..=100 only.
+		let sync = gap(GapSyncMode::Full { body_depth: Some(10) });
+		assert_eq!(
+			sync.block_attributes(full, &(1..91)),
+			BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION,
+		);
+		assert_eq!(sync.block_attributes(full, &(1..92)), full);
+		assert_eq!(sync.block_attributes(full, &(91..101)), full);
+
+		let sync = gap(GapSyncMode::Full { body_depth: Some(0) });
+		assert_eq!(
+			sync.block_attributes(full, &(65..101)),
+			BlockAttributes::HEADER | BlockAttributes::JUSTIFICATION,
+		);
+	}
+
+	#[test]
+	fn gap_body_depth_is_limited_by_pruning() {
+		assert_eq!(
+			GapSyncMode::Full { body_depth: None }.limit_body_depth(256),
+			GapSyncMode::Full { body_depth: Some(256) },
+		);
+		assert_eq!(
+			GapSyncMode::Full { body_depth: Some(16) }.limit_body_depth(256),
+			GapSyncMode::Full { body_depth: Some(16) },
+		);
+		assert_eq!(GapSyncMode::Headers.limit_body_depth(256), GapSyncMode::Headers);
+	}
 }
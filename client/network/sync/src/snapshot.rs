@@ -0,0 +1,295 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Download of snapshots of finalized state.
+//!
+//! Snapshots are exported by the nodes serving them, see [`sc_client_api::snapshot`]. As every
+//! chunk is a range proof against the state root of the block, chunks can be downloaded from
+//! different peers and verified independently of each other.
+
+use codec::{Decode, Encode};
+use libp2p::PeerId;
+use sp_core::hashing::blake2_256;
+use std::collections::HashSet;
+
+pub use sc_client_api::snapshot::{
+	ChunkHash, ChunkInfo, SnapshotManifest, SnapshotProvider, CHUNK_SIZE, SNAPSHOT_PERIOD,
+};
+
+/// Number of peers that may fail to serve a snapshot before state is downloaded by key ranges
+/// instead.
+const MAX_UNAVAILABLE_PEERS: usize = 3;
+
+/// Request of the snapshot request protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum SnapshotRequest<Hash> {
+	/// Request the manifest of the snapshot of the state of `block`.
+	Manifest {
+		/// Block the snapshot is of.
+		block: Hash,
+	},
+	/// Request a chunk of the snapshot of the state of `block`.
+	Chunk {
+		/// Block the snapshot is of.
+		block: Hash,
+		/// Hash of the chunk, as listed in the manifest.
+		hash: ChunkHash,
+	},
+}
+
+/// Response of the snapshot request protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum SnapshotResponse<Hash> {
+	/// Manifest of the requested snapshot.
+	Manifest(SnapshotManifest<Hash>),
+	/// Encoded compact proof making up the requested chunk.
+	Chunk(Vec<u8>),
+	/// The peer doesn't serve the requested snapshot.
+	Unavailable,
+}
+
+/// Download of the chunks of a snapshot from any number of peers.
+pub(crate) struct SnapshotDownload<Hash> {
+	block: Hash,
+	state_root: Hash,
+	/// Manifest of the snapshot, once received.
+	manifest: Option<SnapshotManifest<Hash>>,
+	/// Peer the manifest is being requested from.
+	manifest_peer: Option<PeerId>,
+	/// Indices of the chunks left to download, along with the peer they are being requested
+	/// from.
+	pending: Vec<(usize, Option<PeerId>)>,
+	/// Peers that don't serve the snapshot.
+	unavailable: HashSet<PeerId>,
+}
+
+/// Chunk received from a peer, to be verified.
+pub(crate) struct ReceivedChunk {
+	/// Index of the chunk in the manifest.
+	pub index: usize,
+	/// Key cursor the chunk starts after.
+	pub start: Vec<Vec<u8>>,
+	/// Key cursor the next chunk starts after, `None` for the last chunk.
+	pub end: Option<Vec<Vec<u8>>>,
+}
+
+impl<Hash: Clone + PartialEq> SnapshotDownload<Hash> {
+	/// Start downloading the snapshot of the state of `block`, with the given state root.
+	pub fn new(block: Hash, state_root: Hash) -> Self {
+		Self {
+			block,
+			state_root,
+			manifest: None,
+			manifest_peer: None,
+			pending: Vec::new(),
+			unavailable: HashSet::new(),
+		}
+	}
+
+	/// Resume downloading the snapshot described by `manifest`, whose chunks at the `imported`
+	/// indices were already downloaded and verified.
+	pub fn resume(manifest: SnapshotManifest<Hash>, imported: &[u32]) -> Self {
+		let pending = (0..manifest.chunks.len())
+			.filter(|index| !imported.contains(&(*index as u32)))
+			.map(|index| (index, None))
+			.collect();
+		Self {
+			block: manifest.block.clone(),
+			state_root: manifest.state_root.clone(),
+			manifest: Some(manifest),
+			manifest_peer: None,
+			pending,
+			unavailable: HashSet::new(),
+		}
+	}
+
+	/// Returns the manifest of the snapshot, once received.
+	pub fn manifest(&self) -> Option<&SnapshotManifest<Hash>> {
+		self.manifest.as_ref()
+	}
+
+	/// Returns the indices of the chunks downloaded and verified so far.
+	pub fn imported(&self) -> Vec<u32> {
+		let total = self.manifest.as_ref().map_or(0, |manifest| manifest.chunks.len());
+		(0..total)
+			.filter(|index| !self.pending.iter().any(|(pending, _)| pending == index))
+			.map(|index| index as u32)
+			.collect()
+	}
+
+	/// Produce the next request, to be sent to `who`.
+	pub fn next_request(&mut self, who: PeerId) -> Option<SnapshotRequest<Hash>> {
+		if self.unavailable.contains(&who) {
+			return None
+		}
+		let Some(manifest) = &self.manifest else {
+			if self.manifest_peer.is_some() {
+				return None
+			}
+			self.manifest_peer = Some(who);
+			return Some(SnapshotRequest::Manifest { block: self.block.clone() })
+		};
+		let (index, peer) = self.pending.iter_mut().find(|(_, peer)| peer.is_none())?;
+		*peer = Some(who);
+		Some(SnapshotRequest::Chunk {
+			block: self.block.clone(),
+			hash: manifest.chunks[*index].hash,
+		})
+	}
+
+	/// Import the manifest received from `who`. Returns `false` if it wasn't requested from `who`
+	/// or doesn't describe the expected state.
+	pub fn import_manifest(&mut self, who: PeerId, manifest: SnapshotManifest<Hash>) -> bool {
+		if self.manifest_peer != Some(who) {
+			return false
+		}
+		self.manifest_peer = None;
+		if !manifest.is_valid_for(&self.block, &self.state_root) {
+			return false
+		}
+		self.pending = (0..manifest.chunks.len()).map(|index| (index, None)).collect();
+		self.manifest = Some(manifest);
+		true
+	}
+
+	/// Match the `chunk` received from `who` against the manifest. Returns `None` if it wasn't
+	/// requested from `who` or doesn't have the expected hash.
+	pub fn receive_chunk(&mut self, who: PeerId, chunk: &[u8]) -> Option<ReceivedChunk> {
+		let manifest = self.manifest.as_ref()?;
+		let (index, peer) = self.pending.iter_mut().find(|(_, peer)| *peer == Some(who))?;
+		// Whatever the outcome, the chunk can be requested again.
+		*peer = None;
+		let info = &manifest.chunks[*index];
+		if blake2_256(chunk) != info.hash {
+			return None
+		}
+		Some(ReceivedChunk {
+			index: *index,
+			start: info.start.clone(),
+			end: manifest.chunks.get(*index + 1).map(|next| next.start.clone()),
+		})
+	}
+
+	/// Mark the chunk at `index` as downloaded and verified.
+	pub fn chunk_imported(&mut self, index: usize) {
+		self.pending.retain(|(pending, _)| *pending != index);
+	}
+
+	/// Record that `who` doesn't serve the snapshot.
+	pub fn peer_unavailable(&mut self, who: PeerId) {
+		self.release_peer(&who);
+		self.unavailable.insert(who);
+	}
+
+	/// Forget about the request sent to `who`.
+	pub fn release_peer(&mut self, who: &PeerId) {
+		if self.manifest_peer.as_ref() == Some(who) {
+			self.manifest_peer = None;
+		}
+		for (_, peer) in self.pending.iter_mut().filter(|(_, peer)| peer.as_ref() == Some(who)) {
+			*peer = None;
+		}
+	}
+
+	/// Returns `true` if too many peers failed to serve the snapshot to keep trying.
+	pub fn should_give_up(&self) -> bool {
+		self.unavailable.len() >= MAX_UNAVAILABLE_PEERS
+	}
+
+	/// Returns `true` once all the chunks have been downloaded.
+	pub fn is_complete(&self) -> bool {
+		self.manifest.is_some() && self.pending.is_empty()
+	}
+
+	/// Share of the chunks downloaded so far, between 0 and 1.
+	pub fn progress(&self) -> f64 {
+		match &self.manifest {
+			Some(manifest) if !manifest.chunks.is_empty() =>
+				1.0 - self.pending.len() as f64 / manifest.chunks.len() as f64,
+			_ => 0.0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn chunks_are_requested_once_the_manifest_is_known() {
+		let manifest = SnapshotManifest {
+			block: 1u64,
+			state_root: 2u64,
+			chunks: vec![
+				ChunkInfo { hash: blake2_256(b"first"), start: Vec::new() },
+				ChunkInfo { hash: blake2_256(b"second"), start: vec![b"key".to_vec()] },
+			],
+		};
+		let mut download = SnapshotDownload::new(1u64, 2u64);
+		let (first, second) = (PeerId::random(), PeerId::random());
+
+		assert_eq!(download.next_request(first), Some(SnapshotRequest::Manifest { block: 1 }));
+		assert_eq!(download.next_request(second), None);
+		assert!(!download.import_manifest(second, manifest.clone()));
+		assert!(download.import_manifest(first, manifest.clone()));
+
+		assert_eq!(
+			download.next_request(first),
+			Some(SnapshotRequest::Chunk { block: 1, hash: manifest.chunks[0].hash }),
+		);
+		assert_eq!(
+			download.next_request(second),
+			Some(SnapshotRequest::Chunk { block: 1, hash: manifest.chunks[1].hash }),
+		);
+
+		// A chunk that doesn't match its hash is requested again.
+		assert!(download.receive_chunk(second, b"third").is_none());
+		assert!(download.next_request(second).is_some());
+		let chunk = download.receive_chunk(second, b"second").unwrap();
+		assert_eq!((chunk.index, chunk.end), (1, None));
+		download.chunk_imported(1);
+
+		assert_eq!(download.imported(), vec![1]);
+
+		// Only the chunks left are requested once resumed.
+		let mut resumed = SnapshotDownload::resume(manifest.clone(), &download.imported());
+		assert_eq!(
+			resumed.next_request(second),
+			Some(SnapshotRequest::Chunk { block: 1, hash: manifest.chunks[0].hash }),
+		);
+		assert_eq!(resumed.next_request(first), None);
+
+		let chunk = download.receive_chunk(first, b"first").unwrap();
+		assert_eq!(chunk.end, Some(vec![b"key".to_vec()]));
+		download.chunk_imported(0);
+		assert!(download.is_complete());
+	}
+
+	#[test]
+	fn gives_up_when_peers_do_not_serve_snapshots() {
+		let mut download = SnapshotDownload::new(1u64, 2u64);
+		for _ in 0..MAX_UNAVAILABLE_PEERS {
+			let peer = PeerId::random();
+			assert!(download.next_request(peer).is_some());
+			assert!(!download.should_give_up());
+			download.peer_unavailable(peer);
+			assert!(download.next_request(peer).is_none());
+		}
+		assert!(download.should_give_up());
+	}
+}
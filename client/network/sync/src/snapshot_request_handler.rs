@@ -0,0 +1,165 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Helper for handling (i.e. answering) snapshot requests from a remote peer.
+//!
+//! Only the snapshots exported beforehand are served, see [`SnapshotProvider`]. Chunks are read
+//! from disk, [`SnapshotRequestHandler::run`] should therefore be spawned as a blocking task.
+
+use crate::snapshot::{SnapshotProvider, SnapshotRequest, SnapshotResponse};
+
+use codec::{Decode, Encode};
+use futures::{
+	channel::{mpsc, oneshot},
+	stream::StreamExt,
+};
+use libp2p::PeerId;
+use log::{debug, trace};
+
+use sc_client_api::BlockBackend;
+use sc_network::{
+	config::ProtocolId,
+	request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig},
+};
+use sp_runtime::traits::Block as BlockT;
+
+use std::{sync::Arc, time::Duration};
+
+const LOG_TARGET: &str = "sync";
+
+/// Generates a [`ProtocolConfig`] for the snapshot request protocol, refusing incoming requests.
+pub fn generate_protocol_config<Hash: AsRef<[u8]>>(
+	protocol_id: &ProtocolId,
+	genesis_hash: Hash,
+	fork_id: Option<&str>,
+) -> ProtocolConfig {
+	ProtocolConfig {
+		name: generate_protocol_name(genesis_hash, fork_id).into(),
+		fallback_names: std::iter::once(generate_legacy_protocol_name(protocol_id).into())
+			.collect(),
+		max_request_size: 1024,
+		max_response_size: 4 * crate::snapshot::CHUNK_SIZE as u64,
+		request_timeout: Duration::from_secs(120),
+		inbound_queue: None,
+	}
+}
+
+/// Generate the snapshot protocol name from the genesis hash and fork id.
+fn generate_protocol_name<Hash: AsRef<[u8]>>(genesis_hash: Hash, fork_id: Option<&str>) -> String {
+	let genesis_hash = genesis_hash.as_ref();
+	if let Some(fork_id) = fork_id {
+		format!("/{}/{}/snapshot/1", array_bytes::bytes2hex("", genesis_hash), fork_id)
+	} else {
+		format!("/{}/snapshot/1", array_bytes::bytes2hex("", genesis_hash))
+	}
+}
+
+/// Generate the legacy snapshot protocol name from chain specific protocol identifier.
+fn generate_legacy_protocol_name(protocol_id: &ProtocolId) -> String {
+	format!("/{}/snapshot/1", protocol_id.as_ref())
+}
+
+/// Handler for incoming snapshot requests from a remote peer.
+pub struct SnapshotRequestHandler<B: BlockT> {
+	snapshots: Arc<dyn SnapshotProvider<B>>,
+	request_receiver: mpsc::Receiver<IncomingRequest>,
+}
+
+impl<B: BlockT> SnapshotRequestHandler<B> {
+	/// Create a new [`SnapshotRequestHandler`], serving the snapshots provided by `snapshots`.
+	pub fn new<Client: BlockBackend<B>>(
+		protocol_id: &ProtocolId,
+		fork_id: Option<&str>,
+		client: Arc<Client>,
+		snapshots: Arc<dyn SnapshotProvider<B>>,
+		num_peer_hint: usize,
+	) -> (Self, ProtocolConfig) {
+		// Reserve enough request slots for one request per peer when we are at the maximum
+		// number of peers.
+		let (tx, request_receiver) = mpsc::channel(num_peer_hint);
+
+		let mut protocol_config = generate_protocol_config(
+			protocol_id,
+			client
+				.block_hash(0u32.into())
+				.ok()
+				.flatten()
+				.expect("Genesis block exists; qed"),
+			fork_id,
+		);
+		protocol_config.inbound_queue = Some(tx);
+
+		(Self { snapshots, request_receiver }, protocol_config)
+	}
+
+	/// Run [`SnapshotRequestHandler`].
+	pub async fn run(mut self) {
+		while let Some(request) = self.request_receiver.next().await {
+			let IncomingRequest { peer, payload, pending_response } = request;
+
+			match self.handle_request(payload, pending_response, &peer) {
+				Ok(()) => debug!(target: LOG_TARGET, "Handled snapshot request from {}.", peer),
+				Err(e) => debug!(
+					target: LOG_TARGET,
+					"Failed to handle snapshot request from {}: {}", peer, e,
+				),
+			}
+		}
+	}
+
+	fn handle_request(
+		&mut self,
+		payload: Vec<u8>,
+		pending_response: oneshot::Sender<OutgoingResponse>,
+		peer: &PeerId,
+	) -> Result<(), HandleRequestError> {
+		let request = SnapshotRequest::<B::Hash>::decode(&mut &payload[..])?;
+		trace!(target: LOG_TARGET, "Handling snapshot request from {}: {:?}", peer, request);
+
+		let response = match request {
+			SnapshotRequest::Manifest { block } => self
+				.snapshots
+				.snapshot_manifest(block)?
+				.map_or(SnapshotResponse::Unavailable, SnapshotResponse::Manifest),
+			SnapshotRequest::Chunk { block, hash } => self
+				.snapshots
+				.snapshot_chunk(block, hash)?
+				.map_or(SnapshotResponse::Unavailable, SnapshotResponse::Chunk),
+		};
+
+		pending_response
+			.send(OutgoingResponse {
+				result: Ok(response.encode()),
+				reputation_changes: Vec::new(),
+				sent_feedback: None,
+			})
+			.map_err(|_| HandleRequestError::SendResponse)
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+enum HandleRequestError {
+	#[error("Failed to decode request: {0}.")]
+	DecodeScale(#[from] codec::Error),
+
+	#[error(transparent)]
+	Client(#[from] sp_blockchain::Error),
+
+	#[error("Failed to send response.")]
+	SendResponse,
+}
@@ -17,20 +17,40 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! State sync support.
+//!
+//! The key space of the top trie is split into disjoint ranges as more peers become available,
+//! each of them downloaded from one peer at a time with its own key cursor. A child trie is
+//! downloaded along with the range containing its root key in the top trie.
+//!
+//! When enabled, the state is first downloaded as a snapshot, whose chunks are requested from
+//! as many peers as are available. Key ranges are only used if peers don't serve the snapshot.
+//!
+//! Every verified response or snapshot chunk is checkpointed, see [`checkpoint`].
 
-use crate::schema::v1::{StateEntry, StateRequest, StateResponse};
+use crate::{
+	checkpoint::{self, RangeCheckpoint, SnapshotCheckpoint, StateCheckpoint, StateChunk},
+	schema::v1::{StateEntry, StateRequest, StateResponse},
+	snapshot::{SnapshotDownload, SnapshotRequest, SnapshotResponse},
+};
 use codec::{Decode, Encode};
+use libp2p::PeerId;
 use log::debug;
-use sc_client_api::{CompactProof, ProofProvider};
+use sc_client_api::{AuxStore, CompactProof, ProofProvider};
 use sc_consensus::ImportedState;
 use sc_network_common::sync::StateDownloadProgress;
-use smallvec::SmallVec;
+use smallvec::{smallvec, SmallVec};
 use sp_core::storage::well_known_keys;
 use sp_runtime::{
 	traits::{Block as BlockT, Header, NumberFor},
 	Justifications,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+/// Maximum number of key ranges downloaded in parallel.
+const MAX_PARALLEL_RANGES: usize = 8;
 
 /// State sync state machine. Accumulates partial state data until it
 /// is ready to be imported.
@@ -40,12 +60,53 @@ pub struct StateSync<B: BlockT, Client> {
 	target_root: B::Hash,
 	target_body: Option<Vec<B::Extrinsic>>,
 	target_justifications: Option<Justifications>,
-	last_key: SmallVec<[Vec<u8>; 2]>,
+	/// Key ranges left to download.
+	ranges: Vec<Range>,
 	state: HashMap<Vec<u8>, (Vec<(Vec<u8>, Vec<u8>)>, Vec<Vec<u8>>)>,
 	complete: bool,
 	client: Arc<Client>,
 	imported_bytes: u64,
 	skip_proof: bool,
+	/// Number of chunks of state checkpointed so far.
+	checkpointed_chunks: u32,
+	/// Snapshot download in progress, if any.
+	snapshot: Option<SnapshotDownload<B::Hash>>,
+}
+
+/// Request produced by [`StateSync`].
+#[derive(Debug)]
+pub enum StateSyncRequest<B: BlockT> {
+	/// Request of a range of keys, for the state request protocol.
+	State(StateRequest),
+	/// Request of a snapshot manifest or chunk, for the snapshot request protocol.
+	Snapshot(SnapshotRequest<B::Hash>),
+}
+
+/// A range of keys of the top trie.
+struct Range {
+	/// Key cursor of the next request. The keys up to the cursor have been downloaded.
+	cursor: SmallVec<[Vec<u8>; 2]>,
+	/// Last key of the range, or `None` if the range extends to the end of the trie.
+	end: Option<Vec<u8>>,
+	/// Peer the range is being downloaded from.
+	peer: Option<PeerId>,
+}
+
+impl Range {
+	/// Returns `true` if all the keys of the range are before `cursor`.
+	fn is_before(&self, cursor: &[Vec<u8>]) -> bool {
+		match (&self.end, cursor.first()) {
+			(Some(end), Some(key)) => key > end || (key == end && cursor.len() == 1),
+			_ => false,
+		}
+	}
+
+	/// Approximate share of the key space left to download in this range.
+	fn width(&self) -> f64 {
+		let start = self.cursor.first().map_or(0.0, |key| key_position(key));
+		let end = self.end.as_ref().map_or(1.0, |key| key_position(key));
+		(end - start).max(0.0)
+	}
 }
 
 /// Import state chunk result.
@@ -61,9 +122,12 @@ pub enum ImportResult<B: BlockT> {
 impl<B, Client> StateSync<B, Client>
 where
 	B: BlockT,
-	Client: ProofProvider<B> + Send + Sync + 'static,
+	Client: ProofProvider<B> + AuxStore + Send + Sync + 'static,
 {
 	///  Create a new instance.
+	///
+	/// Resumes from the state checkpointed by a previous instance if it was downloading the state
+	/// of the same block, and discards it otherwise.
 	pub fn new(
 		client: Arc<Client>,
 		target_header: B::Header,
@@ -71,23 +135,100 @@ where
 		target_justifications: Option<Justifications>,
 		skip_proof: bool,
 	) -> Self {
-		Self {
+		let mut sync = Self {
 			client,
 			target_block: target_header.hash(),
 			target_root: *target_header.state_root(),
 			target_header,
 			target_body,
 			target_justifications,
-			last_key: SmallVec::default(),
+			ranges: vec![Range { cursor: SmallVec::default(), end: None, peer: None }],
 			state: HashMap::default(),
 			complete: false,
 			imported_bytes: 0,
 			skip_proof,
+			checkpointed_chunks: 0,
+			snapshot: None,
+		};
+		sync.resume();
+		sync
+	}
+
+	/// Download the state as a snapshot, unless resuming from checkpointed key ranges or
+	/// snapshot.
+	pub fn enable_snapshot(&mut self) {
+		if self.checkpointed_chunks == 0 && self.snapshot.is_none() {
+			self.snapshot = Some(SnapshotDownload::new(self.target_block, self.target_root));
+		}
+	}
+
+	/// Returns `true` if the state is being downloaded as a snapshot.
+	pub fn downloads_snapshot(&self) -> bool {
+		self.snapshot.is_some()
+	}
+
+	/// Returns the header of the block whose state was being downloaded before a restart, if any.
+	pub fn checkpointed_target(client: &Client, skip_proof: bool) -> Option<B::Header> {
+		checkpoint::load_state_checkpoint::<B, _>(client)
+			.filter(|checkpoint| checkpoint.skip_proof == skip_proof)
+			.map(|checkpoint| checkpoint.header)
+	}
+
+	fn resume(&mut self) {
+		let Some(checkpoint) = checkpoint::load_state_checkpoint::<B, _>(&*self.client) else {
+			return
+		};
+		let valid_snapshot = checkpoint.snapshot.as_ref().map_or(true, |snapshot| {
+			!self.skip_proof &&
+				snapshot.manifest.is_valid_for(&self.target_block, &self.target_root)
+		});
+		if checkpoint.header.hash() != self.target_block ||
+			checkpoint.skip_proof != self.skip_proof ||
+			checkpoint.ranges.is_empty() ||
+			!valid_snapshot
+		{
+			debug!(
+				target: "sync",
+				"Discarding state checkpointed for block {:?}",
+				checkpoint.header.hash(),
+			);
+			checkpoint::clear_state(&*self.client);
+			return
+		}
+		let Some((checkpoint, chunks)) = checkpoint::load_state::<B, _>(&*self.client) else {
+			return
+		};
+
+		for chunk in chunks {
+			self.apply_chunk(chunk);
 		}
+		self.ranges = checkpoint
+			.ranges
+			.into_iter()
+			.map(|range| Range { cursor: range.cursor.into(), end: range.end, peer: None })
+			.collect();
+		self.imported_bytes = checkpoint.imported_bytes;
+		self.checkpointed_chunks = checkpoint.chunks;
+		self.snapshot = checkpoint
+			.snapshot
+			.map(|snapshot| SnapshotDownload::resume(snapshot.manifest, &snapshot.imported));
+		debug!(
+			target: "sync",
+			"Resuming state sync of block {:?} from {} checkpointed chunks",
+			self.target_block,
+			self.checkpointed_chunks,
+		);
 	}
 
-	///  Validate and import a state response.
-	pub fn import(&mut self, response: StateResponse) -> ImportResult<B> {
+	///  Validate and import a state response received from `who`.
+	pub fn import(&mut self, who: PeerId, response: StateResponse) -> ImportResult<B> {
+		let Some(index) = self.ranges.iter().position(|range| range.peer == Some(who)) else {
+			debug!(target: "sync", "Unexpected state response from {}", who);
+			return ImportResult::BadResponse
+		};
+		// Whatever the outcome, the range can be requested again.
+		self.ranges[index].peer = None;
+
 		if response.entries.is_empty() && response.proof.is_empty() {
 			debug!(target: "sync", "Bad state response");
 			return ImportResult::BadResponse
@@ -96,7 +237,12 @@ where
 			debug!(target: "sync", "Missing proof");
 			return ImportResult::BadResponse
 		}
-		let complete = if !self.skip_proof {
+		if self.skip_proof && response.entries.is_empty() {
+			debug!(target: "sync", "Missing entries");
+			return ImportResult::BadResponse
+		}
+		let mut cursor = self.ranges[index].cursor.clone();
+		let (mut chunk, range_complete) = if !self.skip_proof {
 			debug!(target: "sync", "Importing state from {} trie nodes", response.proof.len());
 			let proof_size = response.proof.len() as u64;
 			let proof = match CompactProof::decode(&mut response.proof.as_ref()) {
@@ -106,81 +252,45 @@ where
 					return ImportResult::BadResponse
 				},
 			};
-			let (values, completed) = match self.client.verify_range_proof(
-				self.target_root,
-				proof,
-				self.last_key.as_slice(),
-			) {
-				Err(e) => {
-					debug!(
-						target: "sync",
-						"StateResponse failed proof verification: {}",
-						e,
-					);
-					return ImportResult::BadResponse
-				},
-				Ok(values) => values,
-			};
+			let (values, completed) =
+				match self.client.verify_range_proof(self.target_root, proof, cursor.as_slice()) {
+					Err(e) => {
+						debug!(
+							target: "sync",
+							"StateResponse failed proof verification: {}",
+							e,
+						);
+						return ImportResult::BadResponse
+					},
+					Ok(values) => values,
+				};
 			debug!(target: "sync", "Imported with {} keys", values.len());
 
 			let complete = completed == 0;
-			if !complete && !values.update_last_key(completed, &mut self.last_key) {
+			if !complete && !values.update_last_key(completed, &mut cursor) {
 				debug!(target: "sync", "Error updating key cursor, depth: {}", completed);
 			};
 
-			for values in values.0 {
-				let key_values = if values.state_root.is_empty() {
-					// Read child trie roots.
-					values
-						.key_values
-						.into_iter()
-						.filter(|key_value| {
-							if well_known_keys::is_child_storage_key(key_value.0.as_slice()) {
-								self.state
-									.entry(key_value.1.clone())
-									.or_default()
-									.1
-									.push(key_value.0.clone());
-								false
-							} else {
-								true
-							}
-						})
-						.collect()
-				} else {
-					values.key_values
-				};
-				let mut entry = self.state.entry(values.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child_trie with same root.
-					// Warning this will not work with parallel download.
-				} else if entry.0.is_empty() {
-					for (key, _value) in key_values.iter() {
-						self.imported_bytes += key.len() as u64;
-					}
-
-					entry.0 = key_values;
-				} else {
-					for (key, value) in key_values {
-						self.imported_bytes += key.len() as u64;
-						entry.0.push((key, value))
-					}
-				}
-			}
+			let chunk = values
+				.0
+				.into_iter()
+				.map(|values| (values.state_root, values.key_values))
+				.collect::<StateChunk>();
 			self.imported_bytes += proof_size;
-			complete
+			(chunk, complete)
 		} else {
 			let mut complete = true;
 			// if the trie is a child trie and one of its parent trie is empty,
 			// the parent cursor stays valid.
 			// Empty parent trie content only happens when all the response content
 			// is part of a single child trie.
-			if self.last_key.len() == 2 && response.entries[0].entries.is_empty() {
+			if cursor.len() == 2 && response.entries[0].entries.is_empty() {
 				// Do not remove the parent trie position.
-				self.last_key.pop();
+				cursor.pop();
 			} else {
-				self.last_key.clear();
+				cursor.clear();
 			}
+			let mut chunk = StateChunk::with_capacity(response.entries.len());
 			for state in response.entries {
 				debug!(
 					target: "sync",
@@ -191,55 +301,260 @@ where
 
 				if !state.complete {
 					if let Some(e) = state.entries.last() {
-						self.last_key.push(e.key.clone());
+						cursor.push(e.key.clone());
 					}
 					complete = false;
 				}
-				let is_top = state.state_root.is_empty();
-				let entry = self.state.entry(state.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child trie with same root.
-				} else {
-					let mut child_roots = Vec::new();
-					for StateEntry { key, value } in state.entries {
-						// Skip all child key root (will be recalculated on import).
-						if is_top && well_known_keys::is_child_storage_key(key.as_slice()) {
-							child_roots.push((value, key));
-						} else {
-							self.imported_bytes += key.len() as u64;
-							entry.0.push((key, value))
-						}
-					}
-					for (root, storage_key) in child_roots {
-						self.state.entry(root).or_default().1.push(storage_key);
-					}
-				}
+				let key_values = state
+					.entries
+					.into_iter()
+					.map(|StateEntry { key, value }| (key, value))
+					.collect();
+				chunk.push((state.state_root, key_values));
 			}
-			complete
+			(chunk, complete)
 		};
+
+		// Peers don't know where the range ends, drop whatever belongs to the next ones.
+		let range = &mut self.ranges[index];
+		if let Some(end) = &range.end {
+			truncate_chunk(&mut chunk, end);
+		}
+		if range_complete || range.is_before(&cursor) {
+			self.ranges.swap_remove(index);
+		} else {
+			range.cursor = cursor;
+		}
+
+		let complete = self.ranges.is_empty();
+		self.import_chunk(chunk, complete);
 		if complete {
-			self.complete = true;
-			ImportResult::Import(
-				self.target_block,
-				self.target_header.clone(),
-				ImportedState {
-					block: self.target_block,
-					state: std::mem::take(&mut self.state).into(),
+			self.complete()
+		} else {
+			ImportResult::Continue
+		}
+	}
+
+	/// Validate and import a snapshot response received from `who`.
+	pub fn import_snapshot(
+		&mut self,
+		who: PeerId,
+		response: SnapshotResponse<B::Hash>,
+	) -> ImportResult<B> {
+		let Some(snapshot) = &mut self.snapshot else {
+			// Requested before falling back to key ranges.
+			debug!(target: "sync", "Ignored obsolete snapshot response from {}", who);
+			return ImportResult::Continue
+		};
+		let data = match response {
+			SnapshotResponse::Unavailable => {
+				debug!(target: "sync", "Snapshot of {:?} unavailable from {}", self.target_block, who);
+				snapshot.peer_unavailable(who);
+				if snapshot.should_give_up() {
+					debug!(target: "sync", "Downloading state by key ranges instead of snapshot");
+					self.snapshot = None;
+					self.state.clear();
+					self.imported_bytes = 0;
+					self.checkpointed_chunks = 0;
+					checkpoint::clear_state(&*self.client);
+				}
+				return ImportResult::Continue
+			},
+			SnapshotResponse::Manifest(manifest) => {
+				if !snapshot.import_manifest(who, manifest) {
+					debug!(target: "sync", "Bad snapshot manifest from {}", who);
+					return ImportResult::BadResponse
+				}
+				return ImportResult::Continue
+			},
+			SnapshotResponse::Chunk(data) => data,
+		};
+
+		let Some(received) = snapshot.receive_chunk(who, &data) else {
+			debug!(target: "sync", "Unexpected snapshot chunk from {}", who);
+			return ImportResult::BadResponse
+		};
+		let proof = match CompactProof::decode(&mut data.as_ref()) {
+			Ok(proof) => proof,
+			Err(e) => {
+				debug!(target: "sync", "Error decoding snapshot chunk: {:?}", e);
+				return ImportResult::BadResponse
+			},
+		};
+		let (values, completed) =
+			match self.client.verify_range_proof(self.target_root, proof, &received.start) {
+				Ok(values) => values,
+				Err(e) => {
+					debug!(target: "sync", "Snapshot chunk failed proof verification: {}", e);
+					return ImportResult::BadResponse
 				},
-				self.target_body.clone(),
-				self.target_justifications.clone(),
-			)
+			};
+		// The chunk must stop right where the next one starts, or at the end of the state.
+		let ends_as_expected = match &received.end {
+			Some(end) => {
+				let mut cursor = SmallVec::from_vec(received.start.clone());
+				completed != 0 &&
+					values.update_last_key(completed, &mut cursor) &&
+					cursor[..] == end[..]
+			},
+			None => completed == 0,
+		};
+		if !ends_as_expected {
+			debug!(target: "sync", "Snapshot chunk {} doesn't end as expected", received.index);
+			return ImportResult::BadResponse
+		}
+
+		snapshot.chunk_imported(received.index);
+		let complete = snapshot.is_complete();
+		self.imported_bytes += data.len() as u64;
+		self.import_chunk(
+			values
+				.0
+				.into_iter()
+				.map(|values| (values.state_root, values.key_values))
+				.collect(),
+			complete,
+		);
+		if complete {
+			self.complete()
 		} else {
 			ImportResult::Continue
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_request(&self) -> StateRequest {
-		StateRequest {
+	/// Mark the state as complete, handing it over for import.
+	fn complete(&mut self) -> ImportResult<B> {
+		self.complete = true;
+		ImportResult::Import(
+			self.target_block,
+			self.target_header.clone(),
+			ImportedState {
+				block: self.target_block,
+				state: std::mem::take(&mut self.state).into(),
+			},
+			self.target_body.clone(),
+			self.target_justifications.clone(),
+		)
+	}
+
+	/// Apply a chunk of verified state, checkpointing it unless it completes the state.
+	fn import_chunk(&mut self, chunk: StateChunk, complete: bool) {
+		if complete {
+			self.apply_chunk(chunk);
+			return
+		}
+
+		self.apply_chunk(chunk.clone());
+		self.checkpointed_chunks += 1;
+		let checkpoint = StateCheckpoint::<B> {
+			header: self.target_header.clone(),
+			body: self.target_body.clone(),
+			justifications: self.target_justifications.clone(),
+			skip_proof: self.skip_proof,
+			ranges: self
+				.ranges
+				.iter()
+				.map(|range| RangeCheckpoint {
+					cursor: range.cursor.to_vec(),
+					end: range.end.clone(),
+				})
+				.collect(),
+			imported_bytes: self.imported_bytes,
+			chunks: self.checkpointed_chunks,
+			snapshot: self.snapshot.as_ref().and_then(|snapshot| {
+				Some(SnapshotCheckpoint {
+					manifest: snapshot.manifest()?.clone(),
+					imported: snapshot.imported(),
+				})
+			}),
+		};
+		checkpoint::store_state_chunk(&*self.client, &checkpoint, &chunk);
+	}
+
+	fn apply_chunk(&mut self, chunk: StateChunk) {
+		for (state_root, key_values) in chunk {
+			let is_top = state_root.is_empty();
+			let entry = self.state.entry(state_root).or_default();
+			if entry.0.len() > 0 && entry.1.len() > 1 {
+				// Already imported child trie with same root.
+				// Child tries are only ever downloaded along with the range containing the child
+				// storage keys, so the same trie isn't downloaded twice in parallel.
+				continue
+			}
+
+			let mut child_roots = Vec::new();
+			for (key, value) in key_values {
+				// Skip all child key root (will be recalculated on import).
+				if is_top && well_known_keys::is_child_storage_key(key.as_slice()) {
+					child_roots.push((value, key));
+				} else {
+					self.imported_bytes += key.len() as u64;
+					entry.0.push((key, value))
+				}
+			}
+			for (root, storage_key) in child_roots {
+				self.state.entry(root).or_default().1.push(storage_key);
+			}
+		}
+	}
+
+	/// Produce the next request, to be sent to `who`.
+	///
+	/// Returns `None` if all the ranges left are being downloaded and none can be split further,
+	/// or if there is nothing `who` can be asked for about the snapshot being downloaded.
+	pub fn next_request(&mut self, who: PeerId) -> Option<StateSyncRequest<B>> {
+		if self.complete {
+			return None
+		}
+		if let Some(snapshot) = &mut self.snapshot {
+			return snapshot.next_request(who).map(StateSyncRequest::Snapshot)
+		}
+
+		let index = match self.ranges.iter().position(|range| range.peer.is_none()) {
+			Some(index) => index,
+			None => self.split_range()?,
+		};
+		let range = &mut self.ranges[index];
+		range.peer = Some(who);
+		Some(StateSyncRequest::State(StateRequest {
 			block: self.target_block.encode(),
-			start: self.last_key.clone().into_vec(),
+			start: range.cursor.to_vec(),
 			no_proof: self.skip_proof,
+		}))
+	}
+
+	/// Split the widest range in two, returning the index of the new range.
+	fn split_range(&mut self) -> Option<usize> {
+		if self.ranges.len() >= MAX_PARALLEL_RANGES {
+			return None
+		}
+
+		let (index, middle) = self
+			.ranges
+			.iter()
+			.enumerate()
+			.filter_map(|(index, range)| {
+				let start = range.cursor.first().map_or(&[][..], |key| &key[..]);
+				let middle = middle_key(start, range.end.as_deref())?;
+				Some((index, middle, range.width()))
+			})
+			.max_by(|a, b| a.2.total_cmp(&b.2))
+			.map(|(index, middle, _)| (index, middle))?;
+
+		// The middle key is the last of the first half, and the cursor of the second one.
+		let end = self.ranges[index].end.replace(middle.clone());
+		self.ranges.push(Range { cursor: smallvec![middle], end, peer: None });
+		Some(self.ranges.len() - 1)
+	}
+
+	/// Forget about the request sent to `who`, so that its range can be requested from another
+	/// peer.
+	pub fn release_peer(&mut self, who: &PeerId) {
+		if let Some(snapshot) = &mut self.snapshot {
+			snapshot.release_peer(who);
+		}
+		for range in self.ranges.iter_mut().filter(|range| range.peer.as_ref() == Some(who)) {
+			range.peer = None;
 		}
 	}
 
@@ -260,8 +575,240 @@ where
 
 	/// Returns state sync estimated progress.
 	pub fn progress(&self) -> StateDownloadProgress {
-		let cursor = *self.last_key.get(0).and_then(|last| last.get(0)).unwrap_or(&0u8);
-		let percent_done = cursor as u32 * 100 / 256;
+		let remaining = match &self.snapshot {
+			Some(snapshot) => 1.0 - snapshot.progress(),
+			None => self.ranges.iter().map(Range::width).sum::<f64>(),
+		};
+		let percent_done = ((1.0 - remaining) * 100.0).clamp(0.0, 100.0) as u32;
 		StateDownloadProgress { percentage: percent_done, size: self.imported_bytes }
 	}
 }
+
+/// Remove from `chunk` the keys of the top trie after `end`, along with the child tries they
+/// point to.
+fn truncate_chunk(chunk: &mut StateChunk, end: &[u8]) {
+	let mut kept_roots = HashSet::new();
+	let mut dropped_roots = HashSet::new();
+	for (_, key_values) in chunk.iter_mut().filter(|(root, _)| root.is_empty()) {
+		key_values.retain(|(key, value)| {
+			let keep = key.as_slice() <= end;
+			if well_known_keys::is_child_storage_key(key) {
+				let roots = if keep { &mut kept_roots } else { &mut dropped_roots };
+				roots.insert(value.clone());
+			}
+			keep
+		});
+	}
+	chunk.retain(|(root, _)| {
+		root.is_empty() || kept_roots.contains(root) || !dropped_roots.contains(root)
+	});
+}
+
+/// Approximate position of `key` in the key space, between 0 and 1.
+fn key_position(key: &[u8]) -> f64 {
+	let mut bytes = [0u8; 8];
+	let len = key.len().min(bytes.len());
+	bytes[..len].copy_from_slice(&key[..len]);
+	u64::from_be_bytes(bytes) as f64 / (u64::MAX as f64 + 1.0)
+}
+
+/// Returns a key strictly between `start` and `end`, if there is one. A missing `end` stands for
+/// the end of the key space.
+fn middle_key(start: &[u8], end: Option<&[u8]>) -> Option<Vec<u8>> {
+	// Keys are compared as big-endian numbers of the same length, one byte longer than the
+	// longest of them so that there is room for a key in between.
+	let len = start.len().max(end.map_or(0, |end| end.len())) + 1;
+	let pad = |key: &[u8]| {
+		let mut padded = key.to_vec();
+		padded.resize(len, 0);
+		padded
+	};
+	let start = pad(start);
+	let end = end.map_or_else(|| vec![0xff; len], pad);
+
+	let mut middle = vec![0u8; len];
+	let mut carry = 0u16;
+	for i in (0..len).rev() {
+		let sum = u16::from(start[i]) + u16::from(end[i]) + carry;
+		middle[i] = sum as u8;
+		carry = sum >> 8;
+	}
+	for byte in middle.iter_mut() {
+		let low = *byte & 1;
+		*byte = (*byte >> 1) | ((carry as u8) << 7);
+		carry = u16::from(low);
+	}
+
+	(middle > start && middle < end).then_some(middle)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::snapshot::SnapshotProvider;
+	use sc_client_db::SnapshotStore;
+	use sp_blockchain::HeaderBackend;
+	use substrate_test_runtime_client::{
+		runtime::Block, DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
+
+	fn start(request: Option<StateSyncRequest<Block>>) -> Vec<Vec<u8>> {
+		match request {
+			Some(StateSyncRequest::State(request)) => request.start,
+			request => panic!("Expected a state request, got {:?}", request),
+		}
+	}
+
+	#[test]
+	fn middle_key_splits_ranges() {
+		assert_eq!(middle_key(&[], None), Some(vec![0x7f]));
+		assert_eq!(middle_key(&[0x10], Some(&[0x20])), Some(vec![0x18, 0x00]));
+		assert_eq!(middle_key(&[0x10], Some(&[0x11])), Some(vec![0x10, 0x80]));
+		assert_eq!(middle_key(&[0x10, 0xff], Some(&[0x11])), Some(vec![0x10, 0xff, 0x80]));
+		assert_eq!(middle_key(&[0x10], Some(&[0x10, 0x00])), None);
+		assert_eq!(middle_key(&[0xaa, 0x01], Some(&[0xaa, 0x01])), None);
+	}
+
+	#[test]
+	fn truncated_chunks_keep_child_tries_in_range() {
+		let child_key =
+			|name: &[u8]| [well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, name].concat();
+		let mut chunk: StateChunk = vec![
+			(
+				Vec::new(),
+				vec![
+					(child_key(b"a"), b"root_a".to_vec()),
+					(child_key(b"b"), b"root_b".to_vec()),
+					(b"z".to_vec(), Vec::new()),
+				],
+			),
+			(b"root_a".to_vec(), vec![(b"1".to_vec(), b"1".to_vec())]),
+			(b"root_b".to_vec(), vec![(b"2".to_vec(), b"2".to_vec())]),
+			(b"root_c".to_vec(), vec![(b"3".to_vec(), b"3".to_vec())]),
+		];
+		truncate_chunk(&mut chunk, &child_key(b"a"));
+
+		let roots = chunk.iter().map(|(root, _)| root.clone()).collect::<Vec<_>>();
+		assert_eq!(roots, vec![Vec::new(), b"root_a".to_vec(), b"root_c".to_vec()]);
+		assert_eq!(chunk[0].1, vec![(child_key(b"a"), b"root_a".to_vec())]);
+	}
+
+	#[test]
+	fn ranges_are_split_between_peers() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.header(client.info().genesis_hash).unwrap().unwrap();
+		let mut sync = StateSync::new(client, genesis, None, None, false);
+
+		let first = PeerId::random();
+		assert!(start(sync.next_request(first)).is_empty());
+		let second = PeerId::random();
+		assert_eq!(start(sync.next_request(second)), vec![vec![0x7f]]);
+		for _ in 2..MAX_PARALLEL_RANGES {
+			assert!(sync.next_request(PeerId::random()).is_some());
+		}
+		assert!(sync.next_request(PeerId::random()).is_none());
+
+		// The range of a peer that went away is downloaded from another one.
+		sync.release_peer(&second);
+		assert_eq!(start(sync.next_request(PeerId::random())), vec![vec![0x7f]]);
+		assert_eq!(sync.progress().percentage, 0);
+	}
+
+	#[test]
+	fn state_is_downloaded_as_snapshot() {
+		let mut builder = TestClientBuilder::new();
+		let storage = &mut builder.genesis_init_mut().extra_storage().top;
+		for i in 0u32..40_000 {
+			storage.insert(sp_core::blake2_256(&i.encode()).to_vec(), vec![0x42; 512]);
+		}
+		let backend = builder.backend();
+		let client = Arc::new(builder.build());
+		let genesis = client.header(client.info().genesis_hash).unwrap().unwrap();
+		let directory = tempfile::tempdir().unwrap();
+		let store = SnapshotStore::new(directory.path().to_path_buf(), 1);
+		store.export(&*backend, genesis.hash()).unwrap();
+		let manifest = SnapshotProvider::<Block>::snapshot_manifest(&store, genesis.hash())
+			.unwrap()
+			.unwrap();
+		let chunk = |hash| {
+			SnapshotProvider::<Block>::snapshot_chunk(&store, genesis.hash(), hash)
+				.unwrap()
+				.unwrap()
+		};
+		let mut sync = StateSync::new(client.clone(), genesis.clone(), None, None, false);
+		sync.enable_snapshot();
+
+		let peers = (0..manifest.chunks.len()).map(|_| PeerId::random()).collect::<Vec<_>>();
+		let Some(StateSyncRequest::Snapshot(SnapshotRequest::Manifest { .. })) =
+			sync.next_request(peers[0])
+		else {
+			panic!("Expected a manifest request")
+		};
+		assert!(matches!(
+			sync.import_snapshot(peers[0], SnapshotResponse::Manifest(manifest.clone())),
+			ImportResult::Continue,
+		));
+
+		// Every chunk is requested from a different peer and they respond in reverse order.
+		let mut requested = Vec::new();
+		for peer in &peers {
+			let Some(StateSyncRequest::Snapshot(SnapshotRequest::Chunk { hash, .. })) =
+				sync.next_request(*peer)
+			else {
+				panic!("Expected a chunk request")
+			};
+			requested.push((*peer, hash));
+		}
+		assert!(sync.next_request(PeerId::random()).is_none());
+
+		let last = requested.remove(0);
+		for (peer, hash) in requested.into_iter().rev() {
+			assert!(matches!(
+				sync.import_snapshot(peer, SnapshotResponse::Chunk(chunk(hash))),
+				ImportResult::Continue,
+			));
+		}
+
+		// After a restart, only the chunk that wasn't checkpointed is left to download.
+		let mut sync = StateSync::new(client.clone(), genesis.clone(), None, None, false);
+		sync.enable_snapshot();
+		assert!(sync.downloads_snapshot());
+		let Some(StateSyncRequest::Snapshot(SnapshotRequest::Chunk { hash, .. })) =
+			sync.next_request(last.0)
+		else {
+			panic!("Expected a chunk request")
+		};
+		assert_eq!(hash, last.1);
+		assert!(sync.next_request(PeerId::random()).is_none());
+
+		let ImportResult::Import(_, _, state, _, _) =
+			sync.import_snapshot(last.0, SnapshotResponse::Chunk(chunk(hash)))
+		else {
+			panic!("Expected the state to be complete")
+		};
+		let keys = state.state.0.iter().map(|level| level.key_values.len()).sum::<usize>();
+		assert!(keys >= 40_000);
+	}
+
+	#[test]
+	fn falls_back_to_key_ranges_without_snapshot() {
+		let client = Arc::new(TestClientBuilder::new().build());
+		let genesis = client.header(client.info().genesis_hash).unwrap().unwrap();
+		let mut sync = StateSync::new(client, genesis, None, None, false);
+		sync.enable_snapshot();
+
+		loop {
+			let peer = PeerId::random();
+			match sync.next_request(peer) {
+				Some(StateSyncRequest::Snapshot(_)) => {
+					sync.import_snapshot(peer, SnapshotResponse::Unavailable);
+				},
+				request => {
+					assert!(start(request).is_empty());
+					break
+				},
+			}
+		}
+		assert!(!sync.downloads_snapshot());
+	}
+}
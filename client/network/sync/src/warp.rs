@@ -19,13 +19,16 @@
 //! Warp sync support.
 
 use crate::{
+	checkpoint::{self, WarpCheckpoint},
 	oneshot,
-	schema::v1::{StateRequest, StateResponse},
-	state::{ImportResult, StateSync},
+	schema::v1::StateResponse,
+	snapshot::SnapshotResponse,
+	state::{ImportResult, StateSync, StateSyncRequest},
 };
 use futures::FutureExt;
+use libp2p::PeerId;
 use log::error;
-use sc_client_api::ProofProvider;
+use sc_client_api::{AuxStore, ProofProvider};
 use sc_network_common::sync::{
 	message::{BlockAttributes, BlockData, BlockRequest, Direction, FromBlock},
 	warp::{
@@ -73,32 +76,70 @@ pub struct WarpSync<B: BlockT, Client> {
 	phase: Phase<B, Client>,
 	client: Arc<Client>,
 	total_proof_bytes: u64,
+	/// Download the state as a snapshot.
+	snapshot: bool,
 }
 
 impl<B, Client> WarpSync<B, Client>
 where
 	B: BlockT,
-	Client: HeaderBackend<B> + ProofProvider<B> + 'static,
+	Client: HeaderBackend<B> + ProofProvider<B> + AuxStore + 'static,
 {
 	/// Create a new instance. When passing a warp sync provider we will be checking for proof and
 	/// authorities. Alternatively we can pass a target block when we want to skip downloading
 	/// proofs, in this case we will continue polling until the target block is known.
-	pub fn new(client: Arc<Client>, warp_sync_params: WarpSyncParams<B>) -> Self {
+	///
+	/// With a warp sync provider, the proofs verified before a restart are not downloaded again.
+	/// With `snapshot`, the state of the target block is downloaded as a snapshot if peers serve
+	/// it.
+	pub fn new(client: Arc<Client>, warp_sync_params: WarpSyncParams<B>, snapshot: bool) -> Self {
 		let last_hash = client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
 		match warp_sync_params {
-			WarpSyncParams::WithProvider(warp_sync_provider) => {
-				let phase = Phase::WarpProof {
-					set_id: 0,
-					authorities: warp_sync_provider.current_authorities(),
-					last_hash,
-					warp_sync_provider: warp_sync_provider.clone(),
-				};
-				Self { client, phase, total_proof_bytes: 0 }
-			},
+			WarpSyncParams::WithProvider(warp_sync_provider) =>
+				match checkpoint::load_warp::<B, _>(&*client) {
+					Some(WarpCheckpoint::Proofs {
+						set_id,
+						authorities,
+						last_hash,
+						total_proof_bytes,
+					}) => {
+						log::debug!(
+							target: "sync",
+							"Resuming warp sync from checkpoint, set_id={:?}",
+							set_id,
+						);
+						let phase =
+							Phase::WarpProof { set_id, authorities, last_hash, warp_sync_provider };
+						Self { client, phase, total_proof_bytes, snapshot }
+					},
+					Some(WarpCheckpoint::TargetBlock { header, total_proof_bytes }) => {
+						log::debug!(
+							target: "sync",
+							"Resuming warp sync of target block {:?} from checkpoint",
+							header.hash(),
+						);
+						Self {
+							client,
+							phase: Phase::TargetBlock(header),
+							total_proof_bytes,
+							snapshot,
+						}
+					},
+					None => {
+						let phase = Phase::WarpProof {
+							set_id: 0,
+							authorities: warp_sync_provider.current_authorities(),
+							last_hash,
+							warp_sync_provider: warp_sync_provider.clone(),
+						};
+						Self { client, phase, total_proof_bytes: 0, snapshot }
+					},
+				},
 			WarpSyncParams::WaitForTarget(block) => Self {
 				client,
 				phase: Phase::PendingTargetBlock { target_block: Some(block) },
 				total_proof_bytes: 0,
+				snapshot,
 			},
 		}
 	}
@@ -126,14 +167,29 @@ where
 		self.phase = new_phase;
 	}
 
-	///  Validate and import a state response.
-	pub fn import_state(&mut self, response: StateResponse) -> ImportResult<B> {
+	///  Validate and import a state response received from `who`.
+	pub fn import_state(&mut self, who: PeerId, response: StateResponse) -> ImportResult<B> {
 		match &mut self.phase {
 			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } => {
 				log::debug!(target: "sync", "Unexpected state response");
 				ImportResult::BadResponse
 			},
-			Phase::State(sync) => sync.import(response),
+			Phase::State(sync) => sync.import(who, response),
+		}
+	}
+
+	///  Validate and import a snapshot response received from `who`.
+	pub fn import_snapshot(
+		&mut self,
+		who: PeerId,
+		response: SnapshotResponse<B::Hash>,
+	) -> ImportResult<B> {
+		match &mut self.phase {
+			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } => {
+				log::debug!(target: "sync", "Unexpected snapshot response");
+				ImportResult::BadResponse
+			},
+			Phase::State(sync) => sync.import_snapshot(who, response),
 		}
 	}
 
@@ -156,11 +212,27 @@ where
 						*authorities = new_authorities;
 						*last_hash = new_last_hash;
 						self.total_proof_bytes += response.0.len() as u64;
+						checkpoint::store_warp(
+							&*self.client,
+							&WarpCheckpoint::<B>::Proofs {
+								set_id: new_set_id,
+								authorities: authorities.clone(),
+								last_hash: new_last_hash,
+								total_proof_bytes: self.total_proof_bytes,
+							},
+						);
 						WarpProofImportResult::Success
 					},
 					Ok(VerificationResult::Complete(new_set_id, _, header)) => {
 						log::debug!(target: "sync", "Verified complete proof, set_id={:?}", new_set_id);
 						self.total_proof_bytes += response.0.len() as u64;
+						checkpoint::store_warp(
+							&*self.client,
+							&WarpCheckpoint::<B>::TargetBlock {
+								header: header.clone(),
+								total_proof_bytes: self.total_proof_bytes,
+							},
+						);
 						self.phase = Phase::TargetBlock(header);
 						WarpProofImportResult::Success
 					},
@@ -179,13 +251,16 @@ where
 				if let Some(block_header) = &block.header {
 					if block_header == header {
 						if block.body.is_some() {
-							let state_sync = StateSync::new(
+							let mut state_sync = StateSync::new(
 								self.client.clone(),
 								header.clone(),
 								block.body,
 								block.justifications,
 								false,
 							);
+							if self.snapshot {
+								state_sync.enable_snapshot();
+							}
 							self.phase = Phase::State(state_sync);
 							TargetBlockImportResult::Success
 						} else {
@@ -209,12 +284,19 @@ where
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_state_request(&self) -> Option<StateRequest> {
-		match &self.phase {
+	/// Produce next state request, to be sent to `who`.
+	pub fn next_state_request(&mut self, who: PeerId) -> Option<StateSyncRequest<B>> {
+		match &mut self.phase {
 			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } =>
 				None,
-			Phase::State(sync) => Some(sync.next_request()),
+			Phase::State(sync) => sync.next_request(who),
+		}
+	}
+
+	/// Forget about the state request sent to `who`.
+	pub fn release_peer(&mut self, who: &PeerId) {
+		if let Phase::State(sync) = &mut self.phase {
+			sync.release_peer(who);
 		}
 	}
 
@@ -234,7 +316,8 @@ where
 				let request = BlockRequest::<B> {
 					id: 0,
 					fields: BlockAttributes::HEADER |
-						BlockAttributes::BODY | BlockAttributes::JUSTIFICATION,
+						BlockAttributes::BODY |
+						BlockAttributes::JUSTIFICATION,
 					from: FromBlock::Hash(header.hash()),
 					direction: Direction::Ascending,
 					max: Some(1),
@@ -0,0 +1,110 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Benchmark of the download of the state of a block from a varying number of peers.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use futures::future::poll_fn;
+use sc_network::config::SyncMode;
+use sc_network_test::{FullPeerConfig, TestNet, TestNetFactory};
+use sp_core::storage::Storage;
+use sp_runtime::{codec::Encode, generic::BlockId};
+use std::task::Poll;
+
+const KEYS: u32 = 50_000;
+const VALUE_SIZE: usize = 256;
+
+fn storage() -> Storage {
+	let mut storage = Storage::default();
+	for i in 0..KEYS {
+		storage
+			.top
+			.insert(sp_core::blake2_256(&i.encode()).to_vec(), vec![1; VALUE_SIZE]);
+	}
+	storage
+}
+
+/// Build a network of `peers` peers with the state, and one more that only has the headers of
+/// the chain.
+async fn prepare(peers: usize, storage: &Storage, skip_proofs: bool) -> TestNet {
+	let mut net = TestNet::new(0);
+	for _ in 0..peers {
+		let mut config = FullPeerConfig::default();
+		config.extra_storage = Some(storage.clone());
+		net.add_full_peer_with_config(config);
+	}
+	let mut config = FullPeerConfig::default();
+	config.extra_storage = Some(storage.clone());
+	config.sync_mode = SyncMode::Fast { skip_proofs, storage_chain_mode: false };
+	net.add_full_peer_with_config(config);
+
+	net.peer(0).push_blocks(8, false);
+	net.run_until_sync().await;
+	net
+}
+
+/// Finalize the last block on the last peer, and wait until its state is downloaded.
+async fn sync_state(net: &mut TestNet) {
+	let syncing = net.peers().len() - 1;
+	let best_hash = net.peer(0).client().info().best_hash;
+	net.peer(syncing)
+		.client()
+		.finalize_block(best_hash, Some((*b"FRNK", Vec::new())), true)
+		.unwrap();
+	poll_fn(|cx| {
+		net.poll(cx);
+		if net.peer(syncing).client().has_state_at(&BlockId::Hash(best_hash)) {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	})
+	.await;
+}
+
+fn state_sync(c: &mut Criterion) {
+	sp_tracing::try_init_simple();
+
+	let runtime = tokio::runtime::Runtime::new().expect("Creates tokio runtime");
+	let _guard = runtime.enter();
+	let storage = storage();
+
+	let mut group = c.benchmark_group("State sync");
+	group.sample_size(10);
+	group.throughput(Throughput::Bytes(KEYS as u64 * (32 + VALUE_SIZE) as u64));
+
+	for skip_proofs in [false, true] {
+		for peers in [1, 2, 4, 8] {
+			let name = format!("{} peers{}", peers, if skip_proofs { " (no proofs)" } else { "" });
+			group.bench_function(name, |b| {
+				b.iter_batched(
+					|| runtime.block_on(prepare(peers, &storage, skip_proofs)),
+					|mut net| {
+						runtime.block_on(sync_state(&mut net));
+						net
+					},
+					BatchSize::PerIteration,
+				)
+			});
+		}
+	}
+}
+
+criterion_group!(benches, state_sync);
+criterion_main!(benches);
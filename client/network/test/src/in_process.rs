@@ -0,0 +1,1533 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! In-process [`NetworkBackend`].
+//!
+//! Nodes are registered in an [`InProcessHub`] and exchange notifications, requests and DHT
+//! records with the other nodes of the same hub by passing them directly to each other, without
+//! any transport, encryption or multiplexing in between. This makes tests exercising the protocols
+//! on top of the network fast and deterministic. Each test creates its own hub and builds its
+//! nodes with [`InProcessNetwork::new_in`], nodes built with [`NetworkBackend::new`] are alone in
+//! a hub of their own.
+//!
+//! Nodes are reachable through their peer ID only, the addresses of boot nodes, reserved nodes and
+//! known addresses are ignored. Connections are only opened once both sides are running.
+//!
+//! Limitations compared to the libp2p backend:
+//!
+//! - Peer sets aren't tracked per protocol. A connection always opens every notification protocol
+//!   both sides have in common.
+//! - Bans on IP addresses are recorded but have no effect, nodes don't have one.
+//! - The [`TestNet`](crate::TestNet) harness is built around the libp2p backend. Nodes syncing
+//!   blocks through this backend have to set up their syncing engine themselves.
+//!
+//! As with the libp2p backend, the block announcement protocol isn't reported on the event
+//! streams but handed to the syncing engine through [`Params::tx`].
+
+use futures::{
+	channel::{mpsc, oneshot},
+	future::BoxFuture,
+	prelude::*,
+	stream::FuturesUnordered,
+};
+use libp2p::{
+	identity::{error::SigningError, Keypair},
+	multiaddr::Protocol,
+	Multiaddr, PeerId,
+};
+use log::{debug, trace};
+use parking_lot::Mutex;
+use sc_network::{
+	config::{parse_addr, MultiaddrWithPeerId, NonReservedPeerMode, Params, Role},
+	error::Error,
+	event::{DhtEvent, Event, SyncEvent},
+	network_state::{Endpoint, NetworkState, NotConnectedPeer, Peer, PeerEndpoint},
+	request_responses::{IncomingRequest, OutgoingResponse},
+	types::ProtocolName,
+	Ban, BanTarget, IfDisconnected, KademliaKey, MessageSink, NetworkBackend, NetworkDHTProvider,
+	NetworkEventStream, NetworkNotification, NetworkPeerAdmin, NetworkPeers, NetworkRequest,
+	NetworkSigner, NetworkStateInfo, NetworkStatus, NetworkStatusProvider, NotificationSenderError,
+	NotificationSenderReady, NotificationSenderT, PeerReputation, ReputationChange, RequestFailure,
+	Signature,
+};
+use sc_network_common::{role::ObservedRole, sync::message::BlockAnnouncesHandshake, ExHashT};
+use sc_peerset::{BANNED_THRESHOLD, MAX_BAN_DURATION};
+use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
+use sp_runtime::{codec::DecodeAll, traits::Block as BlockT};
+use std::{
+	collections::{HashMap, HashSet},
+	fmt,
+	marker::PhantomData,
+	sync::{Arc, Weak},
+	time::{Duration, SystemTime},
+};
+
+const LOG_TARGET: &str = "in-process-network";
+
+/// Set of in-process nodes that can reach each other.
+///
+/// Cloning the hub gives another handle to the same nodes. Tests running in parallel should each
+/// use their own hub, so that they don't see each other's nodes and DHT records.
+#[derive(Clone)]
+pub struct InProcessHub(Arc<Mutex<Hub>>);
+
+impl InProcessHub {
+	fn with<R>(&self, f: impl FnOnce(&mut Hub) -> R) -> R {
+		f(&mut self.0.lock())
+	}
+}
+
+impl Default for InProcessHub {
+	fn default() -> Self {
+		Self(Arc::new_cyclic(|handle| {
+			Mutex::new(Hub { handle: handle.clone(), nodes: HashMap::new(), dht: HashMap::new() })
+		}))
+	}
+}
+
+struct Hub {
+	/// Handle to the hub itself, for the tasks and sinks outliving a call.
+	handle: Weak<Mutex<Hub>>,
+	nodes: HashMap<PeerId, Node>,
+	/// Records stored in the DHT shared by all nodes.
+	dht: HashMap<KademliaKey, Vec<u8>>,
+}
+
+struct Node {
+	role: ObservedRole,
+	/// Set once [`NetworkBackend::run`] has been called.
+	running: bool,
+	/// Notification protocols and the handshake we send on each of them.
+	notification_protocols: HashMap<ProtocolName, Vec<u8>>,
+	/// Notification protocol handed to the syncing engine instead of the event streams.
+	block_announce_protocol: ProtocolName,
+	sync_events: Box<dyn SyncEvents>,
+	/// Request-response protocols and where to send inbound requests.
+	request_protocols: HashMap<ProtocolName, Option<mpsc::Sender<IncomingRequest>>>,
+	event_streams: Vec<TracingUnboundedSender<Event>>,
+	/// Notification protocols currently open with each peer.
+	open: HashMap<PeerId, HashSet<ProtocolName>>,
+	/// Peers we try to connect to once both sides are running.
+	wanted: HashSet<PeerId>,
+	reserved: HashSet<PeerId>,
+	reserved_only: bool,
+	reputations: HashMap<PeerId, i32>,
+	/// Bans placed with [`NetworkPeerAdmin::ban`] and when they expire.
+	bans: HashMap<BanTarget, SystemTime>,
+	/// Responses to requests we sent and substreams waiting for the syncing engine, to be driven
+	/// by the worker.
+	to_worker: TracingUnboundedSender<BoxFuture<'static, ()>>,
+	bytes_inbound: u64,
+	bytes_outbound: u64,
+}
+
+impl Node {
+	fn accepts(&self, peer: &PeerId) -> bool {
+		let banned = self.reputations.get(peer).map_or(false, |r| *r < BANNED_THRESHOLD) ||
+			self.bans
+				.get(&BanTarget::Peer(*peer))
+				.map_or(false, |expires_at| *expires_at > SystemTime::now());
+		!banned && (!self.reserved_only || self.reserved.contains(peer))
+	}
+
+	fn emit(&mut self, event: Event) {
+		self.event_streams.retain(|stream| stream.unbounded_send(event.clone()).is_ok());
+	}
+}
+
+/// Channel to the syncing engine of a node, see [`Params::tx`].
+///
+/// Hides the block type, which the hub doesn't know about.
+trait SyncEvents: Send {
+	/// Report a block announcement substream opened with `remote`.
+	///
+	/// Returns whether the engine accepted the substream, or `None` if the handshake of `remote`
+	/// is invalid.
+	fn opened(
+		&self,
+		remote: PeerId,
+		received_handshake: &[u8],
+		sink: Box<dyn MessageSink>,
+	) -> Option<oneshot::Receiver<bool>>;
+
+	fn closed(&self, remote: PeerId);
+
+	fn received(&self, remote: PeerId, message: Vec<u8>);
+}
+
+impl<B: BlockT> SyncEvents for TracingUnboundedSender<SyncEvent<B>> {
+	fn opened(
+		&self,
+		remote: PeerId,
+		received_handshake: &[u8],
+		sink: Box<dyn MessageSink>,
+	) -> Option<oneshot::Receiver<bool>> {
+		let received_handshake =
+			BlockAnnouncesHandshake::<B>::decode_all(&mut &received_handshake[..]).ok()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.unbounded_send(SyncEvent::NotificationStreamOpened {
+			remote,
+			received_handshake,
+			sink,
+			tx,
+		});
+		Some(rx)
+	}
+
+	fn closed(&self, remote: PeerId) {
+		let _ = self.unbounded_send(SyncEvent::NotificationStreamClosed { remote });
+	}
+
+	fn received(&self, remote: PeerId, message: Vec<u8>) {
+		let _ = self.unbounded_send(SyncEvent::NotificationsReceived {
+			remote,
+			messages: vec![message.into()],
+		});
+	}
+}
+
+impl Hub {
+	/// Remember that `local` wants to be connected to `remote` and connect them if possible.
+	fn want(&mut self, local: PeerId, remote: PeerId) {
+		if let Some(node) = self.nodes.get_mut(&local) {
+			node.wanted.insert(remote);
+		}
+		self.connect(local, remote);
+	}
+
+	/// Try to connect `local` to all the peers it wants to be connected to.
+	fn connect_wanted(&mut self, local: PeerId) {
+		let wanted = self
+			.nodes
+			.get(&local)
+			.map(|node| node.wanted.iter().cloned().collect::<Vec<_>>())
+			.unwrap_or_default();
+		for peer in wanted {
+			self.connect(local, peer);
+		}
+	}
+
+	/// Open all the notification protocols `local` and `remote` have in common.
+	fn connect(&mut self, local: PeerId, remote: PeerId) {
+		if local == remote {
+			return
+		}
+
+		let (Some(local_node), Some(remote_node)) =
+			(self.nodes.get(&local), self.nodes.get(&remote))
+		else {
+			return
+		};
+
+		if !local_node.running ||
+			!remote_node.running ||
+			!local_node.accepts(&remote) ||
+			!remote_node.accepts(&local)
+		{
+			return
+		}
+
+		let already_open = local_node.open.get(&remote).cloned().unwrap_or_default();
+		let to_open = local_node
+			.notification_protocols
+			.iter()
+			.filter(|(protocol, _)| !already_open.contains(*protocol))
+			.filter_map(|(protocol, handshake)| {
+				let remote_handshake = remote_node.notification_protocols.get(protocol)?;
+				Some((protocol.clone(), handshake.clone(), remote_handshake.clone()))
+			})
+			.collect::<Vec<_>>();
+		let (local_role, remote_role) = (local_node.role.clone(), remote_node.role.clone());
+
+		for (protocol, local_handshake, remote_handshake) in to_open {
+			trace!(target: LOG_TARGET, "{local}: opening {protocol} with {remote}");
+			self.open_one(local, remote, &protocol, remote_role.clone(), remote_handshake);
+			self.open_one(remote, local, &protocol, local_role.clone(), local_handshake);
+		}
+	}
+
+	fn open_one(
+		&mut self,
+		local: PeerId,
+		remote: PeerId,
+		protocol: &ProtocolName,
+		role: ObservedRole,
+		received_handshake: Vec<u8>,
+	) {
+		let node = self.nodes.get_mut(&local).expect("Checked by the caller; qed");
+		node.open.entry(remote).or_default().insert(protocol.clone());
+
+		if *protocol != node.block_announce_protocol {
+			node.emit(Event::NotificationStreamOpened {
+				remote,
+				protocol: protocol.clone(),
+				negotiated_fallback: None,
+				role,
+				received_handshake,
+			});
+			return
+		}
+
+		let sink = BlockAnnounceSink {
+			hub: self.handle.clone(),
+			local,
+			target: remote,
+			protocol: protocol.clone(),
+		};
+		let validation = node.sync_events.opened(remote, &received_handshake, Box::new(sink));
+		let hub = self.handle.clone();
+		let protocol = protocol.clone();
+		let pending = async move {
+			let accepted = match validation {
+				Some(validation) => validation.await.unwrap_or(false),
+				None => false,
+			};
+			if !accepted {
+				debug!(target: LOG_TARGET, "{local}: syncing engine refused {remote}");
+				if let Some(hub) = hub.upgrade() {
+					hub.lock().disconnect(local, remote, Some(&protocol));
+				}
+			}
+		};
+		let _ = node.to_worker.unbounded_send(pending.boxed());
+	}
+
+	/// Close `protocol` between `local` and `remote`, or all protocols if `None`.
+	fn disconnect(&mut self, local: PeerId, remote: PeerId, protocol: Option<&ProtocolName>) {
+		for (a, b) in [(local, remote), (remote, local)] {
+			let Some(node) = self.nodes.get_mut(&a) else { continue };
+			let Some(open) = node.open.get_mut(&b) else { continue };
+
+			let closed = match protocol {
+				Some(protocol) => open.take(protocol).into_iter().collect(),
+				None => std::mem::take(open).into_iter().collect::<Vec<_>>(),
+			};
+			if open.is_empty() {
+				node.open.remove(&b);
+			}
+
+			for protocol in closed {
+				if protocol == node.block_announce_protocol {
+					node.sync_events.closed(b);
+				} else {
+					node.emit(Event::NotificationStreamClosed { remote: b, protocol });
+				}
+			}
+		}
+	}
+
+	/// Disconnect from all the peers `local` no longer accepts.
+	fn disconnect_unaccepted(&mut self, local: PeerId) {
+		let Some(node) = self.nodes.get(&local) else { return };
+		let unaccepted =
+			node.open.keys().filter(|peer| !node.accepts(peer)).cloned().collect::<Vec<_>>();
+
+		for remote in unaccepted {
+			self.disconnect(local, remote, None);
+		}
+	}
+
+	fn report(&mut self, local: PeerId, who: PeerId, change: ReputationChange) {
+		let Some(node) = self.nodes.get_mut(&local) else { return };
+		let reputation = node.reputations.entry(who).or_default();
+		*reputation = reputation.saturating_add(change.value);
+
+		if *reputation < BANNED_THRESHOLD {
+			debug!(target: LOG_TARGET, "{local}: banning {who} ({})", change.reason);
+			self.disconnect(local, who, None);
+		}
+	}
+
+	fn write_notification(
+		&mut self,
+		local: PeerId,
+		target: PeerId,
+		protocol: ProtocolName,
+		message: Vec<u8>,
+	) -> Result<(), NotificationSenderError> {
+		let is_open =
+			|node: &Node, peer| node.open.get(peer).map_or(false, |p| p.contains(&protocol));
+		let (Some(local_node), Some(target_node)) =
+			(self.nodes.get(&local), self.nodes.get(&target))
+		else {
+			return Err(NotificationSenderError::Closed)
+		};
+		if !is_open(local_node, &target) || !is_open(target_node, &local) {
+			return Err(NotificationSenderError::Closed)
+		}
+
+		let len = message.len() as u64;
+		if let Some(node) = self.nodes.get_mut(&local) {
+			node.bytes_outbound += len;
+		}
+		let target_node = self.nodes.get_mut(&target).expect("Checked above; qed");
+		target_node.bytes_inbound += len;
+		if protocol == target_node.block_announce_protocol {
+			target_node.sync_events.received(local, message);
+		} else {
+			target_node.emit(Event::NotificationsReceived {
+				remote: local,
+				messages: vec![(protocol, message.into())],
+			});
+		}
+
+		Ok(())
+	}
+
+	/// Deliver a request to `target` and return the channel its response arrives on.
+	fn send_request(
+		&mut self,
+		local: PeerId,
+		target: PeerId,
+		protocol: &ProtocolName,
+		payload: Vec<u8>,
+		connect: IfDisconnected,
+	) -> Result<oneshot::Receiver<OutgoingResponse>, RequestFailure> {
+		let connected = self.nodes.get(&local).map_or(false, |n| n.open.contains_key(&target));
+		if !connected {
+			if !connect.should_connect() {
+				return Err(RequestFailure::NotConnected)
+			}
+			self.want(local, target);
+		}
+
+		// Requests don't need a notification protocol to be open, but they do need the remote to
+		// be running and accepting us.
+		let accepted = self.nodes.get(&target).map_or(false, |n| n.running && n.accepts(&local));
+		if !accepted {
+			return Err(RequestFailure::NotConnected)
+		}
+
+		let len = payload.len() as u64;
+		let target_node = self.nodes.get_mut(&target).expect("Checked above; qed");
+		let Some(Some(inbound_queue)) = target_node.request_protocols.get(protocol) else {
+			return Err(RequestFailure::UnknownProtocol)
+		};
+
+		let (tx, rx) = oneshot::channel();
+		inbound_queue
+			.clone()
+			.try_send(IncomingRequest { peer: local, payload, pending_response: tx })
+			.map_err(|_| RequestFailure::Refused)?;
+		target_node.bytes_inbound += len;
+		if let Some(node) = self.nodes.get_mut(&local) {
+			node.bytes_outbound += len;
+		}
+
+		Ok(rx)
+	}
+}
+
+/// Handle to a node of the in-process network.
+pub struct InProcessNetworkService {
+	hub: InProcessHub,
+	local_peer_id: PeerId,
+	local_identity: Keypair,
+	listen_addresses: Vec<Multiaddr>,
+}
+
+impl InProcessNetworkService {
+	fn with_node<R>(&self, f: impl FnOnce(&mut Node) -> R) -> Option<R> {
+		self.hub.with(|hub| hub.nodes.get_mut(&self.local_peer_id).map(f))
+	}
+
+	fn peer_ids(&self, peers: HashSet<Multiaddr>) -> Result<Vec<PeerId>, String> {
+		peers
+			.into_iter()
+			.map(|addr| {
+				let (peer_id, _) = parse_addr(addr).map_err(|e| e.to_string())?;
+				if peer_id == self.local_peer_id {
+					return Err("Local peer ID cannot be added as a reserved peer.".to_string())
+				}
+				Ok(peer_id)
+			})
+			.collect()
+	}
+
+	fn add_reserved(&self, peers: impl IntoIterator<Item = PeerId>) {
+		let local = self.local_peer_id;
+		self.hub.with(|hub| {
+			for peer in peers {
+				if let Some(node) = hub.nodes.get_mut(&local) {
+					node.reserved.insert(peer);
+				}
+				hub.want(local, peer);
+			}
+		})
+	}
+
+	fn remove_reserved(&self, peers: impl IntoIterator<Item = PeerId>) {
+		let local = self.local_peer_id;
+		self.hub.with(|hub| {
+			if let Some(node) = hub.nodes.get_mut(&local) {
+				for peer in peers {
+					node.reserved.remove(&peer);
+					node.wanted.remove(&peer);
+				}
+			}
+			hub.disconnect_unaccepted(local);
+		})
+	}
+
+	fn set_reserved_only(&self, reserved_only: bool) {
+		let local = self.local_peer_id;
+		self.hub.with(|hub| {
+			let Some(node) = hub.nodes.get_mut(&local) else { return };
+			node.reserved_only = reserved_only;
+
+			hub.disconnect_unaccepted(local);
+			hub.connect_wanted(local);
+		})
+	}
+}
+
+impl NetworkSigner for InProcessNetworkService {
+	fn sign_with_local_identity(&self, msg: impl AsRef<[u8]>) -> Result<Signature, SigningError> {
+		Signature::sign_message(msg.as_ref(), &self.local_identity)
+	}
+}
+
+impl NetworkDHTProvider for InProcessNetworkService {
+	fn get_value(&self, key: &KademliaKey) {
+		self.hub.with(|hub| {
+			let event = match hub.dht.get(key) {
+				Some(value) => DhtEvent::ValueFound(vec![(key.clone(), value.clone())]),
+				None => DhtEvent::ValueNotFound(key.clone()),
+			};
+			if let Some(node) = hub.nodes.get_mut(&self.local_peer_id) {
+				node.emit(Event::Dht(event));
+			}
+		})
+	}
+
+	fn put_value(&self, key: KademliaKey, value: Vec<u8>) {
+		self.hub.with(|hub| {
+			hub.dht.insert(key.clone(), value);
+			if let Some(node) = hub.nodes.get_mut(&self.local_peer_id) {
+				node.emit(Event::Dht(DhtEvent::ValuePut(key)));
+			}
+		})
+	}
+}
+
+#[async_trait::async_trait]
+impl NetworkStatusProvider for InProcessNetworkService {
+	async fn status(&self) -> Result<NetworkStatus, ()> {
+		self.with_node(|node| NetworkStatus {
+			num_connected_peers: node.open.len(),
+			total_bytes_inbound: node.bytes_inbound,
+			total_bytes_outbound: node.bytes_outbound,
+		})
+		.ok_or(())
+	}
+}
+
+#[async_trait::async_trait]
+impl NetworkPeerAdmin for InProcessNetworkService {
+	async fn network_state(&self) -> Result<NetworkState, ()> {
+		self.with_node(|node| {
+			// There are no connections, peers are reported as dialed through their peer ID.
+			let connected_peers = node
+				.open
+				.keys()
+				.map(|peer| {
+					let address = Multiaddr::empty().with(Protocol::P2p((*peer).into()));
+					let peer_info = Peer {
+						endpoint: PeerEndpoint::Dialing(address, Endpoint::Dialer),
+						version_string: None,
+						latest_ping_time: None,
+						known_addresses: HashSet::new(),
+					};
+					(peer.to_base58(), peer_info)
+				})
+				.collect();
+			let not_connected_peers = node
+				.wanted
+				.iter()
+				.filter(|peer| !node.open.contains_key(*peer))
+				.map(|peer| {
+					let peer_info = NotConnectedPeer {
+						known_addresses: HashSet::new(),
+						version_string: None,
+						latest_ping_time: None,
+					};
+					(peer.to_base58(), peer_info)
+				})
+				.collect();
+
+			NetworkState {
+				peer_id: self.local_peer_id.to_base58(),
+				listened_addresses: self.listen_addresses.iter().cloned().collect(),
+				external_addresses: HashSet::new(),
+				connected_peers,
+				not_connected_peers,
+				peerset: Default::default(),
+			}
+		})
+		.ok_or(())
+	}
+
+	async fn reserved_peers(&self) -> Result<Vec<PeerId>, ()> {
+		self.with_node(|node| node.reserved.iter().cloned().collect()).ok_or(())
+	}
+
+	async fn peer_reputations(&self) -> Result<Vec<PeerReputation>, ()> {
+		let now = SystemTime::now();
+		self.with_node(|node| {
+			node.reputations
+				.iter()
+				.map(|(peer_id, reputation)| PeerReputation {
+					peer_id: *peer_id,
+					reputation: *reputation,
+					recent_changes: Vec::new(),
+					banned_for: node
+						.bans
+						.get(&BanTarget::Peer(*peer_id))
+						.and_then(|expires_at| expires_at.duration_since(now).ok()),
+				})
+				.collect()
+		})
+		.ok_or(())
+	}
+
+	fn ban(&self, target: BanTarget, duration: Duration) {
+		let local = self.local_peer_id;
+		let now = SystemTime::now();
+		let expires_at = now.checked_add(duration.min(MAX_BAN_DURATION)).unwrap_or(now);
+
+		self.hub.with(|hub| {
+			let Some(node) = hub.nodes.get_mut(&local) else { return };
+			debug!(target: LOG_TARGET, "{local}: banning {target} for {duration:?}");
+			node.bans.insert(target, expires_at);
+			hub.disconnect_unaccepted(local);
+		})
+	}
+
+	async fn unban(&self, target: BanTarget) -> Result<bool, ()> {
+		let local = self.local_peer_id;
+		self.hub.with(|hub| {
+			let node = hub.nodes.get_mut(&local).ok_or(())?;
+			let unbanned = node
+				.bans
+				.remove(&target)
+				.map_or(false, |expires_at| expires_at > SystemTime::now());
+			hub.connect_wanted(local);
+			Ok(unbanned)
+		})
+	}
+
+	async fn bans(&self) -> Result<Vec<Ban>, ()> {
+		let now = SystemTime::now();
+		self.with_node(|node| {
+			node.bans
+				.iter()
+				.filter(|(_, expires_at)| **expires_at > now)
+				.map(|(target, expires_at)| Ban { target: *target, expires_at: *expires_at })
+				.collect()
+		})
+		.ok_or(())
+	}
+}
+
+impl NetworkPeers for InProcessNetworkService {
+	fn set_authorized_peers(&self, peers: HashSet<PeerId>) {
+		let removed = self
+			.with_node(|node| node.reserved.difference(&peers).cloned().collect::<Vec<_>>())
+			.unwrap_or_default();
+		self.remove_reserved(removed);
+		self.add_reserved(peers);
+	}
+
+	fn set_authorized_only(&self, reserved_only: bool) {
+		self.set_reserved_only(reserved_only);
+	}
+
+	fn add_known_address(&self, _peer_id: PeerId, _addr: Multiaddr) {
+		// Every node of the hub is reachable, there is no address book to maintain.
+	}
+
+	fn report_peer(&self, who: PeerId, cost_benefit: ReputationChange) {
+		self.hub.with(|hub| hub.report(self.local_peer_id, who, cost_benefit));
+	}
+
+	fn disconnect_peer(&self, who: PeerId, protocol: ProtocolName) {
+		self.hub.with(|hub| hub.disconnect(self.local_peer_id, who, Some(&protocol)));
+	}
+
+	fn accept_unreserved_peers(&self) {
+		self.set_reserved_only(false);
+	}
+
+	fn deny_unreserved_peers(&self) {
+		self.set_reserved_only(true);
+	}
+
+	fn add_reserved_peer(&self, peer: MultiaddrWithPeerId) -> Result<(), String> {
+		if peer.peer_id == self.local_peer_id {
+			return Err("Local peer ID cannot be added as a reserved peer.".to_string())
+		}
+
+		self.add_reserved([peer.peer_id]);
+		Ok(())
+	}
+
+	fn remove_reserved_peer(&self, peer_id: PeerId) {
+		self.remove_reserved([peer_id]);
+	}
+
+	fn set_reserved_peers(
+		&self,
+		_protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		self.set_authorized_peers(self.peer_ids(peers)?.into_iter().collect());
+		Ok(())
+	}
+
+	fn add_peers_to_reserved_set(
+		&self,
+		_protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		self.add_reserved(self.peer_ids(peers)?);
+		Ok(())
+	}
+
+	fn remove_peers_from_reserved_set(&self, _protocol: ProtocolName, peers: Vec<PeerId>) {
+		self.remove_reserved(peers);
+	}
+
+	fn add_to_peers_set(
+		&self,
+		_protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		let peers = self.peer_ids(peers)?;
+		self.hub.with(|hub| {
+			for peer in peers {
+				hub.want(self.local_peer_id, peer);
+			}
+		});
+		Ok(())
+	}
+
+	fn remove_from_peers_set(&self, protocol: ProtocolName, peers: Vec<PeerId>) {
+		self.hub.with(|hub| {
+			for peer in peers {
+				hub.disconnect(self.local_peer_id, peer, Some(&protocol));
+			}
+		})
+	}
+
+	fn sync_num_connected(&self) -> usize {
+		self.with_node(|node| node.open.len()).unwrap_or_default()
+	}
+}
+
+impl NetworkEventStream for InProcessNetworkService {
+	fn event_stream(
+		&self,
+		name: &'static str,
+	) -> std::pin::Pin<Box<dyn Stream<Item = Event> + Send>> {
+		let (tx, rx) = tracing_unbounded(name, 100_000);
+		self.with_node(|node| node.event_streams.push(tx));
+		Box::pin(rx)
+	}
+}
+
+impl NetworkStateInfo for InProcessNetworkService {
+	fn external_addresses(&self) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn listen_addresses(&self) -> Vec<Multiaddr> {
+		self.listen_addresses.clone()
+	}
+
+	fn local_peer_id(&self) -> PeerId {
+		self.local_peer_id
+	}
+}
+
+impl NetworkNotification for InProcessNetworkService {
+	fn write_notification(&self, target: PeerId, protocol: ProtocolName, message: Vec<u8>) {
+		let _ = self
+			.hub
+			.with(|hub| hub.write_notification(self.local_peer_id, target, protocol, message));
+	}
+
+	fn notification_sender(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+	) -> Result<Box<dyn NotificationSenderT>, NotificationSenderError> {
+		let known = self
+			.with_node(|node| node.notification_protocols.contains_key(&protocol))
+			.unwrap_or_default();
+		if !known {
+			return Err(NotificationSenderError::BadProtocol)
+		}
+
+		Ok(Box::new(NotificationSender {
+			hub: self.hub.clone(),
+			local: self.local_peer_id,
+			target,
+			protocol,
+		}))
+	}
+
+	fn set_notification_handshake(&self, protocol: ProtocolName, handshake: Vec<u8>) {
+		self.with_node(|node| {
+			if let Some(current) = node.notification_protocols.get_mut(&protocol) {
+				*current = handshake;
+			}
+		});
+	}
+}
+
+#[async_trait::async_trait]
+impl NetworkRequest for InProcessNetworkService {
+	async fn request(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+		request: Vec<u8>,
+		connect: IfDisconnected,
+	) -> Result<Vec<u8>, RequestFailure> {
+		let (tx, rx) = oneshot::channel();
+		self.start_request(target, protocol, request, tx, connect);
+
+		match rx.await {
+			Ok(v) => v,
+			// The channel can only be closed if the worker no longer exists.
+			Err(_) => Err(RequestFailure::Network(sc_network::OutboundFailure::ConnectionClosed)),
+		}
+	}
+
+	fn start_request(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+		request: Vec<u8>,
+		tx: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+		connect: IfDisconnected,
+	) {
+		let local = self.local_peer_id;
+		let shared_hub = self.hub.clone();
+		self.hub.with(|hub| {
+			let response = match hub.send_request(local, target, &protocol, request, connect) {
+				Ok(response) => response,
+				Err(e) => {
+					let _ = tx.send(Err(e));
+					return
+				},
+			};
+
+			let pending = async move {
+				let result = match response.await {
+					Ok(OutgoingResponse { result, reputation_changes, sent_feedback }) => {
+						shared_hub.with(|hub| {
+							for change in reputation_changes {
+								hub.report(target, local, change);
+							}
+							if let (Ok(payload), Some(node)) = (&result, hub.nodes.get_mut(&local))
+							{
+								node.bytes_inbound += payload.len() as u64;
+							}
+						});
+						if let Some(sent_feedback) = sent_feedback {
+							let _ = sent_feedback.send(());
+						}
+						result.map_err(|()| RequestFailure::Refused)
+					},
+					Err(_) => Err(RequestFailure::Refused),
+				};
+				let _ = tx.send(result);
+			};
+
+			if let Some(node) = hub.nodes.get(&local) {
+				let _ = node.to_worker.unbounded_send(pending.boxed());
+			}
+		})
+	}
+}
+
+struct NotificationSender {
+	hub: InProcessHub,
+	local: PeerId,
+	target: PeerId,
+	protocol: ProtocolName,
+}
+
+#[async_trait::async_trait]
+impl NotificationSenderT for NotificationSender {
+	async fn ready(
+		&self,
+	) -> Result<Box<dyn NotificationSenderReady + '_>, NotificationSenderError> {
+		let is_open = self.hub.with(|hub| {
+			hub.nodes
+				.get(&self.local)
+				.and_then(|node| node.open.get(&self.target))
+				.map_or(false, |open| open.contains(&self.protocol))
+		});
+		if !is_open {
+			return Err(NotificationSenderError::Closed)
+		}
+
+		Ok(Box::new(NotificationSenderReadyImpl(self)))
+	}
+}
+
+struct NotificationSenderReadyImpl<'a>(&'a NotificationSender);
+
+impl<'a> NotificationSenderReady for NotificationSenderReadyImpl<'a> {
+	fn send(&mut self, notification: Vec<u8>) -> Result<(), NotificationSenderError> {
+		let sender = self.0;
+		sender.hub.with(|hub| {
+			hub.write_notification(
+				sender.local,
+				sender.target,
+				sender.protocol.clone(),
+				notification,
+			)
+		})
+	}
+}
+
+/// Block announcement substream handed to the syncing engine.
+struct BlockAnnounceSink {
+	hub: Weak<Mutex<Hub>>,
+	local: PeerId,
+	target: PeerId,
+	protocol: ProtocolName,
+}
+
+impl fmt::Debug for BlockAnnounceSink {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BlockAnnounceSink")
+			.field("local", &self.local)
+			.field("target", &self.target)
+			.finish()
+	}
+}
+
+impl MessageSink for BlockAnnounceSink {
+	fn send_sync_notification(&self, notification: Vec<u8>) {
+		let Some(hub) = self.hub.upgrade() else { return };
+		let _ = hub.lock().write_notification(
+			self.local,
+			self.target,
+			self.protocol.clone(),
+			notification,
+		);
+	}
+}
+
+/// Node of the in-process network.
+///
+/// Must be [run](NetworkBackend::run) for the node to connect to other nodes and receive
+/// responses to its requests.
+pub struct InProcessNetwork<B, H> {
+	service: Arc<InProcessNetworkService>,
+	/// Responses to requests sent through the service.
+	from_service: TracingUnboundedReceiver<BoxFuture<'static, ()>>,
+	pending_responses: FuturesUnordered<BoxFuture<'static, ()>>,
+	_marker: PhantomData<(B, H)>,
+}
+
+impl<B, H> InProcessNetwork<B, H>
+where
+	B: BlockT + 'static,
+	H: ExHashT,
+{
+	/// Create a node registered in `hub`.
+	pub fn new_in(hub: &InProcessHub, params: Params<B>) -> Result<Self, Error> {
+		let config = params.network_config;
+		let local_identity = config.node_key.clone().into_keypair()?;
+		let local_peer_id = local_identity.public().to_peer_id();
+
+		let block_announce_protocol = params.block_announce_config.notifications_protocol.clone();
+		let notification_protocols = std::iter::once(&params.block_announce_config)
+			.chain(config.extra_sets.iter())
+			.map(|set| {
+				let handshake = set.handshake.as_ref().map(|h| h.to_vec()).unwrap_or_default();
+				(set.notifications_protocol.clone(), handshake)
+			})
+			.collect();
+		let request_protocols = params
+			.request_response_protocol_configs
+			.into_iter()
+			.chain(config.request_response_protocols)
+			.map(|config| (config.name, config.inbound_queue))
+			.collect();
+
+		let reserved = std::iter::once(&config.default_peers_set)
+			.chain(config.extra_sets.iter().map(|set| &set.set_config))
+			.flat_map(|set| set.reserved_nodes.iter())
+			.map(|node| node.peer_id)
+			.collect::<HashSet<_>>();
+		let reserved_only = config.default_peers_set.non_reserved_mode == NonReservedPeerMode::Deny;
+		let wanted = config
+			.boot_nodes
+			.iter()
+			.map(|node| node.peer_id)
+			.chain(reserved.iter().cloned())
+			.collect();
+
+		let role = match params.role {
+			Role::Full => ObservedRole::Full,
+			Role::Authority => ObservedRole::Authority,
+		};
+
+		let (to_worker, from_service) = tracing_unbounded("mpsc_in_process_network", 100_000);
+		let node = Node {
+			role,
+			running: false,
+			notification_protocols,
+			block_announce_protocol,
+			sync_events: Box::new(params.tx),
+			request_protocols,
+			event_streams: Vec::new(),
+			open: HashMap::new(),
+			wanted,
+			reserved,
+			reserved_only,
+			reputations: HashMap::new(),
+			bans: HashMap::new(),
+			to_worker,
+			bytes_inbound: 0,
+			bytes_outbound: 0,
+		};
+		hub.with(|hub| hub.nodes.insert(local_peer_id, node));
+
+		let listen_addresses = config
+			.listen_addresses
+			.into_iter()
+			.map(|addr| addr.with(Protocol::P2p(local_peer_id.into())))
+			.collect();
+		let service = Arc::new(InProcessNetworkService {
+			hub: hub.clone(),
+			local_peer_id,
+			local_identity,
+			listen_addresses,
+		});
+
+		Ok(Self {
+			service,
+			from_service,
+			pending_responses: FuturesUnordered::new(),
+			_marker: PhantomData,
+		})
+	}
+}
+
+#[async_trait::async_trait]
+impl<B, H> NetworkBackend<B, H> for InProcessNetwork<B, H>
+where
+	B: BlockT + 'static,
+	H: ExHashT,
+{
+	type NetworkService = Arc<InProcessNetworkService>;
+
+	/// Create a node that is alone in a hub of its own, use [`InProcessNetwork::new_in`] to build
+	/// nodes that can reach each other.
+	fn new(params: Params<B>) -> Result<Self, Error> {
+		Self::new_in(&InProcessHub::default(), params)
+	}
+
+	fn network_service(&self) -> Self::NetworkService {
+		self.service.clone()
+	}
+
+	fn num_connected_peers(&self) -> usize {
+		self.service.sync_num_connected()
+	}
+
+	async fn run(mut self) {
+		let local = self.service.local_peer_id;
+		self.service.hub.with(|hub| {
+			let Some(node) = hub.nodes.get_mut(&local) else { return };
+			node.running = true;
+
+			let mut to_connect = node.wanted.iter().cloned().collect::<Vec<_>>();
+			to_connect.extend(
+				hub.nodes
+					.iter()
+					.filter(|(_, node)| node.wanted.contains(&local))
+					.map(|(peer_id, _)| *peer_id),
+			);
+			for remote in to_connect {
+				hub.connect(local, remote);
+			}
+		});
+
+		loop {
+			futures::select! {
+				pending = self.from_service.next() => match pending {
+					Some(pending) => self.pending_responses.push(pending),
+					None => return,
+				},
+				() = self.pending_responses.select_next_some() => {},
+			}
+		}
+	}
+}
+
+impl<B, H> Drop for InProcessNetwork<B, H> {
+	fn drop(&mut self) {
+		let local = self.service.local_peer_id;
+		self.service.hub.with(|hub| {
+			let peers = hub
+				.nodes
+				.get(&local)
+				.map(|node| node.open.keys().cloned().collect::<Vec<_>>())
+				.unwrap_or_default();
+			for remote in peers {
+				hub.disconnect(local, remote, None);
+			}
+			hub.nodes.remove(&local);
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::PassThroughVerifier;
+	use sc_block_builder::BlockBuilderProvider;
+	use sc_client_api::BlockchainEvents;
+	use sc_consensus::{BasicQueue, ImportQueue};
+	use sc_network::{
+		config::{NetworkConfiguration, NonDefaultSetConfig, ProtocolId},
+		request_responses::ProtocolConfig as RequestResponseConfig,
+	};
+	use sc_network_common::role::Roles;
+	use sc_network_sync::{
+		block_request_handler::BlockRequestHandler, engine::SyncingEngine,
+		service::network::NetworkServiceProvider, state_request_handler::StateRequestHandler,
+	};
+	use sp_blockchain::HeaderBackend;
+	use sp_consensus::{block_validation::DefaultBlockAnnounceValidator, BlockOrigin};
+	use substrate_test_runtime_client::{
+		runtime::{Block, Hash},
+		ClientBlockImportExt, TestClient,
+	};
+
+	type TestNetwork = InProcessNetwork<Block, Hash>;
+
+	const NOTIFICATIONS_PROTOCOL: &str = "/in-process/notifications/1";
+	const REQUEST_PROTOCOL: &str = "/in-process/request/1";
+
+	fn build_node(
+		hub: &InProcessHub,
+		request_response_protocol_configs: Vec<RequestResponseConfig>,
+	) -> (TestNetwork, Arc<InProcessNetworkService>) {
+		let mut network_config =
+			NetworkConfiguration::new("test-node", "test-client", Default::default(), None);
+		network_config.extra_sets = vec![NonDefaultSetConfig {
+			notifications_protocol: NOTIFICATIONS_PROTOCOL.into(),
+			fallback_names: Vec::new(),
+			max_notification_size: 1024 * 1024,
+			handshake: None,
+			set_config: Default::default(),
+		}];
+		let (tx, _rx) = tracing_unbounded("mpsc_test_sync_events", 100_000);
+
+		let network = TestNetwork::new_in(
+			hub,
+			Params {
+				role: Role::Full,
+				executor: Box::new(|f| {
+					tokio::spawn(f);
+				}),
+				network_config,
+				genesis_hash: Default::default(),
+				protocol_id: ProtocolId::from("test-protocol-name"),
+				fork_id: None,
+				metrics_registry: None,
+				block_announce_config: NonDefaultSetConfig {
+					notifications_protocol: "/block-announces/1".into(),
+					fallback_names: Vec::new(),
+					max_notification_size: 1024 * 1024,
+					handshake: None,
+					set_config: Default::default(),
+				},
+				tx,
+				request_response_protocol_configs,
+			},
+		)
+		.unwrap();
+		let service = network.network_service();
+
+		(network, service)
+	}
+
+	/// Build a running node whose syncing engine keeps `client` up to date.
+	fn build_syncing_node(
+		hub: &InProcessHub,
+		client: Arc<TestClient>,
+	) -> Arc<InProcessNetworkService> {
+		let network_config =
+			NetworkConfiguration::new("test-node", "test-client", Default::default(), None);
+		let protocol_id = ProtocolId::from("test-protocol-name");
+		let fork_id = None;
+
+		let block_request_protocol_config = {
+			let (handler, protocol_config) =
+				BlockRequestHandler::new(&protocol_id, None, client.clone(), 50);
+			tokio::spawn(handler.run());
+			protocol_config
+		};
+		let state_request_protocol_config = {
+			let (handler, protocol_config) =
+				StateRequestHandler::new(&protocol_id, None, client.clone(), 50);
+			tokio::spawn(handler.run());
+			protocol_config
+		};
+
+		let import_queue = BasicQueue::new(
+			PassThroughVerifier::new(false),
+			Box::new(client.clone()),
+			None,
+			&sp_core::testing::TaskExecutor::new(),
+			None,
+		);
+		let (chain_sync_network_provider, chain_sync_network_handle) =
+			NetworkServiceProvider::new();
+		let (tx, rx) = tracing_unbounded("mpsc_syncing_engine_protocol", 100_000);
+		let (engine, sync_service, block_announce_config) = SyncingEngine::new(
+			Roles::FULL,
+			client.clone(),
+			None,
+			&network_config,
+			protocol_id.clone(),
+			&fork_id,
+			Box::new(DefaultBlockAnnounceValidator),
+			None,
+			chain_sync_network_handle,
+			import_queue.service(),
+			block_request_protocol_config.name.clone(),
+			state_request_protocol_config.name.clone(),
+			None,
+			None,
+			rx,
+		)
+		.unwrap();
+
+		let network = TestNetwork::new_in(
+			hub,
+			Params {
+				role: Role::Full,
+				executor: Box::new(|f| {
+					tokio::spawn(f);
+				}),
+				network_config,
+				genesis_hash: client.info().genesis_hash,
+				protocol_id,
+				fork_id,
+				metrics_registry: None,
+				block_announce_config,
+				tx,
+				request_response_protocol_configs: vec![
+					block_request_protocol_config,
+					state_request_protocol_config,
+				],
+			},
+		)
+		.unwrap();
+		let service = network.network_service();
+
+		tokio::spawn(chain_sync_network_provider.run(service.clone()));
+		tokio::spawn(import_queue.run(Box::new(sync_service)));
+		tokio::spawn(engine.run());
+		tokio::spawn(network.run());
+
+		service
+	}
+
+	/// Build two running nodes, connected to each other.
+	async fn build_connected_pair(
+		request_response_protocol_configs: Vec<RequestResponseConfig>,
+	) -> (
+		(Arc<InProcessNetworkService>, impl Stream<Item = Event> + Unpin),
+		(Arc<InProcessNetworkService>, impl Stream<Item = Event> + Unpin),
+	) {
+		let hub = InProcessHub::default();
+		let (network1, service1) = build_node(&hub, Vec::new());
+		let (network2, service2) = build_node(&hub, request_response_protocol_configs);
+		let mut events1 = service1.event_stream("test");
+		let mut events2 = service2.event_stream("test");
+
+		service1
+			.add_reserved_peer(MultiaddrWithPeerId {
+				multiaddr: Multiaddr::empty(),
+				peer_id: service2.local_peer_id(),
+			})
+			.unwrap();
+		tokio::spawn(network1.run());
+		tokio::spawn(network2.run());
+
+		// Both sides open the notifications protocol, the block announcement protocol goes to the
+		// syncing engines.
+		for events in [&mut events1, &mut events2] {
+			assert!(matches!(
+				events.next().await,
+				Some(Event::NotificationStreamOpened { protocol, .. })
+					if protocol == ProtocolName::from(NOTIFICATIONS_PROTOCOL)
+			));
+		}
+
+		((service1, events1), (service2, events2))
+	}
+
+	#[tokio::test]
+	async fn notifications_are_delivered() {
+		let ((service1, _), (service2, mut events2)) = build_connected_pair(Vec::new()).await;
+		assert_eq!(service1.sync_num_connected(), 1);
+
+		service1.write_notification(
+			service2.local_peer_id(),
+			NOTIFICATIONS_PROTOCOL.into(),
+			b"hello".to_vec(),
+		);
+		let sender = service1
+			.notification_sender(service2.local_peer_id(), NOTIFICATIONS_PROTOCOL.into())
+			.unwrap();
+		sender.ready().await.unwrap().send(b"world".to_vec()).unwrap();
+
+		for expected in [&b"hello"[..], &b"world"[..]] {
+			match events2.next().await {
+				Some(Event::NotificationsReceived { remote, messages }) => {
+					assert_eq!(remote, service1.local_peer_id());
+					assert_eq!(messages.len(), 1);
+					assert_eq!(messages[0].0, ProtocolName::from(NOTIFICATIONS_PROTOCOL));
+					assert_eq!(&messages[0].1[..], expected);
+				},
+				event => panic!("Unexpected event: {event:?}"),
+			}
+		}
+
+		assert!(matches!(
+			service1.notification_sender(service2.local_peer_id(), "/unknown/1".into()),
+			Err(NotificationSenderError::BadProtocol)
+		));
+	}
+
+	#[tokio::test]
+	async fn requests_are_answered() {
+		let (inbound_queue, mut requests) = mpsc::channel(10);
+		let config = RequestResponseConfig {
+			name: REQUEST_PROTOCOL.into(),
+			fallback_names: Vec::new(),
+			max_request_size: 1024,
+			max_response_size: 1024,
+			request_timeout: Duration::from_secs(10),
+			inbound_queue: Some(inbound_queue),
+		};
+		let ((service1, _), (service2, _)) = build_connected_pair(vec![config]).await;
+
+		tokio::spawn(async move {
+			while let Some(IncomingRequest { payload, pending_response, .. }) =
+				requests.next().await
+			{
+				let _ = pending_response.send(OutgoingResponse {
+					result: Ok(payload.into_iter().rev().collect()),
+					reputation_changes: Vec::new(),
+					sent_feedback: None,
+				});
+			}
+		});
+
+		let response = service1
+			.request(
+				service2.local_peer_id(),
+				REQUEST_PROTOCOL.into(),
+				vec![1, 2, 3],
+				IfDisconnected::ImmediateError,
+			)
+			.await
+			.unwrap();
+		assert_eq!(response, vec![3, 2, 1]);
+
+		assert!(matches!(
+			service1
+				.request(
+					service2.local_peer_id(),
+					"/unknown/1".into(),
+					vec![1, 2, 3],
+					IfDisconnected::ImmediateError,
+				)
+				.await,
+			Err(RequestFailure::UnknownProtocol)
+		));
+		assert!(matches!(
+			service1
+				.request(
+					PeerId::random(),
+					REQUEST_PROTOCOL.into(),
+					vec![1, 2, 3],
+					IfDisconnected::ImmediateError,
+				)
+				.await,
+			Err(RequestFailure::NotConnected)
+		));
+	}
+
+	#[tokio::test]
+	async fn banned_peers_are_disconnected() {
+		let ((service1, mut events1), (service2, mut events2)) =
+			build_connected_pair(Vec::new()).await;
+
+		service1.report_peer(service2.local_peer_id(), ReputationChange::new_fatal("test"));
+
+		for events in [&mut events1, &mut events2] {
+			assert!(matches!(
+				events.next().await,
+				Some(Event::NotificationStreamClosed { protocol, .. })
+					if protocol == ProtocolName::from(NOTIFICATIONS_PROTOCOL)
+			));
+		}
+		assert_eq!(service1.sync_num_connected(), 0);
+		assert_eq!(service2.sync_num_connected(), 0);
+
+		// Banned peers can't reconnect.
+		service2
+			.add_reserved_peer(MultiaddrWithPeerId {
+				multiaddr: Multiaddr::empty(),
+				peer_id: service1.local_peer_id(),
+			})
+			.unwrap();
+		assert_eq!(service2.sync_num_connected(), 0);
+	}
+
+	#[tokio::test]
+	async fn dht_records_are_shared() {
+		let ((service1, mut events1), (service2, mut events2)) =
+			build_connected_pair(Vec::new()).await;
+		let key = KademliaKey::new(&b"key".to_vec());
+
+		service2.get_value(&key);
+		assert!(matches!(
+			events2.next().await,
+			Some(Event::Dht(DhtEvent::ValueNotFound(k))) if k == key
+		));
+
+		service1.put_value(key.clone(), b"value".to_vec());
+		assert!(
+			matches!(events1.next().await, Some(Event::Dht(DhtEvent::ValuePut(k))) if k == key)
+		);
+
+		service2.get_value(&key);
+		match events2.next().await {
+			Some(Event::Dht(DhtEvent::ValueFound(records))) =>
+				assert_eq!(records, vec![(key, b"value".to_vec())]),
+			event => panic!("Unexpected event: {event:?}"),
+		}
+	}
+
+	#[tokio::test]
+	async fn hubs_are_isolated() {
+		let (network1, service1) = build_node(&InProcessHub::default(), Vec::new());
+		let (network2, service2) = build_node(&InProcessHub::default(), Vec::new());
+		let mut events2 = service2.event_stream("test");
+		let key = KademliaKey::new(&b"key".to_vec());
+
+		service1
+			.add_reserved_peer(MultiaddrWithPeerId {
+				multiaddr: Multiaddr::empty(),
+				peer_id: service2.local_peer_id(),
+			})
+			.unwrap();
+		tokio::spawn(network1.run());
+		tokio::spawn(network2.run());
+		tokio::task::yield_now().await;
+
+		assert_eq!(service1.sync_num_connected(), 0);
+		assert_eq!(service2.sync_num_connected(), 0);
+
+		service1.put_value(key.clone(), b"value".to_vec());
+		service2.get_value(&key);
+		assert!(matches!(
+			events2.next().await,
+			Some(Event::Dht(DhtEvent::ValueNotFound(k))) if k == key
+		));
+	}
+
+	#[tokio::test]
+	async fn bans_are_enforced_until_lifted() {
+		let ((service1, mut events1), (service2, mut events2)) =
+			build_connected_pair(Vec::new()).await;
+		let target = BanTarget::Peer(service2.local_peer_id());
+
+		service1.ban(target, Duration::from_secs(3600));
+		for events in [&mut events1, &mut events2] {
+			assert!(matches!(
+				events.next().await,
+				Some(Event::NotificationStreamClosed { protocol, .. })
+					if protocol == ProtocolName::from(NOTIFICATIONS_PROTOCOL)
+			));
+		}
+		let bans = service1.bans().await.unwrap();
+		assert_eq!(bans.iter().map(|ban| ban.target).collect::<Vec<_>>(), vec![target]);
+		let network_state = service1.network_state().await.unwrap();
+		assert!(network_state.connected_peers.is_empty());
+		assert!(network_state
+			.not_connected_peers
+			.contains_key(&service2.local_peer_id().to_base58()));
+
+		// Lifting the ban reconnects to the reserved peer.
+		assert_eq!(service1.unban(target).await, Ok(true));
+		assert_eq!(service1.unban(target).await, Ok(false));
+		for events in [&mut events1, &mut events2] {
+			assert!(matches!(
+				events.next().await,
+				Some(Event::NotificationStreamOpened { protocol, .. })
+					if protocol == ProtocolName::from(NOTIFICATIONS_PROTOCOL)
+			));
+		}
+		assert!(service1.bans().await.unwrap().is_empty());
+		assert_eq!(service1.reserved_peers().await, Ok(vec![service2.local_peer_id()]));
+	}
+	#[tokio::test]
+	async fn blocks_are_synced() {
+		let hub = InProcessHub::default();
+		let mut client1 = Arc::new(substrate_test_runtime_client::new());
+		for _ in 0..10 {
+			let block = client1.new_block(Default::default()).unwrap().build().unwrap().block;
+			client1.import(BlockOrigin::Own, block).await.unwrap();
+		}
+		let client2 = Arc::new(substrate_test_runtime_client::new());
+		let mut imported = client2.import_notification_stream();
+
+		let service1 = build_syncing_node(&hub, client1.clone());
+		let service2 = build_syncing_node(&hub, client2.clone());
+		service2
+			.add_reserved_peer(MultiaddrWithPeerId {
+				multiaddr: Multiaddr::empty(),
+				peer_id: service1.local_peer_id(),
+			})
+			.unwrap();
+
+		// The engine of the second node learns the best block of the first one from its
+		// handshake and downloads the chain through the block request protocol.
+		while client2.info().best_number < 10 {
+			imported.next().await.unwrap();
+		}
+		assert_eq!(client2.info().best_hash, client1.info().best_hash);
+	}
+}
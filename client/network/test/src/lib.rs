@@ -19,6 +19,7 @@
 
 #[cfg(test)]
 mod block_import;
+pub mod in_process;
 #[cfg(test)]
 mod service;
 #[cfg(test)]
@@ -43,6 +44,7 @@ use sc_client_api::{
 	BlockBackend, BlockImportNotification, BlockchainEvents, FinalityNotification,
 	FinalityNotifications, ImportNotifications,
 };
+use sc_client_db::SnapshotStore;
 use sc_consensus::{
 	BasicQueue, BlockCheckParams, BlockImport, BlockImportParams, BoxJustificationImport,
 	ForkChoiceStrategy, ImportQueue, ImportResult, JustificationImport, JustificationSyncLink,
@@ -68,6 +70,7 @@ use sc_network_light::light_client_requests::handler::LightClientRequestHandler;
 use sc_network_sync::{
 	block_request_handler::BlockRequestHandler,
 	service::{chain_sync::SyncingService, network::NetworkServiceProvider},
+	snapshot_request_handler::SnapshotRequestHandler,
 	state_request_handler::StateRequestHandler,
 	warp_request_handler,
 };
@@ -242,6 +245,8 @@ pub struct Peer<D, BlockImport> {
 	imported_blocks_stream: Pin<Box<dyn Stream<Item = BlockImportNotification<Block>> + Send>>,
 	finality_notification_stream: Pin<Box<dyn Stream<Item = FinalityNotification<Block>> + Send>>,
 	listen_addr: Multiaddr,
+	/// Snapshots served by the peer, along with the directory they are stored in.
+	snapshots: Option<(Arc<SnapshotStore>, tempfile::TempDir)>,
 }
 
 impl<D, B> Peer<D, B>
@@ -254,6 +259,16 @@ where
 		self.network.service().local_peer_id()
 	}
 
+	/// Export the snapshot of the state of the finalized block `hash`, to be served to other
+	/// peers.
+	///
+	/// Panics if the peer doesn't serve snapshots.
+	pub fn export_snapshot(&self, hash: <Block as BlockT>::Hash) {
+		let (snapshots, _) = self.snapshots.as_ref().expect("The peer serves snapshots");
+		let backend = self.backend.as_ref().expect("Full peers have a backend");
+		snapshots.export(&**backend, hash).unwrap();
+	}
+
 	/// Returns true if we're major syncing.
 	pub fn is_major_syncing(&self) -> bool {
 		self.sync_service.is_major_syncing()
@@ -720,8 +735,15 @@ pub struct FullPeerConfig {
 	pub storage_chain: bool,
 	/// Optional target block header to sync to
 	pub target_block: Option<<Block as BlockT>::Header>,
+	/// Serve state snapshots and download state as snapshots, taken every
+	/// [`TEST_SNAPSHOT_PERIOD`] blocks.
+	pub snapshots: bool,
 }
 
+/// Number of blocks between two snapshots, for the peers configured with
+/// [`FullPeerConfig::snapshots`].
+pub const TEST_SNAPSHOT_PERIOD: u32 = 20;
+
 #[async_trait::async_trait]
 pub trait TestNetFactory: Default + Sized + Send
 where
@@ -809,6 +831,7 @@ where
 		let mut network_config =
 			NetworkConfiguration::new("test-node", "test-client", Default::default(), None);
 		network_config.sync_mode = config.sync_mode;
+		network_config.snapshot_period = TEST_SNAPSHOT_PERIOD;
 		network_config.transport = TransportConfig::MemoryOnly;
 		network_config.listen_addresses = vec![listen_addr.clone()];
 		network_config.allow_non_globals_in_dht = true;
@@ -857,6 +880,22 @@ where
 			protocol_config
 		};
 
+		let snapshots = config.snapshots.then(|| {
+			let directory = tempfile::tempdir().unwrap();
+			(Arc::new(SnapshotStore::new(directory.path().to_path_buf(), 1)), directory)
+		});
+		let snapshot_request_protocol_config = snapshots.as_ref().map(|(snapshots, _)| {
+			let (handler, protocol_config) = SnapshotRequestHandler::<Block>::new(
+				&protocol_id,
+				None,
+				client.clone(),
+				snapshots.clone(),
+				50,
+			);
+			self.spawn_task(handler.run().boxed());
+			protocol_config
+		});
+
 		let light_client_request_protocol_config = {
 			let (handler, protocol_config) =
 				LightClientRequestHandler::new(&protocol_id, None, client.clone());
@@ -912,6 +951,7 @@ where
 				block_request_protocol_config.name.clone(),
 				state_request_protocol_config.name.clone(),
 				Some(warp_protocol_config.name.clone()),
+				snapshot_request_protocol_config.as_ref().map(|config| config.name.clone()),
 				rx,
 			)
 			.unwrap();
@@ -938,7 +978,9 @@ where
 				light_client_request_protocol_config,
 				warp_protocol_config,
 			]
-			.to_vec(),
+			.into_iter()
+			.chain(snapshot_request_protocol_config)
+			.collect(),
 		})
 		.unwrap();
 
@@ -979,6 +1021,7 @@ where
 				network,
 				sync_service,
 				listen_addr,
+				snapshots,
 			});
 		});
 	}
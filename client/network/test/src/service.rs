@@ -17,14 +17,16 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use futures::prelude::*;
-use libp2p::{Multiaddr, PeerId};
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
 
+use crate::in_process::{InProcessHub, InProcessNetwork, InProcessNetworkService};
 use sc_consensus::{ImportQueue, Link};
 use sc_network::{
 	config::{self, MultiaddrWithPeerId, ProtocolId, TransportConfig},
+	error::Error,
 	event::Event,
-	NetworkEventStream, NetworkNotification, NetworkPeers, NetworkService, NetworkStateInfo,
-	NetworkWorker,
+	NetworkBackend, NetworkEventStream, NetworkNotification, NetworkPeers, NetworkService,
+	NetworkServiceT, NetworkStateInfo, NetworkWorker,
 };
 use sc_network_common::role::Roles;
 use sc_network_light::light_client_requests::handler::LightClientRequestHandler;
@@ -45,26 +47,27 @@ use std::{sync::Arc, time::Duration};
 
 type TestNetworkWorker = NetworkWorker<TestBlock, TestHash>;
 type TestNetworkService = NetworkService<TestBlock, TestHash>;
+type TestInProcessNetwork = InProcessNetwork<TestBlock, TestHash>;
 
 const PROTOCOL_NAME: &str = "/foo";
 
-struct TestNetwork {
-	network: TestNetworkWorker,
+struct TestNetwork<N = TestNetworkWorker> {
+	network: N,
 }
 
-impl TestNetwork {
-	pub fn new(network: TestNetworkWorker) -> Self {
+impl<N: NetworkBackend<TestBlock, TestHash>> TestNetwork<N> {
+	pub fn new(network: N) -> Self {
 		Self { network }
 	}
 
 	pub fn start_network(
 		self,
-	) -> (Arc<TestNetworkService>, (impl Stream<Item = Event> + std::marker::Unpin)) {
-		let worker = self.network;
-		let service = worker.service().clone();
+	) -> (N::NetworkService, (impl Stream<Item = Event> + std::marker::Unpin)) {
+		let network = self.network;
+		let service = network.network_service();
 		let event_stream = service.event_stream("test");
 
-		tokio::spawn(worker.run());
+		tokio::spawn(network.run());
 
 		(service, event_stream)
 	}
@@ -108,7 +111,20 @@ impl TestNetworkBuilder {
 		self
 	}
 
-	pub fn build(mut self) -> TestNetwork {
+	pub fn build(self) -> TestNetwork {
+		self.build_with(TestNetworkWorker::new)
+	}
+
+	/// Build a node of the in-process network registered in `hub`.
+	pub fn build_in_process(self, hub: &InProcessHub) -> TestNetwork<TestInProcessNetwork> {
+		self.build_with(|params| TestInProcessNetwork::new_in(hub, params))
+	}
+
+	/// Build a node on the backend returned by `new_network`.
+	pub fn build_with<N: NetworkBackend<TestBlock, TestHash>>(
+		mut self,
+		new_network: impl FnOnce(config::Params<TestBlock>) -> Result<N, Error>,
+	) -> TestNetwork<N> {
 		let client = self.client.as_mut().map_or(
 			Arc::new(TestClientBuilder::with_default_backend().build_with_longest_chain().0),
 			|v| v.clone(),
@@ -193,16 +209,14 @@ impl TestNetworkBuilder {
 			block_request_protocol_config.name.clone(),
 			state_request_protocol_config.name.clone(),
 			None,
+			None,
 			rx,
 		)
 		.unwrap();
 		let mut link = self.link.unwrap_or(Box::new(chain_sync_service.clone()));
 		let genesis_hash =
 			client.hash(Zero::zero()).ok().flatten().expect("Genesis block exists; qed");
-		let worker = NetworkWorker::<
-			substrate_test_runtime_client::runtime::Block,
-			substrate_test_runtime_client::runtime::Hash,
-		>::new(config::Params::<substrate_test_runtime_client::runtime::Block> {
+		let network = new_network(config::Params::<TestBlock> {
 			block_announce_config,
 			role: config::Role::Full,
 			executor: Box::new(|f| {
@@ -223,9 +237,9 @@ impl TestNetworkBuilder {
 		})
 		.unwrap();
 
-		let service = worker.service().clone();
+		let service = network.network_service();
 		tokio::spawn(async move {
-			let _ = chain_sync_network_provider.run(service).await;
+			let _ = chain_sync_network_provider.run(Arc::new(service)).await;
 		});
 		tokio::spawn(async move {
 			loop {
@@ -239,7 +253,7 @@ impl TestNetworkBuilder {
 		});
 		tokio::spawn(engine.run());
 
-		TestNetwork::new(worker)
+		TestNetwork::new(network)
 	}
 }
 
@@ -272,13 +286,52 @@ fn build_nodes_one_proto() -> (
 	(node1, events_stream1, node2, events_stream2)
 }
 
+/// Same as [`build_nodes_one_proto`], but on the in-process backend.
+fn build_in_process_nodes_one_proto() -> (
+	Arc<InProcessNetworkService>,
+	impl Stream<Item = Event>,
+	Arc<InProcessNetworkService>,
+	impl Stream<Item = Event>,
+) {
+	let hub = InProcessHub::default();
+
+	let (node1, events_stream1) = TestNetworkBuilder::new().build_in_process(&hub).start_network();
+
+	let (node2, events_stream2) = TestNetworkBuilder::new()
+		.with_set_config(config::SetConfig {
+			reserved_nodes: vec![MultiaddrWithPeerId {
+				multiaddr: Multiaddr::empty(),
+				peer_id: node1.local_peer_id(),
+			}],
+			..Default::default()
+		})
+		.build_in_process(&hub)
+		.start_network();
+
+	(node1, events_stream1, node2, events_stream2)
+}
+
 #[tokio::test]
 async fn notifications_state_consistent() {
+	let (node1, events_stream1, node2, events_stream2) = build_nodes_one_proto();
+	check_notifications_state_consistent(node1, events_stream1, node2, events_stream2).await;
+}
+
+#[tokio::test]
+async fn notifications_state_consistent_in_process() {
+	let (node1, events_stream1, node2, events_stream2) = build_in_process_nodes_one_proto();
+	check_notifications_state_consistent(node1, events_stream1, node2, events_stream2).await;
+}
+
+async fn check_notifications_state_consistent(
+	node1: impl NetworkServiceT,
+	mut events_stream1: impl Stream<Item = Event> + Unpin,
+	node2: impl NetworkServiceT,
+	mut events_stream2: impl Stream<Item = Event> + Unpin,
+) {
 	// Runs two nodes and ensures that events are propagated out of the API in a consistent
 	// correct order, which means no notification received on a closed substream.
 
-	let (node1, mut events_stream1, node2, mut events_stream2) = build_nodes_one_proto();
-
 	// Write some initial notifications that shouldn't get through.
 	for _ in 0..(rand::random::<u8>() % 5) {
 		node1.write_notification(
@@ -484,12 +537,27 @@ async fn lots_of_incoming_peers_works() {
 
 #[tokio::test]
 async fn notifications_back_pressure() {
+	let (node1, events_stream1, node2, events_stream2) = build_nodes_one_proto();
+	check_notifications_back_pressure(node1, events_stream1, node2, events_stream2).await;
+}
+
+#[tokio::test]
+async fn notifications_back_pressure_in_process() {
+	let (node1, events_stream1, node2, events_stream2) = build_in_process_nodes_one_proto();
+	check_notifications_back_pressure(node1, events_stream1, node2, events_stream2).await;
+}
+
+async fn check_notifications_back_pressure(
+	node1: impl NetworkServiceT,
+	mut events_stream1: impl Stream<Item = Event> + Unpin,
+	node2: impl NetworkServiceT,
+	mut events_stream2: impl Stream<Item = Event> + Send + Unpin + 'static,
+) {
 	// Node 1 floods node 2 with notifications. Random sleeps are done on node 2 to simulate the
 	// node being busy. We make sure that all notifications are received.
 
 	const TOTAL_NOTIFS: usize = 10_000;
 
-	let (node1, mut events_stream1, node2, mut events_stream2) = build_nodes_one_proto();
 	let node2_id = node2.local_peer_id();
 
 	let receiver = tokio::spawn(async move {
@@ -794,3 +862,91 @@ async fn ensure_public_addresses_consistent_with_transport_not_memory() {
 		.build()
 		.start_network();
 }
+
+/// Network configuration with the `PROTOCOL_NAME` protocol registered, using the real transport.
+fn tcp_and_quic_config(
+	listen_addresses: Vec<Multiaddr>,
+	enable_quic: bool,
+	reserved_nodes: Vec<MultiaddrWithPeerId>,
+) -> config::NetworkConfiguration {
+	config::NetworkConfiguration {
+		extra_sets: vec![config::NonDefaultSetConfig {
+			notifications_protocol: PROTOCOL_NAME.into(),
+			fallback_names: Vec::new(),
+			max_notification_size: 1024 * 1024,
+			handshake: None,
+			set_config: config::SetConfig { reserved_nodes, ..Default::default() },
+		}],
+		listen_addresses,
+		transport: TransportConfig::Normal {
+			enable_mdns: false,
+			allow_private_ip: true,
+			enable_quic,
+		},
+		..config::NetworkConfiguration::new_local()
+	}
+}
+
+#[tokio::test]
+async fn mixed_tcp_and_quic_network() {
+	sp_tracing::try_init_simple();
+
+	// The main node supports both transports and listens on both.
+	let (main_node, mut events) = TestNetworkBuilder::new()
+		.with_config(tcp_and_quic_config(
+			vec![
+				config::build_multiaddr![Ip4([127, 0, 0, 1]), Tcp(0_u16)],
+				config::build_multiaddr![Ip4([127, 0, 0, 1]), Udp(0_u16), Quic],
+			],
+			true,
+			Vec::new(),
+		))
+		.build()
+		.start_network();
+
+	// Wait until the listeners are up, so that we know the actual ports.
+	let is_quic = |addr: &Multiaddr| addr.iter().any(|p| matches!(p, Protocol::Quic));
+	let (tcp_addr, quic_addr) = loop {
+		let addresses = main_node.listen_addresses();
+		let tcp = addresses.iter().find(|a| !is_quic(a)).cloned();
+		let quic = addresses.iter().find(|a| is_quic(a)).cloned();
+		if let (Some(tcp), Some(quic)) = (tcp, quic) {
+			break (tcp, quic)
+		}
+		tokio::time::sleep(Duration::from_millis(50)).await;
+	};
+	let main_peer_id = main_node.local_peer_id();
+
+	// One node connects over QUIC, the other one doesn't support QUIC and uses TCP.
+	let (quic_node, _) = TestNetworkBuilder::new()
+		.with_config(tcp_and_quic_config(
+			Vec::new(),
+			true,
+			vec![MultiaddrWithPeerId { multiaddr: quic_addr, peer_id: main_peer_id }],
+		))
+		.build()
+		.start_network();
+	let (tcp_node, _) = TestNetworkBuilder::new()
+		.with_config(tcp_and_quic_config(
+			Vec::new(),
+			false,
+			vec![MultiaddrWithPeerId { multiaddr: tcp_addr, peer_id: main_peer_id }],
+		))
+		.build()
+		.start_network();
+
+	let mut pending = vec![quic_node.local_peer_id(), tcp_node.local_peer_id()];
+	let wait = async {
+		while !pending.is_empty() {
+			match events.next().await.unwrap() {
+				Event::NotificationStreamOpened { remote, protocol, .. }
+					if protocol == PROTOCOL_NAME.into() =>
+					pending.retain(|p| *p != remote),
+				_ => {},
+			}
+		}
+	};
+	tokio::time::timeout(Duration::from_secs(30), wait)
+		.await
+		.expect("Both nodes connect to the main node");
+}
@@ -1166,6 +1166,108 @@ async fn syncs_state() {
 	}
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn syncs_state_from_multiple_peers() {
+	sp_tracing::try_init_simple();
+	for skip_proofs in &[false, true] {
+		let mut net = TestNet::new(0);
+		// Large enough for every range to take a few responses.
+		let mut genesis_storage: sp_core::storage::Storage = Default::default();
+		for i in 0u32..20_000 {
+			genesis_storage
+				.top
+				.insert(sp_core::blake2_256(&i.encode()).to_vec(), vec![1; 256]);
+		}
+		let child = sp_core::storage::StorageChild {
+			data: (0u8..=255).map(|i| (vec![i; 5], vec![i; 33])).collect(),
+			child_info: sp_core::storage::ChildInfo::new_default(b"child"),
+		};
+		genesis_storage
+			.children_default
+			.insert(child.child_info.storage_key().to_vec(), child);
+		for _ in 0..3 {
+			let mut config = FullPeerConfig::default();
+			config.extra_storage = Some(genesis_storage.clone());
+			net.add_full_peer_with_config(config);
+		}
+		let mut config = FullPeerConfig::default();
+		config.extra_storage = Some(genesis_storage);
+		config.sync_mode = SyncMode::Fast { skip_proofs: *skip_proofs, storage_chain_mode: false };
+		net.add_full_peer_with_config(config);
+		let hashes = net.peer(0).push_blocks(64, false);
+		net.run_until_sync().await;
+		assert!(!net.peer(3).client().has_state_at(&BlockId::Number(60)));
+
+		net.peer(3)
+			.client()
+			.finalize_block(hashes[59], Some((*b"FRNK", Vec::new())), true)
+			.unwrap();
+		futures::future::poll_fn::<(), _>(|cx| {
+			net.poll(cx);
+			if net.peer(3).client().has_state_at(&BlockId::Number(60)) {
+				Poll::Ready(())
+			} else {
+				Poll::Pending
+			}
+		})
+		.await;
+		assert_eq!(net.peer(3).client().info().finalized_state.map(|(_, n)| n), Some(60));
+	}
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn syncs_state_as_snapshot() {
+	sp_tracing::try_init_simple();
+	let mut net = TestNet::new(0);
+	// Large enough for the snapshot to take a few chunks.
+	let mut genesis_storage: sp_core::storage::Storage = Default::default();
+	for i in 0u32..40_000 {
+		genesis_storage
+			.top
+			.insert(sp_core::blake2_256(&i.encode()).to_vec(), vec![1; 512]);
+	}
+	for _ in 0..3 {
+		net.add_full_peer_with_config(FullPeerConfig {
+			extra_storage: Some(genesis_storage.clone()),
+			snapshots: true,
+			..Default::default()
+		});
+	}
+	net.add_full_peer_with_config(FullPeerConfig {
+		extra_storage: Some(genesis_storage),
+		sync_mode: SyncMode::Fast { skip_proofs: false, storage_chain_mode: false },
+		snapshots: true,
+		..Default::default()
+	});
+	let hashes = net.peer(0).push_blocks(64, false);
+	net.run_until_sync().await;
+	assert!(!net.peer(3).client().has_state_at(&BlockId::Number(60)));
+
+	// Snapshots are only served once exported, after finalization.
+	assert_eq!(60 % TEST_SNAPSHOT_PERIOD, 0);
+	for peer in 0..3 {
+		net.peer(peer)
+			.client()
+			.finalize_block(hashes[59], Some((*b"FRNK", Vec::new())), true)
+			.unwrap();
+		net.peer(peer).export_snapshot(hashes[59]);
+	}
+	net.peer(3)
+		.client()
+		.finalize_block(hashes[59], Some((*b"FRNK", Vec::new())), true)
+		.unwrap();
+	futures::future::poll_fn::<(), _>(|cx| {
+		net.poll(cx);
+		if net.peer(3).client().has_state_at(&BlockId::Number(60)) {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	})
+	.await;
+	assert_eq!(net.peer(3).client().info().finalized_state.map(|(_, n)| n), Some(60));
+}
+
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
 async fn syncs_indexed_blocks() {
 	use sp_runtime::traits::Hash;
@@ -55,6 +55,11 @@ const DISCONNECT_REPUTATION_CHANGE: i32 = -256;
 /// Amount of time between the moment we disconnect from a node and the moment we remove it from
 /// the list.
 const FORGET_AFTER: Duration = Duration::from_secs(3600);
+/// Number of reputation changes remembered for each peer.
+const MAX_RECENT_CHANGES: usize = 16;
+/// Longest ban that can be placed with [`PeersetHandle::ban_peer`], longer bans are shortened to
+/// this duration.
+pub const MAX_BAN_DURATION: Duration = Duration::from_secs(10 * 365 * 24 * 3600);
 
 #[derive(Debug)]
 enum Action {
@@ -66,6 +71,9 @@ enum Action {
 	AddToPeersSet(SetId, PeerId),
 	RemoveFromPeersSet(SetId, PeerId),
 	PeerReputation(PeerId, oneshot::Sender<i32>),
+	PeerReputations(oneshot::Sender<Vec<PeerReputation>>),
+	BanPeer(PeerId, Duration),
+	UnbanPeer(PeerId),
 }
 
 /// Identifier of a set in the peerset.
@@ -176,6 +184,46 @@ impl PeersetHandle {
 		// The channel can only be closed if the peerset no longer exists.
 		rx.await.map_err(|_| ())
 	}
+
+	/// Returns the reputation of all the peers we know about, along with the most recent
+	/// adjustments made to it.
+	pub async fn peer_reputations(self) -> Result<Vec<PeerReputation>, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self.tx.unbounded_send(Action::PeerReputations(tx));
+
+		// The channel can only be closed if the peerset no longer exists.
+		rx.await.map_err(|_| ())
+	}
+
+	/// Bans a peer for the given duration, disconnecting it from all sets.
+	///
+	/// The peer is treated as if its reputation was below [`BANNED_THRESHOLD`] until the ban
+	/// expires or is lifted with [`PeersetHandle::unban_peer`], even if it is reserved. The
+	/// duration is capped to [`MAX_BAN_DURATION`].
+	pub fn ban_peer(&self, peer_id: PeerId, duration: Duration) {
+		let _ = self.tx.unbounded_send(Action::BanPeer(peer_id, duration));
+	}
+
+	/// Lifts a ban placed with [`PeersetHandle::ban_peer`] and resets the reputation of the peer.
+	///
+	/// Has no effect if the peer isn't banned.
+	pub fn unban_peer(&self, peer_id: PeerId) {
+		let _ = self.tx.unbounded_send(Action::UnbanPeer(peer_id));
+	}
+}
+
+/// Reputation of a peer, as returned by [`PeersetHandle::peer_reputations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerReputation {
+	/// Peer the reputation belongs to.
+	pub peer_id: PeerId,
+	/// Current reputation value.
+	pub reputation: i32,
+	/// Most recent reputation changes, oldest first.
+	pub recent_changes: Vec<ReputationChange>,
+	/// Remaining duration of the ban placed with [`PeersetHandle::ban_peer`], if any.
+	pub banned_for: Option<Duration>,
 }
 
 /// Message that can be sent by the peer set manager (PSM).
@@ -270,6 +318,10 @@ pub struct Peerset {
 	/// Next time to do a periodic call to `alloc_slots` with all sets. This is done once per
 	/// second, to match the period of the reputation updates.
 	next_periodic_alloc_slots: Delay,
+	/// Most recent reputation changes of each peer, oldest first.
+	recent_changes: HashMap<PeerId, VecDeque<ReputationChange>>,
+	/// Peers banned with [`PeersetHandle::ban_peer`], and when their ban expires.
+	bans: HashMap<PeerId, Instant>,
 }
 
 impl Peerset {
@@ -297,6 +349,8 @@ impl Peerset {
 				created: now,
 				latest_time_update: now,
 				next_periodic_alloc_slots: Delay::new(Duration::new(0, 0)),
+				recent_changes: HashMap::new(),
+				bans: HashMap::new(),
 			}
 		};
 
@@ -436,6 +490,12 @@ impl Peerset {
 		// We want reputations to be up-to-date before adjusting them.
 		self.update_time();
 
+		let recent_changes = self.recent_changes.entry(peer_id).or_default();
+		if recent_changes.len() == MAX_RECENT_CHANGES {
+			recent_changes.pop_front();
+		}
+		recent_changes.push_back(change);
+
 		let mut reputation = self.data.peer_reputation(peer_id);
 		reputation.add_reputation(change.value);
 		if reputation.reputation() >= BANNED_THRESHOLD {
@@ -450,7 +510,11 @@ impl Peerset {
 		);
 
 		drop(reputation);
+		self.disconnect_from_all_sets(peer_id);
+	}
 
+	/// Disconnect a peer whose reputation has fallen below [`BANNED_THRESHOLD`].
+	fn disconnect_from_all_sets(&mut self, peer_id: PeerId) {
 		for set_index in 0..self.data.num_sets() {
 			if let peersstate::Peer::Connected(peer) = self.data.peer(set_index, &peer_id) {
 				let peer = peer.disconnect();
@@ -469,6 +533,55 @@ impl Peerset {
 		let _ = pending_response.send(reputation.reputation());
 	}
 
+	fn on_peer_reputations(&mut self, pending_response: oneshot::Sender<Vec<PeerReputation>>) {
+		self.update_time();
+
+		let now = Instant::now();
+		let reputations = self
+			.data
+			.peers()
+			.cloned()
+			.collect::<Vec<_>>()
+			.into_iter()
+			.map(|peer_id| PeerReputation {
+				peer_id,
+				reputation: self.data.peer_reputation(peer_id).reputation(),
+				recent_changes: self
+					.recent_changes
+					.get(&peer_id)
+					.map(|changes| changes.iter().cloned().collect())
+					.unwrap_or_default(),
+				banned_for: self
+					.bans
+					.get(&peer_id)
+					.map(|until| until.saturating_duration_since(now)),
+			})
+			.collect();
+		let _ = pending_response.send(reputations);
+	}
+
+	fn on_ban_peer(&mut self, peer_id: PeerId, duration: Duration) {
+		debug!(target: "peerset", "Banning {} for {:?}", peer_id, duration);
+
+		let now = Instant::now();
+		let until = now.checked_add(duration.min(MAX_BAN_DURATION)).unwrap_or(now);
+		self.bans.insert(peer_id, until);
+		self.data.peer_reputation(peer_id).set_reputation(i32::MIN);
+		self.disconnect_from_all_sets(peer_id);
+	}
+
+	fn on_unban_peer(&mut self, peer_id: PeerId) {
+		if self.bans.remove(&peer_id).is_none() {
+			return
+		}
+
+		debug!(target: "peerset", "Unbanning {}", peer_id);
+		self.data.peer_reputation(peer_id).set_reputation(0);
+		for set_index in 0..self.data.num_sets() {
+			self.alloc_slots(SetId(set_index));
+		}
+	}
+
 	/// Updates the value of `self.latest_time_update` and performs all the updates that happen
 	/// over time, such as reputation increases for staying connected.
 	fn update_time(&mut self) {
@@ -503,6 +616,16 @@ impl Peerset {
 
 				let mut peer_reputation = self.data.peer_reputation(peer_id);
 
+				// Manually banned peers stay at the lowest reputation until the ban expires.
+				if let Some(until) = self.bans.get(&peer_id) {
+					if *until > now {
+						continue
+					}
+
+					self.bans.remove(&peer_id);
+					peer_reputation.set_reputation(0);
+				}
+
 				let before = peer_reputation.reputation();
 				let after = reput_tick(before);
 				trace!(target: "peerset", "Fleeting {}: {} -> {}", peer_id, before, after);
@@ -513,6 +636,7 @@ impl Peerset {
 				}
 
 				drop(peer_reputation);
+				self.recent_changes.remove(&peer_id);
 
 				// If the peer reaches a reputation of 0, and there is no connection to it,
 				// forget it.
@@ -758,6 +882,10 @@ impl Stream for Peerset {
 					self.on_remove_from_peers_set(sets_name, peer_id),
 				Action::PeerReputation(peer_id, pending_response) =>
 					self.on_peer_reputation(peer_id, pending_response),
+				Action::PeerReputations(pending_response) =>
+					self.on_peer_reputations(pending_response),
+				Action::BanPeer(peer_id, duration) => self.on_ban_peer(peer_id, duration),
+				Action::UnbanPeer(peer_id) => self.on_unban_peer(peer_id),
 			}
 		}
 	}
@@ -778,7 +906,7 @@ pub enum DropReason {
 mod tests {
 	use super::{
 		IncomingIndex, Message, Peerset, PeersetConfig, ReputationChange, SetConfig, SetId,
-		BANNED_THRESHOLD,
+		BANNED_THRESHOLD, MAX_RECENT_CHANGES,
 	};
 	use futures::prelude::*;
 	use libp2p::PeerId;
@@ -996,4 +1124,133 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	#[test]
+	fn test_manual_ban_outlives_reputation_recovery() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, Duration::from_secs(3600));
+
+		let fut = futures::future::poll_fn(move |cx| {
+			// We need one polling for the message to be processed.
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(1)));
+			} else {
+				panic!()
+			}
+
+			// A reputation-based ban would have expired by now, a manual one doesn't.
+			thread::sleep(Duration::from_millis(1500));
+			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(2));
+			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(2)));
+			} else {
+				panic!()
+			}
+
+			handle.unban_peer(peer_id);
+			while let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Connect { set_id: SetId::from(0), peer_id });
+			}
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
+
+	#[test]
+	fn test_ban_peer_duration_is_capped() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, Duration::from_secs(u64::MAX));
+
+		let reputations = handle.clone().peer_reputations();
+		futures::pin_mut!(reputations);
+
+		let fut = futures::future::poll_fn(move |cx| {
+			assert_eq!(reputations.as_mut().poll(cx), Poll::Pending);
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			let Poll::Ready(Ok(reputations)) = reputations.as_mut().poll(cx) else { panic!() };
+			let banned_for = reputations[0].banned_for.unwrap();
+			assert!(banned_for <= MAX_BAN_DURATION);
+			assert!(banned_for > MAX_BAN_DURATION - Duration::from_secs(60));
+
+			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
+				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(1)));
+			} else {
+				panic!()
+			}
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
+
+	#[test]
+	fn test_peer_reputations_keep_recent_changes() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+		});
+
+		let peer_id = PeerId::random();
+		for _ in 0..MAX_RECENT_CHANGES {
+			handle.report_peer(peer_id, ReputationChange::new(-1, "old"));
+		}
+		handle.report_peer(peer_id, ReputationChange::new(-10, "new"));
+		handle.ban_peer(peer_id, Duration::from_secs(3600));
+
+		let reputations = handle.clone().peer_reputations();
+		futures::pin_mut!(reputations);
+
+		let fut = futures::future::poll_fn(move |cx| {
+			assert_eq!(reputations.as_mut().poll(cx), Poll::Pending);
+			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
+
+			let Poll::Ready(Ok(reputations)) = reputations.as_mut().poll(cx) else { panic!() };
+			assert_eq!(reputations.len(), 1);
+
+			let reputation = &reputations[0];
+			assert_eq!(reputation.peer_id, peer_id);
+			assert_eq!(reputation.reputation, i32::MIN);
+			assert_eq!(reputation.recent_changes.len(), MAX_RECENT_CHANGES);
+			assert_eq!(reputation.recent_changes[0].reason, "old");
+			assert_eq!(reputation.recent_changes.last(), Some(&ReputationChange::new(-10, "new")));
+			assert!(reputation.banned_for.unwrap() > Duration::from_secs(3500));
+
+			Poll::Ready(())
+		});
+
+		futures::executor::block_on(fut);
+	}
 }
@@ -52,6 +52,12 @@ pub enum Error {
 	/// Call to an unsafe RPC was denied.
 	#[error(transparent)]
 	UnsafeRpcCalled(#[from] crate::policy::UnsafeRpcError),
+	/// The runtime call was aborted for exceeding one of its resource limits.
+	#[error("Runtime call exceeded its {0} limit")]
+	ResourceLimitExceeded(String),
+	/// No host function calls were recorded while profiling a block.
+	#[error("No host function calls were recorded, the node must run with `--profile-host-functions` and execute the runtime in compiled wasm")]
+	HostFunctionProfilingDisabled,
 }
 
 /// Base code for all state errors.
@@ -66,6 +72,12 @@ impl From<Error> for JsonRpseeError {
 			Error::InvalidCount { .. } =>
 				CallError::Custom(ErrorObject::owned(BASE_ERROR + 2, e.to_string(), None::<()>))
 					.into(),
+			Error::ResourceLimitExceeded(_) =>
+				CallError::Custom(ErrorObject::owned(BASE_ERROR + 3, e.to_string(), None::<()>))
+					.into(),
+			Error::HostFunctionProfilingDisabled =>
+				CallError::Custom(ErrorObject::owned(BASE_ERROR + 4, e.to_string(), None::<()>))
+					.into(),
 			e => Self::to_call_error(e),
 		}
 	}
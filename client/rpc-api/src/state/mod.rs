@@ -289,4 +289,30 @@ pub trait StateApi<Hash> {
 		storage_keys: Option<String>,
 		methods: Option<String>,
 	) -> RpcResult<sp_rpc::tracing::TraceBlockResponse>;
+
+	/// The `profileBlock` RPC re-executes a single block and reports the number of calls of each
+	/// host function and the time spent in them.
+	///
+	/// ## Node requirements
+	///
+	/// - Node running with `--profile-host-functions`, which also exports the timings of all host
+	///   function calls as Prometheus metrics.
+	/// - Compiled wasm execution (`--wasm-execution=compiled`), the interpreter isn't profiled.
+	/// - A node that still has the state of the parent of the block.
+	///
+	/// ### `curl` example
+	///
+	/// ```text
+	/// curl \
+	/// 	-H "Content-Type: application/json" \
+	/// 	-d '{"id":1, "jsonrpc":"2.0", "method": "state_profileBlock", \
+	/// 		"params": ["0xb246acf1adea1f801ce15c77a5fa7d8f2eb8fed466978bcee172cc02cf64e264"]}' \
+	/// 	http://localhost:9933/
+	/// ```
+	///
+	/// ### Params
+	///
+	/// - `block` (param index 0): Hash of the block to profile.
+	#[method(name = "state_profileBlock", blocking)]
+	fn profile_block(&self, block: Hash) -> RpcResult<sp_rpc::tracing::BlockProfile>;
 }
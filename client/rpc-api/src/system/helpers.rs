@@ -80,6 +80,40 @@ pub enum NodeRole {
 	Authority,
 }
 
+/// Reputation of a peer, as tracked by the peer set manager.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+	/// Peer ID
+	pub peer_id: String,
+	/// Current reputation
+	pub reputation: i32,
+	/// Most recent changes of the reputation, oldest first
+	pub recent_changes: Vec<ReputationChange>,
+	/// Number of seconds until the peer's ban is lifted, if it was banned manually
+	pub banned_for: Option<u64>,
+}
+
+/// Adjustment made to the reputation of a peer.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReputationChange {
+	/// Reputation delta
+	pub value: i32,
+	/// Reason for the change
+	pub reason: String,
+}
+
+/// Peer or IP address banned by the node operator.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BannedPeer {
+	/// Banned base58-encoded PeerId or IP address
+	pub target: String,
+	/// UNIX timestamp, in seconds, at which the ban expires
+	pub expires_at: u64,
+}
+
 /// The state of the syncing of the node.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -123,6 +157,29 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_serialize_peer_reputation() {
+		assert_eq!(
+			::serde_json::to_string(&PeerReputation {
+				peer_id: "2".into(),
+				reputation: -10,
+				recent_changes: vec![ReputationChange { value: -10, reason: "a".into() }],
+				banned_for: Some(60),
+			})
+			.unwrap(),
+			r#"{"peerId":"2","reputation":-10,"recentChanges":[{"value":-10,"reason":"a"}],"bannedFor":60}"#,
+		);
+	}
+
+	#[test]
+	fn should_serialize_banned_peer() {
+		assert_eq!(
+			::serde_json::to_string(&BannedPeer { target: "::1".into(), expires_at: 1000 })
+				.unwrap(),
+			r#"{"target":"::1","expiresAt":1000}"#,
+		);
+	}
+
 	#[test]
 	fn should_serialize_sync_state() {
 		assert_eq!(
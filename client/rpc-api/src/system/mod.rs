@@ -23,7 +23,9 @@ use jsonrpsee::{
 	proc_macros::rpc,
 };
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{
+	BannedPeer, Health, NodeRole, PeerInfo, PeerReputation, ReputationChange, SyncState, SystemInfo,
+};
 
 pub mod error;
 pub mod helpers;
@@ -100,6 +102,27 @@ pub trait SystemApi<Hash, Number> {
 	#[method(name = "system_reservedPeers")]
 	async fn system_reserved_peers(&self) -> RpcResult<Vec<String>>;
 
+	/// Returns the reputation of every peer the node knows about, along with the most recent
+	/// reasons for changing it.
+	#[method(name = "system_peerReputations")]
+	async fn system_peer_reputations(&self) -> RpcResult<Vec<PeerReputation>>;
+
+	/// Bans a peer or an IP address for `duration` seconds, disconnecting from it. The string
+	/// parameter should encode either a PeerId e.g.
+	/// `QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV` or an IP address e.g. `198.51.100.19`.
+	///
+	/// Bans are persisted across restarts. Bans longer than ten years are shortened to ten years.
+	#[method(name = "system_banPeer")]
+	async fn system_ban_peer(&self, target: String, duration: u64) -> RpcResult<()>;
+
+	/// Lifts a ban placed with `system_banPeer`. Returns whether the target was banned.
+	#[method(name = "system_unbanPeer")]
+	async fn system_unban_peer(&self, target: String) -> RpcResult<bool>;
+
+	/// Returns the peers and IP addresses banned with `system_banPeer`.
+	#[method(name = "system_bannedPeers")]
+	async fn system_banned_peers(&self) -> RpcResult<Vec<BannedPeer>>;
+
 	/// Returns the roles the node is running as.
 	#[method(name = "system_nodeRoles")]
 	async fn system_node_roles(&self) -> RpcResult<Vec<NodeRole>>;
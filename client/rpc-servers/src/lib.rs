@@ -29,7 +29,8 @@ use jsonrpsee::{
 };
 use std::{error::Error as StdError, net::SocketAddr};
 
-pub use crate::middleware::RpcMetrics;
+use crate::middleware::{AccessLayer, AccessLogger};
+pub use crate::middleware::{RateLimit, RateLimitConfig, RpcMetrics};
 use http::header::HeaderValue;
 pub use jsonrpsee::core::{
 	id_providers::{RandomIntegerIdProvider, RandomStringIdProvider},
@@ -66,6 +67,26 @@ pub struct WsConfig {
 	pub max_payload_out_mb: Option<usize>,
 }
 
+/// Methods exposed by the RPC servers.
+///
+/// Calls to methods that are not exposed fail with a "method not found" error and do not show up
+/// in `rpc_methods`.
+#[derive(Debug, Clone, Default)]
+pub struct MethodFilter {
+	/// If set, only these methods are exposed.
+	pub allow: Option<Vec<String>>,
+	/// Methods that are never exposed, even if allowed.
+	pub deny: Vec<String>,
+}
+
+impl MethodFilter {
+	/// Returns `true` if `method` is exposed.
+	pub fn is_allowed(&self, method: &str) -> bool {
+		self.allow.as_ref().map_or(true, |allow| allow.iter().any(|m| m == method)) &&
+			!self.deny.iter().any(|m| m == method)
+	}
+}
+
 impl WsConfig {
 	// Deconstructs the config to get the finalized inner values.
 	//
@@ -86,6 +107,8 @@ pub async fn start_http<M: Send + Sync + 'static>(
 	cors: Option<&Vec<String>>,
 	max_payload_in_mb: Option<usize>,
 	max_payload_out_mb: Option<usize>,
+	rate_limit: RateLimitConfig,
+	method_filter: &MethodFilter,
 	metrics: Option<RpcMetrics>,
 	rpc_api: RpcModule<M>,
 	rt: tokio::runtime::Handle,
@@ -97,26 +120,27 @@ pub async fn start_http<M: Send + Sync + 'static>(
 	let middleware = tower::ServiceBuilder::new()
 		// Proxy `GET /health` requests to internal `system_health` method.
 		.layer(ProxyGetRequestLayer::new("/health", "system_health")?)
-		.layer(try_into_cors(cors)?);
+		.layer(try_into_cors(cors)?)
+		.layer(AccessLayer::new(
+			method_filter.clone(),
+			rate_limit,
+			max_payload_in,
+			metrics.clone(),
+		));
 
 	let builder = ServerBuilder::new()
 		.max_request_body_size(max_payload_in)
 		.max_response_body_size(max_payload_out)
 		.set_host_filtering(host_filter)
 		.set_middleware(middleware)
+		.set_logger(AccessLogger::new(metrics))
 		.custom_tokio_runtime(rt)
 		.http_only();
 
-	let rpc_api = build_rpc_api(rpc_api);
-	let (handle, addr) = if let Some(metrics) = metrics {
-		let server = builder.set_logger(metrics).build(&addrs[..]).await?;
-		let addr = server.local_addr();
-		(server.start(rpc_api)?, addr)
-	} else {
-		let server = builder.build(&addrs[..]).await?;
-		let addr = server.local_addr();
-		(server.start(rpc_api)?, addr)
-	};
+	let rpc_api = build_rpc_api(rpc_api, method_filter);
+	let server = builder.build(&addrs[..]).await?;
+	let addr = server.local_addr();
+	let handle = server.start(rpc_api)?;
 
 	log::info!(
 		"Running JSON-RPC HTTP server: addr={}, allowed origins={}",
@@ -132,6 +156,8 @@ pub async fn start<M: Send + Sync + 'static>(
 	addrs: [SocketAddr; 2],
 	cors: Option<&Vec<String>>,
 	ws_config: WsConfig,
+	rate_limit: RateLimitConfig,
+	method_filter: &MethodFilter,
 	metrics: Option<RpcMetrics>,
 	rpc_api: RpcModule<M>,
 	rt: tokio::runtime::Handle,
@@ -145,7 +171,13 @@ pub async fn start<M: Send + Sync + 'static>(
 	let middleware = tower::ServiceBuilder::new()
 		// Proxy `GET /health` requests to internal `system_health` method.
 		.layer(ProxyGetRequestLayer::new("/health", "system_health")?)
-		.layer(try_into_cors(cors)?);
+		.layer(try_into_cors(cors)?)
+		.layer(AccessLayer::new(
+			method_filter.clone(),
+			rate_limit,
+			max_payload_in,
+			metrics.clone(),
+		));
 
 	let mut builder = ServerBuilder::new()
 		.max_request_body_size(max_payload_in)
@@ -155,6 +187,7 @@ pub async fn start<M: Send + Sync + 'static>(
 		.ping_interval(std::time::Duration::from_secs(30))
 		.set_host_filtering(host_filter)
 		.set_middleware(middleware)
+		.set_logger(AccessLogger::new(metrics))
 		.custom_tokio_runtime(rt);
 
 	if let Some(provider) = id_provider {
@@ -163,16 +196,10 @@ pub async fn start<M: Send + Sync + 'static>(
 		builder = builder.set_id_provider(RandomStringIdProvider::new(16));
 	};
 
-	let rpc_api = build_rpc_api(rpc_api);
-	let (handle, addr) = if let Some(metrics) = metrics {
-		let server = builder.set_logger(metrics).build(&addrs[..]).await?;
-		let addr = server.local_addr();
-		(server.start(rpc_api)?, addr)
-	} else {
-		let server = builder.build(&addrs[..]).await?;
-		let addr = server.local_addr();
-		(server.start(rpc_api)?, addr)
-	};
+	let rpc_api = build_rpc_api(rpc_api, method_filter);
+	let server = builder.build(&addrs[..]).await?;
+	let addr = server.local_addr();
+	let handle = server.start(rpc_api)?;
 
 	log::info!(
 		"Running JSON-RPC WS server: addr={}, allowed origins={}",
@@ -183,8 +210,14 @@ pub async fn start<M: Send + Sync + 'static>(
 	Ok(handle)
 }
 
-fn build_rpc_api<M: Send + Sync + 'static>(mut rpc_api: RpcModule<M>) -> RpcModule<M> {
-	let mut available_methods = rpc_api.method_names().collect::<Vec<_>>();
+fn build_rpc_api<M: Send + Sync + 'static>(
+	mut rpc_api: RpcModule<M>,
+	method_filter: &MethodFilter,
+) -> RpcModule<M> {
+	let mut available_methods = rpc_api
+		.method_names()
+		.filter(|m| method_filter.is_allowed(m))
+		.collect::<Vec<_>>();
 	available_methods.sort();
 
 	rpc_api
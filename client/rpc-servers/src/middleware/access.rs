@@ -0,0 +1,528 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Method filter and rate limits applied to every RPC call.
+//!
+//! [`AccessLayer`] is a tower layer wrapped around the jsonrpsee server. It inspects every HTTP
+//! request and every message received on a WebSocket connection before the server does:
+//!
+//! - calls to methods that are not exposed are renamed to a method that does not exist, so that the
+//!   server answers them with a "method not found" error;
+//! - the calls of the message are charged to the [`RateLimiter`] buckets of the connection and of
+//!   its remote address, and are all answered with [`RATE_LIMIT_ERROR_CODE`] if either bucket
+//!   cannot afford them.
+//!
+//! The server does not let middlewares see the messages of a WebSocket connection once it is
+//! established, so the layer accepts WebSocket connections itself and relays their messages to
+//! the server over an in-memory connection, see [`super::relay`].
+//!
+//! The server does not hand the peer address to middlewares either, it only reports it to its
+//! logger when it is handed a request. [`AccessLogger`] stores it in the [`PeerAddr`] extension
+//! of the request. Requests whose peer is unknown are charged to a bucket shared by all of them.
+
+use super::{
+	rate_limit::{RateLimiter, TokenBucket, DEFAULT_CALL_COST},
+	relay, RateLimitConfig, RejectReason, RpcMetrics, RATE_LIMIT_ERROR_CODE,
+};
+use crate::MethodFilter;
+use futures::future::BoxFuture;
+use http::{header, HeaderValue, StatusCode};
+use hyper::{body::HttpBody, Body, Request, Response};
+use jsonrpsee::{
+	server::logger::{HttpRequest, Logger, MethodKind, Params, TransportProtocol},
+	types::{error::reject_too_big_request, ErrorResponse, Id},
+};
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::{
+	error::Error as StdError,
+	net::{IpAddr, SocketAddr},
+	sync::Arc,
+	task::{Context, Poll},
+	time::Instant,
+};
+use tower::{Layer, Service};
+
+/// Name given to the calls to methods that are not exposed.
+///
+/// Method names starting with `rpc.` are reserved by the JSON-RPC specification.
+const DENIED_METHOD: &str = "rpc.denied";
+
+/// Method always exposed, it lists the exposed methods.
+const RPC_METHODS: &str = "rpc_methods";
+
+/// State shared by the connections of a server.
+#[derive(Debug)]
+pub(crate) struct Access {
+	filter: MethodFilter,
+	limiter: RateLimiter,
+	max_request_body_size: u32,
+	metrics: Option<RpcMetrics>,
+}
+
+impl Access {
+	/// Returns `true` if there are calls to filter or to charge.
+	fn is_enabled(&self) -> bool {
+		self.filter.allow.is_some() ||
+			!self.filter.deny.is_empty() ||
+			self.limiter.config().is_enabled()
+	}
+
+	pub(crate) fn max_request_body_size(&self) -> u32 {
+		self.max_request_body_size
+	}
+
+	pub(crate) fn config(&self) -> &RateLimitConfig {
+		self.limiter.config()
+	}
+
+	/// Response to a message larger than [`Self::max_request_body_size`].
+	pub(crate) fn too_large(&self) -> String {
+		let error = reject_too_big_request(self.max_request_body_size);
+		serde_json::to_string(&ErrorResponse::borrowed(error, Id::Null))
+			.expect("error responses serialize; qed")
+	}
+
+	/// Rename the calls of `message` to methods that are not exposed.
+	///
+	/// Returns the message to hand to the server, `None` if it is unchanged.
+	pub(crate) fn filter(&self, message: &mut Message) -> Option<Vec<u8>> {
+		let mut denied = 0;
+		for call in message.calls_mut() {
+			let exposed = call
+				.get("method")
+				.and_then(Value::as_str)
+				.map_or(true, |method| method == RPC_METHODS || self.filter.is_allowed(method));
+			if !exposed {
+				call["method"] = DENIED_METHOD.into();
+				denied += 1;
+			}
+		}
+
+		if denied == 0 {
+			return None
+		}
+		if let Some(metrics) = &self.metrics {
+			(0..denied).for_each(|_| metrics.on_rejected(RejectReason::Denied));
+		}
+		Some(message.to_vec())
+	}
+
+	/// Charge the calls of `message` to the connection and remote address buckets.
+	///
+	/// Returns the response to send instead of handing the message to the server if they cannot
+	/// afford it.
+	pub(crate) fn charge(
+		&self,
+		message: &Message,
+		connection: Option<&Mutex<TokenBucket>>,
+		remote: Option<IpAddr>,
+	) -> Result<(), String> {
+		let config = self.limiter.config();
+		if !config.is_enabled() {
+			return Ok(())
+		}
+
+		let cost = message.cost(config);
+		self.limiter.try_acquire(connection, remote, cost).map_err(|reason| {
+			log::debug!(
+				target: "rpc",
+				"Rejected RPC request from {:?} costing {}: {:?}",
+				remote,
+				cost,
+				reason,
+			);
+			if let Some(metrics) = &self.metrics {
+				(0..message.len()).for_each(|_| metrics.on_rejected(reason));
+			}
+			message.rejected()
+		})
+	}
+}
+
+/// JSON-RPC message received from a client, a single call or a batch.
+#[derive(Debug)]
+pub(crate) struct Message(Option<Value>);
+
+impl Message {
+	/// Parse a message, the server rejects the ones that are not JSON.
+	pub(crate) fn parse(bytes: &[u8]) -> Self {
+		Self(serde_json::from_slice(bytes).ok())
+	}
+
+	fn calls(&self) -> impl Iterator<Item = &Value> {
+		match &self.0 {
+			Some(Value::Array(calls)) => calls.iter().collect::<Vec<_>>().into_iter(),
+			Some(call) => vec![call].into_iter(),
+			None => Vec::new().into_iter(),
+		}
+	}
+
+	fn calls_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+		match &mut self.0 {
+			Some(Value::Array(calls)) => calls.iter_mut().collect::<Vec<_>>().into_iter(),
+			Some(call) => vec![call].into_iter(),
+			None => Vec::new().into_iter(),
+		}
+	}
+
+	/// Number of calls, malformed messages count as one call.
+	fn len(&self) -> usize {
+		self.calls().count().max(1)
+	}
+
+	/// Summed cost of the calls, malformed messages are charged [`DEFAULT_CALL_COST`].
+	fn cost(&self, config: &RateLimitConfig) -> u32 {
+		self.calls()
+			.map(|call| {
+				call.get("method")
+					.and_then(Value::as_str)
+					.map_or(DEFAULT_CALL_COST, |method| config.method_cost(method))
+			})
+			.fold(0u32, u32::saturating_add)
+			.max(DEFAULT_CALL_COST)
+	}
+
+	fn to_vec(&self) -> Vec<u8> {
+		serde_json::to_vec(&self.0).expect("a JSON value serializes; qed")
+	}
+
+	/// Response rejecting every call of the message because of the rate limits.
+	fn rejected(&self) -> String {
+		let error = |id: &Value| {
+			serde_json::json!({
+				"jsonrpc": "2.0",
+				"error": { "code": RATE_LIMIT_ERROR_CODE, "message": "Too many requests" },
+				"id": id,
+			})
+		};
+
+		let response = match &self.0 {
+			Some(Value::Array(calls)) if !calls.is_empty() => Value::Array(
+				calls.iter().map(|call| error(call.get("id").unwrap_or(&Value::Null))).collect(),
+			),
+			Some(call) => error(call.get("id").unwrap_or(&Value::Null)),
+			None => error(&Value::Null),
+		};
+		response.to_string()
+	}
+}
+
+/// Layer applying the method filter and the rate limits to every call.
+#[derive(Debug, Clone)]
+pub struct AccessLayer {
+	access: Arc<Access>,
+}
+
+impl AccessLayer {
+	/// Create a new layer.
+	///
+	/// `max_request_body_size` bounds the bytes buffered to inspect a request or a WebSocket
+	/// message.
+	pub fn new(
+		filter: MethodFilter,
+		rate_limit: RateLimitConfig,
+		max_request_body_size: u32,
+		metrics: Option<RpcMetrics>,
+	) -> Self {
+		Self {
+			access: Arc::new(Access {
+				filter,
+				limiter: RateLimiter::new(rate_limit),
+				max_request_body_size,
+				metrics,
+			}),
+		}
+	}
+}
+
+impl<S> Layer<S> for AccessLayer {
+	type Service = AccessService<S>;
+
+	fn layer(&self, inner: S) -> Self::Service {
+		// The server builds its middleware stack for every connection it accepts.
+		let connection = self.access.limiter.connection_bucket().map(Arc::new);
+
+		AccessService { inner, access: self.access.clone(), connection }
+	}
+}
+
+/// Service created by [`AccessLayer`].
+#[derive(Debug, Clone)]
+pub struct AccessService<S> {
+	inner: S,
+	access: Arc<Access>,
+	connection: Option<Arc<Mutex<TokenBucket>>>,
+}
+
+impl<S> Service<Request<Body>> for AccessService<S>
+where
+	S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+	S::Error: Into<Box<dyn StdError + Send + Sync>> + 'static,
+	S::Future: Send + 'static,
+{
+	type Response = Response<Body>;
+	type Error = Box<dyn StdError + Send + Sync + 'static>;
+	type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+	fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+		self.inner.poll_ready(cx).map_err(Into::into)
+	}
+
+	fn call(&mut self, req: Request<Body>) -> Self::Future {
+		if !self.access.is_enabled() {
+			let fut = self.inner.call(req);
+			return Box::pin(async move { fut.await.map_err(Into::into) })
+		}
+
+		// Keep the instance `poll_ready` was called on for this request.
+		let clone = self.inner.clone();
+		let inner = std::mem::replace(&mut self.inner, clone);
+		let access = self.access.clone();
+		let connection = self.connection.clone();
+
+		if soketto::handshake::http::is_upgrade_request(&req) {
+			return Box::pin(relay::accept(req, inner, access, connection))
+		}
+
+		Box::pin(handle_http(req, inner, access, connection))
+	}
+}
+
+async fn handle_http<S>(
+	req: Request<Body>,
+	mut inner: S,
+	access: Arc<Access>,
+	connection: Option<Arc<Mutex<TokenBucket>>>,
+) -> Result<Response<Body>, Box<dyn StdError + Send + Sync>>
+where
+	S: Service<Request<Body>, Response = Response<Body>>,
+	S::Error: Into<Box<dyn StdError + Send + Sync>>,
+{
+	let (mut parts, body) = req.into_parts();
+	let Some(mut body) = read_body(body, access.max_request_body_size()).await? else {
+		return Ok(Response::builder()
+			.status(StatusCode::PAYLOAD_TOO_LARGE)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(Body::from(access.too_large()))
+			.expect("status and header are valid; qed"))
+	};
+
+	let mut message = Message::parse(&body);
+	if let Some(filtered) = access.filter(&mut message) {
+		body = filtered;
+		parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+	}
+
+	let peer = PeerAddr::default();
+	parts.extensions.insert(peer.clone());
+	let headers = parts.headers.clone();
+	let (mut sender, channel) = Body::channel();
+	// The server reports the peer to its logger as soon as it is handed the request, and only
+	// reads the body afterwards.
+	let response = inner.call(Request::from_parts(parts, channel));
+
+	let remote = peer.get().map(|peer| access.config().client_address(peer.ip(), &headers));
+	if let Err(rejected) = access.charge(&message, connection.as_deref(), remote) {
+		return Ok(Response::builder()
+			.status(StatusCode::TOO_MANY_REQUESTS)
+			.header(header::CONTENT_TYPE, "application/json")
+			.body(Body::from(rejected))
+			.expect("status and header are valid; qed"))
+	}
+
+	// An empty channel accepts one chunk, and dropping the sender ends the body.
+	let _ = sender.try_send_data(body.into());
+	drop(sender);
+	response.await.map_err(Into::into)
+}
+
+/// Remote address of the connection a request was received on.
+///
+/// Inserted in the request extensions by [`AccessService`] and filled in by [`AccessLogger`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PeerAddr(Arc<Mutex<Option<SocketAddr>>>);
+
+impl PeerAddr {
+	fn set(&self, addr: SocketAddr) {
+		*self.0.lock() = Some(addr);
+	}
+
+	pub(crate) fn get(&self) -> Option<SocketAddr> {
+		*self.0.lock()
+	}
+}
+
+/// Server logger reporting the peer address of requests to [`AccessLayer`].
+///
+/// Everything is forwarded to the metrics, if any.
+#[derive(Debug, Clone)]
+pub struct AccessLogger {
+	metrics: Option<RpcMetrics>,
+}
+
+impl AccessLogger {
+	/// Create a new logger.
+	pub fn new(metrics: Option<RpcMetrics>) -> Self {
+		Self { metrics }
+	}
+}
+
+impl Logger for AccessLogger {
+	type Instant = Instant;
+
+	fn on_connect(&self, remote_addr: SocketAddr, request: &HttpRequest, t: TransportProtocol) {
+		if let Some(peer) = request.extensions().get::<PeerAddr>() {
+			peer.set(remote_addr);
+		}
+		if let Some(metrics) = &self.metrics {
+			metrics.on_connect(remote_addr, request, t);
+		}
+	}
+
+	fn on_request(&self, transport: TransportProtocol) -> Self::Instant {
+		self.metrics
+			.as_ref()
+			.map_or_else(Instant::now, |metrics| metrics.on_request(transport))
+	}
+
+	fn on_call(&self, name: &str, params: Params, kind: MethodKind, transport: TransportProtocol) {
+		if let Some(metrics) = &self.metrics {
+			metrics.on_call(name, params, kind, transport);
+		}
+	}
+
+	fn on_result(
+		&self,
+		name: &str,
+		success: bool,
+		started_at: Self::Instant,
+		transport: TransportProtocol,
+	) {
+		if let Some(metrics) = &self.metrics {
+			metrics.on_result(name, success, started_at, transport);
+		}
+	}
+
+	fn on_response(&self, result: &str, started_at: Self::Instant, transport: TransportProtocol) {
+		if let Some(metrics) = &self.metrics {
+			metrics.on_response(result, started_at, transport);
+		}
+	}
+
+	fn on_disconnect(&self, remote_addr: SocketAddr, transport: TransportProtocol) {
+		if let Some(metrics) = &self.metrics {
+			metrics.on_disconnect(remote_addr, transport);
+		}
+	}
+}
+
+/// Buffer the request body, `None` if it is larger than `max_size` bytes.
+async fn read_body(mut body: Body, max_size: u32) -> Result<Option<Vec<u8>>, hyper::Error> {
+	let mut bytes = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = chunk?;
+		if bytes.len() + chunk.len() > max_size as usize {
+			return Ok(None)
+		}
+		bytes.extend_from_slice(&chunk);
+	}
+	Ok(Some(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::middleware::RateLimit;
+
+	fn access(filter: MethodFilter, rate_limit: RateLimitConfig) -> Access {
+		Access {
+			filter,
+			limiter: RateLimiter::new(rate_limit),
+			max_request_body_size: 1024,
+			metrics: None,
+		}
+	}
+
+	#[test]
+	fn message_cost_sums_batches() {
+		let config = RateLimitConfig {
+			method_costs: [("state_call".to_owned(), 3)].into_iter().collect(),
+			..Default::default()
+		};
+
+		let single = br#"{"jsonrpc":"2.0","id":1,"method":"state_call","params":[]}"#;
+		assert_eq!(Message::parse(single).cost(&config), 3);
+
+		let batch = br#"[
+			{"jsonrpc":"2.0","id":1,"method":"state_call","params":[]},
+			{"jsonrpc":"2.0","id":2,"method":"system_health","params":[]}
+		]"#;
+		assert_eq!(Message::parse(batch).cost(&config), 4);
+
+		assert_eq!(Message::parse(b"[]").cost(&config), DEFAULT_CALL_COST);
+		assert_eq!(Message::parse(b"not json").cost(&config), DEFAULT_CALL_COST);
+	}
+
+	#[test]
+	fn calls_to_hidden_methods_are_renamed() {
+		let filter = MethodFilter { allow: None, deny: vec!["author_rotateKeys".into()] };
+		let access = access(filter, Default::default());
+
+		let mut allowed = Message::parse(br#"{"jsonrpc":"2.0","id":1,"method":"system_health"}"#);
+		assert_eq!(access.filter(&mut allowed), None);
+
+		let mut batch = Message::parse(
+			br#"[
+				{"jsonrpc":"2.0","id":1,"method":"author_rotateKeys"},
+				{"jsonrpc":"2.0","id":2,"method":"rpc_methods"}
+			]"#,
+		);
+		let filtered: Value = serde_json::from_slice(&access.filter(&mut batch).unwrap()).unwrap();
+		assert_eq!(filtered[0]["method"], DENIED_METHOD);
+		assert_eq!(filtered[0]["id"], 1);
+		assert_eq!(filtered[1]["method"], RPC_METHODS);
+	}
+
+	#[test]
+	fn rate_limited_calls_are_answered_with_their_id() {
+		let access = access(
+			Default::default(),
+			RateLimitConfig {
+				per_connection: Some(RateLimit { burst: 2, per_second: 0 }),
+				..Default::default()
+			},
+		);
+		let connection = access.limiter.connection_bucket();
+
+		let batch = Message::parse(
+			br#"[
+				{"jsonrpc":"2.0","id":1,"method":"system_health"},
+				{"jsonrpc":"2.0","id":"a","method":"system_name"}
+			]"#,
+		);
+		assert_eq!(access.charge(&batch, connection.as_ref(), None), Ok(()));
+
+		let rejected: Value =
+			serde_json::from_str(&access.charge(&batch, connection.as_ref(), None).unwrap_err())
+				.unwrap();
+		assert_eq!(rejected[0]["id"], 1);
+		assert_eq!(rejected[1]["id"], "a");
+		assert_eq!(rejected[1]["error"]["code"], RATE_LIMIT_ERROR_CODE);
+	}
+}
@@ -16,7 +16,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-//! RPC middleware to collect prometheus metrics on RPC calls.
+//! Prometheus metrics on RPC calls.
 
 use jsonrpsee::server::logger::{HttpRequest, Logger, MethodKind, Params, TransportProtocol};
 use prometheus_endpoint::{
@@ -54,6 +54,8 @@ pub struct RpcMetrics {
 	calls_started: CounterVec<U64>,
 	/// Number of calls completed.
 	calls_finished: CounterVec<U64>,
+	/// Number of calls rejected by the rate limiter or the method filter.
+	calls_rejected: CounterVec<U64>,
 	/// Number of Websocket sessions opened.
 	ws_sessions_opened: Option<Counter<U64>>,
 	/// Number of Websocket sessions closed.
@@ -116,6 +118,16 @@ impl RpcMetrics {
 					)?,
 					metrics_registry,
 				)?,
+				calls_rejected: register(
+					CounterVec::new(
+						Opts::new(
+							"substrate_rpc_calls_rejected",
+							"Number of RPC calls rejected before being processed",
+						),
+						&["reason"],
+					)?,
+					metrics_registry,
+				)?,
 				ws_sessions_opened: register(
 					Counter::new(
 						"substrate_rpc_sessions_opened",
@@ -137,6 +149,32 @@ impl RpcMetrics {
 			Ok(None)
 		}
 	}
+
+	/// Report a call rejected for the given reason.
+	pub(crate) fn on_rejected(&self, reason: RejectReason) {
+		self.calls_rejected.with_label_values(&[reason.as_str()]).inc();
+	}
+}
+
+/// Why a call was rejected before reaching its handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RejectReason {
+	/// The connection ran out of rate limit tokens.
+	ConnectionRateLimit,
+	/// The remote address ran out of rate limit tokens.
+	IpRateLimit,
+	/// The method is not exposed by the node.
+	Denied,
+}
+
+impl RejectReason {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Self::ConnectionRateLimit => "connection_rate_limit",
+			Self::IpRateLimit => "ip_rate_limit",
+			Self::Denied => "denied",
+		}
+	}
 }
 
 impl Logger for RpcMetrics {
@@ -0,0 +1,33 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! RPC middlewares.
+//!
+//! - [`RpcMetrics`] collects prometheus metrics on RPC calls.
+//! - [`AccessLayer`] hides the methods that are not exposed and throttles calls with token buckets
+//!   per connection and per remote address, on HTTP and WebSocket connections alike.
+
+mod access;
+mod metrics;
+mod rate_limit;
+mod relay;
+
+pub use access::{AccessLayer, AccessLogger};
+pub(crate) use metrics::RejectReason;
+pub use metrics::RpcMetrics;
+pub use rate_limit::{RateLimit, RateLimitConfig, RATE_LIMIT_ERROR_CODE};
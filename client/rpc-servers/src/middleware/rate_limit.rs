@@ -0,0 +1,340 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Token bucket rate limiting of RPC calls.
+//!
+//! Calls are charged to the bucket of the connection they are made on and to the bucket of the
+//! remote address of that connection. The `X-Forwarded-For` and `X-Real-IP` headers are only
+//! used to find the remote address when the peer is one of [`RateLimitConfig::trusted_proxies`].
+//! See [`super::AccessLayer`] for how calls are metered.
+
+use super::RejectReason;
+use http::HeaderMap;
+use lru::LruCache;
+use parking_lot::Mutex;
+use std::{collections::HashMap, net::IpAddr, num::NonZeroUsize, time::Instant};
+
+/// Cost of a call to a method without a configured cost.
+pub const DEFAULT_CALL_COST: u32 = 1;
+
+/// JSON-RPC error code returned when a call is rate limited.
+pub const RATE_LIMIT_ERROR_CODE: i32 = -32029;
+
+/// Number of remote addresses whose bucket is kept.
+const MAX_TRACKED_ADDRESSES: usize = 10_000;
+
+/// Headers carrying the client address when the server runs behind a reverse proxy.
+const FORWARDED_HEADERS: [&str; 2] = ["x-forwarded-for", "x-real-ip"];
+
+/// Token bucket parameters.
+///
+/// A request costing more than `burst` is always rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+	/// Maximum number of tokens the bucket holds, must not be zero.
+	pub burst: u32,
+	/// Number of tokens added to the bucket every second.
+	pub per_second: u32,
+}
+
+/// Rate limiting configuration of the RPC servers.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+	/// Limit applied to every HTTP or WebSocket connection.
+	pub per_connection: Option<RateLimit>,
+	/// Limit applied to every remote address, shared by all its connections.
+	pub per_ip: Option<RateLimit>,
+	/// Cost of calling a method, [`DEFAULT_CALL_COST`] if not present.
+	pub method_costs: HashMap<String, u32>,
+	/// Reverse proxies trusted to report the client address in the `X-Forwarded-For` or
+	/// `X-Real-IP` header.
+	pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimitConfig {
+	/// Returns `true` if any limit is configured.
+	pub fn is_enabled(&self) -> bool {
+		self.per_connection.is_some() || self.per_ip.is_some()
+	}
+
+	/// Address whose bucket is charged for a request received from `peer`.
+	///
+	/// Every trusted proxy appends the address it received the request from to
+	/// `X-Forwarded-For`, so the chain is walked back from the peer until an address that is not
+	/// a trusted proxy is found.
+	pub(crate) fn client_address(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+		let mut chain = forwarded_addresses(headers).into_iter().rev();
+		let mut client = peer;
+		while self.trusted_proxies.contains(&client) {
+			match chain.next() {
+				Some(addr) => client = addr,
+				None => break,
+			}
+		}
+		client
+	}
+
+	pub(crate) fn method_cost(&self, method: &str) -> u32 {
+		self.method_costs.get(method).copied().unwrap_or(DEFAULT_CALL_COST)
+	}
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+	limit: RateLimit,
+	tokens: f64,
+	updated_at: Instant,
+}
+
+impl TokenBucket {
+	fn new(limit: RateLimit, now: Instant) -> Self {
+		Self { limit, tokens: limit.burst as f64, updated_at: now }
+	}
+
+	/// Add the tokens accumulated since the last update.
+	fn refill(&mut self, now: Instant) {
+		let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+		self.tokens =
+			(self.tokens + elapsed * self.limit.per_second as f64).min(self.limit.burst as f64);
+		self.updated_at = now;
+	}
+
+	fn has(&self, cost: u32) -> bool {
+		self.tokens >= cost as f64
+	}
+
+	fn take(&mut self, cost: u32) {
+		self.tokens -= cost as f64;
+	}
+}
+
+/// Buckets of the connections and remote addresses.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+	config: RateLimitConfig,
+	/// Buckets of the most recently charged remote addresses.
+	///
+	/// Dropping the bucket of an address refills it, so the addresses that were charged the
+	/// longest time ago, which are the most likely to have a full bucket anyway, are dropped
+	/// first.
+	per_ip: Mutex<LruCache<IpAddr, TokenBucket>>,
+	/// Bucket shared by the requests whose remote address is not known.
+	unknown_ip: Option<Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+	pub(crate) fn new(config: RateLimitConfig) -> Self {
+		Self::with_capacity(config, MAX_TRACKED_ADDRESSES)
+	}
+
+	fn with_capacity(config: RateLimitConfig, max_tracked_addresses: usize) -> Self {
+		let capacity = NonZeroUsize::new(max_tracked_addresses)
+			.expect("the number of tracked addresses is not zero; qed");
+		let unknown_ip =
+			config.per_ip.map(|limit| Mutex::new(TokenBucket::new(limit, Instant::now())));
+
+		Self { config, per_ip: Mutex::new(LruCache::new(capacity)), unknown_ip }
+	}
+
+	pub(crate) fn config(&self) -> &RateLimitConfig {
+		&self.config
+	}
+
+	/// Bucket of a new connection, `None` if connections are not limited.
+	pub(crate) fn connection_bucket(&self) -> Option<Mutex<TokenBucket>> {
+		self.config
+			.per_connection
+			.map(|limit| Mutex::new(TokenBucket::new(limit, Instant::now())))
+	}
+
+	/// Charge `cost` to the connection and remote address buckets.
+	///
+	/// Tokens are only taken if both buckets can afford the request. Requests whose remote
+	/// address is not known are all charged to the same bucket.
+	pub(crate) fn try_acquire(
+		&self,
+		connection: Option<&Mutex<TokenBucket>>,
+		remote: Option<IpAddr>,
+		cost: u32,
+	) -> Result<(), RejectReason> {
+		let now = Instant::now();
+
+		let mut connection = connection.map(|bucket| bucket.lock());
+		if let Some(bucket) = connection.as_mut() {
+			bucket.refill(now);
+			if !bucket.has(cost) {
+				return Err(RejectReason::ConnectionRateLimit)
+			}
+		}
+
+		if let Some(limit) = self.config.per_ip {
+			let mut per_ip;
+			let mut unknown_ip;
+			let bucket = match remote {
+				Some(remote) => {
+					per_ip = self.per_ip.lock();
+					per_ip.get_or_insert_mut(remote, || TokenBucket::new(limit, now))
+				},
+				None => {
+					unknown_ip = self
+						.unknown_ip
+						.as_ref()
+						.expect("the bucket is created when `per_ip` is set; qed")
+						.lock();
+					&mut *unknown_ip
+				},
+			};
+
+			bucket.refill(now);
+			if !bucket.has(cost) {
+				return Err(RejectReason::IpRateLimit)
+			}
+			bucket.take(cost);
+		}
+
+		if let Some(bucket) = connection.as_mut() {
+			bucket.take(cost);
+		}
+
+		Ok(())
+	}
+}
+
+/// Addresses listed by the forwarding headers, closest to the client first.
+///
+/// Parsing stops at the first entry that is not an IP address.
+fn forwarded_addresses(headers: &HeaderMap) -> Vec<IpAddr> {
+	FORWARDED_HEADERS
+		.iter()
+		.find_map(|name| headers.get(*name))
+		.and_then(|value| value.to_str().ok())
+		.map(|value| value.split(',').map_while(|addr| addr.trim().parse().ok()).collect())
+		.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	const LIMIT: RateLimit = RateLimit { burst: 4, per_second: 2 };
+
+	#[test]
+	fn token_bucket_refills_up_to_burst() {
+		let start = Instant::now();
+		let mut bucket = TokenBucket::new(LIMIT, start);
+
+		assert!(bucket.has(4));
+		bucket.take(4);
+		assert!(!bucket.has(1));
+
+		bucket.refill(start + Duration::from_millis(500));
+		assert!(bucket.has(1));
+		assert!(!bucket.has(2));
+
+		bucket.refill(start + Duration::from_secs(10));
+		assert!(bucket.has(4));
+		assert!(!bucket.has(5));
+	}
+
+	#[test]
+	fn tokens_are_only_taken_when_all_buckets_allow() {
+		let limiter = RateLimiter::new(RateLimitConfig {
+			per_connection: Some(RateLimit { burst: 2, per_second: 0 }),
+			per_ip: Some(RateLimit { burst: 3, per_second: 0 }),
+			..Default::default()
+		});
+		let first = limiter.connection_bucket().unwrap();
+		let second = limiter.connection_bucket().unwrap();
+
+		let a = Some(IpAddr::from([10, 0, 0, 1]));
+		let b = Some(IpAddr::from([10, 0, 0, 2]));
+
+		assert_eq!(limiter.try_acquire(Some(&first), a, 2), Ok(()));
+		assert_eq!(limiter.try_acquire(Some(&first), a, 1), Err(RejectReason::ConnectionRateLimit));
+
+		// The address bucket still holds the token the connection could not spend.
+		assert_eq!(limiter.try_acquire(Some(&second), a, 1), Ok(()));
+		assert_eq!(limiter.try_acquire(Some(&second), a, 1), Err(RejectReason::IpRateLimit));
+
+		// Other addresses are not affected.
+		assert_eq!(limiter.try_acquire(None, b, 3), Ok(()));
+	}
+
+	#[test]
+	fn unknown_addresses_share_a_bucket() {
+		let limiter = RateLimiter::new(RateLimitConfig {
+			per_ip: Some(RateLimit { burst: 2, per_second: 0 }),
+			..Default::default()
+		});
+
+		assert_eq!(limiter.try_acquire(None, None, 1), Ok(()));
+		assert_eq!(limiter.try_acquire(None, None, 1), Ok(()));
+		assert_eq!(limiter.try_acquire(None, None, 1), Err(RejectReason::IpRateLimit));
+
+		// Known addresses have their own bucket.
+		assert_eq!(limiter.try_acquire(None, Some(IpAddr::from([10, 0, 0, 1])), 2), Ok(()));
+	}
+
+	#[test]
+	fn address_buckets_are_bounded_under_churn() {
+		let limiter = RateLimiter::with_capacity(
+			RateLimitConfig {
+				per_ip: Some(RateLimit { burst: 1, per_second: 0 }),
+				..Default::default()
+			},
+			8,
+		);
+		let busy = Some(IpAddr::from([192, 0, 2, 1]));
+		assert_eq!(limiter.try_acquire(None, busy, 1), Ok(()));
+
+		for i in 0..1_000u32 {
+			let churn = Some(IpAddr::from((0x0a00_0000 + i).to_be_bytes()));
+			assert_eq!(limiter.try_acquire(None, churn, 1), Ok(()));
+			// Keep the busy address among the most recently used ones.
+			assert_eq!(limiter.try_acquire(None, busy, 1), Err(RejectReason::IpRateLimit));
+		}
+
+		assert_eq!(limiter.per_ip.lock().len(), 8);
+
+		// The first churning addresses were dropped, and get a full bucket back.
+		let first = Some(IpAddr::from([10, 0, 0, 0]));
+		assert_eq!(limiter.try_acquire(None, first, 1), Ok(()));
+	}
+
+	#[test]
+	fn forwarded_headers_are_only_trusted_from_proxies() {
+		let proxy = IpAddr::from([10, 0, 0, 1]);
+		let client = IpAddr::from([192, 0, 2, 7]);
+		let config = RateLimitConfig { trusted_proxies: vec![proxy], ..Default::default() };
+
+		let mut headers = HeaderMap::new();
+		headers.insert("x-forwarded-for", "198.51.100.1, 192.0.2.7".parse().unwrap());
+
+		// The client can prepend any address, only the one appended by the proxy is used.
+		assert_eq!(config.client_address(proxy, &headers), client);
+		// Headers sent by anything but a trusted proxy are ignored.
+		assert_eq!(config.client_address(client, &headers), client);
+		// A trusted proxy not reporting the client is charged itself.
+		assert_eq!(config.client_address(proxy, &HeaderMap::new()), proxy);
+
+		let mut headers = HeaderMap::new();
+		headers.insert("x-real-ip", "192.0.2.7".parse().unwrap());
+		assert_eq!(config.client_address(proxy, &headers), client);
+	}
+}
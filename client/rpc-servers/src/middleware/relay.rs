@@ -0,0 +1,219 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! WebSocket connections accepted by [`AccessLayer`](super::AccessLayer).
+//!
+//! Once a connection is upgraded, the server handles its messages in a task of its own, out of
+//! reach of its middlewares. The layer thus completes the handshake with the client itself and
+//! opens a second WebSocket connection to the server over an in-memory stream. Messages received
+//! from the client are filtered and charged before being relayed to the server, messages of the
+//! server are relayed to the client as they are.
+
+use super::{
+	access::{Access, Message, PeerAddr},
+	rate_limit::TokenBucket,
+};
+use futures::{
+	future,
+	io::{AsyncRead, AsyncWrite, BufReader, BufWriter},
+	lock::Mutex as AsyncMutex,
+	pin_mut,
+};
+use http::{header, HeaderName};
+use hyper::{server::conn::Http, service::service_fn, Body, Request, Response};
+use parking_lot::Mutex;
+use soketto::{
+	connection::Error as WsError,
+	handshake::{client::Header, http::Server, Client, ServerResponse},
+	Data, Receiver, Sender,
+};
+use std::{error::Error as StdError, net::IpAddr, sync::Arc};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+use tower::Service;
+
+/// Capacity of the in-memory stream between the relay and the server.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Accept a WebSocket upgrade request and relay the messages of the connection to `inner`.
+pub(crate) async fn accept<S>(
+	mut req: Request<Body>,
+	mut inner: S,
+	access: Arc<Access>,
+	connection: Option<Arc<Mutex<TokenBucket>>>,
+) -> Result<Response<Body>, Box<dyn StdError + Send + Sync>>
+where
+	S: Service<Request<Body>, Response = Response<Body>> + Send + 'static,
+	S::Error: Into<Box<dyn StdError + Send + Sync>> + 'static,
+	S::Future: Send + 'static,
+{
+	let mut handshake = Server::new();
+	let response = match handshake.receive_request(&req) {
+		Ok(response) => response,
+		// Let the server answer invalid handshakes.
+		Err(_) => return inner.call(req).await.map_err(Into::into),
+	};
+	let on_upgrade = hyper::upgrade::on(&mut req);
+
+	let peer = PeerAddr::default();
+	let (relay_io, server_io) = tokio::io::duplex(BUFFER_SIZE);
+	let service = {
+		let peer = peer.clone();
+		service_fn(move |mut req: Request<Body>| {
+			req.extensions_mut().insert(peer.clone());
+			inner.call(req)
+		})
+	};
+	tokio::spawn(async move {
+		let conn = Http::new().serve_connection(server_io, service).with_upgrades();
+		if let Err(e) = conn.await {
+			log::debug!(target: "rpc", "In-memory RPC connection failed: {}", e);
+		}
+	});
+
+	// The server checks the host and reports the peer to its logger during the handshake.
+	let headers = req.headers();
+	let host = headers
+		.get(header::HOST)
+		.and_then(|host| host.to_str().ok())
+		.unwrap_or_default();
+	let resource = req.uri().path_and_query().map_or("/", |path| path.as_str());
+	let forwarded = headers
+		.iter()
+		.filter(|(name, _)| is_forwarded(name))
+		.map(|(name, value)| Header { name: name.as_str(), value: value.as_bytes() })
+		.collect::<Vec<_>>();
+	let mut connect =
+		Client::new(BufReader::new(BufWriter::new(relay_io.compat())), host, resource);
+	connect.set_headers(&forwarded);
+	match connect.handshake().await? {
+		ServerResponse::Accepted { .. } => {},
+		ServerResponse::Redirect { status_code, .. } | ServerResponse::Rejected { status_code } =>
+			return Ok(Response::builder().status(status_code).body(Body::empty())?),
+	}
+	let to_server = connect.into_builder().finish();
+	let remote = peer.get().map(|peer| access.config().client_address(peer.ip(), headers));
+
+	tokio::spawn(async move {
+		let upgraded = match on_upgrade.await {
+			Ok(upgraded) => upgraded,
+			Err(e) => {
+				log::debug!(target: "rpc", "Could not upgrade RPC connection: {}", e);
+				return
+			},
+		};
+
+		let mut builder = handshake.into_builder(BufReader::new(BufWriter::new(upgraded.compat())));
+		builder.set_max_message_size(access.max_request_body_size() as usize);
+		relay(builder.finish(), to_server, &access, connection.as_deref(), remote).await;
+	});
+
+	Ok(response.map(|()| Body::empty()))
+}
+
+/// Headers of the client handshake request that are passed on to the server.
+///
+/// The others are set by the relay itself.
+fn is_forwarded(name: &HeaderName) -> bool {
+	name != header::HOST &&
+		name != header::CONNECTION &&
+		name != header::UPGRADE &&
+		!name.as_str().starts_with("sec-websocket-")
+}
+
+/// Relay messages between the client and the server until either of them closes the connection.
+async fn relay<C, S>(
+	(client_tx, mut client_rx): (Sender<C>, Receiver<C>),
+	(mut server_tx, mut server_rx): (Sender<S>, Receiver<S>),
+	access: &Access,
+	connection: Option<&Mutex<TokenBucket>>,
+	remote: Option<IpAddr>,
+) where
+	C: AsyncRead + AsyncWrite + Unpin,
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	// Both directions write to the client: responses of the server and errors of the relay.
+	let client_tx = AsyncMutex::new(client_tx);
+
+	let from_client = async {
+		let mut message = Vec::new();
+		loop {
+			message.clear();
+			let data = match client_rx.receive_data(&mut message).await {
+				Ok(data) => data,
+				Err(WsError::MessageTooLarge { .. }) => {
+					if reply(&client_tx, &access.too_large()).await.is_err() {
+						break
+					}
+					continue
+				},
+				Err(_) => break,
+			};
+
+			let mut calls = Message::parse(&message);
+			if let Some(filtered) = access.filter(&mut calls) {
+				message = filtered;
+			}
+			let sent = match access.charge(&calls, connection, remote) {
+				Ok(()) => send(&mut server_tx, &data, &message).await,
+				Err(rejected) => reply(&client_tx, &rejected).await,
+			};
+			if sent.is_err() {
+				break
+			}
+		}
+		let _ = server_tx.close().await;
+	};
+
+	let from_server = async {
+		let mut message = Vec::new();
+		loop {
+			message.clear();
+			let Ok(data) = server_rx.receive_data(&mut message).await else { break };
+			if send(&mut *client_tx.lock().await, &data, &message).await.is_err() {
+				break
+			}
+		}
+	};
+
+	pin_mut!(from_client, from_server);
+	future::select(from_client, from_server).await;
+	let _ = client_tx.lock().await.close().await;
+}
+
+/// Answer the client instead of the server.
+async fn reply<T: AsyncRead + AsyncWrite + Unpin>(
+	sender: &AsyncMutex<Sender<T>>,
+	response: &str,
+) -> Result<(), WsError> {
+	let mut sender = sender.lock().await;
+	sender.send_text(response).await?;
+	sender.flush().await
+}
+
+/// Send a message of the same kind as `data`.
+async fn send<T: AsyncRead + AsyncWrite + Unpin>(
+	sender: &mut Sender<T>,
+	data: &Data,
+	message: &[u8],
+) -> Result<(), WsError> {
+	match data {
+		Data::Text(_) => sender.send_text(std::str::from_utf8(message)?).await?,
+		Data::Binary(_) => sender.send_binary(message).await?,
+	}
+	sender.flush().await
+}
@@ -0,0 +1,352 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! API implementation for `archive`.
+
+use crate::{
+	archive::{
+		api::ArchiveApiServer,
+		archive_storage::{ArchiveStorage, ArchiveStorageDiff},
+		error::Error as ArchiveError,
+		event::{
+			ArchiveStorageDiffItem, ArchiveStorageResult, MethodResult, PaginatedStorageQuery,
+		},
+	},
+	SubscriptionTaskExecutor,
+};
+use codec::Encode;
+use futures::future::FutureExt;
+use jsonrpsee::{
+	core::RpcResult,
+	types::{SubscriptionEmptyError, SubscriptionResult},
+	SubscriptionSink,
+};
+use sc_client_api::{
+	Backend, BlockBackend, CallExecutor, ChildInfo, ExecutorProvider, StorageKey, StorageProvider,
+};
+use sp_blockchain::{
+	Backend as BlockChainBackend, Error as BlockChainError, HeaderBackend, HeaderMetadata,
+};
+use sp_core::{hexdisplay::HexDisplay, traits::CallContext, Bytes};
+use sp_runtime::{
+	traits::{Block as BlockT, NumberFor},
+	SaturatedConversion,
+};
+use std::{collections::HashSet, marker::PhantomData, sync::Arc};
+
+/// The configuration of [`Archive`].
+#[derive(Debug, Clone)]
+pub struct ArchiveConfig {
+	/// The maximum number of items the `archive_unstable_storage` can return for a descendant
+	/// query before pagination is required.
+	pub max_descendant_responses: usize,
+	/// The maximum number of queried items allowed for the `archive_unstable_storage` at a time.
+	pub max_queried_items: usize,
+}
+
+/// The maximum number of items the `archive_unstable_storage` can return for a descendant
+/// query before pagination is required.
+const MAX_DESCENDANT_RESPONSES: usize = 5;
+
+/// The maximum number of queried items allowed for the `archive_unstable_storage` at a time.
+const MAX_QUERIED_ITEMS: usize = 8;
+
+impl Default for ArchiveConfig {
+	fn default() -> Self {
+		Self {
+			max_descendant_responses: MAX_DESCENDANT_RESPONSES,
+			max_queried_items: MAX_QUERIED_ITEMS,
+		}
+	}
+}
+
+/// An API for archive RPC calls.
+pub struct Archive<BE, Block: BlockT, Client> {
+	/// Substrate client.
+	client: Arc<Client>,
+	/// Backend of the chain.
+	backend: Arc<BE>,
+	/// Executor to spawn subscriptions.
+	executor: SubscriptionTaskExecutor,
+	/// The hexadecimal encoded hash of the genesis block.
+	genesis_hash: String,
+	/// The maximum number of items the `archive_unstable_storage` can return for a descendant
+	/// query before pagination is required.
+	storage_max_descendant_responses: usize,
+	/// The maximum number of queried items allowed for the `archive_unstable_storage` at a time.
+	storage_max_queried_items: usize,
+	/// Phantom member to pin the block type.
+	_phantom: PhantomData<Block>,
+}
+
+impl<BE, Block: BlockT, Client> Archive<BE, Block, Client> {
+	/// Create a new [`Archive`].
+	pub fn new<GenesisHash: AsRef<[u8]>>(
+		client: Arc<Client>,
+		backend: Arc<BE>,
+		executor: SubscriptionTaskExecutor,
+		genesis_hash: GenesisHash,
+		config: ArchiveConfig,
+	) -> Self {
+		let genesis_hash = format!("0x{:?}", HexDisplay::from(&genesis_hash.as_ref()));
+
+		Self {
+			client,
+			backend,
+			executor,
+			genesis_hash,
+			storage_max_descendant_responses: config.max_descendant_responses,
+			storage_max_queried_items: config.max_queried_items,
+			_phantom: PhantomData,
+		}
+	}
+}
+
+/// Parse hex-encoded string parameter as raw bytes.
+fn parse_hex_param(param: String) -> Result<Vec<u8>, ArchiveError> {
+	// Methods can accept empty parameters.
+	if param.is_empty() {
+		return Ok(Default::default())
+	}
+
+	array_bytes::hex2bytes(&param).map_err(|_| ArchiveError::InvalidParam(param))
+}
+
+/// Parse the hex-encoded keys of the `archive_unstable_storageDiff` items.
+fn parse_storage_diff_items(
+	items: Vec<ArchiveStorageDiffItem<String>>,
+) -> Result<Vec<ArchiveStorageDiffItem<StorageKey>>, ArchiveError> {
+	items
+		.into_iter()
+		.map(|item| {
+			Ok(ArchiveStorageDiffItem {
+				key: StorageKey(parse_hex_param(item.key)?),
+				return_type: item.return_type,
+				child_trie_key: item
+					.child_trie_key
+					.map(|key| parse_hex_param(key).map(StorageKey))
+					.transpose()?,
+			})
+		})
+		.collect()
+}
+
+impl<BE, Block, Client> Archive<BE, Block, Client>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: HeaderBackend<Block> + HeaderMetadata<Block, Error = BlockChainError> + 'static,
+{
+	/// Ensure the block is known to the node.
+	///
+	/// This distinguishes unknown blocks from blocks whose state was pruned.
+	fn ensure_block_exists(&self, hash: Block::Hash) -> Result<(), ArchiveError> {
+		match self.client.header(hash) {
+			Ok(Some(_)) => Ok(()),
+			Ok(None) => Err(ArchiveError::InvalidBlock),
+			Err(error) => Err(ArchiveError::FetchBlockData(error.to_string())),
+		}
+	}
+
+	/// Collect the hashes of the non-finalized blocks at the given height, on any fork.
+	fn non_finalized_hashes_at(
+		&self,
+		height: NumberFor<Block>,
+	) -> Result<HashSet<Block::Hash>, BlockChainError> {
+		let mut hashes = HashSet::new();
+
+		for leaf in self.backend.blockchain().leaves()? {
+			let mut current = self.client.header_metadata(leaf)?;
+			if current.number < height {
+				continue
+			}
+
+			// Walk the fork back to the requested height.
+			while current.number > height {
+				current = self.client.header_metadata(current.parent)?;
+			}
+
+			hashes.insert(current.hash);
+		}
+
+		Ok(hashes)
+	}
+}
+
+impl<BE, Block, Client> ArchiveApiServer<Block::Hash> for Archive<BE, Block, Client>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: BlockBackend<Block>
+		+ ExecutorProvider<Block>
+		+ HeaderBackend<Block>
+		+ HeaderMetadata<Block, Error = BlockChainError>
+		+ StorageProvider<Block, BE>
+		+ 'static,
+{
+	fn archive_unstable_body(&self, hash: Block::Hash) -> RpcResult<Option<Vec<String>>> {
+		let Some(signed_block) = self
+			.client
+			.block(hash)
+			.map_err(|error| ArchiveError::FetchBlockData(error.to_string()))?
+		else {
+			return Ok(None)
+		};
+
+		let extrinsics = signed_block
+			.block
+			.extrinsics()
+			.iter()
+			.map(|extrinsic| format!("0x{:?}", HexDisplay::from(&extrinsic.encode())))
+			.collect();
+
+		Ok(Some(extrinsics))
+	}
+
+	fn archive_unstable_genesis_hash(&self) -> RpcResult<String> {
+		Ok(self.genesis_hash.clone())
+	}
+
+	fn archive_unstable_header(&self, hash: Block::Hash) -> RpcResult<Option<String>> {
+		let Some(header) = self
+			.client
+			.header(hash)
+			.map_err(|error| ArchiveError::FetchBlockData(error.to_string()))?
+		else {
+			return Ok(None)
+		};
+
+		Ok(Some(format!("0x{:?}", HexDisplay::from(&header.encode()))))
+	}
+
+	fn archive_unstable_finalized_height(&self) -> RpcResult<u64> {
+		Ok(self.client.info().finalized_number.saturated_into())
+	}
+
+	fn archive_unstable_hash_by_height(&self, height: u64) -> RpcResult<Vec<String>> {
+		let height: NumberFor<Block> = height.saturated_into();
+		let finalized_number = self.client.info().finalized_number;
+
+		let hashes = if height <= finalized_number {
+			// The finalized chain contains at most one block at the given height.
+			self.client
+				.hash(height)
+				.map_err(|error| ArchiveError::FetchBlockData(error.to_string()))?
+				.into_iter()
+				.collect()
+		} else {
+			self.non_finalized_hashes_at(height)
+				.map_err(|error| ArchiveError::FetchBlockData(error.to_string()))?
+				.into_iter()
+				.collect::<Vec<_>>()
+		};
+
+		Ok(hashes
+			.into_iter()
+			.map(|hash| format!("0x{:?}", HexDisplay::from(&hash.as_ref())))
+			.collect())
+	}
+
+	fn archive_unstable_call(
+		&self,
+		hash: Block::Hash,
+		function: String,
+		call_parameters: String,
+	) -> RpcResult<MethodResult> {
+		let call_parameters = Bytes::from(parse_hex_param(call_parameters)?);
+		self.ensure_block_exists(hash)?;
+
+		let result = self.client.executor().call(
+			hash,
+			&function,
+			&call_parameters,
+			self.client.execution_extensions().strategies().other,
+			CallContext::Untrusted,
+		);
+
+		Ok(match result {
+			Ok(result) => MethodResult::ok(format!("0x{:?}", HexDisplay::from(&result))),
+			Err(error) => MethodResult::err(error.to_string()),
+		})
+	}
+
+	fn archive_unstable_storage(
+		&self,
+		hash: Block::Hash,
+		items: Vec<PaginatedStorageQuery<String>>,
+		child_trie: Option<String>,
+	) -> RpcResult<ArchiveStorageResult> {
+		let items = items
+			.into_iter()
+			.map(|query| {
+				let key = StorageKey(parse_hex_param(query.key)?);
+				let pagination_start_key = query
+					.pagination_start_key
+					.map(|key| parse_hex_param(key).map(StorageKey))
+					.transpose()?;
+
+				Ok(PaginatedStorageQuery {
+					key,
+					query_type: query.query_type,
+					pagination_start_key,
+				})
+			})
+			.collect::<Result<Vec<_>, ArchiveError>>()?;
+
+		let child_trie = child_trie
+			.map(parse_hex_param)
+			.transpose()?
+			.map(ChildInfo::new_default_from_vec);
+
+		self.ensure_block_exists(hash)?;
+
+		let storage_client = ArchiveStorage::new(
+			self.client.clone(),
+			self.storage_max_descendant_responses,
+			self.storage_max_queried_items,
+		);
+
+		Ok(storage_client.handle_query(hash, items, child_trie)?)
+	}
+
+	fn archive_unstable_storage_diff(
+		&self,
+		mut sink: SubscriptionSink,
+		hash: Block::Hash,
+		items: Vec<ArchiveStorageDiffItem<String>>,
+		previous_hash: Option<Block::Hash>,
+	) -> SubscriptionResult {
+		let items = match parse_storage_diff_items(items) {
+			Ok(items) => items,
+			Err(error) => {
+				let _ = sink.reject(error);
+				return Err(SubscriptionEmptyError)
+			},
+		};
+
+		let storage_diff = ArchiveStorageDiff::new(self.client.clone());
+		let fut = async move {
+			// Walking the tries reads from the database, keep it off the async executor.
+			storage_diff.handle_trie_queries(hash, previous_hash, items, sink);
+		};
+
+		self.executor
+			.spawn_blocking("substrate-rpc-subscription", Some("rpc"), fut.boxed());
+		Ok(())
+	}
+}
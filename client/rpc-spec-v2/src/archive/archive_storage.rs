@@ -0,0 +1,416 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Implementation of the `archive_unstable_storage` and `archive_unstable_storageDiff`
+//! methods.
+
+use crate::archive::{
+	error::Error as ArchiveError,
+	event::{
+		ArchiveStorageDiffError, ArchiveStorageDiffEvent, ArchiveStorageDiffItem,
+		ArchiveStorageDiffOperationType, ArchiveStorageDiffResult, ArchiveStorageDiffResultType,
+		ArchiveStorageResult, PaginatedStorageQuery, StorageQueryType, StorageResult,
+		StorageResultType,
+	},
+};
+use jsonrpsee::SubscriptionSink;
+use sc_client_api::{
+	Backend, ChildInfo, MerkleValue, StorageKey, StorageProvider, TrieDiff, TrieDiffKind,
+	TrieDiffValue,
+};
+use sp_blockchain::{Error as BlockChainError, HeaderBackend};
+use sp_core::hexdisplay::HexDisplay;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+use std::{marker::PhantomData, sync::Arc};
+
+/// Format the provided bytes as a hex string prefixed with `0x`.
+fn hex_string<Data: AsRef<[u8]>>(data: &Data) -> String {
+	format!("0x{:?}", HexDisplay::from(&data.as_ref()))
+}
+
+/// Map the errors produced while reading the state of a block.
+///
+/// The existence of the block is checked before the state is accessed, such that an
+/// unknown block reported by the backend means the state was discarded by the pruning.
+fn map_state_error(error: BlockChainError) -> ArchiveError {
+	match error {
+		BlockChainError::UnknownBlock(msg) => ArchiveError::StateUnavailable(msg),
+		error => ArchiveError::FetchBlockData(error.to_string()),
+	}
+}
+
+/// Generates the events of the `archive_unstable_storage` method.
+pub struct ArchiveStorage<Client, Block, BE> {
+	/// Substrate client.
+	client: Arc<Client>,
+	/// The maximum number of descendant results returned by one call.
+	max_descendant_responses: usize,
+	/// The maximum number of queried items processed by one call.
+	max_queried_items: usize,
+	_phandom: PhantomData<(BE, Block)>,
+}
+
+impl<Client, Block, BE> ArchiveStorage<Client, Block, BE> {
+	/// Constructs a new [`ArchiveStorage`].
+	pub fn new(
+		client: Arc<Client>,
+		max_descendant_responses: usize,
+		max_queried_items: usize,
+	) -> Self {
+		Self { client, max_descendant_responses, max_queried_items, _phandom: PhantomData }
+	}
+}
+
+impl<Client, Block, BE> ArchiveStorage<Client, Block, BE>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: StorageProvider<Block, BE> + 'static,
+{
+	/// Fetch the value from storage.
+	fn query_storage_value(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+		child_key: Option<&ChildInfo>,
+	) -> Result<Option<StorageResult>, ArchiveError> {
+		let result = if let Some(child_key) = child_key {
+			self.client.child_storage(hash, child_key, key)
+		} else {
+			self.client.storage(hash, key)
+		};
+
+		result.map_err(map_state_error).map(|opt| {
+			opt.map(|storage_data| StorageResult {
+				key: hex_string(&key.0),
+				result: StorageResultType::Value(hex_string(&storage_data.0)),
+			})
+		})
+	}
+
+	/// Fetch the hash of a value from storage.
+	fn query_storage_hash(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+		child_key: Option<&ChildInfo>,
+	) -> Result<Option<StorageResult>, ArchiveError> {
+		let result = if let Some(child_key) = child_key {
+			self.client.child_storage_hash(hash, child_key, key)
+		} else {
+			self.client.storage_hash(hash, key)
+		};
+
+		result.map_err(map_state_error).map(|opt| {
+			opt.map(|storage_hash| StorageResult {
+				key: hex_string(&key.0),
+				result: StorageResultType::Hash(hex_string(&storage_hash.as_ref())),
+			})
+		})
+	}
+
+	/// Fetch the closest descendant merkle value.
+	fn query_storage_merkle_value(
+		&self,
+		hash: Block::Hash,
+		key: &StorageKey,
+		child_key: Option<&ChildInfo>,
+	) -> Result<Option<StorageResult>, ArchiveError> {
+		let result = if let Some(child_key) = child_key {
+			self.client.child_closest_merkle_value(hash, child_key, key)
+		} else {
+			self.client.closest_merkle_value(hash, key)
+		};
+
+		result.map_err(map_state_error).map(|opt| {
+			opt.map(|merkle_value| {
+				let merkle_value = match merkle_value {
+					MerkleValue::Node(data) => hex_string(&data),
+					MerkleValue::Hash(hash) => hex_string(&hash.as_ref()),
+				};
+
+				StorageResult {
+					key: hex_string(&key.0),
+					result: StorageResultType::ClosestDescendantMerkleValue(merkle_value),
+				}
+			})
+		})
+	}
+
+	/// Iterate over the descendants of the provided key, collecting at most `limit` results.
+	///
+	/// Returns `true` if the iteration was completed.
+	fn query_storage_descendants(
+		&self,
+		hash: Block::Hash,
+		query: &PaginatedStorageQuery<StorageKey>,
+		child_key: Option<&ChildInfo>,
+		limit: usize,
+		results: &mut Vec<StorageResult>,
+	) -> Result<bool, ArchiveError> {
+		let keys_iter = if let Some(child_key) = child_key {
+			self.client.child_storage_keys(
+				hash,
+				child_key.clone(),
+				Some(&query.key),
+				query.pagination_start_key.as_ref(),
+			)
+		} else {
+			self.client
+				.storage_keys(hash, Some(&query.key), query.pagination_start_key.as_ref())
+		}
+		.map_err(map_state_error)?;
+
+		let mut collected = 0;
+		for key in keys_iter {
+			if collected == limit {
+				return Ok(false)
+			}
+
+			let result = match query.query_type {
+				StorageQueryType::DescendantsValues =>
+					self.query_storage_value(hash, &key, child_key)?,
+				_ => self.query_storage_hash(hash, &key, child_key)?,
+			};
+
+			if let Some(result) = result {
+				results.push(result);
+				collected += 1;
+			}
+		}
+
+		Ok(true)
+	}
+
+	/// Handle the queries of one `archive_unstable_storage` call.
+	///
+	/// At most `max_queried_items` queries are processed, and the descendant queries produce at
+	/// most `max_descendant_responses` results in total. A descendant query interrupted by the
+	/// limit is reported as discarded together with the queries following it; the caller can
+	/// resume it by providing the last returned key as `paginationStartKey`.
+	pub fn handle_query(
+		&self,
+		hash: Block::Hash,
+		mut items: Vec<PaginatedStorageQuery<StorageKey>>,
+		child_key: Option<ChildInfo>,
+	) -> Result<ArchiveStorageResult, ArchiveError> {
+		let discarded_items = items.len().saturating_sub(self.max_queried_items);
+		items.truncate(self.max_queried_items);
+
+		let mut result = Vec::with_capacity(items.len());
+		let mut remaining_descendants = self.max_descendant_responses;
+
+		for (index, item) in items.iter().enumerate() {
+			let storage_result = match item.query_type {
+				StorageQueryType::Value =>
+					self.query_storage_value(hash, &item.key, child_key.as_ref())?,
+				StorageQueryType::Hash =>
+					self.query_storage_hash(hash, &item.key, child_key.as_ref())?,
+				StorageQueryType::ClosestDescendantMerkleValue =>
+					self.query_storage_merkle_value(hash, &item.key, child_key.as_ref())?,
+				StorageQueryType::DescendantsValues | StorageQueryType::DescendantsHashes => {
+					let before = result.len();
+					let completed = self.query_storage_descendants(
+						hash,
+						item,
+						child_key.as_ref(),
+						remaining_descendants,
+						&mut result,
+					)?;
+					remaining_descendants -= result.len() - before;
+
+					if !completed {
+						return Ok(ArchiveStorageResult {
+							result,
+							discarded_items: discarded_items + items.len() - index,
+						})
+					}
+
+					None
+				},
+			};
+
+			result.extend(storage_result);
+		}
+
+		Ok(ArchiveStorageResult { result, discarded_items })
+	}
+}
+
+/// Remove the items whose keys are already covered by another item of the same child trie
+/// with the same return type.
+///
+/// An empty list of items compares the whole main trie.
+fn deduplicate_storage_diff_items(
+	mut items: Vec<ArchiveStorageDiffItem<StorageKey>>,
+) -> Vec<ArchiveStorageDiffItem<StorageKey>> {
+	if items.is_empty() {
+		return vec![ArchiveStorageDiffItem {
+			key: StorageKey(Vec::new()),
+			return_type: ArchiveStorageDiffResultType::Value,
+			child_trie_key: None,
+		}]
+	}
+
+	// Shorter prefixes come first, such that the covering items are kept.
+	items.sort_by(|a, b| a.key.cmp(&b.key));
+
+	let mut deduplicated: Vec<ArchiveStorageDiffItem<StorageKey>> = Vec::new();
+	for item in items {
+		let is_covered = deduplicated.iter().any(|kept| {
+			kept.child_trie_key == item.child_trie_key &&
+				kept.return_type == item.return_type &&
+				item.key.0.starts_with(&kept.key.0)
+		});
+
+		if !is_covered {
+			deduplicated.push(item);
+		}
+	}
+
+	deduplicated
+}
+
+/// Generates the events of the `archive_unstable_storageDiff` method.
+pub struct ArchiveStorageDiff<Client, Block, BE> {
+	/// Substrate client.
+	client: Arc<Client>,
+	_phandom: PhantomData<(BE, Block)>,
+}
+
+impl<Client, Block, BE> ArchiveStorageDiff<Client, Block, BE> {
+	/// Constructs a new [`ArchiveStorageDiff`].
+	pub fn new(client: Arc<Client>) -> Self {
+		Self { client, _phandom: PhantomData }
+	}
+}
+
+impl<Client, Block, BE> ArchiveStorageDiff<Client, Block, BE>
+where
+	Block: BlockT + 'static,
+	BE: Backend<Block> + 'static,
+	Client: StorageProvider<Block, BE> + HeaderBackend<Block> + 'static,
+{
+	/// Resolve the block against which `hash` is compared, the parent of `hash` by default.
+	fn previous_hash(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Option<Block::Hash>,
+	) -> Result<Block::Hash, ArchiveError> {
+		let header = match self.client.header(hash) {
+			Ok(Some(header)) => header,
+			Ok(None) => return Err(ArchiveError::InvalidBlock),
+			Err(error) => return Err(ArchiveError::FetchBlockData(error.to_string())),
+		};
+
+		let previous_hash = previous_hash.unwrap_or_else(|| *header.parent_hash());
+		match self.client.header(previous_hash) {
+			Ok(Some(_)) => Ok(previous_hash),
+			Ok(None) => Err(ArchiveError::InvalidBlock),
+			Err(error) => Err(ArchiveError::FetchBlockData(error.to_string())),
+		}
+	}
+
+	/// Report the differences of one item to the sink.
+	///
+	/// Returns `false` if the subscription was closed by the client.
+	fn send_item_diff(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Block::Hash,
+		item: &ArchiveStorageDiffItem<StorageKey>,
+		sink: &mut SubscriptionSink,
+	) -> Result<bool, ArchiveError> {
+		let child_info =
+			item.child_trie_key.clone().map(|key| ChildInfo::new_default_from_vec(key.0));
+		let child_trie_key = item.child_trie_key.as_ref().map(|key| hex_string(&key.0));
+		let fetch_values = item.return_type == ArchiveStorageDiffResultType::Value;
+
+		let mut is_active = true;
+		let mut on_diff = |diff: TrieDiff<Block::Hash>| {
+			let result = match diff.value {
+				TrieDiffValue::Value(value) => StorageResultType::Value(hex_string(&value)),
+				TrieDiffValue::Hash(hash) => StorageResultType::Hash(hex_string(&hash.as_ref())),
+			};
+			let operation_type = match diff.kind {
+				TrieDiffKind::Added => ArchiveStorageDiffOperationType::Added,
+				TrieDiffKind::Modified => ArchiveStorageDiffOperationType::Modified,
+				TrieDiffKind::Deleted => ArchiveStorageDiffOperationType::Deleted,
+			};
+
+			let event = ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+				key: hex_string(&diff.key),
+				result,
+				operation_type,
+				child_trie_key: child_trie_key.clone(),
+			});
+
+			is_active = sink.send(&event).unwrap_or(false);
+			is_active
+		};
+
+		self.client
+			.storage_diff(
+				hash,
+				previous_hash,
+				child_info.as_ref(),
+				Some(&item.key),
+				fetch_values,
+				&mut on_diff,
+			)
+			.map_err(map_state_error)?;
+
+		Ok(is_active)
+	}
+
+	/// Handle the items of one `archive_unstable_storageDiff` subscription.
+	///
+	/// The differences are streamed to the sink as they are found while walking the tries,
+	/// followed by a `storageDiffDone` event, or a `storageDiffError` event on failure.
+	pub fn handle_trie_queries(
+		&self,
+		hash: Block::Hash,
+		previous_hash: Option<Block::Hash>,
+		items: Vec<ArchiveStorageDiffItem<StorageKey>>,
+		mut sink: SubscriptionSink,
+	) {
+		let previous_hash = match self.previous_hash(hash, previous_hash) {
+			Ok(previous_hash) => previous_hash,
+			Err(error) => {
+				let _ = sink.reject(error);
+				return
+			},
+		};
+
+		for item in deduplicate_storage_diff_items(items) {
+			match self.send_item_diff(hash, previous_hash, &item, &mut sink) {
+				Ok(true) => {},
+				// The subscription was closed.
+				Ok(false) => return,
+				Err(error) => {
+					let _ = sink.send(&ArchiveStorageDiffEvent::StorageDiffError(
+						ArchiveStorageDiffError { error: error.to_string() },
+					));
+					return
+				},
+			}
+		}
+
+		let _ = sink.send(&ArchiveStorageDiffEvent::StorageDiffDone);
+	}
+}
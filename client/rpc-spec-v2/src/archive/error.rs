@@ -0,0 +1,72 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Error helpers for `archive` RPC module.
+
+use jsonrpsee::{
+	core::Error as RpcError,
+	types::error::{CallError, ErrorObject},
+};
+
+/// Archive RPC errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	/// The provided block hash is invalid.
+	#[error("Invalid block hash")]
+	InvalidBlock,
+	/// The state of the block is not available, it was pruned by the node.
+	#[error("State unavailable: {0}")]
+	StateUnavailable(String),
+	/// Fetch block data error.
+	#[error("Could not fetch block data: {0}")]
+	FetchBlockData(String),
+	/// Invalid parameter provided to the RPC method.
+	#[error("Invalid parameter: {0}")]
+	InvalidParam(String),
+}
+
+// Base code for all `archive` errors.
+const BASE_ERROR: i32 = 3000;
+/// The provided block hash is invalid.
+const INVALID_BLOCK_ERROR: i32 = BASE_ERROR + 1;
+/// The state of the block is not available.
+const STATE_UNAVAILABLE_ERROR: i32 = BASE_ERROR + 2;
+/// Fetch block data error.
+const FETCH_BLOCK_DATA_ERROR: i32 = BASE_ERROR + 3;
+/// Invalid parameter error.
+const INVALID_PARAM_ERROR: i32 = BASE_ERROR + 4;
+
+impl From<Error> for ErrorObject<'static> {
+	fn from(e: Error) -> Self {
+		let msg = e.to_string();
+
+		match e {
+			Error::InvalidBlock => ErrorObject::owned(INVALID_BLOCK_ERROR, msg, None::<()>),
+			Error::StateUnavailable(_) =>
+				ErrorObject::owned(STATE_UNAVAILABLE_ERROR, msg, None::<()>),
+			Error::FetchBlockData(_) => ErrorObject::owned(FETCH_BLOCK_DATA_ERROR, msg, None::<()>),
+			Error::InvalidParam(_) => ErrorObject::owned(INVALID_PARAM_ERROR, msg, None::<()>),
+		}
+	}
+}
+
+impl From<Error> for RpcError {
+	fn from(e: Error) -> Self {
+		CallError::Custom(e.into()).into()
+	}
+}
@@ -0,0 +1,285 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The types returned by the archive methods.
+
+use serde::{Deserialize, Serialize};
+
+/// The type of the storage query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageQueryType {
+	/// Fetch the value of the provided key.
+	Value,
+	/// Fetch the hash of the value of the provided key.
+	Hash,
+	/// Fetch the closest descendant merkle value.
+	ClosestDescendantMerkleValue,
+	/// Fetch the values of all descendants of the provided key.
+	DescendantsValues,
+	/// Fetch the hashes of the values of all descendants of the provided key.
+	DescendantsHashes,
+}
+
+impl StorageQueryType {
+	/// Returns `true` if the query iterates over the descendants of the key.
+	pub fn is_descendant_query(&self) -> bool {
+		matches!(self, Self::DescendantsValues | Self::DescendantsHashes)
+	}
+}
+
+/// The storage item to query, with an optional pagination start key for descendant queries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedStorageQuery<Key> {
+	/// The provided key.
+	pub key: Key,
+	/// The type of the storage query.
+	#[serde(rename = "type")]
+	pub query_type: StorageQueryType,
+	/// The descendant key to resume the iteration from (exclusive).
+	///
+	/// Only used by the descendant queries.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub pagination_start_key: Option<Key>,
+}
+
+/// The result of a storage query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageResult {
+	/// The hex-encoded key of the result.
+	pub key: String,
+	/// The result of the query.
+	#[serde(flatten)]
+	pub result: StorageResultType,
+}
+
+/// The type of the storage result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageResultType {
+	/// Fetch the value of the provided key.
+	Value(String),
+	/// Fetch the hash of the value of the provided key.
+	Hash(String),
+	/// Fetch the closest descendant merkle value.
+	ClosestDescendantMerkleValue(String),
+}
+
+/// The result of the `archive_unstable_storage` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageResult {
+	/// The storage results.
+	pub result: Vec<StorageResult>,
+	/// The number of queries at the end of the provided list that were not processed.
+	pub discarded_items: usize,
+}
+
+/// The result of the `archive_unstable_call` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MethodResult {
+	/// Whether the runtime call succeeded.
+	pub success: bool,
+	/// The hex-encoded output of the runtime call, present on success.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub value: Option<String>,
+	/// The reason of the failure, present on error.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub error: Option<String>,
+}
+
+impl MethodResult {
+	/// Construct a successful result.
+	pub fn ok(value: impl Into<String>) -> Self {
+		Self { success: true, value: Some(value.into()), error: None }
+	}
+
+	/// Construct a failed result.
+	pub fn err(error: impl Into<String>) -> Self {
+		Self { success: false, value: None, error: Some(error.into()) }
+	}
+}
+
+/// The type of the value reported by the `archive_unstable_storageDiff` method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveStorageDiffResultType {
+	/// Report the values of the changed keys.
+	Value,
+	/// Report the hashes of the values of the changed keys.
+	Hash,
+}
+
+/// The storage item for which the differences are requested.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageDiffItem<Key> {
+	/// The prefix of the keys to compare.
+	pub key: Key,
+	/// The type of the value reported for the changed keys.
+	pub return_type: ArchiveStorageDiffResultType,
+	/// The child trie in which the keys are compared, the main trie if not provided.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub child_trie_key: Option<Key>,
+}
+
+/// The kind of change of a storage key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveStorageDiffOperationType {
+	/// The key is only present in the new block.
+	Added,
+	/// The value of the key changed between the two blocks.
+	Modified,
+	/// The key is only present in the previous block.
+	Deleted,
+}
+
+/// A storage key that differs between the two blocks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageDiffResult {
+	/// The hex-encoded key of the result.
+	pub key: String,
+	/// The value of the key in the new block, or in the previous block for deleted keys.
+	#[serde(flatten)]
+	pub result: StorageResultType,
+	/// The kind of change of the key.
+	#[serde(rename = "type")]
+	pub operation_type: ArchiveStorageDiffOperationType,
+	/// The hex-encoded child trie key, if the key belongs to a child trie.
+	#[serde(skip_serializing_if = "Option::is_none", default)]
+	pub child_trie_key: Option<String>,
+}
+
+/// The error reported when the storage differences cannot be computed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageDiffError {
+	/// The reason of the error.
+	pub error: String,
+}
+
+/// The event generated by the `archive_unstable_storageDiff` method.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "event")]
+pub enum ArchiveStorageDiffEvent {
+	/// A storage key that differs between the two blocks.
+	StorageDiff(ArchiveStorageDiffResult),
+	/// An error occurred while computing the differences. This is definitive.
+	StorageDiffError(ArchiveStorageDiffError),
+	/// All the differences were reported.
+	StorageDiffDone,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn storage_query_serialize() {
+		let query = PaginatedStorageQuery {
+			key: "0x1",
+			query_type: StorageQueryType::DescendantsValues,
+			pagination_start_key: None,
+		};
+		let ser = serde_json::to_string(&query).unwrap();
+		let exp = r#"{"key":"0x1","type":"descendantsValues"}"#;
+		assert_eq!(ser, exp);
+
+		let query = PaginatedStorageQuery {
+			key: "0x1",
+			query_type: StorageQueryType::ClosestDescendantMerkleValue,
+			pagination_start_key: Some("0x12"),
+		};
+		let ser = serde_json::to_string(&query).unwrap();
+		let exp =
+			r#"{"key":"0x1","type":"closestDescendantMerkleValue","paginationStartKey":"0x12"}"#;
+		assert_eq!(ser, exp);
+	}
+
+	#[test]
+	fn storage_result_serialize() {
+		let result =
+			StorageResult { key: "0x1".into(), result: StorageResultType::Value("0x123".into()) };
+		let ser = serde_json::to_string(&result).unwrap();
+		let exp = r#"{"key":"0x1","value":"0x123"}"#;
+		assert_eq!(ser, exp);
+		let de: StorageResult = serde_json::from_str(exp).unwrap();
+		assert_eq!(de, result);
+	}
+
+	#[test]
+	fn method_result_serialize() {
+		let ser = serde_json::to_string(&MethodResult::ok("0x01")).unwrap();
+		assert_eq!(ser, r#"{"success":true,"value":"0x01"}"#);
+
+		let ser = serde_json::to_string(&MethodResult::err("error")).unwrap();
+		assert_eq!(ser, r#"{"success":false,"error":"error"}"#);
+	}
+
+	#[test]
+	fn storage_diff_item_serialize() {
+		let item = ArchiveStorageDiffItem {
+			key: "0x1",
+			return_type: ArchiveStorageDiffResultType::Hash,
+			child_trie_key: None,
+		};
+		let ser = serde_json::to_string(&item).unwrap();
+		assert_eq!(ser, r#"{"key":"0x1","returnType":"hash"}"#);
+
+		let exp = r#"{"key":"0x1","returnType":"value","childTrieKey":"0x2"}"#;
+		let de: ArchiveStorageDiffItem<String> = serde_json::from_str(exp).unwrap();
+		assert_eq!(
+			de,
+			ArchiveStorageDiffItem {
+				key: "0x1".into(),
+				return_type: ArchiveStorageDiffResultType::Value,
+				child_trie_key: Some("0x2".into()),
+			}
+		);
+	}
+
+	#[test]
+	fn storage_diff_event_serialize() {
+		let event = ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: "0x1".into(),
+			result: StorageResultType::Value("0x123".into()),
+			operation_type: ArchiveStorageDiffOperationType::Modified,
+			child_trie_key: None,
+		});
+		let ser = serde_json::to_string(&event).unwrap();
+		let exp = r#"{"event":"storageDiff","key":"0x1","value":"0x123","type":"modified"}"#;
+		assert_eq!(ser, exp);
+		let de: ArchiveStorageDiffEvent = serde_json::from_str(exp).unwrap();
+		assert_eq!(de, event);
+
+		let event = ArchiveStorageDiffEvent::StorageDiffError(ArchiveStorageDiffError {
+			error: "error".into(),
+		});
+		let ser = serde_json::to_string(&event).unwrap();
+		assert_eq!(ser, r#"{"event":"storageDiffError","error":"error"}"#);
+
+		let ser = serde_json::to_string(&ArchiveStorageDiffEvent::StorageDiffDone).unwrap();
+		assert_eq!(ser, r#"{"event":"storageDiffDone"}"#);
+	}
+}
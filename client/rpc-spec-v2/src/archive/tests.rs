@@ -0,0 +1,571 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+use assert_matches::assert_matches;
+use codec::{Decode, Encode};
+use jsonrpsee::{
+	core::{error::Error, server::rpc_module::Subscription as RpcSubscription},
+	rpc_params,
+	types::{error::CallError, EmptyServerParams as EmptyParams},
+	RpcModule,
+};
+use sc_block_builder::BlockBuilderProvider;
+use sc_client_api::ChildInfo;
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_core::{hexdisplay::HexDisplay, testing::TaskExecutor};
+use std::sync::Arc;
+use substrate_test_runtime::Transfer;
+use substrate_test_runtime_client::{
+	prelude::*, runtime, Backend, BlockBuilderExt, Client, ClientBlockImportExt,
+};
+
+type Header = substrate_test_runtime_client::runtime::Header;
+type Block = substrate_test_runtime_client::runtime::Block;
+const CHAIN_GENESIS: [u8; 32] = [0; 32];
+const INVALID_HASH: [u8; 32] = [1; 32];
+const KEY: &[u8] = b":mock";
+const VALUE: &[u8] = b"hello world";
+const CHILD_STORAGE_KEY: &[u8] = b"child";
+const CHILD_VALUE: &[u8] = b"child value";
+const MAX_DESCENDANT_RESPONSES: usize = 2;
+const MAX_QUERIED_ITEMS: usize = 3;
+
+fn setup_api() -> (Arc<Client<Backend>>, RpcModule<Archive<Backend, Block, Client<Backend>>>) {
+	let child_info = ChildInfo::new_default(CHILD_STORAGE_KEY);
+	let builder = TestClientBuilder::new().add_extra_child_storage(
+		&child_info,
+		KEY.to_vec(),
+		CHILD_VALUE.to_vec(),
+	);
+	let backend = builder.backend();
+	let client = Arc::new(builder.build());
+
+	let api = Archive::new(
+		client.clone(),
+		backend,
+		Arc::new(TaskExecutor::default()),
+		CHAIN_GENESIS,
+		ArchiveConfig {
+			max_descendant_responses: MAX_DESCENDANT_RESPONSES,
+			max_queried_items: MAX_QUERIED_ITEMS,
+		},
+	)
+	.into_rpc();
+
+	(client, api)
+}
+
+fn hex_string<Data: AsRef<[u8]>>(data: &Data) -> String {
+	format!("0x{:?}", HexDisplay::from(&data.as_ref()))
+}
+
+async fn get_next_event<T: serde::de::DeserializeOwned>(sub: &mut RpcSubscription) -> T {
+	let (event, _sub_id) = tokio::time::timeout(std::time::Duration::from_secs(60), sub.next())
+		.await
+		.unwrap()
+		.unwrap()
+		.unwrap();
+	event
+}
+
+#[tokio::test]
+async fn archive_genesis() {
+	let (_client, api) = setup_api();
+
+	let genesis: String =
+		api.call("archive_unstable_genesisHash", EmptyParams::new()).await.unwrap();
+	assert_eq!(genesis, hex_string(&CHAIN_GENESIS));
+}
+
+#[tokio::test]
+async fn archive_body() {
+	let (mut client, api) = setup_api();
+
+	// Invalid block hash.
+	let invalid_hash = hex_string(&INVALID_HASH);
+	let body: Option<Vec<String>> =
+		api.call("archive_unstable_body", [invalid_hash]).await.unwrap();
+	assert!(body.is_none());
+
+	// Import a new block with an extrinsic.
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder
+		.push_transfer(runtime::Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 42,
+			nonce: 0,
+		})
+		.unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let expected_tx = hex_string(&block.extrinsics[0].encode());
+
+	let body: Vec<String> = api.call("archive_unstable_body", [block_hash]).await.unwrap();
+	assert_eq!(vec![expected_tx], body);
+}
+
+#[tokio::test]
+async fn archive_header() {
+	let (mut client, api) = setup_api();
+
+	// Invalid block hash.
+	let invalid_hash = hex_string(&INVALID_HASH);
+	let header: Option<String> = api.call("archive_unstable_header", [invalid_hash]).await.unwrap();
+	assert!(header.is_none());
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let header: String = api.call("archive_unstable_header", [block_hash]).await.unwrap();
+	let bytes = array_bytes::hex2bytes(&header).unwrap();
+	let header: Header = Decode::decode(&mut &bytes[..]).unwrap();
+	assert_eq!(header, block.header);
+}
+
+#[tokio::test]
+async fn archive_finalized_height() {
+	let (client, api) = setup_api();
+
+	let client_height: u64 = client.info().finalized_number;
+
+	let height: u64 =
+		api.call("archive_unstable_finalizedHeight", EmptyParams::new()).await.unwrap();
+
+	assert_eq!(client_height, height);
+}
+
+#[tokio::test]
+async fn archive_hash_by_height() {
+	let (mut client, api) = setup_api();
+
+	// Genesis height.
+	let hashes: Vec<String> = api.call("archive_unstable_hashByHeight", [0]).await.unwrap();
+	assert_eq!(hashes, vec![format!("{:?}", client.info().genesis_hash)]);
+
+	// Block tree:
+	// finalized -> block 1 -> block 2 -> block 3
+	//                      -> block 2b
+	let block_1 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_1_hash = block_1.header.hash();
+	client.import(BlockOrigin::Own, block_1.clone()).await.unwrap();
+
+	let block_2 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_2_hash = block_2.header.hash();
+	client.import(BlockOrigin::Own, block_2.clone()).await.unwrap();
+
+	let block_3 = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_3_hash = block_3.header.hash();
+	client.import(BlockOrigin::Own, block_3.clone()).await.unwrap();
+
+	let mut block_builder = client.new_block_at(block_1_hash, Default::default(), false).unwrap();
+	// This push is required as otherwise block 2b has the same hash as block 2 and won't get
+	// imported.
+	block_builder
+		.push_transfer(Transfer {
+			from: AccountKeyring::Alice.into(),
+			to: AccountKeyring::Ferdie.into(),
+			amount: 41,
+			nonce: 0,
+		})
+		.unwrap();
+	let block_2b = block_builder.build().unwrap().block;
+	let block_2b_hash = block_2b.header.hash();
+	client.import(BlockOrigin::Own, block_2b.clone()).await.unwrap();
+
+	// Finalize block 1.
+	client.finalize_block(block_1_hash, None).unwrap();
+
+	// Finalized height.
+	let hashes: Vec<String> = api.call("archive_unstable_hashByHeight", [1]).await.unwrap();
+	assert_eq!(hashes, vec![format!("{:?}", block_1_hash)]);
+
+	// Non-finalized height with two forks.
+	let mut hashes: Vec<String> = api.call("archive_unstable_hashByHeight", [2]).await.unwrap();
+	hashes.sort();
+	let mut expected = vec![format!("{:?}", block_2_hash), format!("{:?}", block_2b_hash)];
+	expected.sort();
+	assert_eq!(hashes, expected);
+
+	// Non-finalized height on the longest fork.
+	let hashes: Vec<String> = api.call("archive_unstable_hashByHeight", [3]).await.unwrap();
+	assert_eq!(hashes, vec![format!("{:?}", block_3_hash)]);
+
+	// Height above the best block.
+	let hashes: Vec<String> = api.call("archive_unstable_hashByHeight", [4]).await.unwrap();
+	assert!(hashes.is_empty());
+}
+
+#[tokio::test]
+async fn archive_call() {
+	let (mut client, api) = setup_api();
+	let invalid_hash = hex_string(&INVALID_HASH);
+
+	// Invalid parameter (non-hex).
+	let err = api
+		.call::<_, serde_json::Value>(
+			"archive_unstable_call",
+			[&invalid_hash, "BabeApi_current_epoch", "0x0000000"],
+		)
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3004 && err.message().contains("Invalid parameter")
+	);
+
+	// Invalid block hash.
+	let err = api
+		.call::<_, serde_json::Value>(
+			"archive_unstable_call",
+			[&invalid_hash, "BabeApi_current_epoch", "0x00"],
+		)
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3001 && err.message() == "Invalid block hash"
+	);
+
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	// Valid call.
+	let alice_id = AccountKeyring::Alice.to_account_id();
+	let call_parameters = hex_string(&alice_id.encode());
+	let result: MethodResult = api
+		.call(
+			"archive_unstable_call",
+			[&block_hash, "AccountNonceApi_account_nonce", &call_parameters],
+		)
+		.await
+		.unwrap();
+	assert_eq!(result, MethodResult::ok("0x0000000000000000"));
+
+	// The runtime call fails.
+	let result: MethodResult = api
+		.call("archive_unstable_call", [&block_hash, "BabeApi_current_epoch", "0x00"])
+		.await
+		.unwrap();
+	assert!(!result.success);
+}
+
+#[tokio::test]
+async fn archive_storage_hashes_values() {
+	let (mut client, api) = setup_api();
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(b":m".to_vec(), Some(b"a".to_vec())).unwrap();
+	builder.push_storage_change(b":mo".to_vec(), Some(b"ab".to_vec())).unwrap();
+	builder.push_storage_change(b":moc".to_vec(), Some(b"abc".to_vec())).unwrap();
+	builder.push_storage_change(KEY.to_vec(), Some(VALUE.to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let key = hex_string(&KEY);
+	let items = vec![
+		PaginatedStorageQuery {
+			key: key.clone(),
+			query_type: StorageQueryType::Value,
+			pagination_start_key: None,
+		},
+		PaginatedStorageQuery {
+			key: key.clone(),
+			query_type: StorageQueryType::Hash,
+			pagination_start_key: None,
+		},
+		PaginatedStorageQuery {
+			key: key.clone(),
+			query_type: StorageQueryType::ClosestDescendantMerkleValue,
+			pagination_start_key: None,
+		},
+	];
+
+	let result: ArchiveStorageResult = api
+		.call("archive_unstable_storage", rpc_params![&block_hash, items])
+		.await
+		.unwrap();
+
+	assert_eq!(result.discarded_items, 0);
+	assert_eq!(result.result.len(), 3);
+	assert_eq!(result.result[0].key, key);
+	assert_eq!(result.result[0].result, StorageResultType::Value(hex_string(&VALUE)));
+	assert_eq!(result.result[1].key, key);
+	let expected_hash = hex_string(&<runtime::Hashing as sp_runtime::traits::Hash>::hash(VALUE));
+	assert_eq!(result.result[1].result, StorageResultType::Hash(expected_hash));
+	assert_eq!(result.result[2].key, key);
+	assert_matches!(result.result[2].result, StorageResultType::ClosestDescendantMerkleValue(_));
+
+	// Child trie value set in `setup_api`.
+	let child_info = hex_string(&CHILD_STORAGE_KEY);
+	let items = vec![PaginatedStorageQuery {
+		key: key.clone(),
+		query_type: StorageQueryType::Value,
+		pagination_start_key: None,
+	}];
+	let result: ArchiveStorageResult = api
+		.call("archive_unstable_storage", rpc_params![&block_hash, items, &child_info])
+		.await
+		.unwrap();
+	assert_eq!(result.result.len(), 1);
+	assert_eq!(result.result[0].result, StorageResultType::Value(hex_string(&CHILD_VALUE)));
+}
+
+#[tokio::test]
+async fn archive_storage_paginate_descendants() {
+	let (mut client, api) = setup_api();
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(b":m".to_vec(), Some(b"a".to_vec())).unwrap();
+	builder.push_storage_change(b":mo".to_vec(), Some(b"ab".to_vec())).unwrap();
+	builder.push_storage_change(b":moc".to_vec(), Some(b"abc".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let query = |pagination_start_key: Option<String>| PaginatedStorageQuery {
+		key: hex_string(b":m"),
+		query_type: StorageQueryType::DescendantsValues,
+		pagination_start_key,
+	};
+
+	// The descendant query is interrupted after `MAX_DESCENDANT_RESPONSES` results.
+	let result: ArchiveStorageResult = api
+		.call("archive_unstable_storage", rpc_params![&block_hash, vec![query(None)]])
+		.await
+		.unwrap();
+	assert_eq!(result.discarded_items, 1);
+	assert_eq!(
+		result.result,
+		vec![
+			StorageResult {
+				key: hex_string(b":m"),
+				result: StorageResultType::Value(hex_string(b"a")),
+			},
+			StorageResult {
+				key: hex_string(b":mo"),
+				result: StorageResultType::Value(hex_string(b"ab")),
+			},
+		]
+	);
+
+	// Resume from the last returned key.
+	let result: ArchiveStorageResult = api
+		.call(
+			"archive_unstable_storage",
+			rpc_params![&block_hash, vec![query(Some(hex_string(b":mo")))]],
+		)
+		.await
+		.unwrap();
+	assert_eq!(result.discarded_items, 0);
+	assert_eq!(
+		result.result,
+		vec![StorageResult {
+			key: hex_string(b":moc"),
+			result: StorageResultType::Value(hex_string(b"abc")),
+		}]
+	);
+}
+
+#[tokio::test]
+async fn archive_storage_discarded_items() {
+	let (client, api) = setup_api();
+	let block_hash = format!("{:?}", client.info().genesis_hash);
+
+	let items = (0..MAX_QUERIED_ITEMS + 2)
+		.map(|_| PaginatedStorageQuery {
+			key: hex_string(&KEY),
+			query_type: StorageQueryType::Value,
+			pagination_start_key: None,
+		})
+		.collect::<Vec<_>>();
+
+	let result: ArchiveStorageResult = api
+		.call("archive_unstable_storage", rpc_params![&block_hash, items])
+		.await
+		.unwrap();
+	assert_eq!(result.discarded_items, 2);
+}
+
+#[tokio::test]
+async fn archive_storage_invalid_block() {
+	let (_client, api) = setup_api();
+	let invalid_hash = hex_string(&INVALID_HASH);
+
+	let items = vec![PaginatedStorageQuery {
+		key: hex_string(&KEY),
+		query_type: StorageQueryType::Value,
+		pagination_start_key: None,
+	}];
+
+	let err = api
+		.call::<_, serde_json::Value>("archive_unstable_storage", rpc_params![&invalid_hash, items])
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3001 && err.message() == "Invalid block hash"
+	);
+}
+
+#[tokio::test]
+async fn archive_storage_pruned_state() {
+	let builder = TestClientBuilder::with_pruning_window(1);
+	let backend = builder.backend();
+	let mut client = Arc::new(builder.build());
+	let api = Archive::new(
+		client.clone(),
+		backend,
+		Arc::new(TaskExecutor::default()),
+		CHAIN_GENESIS,
+		ArchiveConfig::default(),
+	)
+	.into_rpc();
+
+	let genesis_hash = hex_string(&client.genesis_hash().as_ref());
+	for _ in 0..3 {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		client.import_as_final(BlockOrigin::Own, block).await.unwrap();
+	}
+
+	// The genesis block is known, but its state was pruned.
+	let header: Option<String> =
+		api.call("archive_unstable_header", [&genesis_hash]).await.unwrap();
+	assert!(header.is_some());
+
+	let items = vec![PaginatedStorageQuery {
+		key: hex_string(&KEY),
+		query_type: StorageQueryType::Value,
+		pagination_start_key: None,
+	}];
+	let err = api
+		.call::<_, serde_json::Value>("archive_unstable_storage", rpc_params![&genesis_hash, items])
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3002
+	);
+}
+
+#[tokio::test]
+async fn archive_storage_diff() {
+	let (mut client, api) = setup_api();
+
+	let mut builder = client.new_block(Default::default()).unwrap();
+	builder.push_storage_change(b":m".to_vec(), Some(b"a".to_vec())).unwrap();
+	builder.push_storage_change(b":mo".to_vec(), Some(b"ab".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	let mut builder = client.new_block_at(block.header.hash(), Default::default(), false).unwrap();
+	builder.push_storage_change(b":m".to_vec(), Some(b"b".to_vec())).unwrap();
+	builder.push_storage_change(b":mo".to_vec(), None).unwrap();
+	builder.push_storage_change(b":moc".to_vec(), Some(b"abc".to_vec())).unwrap();
+	let block = builder.build().unwrap().block;
+	let block_hash = format!("{:?}", block.header.hash());
+	client.import(BlockOrigin::Own, block.clone()).await.unwrap();
+
+	// Compare against the parent block by default.
+	let items = vec![ArchiveStorageDiffItem {
+		key: hex_string(b":m"),
+		return_type: ArchiveStorageDiffResultType::Value,
+		child_trie_key: None,
+	}];
+	let mut sub = api
+		.subscribe("archive_unstable_storageDiff", rpc_params![&block_hash, items])
+		.await
+		.unwrap();
+
+	let diff = |key: &[u8], value: &[u8], operation_type| {
+		ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: hex_string(&key),
+			result: StorageResultType::Value(hex_string(&value)),
+			operation_type,
+			child_trie_key: None,
+		})
+	};
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, diff(b":m", b"b", ArchiveStorageDiffOperationType::Modified));
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, diff(b":mo", b"ab", ArchiveStorageDiffOperationType::Deleted));
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, diff(b":moc", b"abc", ArchiveStorageDiffOperationType::Added));
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+
+	// Compare against the genesis block, the overlapping item is reported once.
+	let genesis_hash = format!("{:?}", client.info().genesis_hash);
+	let items = vec![
+		ArchiveStorageDiffItem {
+			key: hex_string(b":moc"),
+			return_type: ArchiveStorageDiffResultType::Hash,
+			child_trie_key: None,
+		},
+		ArchiveStorageDiffItem {
+			key: hex_string(b":mo"),
+			return_type: ArchiveStorageDiffResultType::Hash,
+			child_trie_key: None,
+		},
+	];
+	let mut sub = api
+		.subscribe("archive_unstable_storageDiff", rpc_params![&block_hash, items, &genesis_hash])
+		.await
+		.unwrap();
+
+	let expected_hash = hex_string(&<runtime::Hashing as sp_runtime::traits::Hash>::hash(b"abc"));
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(
+		event,
+		ArchiveStorageDiffEvent::StorageDiff(ArchiveStorageDiffResult {
+			key: hex_string(b":moc"),
+			result: StorageResultType::Hash(expected_hash),
+			operation_type: ArchiveStorageDiffOperationType::Added,
+			child_trie_key: None,
+		})
+	);
+	let event: ArchiveStorageDiffEvent = get_next_event(&mut sub).await;
+	assert_eq!(event, ArchiveStorageDiffEvent::StorageDiffDone);
+}
+
+#[tokio::test]
+async fn archive_storage_diff_invalid_block() {
+	let (client, api) = setup_api();
+	let genesis_hash = format!("{:?}", client.info().genesis_hash);
+	let invalid_hash = hex_string(&INVALID_HASH);
+	let items: Vec<ArchiveStorageDiffItem<String>> = Vec::new();
+
+	let err = api
+		.subscribe("archive_unstable_storageDiff", rpc_params![&invalid_hash, items.clone()])
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3001 && err.message() == "Invalid block hash"
+	);
+
+	let err = api
+		.subscribe("archive_unstable_storageDiff", rpc_params![&genesis_hash, items, &invalid_hash])
+		.await
+		.unwrap_err();
+	assert_matches!(err,
+		Error::Call(CallError::Custom(ref err)) if err.code() == 3001 && err.message() == "Invalid block hash"
+	);
+}
@@ -395,7 +395,7 @@ where
 					&function,
 					&call_parameters,
 					client.execution_extensions().strategies().other,
-					CallContext::Offchain,
+					CallContext::Untrusted,
 				)
 				.map(|result| {
 					let result = format!("0x{:?}", HexDisplay::from(&result));
@@ -37,7 +37,7 @@ use sc_transaction_pool_api::{
 	error::IntoPoolError, BlockHash, InPoolTransaction, TransactionFor, TransactionPool,
 	TransactionSource, TxHash,
 };
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ExecutionContext, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::Bytes;
 use sp_keystore::KeystorePtr;
@@ -124,7 +124,11 @@ where
 		let best_block_hash = self.client.info().best_hash;
 		self.client
 			.runtime_api()
-			.generate_session_keys(best_block_hash, None)
+			.generate_session_keys_with_context(
+				best_block_hash,
+				ExecutionContext::UntrustedCall,
+				None,
+			)
 			.map(Into::into)
 			.map_err(|api_err| Error::Client(Box::new(api_err)).into())
 	}
@@ -136,7 +140,11 @@ where
 		let keys = self
 			.client
 			.runtime_api()
-			.decode_session_keys(best_block_hash, session_keys.to_vec())
+			.decode_session_keys_with_context(
+				best_block_hash,
+				ExecutionContext::UntrustedCall,
+				session_keys.to_vec(),
+			)
 			.map_err(|e| Error::Client(Box::new(e)))?
 			.ok_or(Error::InvalidSessionKeys)?;
 
@@ -25,7 +25,7 @@ mod tests;
 use jsonrpsee::core::RpcResult;
 use sc_client_api::{BlockBackend, HeaderBackend};
 use sc_rpc_api::{dev::error::Error, DenyUnsafe};
-use sp_api::{ApiExt, Core, ProvideRuntimeApi};
+use sp_api::{ApiExt, Core, ExecutionContext, ProvideRuntimeApi};
 use sp_core::Encode;
 use sp_runtime::{
 	generic::DigestItem,
@@ -98,7 +98,11 @@ where
 		let mut runtime_api = self.client.runtime_api();
 		runtime_api.record_proof();
 		runtime_api
-			.execute_block(parent_header.hash(), block)
+			.execute_block_with_context(
+				parent_header.hash(),
+				ExecutionContext::UntrustedCall,
+				block,
+			)
 			.map_err(|_| Error::BlockExecutionFailed)?;
 		let witness = runtime_api
 			.extract_proof()
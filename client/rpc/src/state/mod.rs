@@ -157,6 +157,9 @@ where
 		methods: Option<String>,
 	) -> Result<sp_rpc::tracing::TraceBlockResponse, Error>;
 
+	/// Profile the host function calls of a block
+	fn profile_block(&self, block: Block::Hash) -> Result<sp_rpc::tracing::BlockProfile, Error>;
+
 	/// New runtime version subscription
 	fn subscribe_runtime_version(&self, sink: SubscriptionSink);
 
@@ -325,6 +328,15 @@ where
 			.map_err(Into::into)
 	}
 
+	/// Re-execute the given block and report the time spent in each host function.
+	///
+	/// Note: requires the node to run with `--rpc-methods=Unsafe`.
+	/// Note: requires the node to run with `--profile-host-functions`.
+	fn profile_block(&self, block: Block::Hash) -> RpcResult<sp_rpc::tracing::BlockProfile> {
+		self.deny_unsafe.check_if_safe()?;
+		self.backend.profile_block(block).map_err(Into::into)
+	}
+
 	fn subscribe_runtime_version(&self, sink: SubscriptionSink) -> SubscriptionResult {
 		self.backend.subscribe_runtime_version(sink);
 		Ok(())
@@ -37,7 +37,7 @@ use sc_client_api::{
 	StorageProvider,
 };
 use sc_rpc_api::state::ReadProof;
-use sp_api::{CallApiAt, Metadata, ProvideRuntimeApi};
+use sp_api::{CallApiAt, ExecutionContext, Metadata, ProvideRuntimeApi};
 use sp_blockchain::{
 	CachedHeaderMetadata, Error as ClientError, HeaderBackend, HeaderMetadata,
 	Result as ClientResult,
@@ -203,11 +203,15 @@ where
 						&method,
 						&call_data,
 						self.client.execution_extensions().strategies().other,
-						CallContext::Offchain,
+						CallContext::Untrusted,
 					)
 					.map(Into::into)
 			})
-			.map_err(client_err)
+			.map_err(|e| match e {
+				sp_blockchain::Error::ResourceLimitExceeded(limit) =>
+					Error::ResourceLimitExceeded(limit),
+				e => client_err(e),
+			})
 	}
 
 	// TODO: This is horribly broken; either remove it, or make it streaming.
@@ -322,7 +326,7 @@ where
 		self.block_or_best(block).map_err(client_err).and_then(|block| {
 			self.client
 				.runtime_api()
-				.metadata(block)
+				.metadata_with_context(block, ExecutionContext::UntrustedCall)
 				.map(Into::into)
 				.map_err(|e| Error::Client(Box::new(e)))
 		})
@@ -480,6 +484,20 @@ where
 		.trace_block()
 		.map_err(|e| invalid_block::<Block>(block, None, e.to_string()))
 	}
+
+	fn profile_block(
+		&self,
+		block: Block::Hash,
+	) -> std::result::Result<sp_rpc::tracing::BlockProfile, Error> {
+		let profile =
+			sc_tracing::block::BlockExecutor::new(self.client.clone(), block, None, None, None)
+				.profile_block()
+				.map_err(|e| invalid_block::<Block>(block, None, e.to_string()))?;
+		if profile.host_functions.is_empty() {
+			return Err(Error::HostFunctionProfilingDisabled)
+		}
+		Ok(profile)
+	}
 }
 
 impl<BE, Block, Client> ChildStateBackend<Block, Client> for FullState<BE, Block, Client>
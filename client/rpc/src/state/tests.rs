@@ -477,6 +477,25 @@ async fn should_query_storage() {
 	run_tests(Arc::new(TestClientBuilder::new().build())).await;
 }
 
+#[tokio::test]
+async fn profile_block_should_require_host_function_profiling() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+	let hash = block.header.hash();
+	executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	let (api, _child) = new_full(client, test_executor(), DenyUnsafe::No);
+
+	assert_eq!(
+		api.profile_block(hash).unwrap_err().to_string(),
+		RpcError::Call(RpcCallError::Custom(ErrorObject::owned(
+			4004,
+			Error::HostFunctionProfilingDisabled.to_string(),
+			None::<()>,
+		)))
+		.to_string(),
+	);
+}
+
 #[tokio::test]
 async fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
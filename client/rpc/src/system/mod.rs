@@ -33,7 +33,9 @@ use sp_runtime::traits::{self, Header as HeaderT};
 
 use self::error::Result;
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{
+	BannedPeer, Health, NodeRole, PeerInfo, PeerReputation, ReputationChange, SyncState, SystemInfo,
+};
 pub use sc_rpc_api::system::*;
 
 /// System API implementation
@@ -62,6 +64,15 @@ pub enum Request<B: traits::Block> {
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the list of reserved peers
 	NetworkReservedPeers(oneshot::Sender<Vec<String>>),
+	/// Must return the reputation of the known peers.
+	NetworkPeerReputations(oneshot::Sender<Vec<PeerReputation>>),
+	/// Must ban the peer or IP address for the number of seconds given, and return any
+	/// potential parse error.
+	NetworkBanPeer(String, u64, oneshot::Sender<Result<()>>),
+	/// Must return whether the peer or IP address was banned, or any potential parse error.
+	NetworkUnbanPeer(String, oneshot::Sender<Result<bool>>),
+	/// Must return the list of banned peers and IP addresses.
+	NetworkBannedPeers(oneshot::Sender<Vec<BannedPeer>>),
 	/// Must return the node role.
 	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
 	/// Must return the state of the node syncing.
@@ -166,6 +177,42 @@ impl<B: traits::Block> SystemApiServer<B::Hash, <B::Header as HeaderT>::Number>
 		rx.await.map_err(|e| JsonRpseeError::to_call_error(e))
 	}
 
+	async fn system_peer_reputations(&self) -> RpcResult<Vec<PeerReputation>> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkPeerReputations(tx));
+		rx.await.map_err(|e| JsonRpseeError::to_call_error(e))
+	}
+
+	async fn system_ban_peer(&self, target: String, duration: u64) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkBanPeer(target, duration, tx));
+		match rx.await {
+			Ok(Ok(())) => Ok(()),
+			Ok(Err(e)) => Err(JsonRpseeError::from(e)),
+			Err(e) => Err(JsonRpseeError::to_call_error(e)),
+		}
+	}
+
+	async fn system_unban_peer(&self, target: String) -> RpcResult<bool> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkUnbanPeer(target, tx));
+		match rx.await {
+			Ok(Ok(unbanned)) => Ok(unbanned),
+			Ok(Err(e)) => Err(JsonRpseeError::from(e)),
+			Err(e) => Err(JsonRpseeError::to_call_error(e)),
+		}
+	}
+
+	async fn system_banned_peers(&self) -> RpcResult<Vec<BannedPeer>> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkBannedPeers(tx));
+		rx.await.map_err(|e| JsonRpseeError::to_call_error(e))
+	}
+
 	async fn system_node_roles(&self) -> RpcResult<Vec<NodeRole>> {
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
@@ -25,13 +25,14 @@ use jsonrpsee::{
 	RpcModule,
 };
 use sc_network::{self, config::Role, PeerId};
-use sc_rpc_api::system::helpers::PeerInfo;
+use sc_rpc_api::system::helpers::{BannedPeer, PeerInfo, PeerReputation, ReputationChange};
 use sc_utils::mpsc::tracing_unbounded;
 use sp_core::H256;
 use std::{
 	env,
 	io::{BufRead, BufReader, Write},
 	process::{Command, Stdio},
+	str::FromStr,
 	thread,
 };
 use substrate_test_runtime_client::runtime::Block;
@@ -116,6 +117,35 @@ fn api<T: Into<Option<Status>>>(sync: T) -> RpcModule<System<Block>> {
 					let _ = sender
 						.send(vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()]);
 				},
+				Request::NetworkPeerReputations(sender) => {
+					let _ = sender.send(vec![PeerReputation {
+						peer_id: "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+						reputation: -100,
+						recent_changes: vec![ReputationChange {
+							value: -100,
+							reason: "Bad block".to_string(),
+						}],
+						banned_for: None,
+					}]);
+				},
+				Request::NetworkBanPeer(target, _, sender) => {
+					let _ = match sc_network::BanTarget::from_str(&target) {
+						Ok(_) => sender.send(Ok(())),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s))),
+					};
+				},
+				Request::NetworkUnbanPeer(target, sender) => {
+					let _ = match sc_network::BanTarget::from_str(&target) {
+						Ok(_) => sender.send(Ok(target == "198.51.100.19")),
+						Err(s) => sender.send(Err(error::Error::MalformattedPeerArg(s))),
+					};
+				},
+				Request::NetworkBannedPeers(sender) => {
+					let _ = sender.send(vec![BannedPeer {
+						target: "198.51.100.19".to_string(),
+						expires_at: 1_000_000,
+					}]);
+				},
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				},
@@ -338,6 +368,50 @@ async fn system_network_reserved_peers() {
 	assert_eq!(reserved_peers, vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()],);
 }
 
+#[tokio::test]
+async fn system_peer_reputations() {
+	let reputations: Vec<PeerReputation> =
+		api(None).call("system_peerReputations", EmptyParams::new()).await.unwrap();
+	assert_eq!(reputations.len(), 1);
+	assert_eq!(reputations[0].reputation, -100);
+	assert_eq!(reputations[0].recent_changes[0].reason, "Bad block");
+}
+
+#[tokio::test]
+async fn system_network_ban_peer() {
+	let _good_peer: () = api(None)
+		.call("system_banPeer", ("QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV", 60))
+		.await
+		.expect("call with good peer id works");
+	let _good_ip: () = api(None)
+		.call("system_banPeer", ("198.51.100.19", 60))
+		.await
+		.expect("call with good IP address works");
+
+	assert_matches!(
+		api(None).call::<_, ()>("system_banPeer", ("/ip4/198.51.100.19", 60)).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.message().contains("is neither a peer ID nor an IP address")
+	);
+}
+
+#[tokio::test]
+async fn system_network_unban_peer() {
+	let unbanned: bool = api(None).call("system_unbanPeer", ["198.51.100.19"]).await.unwrap();
+	assert!(unbanned);
+	let unbanned: bool = api(None).call("system_unbanPeer", ["198.51.100.20"]).await.unwrap();
+	assert!(!unbanned);
+}
+
+#[tokio::test]
+async fn system_network_banned_peers() {
+	let bans: Vec<BannedPeer> =
+		api(None).call("system_bannedPeers", EmptyParams::new()).await.unwrap();
+	assert_eq!(
+		bans,
+		vec![BannedPeer { target: "198.51.100.19".to_string(), expires_at: 1_000_000 }]
+	);
+}
+
 #[test]
 fn test_add_reset_log_filter() {
 	const EXPECTED_BEFORE_ADD: &'static str = "EXPECTED_BEFORE_ADD";
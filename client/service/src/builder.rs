@@ -27,27 +27,31 @@ use crate::{
 };
 use futures::{channel::oneshot, future::ready, FutureExt, StreamExt};
 use jsonrpsee::RpcModule;
-use log::info;
+use log::{info, warn};
 use prometheus_endpoint::Registry;
 use sc_chain_spec::get_extension;
 use sc_client_api::{
-	execution_extensions::ExecutionExtensions, proof_provider::ProofProvider, BadBlocks,
+	execution_extensions::ExecutionExtensions, proof_provider::ProofProvider, AuxStore, BadBlocks,
 	BlockBackend, BlockchainEvents, ExecutorProvider, ForkBlocks, StorageProvider, UsageProvider,
 };
-use sc_client_db::{Backend, DatabaseSettings};
+use sc_client_db::{Backend, BlocksPruning, DatabaseSettings, SnapshotStore};
 use sc_consensus::import_queue::ImportQueue;
 use sc_executor::{
-	sp_wasm_interface::HostFunctions, HeapAllocStrategy, NativeElseWasmExecutor,
-	NativeExecutionDispatch, RuntimeVersionOf, WasmExecutor, DEFAULT_HEAP_ALLOC_STRATEGY,
+	sp_wasm_interface::HostFunctions, HeapAllocStrategy, HostFunctionProfiler,
+	NativeElseWasmExecutor, NativeExecutionDispatch, RuntimeVersionOf, WasmExecutor,
+	DEFAULT_HEAP_ALLOC_STRATEGY,
 };
-use sc_keystore::LocalKeystore;
-use sc_network::{config::SyncMode, NetworkService, NetworkStateInfo, NetworkStatusProvider};
+use sc_keystore::{LocalKeystore, RemoteKeystore};
+use sc_network::{config::SyncMode, NetworkBackend, NetworkStateInfo, NetworkStatusProvider};
 use sc_network_bitswap::BitswapRequestHandler;
 use sc_network_common::{role::Roles, sync::warp::WarpSyncParams};
-use sc_network_light::light_client_requests::handler::LightClientRequestHandler;
+use sc_network_light::light_client_requests::{
+	handler::LightClientRequestHandler, handler_v2::LightClientRequestHandlerV2,
+};
 use sc_network_sync::{
 	block_request_handler::BlockRequestHandler, engine::SyncingEngine,
-	service::network::NetworkServiceProvider, state_request_handler::StateRequestHandler,
+	service::network::NetworkServiceProvider, snapshot_request_handler::SnapshotRequestHandler,
+	state_request_handler::StateRequestHandler,
 	warp_request_handler::RequestHandler as WarpSyncRequestHandler, SyncingService,
 };
 use sc_rpc::{
@@ -58,7 +62,9 @@ use sc_rpc::{
 	system::SystemApiServer,
 	DenyUnsafe, SubscriptionTaskExecutor,
 };
-use sc_rpc_spec_v2::{chain_head::ChainHeadApiServer, transaction::TransactionApiServer};
+use sc_rpc_spec_v2::{
+	archive::ArchiveApiServer, chain_head::ChainHeadApiServer, transaction::TransactionApiServer,
+};
 use sc_telemetry::{telemetry, ConnectionMessage, Telemetry, TelemetryHandle, SUBSTRATE_INFO};
 use sc_transaction_pool_api::{MaintainedTransactionPool, TransactionPool};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
@@ -69,9 +75,12 @@ use sp_consensus::block_validation::{
 };
 use sp_core::traits::{CodeExecutor, SpawnNamed};
 use sp_keystore::KeystorePtr;
-use sp_runtime::traits::{Block as BlockT, BlockIdTo, NumberFor, Zero};
+use sp_runtime::traits::{Block as BlockT, BlockIdTo, Header as HeaderT, NumberFor, Zero};
 use std::{str::FromStr, sync::Arc, time::SystemTime};
 
+/// Number of snapshots of finalized state kept on disk.
+const SNAPSHOTS_KEPT: usize = 2;
+
 /// Full client type.
 pub type TFullClient<TBl, TRtApi, TExec> =
 	Client<TFullBackend<TBl>, TFullCallExecutor<TBl, TExec>, TBl, TRtApi>;
@@ -85,29 +94,39 @@ pub type TFullCallExecutor<TBl, TExec> = crate::client::LocalCallExecutor<TBl, B
 type TFullParts<TBl, TRtApi, TExec> =
 	(TFullClient<TBl, TRtApi, TExec>, Arc<TFullBackend<TBl>>, KeystoreContainer, TaskManager);
 
-/// Construct a local keystore shareable container
-pub struct KeystoreContainer(Arc<LocalKeystore>);
+/// Construct a keystore shareable container
+pub struct KeystoreContainer {
+	keystore: KeystorePtr,
+	local: Option<Arc<LocalKeystore>>,
+}
 
 impl KeystoreContainer {
 	/// Construct KeystoreContainer
 	pub fn new(config: &KeystoreConfig) -> Result<Self, Error> {
-		let keystore = Arc::new(match config {
-			KeystoreConfig::Path { path, password } =>
-				LocalKeystore::open(path.clone(), password.clone())?,
-			KeystoreConfig::InMemory => LocalKeystore::in_memory(),
-		});
+		let local = match config {
+			KeystoreConfig::Path { path, password, passphrase: None } =>
+				Arc::new(LocalKeystore::open(path.clone(), password.clone())?),
+			KeystoreConfig::Path { path, password, passphrase: Some(passphrase) } => Arc::new(
+				LocalKeystore::open_encrypted(path.clone(), password.clone(), passphrase.clone())?,
+			),
+			KeystoreConfig::InMemory => Arc::new(LocalKeystore::in_memory()),
+			KeystoreConfig::Remote { uri } =>
+				return Ok(Self { keystore: RemoteKeystore::open(uri)?.into(), local: None }),
+		};
 
-		Ok(Self(keystore))
+		Ok(Self { keystore: local.clone(), local: Some(local) })
 	}
 
 	/// Returns a shared reference to a dynamic `Keystore` trait implementation.
 	pub fn keystore(&self) -> KeystorePtr {
-		self.0.clone()
+		self.keystore.clone()
 	}
 
-	/// Returns a shared reference to the local keystore .
-	pub fn local_keystore(&self) -> Arc<LocalKeystore> {
-		self.0.clone()
+	/// Returns a shared reference to the local keystore.
+	///
+	/// Returns `None` if the keys are held by a remote signer.
+	pub fn local_keystore(&self) -> Option<Arc<LocalKeystore>> {
+		self.local.clone()
 	}
 }
 
@@ -243,13 +262,22 @@ pub fn new_wasm_executor<H: HostFunctions>(config: &Configuration) -> WasmExecut
 	let strategy = config
 		.default_heap_pages
 		.map_or(DEFAULT_HEAP_ALLOC_STRATEGY, |p| HeapAllocStrategy::Static { extra_pages: p as _ });
-	WasmExecutor::<H>::builder()
+	let mut builder = WasmExecutor::<H>::builder()
 		.with_execution_method(config.wasm_method)
 		.with_onchain_heap_alloc_strategy(strategy)
 		.with_offchain_heap_alloc_strategy(strategy)
 		.with_max_runtime_instances(config.max_runtime_instances)
-		.with_runtime_cache_size(config.runtime_cache_size)
-		.build()
+		.with_runtime_cache_size(config.runtime_cache_size);
+	// Compiled runtimes are kept next to the database of the chain, to be reused across restarts.
+	if let Some(base_path) = config.base_path.as_ref() {
+		builder = builder
+			.with_cache_path(base_path.config_dir(config.chain_spec.id()).join("wasm-cache"));
+	}
+	if config.profile_host_functions {
+		builder = builder
+			.with_host_function_profiler(HostFunctionProfiler::new(config.prometheus_registry()));
+	}
+	builder.build()
 }
 
 /// Create an instance of default DB-backend backend.
@@ -477,6 +505,22 @@ where
 		),
 	);
 
+	if config.network.serve_snapshots {
+		if let Some(snapshots) = SnapshotStore::in_database(&config.database, SNAPSHOTS_KEPT) {
+			// Exporting a snapshot reads the whole state.
+			spawn_handle.spawn_blocking(
+				"snapshot-export",
+				None,
+				export_snapshots(
+					client.clone(),
+					backend.clone(),
+					snapshots,
+					config.network.snapshot_period,
+				),
+			);
+		}
+	}
+
 	// Prometheus metrics.
 	let metrics_service =
 		if let Some(PrometheusConfig { port, registry }) = config.prometheus_config.clone() {
@@ -570,6 +614,35 @@ async fn transaction_notifications<Block, ExPool>(
 		.await;
 }
 
+/// Export the snapshot of every finalized block whose number is a multiple of `period`.
+async fn export_snapshots<Block, Client, BE>(
+	client: Arc<Client>,
+	backend: Arc<BE>,
+	snapshots: SnapshotStore,
+	period: u32,
+) where
+	Block: BlockT,
+	Client: BlockchainEvents<Block> + HeaderBackend<Block>,
+	BE: sc_client_api::backend::Backend<Block>,
+{
+	let period = NumberFor::<Block>::from(period.max(1));
+	let mut last_export = Zero::zero();
+	let mut finality_notifications = client.finality_notification_stream();
+	while let Some(notification) = finality_notifications.next().await {
+		let number = *notification.header.number();
+		let snapshot_number = number - number % period;
+		if snapshot_number <= last_export {
+			continue
+		}
+		last_export = snapshot_number;
+
+		let Some(hash) = client.hash(snapshot_number).ok().flatten() else { continue };
+		if let Err(e) = snapshots.export(&*backend, hash) {
+			warn!("Failed to export the snapshot of block #{}: {}", snapshot_number, e);
+		}
+	}
+}
+
 fn init_telemetry<Block, Client, Network>(
 	config: &mut Configuration,
 	network: Network,
@@ -680,6 +753,15 @@ where
 	)
 	.into_rpc();
 
+	let archive_v2 = sc_rpc_spec_v2::archive::Archive::new(
+		client.clone(),
+		backend.clone(),
+		task_executor.clone(),
+		client.info().genesis_hash,
+		Default::default(),
+	)
+	.into_rpc();
+
 	let author = sc_rpc::author::Author::new(
 		client.clone(),
 		transaction_pool,
@@ -700,6 +782,7 @@ where
 	// Part of the RPC v2 spec.
 	rpc_api.merge(transaction_v2).map_err(|e| Error::Application(e.into()))?;
 	rpc_api.merge(chain_head_v2).map_err(|e| Error::Application(e.into()))?;
+	rpc_api.merge(archive_v2).map_err(|e| Error::Application(e.into()))?;
 
 	// Part of the old RPC spec.
 	rpc_api.merge(chain).map_err(|e| Error::Application(e.into()))?;
@@ -733,11 +816,13 @@ pub struct BuildNetworkParams<'a, TBl: BlockT, TExPool, TImpQu, TCl> {
 	pub warp_sync_params: Option<WarpSyncParams<TBl>>,
 }
 /// Build the network service, the network status sinks and an RPC sender.
-pub fn build_network<TBl, TExPool, TImpQu, TCl>(
+///
+/// The networking itself is provided by `TNet`, usually [`sc_network::NetworkWorker`].
+pub fn build_network<TBl, TNet, TExPool, TImpQu, TCl>(
 	params: BuildNetworkParams<TBl, TExPool, TImpQu, TCl>,
 ) -> Result<
 	(
-		Arc<NetworkService<TBl, <TBl as BlockT>::Hash>>,
+		TNet::NetworkService,
 		TracingUnboundedSender<sc_rpc::system::Request<TBl>>,
 		sc_network_transactions::TransactionsHandlerController<<TBl as BlockT>::Hash>,
 		NetworkStarter,
@@ -747,6 +832,7 @@ pub fn build_network<TBl, TExPool, TImpQu, TCl>(
 >
 where
 	TBl: BlockT,
+	TNet: NetworkBackend<TBl, <TBl as BlockT>::Hash>,
 	TCl: ProvideRuntimeApi<TBl>
 		+ HeaderMetadata<TBl, Error = sp_blockchain::Error>
 		+ Chain<TBl>
@@ -755,6 +841,7 @@ where
 		+ ProofProvider<TBl>
 		+ HeaderBackend<TBl>
 		+ BlockchainEvents<TBl>
+		+ AuxStore
 		+ 'static,
 	TExPool: TransactionPool<Block = TBl, Hash = <TBl as BlockT>::Hash> + 'static,
 	TImpQu: ImportQueue<TBl> + 'static,
@@ -816,6 +903,44 @@ where
 		protocol_config
 	};
 
+	let snapshots = config
+		.network
+		.serve_snapshots
+		.then(|| SnapshotStore::in_database(&config.database, SNAPSHOTS_KEPT))
+		.flatten();
+	if config.network.serve_snapshots && snapshots.is_none() {
+		warn!("Snapshots are only served from a database stored on disk");
+	}
+	let snapshot_request_protocol_config = match snapshots {
+		Some(snapshots) => {
+			// Allow both outgoing and incoming requests.
+			let (handler, protocol_config) = SnapshotRequestHandler::<TBl>::new(
+				&protocol_id,
+				config.chain_spec.fork_id(),
+				client.clone(),
+				Arc::new(snapshots),
+				config.network.default_peers_set_num_full as usize,
+			);
+			// Chunks are read from disk.
+			spawn_handle.spawn_blocking(
+				"snapshot-request-handler",
+				Some("networking"),
+				handler.run(),
+			);
+			protocol_config
+		},
+		// Allow only outgoing requests.
+		None => sc_network_sync::snapshot_request_handler::generate_protocol_config(
+			&protocol_id,
+			client
+				.block_hash(0u32.into())
+				.ok()
+				.flatten()
+				.expect("Genesis block exists; qed"),
+			config.chain_spec.fork_id(),
+		),
+	};
+
 	let warp_sync_protocol_config = match warp_sync_params.as_ref() {
 		Some(WarpSyncParams::WithProvider(warp_with_provider)) => {
 			// Allow both outgoing and incoming requests.
@@ -846,13 +971,30 @@ where
 		protocol_config
 	};
 
+	let light_client_request_protocol_config_v2 = {
+		// Allow both outgoing and incoming requests.
+		let (handler, protocol_config) = LightClientRequestHandlerV2::new(
+			&protocol_id,
+			config.chain_spec.fork_id(),
+			client.clone(),
+		);
+		spawn_handle.spawn("light-client-request-handler-v2", Some("networking"), handler.run());
+		protocol_config
+	};
+
+	let mut network_config = config.network.clone();
+	if let BlocksPruning::Some(depth) = config.blocks_pruning {
+		// Bodies older than the pruning window would be discarded right after import.
+		network_config.gap_sync_mode = network_config.gap_sync_mode.limit_body_depth(depth);
+	}
+
 	let (tx, rx) = sc_utils::mpsc::tracing_unbounded("mpsc_syncing_engine_protocol", 100_000);
 	let (chain_sync_network_provider, chain_sync_network_handle) = NetworkServiceProvider::new();
 	let (engine, sync_service, block_announce_config) = SyncingEngine::new(
 		Roles::from(&config.role),
 		client.clone(),
 		config.prometheus_config.as_ref().map(|config| config.registry.clone()).as_ref(),
-		&config.network,
+		&network_config,
 		protocol_id.clone(),
 		&config.chain_spec.fork_id().map(ToOwned::to_owned),
 		block_announce_validator,
@@ -862,6 +1004,7 @@ where
 		block_request_protocol_config.name.clone(),
 		state_request_protocol_config.name.clone(),
 		warp_sync_protocol_config.as_ref().map(|config| config.name.clone()),
+		Some(snapshot_request_protocol_config.name.clone()),
 		rx,
 	)?;
 	let sync_service_import_queue = sync_service.clone();
@@ -882,7 +1025,7 @@ where
 				spawn_handle.spawn("libp2p-node", Some("networking"), fut);
 			})
 		},
-		network_config: config.network.clone(),
+		network_config,
 		genesis_hash,
 		protocol_id: protocol_id.clone(),
 		fork_id: config.chain_spec.fork_id().map(ToOwned::to_owned),
@@ -894,7 +1037,9 @@ where
 			.chain([
 				Some(block_request_protocol_config),
 				Some(state_request_protocol_config),
+				Some(snapshot_request_protocol_config),
 				Some(light_client_request_protocol_config),
+				Some(light_client_request_protocol_config_v2),
 				warp_sync_protocol_config,
 			])
 			.flatten()
@@ -917,8 +1062,8 @@ where
 		.insert(0, transactions_handler_proto.set_config());
 
 	let has_bootnodes = !network_params.network_config.boot_nodes.is_empty();
-	let network_mut = sc_network::NetworkWorker::new(network_params)?;
-	let network = network_mut.service().clone();
+	let network_mut = TNet::new(network_params)?;
+	let network = network_mut.network_service();
 
 	let (tx_handler, tx_handler_controller) = transactions_handler_proto.build(
 		network.clone(),
@@ -931,7 +1076,7 @@ where
 	spawn_handle.spawn_blocking(
 		"chain-sync-network-service-provider",
 		Some("networking"),
-		chain_sync_network_provider.run(network.clone()),
+		chain_sync_network_provider.run(Arc::new(network.clone())),
 	);
 	spawn_handle.spawn("import-queue", None, import_queue.run(Box::new(sync_service_import_queue)));
 	spawn_handle.spawn_blocking("syncing", None, engine.run());
@@ -942,7 +1087,7 @@ where
 		Some("networking"),
 		build_system_rpc_future(
 			config.role.clone(),
-			network_mut.service().clone(),
+			network.clone(),
 			sync_service.clone(),
 			client.clone(),
 			system_rpc_rx,
@@ -199,7 +199,7 @@ where
 		.set_parent_hash(at_hash);
 
 		sm.execute_using_consensus_failure_handler(strategy.get_manager())
-			.map_err(Into::into)
+			.map_err(call_error)
 	}
 
 	fn contextual_call(
@@ -220,6 +220,7 @@ where
 
 		let call_context = match context {
 			ExecutionContext::OffchainCall(_) => CallContext::Offchain,
+			ExecutionContext::UntrustedCall => CallContext::Untrusted,
 			_ => CallContext::Onchain,
 		};
 
@@ -357,6 +358,17 @@ where
 	}
 }
 
+/// Converts the error of a runtime call, singling out the calls that the executor aborted for
+/// exceeding their resource limits.
+fn call_error(error: Box<dyn sp_state_machine::Error>) -> sp_blockchain::Error {
+	// Deref the box, `Box<dyn Error>` implements `Error` as well.
+	match (*error).as_any().downcast_ref::<sc_executor::error::Error>() {
+		Some(sc_executor::error::Error::ResourceLimitExceeded(limit)) =>
+			sp_blockchain::Error::ResourceLimitExceeded(limit.to_string()),
+		_ => error.into(),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -38,8 +38,8 @@ use sc_client_api::{
 	},
 	execution_extensions::ExecutionExtensions,
 	notifications::{StorageEventStream, StorageNotifications},
-	CallExecutor, ExecutorProvider, KeysIter, OnFinalityAction, OnImportAction, PairsIter,
-	ProofProvider, UsageProvider,
+	CallExecutor, ExecutorProvider, KeysIter, MerkleValue, OnFinalityAction, OnImportAction,
+	PairsIter, ProofProvider, TrieDiff, UsageProvider,
 };
 use sc_consensus::{
 	BlockCheckParams, BlockImportParams, ForkChoiceStrategy, ImportResult, StateAction,
@@ -75,10 +75,10 @@ use sp_runtime::{
 	Digest, Justification, Justifications, StateVersion,
 };
 use sp_state_machine::{
-	prove_child_read, prove_range_read_with_child_with_size, prove_read,
-	read_range_proof_check_with_child_on_proving_backend, Backend as StateBackend,
-	ChildStorageCollection, KeyValueStates, KeyValueStorageLevel, StorageCollection,
-	MAX_NESTED_TRIE_DEPTH,
+	backend::AsTrieBackend, prove_child_read, prove_range_read_with_child_with_size,
+	prove_range_read_with_size, prove_read, read_range_proof_check_with_child_on_proving_backend,
+	Backend as StateBackend, ChildStorageCollection, KeyValueStates, KeyValueStorageLevel,
+	StorageCollection, MAX_NESTED_TRIE_DEPTH,
 };
 use sp_trie::{CompactProof, StorageProof};
 use std::{
@@ -1249,6 +1249,22 @@ where
 			.and_then(|state| prove_child_read(state, child_info, keys).map_err(Into::into))
 	}
 
+	fn read_range_proof(
+		&self,
+		hash: Block::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_at: Option<&[u8]>,
+		size_limit: usize,
+	) -> sp_blockchain::Result<(StorageProof, u32)> {
+		self.state_at(hash).and_then(|state| {
+			prove_range_read_with_size::<_, HashFor<Block>>(
+				state, child_info, prefix, size_limit, start_at,
+			)
+			.map_err(Into::into)
+		})
+	}
+
 	fn execution_proof(
 		&self,
 		hash: Block::Hash,
@@ -1556,6 +1572,51 @@ where
 			.child_storage_hash(child_info, &key.0)
 			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
 	}
+
+	fn closest_merkle_value(
+		&self,
+		hash: <Block as BlockT>::Hash,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>> {
+		self.state_at(hash)?
+			.as_trie_backend()
+			.essence()
+			.closest_merkle_value(&key.0)
+			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
+
+	fn child_closest_merkle_value(
+		&self,
+		hash: <Block as BlockT>::Hash,
+		child_info: &ChildInfo,
+		key: &StorageKey,
+	) -> sp_blockchain::Result<Option<MerkleValue<Block::Hash>>> {
+		self.state_at(hash)?
+			.as_trie_backend()
+			.essence()
+			.child_closest_merkle_value(child_info, &key.0)
+			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
+
+	fn storage_diff(
+		&self,
+		hash: <Block as BlockT>::Hash,
+		previous_hash: <Block as BlockT>::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&StorageKey>,
+		fetch_values: bool,
+		on_diff: &mut dyn FnMut(TrieDiff<Block::Hash>) -> bool,
+	) -> sp_blockchain::Result<()> {
+		let previous_state = self.state_at(previous_hash)?;
+		let state = self.state_at(hash)?;
+		let prefix = prefix.map(|p| &p.0[..]).unwrap_or_default();
+
+		previous_state
+			.as_trie_backend()
+			.essence()
+			.diff(state.as_trie_backend().essence(), child_info, prefix, fetch_values, on_diff)
+			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA>
@@ -34,8 +34,12 @@ pub use sc_network::{
 
 use prometheus_endpoint::Registry;
 use sc_chain_spec::ChainSpec;
+pub use sc_rpc_server::{
+	MethodFilter as RpcMethodFilter, RateLimit as RpcRateLimit,
+	RateLimitConfig as RpcRateLimitConfig,
+};
 pub use sc_telemetry::TelemetryEndpoints;
-pub use sc_transaction_pool::Options as TransactionPoolOptions;
+pub use sc_transaction_pool::{TransactionPoolOptions, TransactionPoolType};
 use sp_core::crypto::SecretString;
 use std::{
 	io, iter,
@@ -67,6 +71,10 @@ pub struct Configuration {
 	///
 	/// If `None` is given the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// Maximum size in bytes of the snapshot of the trie cache kept across restarts.
+	///
+	/// If `None` is given no snapshot is written or restored.
+	pub trie_cache_snapshot_maximum_size: Option<usize>,
 	/// State pruning settings.
 	pub state_pruning: Option<PruningMode>,
 	/// Number of blocks to keep in the db.
@@ -109,6 +117,10 @@ pub struct Configuration {
 	///
 	/// Default: 1024.
 	pub rpc_max_subs_per_conn: Option<usize>,
+	/// Rate limits applied to RPC requests. Disabled by default.
+	pub rpc_rate_limit: RpcRateLimitConfig,
+	/// Methods exposed by the RPC servers, on top of `rpc_methods`. All methods by default.
+	pub rpc_method_filter: RpcMethodFilter,
 	/// Maximum size of the output buffer capacity for websocket connections.
 	pub ws_max_out_buffer_capacity: Option<usize>,
 	/// Prometheus endpoint configuration. `None` if disabled.
@@ -146,6 +158,8 @@ pub struct Configuration {
 	pub informant_output_format: sc_informant::OutputFormat,
 	/// Maximum number of different runtime versions that can be cached.
 	pub runtime_cache_size: u8,
+	/// Time every call of the runtime into a host function.
+	pub profile_host_functions: bool,
 }
 
 /// Type for tasks spawned by the executor.
@@ -166,9 +180,17 @@ pub enum KeystoreConfig {
 		path: PathBuf,
 		/// Node keystore's password.
 		password: Option<SecretString>,
+		/// Passphrase the key files are encrypted with at rest.
+		passphrase: Option<SecretString>,
 	},
 	/// In-memory keystore. Recommended for in-browser nodes.
 	InMemory,
+	/// Keystore forwarding all requests to a remote signer holding the keys.
+	Remote {
+		/// The URI of the remote signer, e.g. `unix:///run/signer.sock` or
+		/// `http://127.0.0.1:9955`.
+		uri: String,
+	},
 }
 
 impl KeystoreConfig {
@@ -176,7 +198,7 @@ impl KeystoreConfig {
 	pub fn path(&self) -> Option<&Path> {
 		match self {
 			Self::Path { path, .. } => Some(path),
-			Self::InMemory => None,
+			Self::InMemory | Self::Remote { .. } => None,
 		}
 	}
 }
@@ -247,6 +269,7 @@ impl Configuration {
 	pub fn db_config(&self) -> sc_client_db::DatabaseSettings {
 		sc_client_db::DatabaseSettings {
 			trie_cache_maximum_size: self.trie_cache_maximum_size,
+			trie_cache_snapshot_maximum_size: self.trie_cache_snapshot_maximum_size,
 			state_pruning: self.state_pruning.clone(),
 			source: self.database.clone(),
 			blocks_pruning: self.blocks_pruning,
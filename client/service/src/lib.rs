@@ -34,7 +34,12 @@ mod client;
 mod metrics;
 mod task_manager;
 
-use std::{collections::HashMap, net::SocketAddr};
+use std::{
+	collections::HashMap,
+	net::SocketAddr,
+	str::FromStr,
+	time::{Duration, UNIX_EPOCH},
+};
 
 use codec::{Decode, Encode};
 use futures::{channel::mpsc, pin_mut, FutureExt, StreamExt};
@@ -42,7 +47,8 @@ use jsonrpsee::{core::Error as JsonRpseeError, RpcModule};
 use log::{debug, error, warn};
 use sc_client_api::{blockchain::HeaderBackend, BlockBackend, BlockchainEvents, ProofProvider};
 use sc_network::{
-	config::MultiaddrWithPeerId, NetworkBlock, NetworkPeers, NetworkStateInfo, PeerId,
+	config::MultiaddrWithPeerId, BanTarget, NetworkBackend, NetworkBlock, NetworkPeerAdmin,
+	NetworkPeers, NetworkStateInfo, PeerId,
 };
 use sc_network_sync::SyncingService;
 use sc_utils::mpsc::TracingUnboundedReceiver;
@@ -86,7 +92,7 @@ pub use sc_rpc::{
 	RandomIntegerSubscriptionId, RandomStringSubscriptionId, RpcSubscriptionIdProvider,
 };
 pub use sc_tracing::TracingReceiver;
-pub use sc_transaction_pool::Options as TransactionPoolOptions;
+pub use sc_transaction_pool::{TransactionPoolOptions, TransactionPoolType};
 pub use sc_transaction_pool_api::{error::IntoPoolError, InPoolTransaction, TransactionPool};
 #[doc(hidden)]
 pub use std::{ops::Deref, result::Result, sync::Arc};
@@ -156,8 +162,9 @@ async fn build_network_future<
 		+ Sync
 		+ 'static,
 	H: sc_network_common::ExHashT,
+	N: NetworkBackend<B, H>,
 >(
-	network: sc_network::NetworkWorker<B, H>,
+	network: N,
 	client: Arc<C>,
 	sync_service: Arc<SyncingService<B>>,
 	announce_imported_blocks: bool,
@@ -201,9 +208,9 @@ async fn build_network_future<
 				sync_service.on_block_finalized(notification.hash, notification.header);
 			}
 
-			// Drive the network. Shut down the network future if `NetworkWorker` has terminated.
+			// Drive the network. Shut down the network future if the backend has terminated.
 			_ = network_run => {
-				debug!("`NetworkWorker` has terminated, shutting down the network future.");
+				debug!("The network backend has terminated, shutting down the network future.");
 				return
 			}
 		}
@@ -221,10 +228,10 @@ async fn build_system_rpc_future<
 		+ Send
 		+ Sync
 		+ 'static,
-	H: sc_network_common::ExHashT,
+	N: NetworkStateInfo + NetworkPeers + NetworkPeerAdmin,
 >(
 	role: Role,
-	network_service: Arc<sc_network::NetworkService<B, H>>,
+	network_service: N,
 	sync_service: Arc<SyncingService<B>>,
 	client: Arc<C>,
 	mut rpc_rx: TracingUnboundedReceiver<sc_rpc::system::Request<B>>,
@@ -318,6 +325,63 @@ async fn build_system_rpc_future<
 					break
 				}
 			},
+			sc_rpc::system::Request::NetworkPeerReputations(sender) => {
+				use sc_rpc::system::{PeerReputation, ReputationChange};
+
+				let Ok(reputations) = network_service.peer_reputations().await else { break };
+				let reputations = reputations
+					.into_iter()
+					.map(|peer| PeerReputation {
+						peer_id: peer.peer_id.to_base58(),
+						reputation: peer.reputation,
+						recent_changes: peer
+							.recent_changes
+							.into_iter()
+							.map(|change| ReputationChange {
+								value: change.value,
+								reason: change.reason.into(),
+							})
+							.collect(),
+						banned_for: peer.banned_for.map(|duration| duration.as_secs()),
+					})
+					.collect();
+				let _ = sender.send(reputations);
+			},
+			sc_rpc::system::Request::NetworkBanPeer(target, duration, sender) => {
+				let _ = match BanTarget::from_str(&target) {
+					Ok(target) => {
+						network_service.ban(target, Duration::from_secs(duration));
+						sender.send(Ok(()))
+					},
+					Err(e) =>
+						sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(e))),
+				};
+			},
+			sc_rpc::system::Request::NetworkUnbanPeer(target, sender) => {
+				let _ = match BanTarget::from_str(&target) {
+					Ok(target) => {
+						let Ok(unbanned) = network_service.unban(target).await else { break };
+						sender.send(Ok(unbanned))
+					},
+					Err(e) =>
+						sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(e))),
+				};
+			},
+			sc_rpc::system::Request::NetworkBannedPeers(sender) => {
+				let Ok(bans) = network_service.bans().await else { break };
+				let bans = bans
+					.into_iter()
+					.map(|ban| sc_rpc::system::BannedPeer {
+						target: ban.target.to_string(),
+						expires_at: ban
+							.expires_at
+							.duration_since(UNIX_EPOCH)
+							.unwrap_or_default()
+							.as_secs(),
+					})
+					.collect();
+				let _ = sender.send(bans);
+			},
 			sc_rpc::system::Request::NodeRoles(sender) => {
 				use sc_rpc::system::NodeRole;
 
@@ -346,7 +410,7 @@ async fn build_system_rpc_future<
 		}
 	}
 
-	debug!("`NetworkWorker` has terminated, shutting down the system RPC future.");
+	debug!("The network backend has terminated, shutting down the system RPC future.");
 }
 
 // Wrapper for HTTP and WS servers that makes sure they are properly shut down.
@@ -412,6 +476,8 @@ where
 		config.rpc_cors.as_ref(),
 		max_request_size,
 		http_max_response_size,
+		config.rpc_rate_limit.clone(),
+		&config.rpc_method_filter,
 		metrics.clone(),
 		gen_rpc_module(deny_unsafe(http_addr, &config.rpc_methods))?,
 		config.tokio_handle.clone(),
@@ -421,6 +487,8 @@ where
 		[ws_addr, ws_addr2],
 		config.rpc_cors.as_ref(),
 		server_config.clone(),
+		config.rpc_rate_limit.clone(),
+		&config.rpc_method_filter,
 		metrics.clone(),
 		gen_rpc_module(deny_unsafe(ws_addr, &config.rpc_methods))?,
 		config.tokio_handle.clone(),
@@ -228,7 +228,7 @@ fn node_config<
 	);
 
 	network_config.transport =
-		TransportConfig::Normal { enable_mdns: false, allow_private_ip: true };
+		TransportConfig::Normal { enable_mdns: false, allow_private_ip: true, enable_quic: false };
 
 	Configuration {
 		impl_name: String::from("network-test-impl"),
@@ -237,9 +237,10 @@ fn node_config<
 		tokio_handle,
 		transaction_pool: Default::default(),
 		network: network_config,
-		keystore: KeystoreConfig::Path { path: root.join("key"), password: None },
+		keystore: KeystoreConfig::Path { path: root.join("key"), password: None, passphrase: None },
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(16 * 1024 * 1024),
+		trie_cache_snapshot_maximum_size: None,
 		state_pruning: Default::default(),
 		blocks_pruning: BlocksPruning::KeepFinalized,
 		chain_spec: Box::new((*spec).clone()),
@@ -257,6 +258,8 @@ fn node_config<
 		rpc_max_response_size: None,
 		rpc_id_provider: None,
 		rpc_max_subs_per_conn: None,
+		rpc_rate_limit: Default::default(),
+		rpc_method_filter: Default::default(),
 		ws_max_out_buffer_capacity: None,
 		prometheus_config: None,
 		telemetry_endpoints: None,
@@ -272,6 +275,7 @@ fn node_config<
 		base_path: Some(BasePath::new(root)),
 		informant_output_format: Default::default(),
 		runtime_cache_size: 2,
+		profile_host_functions: false,
 	}
 }
 
@@ -46,11 +46,11 @@ mod pruning;
 #[cfg(test)]
 mod test;
 
-use codec::Codec;
+use codec::{Codec, Decode};
 use log::trace;
-use noncanonical::NonCanonicalOverlay;
+use noncanonical::{NonCanonicalOverlay, LAST_CANONICAL};
 use parking_lot::RwLock;
-use pruning::{HaveBlock, RefWindow};
+use pruning::{HaveBlock, RefWindow, LAST_PRUNED};
 use std::{
 	collections::{hash_map::Entry, HashMap},
 	fmt,
@@ -282,6 +282,85 @@ impl Default for Constraints {
 	}
 }
 
+/// Journal record of a block of the non-canonical overlay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonCanonicalJournalRecord<BlockHash: Hash, Key: Hash> {
+	/// Block number.
+	pub number: u64,
+	/// Index of the block among the blocks of the same number.
+	pub index: u64,
+	/// Block hash.
+	pub hash: BlockHash,
+	/// Parent block hash.
+	pub parent_hash: BlockHash,
+	/// Nodes inserted by the block, not yet written to the backing database.
+	pub inserted: Vec<(Key, DBValue)>,
+	/// Nodes deleted by the block.
+	pub deleted: Vec<Key>,
+}
+
+/// Journal record of a canonical block of the pruning window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PruningJournalRecord<BlockHash: Hash, Key: Hash> {
+	/// Block number.
+	pub number: u64,
+	/// Block hash.
+	pub hash: BlockHash,
+	/// Nodes inserted by the block. Only recorded when the backing database doesn't support
+	/// reference counting.
+	pub inserted: Vec<Key>,
+	/// Nodes to delete from the backing database when the block is pruned.
+	pub deleted: Vec<Key>,
+}
+
+/// Records kept by the state database in the backing database, see [`read_journals`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journals<BlockHash: Hash, Key: Hash> {
+	/// Stored pruning mode. The constraints of [`PruningMode::Constrained`] are not stored.
+	pub pruning_mode: Option<PruningMode>,
+	/// Last canonicalized block.
+	pub last_canonicalized: Option<(BlockHash, u64)>,
+	/// Number of the last pruned block.
+	pub last_pruned: Option<u64>,
+	/// Journal of the non-canonical overlay, ordered by block number.
+	pub non_canonical: Vec<NonCanonicalJournalRecord<BlockHash, Key>>,
+	/// Journal of the pruning window, ordered by block number.
+	pub pruning: Vec<PruningJournalRecord<BlockHash, Key>>,
+}
+
+/// Reads the records of the state database without opening it, e.g. to inspect a database
+/// offline.
+///
+/// Pinned blocks are only tracked in memory by a running [`StateDb`] and are never part of the
+/// records.
+pub fn read_journals<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+) -> Result<Journals<BlockHash, Key>, Error<D::Error>> {
+	let pruning_mode = fetch_stored_pruning_mode(db)?;
+	let last_canonicalized = db
+		.get_meta(&to_meta_key(LAST_CANONICAL, &()))
+		.map_err(Error::Db)?
+		.map(|buffer| <(BlockHash, u64)>::decode(&mut buffer.as_slice()))
+		.transpose()?;
+	let last_pruned = db
+		.get_meta(&to_meta_key(LAST_PRUNED, &()))
+		.map_err(Error::Db)?
+		.map(|buffer| u64::decode(&mut buffer.as_slice()))
+		.transpose()?;
+
+	let non_canonical = match &last_canonicalized {
+		Some((_, number)) => noncanonical::read_journal(db, *number)?,
+		None => Vec::new(),
+	};
+	let pruning = match pruning_mode {
+		Some(PruningMode::Constrained(_)) =>
+			pruning::read_journal(db, last_pruned.map_or(0, |number| number + 1))?,
+		_ => Vec::new(),
+	};
+
+	Ok(Journals { pruning_mode, last_canonicalized, last_pruned, non_canonical, pruning })
+}
+
 fn to_meta_key<S: Codec>(suffix: &[u8], data: &S) -> Vec<u8> {
 	let mut buffer = data.encode();
 	buffer.extend(suffix);
@@ -705,6 +784,7 @@ fn choose_pruning_mode(
 #[cfg(test)]
 mod tests {
 	use crate::{
+		read_journals,
 		test::{make_changeset, make_db, TestDb},
 		Constraints, Error, IsPruned, PruningMode, StateDb, StateDbError,
 	};
@@ -847,6 +927,36 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn journals_are_read_back() {
+		let (db, _) = make_test_db(PruningMode::Constrained(Constraints { max_blocks: Some(2) }));
+		let journals = read_journals::<H256, H256, _>(&db).unwrap();
+
+		assert!(matches!(journals.pruning_mode, Some(PruningMode::Constrained(_))));
+		assert_eq!(journals.last_canonicalized, Some((H256::from_low_u64_be(3), 3)));
+		assert_eq!(journals.last_pruned, Some(1));
+
+		let non_canonical = &journals.non_canonical;
+		assert_eq!(non_canonical.len(), 1);
+		assert_eq!(non_canonical[0].number, 4);
+		assert_eq!(non_canonical[0].hash, H256::from_low_u64_be(4));
+		assert_eq!(non_canonical[0].parent_hash, H256::from_low_u64_be(3));
+		assert_eq!(non_canonical[0].deleted, vec![H256::from_low_u64_be(94)]);
+
+		let pruning = journals
+			.pruning
+			.iter()
+			.map(|record| (record.number, record.hash, record.deleted.clone()))
+			.collect::<Vec<_>>();
+		assert_eq!(
+			pruning,
+			vec![
+				(2, H256::from_low_u64_be(21), make_changeset(&[], &[921, 1]).deleted),
+				(3, H256::from_low_u64_be(3), make_changeset(&[], &[93]).deleted),
+			]
+		);
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
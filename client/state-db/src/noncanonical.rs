@@ -22,7 +22,10 @@
 
 use crate::{LOG_TARGET, LOG_TARGET_PIN};
 
-use super::{to_meta_key, ChangeSet, CommitSet, DBValue, Error, Hash, MetaDb, StateDbError};
+use super::{
+	to_meta_key, ChangeSet, CommitSet, DBValue, Error, Hash, MetaDb, NonCanonicalJournalRecord,
+	StateDbError,
+};
 use codec::{Decode, Encode};
 use log::trace;
 use std::collections::{hash_map::Entry, HashMap, VecDeque};
@@ -81,6 +84,37 @@ fn to_journal_key(block: u64, index: u64) -> Vec<u8> {
 	to_meta_key(NON_CANONICAL_JOURNAL, &(block, index))
 }
 
+/// Reads the journal records of the blocks following the last canonicalized block.
+pub(crate) fn read_journal<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+	last_canonicalized: u64,
+) -> Result<Vec<NonCanonicalJournalRecord<BlockHash, Key>>, Error<D::Error>> {
+	let mut records = Vec::new();
+	let mut block = last_canonicalized + 1;
+	loop {
+		let level_start = records.len();
+		for index in 0..MAX_BLOCKS_PER_LEVEL {
+			if let Some(record) = db.get_meta(&to_journal_key(block, index)).map_err(Error::Db)? {
+				let JournalRecord { hash, parent_hash, inserted, deleted } =
+					Decode::decode(&mut record.as_slice())?;
+				records.push(NonCanonicalJournalRecord {
+					number: block,
+					index,
+					hash,
+					parent_hash,
+					inserted,
+					deleted,
+				});
+			}
+		}
+		if records.len() == level_start {
+			break
+		}
+		block += 1;
+	}
+	Ok(records)
+}
+
 #[cfg_attr(test, derive(PartialEq, Debug))]
 struct BlockOverlay<BlockHash: Hash, Key: Hash> {
 	hash: BlockHash,
@@ -25,8 +25,8 @@
 //! The changes are journaled in the DB.
 
 use crate::{
-	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError,
-	DEFAULT_MAX_BLOCK_CONSTRAINT, LOG_TARGET,
+	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, MetaDb,
+	PruningJournalRecord, StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT, LOG_TARGET,
 };
 use codec::{Decode, Encode};
 use log::trace;
@@ -279,6 +279,21 @@ fn to_journal_key(block: u64) -> Vec<u8> {
 	to_meta_key(PRUNING_JOURNAL, &block)
 }
 
+/// Reads the journal records of the blocks of the pruning window, starting at `base`.
+pub(crate) fn read_journal<BlockHash: Hash, Key: Hash, D: MetaDb>(
+	db: &D,
+	base: u64,
+) -> Result<Vec<PruningJournalRecord<BlockHash, Key>>, Error<D::Error>> {
+	let mut records = Vec::new();
+	let mut block = base;
+	while let Some(record) = db.get_meta(&to_journal_key(block)).map_err(Error::Db)? {
+		let JournalRecord { hash, inserted, deleted } = Decode::decode(&mut record.as_slice())?;
+		records.push(PruningJournalRecord { number: block, hash, inserted, deleted });
+		block += 1;
+	}
+	Ok(records)
+}
+
 /// The result return by `RefWindow::have_block`
 #[derive(Debug, PartialEq, Eq)]
 pub enum HaveBlock {
@@ -22,7 +22,7 @@ use std::{
 		atomic::{AtomicU64, Ordering},
 		Arc,
 	},
-	time::Instant,
+	time::{Duration, Instant},
 };
 
 use parking_lot::Mutex;
@@ -37,7 +37,7 @@ use sc_client_api::BlockBackend;
 use sp_api::{Core, Encode, Metadata, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::hexdisplay::HexDisplay;
-use sp_rpc::tracing::{BlockTrace, Span, TraceBlockResponse};
+use sp_rpc::tracing::{BlockProfile, BlockTrace, HostFunctionProfile, Span, TraceBlockResponse};
 use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Header},
@@ -194,23 +194,7 @@ where
 	/// prefixes in `Self::storage_keys`.
 	pub fn trace_block(&self) -> TraceBlockResult<TraceBlockResponse> {
 		tracing::debug!(target: "state_tracing", "Tracing block: {}", self.block);
-		// Prepare the block
-		let mut header = self
-			.client
-			.header(self.block)
-			.map_err(Error::InvalidBlockId)?
-			.ok_or_else(|| Error::MissingBlockComponent("Header not found".to_string()))?;
-		let extrinsics = self
-			.client
-			.block_body(self.block)
-			.map_err(Error::InvalidBlockId)?
-			.ok_or_else(|| Error::MissingBlockComponent("Extrinsics not found".to_string()))?;
-		tracing::debug!(target: "state_tracing", "Found {} extrinsics", extrinsics.len());
-		let parent_hash = *header.parent_hash();
-		// Remove all `Seal`s as they are added by the consensus engines after building the block.
-		// On import they are normally removed by the consensus engine.
-		header.digest_mut().logs.retain(|d| d.as_seal().is_none());
-		let block = Block::new(header, extrinsics);
+		let (parent_hash, block) = self.prepare_block()?;
 
 		let targets = if let Some(t) = &self.targets { t } else { DEFAULT_TARGETS };
 		let block_subscriber = BlockSubscriber::new(targets);
@@ -277,6 +261,61 @@ where
 			events,
 		}))
 	}
+
+	/// Execute block and record the calls of each host function and the time spent in them.
+	///
+	/// Host functions are only recorded if the executor was built with a
+	/// [`HostFunctionProfiler`](sc_executor::HostFunctionProfiler) and the block is executed in
+	/// compiled wasm.
+	pub fn profile_block(&self) -> TraceBlockResult<BlockProfile> {
+		tracing::debug!(target: "state_tracing", "Profiling block: {}", self.block);
+		let (parent_hash, block) = self.prepare_block()?;
+
+		let start = Instant::now();
+		let (result, host_functions) = sc_executor::record_host_function_calls(|| {
+			self.client.runtime_api().execute_block(parent_hash, block)
+		});
+		let execution_time = start.elapsed();
+		if let Err(e) = result {
+			return Err(Error::Dispatch(format!("Failed to execute block: {}", e)))
+		}
+		tracing::debug!(target: "state_tracing", "Recorded {} host functions", host_functions.len());
+
+		Ok(BlockProfile {
+			block_hash: block_id_as_string(BlockId::<Block>::Hash(self.block)),
+			parent_hash: block_id_as_string(BlockId::<Block>::Hash(parent_hash)),
+			execution_time: as_nanos(execution_time),
+			host_functions: host_functions
+				.into_iter()
+				.map(|(name, stats)| HostFunctionProfile {
+					name: name.to_owned(),
+					calls: stats.calls,
+					total_time: as_nanos(stats.total_time),
+					max_time: as_nanos(stats.max_time),
+				})
+				.collect(),
+		})
+	}
+
+	/// Returns the parent hash and the block to re-execute, stripped of its seals.
+	fn prepare_block(&self) -> TraceBlockResult<(Block::Hash, Block)> {
+		let mut header = self
+			.client
+			.header(self.block)
+			.map_err(Error::InvalidBlockId)?
+			.ok_or_else(|| Error::MissingBlockComponent("Header not found".to_string()))?;
+		let extrinsics = self
+			.client
+			.block_body(self.block)
+			.map_err(Error::InvalidBlockId)?
+			.ok_or_else(|| Error::MissingBlockComponent("Extrinsics not found".to_string()))?;
+		tracing::debug!(target: "state_tracing", "Found {} extrinsics", extrinsics.len());
+		let parent_hash = *header.parent_hash();
+		// Remove all `Seal`s as they are added by the consensus engines after building the block.
+		// On import they are normally removed by the consensus engine.
+		header.digest_mut().logs.retain(|d| d.as_seal().is_none());
+		Ok((parent_hash, Block::new(header, extrinsics)))
+	}
 }
 
 fn event_values_filter(event: &TraceEvent, filter_kind: &str, values: &str) -> bool {
@@ -322,6 +361,10 @@ fn check_target(targets: &str, target: &str, level: &Level) -> bool {
 	false
 }
 
+fn as_nanos(duration: Duration) -> u64 {
+	duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
 fn block_id_as_string<T: BlockT>(block_id: BlockId<T>) -> String {
 	match block_id {
 		BlockId::Hash(h) => HexDisplay::from(&h.encode()).to_string(),
@@ -0,0 +1,973 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The fork aware transaction pool.
+
+use super::{
+	multi_view_listener::MultiViewListener, tx_mem_pool::TxMemPool, view::View,
+	view_store::ViewStore,
+};
+use crate::{
+	api::FullChainApi,
+	graph::{self, ExtrinsicFor, ExtrinsicHash, IsValidator},
+	metrics::MetricsLink as PrometheusMetrics,
+	PersistedTransaction, PolledIterator, ReadyIteratorFor, ReadyPoll, LOG_TARGET,
+};
+use codec::Encode;
+use futures::{channel::mpsc, future, prelude::*};
+use parking_lot::Mutex;
+use prometheus_endpoint::Registry as PrometheusRegistry;
+use sc_transaction_pool_api::{
+	error::Error as TxPoolError, ChainEvent, ImportNotificationStream, MaintainedTransactionPool,
+	PoolFuture, PoolStatus, TransactionFor, TransactionPool, TransactionSource,
+	TransactionStatusStreamFor, TxHash,
+};
+use sp_blockchain::HashAndNumber;
+use sp_core::traits::SpawnEssentialNamed;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, Extrinsic, NumberFor, Saturating},
+};
+use std::{
+	collections::{HashMap, HashSet},
+	pin::Pin,
+	sync::Arc,
+};
+
+/// The number of blocks after which a new view revalidates all the transactions.
+///
+/// In between, a new view starts from the transactions of the view of an ancestor block, only
+/// pruning the transactions of the blocks in between and validating the transactions missing
+/// from the ancestor view.
+const FULL_REVALIDATION_INTERVAL: u32 = 20;
+
+/// The outcome of adding a submitted transaction to the mempool.
+enum MempoolSubmission<Hash, Error> {
+	/// The transaction was added and still has to be submitted to the views.
+	Added(Hash),
+	/// The transaction was rejected by the mempool.
+	Rejected(Error),
+}
+
+/// A transaction pool maintaining a view of the transactions for every best-fork leaf.
+///
+/// See the [module documentation](super) for details.
+pub struct ForkAwareTxPool<ChainApi, Block>
+where
+	Block: BlockT,
+	ChainApi: graph::ChainApi<Block = Block> + 'static,
+{
+	api: Arc<ChainApi>,
+	/// All the transactions submitted to the pool.
+	mempool: Arc<TxMemPool<ChainApi>>,
+	/// The views of the pool.
+	view_store: Arc<ViewStore<ChainApi>>,
+	/// The watchers of the transactions.
+	listener: Arc<MultiViewListener<ChainApi>>,
+	/// The hashes of the transactions included in the non-finalized blocks seen so far.
+	included_transactions:
+		Mutex<HashMap<Block::Hash, (NumberFor<Block>, Vec<ExtrinsicHash<ChainApi>>)>>,
+	ready_poll: Arc<Mutex<ReadyPoll<ReadyIteratorFor<ChainApi>, Block>>>,
+	import_notification_sinks: Arc<Mutex<Vec<mpsc::Sender<ExtrinsicHash<ChainApi>>>>>,
+	/// The options used to create the views.
+	options: graph::Options,
+	is_validator: Arc<IsValidator>,
+	/// The most recent finalized block.
+	finalized: Mutex<HashAndNumber<Block>>,
+	/// The number of the most recent block a view revalidating all transactions was built at.
+	last_full_revalidation: Mutex<Option<NumberFor<Block>>>,
+	metrics: PrometheusMetrics,
+}
+
+impl<ChainApi, Block> ForkAwareTxPool<ChainApi, Block>
+where
+	Block: BlockT,
+	ChainApi: graph::ChainApi<Block = Block> + 'static,
+{
+	/// Create new fork aware transaction pool with provided api, for tests.
+	pub fn new_test(
+		api: Arc<ChainApi>,
+		best_block_hash: Block::Hash,
+		finalized_hash: Block::Hash,
+	) -> Self {
+		let number_of =
+			|hash| api.block_id_to_number(&BlockId::Hash(hash)).ok().flatten().unwrap_or_default();
+		let best_block =
+			HashAndNumber { hash: best_block_hash, number: number_of(best_block_hash) };
+		let finalized_block =
+			HashAndNumber { hash: finalized_hash, number: number_of(finalized_hash) };
+
+		Self::new_with_options(
+			Default::default(),
+			true.into(),
+			api,
+			None,
+			best_block,
+			finalized_block,
+		)
+	}
+
+	/// Create new fork aware transaction pool with provided api and options.
+	pub fn new_with_options(
+		options: graph::Options,
+		is_validator: IsValidator,
+		api: Arc<ChainApi>,
+		prometheus: Option<&PrometheusRegistry>,
+		best_block: HashAndNumber<Block>,
+		finalized_block: HashAndNumber<Block>,
+	) -> Self {
+		let mempool = TxMemPool::new(
+			options.ready.count + options.future.count,
+			options.ready.total_bytes + options.future.total_bytes,
+		);
+
+		let pool = Self {
+			api,
+			mempool: Arc::new(mempool),
+			view_store: Arc::new(ViewStore::new()),
+			listener: Arc::new(MultiViewListener::new()),
+			included_transactions: Default::default(),
+			ready_poll: Arc::new(Mutex::new(ReadyPoll::new(best_block.number))),
+			import_notification_sinks: Default::default(),
+			options,
+			is_validator: Arc::new(is_validator),
+			finalized: Mutex::new(finalized_block),
+			last_full_revalidation: Mutex::new(None),
+			metrics: PrometheusMetrics::new(prometheus),
+		};
+
+		pool.view_store.insert_best(Arc::new(pool.new_view(best_block)));
+		pool
+	}
+
+	/// Get access to the underlying api.
+	pub fn api(&self) -> &ChainApi {
+		&self.api
+	}
+
+	/// Returns the number of views maintained by the pool.
+	pub fn views_count(&self) -> usize {
+		self.view_store.views().len()
+	}
+
+	/// Returns the number of transactions in the mempool.
+	pub fn mempool_len(&self) -> usize {
+		self.mempool.transactions().len()
+	}
+
+	/// Returns the transactions of the mempool, in the form they are journaled.
+	///
+	/// The priority of a transaction is the one it has in the most recent best view, if any.
+	pub fn persisted_transactions(&self) -> Vec<PersistedTransaction> {
+		let view = self.view_store.most_recent_view();
+		self.mempool
+			.transactions()
+			.into_iter()
+			.map(|(hash, tx)| PersistedTransaction {
+				source: tx.source,
+				priority: view
+					.as_ref()
+					.and_then(|view| view.pool.validated_pool().ready_by_hash(&hash))
+					.map_or(0, |tx| tx.priority),
+				data: tx.tx.encode(),
+			})
+			.collect()
+	}
+
+	/// Create an empty view at the given block.
+	fn new_view(&self, at: HashAndNumber<Block>) -> View<ChainApi> {
+		let is_validator = self.is_validator.clone();
+		View::new(
+			self.api.clone(),
+			at,
+			self.options.clone(),
+			IsValidator::from(Box::new(move || is_validator.is_validator())
+				as Box<dyn Fn() -> bool + Send + Sync>),
+		)
+	}
+
+	/// Submit the transactions to the mempool and to all the views.
+	fn submit(
+		&self,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<ChainApi>>,
+	) -> impl Future<Output = Vec<Result<ExtrinsicHash<ChainApi>, ChainApi::Error>>> + Send + 'static
+	{
+		let mempool = self.mempool.clone();
+		let view_store = self.view_store.clone();
+		let sinks = self.import_notification_sinks.clone();
+
+		let mut added = Vec::with_capacity(xts.len());
+		let mut to_submit = Vec::new();
+		for xt in xts {
+			let (hash, bytes) = self.api.hash_and_length(&xt);
+			match mempool.push(hash, source, xt.clone(), bytes) {
+				Ok(()) => {
+					added.push(MempoolSubmission::Added(hash));
+					to_submit.push(xt);
+				},
+				Err(e) => added.push(MempoolSubmission::Rejected(e.into())),
+			}
+		}
+
+		async move {
+			let mut submitted = view_store.submit(source, to_submit).await.into_iter();
+			let results = added
+				.into_iter()
+				.map(|submission| match submission {
+					MempoolSubmission::Rejected(e) => Err(e),
+					MempoolSubmission::Added(hash) => {
+						let result = submitted
+							.next()
+							.expect("One submission result per submitted transaction; qed");
+						// Transactions rejected by every view do not belong to the mempool.
+						if result.is_err() {
+							mempool.remove(&hash);
+						}
+						result
+					},
+				})
+				.collect::<Vec<_>>();
+
+			let view = view_store.most_recent_view();
+			for hash in results.iter().flatten() {
+				if view
+					.as_ref()
+					.map_or(false, |view| view.pool.validated_pool().ready_by_hash(hash).is_some())
+				{
+					notify_import(&sinks, *hash);
+				}
+			}
+
+			results
+		}
+	}
+
+	/// Returns the extrinsics of the given block, or none if the body is unavailable.
+	async fn block_body(&self, at: Block::Hash) -> Vec<ExtrinsicFor<ChainApi>> {
+		self.api
+			.block_body(at)
+			.await
+			.unwrap_or_else(|e| {
+				log::warn!(target: LOG_TARGET, "Failed to fetch block body: {}", e);
+				None
+			})
+			.unwrap_or_default()
+	}
+
+	/// Returns the hashes of the transactions included in the given block.
+	async fn block_transactions(&self, at: &HashAndNumber<Block>) -> Vec<ExtrinsicHash<ChainApi>> {
+		if let Some((_, hashes)) = self.included_transactions.lock().get(&at.hash) {
+			return hashes.clone()
+		}
+
+		let hashes = self
+			.block_body(at.hash)
+			.await
+			.iter()
+			.map(|xt| self.api.hash_and_length(xt).0)
+			.collect::<Vec<_>>();
+
+		self.included_transactions.lock().insert(at.hash, (at.number, hashes.clone()));
+		hashes
+	}
+
+	/// Returns the hashes of the transactions included in the non-finalized blocks of the fork
+	/// of the given block.
+	async fn included_in_fork(
+		&self,
+		at: &HashAndNumber<Block>,
+	) -> HashSet<ExtrinsicHash<ChainApi>> {
+		let finalized = self.finalized.lock().hash;
+		let tree_route = match self.api.tree_route(finalized, at.hash) {
+			Ok(tree_route) => tree_route,
+			Err(e) => {
+				log::debug!(
+					target: LOG_TARGET,
+					"Error computing tree route from {:?} to {:?}: {}",
+					finalized,
+					at.hash,
+					e,
+				);
+				return Default::default()
+			},
+		};
+
+		let mut included = HashSet::new();
+		for block in tree_route.enacted() {
+			included.extend(self.block_transactions(block).await);
+		}
+		included
+	}
+
+	/// Returns the view of the closest ancestor of the given block, if any.
+	fn ancestor_view(&self, at: &HashAndNumber<Block>) -> Option<Arc<View<ChainApi>>> {
+		self.view_store
+			.views()
+			.into_iter()
+			.filter(|view| view.at.number < at.number)
+			.filter(|view| {
+				self.api
+					.tree_route(view.at.hash, at.hash)
+					.map_or(false, |tree_route| tree_route.retracted().is_empty())
+			})
+			.max_by_key(|view| view.at.number)
+	}
+
+	/// Build a new view at the given block, containing the transactions of the mempool that
+	/// are not included in the fork of the block.
+	///
+	/// The view starts from the view of an ancestor if there is one, such that only the
+	/// transactions missing from it are validated. Every [`FULL_REVALIDATION_INTERVAL`] blocks
+	/// all the transactions are validated again instead.
+	async fn build_view(&self, at: HashAndNumber<Block>) -> Arc<View<ChainApi>> {
+		let view = self.new_view(at.clone());
+
+		let full_revalidation_due = self.last_full_revalidation.lock().map_or(true, |last| {
+			at.number.saturating_sub(last) >= FULL_REVALIDATION_INTERVAL.into()
+		});
+		match self.ancestor_view(&at).filter(|_| !full_revalidation_due) {
+			Some(ancestor) => {
+				view.import_validated_from(&ancestor);
+				self.prune_enacted(&view, &ancestor.at).await;
+				if let Err(e) = view.pool.validated_pool().clear_stale(&BlockId::Hash(at.hash)) {
+					log::debug!(target: LOG_TARGET, "Error clearing stale transactions: {}", e);
+				}
+			},
+			None => *self.last_full_revalidation.lock() = Some(at.number),
+		}
+
+		let included = self.included_in_fork(&at).await;
+		let transactions = self
+			.mempool
+			.transactions()
+			.into_iter()
+			.filter(|(hash, _)| !included.contains(hash))
+			.filter(|(hash, _)| !view.pool.validated_pool().is_imported(hash))
+			.map(|(_, tx)| (tx.source, tx.tx.clone()))
+			.collect::<Vec<_>>();
+		Self::submit_to_view(&view, transactions).await;
+
+		log::debug!(
+			target: LOG_TARGET,
+			"Built view at {:?}: {:?}",
+			at.hash,
+			view.pool.validated_pool().status(),
+		);
+
+		Arc::new(view)
+	}
+
+	/// Prune the transactions of the blocks between `ancestor` and the block of `view` from
+	/// `view`.
+	async fn prune_enacted(&self, view: &View<ChainApi>, ancestor: &HashAndNumber<Block>) {
+		let tree_route = match self.api.tree_route(ancestor.hash, view.at.hash) {
+			Ok(tree_route) => tree_route,
+			Err(e) => {
+				log::debug!(
+					target: LOG_TARGET,
+					"Error computing tree route from {:?} to {:?}: {}",
+					ancestor.hash,
+					view.at.hash,
+					e,
+				);
+				return
+			},
+		};
+
+		let mut parent = ancestor.hash;
+		for block in tree_route.enacted() {
+			let extrinsics = self.block_body(block.hash).await;
+			if let Err(e) = view
+				.pool
+				.prune(&BlockId::Hash(block.hash), &BlockId::Hash(parent), &extrinsics)
+				.await
+			{
+				log::debug!(
+					target: LOG_TARGET,
+					"Error pruning the transactions of {:?} from the view at {:?}: {}",
+					block.hash,
+					view.at.hash,
+					e,
+				);
+			}
+			parent = block.hash;
+		}
+	}
+
+	/// Validate the given transactions against the view and import them, by source.
+	async fn submit_to_view(
+		view: &View<ChainApi>,
+		transactions: Vec<(TransactionSource, ExtrinsicFor<ChainApi>)>,
+	) {
+		for source in
+			[TransactionSource::Local, TransactionSource::InBlock, TransactionSource::External]
+		{
+			let xts = transactions
+				.iter()
+				.filter(|(tx_source, _)| *tx_source == source)
+				.map(|(_, xt)| xt.clone())
+				.collect::<Vec<_>>();
+			if xts.is_empty() {
+				continue
+			}
+
+			if let Err(e) = view.submit_many(source, xts).await {
+				log::debug!(
+					target: LOG_TARGET,
+					"Error submitting transactions to the view at {:?}: {}",
+					view.at.hash,
+					e,
+				);
+			}
+		}
+	}
+
+	/// Submit the transactions of retracted blocks to the existing views they are missing from,
+	/// unless they are included in the fork of the view.
+	async fn resubmit_to_views(&self, retracted: &[ExtrinsicFor<ChainApi>]) {
+		if retracted.is_empty() {
+			return
+		}
+
+		for view in self.view_store.views() {
+			let included = self.included_in_fork(&view.at).await;
+			let missing = retracted
+				.iter()
+				.filter(|xt| {
+					let hash = self.api.hash_and_length(xt).0;
+					!included.contains(&hash) && !view.pool.validated_pool().is_imported(&hash)
+				})
+				.map(|xt| (TransactionSource::External, xt.clone()))
+				.collect::<Vec<_>>();
+			Self::submit_to_view(&view, missing).await;
+		}
+	}
+
+	/// Handle a new best block: update the watchers of the transactions of the enacted and
+	/// retracted blocks and make the view of the block the most recent one.
+	async fn handle_new_best_block(&self, at: HashAndNumber<Block>) {
+		if let Some(previous) = self.view_store.most_recent_view() {
+			if previous.at.hash == at.hash {
+				return
+			}
+
+			match self.api.tree_route(previous.at.hash, at.hash) {
+				Ok(tree_route) => {
+					let mut resubmit = Vec::new();
+					for block in tree_route.retracted() {
+						for hash in self.block_transactions(block).await {
+							self.listener.retracted(&hash, block.hash);
+						}
+
+						let body = self.block_body(block.hash).await;
+						resubmit
+							.extend(body.into_iter().filter(|xt| xt.is_signed().unwrap_or(true)));
+					}
+
+					for block in tree_route.enacted() {
+						for (index, hash) in
+							self.block_transactions(block).await.into_iter().enumerate()
+						{
+							self.listener.in_block(&hash, block.hash, index);
+						}
+					}
+
+					// Transactions of retracted blocks are coming from the network, we should
+					// simply consider them external.
+					let mut resubmitted = 0;
+					for xt in &resubmit {
+						let (hash, bytes) = self.api.hash_and_length(xt);
+						if self
+							.mempool
+							.push(hash, TransactionSource::External, xt.clone(), bytes)
+							.is_ok()
+						{
+							resubmitted += 1;
+						}
+					}
+					self.metrics.report(|metrics| {
+						metrics.block_transactions_resubmitted.inc_by(resubmitted)
+					});
+
+					// The views built later get them from the mempool, the existing ones have to
+					// be updated, e.g. the view of the new best block if it is reused.
+					self.resubmit_to_views(&resubmit).await;
+				},
+				Err(e) => log::debug!(
+					target: LOG_TARGET,
+					"Error computing tree route from {:?} to {:?}: {}",
+					previous.at.hash,
+					at.hash,
+					e,
+				),
+			}
+		} else {
+			let finalized = self.finalized.lock().hash;
+			let enacted = self
+				.api
+				.tree_route(finalized, at.hash)
+				.map(|tree_route| tree_route.enacted().to_vec())
+				.unwrap_or_default();
+			for block in enacted {
+				for (index, hash) in self.block_transactions(&block).await.into_iter().enumerate() {
+					self.listener.in_block(&hash, block.hash, index);
+				}
+			}
+		}
+
+		let view = match self.view_store.get(&at.hash) {
+			Some(view) => view,
+			None => self.build_view(at.clone()).await,
+		};
+		self.view_store.insert_best(view.clone());
+
+		// The views of the ancestors of the new best block are superseded by the new view.
+		let superseded = self
+			.view_store
+			.views()
+			.into_iter()
+			.filter(|v| v.at.number < at.number)
+			.filter(|v| {
+				self.api
+					.tree_route(v.at.hash, at.hash)
+					.map_or(false, |tree_route| tree_route.retracted().is_empty())
+			})
+			.map(|v| v.at.hash)
+			.collect::<Vec<_>>();
+		self.view_store.remove(&superseded);
+
+		self.purge_mempool();
+		self.listener.update_from_view(&view);
+
+		let ready_view = view.clone();
+		self.ready_poll
+			.lock()
+			.trigger(at.number, move || Box::new(ready_view.pool.validated_pool().ready()));
+	}
+
+	/// Handle the finalization of the given blocks, the last one being the new finalized block.
+	async fn handle_finalized(&self, finalized: HashAndNumber<Block>, route: &[Block::Hash]) {
+		let mut pruned = 0;
+		for hash in route.iter().chain(std::iter::once(&finalized.hash)) {
+			let number = match self.api.block_id_to_number(&BlockId::Hash(*hash)) {
+				Ok(Some(number)) => number,
+				_ => {
+					log::debug!(target: LOG_TARGET, "Unknown finalized block {:?}", hash);
+					continue
+				},
+			};
+
+			let block = HashAndNumber { hash: *hash, number };
+			for (index, tx_hash) in self.block_transactions(&block).await.into_iter().enumerate() {
+				self.listener.finalized(&tx_hash, *hash, index);
+				if self.mempool.remove(&tx_hash).is_some() {
+					pruned += 1;
+				}
+			}
+		}
+		self.metrics.report(|metrics| metrics.block_transactions_pruned.inc_by(pruned));
+
+		*self.finalized.lock() = finalized.clone();
+		self.included_transactions
+			.lock()
+			.retain(|_, (number, _)| *number > finalized.number);
+
+		// Drop the views which are not descendants of the finalized block.
+		let most_recent = self.view_store.most_recent_view().map(|view| view.at.hash);
+		let stale = self
+			.view_store
+			.views()
+			.into_iter()
+			.filter(|v| Some(v.at.hash) != most_recent)
+			.filter(|v| {
+				v.at.number <= finalized.number ||
+					self.api
+						.tree_route(finalized.hash, v.at.hash)
+						.map_or(true, |tree_route| !tree_route.retracted().is_empty())
+			})
+			.map(|v| v.at.hash)
+			.collect::<Vec<_>>();
+		self.view_store.remove(&stale);
+
+		self.purge_mempool();
+	}
+
+	/// Remove the transactions of the mempool which are neither in any view nor included in any
+	/// of the known non-finalized blocks. Such transactions were found invalid by every view.
+	fn purge_mempool(&self) {
+		let views = self.view_store.views();
+		if views.is_empty() {
+			return
+		}
+
+		let included = self
+			.included_transactions
+			.lock()
+			.values()
+			.flat_map(|(_, hashes)| hashes.iter().copied())
+			.collect::<HashSet<_>>();
+
+		let mut invalid = 0;
+		for (hash, _) in self.mempool.transactions() {
+			let in_view = views.iter().any(|view| view.pool.validated_pool().is_imported(&hash));
+			if !in_view && !included.contains(&hash) {
+				log::debug!(target: LOG_TARGET, "[{:?}] Removing invalid transaction", hash);
+				self.mempool.remove(&hash);
+				self.listener.invalid(&hash);
+				invalid += 1;
+			}
+		}
+		self.metrics.report(|metrics| metrics.validations_invalid.inc_by(invalid));
+	}
+
+	/// Resolve the number of the given block.
+	fn hash_and_number(&self, hash: Block::Hash) -> Option<HashAndNumber<Block>> {
+		match self.api.block_id_to_number(&BlockId::Hash(hash)) {
+			Ok(Some(number)) => Some(HashAndNumber { hash, number }),
+			Ok(None) => {
+				log::debug!(target: LOG_TARGET, "Unknown block {:?}", hash);
+				None
+			},
+			Err(e) => {
+				log::debug!(target: LOG_TARGET, "Error resolving block {:?}: {}", hash, e);
+				None
+			},
+		}
+	}
+}
+
+/// Notify the import notification sinks about the given ready transaction.
+fn notify_import<Hash: Copy + std::fmt::Debug>(sinks: &Mutex<Vec<mpsc::Sender<Hash>>>, hash: Hash) {
+	sinks.lock().retain_mut(|sink| match sink.try_send(hash) {
+		Ok(()) => true,
+		Err(e) =>
+			if e.is_full() {
+				log::warn!(
+					target: LOG_TARGET,
+					"[{:?}] Trying to notify an import but the channel is full",
+					hash,
+				);
+				true
+			} else {
+				false
+			},
+	});
+}
+
+impl<ChainApi, Block> TransactionPool for ForkAwareTxPool<ChainApi, Block>
+where
+	Block: BlockT,
+	ChainApi: 'static + graph::ChainApi<Block = Block>,
+{
+	type Block = ChainApi::Block;
+	type Hash = ExtrinsicHash<ChainApi>;
+	type InPoolTransaction = graph::base_pool::Transaction<TxHash<Self>, TransactionFor<Self>>;
+	type Error = ChainApi::Error;
+
+	fn submit_at(
+		&self,
+		_: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		self.metrics
+			.report(|metrics| metrics.submitted_transactions.inc_by(xts.len() as u64));
+
+		let submission = self.submit(source, xts);
+
+		async move { Ok(submission.await) }.boxed()
+	}
+
+	fn submit_one(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		let submission = self.submit_at(at, source, vec![xt]);
+
+		async move {
+			let mut results = submission.await?;
+			results.pop().expect("One submission result per submitted transaction; qed")
+		}
+		.boxed()
+	}
+
+	fn submit_and_watch(
+		&self,
+		_: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		self.metrics.report(|metrics| metrics.submitted_transactions.inc());
+
+		let hash = self.hash_of(&xt);
+		if self.mempool.contains(&hash) {
+			return future::ready(Err(TxPoolError::AlreadyImported(Box::new(hash)).into())).boxed()
+		}
+
+		let watcher = self.listener.create_external_watcher(hash);
+		let view_store = self.view_store.clone();
+		let listener = self.listener.clone();
+		let submission = self.submit(source, vec![xt]);
+
+		async move {
+			let mut results = submission.await;
+			match results.pop().expect("One submission result per submitted transaction; qed") {
+				Ok(_) => {
+					if let Some(view) = view_store.most_recent_view() {
+						listener.update_from_view(&view);
+					}
+					Ok(watcher.into_stream().boxed())
+				},
+				Err(e) => {
+					listener.remove_external_watcher(&hash);
+					Err(e)
+				},
+			}
+		}
+		.boxed()
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		let mut removed = HashMap::new();
+		for view in self.view_store.views() {
+			for tx in view.pool.validated_pool().remove_invalid(hashes) {
+				removed.entry(tx.hash).or_insert(tx);
+			}
+		}
+
+		for hash in hashes {
+			self.mempool.remove(hash);
+			self.listener.invalid(hash);
+		}
+
+		self.metrics
+			.report(|metrics| metrics.validations_invalid.inc_by(removed.len() as u64));
+		removed.into_values().collect()
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.view_store
+			.most_recent_view()
+			.map_or(PoolStatus { ready: 0, ready_bytes: 0, future: 0, future_bytes: 0 }, |view| {
+				view.pool.validated_pool().status()
+			})
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		const CHANNEL_BUFFER_SIZE: usize = 1024;
+
+		let (sink, stream) = mpsc::channel(CHANNEL_BUFFER_SIZE);
+		self.import_notification_sinks.lock().push(sink);
+		stream
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.api.hash_and_length(xt).0
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		for (hash, peers) in propagations {
+			self.listener.broadcasted(&hash, peers);
+		}
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.view_store
+			.most_recent_view()
+			.and_then(|view| view.pool.validated_pool().ready_by_hash(hash))
+	}
+
+	fn ready_at(&self, at: NumberFor<Self::Block>) -> PolledIterator<ChainApi> {
+		// If there are no transactions in the pool, it is fine to return early.
+		if self.mempool.is_empty() {
+			return async { Box::new(std::iter::empty()) as Box<_> }.boxed()
+		}
+
+		if self.ready_poll.lock().updated_at() >= at {
+			log::trace!(target: LOG_TARGET, "Transaction pool already processed block  #{}", at);
+			let iterator = self.ready();
+			return async move { iterator }.boxed()
+		}
+
+		self.ready_poll
+			.lock()
+			.add(at)
+			.map(|received| {
+				received.unwrap_or_else(|e| {
+					log::warn!("Error receiving pending set: {:?}", e);
+					Box::new(std::iter::empty())
+				})
+			})
+			.boxed()
+	}
+
+	fn ready(&self) -> ReadyIteratorFor<ChainApi> {
+		match self.view_store.most_recent_view() {
+			Some(view) => Box::new(view.pool.validated_pool().ready()),
+			None => Box::new(std::iter::empty()),
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl<ChainApi, Block> MaintainedTransactionPool for ForkAwareTxPool<ChainApi, Block>
+where
+	Block: BlockT,
+	ChainApi: 'static + graph::ChainApi<Block = Block>,
+{
+	async fn maintain(&self, event: ChainEvent<Self::Block>) {
+		match event {
+			ChainEvent::NewBestBlock { hash, .. } =>
+				if let Some(at) = self.hash_and_number(hash) {
+					self.handle_new_best_block(at).await;
+				},
+			ChainEvent::Finalized { hash, tree_route } => {
+				let Some(at) = self.hash_and_number(hash) else { return };
+
+				// The finalized block may not have been announced as best block.
+				if self
+					.view_store
+					.most_recent_view()
+					.map_or(true, |view| view.at.number < at.number)
+				{
+					self.handle_new_best_block(at.clone()).await;
+				}
+
+				self.handle_finalized(at, &tree_route).await;
+			},
+		}
+	}
+}
+
+impl<Block, Client> ForkAwareTxPool<FullChainApi<Client, Block>, Block>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sc_client_api::ExecutorProvider<Block>
+		+ sc_client_api::UsageProvider<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+		+ Send
+		+ Sync
+		+ 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	/// Create new fork aware transaction pool for a full node with the provided api.
+	pub fn new_full(
+		options: graph::Options,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+	) -> Arc<Self> {
+		let api = Arc::new(FullChainApi::new(client.clone(), prometheus, &spawner));
+		let info = client.usage_info().chain;
+		let pool = Arc::new(Self::new_with_options(
+			options,
+			is_validator,
+			api,
+			prometheus,
+			HashAndNumber { hash: info.best_hash, number: info.best_number },
+			HashAndNumber { hash: info.finalized_hash, number: info.finalized_number },
+		));
+
+		// make transaction pool available for off-chain runtime calls.
+		client.execution_extensions().register_transaction_pool(&pool);
+
+		pool
+	}
+}
+
+impl<Block, Client> sc_transaction_pool_api::LocalTransactionPool
+	for ForkAwareTxPool<FullChainApi<Client, Block>, Block>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	Client: Send + Sync + 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	type Block = Block;
+	type Hash = ExtrinsicHash<FullChainApi<Client, Block>>;
+	type Error = <FullChainApi<Client, Block> as graph::ChainApi>::Error;
+
+	fn submit_local(
+		&self,
+		_: &BlockId<Self::Block>,
+		xt: sc_transaction_pool_api::LocalTransactionFor<Self>,
+	) -> Result<Self::Hash, Self::Error> {
+		use graph::ChainApi as _;
+		use sp_runtime::{
+			traits::SaturatedConversion, transaction_validity::TransactionValidityError,
+		};
+
+		let (hash, bytes) = self.api.hash_and_length(&xt);
+		self.mempool.push(hash, TransactionSource::Local, xt.clone(), bytes)?;
+
+		// Like other submissions, the transaction is validated against the block of every view,
+		// as it may only be valid on some forks.
+		let submit_to_view = |view: &View<FullChainApi<Client, Block>>| -> Result<_, Self::Error> {
+			let validity = self
+				.api
+				.validate_transaction_blocking(
+					&BlockId::Hash(view.at.hash),
+					TransactionSource::Local,
+					xt.clone(),
+				)?
+				.map_err(|e| {
+					Self::Error::Pool(match e {
+						TransactionValidityError::Invalid(i) => TxPoolError::InvalidTransaction(i),
+						TransactionValidityError::Unknown(u) => TxPoolError::UnknownTransaction(u),
+					})
+				})?;
+			let validated = graph::ValidatedTransaction::valid_at(
+				view.at.number.saturated_into::<u64>(),
+				hash,
+				TransactionSource::Local,
+				xt.clone(),
+				bytes,
+				validity,
+			);
+			view.pool.validated_pool().submit(vec![validated]).remove(0)
+		};
+
+		// The most recent best view provides the error if no view accepted the transaction.
+		let mut result: Option<Result<Self::Hash, Self::Error>> = None;
+		for view in self.view_store.views() {
+			let view_result = submit_to_view(&view);
+			if result.as_ref().map_or(true, |result| result.is_err() && view_result.is_ok()) {
+				result = Some(view_result);
+			}
+		}
+		let result = result
+			.unwrap_or_else(|| Err(TxPoolError::InvalidBlockId("No view available".into()).into()));
+
+		if result.is_err() {
+			self.mempool.remove(&hash);
+		}
+		result
+	}
+}
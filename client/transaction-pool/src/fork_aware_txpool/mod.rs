@@ -0,0 +1,47 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Fork aware transaction pool.
+//!
+//! The [`ForkAwareTxPool`] keeps every submitted transaction in a mempool that is independent
+//! of the chain state, and maintains a *view* for every best-fork leaf it was notified about.
+//! A view is a regular [`Pool`](crate::Pool) whose transactions were validated against
+//! the state of the view's block, with the transactions included in the fork of that block
+//! left out.
+//!
+//! On [`ChainEvent::NewBestBlock`](sc_transaction_pool_api::ChainEvent::NewBestBlock) a view
+//! is created for the new best block (or an existing one is reused), the views it supersedes
+//! are dropped and the inclusion of the transactions on the new best fork is reported to the
+//! watchers. A new view starts from the already validated transactions of the view of its closest
+//! ancestor, pruned by the blocks in between, and only validates the mempool transactions missing
+//! from it; every few blocks a view revalidating all the transactions is built instead. The
+//! transactions of retracted blocks are added back to the mempool and to the existing views.
+//! On [`ChainEvent::Finalized`](sc_transaction_pool_api::ChainEvent::Finalized) the
+//! finalized transactions leave the mempool and the views of the abandoned forks are dropped.
+//!
+//! The status reported to the watchers of a transaction is derived from the view of the most
+//! recent best block, such that reorgs result in `Retracted` and `InBlock` events rather than
+//! in transactions being wrongly dropped or resurrected.
+
+mod fork_aware_pool;
+mod multi_view_listener;
+mod tx_mem_pool;
+mod view;
+mod view_store;
+
+pub use fork_aware_pool::ForkAwareTxPool;
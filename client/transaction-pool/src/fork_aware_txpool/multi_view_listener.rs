@@ -0,0 +1,135 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Watchers of the transactions submitted to the fork aware pool.
+//!
+//! Every view reports the status of the transactions from the point of view of its own block.
+//! The [`MultiViewListener`] aggregates these into a single stream of events per transaction.
+
+use super::view::{View, ViewTxStatus};
+use crate::graph::{watcher, BlockHash, ChainApi, ExtrinsicHash};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// The watcher of a single transaction together with the last status it reported.
+struct ExternalWatcher<Api: ChainApi> {
+	sender: watcher::Sender<ExtrinsicHash<Api>, BlockHash<Api>>,
+	/// The status last reported by the most recent best view.
+	view_status: Option<ViewTxStatus>,
+	/// The block of the best fork the transaction is included in.
+	in_block: Option<BlockHash<Api>>,
+}
+
+/// The watchers of the transactions submitted with `submit_and_watch`.
+pub(super) struct MultiViewListener<Api: ChainApi> {
+	watchers: Mutex<HashMap<ExtrinsicHash<Api>, ExternalWatcher<Api>>>,
+}
+
+impl<Api: ChainApi> MultiViewListener<Api> {
+	/// Create a listener without any watcher.
+	pub(super) fn new() -> Self {
+		Self { watchers: Default::default() }
+	}
+
+	/// Create a new watcher for the given transaction.
+	pub(super) fn create_external_watcher(
+		&self,
+		hash: ExtrinsicHash<Api>,
+	) -> watcher::Watcher<ExtrinsicHash<Api>, BlockHash<Api>> {
+		let mut watchers = self.watchers.lock();
+		let entry = watchers.entry(hash).or_insert_with(|| ExternalWatcher {
+			sender: Default::default(),
+			view_status: None,
+			in_block: None,
+		});
+		entry.sender.new_watcher(hash)
+	}
+
+	/// Remove the watcher of the given transaction, closing its streams.
+	pub(super) fn remove_external_watcher(&self, hash: &ExtrinsicHash<Api>) {
+		self.watchers.lock().remove(hash);
+	}
+
+	/// Report the status of the watched transactions in the given (most recent best) view.
+	pub(super) fn update_from_view(&self, view: &View<Api>) {
+		let mut watchers = self.watchers.lock();
+		for (hash, watcher) in watchers.iter_mut() {
+			if watcher.in_block.is_some() {
+				continue
+			}
+
+			let status = view.status_of(hash);
+			if watcher.view_status == Some(status) {
+				continue
+			}
+			watcher.view_status = Some(status);
+
+			match status {
+				ViewTxStatus::Ready => watcher.sender.ready(),
+				ViewTxStatus::Future => watcher.sender.future(),
+				ViewTxStatus::Missing => (),
+			}
+		}
+	}
+
+	/// The transaction was broadcasted to the given peers.
+	pub(super) fn broadcasted(&self, hash: &ExtrinsicHash<Api>, peers: Vec<String>) {
+		if let Some(watcher) = self.watchers.lock().get_mut(hash) {
+			watcher.sender.broadcast(peers);
+		}
+	}
+
+	/// The transaction was included in the given block of the best fork.
+	pub(super) fn in_block(&self, hash: &ExtrinsicHash<Api>, block: BlockHash<Api>, index: usize) {
+		if let Some(watcher) = self.watchers.lock().get_mut(hash) {
+			if watcher.in_block != Some(block) {
+				watcher.in_block = Some(block);
+				watcher.view_status = None;
+				watcher.sender.in_block(block, index);
+			}
+		}
+	}
+
+	/// The block the transaction was included in was retracted from the best fork.
+	pub(super) fn retracted(&self, hash: &ExtrinsicHash<Api>, block: BlockHash<Api>) {
+		if let Some(watcher) = self.watchers.lock().get_mut(hash) {
+			if watcher.in_block == Some(block) {
+				watcher.in_block = None;
+				watcher.view_status = None;
+				watcher.sender.retracted(block);
+			}
+		}
+	}
+
+	/// The transaction was finalized in the given block. This closes the watcher.
+	pub(super) fn finalized(&self, hash: &ExtrinsicHash<Api>, block: BlockHash<Api>, index: usize) {
+		if let Some(mut watcher) = self.watchers.lock().remove(hash) {
+			if watcher.in_block != Some(block) {
+				watcher.sender.in_block(block, index);
+			}
+			watcher.sender.finalized(block, index);
+		}
+	}
+
+	/// The transaction was found invalid. This closes the watcher.
+	pub(super) fn invalid(&self, hash: &ExtrinsicHash<Api>) {
+		if let Some(mut watcher) = self.watchers.lock().remove(hash) {
+			watcher.sender.invalid();
+		}
+	}
+}
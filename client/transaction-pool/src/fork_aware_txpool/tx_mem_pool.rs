@@ -0,0 +1,98 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Transactions submitted to the fork aware pool, independently of the views.
+
+use crate::graph::{ChainApi, ExtrinsicFor, ExtrinsicHash};
+use parking_lot::RwLock;
+use sc_transaction_pool_api::error;
+use sp_runtime::transaction_validity::TransactionSource;
+use std::{collections::HashMap, sync::Arc};
+
+/// A transaction kept in the mempool.
+pub(super) struct TxInMemPool<Api: ChainApi> {
+	/// The source of the transaction.
+	pub(super) source: TransactionSource,
+	/// The transaction.
+	pub(super) tx: ExtrinsicFor<Api>,
+	/// The size of the encoded transaction.
+	pub(super) bytes: usize,
+}
+
+/// The transactions submitted to the pool.
+///
+/// The mempool is the source of the transactions of newly created views. Transactions stay in
+/// the mempool until they are finalized or found invalid in every view.
+pub(super) struct TxMemPool<Api: ChainApi> {
+	transactions: RwLock<HashMap<ExtrinsicHash<Api>, Arc<TxInMemPool<Api>>>>,
+	/// The maximum number of transactions in the mempool.
+	max_count: usize,
+	/// The maximum total size of the transactions in the mempool.
+	max_bytes: usize,
+}
+
+impl<Api: ChainApi> TxMemPool<Api> {
+	/// Create a new mempool with the given limits.
+	pub(super) fn new(max_count: usize, max_bytes: usize) -> Self {
+		Self { transactions: Default::default(), max_count, max_bytes }
+	}
+
+	/// Add a transaction to the mempool.
+	///
+	/// Fails if the transaction is already known or if the mempool is full.
+	pub(super) fn push(
+		&self,
+		hash: ExtrinsicHash<Api>,
+		source: TransactionSource,
+		tx: ExtrinsicFor<Api>,
+		bytes: usize,
+	) -> Result<(), error::Error> {
+		let mut transactions = self.transactions.write();
+		if transactions.contains_key(&hash) {
+			return Err(error::Error::AlreadyImported(Box::new(hash)))
+		}
+
+		let total_bytes = transactions.values().map(|tx| tx.bytes).sum::<usize>();
+		if transactions.len() >= self.max_count || total_bytes + bytes > self.max_bytes {
+			return Err(error::Error::ImmediatelyDropped)
+		}
+
+		transactions.insert(hash, Arc::new(TxInMemPool { source, tx, bytes }));
+		Ok(())
+	}
+
+	/// Remove a transaction from the mempool.
+	pub(super) fn remove(&self, hash: &ExtrinsicHash<Api>) -> Option<Arc<TxInMemPool<Api>>> {
+		self.transactions.write().remove(hash)
+	}
+
+	/// Returns `true` if the transaction is in the mempool.
+	pub(super) fn contains(&self, hash: &ExtrinsicHash<Api>) -> bool {
+		self.transactions.read().contains_key(hash)
+	}
+
+	/// Returns `true` if the mempool is empty.
+	pub(super) fn is_empty(&self) -> bool {
+		self.transactions.read().is_empty()
+	}
+
+	/// Returns a snapshot of the transactions in the mempool.
+	pub(super) fn transactions(&self) -> Vec<(ExtrinsicHash<Api>, Arc<TxInMemPool<Api>>)> {
+		self.transactions.read().iter().map(|(hash, tx)| (*hash, tx.clone())).collect()
+	}
+}
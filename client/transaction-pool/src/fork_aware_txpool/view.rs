@@ -0,0 +1,87 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A view of the transaction pool at a given block.
+
+use crate::graph::{
+	self, ChainApi, ExtrinsicFor, ExtrinsicHash, IsValidator, ValidatedTransaction,
+};
+use sp_blockchain::HashAndNumber;
+use sp_runtime::{generic::BlockId, transaction_validity::TransactionSource};
+use std::sync::Arc;
+
+/// The status of a transaction in a view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ViewTxStatus {
+	/// The transaction is in the ready queue of the view.
+	Ready,
+	/// The transaction is in the future queue of the view.
+	Future,
+	/// The transaction is not in the view.
+	Missing,
+}
+
+/// The transactions of the pool validated against the state of a given block.
+pub(super) struct View<Api: ChainApi> {
+	/// The pool of the transactions valid at the view's block.
+	pub(super) pool: graph::Pool<Api>,
+	/// The block of the view.
+	pub(super) at: HashAndNumber<Api::Block>,
+}
+
+impl<Api: ChainApi> View<Api> {
+	/// Create a new empty view at the given block.
+	pub(super) fn new(
+		api: Arc<Api>,
+		at: HashAndNumber<Api::Block>,
+		options: graph::Options,
+		is_validator: IsValidator,
+	) -> Self {
+		Self { pool: graph::Pool::new(options, is_validator, api), at }
+	}
+
+	/// Validate the transactions against the view's block and import them into the view.
+	pub(super) async fn submit_many(
+		&self,
+		source: TransactionSource,
+		xts: impl IntoIterator<Item = ExtrinsicFor<Api>>,
+	) -> Result<Vec<Result<ExtrinsicHash<Api>, Api::Error>>, Api::Error> {
+		self.pool.submit_at(&BlockId::Hash(self.at.hash), source, xts).await
+	}
+
+	/// Import the transactions of `other` with the validity they have there, without validating
+	/// them again.
+	pub(super) fn import_validated_from(&self, other: &View<Api>) {
+		let transactions = other.pool.validated_pool().duplicate_transactions();
+		self.pool
+			.validated_pool()
+			.submit(transactions.into_iter().map(ValidatedTransaction::Valid));
+	}
+
+	/// Returns the status of the given transaction in the view.
+	pub(super) fn status_of(&self, hash: &ExtrinsicHash<Api>) -> ViewTxStatus {
+		let validated_pool = self.pool.validated_pool();
+		if validated_pool.ready_by_hash(hash).is_some() {
+			ViewTxStatus::Ready
+		} else if validated_pool.is_imported(hash) {
+			ViewTxStatus::Future
+		} else {
+			ViewTxStatus::Missing
+		}
+	}
+}
@@ -0,0 +1,133 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! The set of views maintained by the fork aware pool.
+
+use super::view::View;
+use crate::{
+	graph::{ChainApi, ExtrinsicFor, ExtrinsicHash},
+	LOG_TARGET,
+};
+use futures::future;
+use parking_lot::RwLock;
+use sc_transaction_pool_api::error;
+use sp_runtime::{traits::Block as BlockT, transaction_validity::TransactionSource};
+use std::{collections::HashMap, sync::Arc};
+
+/// The views of the pool, one per best-fork leaf.
+pub(super) struct ViewStore<Api: ChainApi> {
+	/// The views, indexed by the hash of their block.
+	views: RwLock<HashMap<<Api::Block as BlockT>::Hash, Arc<View<Api>>>>,
+	/// The hash of the block of the most recent best view.
+	most_recent_view: RwLock<Option<<Api::Block as BlockT>::Hash>>,
+}
+
+impl<Api: ChainApi> ViewStore<Api> {
+	/// Create an empty view store.
+	pub(super) fn new() -> Self {
+		Self { views: Default::default(), most_recent_view: Default::default() }
+	}
+
+	/// Returns the view of the most recent best block.
+	pub(super) fn most_recent_view(&self) -> Option<Arc<View<Api>>> {
+		let hash = (*self.most_recent_view.read())?;
+		self.views.read().get(&hash).cloned()
+	}
+
+	/// Returns the view at the given block.
+	pub(super) fn get(&self, hash: &<Api::Block as BlockT>::Hash) -> Option<Arc<View<Api>>> {
+		self.views.read().get(hash).cloned()
+	}
+
+	/// Returns all the views, the most recent best view first.
+	pub(super) fn views(&self) -> Vec<Arc<View<Api>>> {
+		let most_recent = *self.most_recent_view.read();
+		let mut views = self.views.read().values().cloned().collect::<Vec<_>>();
+		views.sort_by_key(|view| Some(view.at.hash) != most_recent);
+		views
+	}
+
+	/// Insert the view and make it the most recent best view.
+	pub(super) fn insert_best(&self, view: Arc<View<Api>>) {
+		let hash = view.at.hash;
+		self.views.write().insert(hash, view);
+		*self.most_recent_view.write() = Some(hash);
+	}
+
+	/// Remove the views at the given blocks.
+	pub(super) fn remove(&self, hashes: &[<Api::Block as BlockT>::Hash]) {
+		let mut views = self.views.write();
+		for hash in hashes {
+			log::debug!(target: LOG_TARGET, "Removing view at {:?}", hash);
+			views.remove(hash);
+		}
+	}
+
+	/// Submit the transactions to all the views.
+	///
+	/// A transaction is accepted if at least one view accepted it, in which case its hash is
+	/// returned. For transactions rejected by all the views, the error reported by the first view
+	/// in the order of [`Self::views`] is returned, i.e. the most recent best view unless its
+	/// submission failed as a whole, in which case that view is skipped.
+	pub(super) async fn submit(
+		&self,
+		source: TransactionSource,
+		xts: Vec<ExtrinsicFor<Api>>,
+	) -> Vec<Result<ExtrinsicHash<Api>, Api::Error>> {
+		let views = self.views();
+		let results =
+			future::join_all(views.iter().map(|view| view.submit_many(source, xts.clone()))).await;
+
+		let mut aggregated: Option<Vec<Result<ExtrinsicHash<Api>, Api::Error>>> = None;
+		for (view, results) in views.iter().zip(results) {
+			let results = match results {
+				Ok(results) => results,
+				Err(e) => {
+					log::debug!(
+						target: LOG_TARGET,
+						"Submission to the view at {:?} failed: {}",
+						view.at.hash,
+						e,
+					);
+					continue
+				},
+			};
+
+			// The first view to report results provides the errors, later views can only turn
+			// them into acceptances.
+			match aggregated.as_mut() {
+				None => aggregated = Some(results),
+				Some(aggregated) =>
+					for (aggregated, result) in aggregated.iter_mut().zip(results) {
+						if aggregated.is_err() && result.is_ok() {
+							*aggregated = result;
+						}
+					},
+			}
+		}
+
+		aggregated.unwrap_or_else(|| {
+			xts.iter()
+				.map(|_| {
+					Err(error::Error::InvalidBlockId("No view available for submission".into())
+						.into())
+				})
+				.collect()
+		})
+	}
+}
@@ -30,7 +30,9 @@ use serde::Serialize;
 use sp_runtime::{
 	generic::BlockId,
 	traits::{self, SaturatedConversion},
-	transaction_validity::{TransactionSource, TransactionTag as Tag, ValidTransaction},
+	transaction_validity::{
+		TransactionPriority, TransactionSource, TransactionTag as Tag, ValidTransaction,
+	},
 };
 use std::time::Instant;
 
@@ -100,6 +102,13 @@ impl From<Box<dyn Fn() -> bool + Send + Sync>> for IsValidator {
 	}
 }
 
+impl IsValidator {
+	/// Returns `true` if the local node is currently a validator.
+	pub(crate) fn is_validator(&self) -> bool {
+		(self.0)()
+	}
+}
+
 /// Pool that deals with validated transactions.
 pub struct ValidatedPool<B: ChainApi> {
 	api: Arc<B>,
@@ -156,6 +165,11 @@ impl<B: ChainApi> ValidatedPool<B> {
 		}
 	}
 
+	/// Returns `true` if the transaction with given hash is in the ready or future queue.
+	pub fn is_imported(&self, tx_hash: &ExtrinsicHash<B>) -> bool {
+		self.pool.read().is_imported(tx_hash)
+	}
+
 	/// Imports a bunch of pre-validated transactions to the pool.
 	pub fn submit(
 		&self,
@@ -625,6 +639,29 @@ impl<B: ChainApi> ValidatedPool<B> {
 		self.pool.read().futures().map(|tx| (tx.hash, tx.data.clone())).collect()
 	}
 
+	/// Returns copies of all the transactions in the pool, ready and future.
+	///
+	/// The copies keep the validity of the transactions, which allows importing them into
+	/// another pool without validating them again.
+	pub fn duplicate_transactions(
+		&self,
+	) -> Vec<base::Transaction<ExtrinsicHash<B>, ExtrinsicFor<B>>> {
+		let pool = self.pool.read();
+		pool.ready()
+			.map(|tx| tx.duplicate())
+			.chain(pool.futures().map(|tx| tx.duplicate()))
+			.collect()
+	}
+
+	/// Returns the source, priority and extrinsic of all the transactions in the pool.
+	pub fn transactions(&self) -> Vec<(TransactionSource, TransactionPriority, ExtrinsicFor<B>)> {
+		let pool = self.pool.read();
+		pool.ready()
+			.map(|tx| (tx.source, tx.priority, tx.data.clone()))
+			.chain(pool.futures().map(|tx| (tx.source, tx.priority, tx.data.clone())))
+			.collect()
+	}
+
 	/// Returns pool status.
 	pub fn status(&self) -> PoolStatus {
 		self.pool.read().status()
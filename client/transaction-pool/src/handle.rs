@@ -0,0 +1,317 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Selection of the transaction pool implementation used by a full node.
+
+use crate::{
+	error::Error,
+	graph::{self, IsValidator},
+	persistence::{
+		resubmit_journaled, JournalWriter, PersistedTransaction, TransactionPoolJournal,
+	},
+	ForkAwareTxPool, FullChainApi, FullPool, LOG_TARGET,
+};
+use async_trait::async_trait;
+use futures::Future;
+use parking_lot::Mutex;
+use prometheus_endpoint::Registry as PrometheusRegistry;
+use sc_transaction_pool_api::{
+	ChainEvent, ImportNotificationStream, LocalTransactionFor, LocalTransactionPool,
+	MaintainedTransactionPool, PoolFuture, PoolStatus, ReadyTransactions, TransactionFor,
+	TransactionPool, TransactionSource, TransactionStatusStreamFor, TxHash,
+};
+use sp_core::traits::SpawnEssentialNamed;
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, NumberFor},
+};
+use std::{collections::HashMap, path::PathBuf, pin::Pin, sync::Arc};
+
+/// The type of the transaction pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransactionPoolType {
+	/// A pool maintaining a single state, revalidated against the best block.
+	#[default]
+	SingleState,
+	/// A pool maintaining a view of the transactions for every best-fork leaf.
+	ForkAware,
+}
+
+/// Transaction pool configuration.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionPoolOptions {
+	/// The options of the underlying pools.
+	pub options: graph::Options,
+	/// The type of the transaction pool.
+	pub txpool_type: TransactionPoolType,
+	/// The path of the journal the transactions are persisted to across restarts, if any.
+	pub journal_path: Option<PathBuf>,
+}
+
+/// A transaction pool for a full node, whatever its implementation.
+pub trait FullClientTransactionPool<Block: BlockT>:
+	MaintainedTransactionPool<
+		Block = Block,
+		Hash = Block::Hash,
+		InPoolTransaction = graph::base_pool::Transaction<Block::Hash, Block::Extrinsic>,
+		Error = Error,
+	> + LocalTransactionPool<Block = Block, Hash = Block::Hash, Error = Error>
+{
+	/// Returns the transactions of the pool, in the form they are journaled.
+	fn persisted_transactions(&self) -> Vec<PersistedTransaction>;
+}
+
+impl<Block, Client> FullClientTransactionPool<Block> for FullPool<Block, Client>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	Client: Send + Sync + 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	fn persisted_transactions(&self) -> Vec<PersistedTransaction> {
+		FullPool::persisted_transactions(self)
+	}
+}
+
+impl<Block, Client> FullClientTransactionPool<Block>
+	for ForkAwareTxPool<FullChainApi<Client, Block>, Block>
+where
+	Block: BlockT,
+	Client: sp_api::ProvideRuntimeApi<Block>
+		+ sc_client_api::BlockBackend<Block>
+		+ sc_client_api::blockchain::HeaderBackend<Block>
+		+ sp_runtime::traits::BlockIdTo<Block>
+		+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>,
+	Client: Send + Sync + 'static,
+	Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+{
+	fn persisted_transactions(&self) -> Vec<PersistedTransaction> {
+		ForkAwareTxPool::persisted_transactions(self)
+	}
+}
+
+/// The transaction pool of a full node, of the type selected by [`TransactionPoolOptions`].
+pub struct TransactionPoolHandle<Block: BlockT> {
+	pool: Arc<dyn FullClientTransactionPool<Block>>,
+	journal: Option<JournalWriter>,
+	/// The journaled transactions not resubmitted to the pool yet.
+	///
+	/// They are resubmitted on the first maintenance of the pool, at the notified block.
+	journaled: Mutex<Vec<PersistedTransaction>>,
+}
+
+impl<Block: BlockT> TransactionPoolHandle<Block> {
+	/// Create new transaction pool for a full node, of the type given in the options.
+	pub fn new_full<Client>(
+		options: TransactionPoolOptions,
+		is_validator: IsValidator,
+		prometheus: Option<&PrometheusRegistry>,
+		spawner: impl SpawnEssentialNamed,
+		client: Arc<Client>,
+	) -> Arc<Self>
+	where
+		Client: sp_api::ProvideRuntimeApi<Block>
+			+ sc_client_api::BlockBackend<Block>
+			+ sc_client_api::blockchain::HeaderBackend<Block>
+			+ sp_runtime::traits::BlockIdTo<Block>
+			+ sc_client_api::ExecutorProvider<Block>
+			+ sc_client_api::UsageProvider<Block>
+			+ sp_blockchain::HeaderMetadata<Block, Error = sp_blockchain::Error>
+			+ Send
+			+ Sync
+			+ 'static,
+		Client::Api: sp_transaction_pool::runtime_api::TaggedTransactionQueue<Block>,
+	{
+		let pool: Arc<dyn FullClientTransactionPool<Block>> = match options.txpool_type {
+			TransactionPoolType::SingleState => FullPool::<Block, Client>::new_full(
+				options.options,
+				is_validator,
+				prometheus,
+				spawner,
+				client,
+			),
+			TransactionPoolType::ForkAware =>
+				ForkAwareTxPool::<FullChainApi<Client, Block>, Block>::new_full(
+					options.options,
+					is_validator,
+					prometheus,
+					spawner,
+					client,
+				),
+		};
+
+		let journal = options.journal_path.map(TransactionPoolJournal::new);
+		let journaled = journal
+			.as_ref()
+			.map(|journal| {
+				journal.read().unwrap_or_else(|e| {
+					log::warn!(
+						target: LOG_TARGET,
+						"Failed to read the transaction pool journal at {}: {}",
+						journal.path().display(),
+						e,
+					);
+					Vec::new()
+				})
+			})
+			.unwrap_or_default();
+		let journal = journal.and_then(|journal| {
+			JournalWriter::new(journal)
+				.map_err(|e| {
+					log::warn!(
+						target: LOG_TARGET,
+						"Failed to start the transaction pool journal writer: {}",
+						e,
+					)
+				})
+				.ok()
+		});
+
+		Arc::new(Self { pool, journal, journaled: Mutex::new(journaled) })
+	}
+}
+
+impl<Block: BlockT> Drop for TransactionPoolHandle<Block> {
+	fn drop(&mut self) {
+		if let Some(writer) = &self.journal {
+			// Keep the journaled transactions that never made it back into the pool.
+			let mut transactions = self.pool.persisted_transactions();
+			transactions.append(self.journaled.get_mut());
+
+			log::debug!(
+				target: LOG_TARGET,
+				"Flushing {} transactions to the transaction pool journal at {}",
+				transactions.len(),
+				writer.journal().path().display(),
+			);
+			writer.write(transactions);
+		}
+	}
+}
+
+impl<Block: BlockT> TransactionPool for TransactionPoolHandle<Block> {
+	type Block = Block;
+	type Hash = Block::Hash;
+	type InPoolTransaction = graph::base_pool::Transaction<Block::Hash, Block::Extrinsic>;
+	type Error = Error;
+
+	fn submit_at(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xts: Vec<TransactionFor<Self>>,
+	) -> PoolFuture<Vec<Result<TxHash<Self>, Self::Error>>, Self::Error> {
+		self.pool.submit_at(at, source, xts)
+	}
+
+	fn submit_one(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<TxHash<Self>, Self::Error> {
+		self.pool.submit_one(at, source, xt)
+	}
+
+	fn submit_and_watch(
+		&self,
+		at: &BlockId<Self::Block>,
+		source: TransactionSource,
+		xt: TransactionFor<Self>,
+	) -> PoolFuture<Pin<Box<TransactionStatusStreamFor<Self>>>, Self::Error> {
+		self.pool.submit_and_watch(at, source, xt)
+	}
+
+	fn ready_at(
+		&self,
+		at: NumberFor<Self::Block>,
+	) -> Pin<
+		Box<
+			dyn Future<
+					Output = Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send>,
+				> + Send,
+		>,
+	> {
+		self.pool.ready_at(at)
+	}
+
+	fn ready(&self) -> Box<dyn ReadyTransactions<Item = Arc<Self::InPoolTransaction>> + Send> {
+		self.pool.ready()
+	}
+
+	fn remove_invalid(&self, hashes: &[TxHash<Self>]) -> Vec<Arc<Self::InPoolTransaction>> {
+		self.pool.remove_invalid(hashes)
+	}
+
+	fn status(&self) -> PoolStatus {
+		self.pool.status()
+	}
+
+	fn import_notification_stream(&self) -> ImportNotificationStream<TxHash<Self>> {
+		self.pool.import_notification_stream()
+	}
+
+	fn on_broadcasted(&self, propagations: HashMap<TxHash<Self>, Vec<String>>) {
+		self.pool.on_broadcasted(propagations)
+	}
+
+	fn hash_of(&self, xt: &TransactionFor<Self>) -> TxHash<Self> {
+		self.pool.hash_of(xt)
+	}
+
+	fn ready_transaction(&self, hash: &TxHash<Self>) -> Option<Arc<Self::InPoolTransaction>> {
+		self.pool.ready_transaction(hash)
+	}
+}
+
+#[async_trait]
+impl<Block: BlockT> MaintainedTransactionPool for TransactionPoolHandle<Block> {
+	async fn maintain(&self, event: ChainEvent<Self::Block>) {
+		let at = match &event {
+			ChainEvent::NewBestBlock { hash, .. } | ChainEvent::Finalized { hash, .. } => *hash,
+		};
+		self.pool.maintain(event).await;
+
+		let journaled = std::mem::take(&mut *self.journaled.lock());
+		if !journaled.is_empty() {
+			resubmit_journaled(&*self.pool, at, journaled).await;
+		}
+
+		if let Some(writer) = &self.journal {
+			writer.maybe_write(|| self.pool.persisted_transactions());
+		}
+	}
+}
+
+impl<Block: BlockT> LocalTransactionPool for TransactionPoolHandle<Block> {
+	type Block = Block;
+	type Hash = Block::Hash;
+	type Error = Error;
+
+	fn submit_local(
+		&self,
+		at: &BlockId<Self::Block>,
+		xt: LocalTransactionFor<Self>,
+	) -> Result<Self::Hash, Self::Error> {
+		self.pool.submit_local(at, xt)
+	}
+}
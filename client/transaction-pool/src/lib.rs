@@ -25,15 +25,20 @@
 mod api;
 mod enactment_state;
 pub mod error;
+mod fork_aware_txpool;
 mod graph;
+mod handle;
 mod metrics;
+mod persistence;
 mod revalidation;
 #[cfg(test)]
 mod tests;
 
 pub use crate::api::FullChainApi;
 use async_trait::async_trait;
+use codec::Encode;
 use enactment_state::{EnactmentAction, EnactmentState};
+pub use fork_aware_txpool::ForkAwareTxPool;
 use futures::{
 	channel::oneshot,
 	future::{self, ready},
@@ -42,7 +47,11 @@ use futures::{
 pub use graph::{
 	base_pool::Limit as PoolLimit, ChainApi, Options, Pool, Transaction, ValidatedTransaction,
 };
+pub use handle::{
+	FullClientTransactionPool, TransactionPoolHandle, TransactionPoolOptions, TransactionPoolType,
+};
 use parking_lot::Mutex;
+pub use persistence::{PersistedTransaction, TransactionPoolJournal};
 use std::{
 	collections::{HashMap, HashSet},
 	pin::Pin,
@@ -240,6 +249,20 @@ where
 	pub fn api(&self) -> &PoolApi {
 		&self.api
 	}
+
+	/// Returns the transactions of the pool, in the form they are journaled.
+	pub fn persisted_transactions(&self) -> Vec<PersistedTransaction> {
+		self.pool
+			.validated_pool()
+			.transactions()
+			.into_iter()
+			.map(|(source, priority, xt)| PersistedTransaction {
+				source,
+				priority,
+				data: xt.encode(),
+			})
+			.collect()
+	}
 }
 
 impl<PoolApi, Block> TransactionPool for BasicPool<PoolApi, Block>
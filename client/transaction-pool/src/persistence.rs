@@ -0,0 +1,323 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of the transaction pool across node restarts.
+//!
+//! When enabled, the transactions of the pool are journaled to a file at most every
+//! [`JOURNAL_WRITE_INTERVAL`] while the pool is maintained, and once more when the pool is
+//! dropped. After a restart, the journaled transactions are submitted again to the pool on its
+//! first maintenance, revalidating them against the notified block.
+
+use crate::LOG_TARGET;
+use codec::{Decode, Encode};
+use parking_lot::{Condvar, Mutex};
+use sc_transaction_pool_api::{TransactionFor, TransactionPool};
+use sp_runtime::{
+	generic::BlockId,
+	traits::Block as BlockT,
+	transaction_validity::{TransactionPriority, TransactionSource},
+};
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+	sync::Arc,
+	thread,
+	time::{Duration, Instant},
+};
+
+/// The version of the journal format.
+const JOURNAL_VERSION: u32 = 1;
+
+/// The minimum time between two snapshots of the pool written to the journal.
+///
+/// Transactions received since the last snapshot are lost if the node crashes.
+pub(crate) const JOURNAL_WRITE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A transaction as stored in the journal.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct PersistedTransaction {
+	/// The source of the transaction.
+	pub source: TransactionSource,
+	/// The priority of the transaction when it was journaled.
+	pub priority: TransactionPriority,
+	/// The encoded transaction.
+	pub data: Vec<u8>,
+}
+
+/// The file the transactions of the pool are journaled to.
+#[derive(Debug, Clone)]
+pub struct TransactionPoolJournal {
+	path: PathBuf,
+}
+
+impl TransactionPoolJournal {
+	/// Create a journal stored at the given path.
+	pub fn new(path: impl Into<PathBuf>) -> Self {
+		Self { path: path.into() }
+	}
+
+	/// Returns the path of the journal.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Replace the content of the journal with the given transactions.
+	///
+	/// The journal is written to a temporary file first, such that a crash while writing leaves
+	/// the previous journal intact.
+	pub fn write(&self, transactions: &[PersistedTransaction]) -> io::Result<()> {
+		if let Some(parent) = self.path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		let tmp_path = self.path.with_extension("tmp");
+		fs::write(&tmp_path, (JOURNAL_VERSION, transactions).encode())?;
+		fs::rename(tmp_path, &self.path)
+	}
+
+	/// Read the transactions of the journal.
+	///
+	/// A missing journal, or a journal of an unknown version, contains no transaction.
+	pub fn read(&self) -> io::Result<Vec<PersistedTransaction>> {
+		let bytes = match fs::read(&self.path) {
+			Ok(bytes) => bytes,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+			Err(e) => return Err(e),
+		};
+
+		let (version, transactions) = <(u32, Vec<PersistedTransaction>)>::decode(&mut &bytes[..])
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		if version != JOURNAL_VERSION {
+			log::warn!(
+				target: LOG_TARGET,
+				"Ignoring transaction pool journal of unknown version {}",
+				version,
+			);
+			return Ok(Vec::new())
+		}
+
+		Ok(transactions)
+	}
+}
+
+/// Writes snapshots of the pool to a [`TransactionPoolJournal`] from a dedicated thread.
+///
+/// Taking a snapshot is rate limited to [`JOURNAL_WRITE_INTERVAL`], and the file is written off
+/// the async maintenance task. Pending snapshots are written before the writer is dropped.
+pub(crate) struct JournalWriter {
+	journal: TransactionPoolJournal,
+	state: Arc<(Mutex<WriterState>, Condvar)>,
+	/// When the next snapshot may be taken.
+	next_snapshot: Mutex<Instant>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct WriterState {
+	/// The latest snapshot not written yet.
+	pending: Option<Vec<PersistedTransaction>>,
+	shutdown: bool,
+}
+
+impl JournalWriter {
+	/// Start a writer for `journal`.
+	pub fn new(journal: TransactionPoolJournal) -> io::Result<Self> {
+		let state = Arc::new((Mutex::new(WriterState::default()), Condvar::new()));
+
+		let (thread_state, thread_journal) = (state.clone(), journal.clone());
+		let thread = thread::Builder::new()
+			.name("txpool-journal".into())
+			.spawn(move || write_snapshots(thread_journal, thread_state))?;
+
+		Ok(Self { journal, state, next_snapshot: Mutex::new(Instant::now()), thread: Some(thread) })
+	}
+
+	/// Returns the journal written to.
+	pub fn journal(&self) -> &TransactionPoolJournal {
+		&self.journal
+	}
+
+	/// Take a snapshot with `snapshot` and write it, unless one was taken recently.
+	pub fn maybe_write(&self, snapshot: impl FnOnce() -> Vec<PersistedTransaction>) {
+		let now = Instant::now();
+		{
+			let mut next_snapshot = self.next_snapshot.lock();
+			if now < *next_snapshot {
+				return
+			}
+			*next_snapshot = now + JOURNAL_WRITE_INTERVAL;
+		}
+		self.write(snapshot());
+	}
+
+	/// Write `transactions`, replacing any snapshot not written yet.
+	pub fn write(&self, transactions: Vec<PersistedTransaction>) {
+		let (state, condvar) = &*self.state;
+		state.lock().pending = Some(transactions);
+		condvar.notify_one();
+	}
+}
+
+impl Drop for JournalWriter {
+	fn drop(&mut self) {
+		let (state, condvar) = &*self.state;
+		state.lock().shutdown = true;
+		condvar.notify_one();
+
+		if let Some(thread) = self.thread.take() {
+			if thread.join().is_err() {
+				log::warn!(target: LOG_TARGET, "The transaction pool journal writer panicked");
+			}
+		}
+	}
+}
+
+/// Write the snapshots handed over by a [`JournalWriter`] until it is dropped.
+fn write_snapshots(journal: TransactionPoolJournal, state: Arc<(Mutex<WriterState>, Condvar)>) {
+	let (state, condvar) = &*state;
+	loop {
+		let transactions = {
+			let mut state = state.lock();
+			while state.pending.is_none() && !state.shutdown {
+				condvar.wait(&mut state);
+			}
+			match state.pending.take() {
+				Some(transactions) => transactions,
+				None => return,
+			}
+		};
+
+		if let Err(e) = journal.write(&transactions) {
+			log::warn!(
+				target: LOG_TARGET,
+				"Failed to write the transaction pool journal at {}: {}",
+				journal.path().display(),
+				e,
+			);
+		}
+	}
+}
+
+/// Submit the journaled transactions to the pool, validating them at the given block.
+///
+/// Transactions are submitted by decreasing priority, such that the most valuable ones make it
+/// into a pool that would be too small to hold all of them.
+pub(crate) async fn resubmit_journaled<P: TransactionPool + ?Sized>(
+	pool: &P,
+	at: <P::Block as BlockT>::Hash,
+	mut transactions: Vec<PersistedTransaction>,
+) {
+	transactions.sort_by(|a, b| b.priority.cmp(&a.priority));
+
+	let mut imported = 0;
+	let total = transactions.len();
+	for source in
+		[TransactionSource::Local, TransactionSource::InBlock, TransactionSource::External]
+	{
+		let xts = transactions
+			.iter()
+			.filter(|tx| tx.source == source)
+			.filter_map(|tx| match TransactionFor::<P>::decode(&mut &tx.data[..]) {
+				Ok(xt) => Some(xt),
+				Err(e) => {
+					log::debug!(target: LOG_TARGET, "Failed to decode journaled transaction: {}", e);
+					None
+				},
+			})
+			.collect::<Vec<_>>();
+		if xts.is_empty() {
+			continue
+		}
+
+		match pool.submit_at(&BlockId::Hash(at), source, xts).await {
+			Ok(results) => imported += results.iter().filter(|r| r.is_ok()).count(),
+			Err(e) => log::debug!(
+				target: LOG_TARGET,
+				"Failed to resubmit journaled transactions: {}",
+				e,
+			),
+		}
+	}
+
+	log::info!(
+		target: LOG_TARGET,
+		"Imported {} of {} transactions from the transaction pool journal",
+		imported,
+		total,
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tempfile::TempDir;
+
+	fn journal(dir: &TempDir) -> TransactionPoolJournal {
+		TransactionPoolJournal::new(dir.path().join("txpool").join("journal"))
+	}
+
+	fn transactions() -> Vec<PersistedTransaction> {
+		vec![
+			PersistedTransaction {
+				source: TransactionSource::External,
+				priority: 10,
+				data: vec![1, 2, 3],
+			},
+			PersistedTransaction { source: TransactionSource::Local, priority: 1, data: vec![4] },
+		]
+	}
+
+	#[test]
+	fn missing_journal_is_empty() {
+		let dir = TempDir::new().unwrap();
+		assert!(journal(&dir).read().unwrap().is_empty());
+	}
+
+	#[test]
+	fn journal_roundtrip_works() {
+		let dir = TempDir::new().unwrap();
+		let journal = journal(&dir);
+
+		journal.write(&transactions()).unwrap();
+		assert_eq!(journal.read().unwrap(), transactions());
+
+		journal.write(&[]).unwrap();
+		assert!(journal.read().unwrap().is_empty());
+	}
+
+	#[test]
+	fn writer_is_rate_limited_and_flushes_on_drop() {
+		let dir = TempDir::new().unwrap();
+		let writer = JournalWriter::new(journal(&dir)).unwrap();
+
+		let mut snapshots = 0;
+		writer.maybe_write(|| {
+			snapshots += 1;
+			transactions()
+		});
+		writer.maybe_write(|| {
+			snapshots += 1;
+			Vec::new()
+		});
+		assert_eq!(snapshots, 1);
+
+		writer.write(transactions()[..1].to_vec());
+		drop(writer);
+		assert_eq!(journal(&dir).read().unwrap(), transactions()[..1].to_vec());
+	}
+}
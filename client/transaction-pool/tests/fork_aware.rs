@@ -0,0 +1,207 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Tests for the fork aware transaction pool.
+
+use futures::executor::{block_on, block_on_stream};
+use sc_block_builder::BlockBuilderProvider;
+use sc_transaction_pool::{ForkAwareTxPool, FullChainApi};
+use sc_transaction_pool_api::{
+	ChainEvent, LocalTransactionPool, MaintainedTransactionPool, TransactionPool, TransactionStatus,
+};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_runtime::{generic::BlockId, transaction_validity::TransactionSource};
+use std::sync::Arc;
+use substrate_test_runtime_client::{
+	runtime::{Block, Hash, Header},
+	AccountKeyring::*,
+	ClientBlockImportExt,
+};
+use substrate_test_runtime_transaction_pool::{uxt, TestApi};
+
+const SOURCE: TransactionSource = TransactionSource::External;
+
+fn pool() -> (ForkAwareTxPool<TestApi, Block>, Arc<TestApi>, Hash) {
+	let api = Arc::new(TestApi::with_alice_nonce(209));
+	let genesis_hash = api
+		.chain()
+		.read()
+		.block_by_number
+		.get(&0)
+		.map(|blocks| blocks[0].0.header.hash())
+		.expect("there is block 0. qed");
+
+	(ForkAwareTxPool::new_test(api.clone(), genesis_hash, genesis_hash), api, genesis_hash)
+}
+
+fn block_event(header: &Header) -> ChainEvent<Block> {
+	ChainEvent::NewBestBlock { hash: header.hash(), tree_route: None }
+}
+
+fn finalized_event(header: &Header, tree_route: Vec<Hash>) -> ChainEvent<Block> {
+	ChainEvent::Finalized { hash: header.hash(), tree_route: Arc::from(tree_route) }
+}
+
+#[test]
+fn fork_aware_pool_keeps_transactions_until_finalized() {
+	let (pool, api, _) = pool();
+
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209))).unwrap();
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 210))).unwrap();
+	assert_eq!(pool.status().ready, 2);
+
+	let header = api.push_block(1, vec![uxt(Alice, 209)], true);
+	api.increment_nonce(Alice.into());
+	block_on(pool.maintain(block_event(&header)));
+
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.views_count(), 1);
+	assert_eq!(pool.mempool_len(), 2);
+
+	block_on(pool.maintain(finalized_event(&header, vec![])));
+
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.mempool_len(), 1);
+}
+
+#[test]
+fn fork_aware_pool_rejects_already_imported_transaction() {
+	let (pool, _, _) = pool();
+
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209))).unwrap();
+	block_on(pool.submit_one(&BlockId::number(0), SOURCE, uxt(Alice, 209)))
+		.expect_err("Already imported");
+	assert_eq!(pool.mempool_len(), 1);
+}
+
+#[test]
+fn fork_aware_pool_reports_invalid_transactions() {
+	let (pool, api, _) = pool();
+	let xt = uxt(Alice, 209);
+
+	let watcher = block_on(pool.submit_and_watch(&BlockId::number(0), SOURCE, xt.clone())).unwrap();
+	assert_eq!(pool.status().ready, 1);
+
+	api.add_invalid(&xt);
+	let header = api.push_block(1, vec![], true);
+	block_on(pool.maintain(block_event(&header)));
+
+	assert_eq!(pool.status().ready, 0);
+	assert_eq!(pool.mempool_len(), 0);
+	assert_eq!(
+		block_on_stream(watcher).collect::<Vec<_>>(),
+		vec![TransactionStatus::Ready, TransactionStatus::Invalid],
+	);
+}
+
+#[test]
+fn fork_aware_pool_follows_transaction_across_forks() {
+	let (pool, api, genesis_hash) = pool();
+	let xt = uxt(Alice, 209);
+
+	let watcher = block_on(pool.submit_and_watch(&BlockId::number(0), SOURCE, xt.clone())).unwrap();
+
+	// the transaction is included in the first fork
+	let header_1a = api.push_block_with_parent(genesis_hash, vec![xt.clone()], true);
+	block_on(pool.maintain(block_event(&header_1a)));
+	assert_eq!(pool.status().ready, 0);
+
+	// the second fork becomes the best one, the transaction is ready again
+	let header_1b = api.push_block_with_parent(genesis_hash, vec![], true);
+	block_on(pool.maintain(block_event(&header_1b)));
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.views_count(), 2);
+
+	// the transaction is included and finalized in the second fork
+	let header_2b = api.push_block_with_parent(header_1b.hash(), vec![xt], true);
+	block_on(pool.maintain(block_event(&header_2b)));
+	block_on(pool.maintain(finalized_event(&header_2b, vec![header_1b.hash()])));
+
+	assert_eq!(pool.status().ready, 0);
+	assert_eq!(pool.views_count(), 1);
+	assert_eq!(pool.mempool_len(), 0);
+	assert_eq!(
+		block_on_stream(watcher).collect::<Vec<_>>(),
+		vec![
+			TransactionStatus::Ready,
+			TransactionStatus::InBlock((header_1a.hash(), 0)),
+			TransactionStatus::Retracted(header_1a.hash()),
+			TransactionStatus::Ready,
+			TransactionStatus::InBlock((header_2b.hash(), 0)),
+			TransactionStatus::Finalized((header_2b.hash(), 0)),
+		],
+	);
+}
+
+#[test]
+fn fork_aware_pool_resubmits_retracted_transactions_to_existing_views() {
+	let (pool, api, genesis_hash) = pool();
+
+	let header_1a = api.push_block_with_parent(genesis_hash, vec![uxt(Alice, 209)], true);
+	let header_1b = api.push_block_with_parent(genesis_hash, vec![], true);
+	block_on(pool.maintain(block_event(&header_1b)));
+	block_on(pool.maintain(block_event(&header_1a)));
+	assert_eq!(pool.views_count(), 2);
+	assert_eq!(pool.status().ready, 0);
+
+	// the view of the second fork is reused and gets the transaction of the retracted block
+	block_on(pool.maintain(block_event(&header_1b)));
+	assert_eq!(pool.views_count(), 2);
+	assert_eq!(pool.status().ready, 1);
+	assert_eq!(pool.mempool_len(), 1);
+}
+
+#[test]
+fn fork_aware_pool_validates_local_transactions_against_every_view() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let genesis_hash = client.info().genesis_hash;
+	let api = FullChainApi::new(client.clone(), None, &sp_core::testing::TaskExecutor::new());
+	let pool = ForkAwareTxPool::new_test(Arc::new(api), genesis_hash, genesis_hash);
+	let new_best = |hash| ChainEvent::NewBestBlock { hash, tree_route: None };
+
+	// the transaction is included in the first fork only
+	let xt = uxt(Alice, 0);
+	let mut block_builder = client.new_block_at(genesis_hash, Default::default(), false).unwrap();
+	block_builder.push(xt.clone()).unwrap();
+	let block_1a = block_builder.build().unwrap().block;
+	let hash_1a = block_1a.header.hash();
+	block_on(client.import(BlockOrigin::Own, block_1a)).unwrap();
+	let block_1b = client
+		.new_block_at(genesis_hash, Default::default(), false)
+		.unwrap()
+		.build()
+		.unwrap()
+		.block;
+	let hash_1b = block_1b.header.hash();
+	block_on(client.import(BlockOrigin::Own, block_1b)).unwrap();
+
+	block_on(pool.maintain(new_best(hash_1b)));
+	block_on(pool.maintain(new_best(hash_1a)));
+	assert_eq!(pool.views_count(), 2);
+
+	// the transaction is stale in the view of the first fork, but valid in the other one
+	let hash = pool.submit_local(&BlockId::Hash(hash_1a), xt).unwrap();
+	assert_eq!(pool.status().ready, 0);
+	assert!(pool.ready_transaction(&hash).is_none());
+
+	block_on(pool.maintain(new_best(hash_1b)));
+	assert_eq!(pool.views_count(), 2);
+	assert_eq!(pool.status().ready, 1);
+	assert!(pool.ready_transaction(&hash).is_some());
+}
@@ -1568,3 +1568,49 @@ fn best_block_after_finalization_does_not_retract() {
 		assert_eq!(stream.next(), None);
 	}
 }
+
+#[test]
+fn journaled_transactions_are_reloaded_after_restart() {
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let best_hash = client.info().best_hash;
+	let best = || ChainEvent::NewBestBlock { hash: best_hash, tree_route: None };
+
+	for txpool_type in [TransactionPoolType::SingleState, TransactionPoolType::ForkAware] {
+		let dir = tempfile::tempdir().unwrap();
+		let start = || {
+			let options = TransactionPoolOptions {
+				txpool_type,
+				journal_path: Some(dir.path().join("txpool-journal")),
+				..Default::default()
+			};
+			TransactionPoolHandle::new_full(
+				options,
+				true.into(),
+				None,
+				sp_core::testing::TaskExecutor::new(),
+				client.clone(),
+			)
+		};
+
+		let pool = start();
+		let xt = uxt(Alice, 0);
+		let hash = block_on(pool.submit_one(&BlockId::number(0), SOURCE, xt)).unwrap();
+		assert_eq!(pool.status().ready, 1);
+		// Dropping the pool flushes the journal, like on shutdown.
+		drop(pool);
+
+		let pool = start();
+		assert_eq!(pool.status().ready, 0);
+		block_on(pool.maintain(best()));
+		assert_eq!(pool.status().ready, 1, "{:?}", txpool_type);
+		assert!(pool.ready_transaction(&hash).is_some());
+
+		// Restarting before the journal is resubmitted doesn't lose it.
+		drop(pool);
+		let pool = start();
+		drop(pool);
+		let pool = start();
+		block_on(pool.maintain(best()));
+		assert_eq!(pool.status().ready, 1, "{:?}", txpool_type);
+	}
+}
@@ -113,6 +113,15 @@
 //!
 //! pub type Executive = executive::Executive<Runtime, Block, Context, Runtime, AllPalletsWithSystem, CustomOnRuntimeUpgrade>;
 //! ```
+//!
+//! ### Multi-block migrations
+//!
+//! Migrations that do not fit into a single block implement
+//! [`SteppedMigration`](frame_support::migrations::SteppedMigration) and are executed by the
+//! [`MultiStepMigrator`](frame_support::migrations::MultiStepMigrator) set as the last generic
+//! parameter, usually `pallet-migrations`. The migrator is stepped after `on_initialize` of every
+//! block. While a migration is ongoing, only inherents are applied; other extrinsics fail with
+//! [`InvalidTransaction::ExhaustsResources`] and stay in the transaction pool.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
@@ -132,7 +141,7 @@ use sp_runtime::{
 		self, Applyable, CheckEqual, Checkable, Dispatchable, Header, NumberFor, One,
 		ValidateUnsigned, Zero,
 	},
-	transaction_validity::{TransactionSource, TransactionValidity},
+	transaction_validity::{TransactionSource, TransactionValidity, TransactionValidityError},
 	ApplyExtrinsicResult,
 };
 use sp_std::{marker::PhantomData, prelude::*};
@@ -155,6 +164,9 @@ pub type OriginOf<E, C> = <CallOf<E, C> as Dispatchable>::RuntimeOrigin;
 ///   used to call hooks e.g. `on_initialize`.
 /// - `OnRuntimeUpgrade`: Custom logic that should be called after a runtime upgrade. Modules are
 ///   already called by `AllPalletsWithSystem`. It will be called before all modules will be called.
+/// - `MultiStepMigrator`: Executes multi-block migrations, usually `pallet-migrations`. It is
+///   stepped after `on_initialize` of every block, and only inherents are applied while a migration
+///   is ongoing.
 pub struct Executive<
 	System,
 	Block,
@@ -162,6 +174,7 @@ pub struct Executive<
 	UnsignedValidator,
 	AllPalletsWithSystem,
 	OnRuntimeUpgrade = (),
+	MultiStepMigrator = (),
 >(
 	PhantomData<(
 		System,
@@ -170,6 +183,7 @@ pub struct Executive<
 		UnsignedValidator,
 		AllPalletsWithSystem,
 		OnRuntimeUpgrade,
+		MultiStepMigrator,
 	)>,
 );
 
@@ -184,8 +198,17 @@ impl<
 			+ OnFinalize<System::BlockNumber>
 			+ OffchainWorker<System::BlockNumber>,
 		COnRuntimeUpgrade: OnRuntimeUpgrade,
+		MultiStepMigrator: frame_support::migrations::MultiStepMigrator,
 	> ExecuteBlock<Block>
-	for Executive<System, Block, Context, UnsignedValidator, AllPalletsWithSystem, COnRuntimeUpgrade>
+	for Executive<
+		System,
+		Block,
+		Context,
+		UnsignedValidator,
+		AllPalletsWithSystem,
+		COnRuntimeUpgrade,
+		MultiStepMigrator,
+	>
 where
 	Block::Extrinsic: Checkable<Context> + Codec,
 	CheckedOf<Block::Extrinsic, Context>: Applyable + GetDispatchInfo,
@@ -202,6 +225,7 @@ where
 			UnsignedValidator,
 			AllPalletsWithSystem,
 			COnRuntimeUpgrade,
+			MultiStepMigrator,
 		>::execute_block(block);
 	}
 }
@@ -219,7 +243,17 @@ impl<
 			+ OffchainWorker<System::BlockNumber>
 			+ frame_support::traits::TryState<System::BlockNumber>,
 		COnRuntimeUpgrade: OnRuntimeUpgrade,
-	> Executive<System, Block, Context, UnsignedValidator, AllPalletsWithSystem, COnRuntimeUpgrade>
+		MultiStepMigrator: frame_support::migrations::MultiStepMigrator,
+	>
+	Executive<
+		System,
+		Block,
+		Context,
+		UnsignedValidator,
+		AllPalletsWithSystem,
+		COnRuntimeUpgrade,
+		MultiStepMigrator,
+	>
 where
 	Block::Extrinsic: Checkable<Context> + Codec,
 	CheckedOf<Block::Extrinsic, Context>: Applyable + GetDispatchInfo,
@@ -267,9 +301,11 @@ where
 			} else {
 				uxt.unchecked_into_checked_i_know_what_i_am_doing(&Default::default())
 			}?;
+			let dispatch_info = xt.get_dispatch_info();
+			Self::ensure_no_ongoing_migration(&dispatch_info)?;
+
 			<frame_system::Pallet<System>>::note_extrinsic(encoded);
 
-			let dispatch_info = xt.get_dispatch_info();
 			let r = Applyable::apply::<UnsignedValidator>(xt, &dispatch_info, encoded_len)?;
 
 			<frame_system::Pallet<System>>::note_applied_extrinsic(&r, dispatch_info);
@@ -375,7 +411,17 @@ impl<
 			+ OnFinalize<System::BlockNumber>
 			+ OffchainWorker<System::BlockNumber>,
 		COnRuntimeUpgrade: OnRuntimeUpgrade,
-	> Executive<System, Block, Context, UnsignedValidator, AllPalletsWithSystem, COnRuntimeUpgrade>
+		MultiStepMigrator: frame_support::migrations::MultiStepMigrator,
+	>
+	Executive<
+		System,
+		Block,
+		Context,
+		UnsignedValidator,
+		AllPalletsWithSystem,
+		COnRuntimeUpgrade,
+		MultiStepMigrator,
+	>
 where
 	Block::Extrinsic: Checkable<Context> + Codec,
 	CheckedOf<Block::Extrinsic, Context>: Applyable + GetDispatchInfo,
@@ -425,6 +471,7 @@ where
 		weight = weight.saturating_add(<AllPalletsWithSystem as OnInitialize<
 			System::BlockNumber,
 		>>::on_initialize(*block_number));
+		weight = weight.saturating_add(MultiStepMigrator::step());
 		weight = weight.saturating_add(
 			<System::BlockWeights as frame_support::traits::Get<_>>::get().base_block,
 		);
@@ -552,6 +599,9 @@ where
 		// Verify that the signature is good.
 		let xt = uxt.check(&Default::default())?;
 
+		let dispatch_info = xt.get_dispatch_info();
+		Self::ensure_no_ongoing_migration(&dispatch_info)?;
+
 		// We don't need to make sure to `note_extrinsic` only after we know it's going to be
 		// executed to prevent it from leaking in storage since at this point, it will either
 		// execute or panic (and revert storage changes).
@@ -560,7 +610,6 @@ where
 		// AUDIT: Under no circumstances may this function panic from here onwards.
 
 		// Decode parameters and dispatch
-		let dispatch_info = xt.get_dispatch_info();
 		let r = Applyable::apply::<UnsignedValidator>(xt, &dispatch_info, encoded_len)?;
 
 		// Mandatory(inherents) are not allowed to fail.
@@ -576,6 +625,19 @@ where
 		Ok(r.map(|_| ()).map_err(|e| e.error))
 	}
 
+	/// Only inherents may be applied while multi-block migrations are ongoing.
+	///
+	/// Other extrinsics are reported as exhausting the block resources, so that block authors keep
+	/// them in the transaction pool until the migrations completed.
+	fn ensure_no_ongoing_migration(
+		dispatch_info: &DispatchInfo,
+	) -> Result<(), TransactionValidityError> {
+		if dispatch_info.class != DispatchClass::Mandatory && MultiStepMigrator::ongoing() {
+			return Err(InvalidTransaction::ExhaustsResources.into())
+		}
+		Ok(())
+	}
+
 	fn final_checks(header: &System::Header) {
 		sp_tracing::enter_span!(sp_tracing::Level::TRACE, "final_checks");
 		// remove temporaries
@@ -685,6 +747,7 @@ mod tests {
 
 	use frame_support::{
 		assert_err, parameter_types,
+		storage::unhashed,
 		traits::{fungible, ConstU32, ConstU64, ConstU8, Currency},
 		weights::{ConstantMultiplier, IdentityFee, RuntimeDbWeight, Weight, WeightToFee},
 	};
@@ -956,6 +1019,35 @@ mod tests {
 		CustomOnRuntimeUpgrade,
 	>;
 
+	// Number of steps left in the mocked multi-block migration.
+	const MBM_STEPS_KEY: &[u8] = b":mbm:steps";
+
+	struct MockedMigrator;
+	impl frame_support::migrations::MultiStepMigrator for MockedMigrator {
+		fn ongoing() -> bool {
+			unhashed::get_or_default::<u32>(MBM_STEPS_KEY) > 0
+		}
+
+		fn step() -> Weight {
+			let steps = unhashed::get_or_default::<u32>(MBM_STEPS_KEY);
+			if steps == 0 {
+				return Weight::zero()
+			}
+			unhashed::put(MBM_STEPS_KEY, &(steps - 1));
+			Weight::from_parts(10, 0)
+		}
+	}
+
+	type ExecutiveWithMigrator = super::Executive<
+		Runtime,
+		Block<TestXt>,
+		ChainContext<Runtime>,
+		Runtime,
+		AllPalletsWithSystem,
+		(),
+		MockedMigrator,
+	>;
+
 	fn extra(nonce: u64, fee: Balance) -> SignedExtra {
 		(
 			frame_system::CheckEra::from(Era::Immortal),
@@ -1622,6 +1714,36 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn only_inherents_are_applied_while_migrations_are_ongoing() {
+		let inherent = TestXt::new(RuntimeCall::Custom(custom::Call::inherent_call {}), None);
+		let transfer = TestXt::new(call_transfer(33, 0), sign_extra(1, 0, 0));
+
+		new_test_ext(1).execute_with(|| {
+			unhashed::put(MBM_STEPS_KEY, &2u32);
+
+			ExecutiveWithMigrator::initialize_block(&Header::new_from_number(1));
+			assert_eq!(unhashed::get::<u32>(MBM_STEPS_KEY), Some(1));
+			// `on_initialize`, the base block weight and the migration step.
+			assert_eq!(
+				<frame_system::Pallet<Runtime>>::block_weight().total(),
+				Weight::from_parts(175 + 10 + 10, 0)
+			);
+
+			ExecutiveWithMigrator::apply_extrinsic(inherent.clone()).unwrap().unwrap();
+			assert_eq!(
+				ExecutiveWithMigrator::apply_extrinsic(transfer.clone()),
+				Err(InvalidTransaction::ExhaustsResources.into())
+			);
+			ExecutiveWithMigrator::finalize_block();
+
+			// The last step completes the migration, transactions are applied again.
+			ExecutiveWithMigrator::initialize_block(&Header::new_from_number(2));
+			assert_eq!(unhashed::get::<u32>(MBM_STEPS_KEY), Some(0));
+			ExecutiveWithMigrator::apply_extrinsic(transfer).unwrap().unwrap();
+		});
+	}
+
 	// Inherents are created by the runtime and don't need to be validated.
 	#[test]
 	fn inherents_fail_validate_block() {
@@ -0,0 +1,109 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks for the migrations pallet.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::{Pallet as Migrations, *};
+
+use frame_benchmarking::v2::*;
+use frame_system::RawOrigin;
+
+#[benchmarks]
+mod benches {
+	use super::*;
+
+	#[benchmark]
+	fn onboard_new_mbms() {
+		Cursor::<T>::put(MigrationCursor::Stuck);
+
+		#[block]
+		{
+			Migrations::<T>::onboard_new_mbms();
+		}
+	}
+
+	#[benchmark]
+	fn progress_mbms_none() {
+		#[block]
+		{
+			Migrations::<T>::progress_mbms(System::<T>::block_number());
+		}
+	}
+
+	/// Completing the upgrade is the heaviest branch apart from the step of the migration
+	/// itself, which is accounted for by the migration.
+	#[benchmark]
+	fn exec_migration() {
+		let cursor = ActiveCursor {
+			index: T::Migrations::len(),
+			inner_cursor: None,
+			started_at: System::<T>::block_number(),
+		};
+		Cursor::<T>::put(MigrationCursor::Active(cursor.clone()));
+
+		#[block]
+		{
+			Migrations::<T>::exec_migration(
+				cursor,
+				false,
+				System::<T>::block_number(),
+				&mut WeightMeter::max_limit(),
+			);
+		}
+
+		assert!(!Cursor::<T>::exists());
+	}
+
+	#[benchmark]
+	fn force_set_cursor() {
+		#[extrinsic_call]
+		_(RawOrigin::Root, Some(MigrationCursor::Stuck));
+	}
+
+	#[benchmark]
+	fn force_set_active_cursor() {
+		#[extrinsic_call]
+		_(RawOrigin::Root, 0, None, None);
+	}
+
+	#[benchmark]
+	fn force_onboard_mbms() {
+		#[extrinsic_call]
+		_(RawOrigin::Root);
+	}
+
+	#[benchmark]
+	fn clear_historic(n: Linear<0, 1_000>) {
+		let ids = (0..n)
+			.map(|i| {
+				let id: IdentifierOf<T> =
+					i.encode().try_into().expect("IdentifierMaxLen fits an u32; qed");
+				Historic::<T>::insert(&id, ());
+				id
+			})
+			.collect::<Vec<_>>();
+
+		#[extrinsic_call]
+		_(RawOrigin::Root, ids);
+
+		assert_eq!(Historic::<T>::iter().count(), 0);
+	}
+
+	impl_benchmark_test_suite! { Migrations, crate::mock::new_test_ext(), crate::mock::Test }
+}
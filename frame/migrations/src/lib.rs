@@ -0,0 +1,543 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! # Multi-Block Migrations Pallet
+//!
+//! Executes [`SteppedMigration`]s across multiple blocks.
+//!
+//! ## Overview
+//!
+//! A runtime upgrade onboards the migrations configured in [`Config::Migrations`]. From then on,
+//! every block advances the migrations by calling [`SteppedMigration::step`] with the weight
+//! limited to [`Config::MaxServiceWeight`]. The cursor returned by a step is persisted in
+//! [`Cursor`], so that the next block resumes where the last one stopped. Once a migration
+//! returns no cursor, it is recorded in [`Historic`] and the next migration starts, possibly in
+//! the same block.
+//!
+//! The pallet implements [`MultiStepMigrator`] and is meant to be passed to `frame-executive`,
+//! which steps it after `on_initialize` and only applies inherents while a migration is ongoing.
+//!
+//! ## Failure handling
+//!
+//! A migration fails if a step returns an error, if it takes more than
+//! [`SteppedMigration::max_steps`] blocks, or if a runtime upgrade happens while it is ongoing.
+//! The changes of the failing step are rolled back and [`Config::FailedMigrationHandler`] decides
+//! whether the chain stays stuck, in which case [`Cursor`] is set to [`MigrationCursor::Stuck`],
+//! or resumes normal operation. A stuck chain only applies inherents; governance can recover it
+//! with a runtime upgrade that onboards fixed migrations.
+//!
+//! ## Try-runtime
+//!
+//! With the `try-runtime` feature, [`SteppedMigration::pre_upgrade`] is called before the first
+//! step of a migration and [`SteppedMigration::post_upgrade`] after its last one. A failing check
+//! fails the migration.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod benchmarking;
+mod mock;
+pub mod mock_helpers;
+mod tests;
+pub mod weights;
+
+use frame_support::{
+	defensive,
+	migrations::{
+		ActiveCursor, FailedMigrationHandler, FailedMigrationHandling, MigrationCursor,
+		MigrationStatusHandler, MultiStepMigrator, SteppedMigrationError, SteppedMigrations,
+	},
+	pallet_prelude::*,
+};
+use frame_system::{pallet_prelude::*, Pallet as System};
+pub use pallet::*;
+use sp_runtime::{traits::One, Saturating};
+use sp_std::{ops::ControlFlow, prelude::*};
+use sp_weights::WeightMeter;
+pub use weights::WeightInfo;
+
+#[cfg(doc)]
+use frame_support::migrations::SteppedMigration;
+
+const LOG_TARGET: &str = "runtime::migrations";
+
+/// The encoded cursor of a migration.
+pub type RawCursorOf<T> = BoundedVec<u8, <T as Config>::CursorMaxLen>;
+
+/// The encoded identifier of a migration.
+pub type IdentifierOf<T> = BoundedVec<u8, <T as Config>::IdentifierMaxLen>;
+
+/// The progress of the ongoing migrations.
+pub type CursorOf<T> = MigrationCursor<RawCursorOf<T>, BlockNumberFor<T>>;
+
+/// The progress of the migration that is currently executed.
+pub type ActiveCursorOf<T> = ActiveCursor<RawCursorOf<T>, BlockNumberFor<T>>;
+
+#[frame_support::pallet]
+pub mod pallet {
+	use super::*;
+
+	#[pallet::pallet]
+	pub struct Pallet<T>(_);
+
+	#[pallet::config]
+	pub trait Config: frame_system::Config {
+		/// The overarching event type.
+		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// The migrations onboarded by a runtime upgrade, executed in order.
+		///
+		/// Migrations that already completed, as recorded in [`Historic`], are skipped.
+		type Migrations: SteppedMigrations;
+
+		/// The maximal length of an encoded cursor.
+		///
+		/// Must be at least the maximal encoded length of the cursors of all migrations.
+		#[pallet::constant]
+		type CursorMaxLen: Get<u32>;
+
+		/// The maximal length of an encoded migration identifier.
+		///
+		/// Must be at least the maximal encoded length of the identifiers of all migrations.
+		#[pallet::constant]
+		type IdentifierMaxLen: Get<u32>;
+
+		/// Notified when the migrations of an upgrade start and complete.
+		type MigrationStatusHandler: MigrationStatusHandler;
+
+		/// Decides what happens after a migration failed.
+		type FailedMigrationHandler: FailedMigrationHandler;
+
+		/// The weight available to migrations in every block.
+		#[pallet::constant]
+		type MaxServiceWeight: Get<Weight>;
+
+		/// Weight information for extrinsics and hooks in this pallet.
+		type WeightInfo: WeightInfo;
+	}
+
+	#[pallet::event]
+	#[pallet::generate_deposit(pub(super) fn deposit_event)]
+	pub enum Event<T: Config> {
+		/// The migrations of a runtime upgrade started.
+		UpgradeStarted {
+			/// The number of migrations to execute, including the ones that will be skipped.
+			migrations: u32,
+		},
+		/// All migrations of a runtime upgrade completed.
+		UpgradeCompleted,
+		/// A migration failed, see [`Config::FailedMigrationHandler`] for what happens next.
+		UpgradeFailed,
+		/// A migration was skipped since it already completed in the past.
+		MigrationSkipped {
+			/// The index of the migration.
+			index: u32,
+		},
+		/// A migration made progress.
+		MigrationAdvanced {
+			/// The index of the migration.
+			index: u32,
+			/// The number of blocks since the migration started.
+			took: BlockNumberFor<T>,
+		},
+		/// A migration completed.
+		MigrationCompleted {
+			/// The index of the migration.
+			index: u32,
+			/// The number of blocks the migration took.
+			took: BlockNumberFor<T>,
+		},
+		/// A migration failed.
+		MigrationFailed {
+			/// The index of the migration.
+			index: u32,
+			/// The number of blocks since the migration started.
+			took: BlockNumberFor<T>,
+		},
+		/// Migrations were removed from [`Historic`].
+		HistoricCleared {
+			/// The number of removed migrations.
+			count: u32,
+		},
+	}
+
+	#[pallet::error]
+	pub enum Error<T> {
+		/// The operation cannot be done while migrations are ongoing.
+		Ongoing,
+	}
+
+	/// The progress of the ongoing migrations, `None` if there are none.
+	#[pallet::storage]
+	pub type Cursor<T: Config> = StorageValue<_, CursorOf<T>, OptionQuery>;
+
+	/// The identifiers of the migrations that completed.
+	#[pallet::storage]
+	pub type Historic<T: Config> = StorageMap<_, Twox64Concat, IdentifierOf<T>, (), OptionQuery>;
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		fn on_runtime_upgrade() -> Weight {
+			Self::onboard_new_mbms()
+		}
+
+		/// Check all assumptions about [`crate::Config`].
+		fn integrity_test() {
+			let max_block = <T as frame_system::Config>::BlockWeights::get().max_block;
+			assert!(
+				T::MaxServiceWeight::get().all_lte(max_block),
+				"MaxServiceWeight exceeds the maximal block weight"
+			);
+			assert!(
+				T::Migrations::cursor_max_encoded_len() <= T::CursorMaxLen::get() as usize,
+				"CursorMaxLen is shorter than the cursor of a migration"
+			);
+			assert!(
+				T::Migrations::identifier_max_encoded_len() <= T::IdentifierMaxLen::get() as usize,
+				"IdentifierMaxLen is shorter than the identifier of a migration"
+			);
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn try_state(_n: BlockNumberFor<T>) -> Result<(), &'static str> {
+			if let Some(MigrationCursor::Active(cursor)) = Cursor::<T>::get() {
+				ensure!(
+					cursor.index <= T::Migrations::len(),
+					"The cursor points past the last migration"
+				);
+			}
+			Ok(())
+		}
+	}
+
+	#[pallet::call]
+	impl<T: Config> Pallet<T> {
+		/// Overwrite the progress of the ongoing migrations.
+		///
+		/// `None` resumes normal operation, the remaining migrations are not executed.
+		#[pallet::call_index(0)]
+		#[pallet::weight(T::WeightInfo::force_set_cursor())]
+		pub fn force_set_cursor(
+			origin: OriginFor<T>,
+			cursor: Option<CursorOf<T>>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			Cursor::<T>::set(cursor);
+			Ok(())
+		}
+
+		/// Resume the migrations at the migration with the given `index`.
+		///
+		/// `started_at` defaults to the current block.
+		#[pallet::call_index(1)]
+		#[pallet::weight(T::WeightInfo::force_set_active_cursor())]
+		pub fn force_set_active_cursor(
+			origin: OriginFor<T>,
+			index: u32,
+			inner_cursor: Option<RawCursorOf<T>>,
+			started_at: Option<BlockNumberFor<T>>,
+		) -> DispatchResult {
+			ensure_root(origin)?;
+			let started_at = started_at.unwrap_or_else(System::<T>::block_number);
+			Cursor::<T>::put(MigrationCursor::Active(ActiveCursor {
+				index,
+				inner_cursor,
+				started_at,
+			}));
+			Ok(())
+		}
+
+		/// Start the migrations of [`Config::Migrations`] as if a runtime upgrade happened.
+		#[pallet::call_index(2)]
+		#[pallet::weight(T::WeightInfo::force_onboard_mbms())]
+		pub fn force_onboard_mbms(origin: OriginFor<T>) -> DispatchResult {
+			ensure_root(origin)?;
+			ensure!(!Cursor::<T>::exists(), Error::<T>::Ongoing);
+
+			let migrations = T::Migrations::len();
+			if migrations > 0 {
+				Self::start_migrations(migrations, Self::next_block());
+			}
+			Ok(())
+		}
+
+		/// Remove migrations from [`Historic`], so that they are executed again by the next
+		/// upgrade.
+		#[pallet::call_index(3)]
+		#[pallet::weight(T::WeightInfo::clear_historic(ids.len() as u32))]
+		pub fn clear_historic(origin: OriginFor<T>, ids: Vec<IdentifierOf<T>>) -> DispatchResult {
+			ensure_root(origin)?;
+
+			let mut count = 0u32;
+			for id in ids {
+				if Historic::<T>::take(id).is_some() {
+					count.saturating_inc();
+				}
+			}
+			Self::deposit_event(Event::HistoricCleared { count });
+			Ok(())
+		}
+	}
+}
+
+impl<T: Config> Pallet<T> {
+	/// Onboard the migrations of a runtime upgrade.
+	fn onboard_new_mbms() -> Weight {
+		match Cursor::<T>::get() {
+			Some(MigrationCursor::Active(cursor)) => {
+				log::error!(
+					target: LOG_TARGET,
+					"Runtime upgrade while migration {} is ongoing",
+					cursor.index
+				);
+				Self::upgrade_failed(Some(cursor.index));
+				return T::WeightInfo::onboard_new_mbms()
+			},
+			Some(MigrationCursor::Stuck) => {
+				log::info!(target: LOG_TARGET, "Runtime upgrade of a stuck chain, retrying");
+			},
+			None => (),
+		}
+
+		let migrations = T::Migrations::len();
+		log::debug!(target: LOG_TARGET, "Onboarding {} migrations", migrations);
+		if migrations > 0 {
+			// Runtime upgrades are applied before the block number is set, so this is the block in
+			// which the first step is executed.
+			Self::start_migrations(migrations, Self::next_block());
+		} else {
+			Cursor::<T>::kill();
+		}
+
+		T::WeightInfo::onboard_new_mbms()
+	}
+
+	/// Start the migrations, executing their first step in the block `started_at`.
+	fn start_migrations(migrations: u32, started_at: BlockNumberFor<T>) {
+		let cursor = ActiveCursor { index: 0, inner_cursor: None, started_at };
+		Cursor::<T>::put(MigrationCursor::Active(cursor));
+		Self::deposit_event(Event::UpgradeStarted { migrations });
+		T::MigrationStatusHandler::started();
+	}
+
+	fn next_block() -> BlockNumberFor<T> {
+		System::<T>::block_number().saturating_add(One::one())
+	}
+
+	/// Advance the ongoing migrations as far as [`Config::MaxServiceWeight`] allows.
+	fn progress_mbms(now: BlockNumberFor<T>) -> Weight {
+		let mut meter = WeightMeter::from_limit(T::MaxServiceWeight::get());
+		meter.defensive_saturating_accrue(T::WeightInfo::progress_mbms_none());
+
+		let mut cursor = match Cursor::<T>::get() {
+			None => return meter.consumed,
+			Some(MigrationCursor::Active(cursor)) => cursor,
+			Some(MigrationCursor::Stuck) => {
+				log::error!(target: LOG_TARGET, "Migrations are stuck, governance must intervene");
+				return meter.consumed
+			},
+		};
+
+		let mut is_first = true;
+		loop {
+			match Self::exec_migration(cursor, is_first, now, &mut meter) {
+				// The cursor was already updated.
+				None => return meter.consumed,
+				Some(ControlFlow::Break(next)) => {
+					cursor = next;
+					break
+				},
+				Some(ControlFlow::Continue(next)) => cursor = next,
+			}
+			is_first = false;
+		}
+
+		Cursor::<T>::put(MigrationCursor::Active(cursor));
+		meter.consumed
+	}
+
+	/// Execute one step of the migration at `cursor`.
+	///
+	/// Returns whether the next migration can be executed in the same block, or `None` if the
+	/// migrations completed or failed.
+	fn exec_migration(
+		mut cursor: ActiveCursorOf<T>,
+		is_first: bool,
+		now: BlockNumberFor<T>,
+		meter: &mut WeightMeter,
+	) -> Option<ControlFlow<ActiveCursorOf<T>, ActiveCursorOf<T>>> {
+		// The overhead of the branches below is similar, account for the largest one.
+		if !meter.check_accrue(T::WeightInfo::exec_migration()) {
+			if is_first {
+				defensive!("MaxServiceWeight does not allow for a single migration step");
+			}
+			return Some(ControlFlow::Break(cursor))
+		}
+
+		let Some(id) = T::Migrations::nth_id(cursor.index) else {
+			// No more migrations, the upgrade is done.
+			Cursor::<T>::kill();
+			Self::deposit_event(Event::UpgradeCompleted);
+			T::MigrationStatusHandler::completed();
+			return None
+		};
+		let Ok(id): Result<IdentifierOf<T>, _> = id.try_into() else {
+			defensive!("The identifier of a migration exceeds IdentifierMaxLen");
+			Self::upgrade_failed(Some(cursor.index));
+			return None
+		};
+
+		if Historic::<T>::contains_key(&id) {
+			Self::deposit_event(Event::MigrationSkipped { index: cursor.index });
+			cursor.goto_next_migration(now);
+			return Some(ControlFlow::Continue(cursor))
+		}
+
+		let took = now.saturating_sub(cursor.started_at);
+
+		#[cfg(feature = "try-runtime")]
+		if cursor.inner_cursor.is_none() {
+			match T::Migrations::nth_pre_upgrade(cursor.index) {
+				Some(Ok(state)) => try_runtime::store_pre_upgrade_state(cursor.index, state),
+				Some(Err(e)) => {
+					log::error!(
+						target: LOG_TARGET,
+						"Pre-upgrade of migration {} failed: {}",
+						cursor.index,
+						e
+					);
+					Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+					Self::upgrade_failed(Some(cursor.index));
+					return None
+				},
+				None => (),
+			}
+		}
+
+		let max_steps = T::Migrations::nth_max_steps(cursor.index);
+		let next_cursor = T::Migrations::nth_transactional_step(
+			cursor.index,
+			cursor.inner_cursor.clone().map(|c| c.into_inner()),
+			meter,
+		);
+		let Some((max_steps, next_cursor)) = max_steps.zip(next_cursor) else {
+			defensive!("The migration of a valid index does not exist");
+			Self::upgrade_failed(Some(cursor.index));
+			return None
+		};
+
+		match next_cursor {
+			Ok(Some(next_cursor)) => {
+				let Ok(next_cursor) = next_cursor.try_into() else {
+					defensive!("The cursor of a migration exceeds CursorMaxLen");
+					Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+					Self::upgrade_failed(Some(cursor.index));
+					return None
+				};
+				Self::deposit_event(Event::MigrationAdvanced { index: cursor.index, took });
+				cursor.inner_cursor = Some(next_cursor);
+
+				// A migration takes one step per block, so `took` is the number of previous steps.
+				if max_steps.map_or(false, |max| took.saturating_add(One::one()) >= max.into()) {
+					Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+					Self::upgrade_failed(Some(cursor.index));
+					None
+				} else {
+					Some(ControlFlow::Break(cursor))
+				}
+			},
+			Ok(None) => {
+				#[cfg(feature = "try-runtime")]
+				{
+					let state = try_runtime::take_pre_upgrade_state(cursor.index);
+					if let Some(Err(e)) = T::Migrations::nth_post_upgrade(cursor.index, state) {
+						log::error!(
+							target: LOG_TARGET,
+							"Post-upgrade of migration {} failed: {}",
+							cursor.index,
+							e
+						);
+						Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+						Self::upgrade_failed(Some(cursor.index));
+						return None
+					}
+				}
+
+				Self::deposit_event(Event::MigrationCompleted { index: cursor.index, took });
+				Historic::<T>::insert(&id, ());
+				cursor.goto_next_migration(now);
+				Some(ControlFlow::Continue(cursor))
+			},
+			Err(SteppedMigrationError::InsufficientWeight { required }) => {
+				if is_first || required.any_gt(meter.limit) {
+					Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+					Self::upgrade_failed(Some(cursor.index));
+					None
+				} else {
+					// Retry in the next block, which starts with a full meter.
+					Some(ControlFlow::Break(cursor))
+				}
+			},
+			Err(SteppedMigrationError::InvalidCursor | SteppedMigrationError::Failed) => {
+				Self::deposit_event(Event::MigrationFailed { index: cursor.index, took });
+				Self::upgrade_failed(Some(cursor.index));
+				None
+			},
+		}
+	}
+
+	/// Let [`Config::FailedMigrationHandler`] decide how to continue after a failed migration.
+	fn upgrade_failed(migration: Option<u32>) {
+		Self::deposit_event(Event::UpgradeFailed);
+
+		match T::FailedMigrationHandler::failed(migration) {
+			FailedMigrationHandling::KeepStuck => Cursor::<T>::put(MigrationCursor::Stuck),
+			FailedMigrationHandling::ForceUnstuck => Cursor::<T>::kill(),
+		}
+	}
+}
+
+impl<T: Config> MultiStepMigrator for Pallet<T> {
+	fn ongoing() -> bool {
+		Cursor::<T>::exists()
+	}
+
+	fn step() -> Weight {
+		Self::progress_mbms(System::<T>::block_number())
+	}
+}
+
+#[cfg(feature = "try-runtime")]
+mod try_runtime {
+	use codec::Encode;
+	use frame_support::storage::unhashed;
+	use sp_std::vec::Vec;
+
+	/// Prefix of the keys storing the pre-upgrade state of migrations.
+	const PRE_UPGRADE_STATE_PREFIX: &[u8] = b":migrations:pre_upgrade:";
+
+	fn key(index: u32) -> Vec<u8> {
+		(PRE_UPGRADE_STATE_PREFIX, index).encode()
+	}
+
+	pub(crate) fn store_pre_upgrade_state(index: u32, state: Vec<u8>) {
+		unhashed::put_raw(&key(index), &state);
+	}
+
+	pub(crate) fn take_pre_upgrade_state(index: u32) -> Vec<u8> {
+		unhashed::take_raw(&key(index)).unwrap_or_default()
+	}
+}
@@ -0,0 +1,125 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Test helpers for internal and external usage.
+
+#![allow(missing_docs)]
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use frame_support::{
+	migrations::{SteppedMigrationError, SteppedMigrations},
+	storage::unhashed,
+	weights::Weight,
+};
+use scale_info::TypeInfo;
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+use sp_weights::WeightMeter;
+
+/// Storage key of the migrations executed by [`MockedMigrations`].
+const MOCKED_MIGRATIONS_KEY: &[u8] = b":mocked_migrations:";
+
+/// How a mocked migration behaves once it took its number of steps.
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum MockedMigrationKind {
+	/// Complete.
+	SucceedAfter,
+	/// Fail with [`SteppedMigrationError::Failed`].
+	FailAfter,
+	/// Never complete, with its number of steps as `max_steps`.
+	TimeoutAfter,
+	/// Fail with [`SteppedMigrationError::InsufficientWeight`].
+	HighWeightAfter(Weight),
+}
+
+/// Set the migrations executed by [`MockedMigrations`], as kinds and numbers of steps.
+pub fn set_mocked_migrations(migrations: Vec<(MockedMigrationKind, u32)>) {
+	unhashed::put(MOCKED_MIGRATIONS_KEY, &migrations);
+}
+
+fn mocked_migrations() -> Vec<(MockedMigrationKind, u32)> {
+	unhashed::get_or_default(MOCKED_MIGRATIONS_KEY)
+}
+
+/// The identifier of a mocked migration.
+pub fn mocked_id(kind: MockedMigrationKind, steps: u32) -> Vec<u8> {
+	(b"MockedMigration", kind, steps).encode()
+}
+
+/// Migrations configured at runtime through [`set_mocked_migrations`].
+///
+/// The cursor of a migration is the number of steps it took.
+pub struct MockedMigrations;
+
+impl SteppedMigrations for MockedMigrations {
+	fn len() -> u32 {
+		mocked_migrations().len() as u32
+	}
+
+	fn nth_id(n: u32) -> Option<Vec<u8>> {
+		let (kind, steps) = mocked_migrations().get(n as usize).copied()?;
+		Some(mocked_id(kind, steps))
+	}
+
+	fn nth_max_steps(n: u32) -> Option<Option<u32>> {
+		let (kind, steps) = mocked_migrations().get(n as usize).copied()?;
+		Some((kind == MockedMigrationKind::TimeoutAfter).then_some(steps))
+	}
+
+	fn nth_transactional_step(
+		n: u32,
+		cursor: Option<Vec<u8>>,
+		_meter: &mut WeightMeter,
+	) -> Option<Result<Option<Vec<u8>>, SteppedMigrationError>> {
+		let (kind, steps) = mocked_migrations().get(n as usize).copied()?;
+		let count = match cursor.map(|cursor| u32::decode(&mut &cursor[..])).transpose() {
+			Ok(count) => count.unwrap_or_default(),
+			Err(_) => return Some(Err(SteppedMigrationError::InvalidCursor)),
+		};
+		log::debug!(target: "runtime::migrations", "MockedMigration {:?} step {}", kind, count);
+
+		if count != steps || kind == MockedMigrationKind::TimeoutAfter {
+			return Some(Ok(Some((count + 1).encode())))
+		}
+
+		Some(match kind {
+			MockedMigrationKind::SucceedAfter => Ok(None),
+			MockedMigrationKind::FailAfter => Err(SteppedMigrationError::Failed),
+			MockedMigrationKind::HighWeightAfter(required) =>
+				Err(SteppedMigrationError::InsufficientWeight { required }),
+			MockedMigrationKind::TimeoutAfter => unreachable!("never completes; qed"),
+		})
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn nth_pre_upgrade(n: u32) -> Option<Result<Vec<u8>, &'static str>> {
+		(n < Self::len()).then(|| Ok(Vec::new()))
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn nth_post_upgrade(n: u32, _state: Vec<u8>) -> Option<Result<(), &'static str>> {
+		(n < Self::len()).then_some(Ok(()))
+	}
+
+	fn cursor_max_encoded_len() -> usize {
+		u32::max_encoded_len()
+	}
+
+	fn identifier_max_encoded_len() -> usize {
+		b"MockedMigration".len() + MockedMigrationKind::max_encoded_len() + u32::max_encoded_len()
+	}
+}
@@ -0,0 +1,327 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the migrations pallet.
+
+#![cfg(test)]
+
+use crate::{mock::*, mock_helpers::MockedMigrationKind::*, *};
+
+use frame_support::{assert_noop, assert_ok, migrations::FailedMigrationHandling};
+use frame_system::RawOrigin;
+use sp_runtime::DispatchError;
+
+#[test]
+fn basic_works() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 1), (SucceedAfter, 2)]);
+		assert!(Migrations::ongoing());
+
+		run_to_block(10);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 3 },
+				// Block 2
+				Event::MigrationCompleted { index: 0, took: 0 },
+				Event::MigrationAdvanced { index: 1, took: 0 },
+				// Block 3
+				Event::MigrationCompleted { index: 1, took: 1 },
+				Event::MigrationAdvanced { index: 2, took: 0 },
+				// Block 4
+				Event::MigrationAdvanced { index: 2, took: 1 },
+				// Block 5
+				Event::MigrationCompleted { index: 2, took: 2 },
+				Event::UpgradeCompleted,
+			]
+		);
+		assert!(!Migrations::ongoing());
+		assert_eq!(Historic::<Test>::iter().count(), 3);
+		assert_eq!((UpgradesStarted::get(), UpgradesCompleted::get()), (1, 1));
+	});
+}
+
+#[test]
+fn empty_upgrade_does_nothing() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![]);
+		run_to_block(10);
+
+		assert!(take_events().is_empty());
+		assert!(!Migrations::ongoing());
+		assert_eq!(UpgradesStarted::get(), 0);
+	});
+}
+
+#[test]
+fn historic_migrations_are_skipped() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 1)]);
+		run_to_block(10);
+		take_events();
+
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 2), (SucceedAfter, 1)]);
+		run_to_block(20);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 3 },
+				Event::MigrationSkipped { index: 0 },
+				Event::MigrationAdvanced { index: 1, took: 0 },
+				Event::MigrationAdvanced { index: 1, took: 1 },
+				Event::MigrationCompleted { index: 1, took: 2 },
+				Event::MigrationSkipped { index: 2 },
+				Event::UpgradeCompleted,
+			]
+		);
+	});
+}
+
+#[test]
+fn failed_migration_keeps_chain_stuck() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (FailAfter, 1), (SucceedAfter, 0)]);
+		run_to_block(10);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 3 },
+				Event::MigrationCompleted { index: 0, took: 0 },
+				Event::MigrationAdvanced { index: 1, took: 0 },
+				Event::MigrationFailed { index: 1, took: 1 },
+				Event::UpgradeFailed,
+			]
+		);
+		assert_eq!(Cursor::<Test>::get(), Some(MigrationCursor::Stuck));
+		assert!(Migrations::ongoing());
+		assert_eq!(UpgradesCompleted::get(), 0);
+	});
+}
+
+#[test]
+fn failed_migration_can_unstuck_chain() {
+	new_test_ext().execute_with(|| {
+		FailedUpgradeResponse::set(FailedMigrationHandling::ForceUnstuck);
+		upgrade_with(vec![(FailAfter, 0), (SucceedAfter, 0)]);
+		run_to_block(10);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 2 },
+				Event::MigrationFailed { index: 0, took: 0 },
+				Event::UpgradeFailed,
+			]
+		);
+		assert!(!Migrations::ongoing());
+		// The remaining migration was not executed.
+		assert_eq!(Historic::<Test>::iter().count(), 0);
+	});
+}
+
+#[test]
+fn stuck_chain_recovers_with_runtime_upgrade() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (FailAfter, 0)]);
+		run_to_block(10);
+		assert_eq!(Cursor::<Test>::get(), Some(MigrationCursor::Stuck));
+		take_events();
+
+		// The fixed migration replaces the failing one.
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 1)]);
+		run_to_block(20);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 2 },
+				Event::MigrationSkipped { index: 0 },
+				Event::MigrationAdvanced { index: 1, took: 0 },
+				Event::MigrationCompleted { index: 1, took: 1 },
+				Event::UpgradeCompleted,
+			]
+		);
+		assert!(!Migrations::ongoing());
+	});
+}
+
+#[test]
+fn runtime_upgrade_fails_ongoing_migrations() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 5)]);
+		run_to_block(3);
+		take_events();
+
+		upgrade_with(vec![(SucceedAfter, 0)]);
+
+		assert_eq!(take_events(), vec![Event::UpgradeFailed]);
+		assert_eq!(Cursor::<Test>::get(), Some(MigrationCursor::Stuck));
+	});
+}
+
+#[test]
+fn migration_times_out_after_max_steps() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(TimeoutAfter, 3)]);
+		run_to_block(10);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 1 },
+				Event::MigrationAdvanced { index: 0, took: 0 },
+				Event::MigrationAdvanced { index: 0, took: 1 },
+				Event::MigrationAdvanced { index: 0, took: 2 },
+				Event::MigrationFailed { index: 0, took: 2 },
+				Event::UpgradeFailed,
+			]
+		);
+	});
+}
+
+#[test]
+fn insufficient_weight_is_retried_in_next_block() {
+	new_test_ext().execute_with(|| {
+		let required = Weight::from_parts(1, 0);
+		upgrade_with(vec![(SucceedAfter, 0), (HighWeightAfter(required), 0)]);
+
+		run_to_block(2);
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 2 },
+				Event::MigrationCompleted { index: 0, took: 0 },
+			]
+		);
+		assert_eq!(Cursor::<Test>::get().unwrap().as_active().unwrap().index, 1);
+
+		// The mocked migration never has enough weight, not even with a full block.
+		run_to_block(3);
+		assert_eq!(
+			take_events(),
+			vec![Event::MigrationFailed { index: 1, took: 1 }, Event::UpgradeFailed]
+		);
+	});
+}
+
+#[test]
+fn insufficient_weight_for_a_full_block_fails() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (HighWeightAfter(Weight::MAX), 0)]);
+		run_to_block(2);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 2 },
+				Event::MigrationCompleted { index: 0, took: 0 },
+				Event::MigrationFailed { index: 1, took: 0 },
+				Event::UpgradeFailed,
+			]
+		);
+	});
+}
+
+#[test]
+fn force_set_cursor_works() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 5)]);
+		assert_noop!(
+			Migrations::force_set_cursor(RuntimeOrigin::signed(1), None),
+			DispatchError::BadOrigin
+		);
+
+		assert_ok!(Migrations::force_set_cursor(RawOrigin::Root.into(), None));
+		assert!(!Migrations::ongoing());
+
+		assert_ok!(Migrations::force_set_cursor(
+			RawOrigin::Root.into(),
+			Some(MigrationCursor::Stuck)
+		));
+		assert!(Migrations::ongoing());
+	});
+}
+
+#[test]
+fn force_set_active_cursor_works() {
+	new_test_ext().execute_with(|| {
+		set_mocked_migrations(vec![(SucceedAfter, 0), (SucceedAfter, 1)]);
+		assert_ok!(Migrations::force_set_active_cursor(RawOrigin::Root.into(), 1, None, None));
+		run_to_block(10);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::MigrationAdvanced { index: 1, took: 1 },
+				Event::MigrationCompleted { index: 1, took: 2 },
+				Event::UpgradeCompleted,
+			]
+		);
+	});
+}
+
+#[test]
+fn force_onboard_mbms_works() {
+	new_test_ext().execute_with(|| {
+		set_mocked_migrations(vec![(SucceedAfter, 0)]);
+		assert_ok!(Migrations::force_onboard_mbms(RawOrigin::Root.into()));
+		assert_noop!(
+			Migrations::force_onboard_mbms(RawOrigin::Root.into()),
+			Error::<Test>::Ongoing
+		);
+		run_to_block(2);
+
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 1 },
+				Event::MigrationCompleted { index: 0, took: 0 },
+				Event::UpgradeCompleted,
+			]
+		);
+	});
+}
+
+#[test]
+fn clear_historic_works() {
+	new_test_ext().execute_with(|| {
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 1)]);
+		run_to_block(10);
+		take_events();
+
+		let id: IdentifierOf<Test> = mocked_id(SucceedAfter, 0).try_into().unwrap();
+		let unknown: IdentifierOf<Test> = mocked_id(FailAfter, 0).try_into().unwrap();
+		assert_ok!(Migrations::clear_historic(RawOrigin::Root.into(), vec![id, unknown]));
+		assert_eq!(take_events(), vec![Event::HistoricCleared { count: 1 }]);
+
+		// Only the cleared migration is executed again.
+		upgrade_with(vec![(SucceedAfter, 0), (SucceedAfter, 1)]);
+		run_to_block(20);
+		assert_eq!(
+			take_events(),
+			vec![
+				Event::UpgradeStarted { migrations: 2 },
+				Event::MigrationCompleted { index: 0, took: 0 },
+				Event::MigrationSkipped { index: 1 },
+				Event::UpgradeCompleted,
+			]
+		);
+	});
+}
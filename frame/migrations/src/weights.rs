@@ -0,0 +1,139 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Placeholder weights for pallet_migrations.
+//!
+//! These weights were NOT generated by benchmarking. The reads and writes match the storage
+//! accessed by each call, the execution times and proof sizes are rough estimates.
+//!
+//! TODO: add the pallet to the kitchensink runtime and generate these weights with
+//! `benchmark pallet --pallet=pallet_migrations` and the `frame-weight-template.hbs` template.
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::{traits::Get, weights::{Weight, constants::RocksDbWeight}};
+use sp_std::marker::PhantomData;
+
+/// Weight functions needed for pallet_migrations.
+pub trait WeightInfo {
+	fn onboard_new_mbms() -> Weight;
+	fn progress_mbms_none() -> Weight;
+	fn exec_migration() -> Weight;
+	fn force_set_cursor() -> Weight;
+	fn force_set_active_cursor() -> Weight;
+	fn force_onboard_mbms() -> Weight;
+	fn clear_historic(n: u32, ) -> Weight;
+}
+
+/// Placeholder weights for pallet_migrations, see the module documentation.
+pub struct SubstrateWeight<T>(PhantomData<T>);
+impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
+	/// Storage: Migrations Cursor (r:1 w:1)
+	fn onboard_new_mbms() -> Weight {
+		Weight::from_parts(3_000_000, 67035)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Cursor (r:1 w:0)
+	fn progress_mbms_none() -> Weight {
+		Weight::from_parts(2_000_000, 67035)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+	}
+	/// Storage: Migrations Historic (r:1 w:1)
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn exec_migration() -> Weight {
+		Weight::from_parts(9_000_000, 3731)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(2_u64))
+	}
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn force_set_cursor() -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: System Number (r:1 w:0)
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn force_set_active_cursor() -> Weight {
+		Weight::from_parts(5_000_000, 1489)
+			.saturating_add(T::DbWeight::get().reads(1_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Cursor (r:1 w:1)
+	/// Storage: System Number (r:1 w:0)
+	fn force_onboard_mbms() -> Weight {
+		Weight::from_parts(6_000_000, 67035)
+			.saturating_add(T::DbWeight::get().reads(2_u64))
+			.saturating_add(T::DbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Historic (r:0 w:1000)
+	/// The range of component `n` is `[0, 1000]`.
+	fn clear_historic(n: u32, ) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(1_400_000, 0).saturating_mul(n.into()))
+			.saturating_add(T::DbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
+
+// For backwards compatibility and tests
+impl WeightInfo for () {
+	/// Storage: Migrations Cursor (r:1 w:1)
+	fn onboard_new_mbms() -> Weight {
+		Weight::from_parts(3_000_000, 67035)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Cursor (r:1 w:0)
+	fn progress_mbms_none() -> Weight {
+		Weight::from_parts(2_000_000, 67035)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+	}
+	/// Storage: Migrations Historic (r:1 w:1)
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn exec_migration() -> Weight {
+		Weight::from_parts(9_000_000, 3731)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(2_u64))
+	}
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn force_set_cursor() -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: System Number (r:1 w:0)
+	/// Storage: Migrations Cursor (r:0 w:1)
+	fn force_set_active_cursor() -> Weight {
+		Weight::from_parts(5_000_000, 1489)
+			.saturating_add(RocksDbWeight::get().reads(1_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Cursor (r:1 w:1)
+	/// Storage: System Number (r:1 w:0)
+	fn force_onboard_mbms() -> Weight {
+		Weight::from_parts(6_000_000, 67035)
+			.saturating_add(RocksDbWeight::get().reads(2_u64))
+			.saturating_add(RocksDbWeight::get().writes(1_u64))
+	}
+	/// Storage: Migrations Historic (r:0 w:1000)
+	/// The range of component `n` is `[0, 1000]`.
+	fn clear_historic(n: u32, ) -> Weight {
+		Weight::from_parts(4_000_000, 0)
+			.saturating_add(Weight::from_parts(1_400_000, 0).saturating_mul(n.into()))
+			.saturating_add(RocksDbWeight::get().writes((1_u64).saturating_mul(n.into())))
+	}
+}
@@ -1659,7 +1659,6 @@ pub mod pallet {
 		pub global_max_commission: Option<Perbill>,
 	}
 
-	#[cfg(feature = "std")]
 	impl<T: Config> Default for GenesisConfig<T> {
 		fn default() -> Self {
 			Self {
@@ -19,7 +19,6 @@ use crate::pallet::Def;
 
 ///
 /// * implement the trait `sp_runtime::BuildModuleGenesisStorage`
-/// * add #[cfg(feature = "std")] to GenesisBuild implementation.
 pub fn expand_genesis_build(def: &mut Def) -> proc_macro2::TokenStream {
 	let genesis_config = if let Some(genesis_config) = &def.genesis_config {
 		genesis_config
@@ -41,16 +40,6 @@ pub fn expand_genesis_build(def: &mut Def) -> proc_macro2::TokenStream {
 
 	let gen_cfg_use_gen = genesis_config.gen_kind.type_use_gen(genesis_build.attr_span);
 
-	let genesis_build_item =
-		&mut def.item.content.as_mut().expect("Checked by def parser").1[genesis_build.index];
-
-	let genesis_build_item_impl = if let syn::Item::Impl(impl_) = genesis_build_item {
-		impl_
-	} else {
-		unreachable!("Checked by genesis_build parser")
-	};
-
-	genesis_build_item_impl.attrs.push(syn::parse_quote!( #[cfg(feature = "std")] ));
 	let where_clause = &genesis_build.where_clause;
 
 	quote::quote_spanned!(genesis_build.attr_span =>
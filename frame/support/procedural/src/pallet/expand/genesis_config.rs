@@ -81,6 +81,24 @@ pub fn expand_genesis_config(def: &mut Def) -> proc_macro2::TokenStream {
 
 	let serde_crate = format!("{}::serde", frame_support);
 
+	// Without `std` the associated types of the pallet config are not bound to serde, so the
+	// bounds are put on the field types and checked where the `GenesisConfig` is used.
+	let field_types = match &*genesis_config_item {
+		syn::Item::Struct(item) => item.fields.iter().map(|f| &f.ty).collect::<Vec<_>>(),
+		syn::Item::Enum(item) =>
+			item.variants.iter().flat_map(|v| v.fields.iter().map(|f| &f.ty)).collect(),
+		_ => Vec::new(),
+	};
+	let serde_bound = |bound: &str| {
+		field_types
+			.iter()
+			.map(|ty| format!("{}: {}::{}", quote::quote!(#ty), serde_crate, bound))
+			.collect::<Vec<_>>()
+			.join(", ")
+	};
+	let serialize_bound = serde_bound("Serialize");
+	let deserialize_bound = serde_bound("Deserialize<'de>");
+
 	match genesis_config_item {
 		syn::Item::Enum(syn::ItemEnum { attrs, .. }) |
 		syn::Item::Struct(syn::ItemStruct { attrs, .. }) |
@@ -94,14 +112,23 @@ pub fn expand_genesis_config(def: &mut Def) -> proc_macro2::TokenStream {
 					"]
 				));
 			}
-			attrs.push(syn::parse_quote!( #[cfg(feature = "std")] ));
 			attrs.push(syn::parse_quote!(
 				#[derive(#frame_support::Serialize, #frame_support::Deserialize)]
 			));
 			attrs.push(syn::parse_quote!( #[serde(rename_all = "camelCase")] ));
 			attrs.push(syn::parse_quote!( #[serde(deny_unknown_fields)] ));
-			attrs.push(syn::parse_quote!( #[serde(bound(serialize = ""))] ));
-			attrs.push(syn::parse_quote!( #[serde(bound(deserialize = ""))] ));
+			attrs.push(syn::parse_quote!(
+				#[cfg_attr(feature = "std", serde(bound(serialize = "")))]
+			));
+			attrs.push(syn::parse_quote!(
+				#[cfg_attr(feature = "std", serde(bound(deserialize = "")))]
+			));
+			attrs.push(syn::parse_quote!(
+				#[cfg_attr(not(feature = "std"), serde(bound(serialize = #serialize_bound)))]
+			));
+			attrs.push(syn::parse_quote!(
+				#[cfg_attr(not(feature = "std"), serde(bound(deserialize = #deserialize_bound)))]
+			));
 			attrs.push(syn::parse_quote!( #[serde(crate = #serde_crate)] ));
 		},
 		_ => unreachable!("Checked by genesis_config parser"),
@@ -126,7 +153,7 @@ pub fn expand_genesis_config(def: &mut Def) -> proc_macro2::TokenStream {
 						stringify!($pallet_name),
 						"` does not have the std feature enabled, this will cause the `",
 						$pallet_path,
-						"::GenesisConfig` type to be undefined."
+						"::GenesisConfig` type to not implement `BuildModuleGenesisStorage`."
 					));
 				};
 			}
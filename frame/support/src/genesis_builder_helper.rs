@@ -0,0 +1,84 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for implementing [`sp_genesis_builder::GenesisBuilder`] for a runtime.
+//!
+//! `construct_runtime!` implements [`BuildRuntimeGenesis`] for the runtime, which the runtime
+//! forwards to in its `impl_runtime_apis!` block:
+//!
+//! ```ignore
+//! impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+//! 	fn create_default_config() -> Vec<u8> {
+//! 		<Runtime as BuildRuntimeGenesis>::create_default_config()
+//! 	}
+//!
+//! 	fn build_config(json: Vec<u8>) -> sp_genesis_builder::Result {
+//! 		<Runtime as BuildRuntimeGenesis>::build_config(json)
+//! 	}
+//! }
+//! ```
+//!
+//! Both functions work the same in the native and the wasm runtime, so the node can build the
+//! genesis state from the runtime code alone.
+
+use sp_runtime::RuntimeString;
+use sp_std::vec::Vec;
+
+/// Build the genesis state of a runtime from the JSON representation of its `GenesisConfig`.
+///
+/// Implemented for the runtime by `construct_runtime!`.
+pub trait BuildRuntimeGenesis {
+	/// The default `GenesisConfig` of the runtime as a JSON blob.
+	fn create_default_config() -> Vec<u8>;
+
+	/// Build the `GenesisConfig` from the given JSON blob and put it into the storage.
+	fn build_config(json: Vec<u8>) -> sp_genesis_builder::Result;
+}
+
+/// Get the default `GenesisConfig` as a JSON blob.
+pub fn create_default_config<GC: serde::Serialize + Default>() -> Vec<u8> {
+	serde_json::to_vec(&GC::default()).expect("serialization to json is expected to work; qed")
+}
+
+/// Deserialize the `GenesisConfig` from a JSON blob, not using any defaults.
+pub fn deserialize_config<GC: serde::de::DeserializeOwned>(
+	json: &[u8],
+) -> Result<GC, RuntimeString> {
+	serde_json::from_slice(json)
+		.map_err(|e| sp_runtime::format_runtime_string!("Invalid JSON blob: {}", e))
+}
+
+/// Build the `GenesisConfig` from a JSON blob, not using any defaults, and put it into the
+/// storage.
+#[cfg(feature = "std")]
+pub fn build_config<GC: sp_runtime::BuildStorage + serde::de::DeserializeOwned>(
+	json: Vec<u8>,
+) -> sp_genesis_builder::Result {
+	let config: GC = deserialize_config(&json)?;
+	let storage = config.build_storage().map_err(RuntimeString::Owned)?;
+
+	for (key, value) in storage.top {
+		sp_io::storage::set(&key, &value);
+	}
+	for child in storage.children_default.into_values() {
+		let storage_key = child.child_info.storage_key();
+		for (key, value) in child.data {
+			sp_io::default_child_storage::set(storage_key, &key, &value);
+		}
+	}
+	Ok(())
+}
@@ -48,13 +48,14 @@ pub use once_cell;
 pub use paste;
 #[doc(hidden)]
 pub use scale_info;
-#[cfg(feature = "std")]
 pub use serde;
 pub use sp_api::metadata_ir;
 pub use sp_core::{OpaqueMetadata, Void};
 #[doc(hidden)]
 pub use sp_core_hashing_proc_macro;
 #[doc(hidden)]
+pub use sp_genesis_builder;
+#[doc(hidden)]
 pub use sp_io::{self, storage::root as storage_root};
 #[cfg(feature = "std")]
 #[doc(hidden)]
@@ -80,6 +81,7 @@ pub mod inherent;
 pub mod error;
 pub mod crypto;
 pub mod dispatch_context;
+pub mod genesis_builder_helper;
 pub mod instances;
 pub mod migrations;
 pub mod traits;
@@ -818,7 +820,6 @@ macro_rules! assert_error_encoded_size {
 	} => {};
 }
 
-#[cfg(feature = "std")]
 #[doc(hidden)]
 pub use serde::{Deserialize, Serialize};
 
@@ -1522,7 +1523,6 @@ pub mod tests {
 
 /// Prelude to be used alongside pallet macro, for ease of use.
 pub mod pallet_prelude {
-	#[cfg(feature = "std")]
 	pub use crate::traits::GenesisBuild;
 	pub use crate::{
 		dispatch::{
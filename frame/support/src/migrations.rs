@@ -18,15 +18,18 @@
 #[cfg(feature = "try-runtime")]
 use crate::storage::unhashed::contains_prefixed_key;
 use crate::{
+	storage::transactional::with_transaction,
 	traits::{GetStorageVersion, PalletInfoAccess},
 	weights::{RuntimeDbWeight, Weight},
 };
+use codec::{Decode, Encode, FullCodec, MaxEncodedLen};
 use impl_trait_for_tuples::impl_for_tuples;
+use scale_info::TypeInfo;
 use sp_core::Get;
 use sp_io::{hashing::twox_128, storage::clear_prefix, KillStorageResult};
-use sp_std::marker::PhantomData;
-#[cfg(feature = "try-runtime")]
-use sp_std::vec::Vec;
+use sp_runtime::{traits::Saturating, DispatchError, RuntimeDebug, TransactionOutcome};
+use sp_std::{marker::PhantomData, vec::Vec};
+use sp_weights::WeightMeter;
 
 /// Trait used by [`migrate_from_pallet_version_to_storage_version`] to do the actual migration.
 pub trait PalletVersionToStorageVersionHelper {
@@ -183,3 +186,486 @@ impl<P: Get<&'static str>, DbWeight: Get<RuntimeDbWeight>> frame_support::traits
 		Ok(())
 	}
 }
+
+/// A migration that can run across multiple blocks.
+///
+/// Every call to [`Self::step`] migrates some part of the storage and returns a cursor to resume
+/// from in a later block, or `None` once the migration is done. The cursor is persisted in storage
+/// between blocks, which is what allows a migration to outlive a single block.
+///
+/// Migrations are executed by a [`MultiStepMigrator`], usually `pallet-migrations`.
+pub trait SteppedMigration {
+	/// The cursor type that stores the progress of the migration.
+	type Cursor: FullCodec + MaxEncodedLen;
+
+	/// The unique identifier type of the migration.
+	type Identifier: FullCodec + MaxEncodedLen;
+
+	/// The unique identifier of this migration.
+	///
+	/// A migration that completed once is never executed again, even if it is still part of a
+	/// later runtime upgrade.
+	fn id() -> Self::Identifier;
+
+	/// The maximum number of steps this migration can take.
+	///
+	/// The migration is considered failed if it did not complete within this number of steps.
+	/// `None` means that it can take as many steps as it needs.
+	fn max_steps() -> Option<u32> {
+		None
+	}
+
+	/// Try to migrate as much as possible within the weight limit of `meter`.
+	///
+	/// Returns the cursor to resume from in the next block, or `None` if the migration is done.
+	/// A step should make progress if the meter allows it; returning
+	/// [`SteppedMigrationError::InsufficientWeight`] postpones the step to the next block.
+	fn step(
+		cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError>;
+
+	/// Same as [`Self::step`], but rolls back all storage changes if the step fails.
+	fn transactional_step(
+		cursor: Option<Self::Cursor>,
+		meter: &mut WeightMeter,
+	) -> Result<Option<Self::Cursor>, SteppedMigrationError> {
+		with_transaction(move || match Self::step(cursor, meter) {
+			Ok(cursor) => TransactionOutcome::Commit(Ok(Ok(cursor))),
+			Err(err) => TransactionOutcome::Rollback(Ok(Err(err))),
+		})
+		// The transactional layer limit was reached.
+		.unwrap_or_else(|_: DispatchError| Err(SteppedMigrationError::Failed))
+	}
+
+	/// Hook executed before the first step of the migration.
+	///
+	/// The returned state is handed to [`Self::post_upgrade`] once the migration completed.
+	#[cfg(feature = "try-runtime")]
+	fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+		Ok(Vec::new())
+	}
+
+	/// Hook executed after the last step of the migration.
+	#[cfg(feature = "try-runtime")]
+	fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+/// Error returned by a [`SteppedMigration`] step.
+#[derive(Debug, Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq)]
+pub enum SteppedMigrationError {
+	/// The weight limit does not allow for the step to make progress.
+	///
+	/// The step is retried in the next block, unless `required` exceeds the weight available to
+	/// migrations in any block, in which case the migration fails.
+	InsufficientWeight {
+		/// The weight required for the step to make progress.
+		required: Weight,
+	},
+	/// The cursor could not be decoded or is not valid for this migration.
+	InvalidCursor,
+	/// The migration failed and must not be resumed.
+	Failed,
+}
+
+/// Executes the migrations of a runtime across multiple blocks.
+///
+/// Used by `frame-executive` to advance migrations at the start of every block and to reject
+/// transactions while migrations are ongoing.
+pub trait MultiStepMigrator {
+	/// Whether a migration is ongoing.
+	///
+	/// Only inherents are applied while this returns `true`.
+	fn ongoing() -> bool;
+
+	/// Advance the ongoing migrations, returning the consumed weight.
+	fn step() -> Weight;
+}
+
+impl MultiStepMigrator for () {
+	fn ongoing() -> bool {
+		false
+	}
+
+	fn step() -> Weight {
+		Weight::zero()
+	}
+}
+
+/// A list of [`SteppedMigration`]s with type erased cursors and identifiers.
+///
+/// Implemented for every [`SteppedMigration`] and for tuples of [`SteppedMigrations`]. The
+/// migrations are indexed in the order in which they appear in the tuple.
+pub trait SteppedMigrations {
+	/// The number of migrations.
+	fn len() -> u32;
+
+	/// The encoded identifier of the `n`th migration, `None` if there is no such migration.
+	fn nth_id(n: u32) -> Option<Vec<u8>>;
+
+	/// The [`SteppedMigration::max_steps`] of the `n`th migration.
+	fn nth_max_steps(n: u32) -> Option<Option<u32>>;
+
+	/// Call [`SteppedMigration::transactional_step`] of the `n`th migration with an encoded
+	/// cursor.
+	///
+	/// Returns `None` if there is no such migration.
+	fn nth_transactional_step(
+		n: u32,
+		cursor: Option<Vec<u8>>,
+		meter: &mut WeightMeter,
+	) -> Option<Result<Option<Vec<u8>>, SteppedMigrationError>>;
+
+	/// Call [`SteppedMigration::pre_upgrade`] of the `n`th migration.
+	#[cfg(feature = "try-runtime")]
+	fn nth_pre_upgrade(n: u32) -> Option<Result<Vec<u8>, &'static str>>;
+
+	/// Call [`SteppedMigration::post_upgrade`] of the `n`th migration.
+	#[cfg(feature = "try-runtime")]
+	fn nth_post_upgrade(n: u32, state: Vec<u8>) -> Option<Result<(), &'static str>>;
+
+	/// The maximal encoded length of the cursors of all migrations.
+	fn cursor_max_encoded_len() -> usize;
+
+	/// The maximal encoded length of the identifiers of all migrations.
+	fn identifier_max_encoded_len() -> usize;
+}
+
+impl<T: SteppedMigration> SteppedMigrations for T {
+	fn len() -> u32 {
+		1
+	}
+
+	fn nth_id(n: u32) -> Option<Vec<u8>> {
+		(n == 0).then(|| T::id().encode())
+	}
+
+	fn nth_max_steps(n: u32) -> Option<Option<u32>> {
+		(n == 0).then(T::max_steps)
+	}
+
+	fn nth_transactional_step(
+		n: u32,
+		cursor: Option<Vec<u8>>,
+		meter: &mut WeightMeter,
+	) -> Option<Result<Option<Vec<u8>>, SteppedMigrationError>> {
+		if n != 0 {
+			return None
+		}
+
+		let cursor = match cursor.map(|cursor| T::Cursor::decode(&mut &cursor[..])).transpose() {
+			Ok(cursor) => cursor,
+			Err(_) => return Some(Err(SteppedMigrationError::InvalidCursor)),
+		};
+
+		Some(T::transactional_step(cursor, meter).map(|cursor| cursor.map(|c| c.encode())))
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn nth_pre_upgrade(n: u32) -> Option<Result<Vec<u8>, &'static str>> {
+		(n == 0).then(T::pre_upgrade)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	fn nth_post_upgrade(n: u32, state: Vec<u8>) -> Option<Result<(), &'static str>> {
+		(n == 0).then(|| T::post_upgrade(state))
+	}
+
+	fn cursor_max_encoded_len() -> usize {
+		T::Cursor::max_encoded_len()
+	}
+
+	fn identifier_max_encoded_len() -> usize {
+		T::Identifier::max_encoded_len()
+	}
+}
+
+#[cfg_attr(all(not(feature = "tuples-96"), not(feature = "tuples-128")), impl_for_tuples(64))]
+#[cfg_attr(all(feature = "tuples-96", not(feature = "tuples-128")), impl_for_tuples(96))]
+#[cfg_attr(feature = "tuples-128", impl_for_tuples(128))]
+impl SteppedMigrations for Tuple {
+	fn len() -> u32 {
+		let mut len = 0u32;
+		for_tuples!( #( len = len.saturating_add(Tuple::len()); )* );
+		len
+	}
+
+	#[allow(unused_assignments)]
+	fn nth_id(n: u32) -> Option<Vec<u8>> {
+		let mut offset = 0u32;
+		for_tuples!( #(
+			if n < offset.saturating_add(Tuple::len()) {
+				return Tuple::nth_id(n - offset)
+			}
+			offset = offset.saturating_add(Tuple::len());
+		)* );
+		None
+	}
+
+	#[allow(unused_assignments)]
+	fn nth_max_steps(n: u32) -> Option<Option<u32>> {
+		let mut offset = 0u32;
+		for_tuples!( #(
+			if n < offset.saturating_add(Tuple::len()) {
+				return Tuple::nth_max_steps(n - offset)
+			}
+			offset = offset.saturating_add(Tuple::len());
+		)* );
+		None
+	}
+
+	#[allow(unused_assignments)]
+	fn nth_transactional_step(
+		n: u32,
+		cursor: Option<Vec<u8>>,
+		meter: &mut WeightMeter,
+	) -> Option<Result<Option<Vec<u8>>, SteppedMigrationError>> {
+		let mut offset = 0u32;
+		for_tuples!( #(
+			if n < offset.saturating_add(Tuple::len()) {
+				return Tuple::nth_transactional_step(n - offset, cursor, meter)
+			}
+			offset = offset.saturating_add(Tuple::len());
+		)* );
+		None
+	}
+
+	#[cfg(feature = "try-runtime")]
+	#[allow(unused_assignments)]
+	fn nth_pre_upgrade(n: u32) -> Option<Result<Vec<u8>, &'static str>> {
+		let mut offset = 0u32;
+		for_tuples!( #(
+			if n < offset.saturating_add(Tuple::len()) {
+				return Tuple::nth_pre_upgrade(n - offset)
+			}
+			offset = offset.saturating_add(Tuple::len());
+		)* );
+		None
+	}
+
+	#[cfg(feature = "try-runtime")]
+	#[allow(unused_assignments)]
+	fn nth_post_upgrade(n: u32, state: Vec<u8>) -> Option<Result<(), &'static str>> {
+		let mut offset = 0u32;
+		for_tuples!( #(
+			if n < offset.saturating_add(Tuple::len()) {
+				return Tuple::nth_post_upgrade(n - offset, state)
+			}
+			offset = offset.saturating_add(Tuple::len());
+		)* );
+		None
+	}
+
+	fn cursor_max_encoded_len() -> usize {
+		let mut max_len = 0;
+		for_tuples!( #( max_len = max_len.max(Tuple::cursor_max_encoded_len()); )* );
+		max_len
+	}
+
+	fn identifier_max_encoded_len() -> usize {
+		let mut max_len = 0;
+		for_tuples!( #( max_len = max_len.max(Tuple::identifier_max_encoded_len()); )* );
+		max_len
+	}
+}
+
+/// What to do after a multi-block migration failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedMigrationHandling {
+	/// Keep the chain stuck: only inherents are applied until governance intervenes.
+	KeepStuck,
+	/// Forget about the ongoing migrations and resume normal operation.
+	///
+	/// The storage may be left partially migrated.
+	ForceUnstuck,
+}
+
+/// Decides what happens after a multi-block migration failed.
+pub trait FailedMigrationHandler {
+	/// Called when the migration at index `migration` failed.
+	///
+	/// `None` means that the failure is not attributable to a single migration, e.g. because a
+	/// runtime upgrade happened while migrations were ongoing.
+	fn failed(migration: Option<u32>) -> FailedMigrationHandling;
+}
+
+/// Keeps the chain stuck after a failed migration.
+///
+/// This is the safe default: no transaction can observe the partially migrated storage.
+pub struct FreezeChainOnFailedMigration;
+
+impl FailedMigrationHandler for FreezeChainOnFailedMigration {
+	fn failed(_migration: Option<u32>) -> FailedMigrationHandling {
+		FailedMigrationHandling::KeepStuck
+	}
+}
+
+/// Resumes normal operation after a failed migration.
+pub struct UnfreezeChainOnFailedMigration;
+
+impl FailedMigrationHandler for UnfreezeChainOnFailedMigration {
+	fn failed(_migration: Option<u32>) -> FailedMigrationHandling {
+		FailedMigrationHandling::ForceUnstuck
+	}
+}
+
+/// Notified when multi-block migrations start and complete.
+///
+/// Can be used to pause other pallets while storage is being migrated.
+pub trait MigrationStatusHandler {
+	/// Called when the migrations of a runtime upgrade started.
+	fn started() {}
+
+	/// Called when all migrations of a runtime upgrade completed.
+	fn completed() {}
+}
+
+impl MigrationStatusHandler for () {}
+
+/// The progress of the ongoing multi-block migrations.
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum MigrationCursor<Cursor, BlockNumber> {
+	/// A migration is in progress.
+	Active(ActiveCursor<Cursor, BlockNumber>),
+	/// A migration failed and the chain is stuck.
+	Stuck,
+}
+
+impl<Cursor, BlockNumber> MigrationCursor<Cursor, BlockNumber> {
+	/// The active cursor, `None` if the chain is stuck.
+	pub fn as_active(&self) -> Option<&ActiveCursor<Cursor, BlockNumber>> {
+		match self {
+			Self::Active(cursor) => Some(cursor),
+			Self::Stuck => None,
+		}
+	}
+}
+
+impl<Cursor, BlockNumber> From<ActiveCursor<Cursor, BlockNumber>>
+	for MigrationCursor<Cursor, BlockNumber>
+{
+	fn from(cursor: ActiveCursor<Cursor, BlockNumber>) -> Self {
+		Self::Active(cursor)
+	}
+}
+
+/// The progress of the migration that is currently executed.
+#[derive(Encode, Decode, MaxEncodedLen, TypeInfo, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct ActiveCursor<Cursor, BlockNumber> {
+	/// The index of the migration in the [`SteppedMigrations`] list.
+	pub index: u32,
+	/// The cursor returned by the last step of the migration, `None` if it did not start yet.
+	pub inner_cursor: Option<Cursor>,
+	/// The block in which the migration started.
+	pub started_at: BlockNumber,
+}
+
+impl<Cursor, BlockNumber> ActiveCursor<Cursor, BlockNumber> {
+	/// Advance to the next migration, starting at `now`.
+	pub fn goto_next_migration(&mut self, now: BlockNumber) {
+		self.index.saturating_inc();
+		self.inner_cursor = None;
+		self.started_at = now;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::storage::unhashed;
+
+	const KEY: &[u8] = b":stepped:key:";
+
+	/// Counts from the cursor up to `N`, one per step, failing on `FAIL_AT`.
+	struct Counter<const ID: u8, const N: u32, const FAIL_AT: u32>;
+
+	impl<const ID: u8, const N: u32, const FAIL_AT: u32> SteppedMigration for Counter<ID, N, FAIL_AT> {
+		type Cursor = u32;
+		type Identifier = u8;
+
+		fn id() -> u8 {
+			ID
+		}
+
+		fn step(
+			cursor: Option<u32>,
+			meter: &mut WeightMeter,
+		) -> Result<Option<u32>, SteppedMigrationError> {
+			let required = Weight::from_parts(1, 0);
+			if !meter.check_accrue(required) {
+				return Err(SteppedMigrationError::InsufficientWeight { required })
+			}
+
+			let next = cursor.unwrap_or_default() + 1;
+			unhashed::put(KEY, &next);
+			if next == FAIL_AT {
+				return Err(SteppedMigrationError::Failed)
+			}
+			Ok((next < N).then_some(next))
+		}
+	}
+
+	type Migrations = (Counter<0, 2, 0>, (Counter<1, 1, 0>, Counter<2, 3, 2>));
+
+	#[test]
+	fn tuples_index_nested_migrations() {
+		assert_eq!(Migrations::len(), 3);
+		assert_eq!(Migrations::nth_id(0), Some(0u8.encode()));
+		assert_eq!(Migrations::nth_id(2), Some(2u8.encode()));
+		assert_eq!(Migrations::nth_id(3), None);
+		assert_eq!(Migrations::nth_max_steps(1), Some(None));
+		assert_eq!(Migrations::cursor_max_encoded_len(), 4);
+		assert_eq!(Migrations::identifier_max_encoded_len(), 1);
+	}
+
+	#[test]
+	fn nth_transactional_step_works() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let mut meter = WeightMeter::max_limit();
+
+			let cursor = Migrations::nth_transactional_step(0, None, &mut meter);
+			assert_eq!(cursor, Some(Ok(Some(1u32.encode()))));
+			let cursor = Migrations::nth_transactional_step(0, Some(1u32.encode()), &mut meter);
+			assert_eq!(cursor, Some(Ok(None)));
+
+			assert_eq!(
+				Migrations::nth_transactional_step(1, Some(vec![1]), &mut meter),
+				Some(Err(SteppedMigrationError::InvalidCursor))
+			);
+			assert_eq!(Migrations::nth_transactional_step(3, None, &mut meter), None);
+		});
+	}
+
+	#[test]
+	fn failed_step_is_rolled_back() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let mut meter = WeightMeter::max_limit();
+
+			assert_eq!(
+				Migrations::nth_transactional_step(2, None, &mut meter),
+				Some(Ok(Some(1u32.encode())))
+			);
+			assert_eq!(
+				Migrations::nth_transactional_step(2, Some(1u32.encode()), &mut meter),
+				Some(Err(SteppedMigrationError::Failed))
+			);
+			assert_eq!(unhashed::get::<u32>(KEY), Some(1));
+		});
+	}
+
+	#[test]
+	fn step_reports_insufficient_weight() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			let mut meter = WeightMeter::from_limit(Weight::zero());
+
+			assert_eq!(
+				Migrations::nth_transactional_step(0, None, &mut meter),
+				Some(Err(SteppedMigrationError::InsufficientWeight {
+					required: Weight::from_parts(1, 0)
+				}))
+			);
+		});
+	}
+}
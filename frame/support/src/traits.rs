@@ -80,7 +80,6 @@ pub use metadata::{
 };
 
 mod hooks;
-#[cfg(feature = "std")]
 pub use hooks::GenesisBuild;
 pub use hooks::{
 	Hooks, IntegrityTest, OnFinalize, OnGenesis, OnIdle, OnInitialize, OnRuntimeUpgrade,
@@ -1237,6 +1237,26 @@ fn pallet_on_genesis() {
 	})
 }
 
+#[test]
+fn genesis_builder_works() {
+	use frame_support::genesis_builder_helper::BuildRuntimeGenesis;
+	use sp_runtime::BuildStorage;
+
+	let expected = GenesisConfig::default().build_storage().unwrap();
+	let json = <Runtime as BuildRuntimeGenesis>::create_default_config();
+
+	TestExternalities::default().execute_with(|| {
+		assert_ok!(<Runtime as BuildRuntimeGenesis>::build_config(json));
+		for (key, value) in &expected.top {
+			assert_eq!(unhashed::get_raw(key).as_ref(), Some(value));
+		}
+
+		assert!(
+			<Runtime as BuildRuntimeGenesis>::build_config(b"{\"unknown\":0}".to_vec()).is_err()
+		);
+	})
+}
+
 #[test]
 fn migrate_from_pallet_version_to_storage_version() {
 	const PALLET_VERSION_STORAGE_KEY_POSTFIX: &[u8] = b":__PALLET_VERSION__:";
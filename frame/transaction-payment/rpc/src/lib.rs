@@ -26,7 +26,7 @@ use jsonrpsee::{
 	types::error::{CallError, ErrorCode, ErrorObject},
 };
 use pallet_transaction_payment_rpc_runtime_api::{FeeDetails, InclusionFee, RuntimeDispatchInfo};
-use sp_api::ProvideRuntimeApi;
+use sp_api::{ExecutionContext, ProvideRuntimeApi};
 use sp_blockchain::HeaderBackend;
 use sp_core::Bytes;
 use sp_rpc::number::NumberOrHex;
@@ -116,7 +116,7 @@ where
 		}
 
 		let res = api
-			.query_info(at_hash, uxt, encoded_len)
+			.query_info_with_context(at_hash, ExecutionContext::UntrustedCall, uxt, encoded_len)
 			.map_err(|e| map_err(e, "Unable to query dispatch info."))?;
 
 		Ok(RuntimeDispatchInfo {
@@ -143,13 +143,20 @@ where
 				Some(format!("{:?}", e)),
 			))
 		})?;
-		let fee_details = api.query_fee_details(at_hash, uxt, encoded_len).map_err(|e| {
-			CallError::Custom(ErrorObject::owned(
-				Error::RuntimeError.into(),
-				"Unable to query fee details.",
-				Some(e.to_string()),
-			))
-		})?;
+		let fee_details = api
+			.query_fee_details_with_context(
+				at_hash,
+				ExecutionContext::UntrustedCall,
+				uxt,
+				encoded_len,
+			)
+			.map_err(|e| {
+				CallError::Custom(ErrorObject::owned(
+					Error::RuntimeError.into(),
+					"Unable to query fee details.",
+					Some(e.to_string()),
+				))
+			})?;
 
 		let try_into_rpc_balance = |value: Balance| {
 			value.try_into().map_err(|_| {
@@ -32,7 +32,7 @@ use sp_std::{
 	prelude::*,
 };
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 /// Integer types that can be used to interact with `FixedPointNumber` implementations.
@@ -935,7 +935,7 @@ macro_rules! implement_fixed {
 			}
 		}
 
-		#[cfg(feature = "std")]
+		#[cfg(any(feature = "std", feature = "serde"))]
 		impl sp_std::str::FromStr for $name {
 			type Err = &'static str;
 
@@ -948,26 +948,27 @@ macro_rules! implement_fixed {
 
 		// Manual impl `Serialize` as serde_json does not support i128.
 		// TODO: remove impl if issue https://github.com/serde-rs/json/issues/548 fixed.
-		#[cfg(feature = "std")]
+		#[cfg(feature = "serde")]
 		impl Serialize for $name {
 			fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 			where
 				S: Serializer,
 			{
-				serializer.serialize_str(&self.to_string())
+				// Same representation as `Display`, which is only available with `std`.
+				serializer.collect_str(&self.0)
 			}
 		}
 
 		// Manual impl `Deserialize` as serde_json does not support i128.
 		// TODO: remove impl if issue https://github.com/serde-rs/json/issues/548 fixed.
-		#[cfg(feature = "std")]
+		#[cfg(feature = "serde")]
 		impl<'de> Deserialize<'de> for $name {
 			fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 			where
 				D: Deserializer<'de>,
 			{
 				use sp_std::str::FromStr;
-				let s = String::deserialize(deserializer)?;
+				let s = alloc::string::String::deserialize(deserializer)?;
 				$name::from_str(&s).map_err(de::Error::custom)
 			}
 		}
@@ -75,6 +75,9 @@ pub enum Error {
 	#[error("Execution failed: {0}")]
 	Execution(Box<dyn sp_state_machine::Error>),
 
+	#[error("Execution exceeded its {0} limit")]
+	ResourceLimitExceeded(String),
+
 	#[error("Blockchain")]
 	Blockchain(#[source] Box<Error>),
 
@@ -25,14 +25,16 @@ pub mod inherents;
 
 use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use sp_runtime::{traits::Header, ConsensusEngineId, RuntimeDebug};
 use sp_std::vec::Vec;
 
 use crate::digests::{NextConfigDescriptor, NextEpochDescriptor};
 
-pub use sp_core::sr25519::vrf::{VrfOutput, VrfProof, VrfSignature, VrfTranscript};
+pub use sp_core::sr25519::vrf::{
+	VrfOutput, VrfProof, VrfSignature, VrfTranscript, VrfTranscriptData,
+};
 
 /// Key type for BABE module.
 pub const KEY_TYPE: sp_core::crypto::KeyTypeId = sp_application_crypto::key_types::BABE;
@@ -96,14 +98,30 @@ pub type BabeBlockWeight = u32;
 
 /// Make a VRF transcript data container
 pub fn make_transcript(randomness: &Randomness, slot: Slot, epoch: u64) -> VrfTranscript {
-	VrfTranscript::new(
-		&BABE_ENGINE_ID,
-		&[
-			(b"slot number", &slot.to_le_bytes()),
-			(b"current epoch", &epoch.to_le_bytes()),
-			(b"chain randomness", randomness),
-		],
-	)
+	with_transcript_items(randomness, slot, epoch, |items| {
+		VrfTranscript::new(&BABE_ENGINE_ID, items)
+	})
+}
+
+/// Make the encodable data the transcript of [`make_transcript`] is built from.
+pub fn make_transcript_data(randomness: &Randomness, slot: Slot, epoch: u64) -> VrfTranscriptData {
+	with_transcript_items(randomness, slot, epoch, |items| {
+		VrfTranscriptData::new(&BABE_ENGINE_ID, items)
+	})
+}
+
+fn with_transcript_items<R>(
+	randomness: &Randomness,
+	slot: Slot,
+	epoch: u64,
+	f: impl FnOnce(&[(&'static [u8], &[u8])]) -> R,
+) -> R {
+	let items: &[(&'static [u8], &[u8])] = &[
+		(b"slot number", &slot.to_le_bytes()),
+		(b"current epoch", &epoch.to_le_bytes()),
+		(b"chain randomness", randomness),
+	];
+	f(items)
 }
 
 /// An consensus log item for BABE.
@@ -210,7 +228,7 @@ impl BabeConfiguration {
 
 /// Types of allowed slots.
 #[derive(Clone, Copy, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum AllowedSlots {
 	/// Only allow primary slots.
 	PrimarySlots,
@@ -234,7 +252,7 @@ impl AllowedSlots {
 
 /// Configuration data used by the BABE consensus engine that may change with epochs.
 #[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug, MaxEncodedLen, TypeInfo)]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BabeEpochConfiguration {
 	/// A constant value that is used in the threshold calculation formula.
 	/// Expressed as a rational where the first member of the tuple is the
@@ -34,16 +34,16 @@ macro_rules! map {
 #[doc(hidden)]
 pub use codec::{Decode, Encode, MaxEncodedLen};
 use scale_info::TypeInfo;
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 pub use serde;
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use sp_runtime_interface::pass_by::{PassByEnum, PassByInner};
 use sp_std::{ops::Deref, prelude::*};
 
 pub use sp_debug_derive::RuntimeDebug;
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 pub use impl_serde::serialize as bytes;
 
 #[cfg(feature = "full_crypto")]
@@ -112,6 +112,10 @@ pub enum ExecutionContext {
 	///
 	/// This allows passing offchain extension and customizing available capabilities.
 	OffchainCall(Option<(Box<dyn offchain::Externalities>, offchain::Capabilities)>),
+	/// Context used for calls made on behalf of untrusted parties, e.g. over RPC.
+	///
+	/// Same as `OffchainCall(None)`, except that the resources the call may consume are bounded.
+	UntrustedCall,
 }
 
 impl ExecutionContext {
@@ -123,7 +127,7 @@ impl ExecutionContext {
 			Importing | Syncing | BlockConstruction => offchain::Capabilities::empty(),
 			// Enable keystore, transaction pool and Offchain DB reads by default for offchain
 			// calls.
-			OffchainCall(None) =>
+			OffchainCall(None) | UntrustedCall =>
 				offchain::Capabilities::KEYSTORE |
 					offchain::Capabilities::OFFCHAIN_DB_READ |
 					offchain::Capabilities::TRANSACTION_POOL,
@@ -204,7 +208,7 @@ impl sp_std::ops::Deref for OpaqueMetadata {
 	PassByInner,
 	TypeInfo,
 )]
-#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct OpaquePeerId(pub Vec<u8>);
 
 impl OpaquePeerId {
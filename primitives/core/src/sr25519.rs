@@ -20,7 +20,7 @@
 //! Note: `CHAIN_CODE_LENGTH` must be equal to `crate::crypto::JUNCTION_ID_LEN`
 //! for this to work.
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use crate::crypto::Ss58Codec;
 #[cfg(feature = "full_crypto")]
 use crate::crypto::{DeriveError, DeriveJunction, Pair as TraitPair, SecretStringError};
@@ -41,8 +41,10 @@ use sp_std::ops::Deref;
 
 #[cfg(feature = "full_crypto")]
 use schnorrkel::keys::{MINI_SECRET_KEY_LENGTH, SECRET_KEY_LENGTH};
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(all(not(feature = "std"), feature = "serde"))]
+use sp_std::alloc::{format, string::String};
 use sp_runtime_interface::pass_by::PassByInner;
 
 // signing context
@@ -176,7 +178,7 @@ impl sp_std::fmt::Debug for Public {
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 impl Serialize for Public {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
@@ -186,7 +188,7 @@ impl Serialize for Public {
 	}
 }
 
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for Public {
 	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
 	where
@@ -557,6 +559,27 @@ pub mod vrf {
 		}
 	}
 
+	/// The data a [`VrfTranscript`] is built from.
+	///
+	/// Unlike the transcript itself it can be encoded, e.g. to send it to a remote signer.
+	#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+	pub struct VrfTranscriptData {
+		/// The label of the transcript.
+		pub label: Vec<u8>,
+		/// The labelled messages appended to the transcript, in order.
+		pub items: Vec<(Vec<u8>, Vec<u8>)>,
+	}
+
+	impl VrfTranscriptData {
+		/// Record the data of a transcript built by [`VrfTranscript::new`] with the same arguments.
+		pub fn new(label: &[u8], data: &[(&[u8], &[u8])]) -> Self {
+			VrfTranscriptData {
+				label: label.to_vec(),
+				items: data.iter().map(|(l, b)| (l.to_vec(), b.to_vec())).collect(),
+			}
+		}
+	}
+
 	/// VRF signature data
 	#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo)]
 	pub struct VrfSignature {
@@ -34,6 +34,10 @@ pub enum CallContext {
 	Offchain,
 	/// The call is happening in some on-chain context like building or importing a block.
 	Onchain,
+	/// The call is made on behalf of an untrusted party, like a `state_call` RPC request.
+	///
+	/// The executor may bound the resources used by such a call.
+	Untrusted,
 }
 
 /// Code execution engine.
@@ -115,4 +115,18 @@ impl<D: KeyValueDB, H: Clone + AsRef<[u8]>> Database<H> for DbAdapter<D> {
 	fn contains(&self, col: ColumnId, key: &[u8]) -> bool {
 		handle_err(self.0.has_key(col, key))
 	}
+
+	fn iter_column_while(
+		&self,
+		col: ColumnId,
+		f: &mut dyn FnMut(Option<&[u8]>, &[u8]) -> bool,
+	) -> error::Result<()> {
+		for entry in self.0.iter(col) {
+			let (key, value) = entry.map_err(|e| error::DatabaseError(Box::new(e)))?;
+			if !f(Some(&key), &value) {
+				break
+			}
+		}
+		Ok(())
+	}
 }
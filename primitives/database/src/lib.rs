@@ -115,6 +115,23 @@ pub trait Database<H: Clone + AsRef<[u8]>>: Send + Sync {
 	///
 	/// Not all database implementations use a prefix for keys, so this function may be a noop.
 	fn sanitize_key(&self, _key: &mut Vec<u8>) {}
+
+	/// Call `f` with the key and value of every entry of `col`, in no particular order, until `f`
+	/// returns `false`.
+	///
+	/// The key is `None` for columns where the database only keeps a hash of the keys.
+	/// Returns an error if the database doesn't support iterating over a column, which is the
+	/// default.
+	fn iter_column_while(
+		&self,
+		_col: ColumnId,
+		_f: &mut dyn FnMut(Option<&[u8]>, &[u8]) -> bool,
+	) -> error::Result<()> {
+		Err(error::DatabaseError(Box::new(std::io::Error::new(
+			std::io::ErrorKind::Unsupported,
+			"Iterating over a column is not supported by the database",
+		))))
+	}
 }
 
 impl<H> std::fmt::Debug for dyn Database<H> {
@@ -73,6 +73,20 @@ where
 		let s = self.0.read();
 		s.get(&col).and_then(|c| c.get(key).map(|(_, v)| v.clone()))
 	}
+
+	fn iter_column_while(
+		&self,
+		col: ColumnId,
+		f: &mut dyn FnMut(Option<&[u8]>, &[u8]) -> bool,
+	) -> error::Result<()> {
+		let s = self.0.read();
+		for (key, (_, value)) in s.get(&col).into_iter().flatten() {
+			if !f(Some(key), value) {
+				break
+			}
+		}
+		Ok(())
+	}
 }
 
 impl MemDb {
@@ -0,0 +1,58 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Substrate genesis config builder.
+//!
+//! This crate contains the [`GenesisBuilder`] runtime API, which allows the node to build the
+//! genesis state of a runtime from a JSON representation of the runtime's `GenesisConfig`. The node
+//! only needs the runtime code, no native `GenesisConfig` type has to be compiled into it.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// The result of [`GenesisBuilder::build_config`], `Err` contains the formatted error message.
+pub type Result = core::result::Result<(), sp_runtime::RuntimeString>;
+
+/// The UTF-8 encoded name of a preset, see [`GenesisBuilder::get_preset`].
+pub type PresetId = sp_std::vec::Vec<u8>;
+
+sp_api::decl_runtime_apis! {
+	/// API to interact with the `GenesisConfig` of the runtime.
+	pub trait GenesisBuilder {
+		/// Returns the default `GenesisConfig` of the runtime as a JSON blob.
+		///
+		/// The blob can be used as a base that is patched with chain specific values before it is
+		/// passed to [`GenesisBuilder::build_config`].
+		fn create_default_config() -> sp_std::vec::Vec<u8>;
+
+		/// Build the `GenesisConfig` from the given JSON blob and put it into the storage.
+		///
+		/// No defaults are used, the JSON blob must contain the full `GenesisConfig`. The storage
+		/// is expected to be empty.
+		fn build_config(json: sp_std::vec::Vec<u8>) -> Result;
+
+		/// Returns the named preset as a JSON blob, `None` if the runtime does not know it.
+		///
+		/// A preset is a patch of the default `GenesisConfig`, for example for a development
+		/// chain, which is applied before the result is passed to [`GenesisBuilder::build_config`].
+		#[api_version(2)]
+		fn get_preset(id: PresetId) -> Option<sp_std::vec::Vec<u8>>;
+
+		/// Returns the names of all presets provided by the runtime.
+		#[api_version(2)]
+		fn preset_names() -> sp_std::vec::Vec<PresetId>;
+	}
+}
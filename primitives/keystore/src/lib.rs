@@ -89,6 +89,22 @@ pub trait Keystore: Send + Sync {
 		transcript: &sr25519::vrf::VrfTranscript,
 	) -> Result<Option<sr25519::vrf::VrfSignature>, Error>;
 
+	/// Generate an sr25519 VRF signature for a given transcript, knowing the data it was built
+	/// from.
+	///
+	/// `data` must be the data `transcript` was built from. Keystores which only forward
+	/// requests, like a remote signer, need it because the transcript itself can't be sent.
+	/// By default `data` is ignored and [`Self::sr25519_vrf_sign`] is called.
+	fn sr25519_vrf_sign_with_data(
+		&self,
+		key_type: KeyTypeId,
+		public: &sr25519::Public,
+		transcript: &sr25519::vrf::VrfTranscript,
+		_data: &sr25519::vrf::VrfTranscriptData,
+	) -> Result<Option<sr25519::vrf::VrfSignature>, Error> {
+		self.sr25519_vrf_sign(key_type, public, transcript)
+	}
+
 	/// Returns all ed25519 public keys for the given key type.
 	fn ed25519_public_keys(&self, key_type: KeyTypeId) -> Vec<ed25519::Public>;
 
@@ -99,3 +99,33 @@ pub enum TraceBlockResponse {
 	/// Successful block tracing response
 	BlockTrace(BlockTrace),
 }
+
+/// Time spent in the host functions called while re-executing a block.
+///
+/// Response for the `state_profileBlock` RPC.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockProfile {
+	/// Hash of the block being profiled
+	pub block_hash: String,
+	/// Parent hash
+	pub parent_hash: String,
+	/// Time spent executing the block, in nanoseconds
+	pub execution_time: u64,
+	/// Calls of each host function, sorted by name
+	pub host_functions: Vec<HostFunctionProfile>,
+}
+
+/// Calls of one host function while executing a block.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HostFunctionProfile {
+	/// Name of the host function
+	pub name: String,
+	/// Number of calls
+	pub calls: u64,
+	/// Time spent in all the calls, in nanoseconds
+	pub total_time: u64,
+	/// Time spent in the longest call, in nanoseconds
+	pub max_time: u64,
+}
@@ -1760,7 +1760,7 @@ macro_rules! impl_opaque_keys_inner {
 /// }
 /// ```
 #[macro_export]
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 macro_rules! impl_opaque_keys {
 	{
 		$( #[ $attr:meta ] )*
@@ -1790,7 +1790,7 @@ macro_rules! impl_opaque_keys {
 }
 
 #[macro_export]
-#[cfg(not(feature = "std"))]
+#[cfg(not(feature = "serde"))]
 #[doc(hidden)]
 macro_rules! impl_opaque_keys {
 	{
@@ -21,9 +21,16 @@ use sp_std::fmt;
 /// State Machine Error bound.
 ///
 /// This should reflect Wasm error type bound for future compatibility.
-pub trait Error: 'static + fmt::Debug + fmt::Display + Send + Sync {}
+pub trait Error: 'static + fmt::Debug + fmt::Display + Send + Sync {
+	/// Returns the error as `Any`, to downcast it to its concrete type.
+	fn as_any(&self) -> &dyn core::any::Any;
+}
 
-impl<T: 'static + fmt::Debug + fmt::Display + Send + Sync> Error for T {}
+impl<T: 'static + fmt::Debug + fmt::Display + Send + Sync> Error for T {
+	fn as_any(&self) -> &dyn core::any::Any {
+		self
+	}
+}
 
 /// Externalities Error.
 ///
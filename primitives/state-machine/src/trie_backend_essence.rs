@@ -32,10 +32,12 @@ use sp_std::{boxed::Box, marker::PhantomData, vec::Vec};
 #[cfg(feature = "std")]
 use sp_trie::recorder::Recorder;
 use sp_trie::{
-	child_delta_trie_root, delta_trie_root, empty_child_trie_root, read_child_trie_hash,
-	read_child_trie_value, read_trie_value,
+	child_delta_trie_root, delta_trie_root, empty_child_trie_root,
+	read_child_trie_first_descendant_value, read_child_trie_hash, read_child_trie_value,
+	read_trie_first_descendant_value, read_trie_value, trie_diff,
 	trie_types::{TrieDBBuilder, TrieError},
-	DBValue, KeySpacedDB, NodeCodec, Trie, TrieCache, TrieDBRawIterator, TrieRecorder,
+	DBValue, KeySpacedDB, MerkleValue, NodeCodec, Trie, TrieCache, TrieDBRawIterator, TrieDiff,
+	TrieRecorder,
 };
 #[cfg(feature = "std")]
 use std::{collections::HashMap, sync::Arc};
@@ -573,6 +575,87 @@ where
 		})
 	}
 
+	/// Get the merkle value of the closest descendant node of the given key.
+	pub fn closest_merkle_value(&self, key: &[u8]) -> Result<Option<MerkleValue<H::Out>>> {
+		let map_e = |e| format!("Trie lookup error: {}", e);
+
+		self.with_recorder_and_cache(None, |recorder, cache| {
+			read_trie_first_descendant_value::<Layout<H>, _>(self, &self.root, key, recorder, cache)
+				.map_err(map_e)
+		})
+	}
+
+	/// Get the merkle value of the closest descendant node of the given key in a child trie.
+	pub fn child_closest_merkle_value(
+		&self,
+		child_info: &ChildInfo,
+		key: &[u8],
+	) -> Result<Option<MerkleValue<H::Out>>> {
+		let child_root = match self.child_root(child_info)? {
+			Some(root) => root,
+			None => return Ok(None),
+		};
+
+		let map_e = |e| format!("Trie lookup error: {}", e);
+
+		self.with_recorder_and_cache(Some(child_root), |recorder, cache| {
+			read_child_trie_first_descendant_value::<Layout<H>, _>(
+				child_info.keyspace(),
+				self,
+				&child_root,
+				key,
+				recorder,
+				cache,
+			)
+			.map_err(map_e)
+		})
+	}
+
+	/// Compute the differences between this state and the `new` state, for the keys starting
+	/// with `prefix` in the main trie or in the given child trie.
+	///
+	/// See [`sp_trie::trie_diff`] for the reporting of the differences.
+	pub fn diff(
+		&self,
+		new: &Self,
+		child_info: Option<&ChildInfo>,
+		prefix: &[u8],
+		fetch_values: bool,
+		on_diff: &mut dyn FnMut(TrieDiff<H::Out>) -> bool,
+	) -> Result<()> {
+		let Some(child_info) = child_info else {
+			return trie_diff::<Layout<H>>(
+				self,
+				&self.root,
+				new,
+				&new.root,
+				prefix,
+				fetch_values,
+				on_diff,
+			)
+			.map_err(|e| format!("Trie diff error: {}", e))
+		};
+
+		// A missing child trie is equivalent to an empty one.
+		let empty_root = empty_child_trie_root::<Layout<H>>();
+		let old_root = self.child_root(child_info)?.unwrap_or(empty_root);
+		let new_root = new.child_root(child_info)?.unwrap_or(empty_root);
+
+		let old_db = KeySpacedDB::new(self, child_info.keyspace());
+		let new_db = KeySpacedDB::new(new, child_info.keyspace());
+
+		trie_diff::<Layout<H>>(
+			&old_db,
+			&old_root,
+			&new_db,
+			&new_root,
+			prefix,
+			fetch_values,
+			on_diff,
+		)
+		.map_err(|e| format!("Trie diff error: {}", e))
+	}
+
 	/// Create a raw iterator over the storage.
 	pub fn raw_iter(&self, args: IterArgs) -> Result<RawIter<S, H, C>> {
 		let root = if let Some(child_info) = args.child_info.as_ref() {
@@ -52,7 +52,7 @@ use trie_db::{node::NodeOwned, CachedValue};
 
 mod shared_cache;
 
-pub use shared_cache::SharedTrieCache;
+pub use shared_cache::{SharedTrieCache, SnapshotError};
 
 use self::shared_cache::ValueCacheKeyHash;
 
@@ -977,4 +977,63 @@ mod tests {
 
 		assert!(shared_cache.used_memory_size() < CACHE_SIZE_RAW);
 	}
+
+	#[test]
+	fn snapshot_restores_the_cache() {
+		let (db, root) = create_trie();
+
+		let shared_cache = Cache::new(CACHE_SIZE);
+		{
+			let local_cache = shared_cache.local_cache();
+
+			let mut cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<Layout>::new(&db, &root).with_cache(&mut cache).build();
+
+			for (k, _) in TEST_DATA {
+				trie.get(k).unwrap().unwrap();
+			}
+			assert!(trie.get(b"missing").unwrap().is_none());
+		}
+
+		let snapshot = shared_cache.snapshot(&root, usize::MAX);
+
+		let restored = Cache::new(CACHE_SIZE);
+		assert!(matches!(
+			restored.restore_snapshot(&Default::default(), &snapshot),
+			Err(SnapshotError::StateRootMismatch)
+		));
+		restored.restore_snapshot(&root, &snapshot).unwrap();
+
+		assert_eq!(
+			shared_cache.read_lock_inner().node_cache().lru.len(),
+			restored.read_lock_inner().node_cache().lru.len(),
+		);
+		assert!(matches!(
+			restored.peek_value_by_hash(
+				ValueCacheKey::hash_data(b"missing", &root),
+				&root,
+				b"missing"
+			),
+			Some(CachedValue::NonExisting)
+		));
+
+		// All the nodes were restored, so the trie can be read without its database.
+		{
+			let db = MemoryDB::default();
+			let local_cache = restored.local_cache();
+
+			let mut cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<Layout>::new(&db, &root).with_cache(&mut cache).build();
+
+			for (k, v) in TEST_DATA {
+				assert_eq!(v.to_vec(), trie.get(k).unwrap().unwrap());
+			}
+		}
+
+		// Nothing fits into an empty snapshot.
+		let restored = Cache::new(CACHE_SIZE);
+		restored.restore_snapshot(&root, &shared_cache.snapshot(&root, 0)).unwrap();
+		assert!(restored.read_lock_inner().node_cache().lru.is_empty());
+		assert!(restored.read_lock_inner().value_cache().lru.is_empty());
+	}
 }
@@ -18,6 +18,8 @@
 ///! Provides the [`SharedNodeCache`], the [`SharedValueCache`] and the [`SharedTrieCache`]
 ///! that combines both caches and is exported to the outside.
 use super::{CacheSize, NodeCached};
+use crate::{LayoutV1, NodeCodec};
+use codec::{Codec, Decode, Encode};
 use hash_db::Hasher;
 use hashbrown::{hash_set::Entry as SetEntry, HashSet};
 use nohash_hasher::BuildNoHashHasher;
@@ -27,7 +29,7 @@ use std::{
 	hash::{BuildHasher, Hasher as _},
 	sync::Arc,
 };
-use trie_db::{node::NodeOwned, CachedValue};
+use trie_db::{node::NodeOwned, CachedValue, NodeCodec as _};
 
 lazy_static::lazy_static! {
 	static ref RANDOM_STATE: ahash::RandomState = ahash::RandomState::default();
@@ -310,6 +312,18 @@ impl<H: AsRef<[u8]> + Eq + std::hash::Hash> SharedNodeCache<H> {
 		);
 	}
 
+	/// Insert the `nodes` of a snapshot, ordered from the least to the most recently used.
+	///
+	/// Unlike [`Self::update`], this evicts as many nodes as needed to stay in the size bounds.
+	fn restore(&mut self, nodes: impl IntoIterator<Item = (H, NodeOwned<H>)>) {
+		self.lru.limiter_mut().items_evicted = 0;
+		self.lru.limiter_mut().max_items_evicted = usize::MAX;
+
+		for (key, node) in nodes {
+			self.lru.insert(key, node);
+		}
+	}
+
 	/// Reset the cache.
 	fn reset(&mut self) {
 		self.lru.clear();
@@ -557,6 +571,18 @@ impl<H: Eq + std::hash::Hash + Clone + Copy + AsRef<[u8]>> SharedValueCache<H> {
 		);
 	}
 
+	/// Insert the `values` of a snapshot, ordered from the least to the most recently used.
+	///
+	/// Unlike [`Self::update`], this evicts as many values as needed to stay in the size bounds.
+	fn restore(&mut self, values: impl IntoIterator<Item = (ValueCacheKey<H>, CachedValue<H>)>) {
+		self.lru.limiter_mut().items_evicted = 0;
+		self.lru.limiter_mut().max_items_evicted = usize::MAX;
+
+		for (key, value) in values {
+			self.lru.insert(key, value);
+		}
+	}
+
 	/// Reset the cache.
 	fn reset(&mut self) {
 		self.lru.clear();
@@ -593,6 +619,47 @@ impl<H: Hasher> SharedTrieCacheInner<H> {
 	}
 }
 
+/// The version of the format of the snapshots of the [`SharedTrieCache`].
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// A snapshot of the [`SharedTrieCache`], see [`SharedTrieCache::snapshot`].
+#[derive(Encode, Decode)]
+struct Snapshot<H> {
+	/// The state root the snapshot was taken at.
+	state_root: H,
+	/// The nodes, ordered from the least to the most recently used.
+	nodes: Vec<SnapshotNode>,
+	/// The storage keys and the hashes of the values of `state_root`, ordered from the least to
+	/// the most recently used. The hash is `None` for values that don't exist.
+	values: Vec<(Vec<u8>, Option<H>)>,
+}
+
+/// A node of a [`Snapshot`].
+#[derive(Encode, Decode)]
+enum SnapshotNode {
+	/// An encoded trie node.
+	Node(Vec<u8>),
+	/// The data of a value node.
+	Value(Vec<u8>),
+}
+
+/// Errors that can occur when restoring a snapshot of the [`SharedTrieCache`].
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+	/// The snapshot was written in a format this version doesn't support.
+	#[error("Unsupported snapshot version: {0}")]
+	UnsupportedVersion(u8),
+	/// The snapshot was taken at another state root.
+	#[error("The snapshot was taken at another state root")]
+	StateRootMismatch,
+	/// The snapshot couldn't be decoded.
+	#[error("Failed to decode the snapshot: {0}")]
+	Decode(#[from] codec::Error),
+	/// The snapshot contains a node that couldn't be decoded.
+	#[error("The snapshot contains an invalid node")]
+	InvalidNode,
+}
+
 /// The shared trie cache.
 ///
 /// It should be instantiated once per node. It will hold the trie nodes and values of all
@@ -748,6 +815,133 @@ impl<H: Hasher> SharedTrieCache<H> {
 	}
 }
 
+impl<H: Hasher> SharedTrieCache<H>
+where
+	H::Out: Codec,
+{
+	/// Encode a snapshot of the cache, to be restored with [`Self::restore_snapshot`].
+	///
+	/// The snapshot holds the most recently used nodes and values of `state_root` that fit into
+	/// `max_size` bytes. Values holding their data are left out, as they are cheaply rebuilt from
+	/// the nodes on first access.
+	///
+	/// This will temporarily lock the shared cache for reading.
+	pub fn snapshot(&self, state_root: &H::Out, max_size: usize) -> Vec<u8> {
+		// Split the budget the same way as between the caches.
+		let mut values_budget = max_size / 5;
+		let mut nodes_budget = max_size - values_budget;
+
+		let inner = self.inner.read();
+
+		let mut nodes = Vec::new();
+		for (_, node) in inner.node_cache.lru.iter() {
+			let node = match node {
+				NodeOwned::Value(data, _) => SnapshotNode::Value(data.to_vec()),
+				node => SnapshotNode::Node(node.to_encoded::<NodeCodec<H>>()),
+			};
+			let size = node.encoded_size();
+			if size > nodes_budget {
+				break
+			}
+			nodes_budget -= size;
+			nodes.push(node);
+		}
+
+		let mut values = Vec::new();
+		for (key, value) in inner.value_cache.lru.iter() {
+			if key.storage_root != *state_root {
+				continue
+			}
+			let hash = match value {
+				CachedValue::NonExisting => None,
+				CachedValue::ExistingHash(hash) => Some(*hash),
+				CachedValue::Existing { .. } => continue,
+			};
+			let value = (key.storage_key.to_vec(), hash);
+			let size = value.encoded_size();
+			if size > values_budget {
+				break
+			}
+			values_budget -= size;
+			values.push(value);
+		}
+
+		drop(inner);
+
+		tracing::debug!(
+			target: super::LOG_TARGET,
+			"Took a snapshot of the shared trie cache: {} nodes, {} values",
+			nodes.len(),
+			values.len(),
+		);
+
+		// The LRU maps iterate from the most recently used entry.
+		nodes.reverse();
+		values.reverse();
+		(SNAPSHOT_VERSION, Snapshot { state_root: *state_root, nodes, values }).encode()
+	}
+
+	/// Restore a snapshot encoded by [`Self::snapshot`], if it was taken at `state_root`.
+	///
+	/// The restored nodes and values become the most recently used ones, evicting older ones if
+	/// the snapshot doesn't fit into the cache.
+	pub fn restore_snapshot(
+		&self,
+		state_root: &H::Out,
+		mut snapshot: &[u8],
+	) -> Result<(), SnapshotError> {
+		let version = u8::decode(&mut snapshot)?;
+		if version != SNAPSHOT_VERSION {
+			return Err(SnapshotError::UnsupportedVersion(version))
+		}
+
+		let snapshot = Snapshot::<H::Out>::decode(&mut snapshot)?;
+		if snapshot.state_root != *state_root {
+			return Err(SnapshotError::StateRootMismatch)
+		}
+
+		let nodes = snapshot
+			.nodes
+			.into_iter()
+			.map(|node| match node {
+				SnapshotNode::Node(encoded) => {
+					let node = NodeCodec::<H>::decode(&encoded)
+						.ok()
+						.and_then(|node| node.to_owned_node::<LayoutV1<H>>().ok())
+						.ok_or(SnapshotError::InvalidNode)?;
+					Ok((H::hash(&encoded), node))
+				},
+				SnapshotNode::Value(data) => {
+					let hash = H::hash(&data);
+					Ok((hash, NodeOwned::Value(data.into(), hash)))
+				},
+			})
+			.collect::<Result<Vec<_>, SnapshotError>>()?;
+
+		let values = snapshot
+			.values
+			.into_iter()
+			.map(|(storage_key, hash)| {
+				let key = ValueCacheRef::new(&storage_key, *state_root).into();
+				(key, hash.map_or(CachedValue::NonExisting, CachedValue::ExistingHash))
+			})
+			.collect::<Vec<_>>();
+
+		tracing::debug!(
+			target: super::LOG_TARGET,
+			"Restoring a snapshot of the shared trie cache: {} nodes, {} values",
+			nodes.len(),
+			values.len(),
+		);
+
+		let mut inner = self.inner.write();
+		inner.node_cache.restore(nodes);
+		inner.value_cache.restore(values);
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
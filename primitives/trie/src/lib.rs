@@ -28,6 +28,7 @@ mod node_header;
 pub mod recorder;
 mod storage_proof;
 mod trie_codec;
+mod trie_diff;
 mod trie_stream;
 
 /// Our `NodeCodec`-specific error.
@@ -51,8 +52,11 @@ pub use trie_db::{
 	nibble_ops,
 	node::{NodePlan, ValuePlan},
 	CError, DBValue, Query, Recorder, Trie, TrieCache, TrieConfiguration, TrieDBIterator,
-	TrieDBKeyIterator, TrieDBRawIterator, TrieLayout, TrieMut, TrieRecorder,
+	TrieDBKeyIterator, TrieDBNodeIterator, TrieDBRawIterator, TrieLayout, TrieMut, TrieRecorder,
 };
+use trie_db::{node::Node, NibbleVec, TrieIterator};
+/// Differences between two tries.
+pub use trie_diff::{trie_diff, TrieDiff, TrieDiffKind, TrieDiffValue};
 /// The Substrate format implementation of `TrieStream`.
 pub use trie_stream::TrieStream;
 
@@ -309,6 +313,88 @@ pub fn read_trie_value_with<
 	TrieDBBuilder::<L>::new(db, root).build().get_with(key, query)
 }
 
+/// Merkle value of a trie node.
+///
+/// Nodes that are small enough are inlined into their parent and have no hash of their own,
+/// in which case the encoded node itself is the merkle value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MerkleValue<H> {
+	/// The encoded node, for nodes inlined into their parent.
+	Node(Vec<u8>),
+	/// The hash of the node.
+	Hash(H),
+}
+
+/// Read the merkle value of the closest descendant of `key` in the trie.
+///
+/// The closest descendant is the node with the shortest full key that starts with `key`,
+/// including the node located exactly at `key`. Returns `None` if no such node exists.
+pub fn read_trie_first_descendant_value<L: TrieLayout, DB>(
+	db: &DB,
+	root: &TrieHash<L>,
+	key: &[u8],
+	recorder: Option<&mut dyn TrieRecorder<TrieHash<L>>>,
+	cache: Option<&mut dyn TrieCache<L::Codec>>,
+) -> Result<Option<MerkleValue<TrieHash<L>>>, Box<TrieError<L>>>
+where
+	DB: hash_db::HashDBRef<L::Hash, trie_db::DBValue>,
+{
+	let trie = TrieDBBuilder::<L>::new(db, root)
+		.with_optional_cache(cache)
+		.with_optional_recorder(recorder)
+		.build();
+	let mut iter = TrieDBNodeIterator::new(&trie)?;
+	iter.seek(key)?;
+
+	let mut key_nibbles = NibbleVec::new();
+	key_nibbles.append_partial(((0, 0), key));
+
+	for item in iter {
+		let (prefix, hash, node) = item?;
+
+		let mut full_key = prefix;
+		match node.node() {
+			Node::Leaf(partial, _) |
+			Node::Extension(partial, _) |
+			Node::NibbledBranch(partial, _, _) => full_key.append_partial(partial.right()),
+			Node::Branch(_, _) => {},
+			Node::Empty => break,
+		}
+
+		if full_key.starts_with(&key_nibbles) {
+			let merkle_value = match hash {
+				Some(hash) => MerkleValue::Hash(hash),
+				None => MerkleValue::Node(node.data().to_vec()),
+			};
+			return Ok(Some(merkle_value))
+		}
+
+		// Nodes on the path towards `key` are ancestors and can be skipped, any other node
+		// means that the iterator went past the keys prefixed by `key`.
+		if !key_nibbles.starts_with(&full_key) {
+			break
+		}
+	}
+
+	Ok(None)
+}
+
+/// Read the merkle value of the closest descendant of `key` in the child trie.
+pub fn read_child_trie_first_descendant_value<L: TrieConfiguration, DB>(
+	keyspace: &[u8],
+	db: &DB,
+	root: &TrieHash<L>,
+	key: &[u8],
+	recorder: Option<&mut dyn TrieRecorder<TrieHash<L>>>,
+	cache: Option<&mut dyn TrieCache<L::Codec>>,
+) -> Result<Option<MerkleValue<TrieHash<L>>>, Box<TrieError<L>>>
+where
+	DB: hash_db::HashDBRef<L::Hash, trie_db::DBValue>,
+{
+	let db = KeySpacedDB::new(db, keyspace);
+	read_trie_first_descendant_value::<L, _>(&db, root, key, recorder, cache)
+}
+
 /// Determine the empty trie root.
 pub fn empty_trie_root<L: TrieConfiguration>() -> <L::Hash as Hasher>::Out {
 	L::trie_root::<_, Vec<u8>, Vec<u8>>(core::iter::empty())
@@ -992,6 +1078,121 @@ mod tests {
 		check(u16::MAX as usize / 2 + 1); // value over old limit still works
 	}
 
+	#[test]
+	fn first_descendant_value_works() {
+		let mut memdb = MemoryDB::<Blake2Hasher>::default();
+		let mut root = Default::default();
+		{
+			let mut t = TrieDBMutBuilder::<LayoutV1>::new(&mut memdb, &mut root).build();
+			t.insert(b"alpha", &[1u8; 64]).unwrap();
+			t.insert(b"alphabet", &[2u8; 64]).unwrap();
+			t.insert(b"beta", &[3u8; 64]).unwrap();
+		}
+
+		let read = |key: &[u8]| {
+			read_trie_first_descendant_value::<LayoutV1, _>(&memdb, &root, key, None, None)
+				.unwrap()
+		};
+
+		assert_eq!(read(b""), Some(MerkleValue::Hash(root)));
+		assert!(read(b"alpha").is_some());
+		assert_eq!(read(b"al"), read(b"alpha"));
+		assert!(read(b"alphab").is_some());
+		assert_ne!(read(b"alphab"), read(b"alpha"));
+		assert_eq!(read(b"alphab"), read(b"alphabet"));
+		assert_eq!(read(b"alphabets"), None);
+		assert_eq!(read(b"gamma"), None);
+	}
+
+	#[test]
+	fn trie_diff_works() {
+		let mut old_memdb = PrefixedMemoryDB::<Blake2Hasher>::default();
+		let mut old_root = Default::default();
+		{
+			let mut t = TrieDBMutBuilder::<LayoutV1>::new(&mut old_memdb, &mut old_root).build();
+			t.insert(b"alpha", &[1u8; 64]).unwrap();
+			t.insert(b"alphabet", b"short").unwrap();
+			t.insert(b"beta", &[3u8; 64]).unwrap();
+			t.insert(b"delta", b"removed").unwrap();
+		}
+		// Removed nodes are pruned from the database, keep the old trie in its own database.
+		let mut new_memdb = old_memdb.clone();
+		let mut new_root = old_root;
+		{
+			let mut t = TrieDBMutBuilder::<LayoutV1>::from_existing(&mut new_memdb, &mut new_root)
+				.build();
+			t.insert(b"alphabet", b"changed").unwrap();
+			t.insert(b"gamma", &[4u8; 64]).unwrap();
+			t.remove(b"delta").unwrap();
+		}
+
+		let diff = |old: (&PrefixedMemoryDB<Blake2Hasher>, &TrieHash<LayoutV1>),
+		            new: (&PrefixedMemoryDB<Blake2Hasher>, &TrieHash<LayoutV1>),
+		            prefix: &[u8],
+		            fetch_values: bool| {
+			let mut diffs = Vec::new();
+			trie_diff::<LayoutV1>(
+				old.0,
+				old.1,
+				new.0,
+				new.1,
+				prefix,
+				fetch_values,
+				&mut |diff| {
+					diffs.push(diff);
+					true
+				},
+			)
+			.unwrap();
+			diffs
+		};
+
+		let value = |key: &[u8], kind, value: &[u8]| TrieDiff {
+			key: key.to_vec(),
+			kind,
+			value: TrieDiffValue::Value(value.to_vec()),
+		};
+
+		assert_eq!(
+			diff((&old_memdb, &old_root), (&new_memdb, &new_root), b"", true),
+			vec![
+				value(b"alphabet", TrieDiffKind::Modified, b"changed"),
+				value(b"delta", TrieDiffKind::Deleted, b"removed"),
+				value(b"gamma", TrieDiffKind::Added, &[4u8; 64]),
+			]
+		);
+
+		// Reversed order of the tries.
+		assert_eq!(
+			diff((&new_memdb, &new_root), (&old_memdb, &old_root), b"", true),
+			vec![
+				value(b"alphabet", TrieDiffKind::Modified, b"short"),
+				value(b"delta", TrieDiffKind::Added, b"removed"),
+				value(b"gamma", TrieDiffKind::Deleted, &[4u8; 64]),
+			]
+		);
+
+		// Only the keys starting with the prefix are reported.
+		assert_eq!(
+			diff((&old_memdb, &old_root), (&new_memdb, &new_root), b"alph", true),
+			vec![value(b"alphabet", TrieDiffKind::Modified, b"changed")]
+		);
+		assert!(diff((&old_memdb, &old_root), (&new_memdb, &new_root), b"beta", true).is_empty());
+
+		// Hashes are reported instead of values.
+		assert_eq!(
+			diff((&old_memdb, &old_root), (&new_memdb, &new_root), b"g", false),
+			vec![TrieDiff {
+				key: b"gamma".to_vec(),
+				kind: TrieDiffKind::Added,
+				value: TrieDiffValue::Hash(Blake2Hasher::hash(&[4u8; 64])),
+			}]
+		);
+
+		// Identical tries have no differences.
+		assert!(diff((&new_memdb, &new_root), (&new_memdb, &new_root), b"", true).is_empty());
+	}
+
 	#[test]
 	fn node_with_no_children_fail_decoding() {
 		let branch = NodeCodec::<Blake2Hasher>::branch_node_nibbled(
@@ -0,0 +1,368 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compute the differences between two tries.
+//!
+//! The tries are walked together from their roots and subtrees that are referenced by the same
+//! hash in both tries are skipped, such that the cost of the walk is proportional to the size
+//! of the difference rather than to the size of the tries.
+
+use crate::{DBValue, TrieError, TrieHash, TrieLayout};
+use hash_db::{HashDBRef, Hasher};
+use sp_std::{boxed::Box, rc::Rc, vec::Vec};
+use trie_db::{
+	node::{decode_hash, Node, NodeHandle, OwnedNode, Value},
+	NibbleSlice, NibbleVec,
+};
+
+/// The kind of difference reported for a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrieDiffKind {
+	/// The key is only present in the new trie.
+	Added,
+	/// The key is present in both tries with different values.
+	Modified,
+	/// The key is only present in the old trie.
+	Deleted,
+}
+
+/// The value reported for a key in a [`TrieDiff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrieDiffValue<H> {
+	/// The value itself.
+	Value(Vec<u8>),
+	/// The hash of the value.
+	Hash(H),
+}
+
+/// A difference between two tries for a single key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrieDiff<H> {
+	/// The key that differs.
+	pub key: Vec<u8>,
+	/// The kind of the difference.
+	pub kind: TrieDiffKind,
+	/// The value in the new trie, or the value in the old trie for deleted keys.
+	pub value: TrieDiffValue<H>,
+}
+
+/// Reference to a child node as stored in its parent.
+#[derive(Clone, PartialEq, Eq)]
+enum ChildRef<H> {
+	Hash(H),
+	Inline(Vec<u8>),
+}
+
+/// A subtree of the trie, rooted at the nibble path currently walked.
+#[derive(Clone)]
+enum Subtree<H> {
+	/// A node that was not loaded yet.
+	Handle(ChildRef<H>),
+	/// A loaded node of which the first `consumed` nibbles of the partial key are above the
+	/// current path.
+	Node { node: Rc<OwnedNode<DBValue>>, child_ref: ChildRef<H>, consumed: usize },
+}
+
+impl<H: PartialEq> Subtree<H> {
+	/// Returns `true` if both subtrees are guaranteed to have the same content.
+	fn is_same(&self, other: &Self) -> bool {
+		match (self, other) {
+			(Subtree::Handle(a), Subtree::Handle(b)) => a == b,
+			(
+				Subtree::Node { child_ref: a, consumed: a_consumed, .. },
+				Subtree::Node { child_ref: b, consumed: b_consumed, .. },
+			) => a == b && a_consumed == b_consumed,
+			_ => false,
+		}
+	}
+}
+
+/// The value stored at the current path.
+enum ValueRef<H> {
+	Inline(Vec<u8>),
+	Hash(H),
+}
+
+/// The content of a trie at a given nibble path: the value at the path and the subtrees of
+/// the children.
+struct View<H> {
+	value: Option<ValueRef<H>>,
+	children: [Option<Subtree<H>>; 16],
+}
+
+impl<H> View<H> {
+	fn empty() -> Self {
+		View { value: None, children: Default::default() }
+	}
+}
+
+/// One side of the diff.
+struct Side<'a, L: TrieLayout> {
+	db: &'a dyn HashDBRef<L::Hash, DBValue>,
+}
+
+impl<'a, L: TrieLayout> Side<'a, L> {
+	/// Load the node referenced by `child_ref`, located at `path`.
+	fn load(
+		&self,
+		child_ref: &ChildRef<TrieHash<L>>,
+		path: &NibbleVec,
+	) -> Result<Rc<OwnedNode<DBValue>>, Box<TrieError<L>>> {
+		let (data, hash) = match child_ref {
+			ChildRef::Hash(hash) => {
+				let data = self
+					.db
+					.get(hash, path.as_prefix())
+					.ok_or_else(|| Box::new(TrieError::IncompleteDatabase(*hash)))?;
+				(data, *hash)
+			},
+			ChildRef::Inline(data) => (data.clone(), Default::default()),
+		};
+
+		OwnedNode::new::<L::Codec>(data)
+			.map(Rc::new)
+			.map_err(|e| Box::new(TrieError::DecoderError(hash, e)))
+	}
+
+	/// Compute the view of the given subtree at `path`.
+	fn view(
+		&self,
+		subtree: Subtree<TrieHash<L>>,
+		path: &NibbleVec,
+	) -> Result<View<TrieHash<L>>, Box<TrieError<L>>> {
+		let (mut node, mut child_ref, mut consumed) = match subtree {
+			Subtree::Handle(child_ref) => (self.load(&child_ref, path)?, child_ref, 0),
+			Subtree::Node { node, child_ref, consumed } => (node, child_ref, consumed),
+		};
+
+		loop {
+			let partial = match node.node() {
+				Node::Leaf(partial, _) |
+				Node::Extension(partial, _) |
+				Node::NibbledBranch(partial, _, _) => partial,
+				Node::Empty | Node::Branch(_, _) => NibbleSlice::new(&[]),
+			};
+
+			// The path is in the middle of the partial key of the node.
+			if consumed < partial.len() {
+				let mut view = View::empty();
+				view.children[partial.at(consumed) as usize] =
+					Some(Subtree::Node { node: node.clone(), child_ref, consumed: consumed + 1 });
+				return Ok(view)
+			}
+
+			let next_ref = match node.node() {
+				Node::Empty => return Ok(View::empty()),
+				Node::Leaf(_, value) => {
+					let mut view = View::empty();
+					view.value = Some(value_ref::<L>(value));
+					return Ok(view)
+				},
+				Node::Branch(children, value) | Node::NibbledBranch(_, children, value) => {
+					let mut view = View::empty();
+					view.value = value.map(value_ref::<L>);
+					for (index, child) in children.into_iter().enumerate() {
+						if let Some(child) = child {
+							view.children[index] =
+								Some(Subtree::Handle(child_handle_ref::<L>(child)?));
+						}
+					}
+					return Ok(view)
+				},
+				// Extension nodes have no value, the view is the one of the child node.
+				Node::Extension(_, child) => child_handle_ref::<L>(child)?,
+			};
+
+			node = self.load(&next_ref, path)?;
+			child_ref = next_ref;
+			consumed = 0;
+		}
+	}
+
+	/// Resolve the value reported for a key located at `path`.
+	fn value(
+		&self,
+		value: &ValueRef<TrieHash<L>>,
+		path: &NibbleVec,
+		fetch_values: bool,
+	) -> Result<TrieDiffValue<TrieHash<L>>, Box<TrieError<L>>> {
+		Ok(match (value, fetch_values) {
+			(ValueRef::Inline(data), true) => TrieDiffValue::Value(data.clone()),
+			(ValueRef::Inline(data), false) => TrieDiffValue::Hash(L::Hash::hash(data)),
+			(ValueRef::Hash(hash), true) => TrieDiffValue::Value(
+				self.db
+					.get(hash, (path.inner(), None))
+					.ok_or_else(|| Box::new(TrieError::IncompleteDatabase(*hash)))?,
+			),
+			(ValueRef::Hash(hash), false) => TrieDiffValue::Hash(*hash),
+		})
+	}
+}
+
+fn value_ref<L: TrieLayout>(value: Value) -> ValueRef<TrieHash<L>> {
+	match value {
+		Value::Inline(data) => ValueRef::Inline(data.to_vec()),
+		Value::Node(hash) => {
+			let mut res = TrieHash::<L>::default();
+			res.as_mut().copy_from_slice(hash);
+			ValueRef::Hash(res)
+		},
+	}
+}
+
+fn child_handle_ref<L: TrieLayout>(
+	handle: NodeHandle,
+) -> Result<ChildRef<TrieHash<L>>, Box<TrieError<L>>> {
+	match handle {
+		NodeHandle::Hash(data) => decode_hash::<L::Hash>(data)
+			.map(ChildRef::Hash)
+			.ok_or_else(|| Box::new(TrieError::InvalidHash(Default::default(), data.to_vec()))),
+		NodeHandle::Inline(data) => Ok(ChildRef::Inline(data.to_vec())),
+	}
+}
+
+/// Hash of the value, used to compare values stored inline with values stored as nodes.
+fn value_hash<L: TrieLayout>(value: &ValueRef<TrieHash<L>>) -> TrieHash<L> {
+	match value {
+		ValueRef::Inline(data) => L::Hash::hash(data),
+		ValueRef::Hash(hash) => *hash,
+	}
+}
+
+/// State of a diff walk.
+struct DiffWalk<'a, 'cb, L: TrieLayout> {
+	old: Side<'a, L>,
+	new: Side<'a, L>,
+	fetch_values: bool,
+	on_diff: &'cb mut dyn FnMut(TrieDiff<TrieHash<L>>) -> bool,
+}
+
+impl<'a, 'cb, L: TrieLayout> DiffWalk<'a, 'cb, L> {
+	/// Report a difference, returns `false` if the walk must stop.
+	fn report(
+		&mut self,
+		kind: TrieDiffKind,
+		value: &ValueRef<TrieHash<L>>,
+		path: &NibbleVec,
+	) -> Result<bool, Box<TrieError<L>>> {
+		let (key, extra_nibble) = path.as_prefix();
+		if let Some(extra_nibble) = extra_nibble {
+			return Err(Box::new(TrieError::ValueAtIncompleteKey(key.to_vec(), extra_nibble)))
+		}
+
+		let value = match kind {
+			TrieDiffKind::Deleted => self.old.value(value, path, self.fetch_values)?,
+			TrieDiffKind::Added | TrieDiffKind::Modified =>
+				self.new.value(value, path, self.fetch_values)?,
+		};
+
+		Ok((self.on_diff)(TrieDiff { key: key.to_vec(), kind, value }))
+	}
+
+	/// Walk the given subtrees, returns `false` if the walk must stop.
+	fn walk(
+		&mut self,
+		old: Option<Subtree<TrieHash<L>>>,
+		new: Option<Subtree<TrieHash<L>>>,
+		path: &mut NibbleVec,
+	) -> Result<bool, Box<TrieError<L>>> {
+		match (&old, &new) {
+			(None, None) => return Ok(true),
+			(Some(old), Some(new)) if old.is_same(new) => return Ok(true),
+			_ => {},
+		}
+
+		let mut old = old.map(|subtree| self.old.view(subtree, path)).transpose()?;
+		let mut new = new.map(|subtree| self.new.view(subtree, path)).transpose()?;
+
+		let old_value = old.as_mut().and_then(|view| view.value.take());
+		let new_value = new.as_mut().and_then(|view| view.value.take());
+		let proceed = match (old_value, new_value) {
+			(None, None) => true,
+			(None, Some(new_value)) => self.report(TrieDiffKind::Added, &new_value, path)?,
+			(Some(old_value), None) => self.report(TrieDiffKind::Deleted, &old_value, path)?,
+			(Some(old_value), Some(new_value)) =>
+				if value_hash::<L>(&old_value) != value_hash::<L>(&new_value) {
+					self.report(TrieDiffKind::Modified, &new_value, path)?
+				} else {
+					true
+				},
+		};
+		if !proceed {
+			return Ok(false)
+		}
+
+		for index in 0..16 {
+			let old_child = old.as_mut().and_then(|view| view.children[index].take());
+			let new_child = new.as_mut().and_then(|view| view.children[index].take());
+			if old_child.is_none() && new_child.is_none() {
+				continue
+			}
+
+			path.push(index as u8);
+			let proceed = self.walk(old_child, new_child, path)?;
+			path.pop();
+
+			if !proceed {
+				return Ok(false)
+			}
+		}
+
+		Ok(true)
+	}
+}
+
+/// Compute the differences between the trie at `old_root` and the trie at `new_root`, for the
+/// keys starting with `prefix`.
+///
+/// The differences are reported to `on_diff` in the lexicographic order of the keys; the walk
+/// stops early if `on_diff` returns `false`. Depending on `fetch_values`, the reported values
+/// are either the values or their hashes.
+pub fn trie_diff<L: TrieLayout>(
+	old_db: &dyn HashDBRef<L::Hash, DBValue>,
+	old_root: &TrieHash<L>,
+	new_db: &dyn HashDBRef<L::Hash, DBValue>,
+	new_root: &TrieHash<L>,
+	prefix: &[u8],
+	fetch_values: bool,
+	on_diff: &mut dyn FnMut(TrieDiff<TrieHash<L>>) -> bool,
+) -> Result<(), Box<TrieError<L>>> {
+	if old_root == new_root {
+		return Ok(())
+	}
+
+	let mut walk =
+		DiffWalk { old: Side { db: old_db }, new: Side { db: new_db }, fetch_values, on_diff };
+
+	let mut old = Some(Subtree::Handle(ChildRef::Hash(*old_root)));
+	let mut new = Some(Subtree::Handle(ChildRef::Hash(*new_root)));
+	let mut path = NibbleVec::new();
+
+	// Descend to the prefix, values above it are not reported.
+	let prefix = NibbleSlice::new(prefix);
+	for index in 0..prefix.len() {
+		let nibble = prefix.at(index);
+		let mut old_view = old.map(|subtree| walk.old.view(subtree, &path)).transpose()?;
+		let mut new_view = new.map(|subtree| walk.new.view(subtree, &path)).transpose()?;
+
+		old = old_view.as_mut().and_then(|view| view.children[nibble as usize].take());
+		new = new_view.as_mut().and_then(|view| view.children[nibble as usize].take());
+		path.push(nibble);
+	}
+
+	walk.walk(old, new, &mut path).map(|_| ())
+}
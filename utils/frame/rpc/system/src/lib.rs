@@ -28,7 +28,7 @@ use jsonrpsee::{
 
 use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::{InPoolTransaction, TransactionPool};
-use sp_api::ApiExt;
+use sp_api::{ApiExt, ExecutionContext};
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::HeaderBackend;
 use sp_core::{hexdisplay::HexDisplay, Bytes};
@@ -102,13 +102,15 @@ where
 		let api = self.client.runtime_api();
 		let best = self.client.info().best_hash;
 
-		let nonce = api.account_nonce(best, account.clone()).map_err(|e| {
-			CallError::Custom(ErrorObject::owned(
-				Error::RuntimeError.into(),
-				"Unable to query nonce.",
-				Some(e.to_string()),
-			))
-		})?;
+		let nonce = api
+			.account_nonce_with_context(best, ExecutionContext::UntrustedCall, account.clone())
+			.map_err(|e| {
+				CallError::Custom(ErrorObject::owned(
+					Error::RuntimeError.into(),
+					"Unable to query nonce.",
+					Some(e.to_string()),
+				))
+			})?;
 		Ok(adjust_nonce(&*self.pool, account, nonce))
 	}
 
@@ -151,8 +153,21 @@ where
 
 		let result = if api_version < 6 {
 			#[allow(deprecated)]
-			api.apply_extrinsic_before_version_6(best_hash, uxt)
-				.map(legacy::byte_sized_error::convert_to_latest)
+			api.apply_extrinsic_before_version_6_with_context(
+				best_hash,
+				ExecutionContext::UntrustedCall,
+				uxt,
+			)
+			.map(legacy::byte_sized_error::convert_to_latest)
+			.map_err(|e| {
+				CallError::Custom(ErrorObject::owned(
+					Error::RuntimeError.into(),
+					"Unable to dry run extrinsic.",
+					Some(e.to_string()),
+				))
+			})?
+		} else {
+			api.apply_extrinsic_with_context(best_hash, ExecutionContext::UntrustedCall, uxt)
 				.map_err(|e| {
 					CallError::Custom(ErrorObject::owned(
 						Error::RuntimeError.into(),
@@ -160,14 +175,6 @@ where
 						Some(e.to_string()),
 					))
 				})?
-		} else {
-			api.apply_extrinsic(best_hash, uxt).map_err(|e| {
-				CallError::Custom(ErrorObject::owned(
-					Error::RuntimeError.into(),
-					"Unable to dry run extrinsic.",
-					Some(e.to_string()),
-				))
-			})?
 		};
 
 		Ok(Encode::encode(&result).into())